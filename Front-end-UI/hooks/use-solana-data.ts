@@ -39,14 +39,18 @@ export const usePools = () => {
 /**
  * Hook to fetch a specific pool by token mints
  */
-export const usePool = (mintA: string | null, mintB: string | null) => {
+export const usePool = (
+  mintA: string | null,
+  mintB: string | null,
+  feeRate: number
+) => {
   const { connected } = useWallet();
 
   return useQuery(
-    [QUERY_KEYS.POOL, mintA, mintB],
+    [QUERY_KEYS.POOL, mintA, mintB, feeRate],
     async () => {
       if (!mintA || !mintB) return null;
-      return solanaService.fetchPoolByMints(mintA, mintB);
+      return solanaService.fetchPoolByMints(mintA, mintB, feeRate);
     },
     {
       enabled: connected && solanaService.isInitialized() && !!mintA && !!mintB,
@@ -231,9 +231,58 @@ export const sendAndConfirmTransaction = async (
  * Find PDA for pool account
  * @param mintA - First token mint
  * @param mintB - Second token mint
+ * @param feeRate - Fee rate of the pool in basis points
  * @returns [poolPda, poolBump]
  */
 export const findPoolPda = async (
+  mintA: PublicKey,
+  mintB: PublicKey,
+  feeRate: number
+): Promise<[PublicKey, number]> => {
+  // Ensure canonical order by comparing toString values
+  if (mintA.toString() > mintB.toString()) {
+    [mintA, mintB] = [mintB, mintA];
+  }
+
+  return PublicKey.findProgramAddress(
+    [
+      Buffer.from("pool"),
+      mintA.toBuffer(),
+      mintB.toBuffer(),
+      new BN(feeRate).toArrayLike(Buffer, "le", 2),
+    ],
+    PROGRAM_ID
+  );
+};
+
+/**
+ * Find PDA for the registry listing every fee tier of a token pair
+ * @param mintA - First token mint
+ * @param mintB - Second token mint
+ * @returns [poolRegistryPda, poolRegistryBump]
+ */
+export const findPoolRegistryPda = async (
+  mintA: PublicKey,
+  mintB: PublicKey
+): Promise<[PublicKey, number]> => {
+  // Ensure canonical order by comparing toString values
+  if (mintA.toString() > mintB.toString()) {
+    [mintA, mintB] = [mintB, mintA];
+  }
+
+  return PublicKey.findProgramAddress(
+    [Buffer.from("pool_registry"), mintA.toBuffer(), mintB.toBuffer()],
+    PROGRAM_ID
+  );
+};
+
+/**
+ * Find PDA for the record of a token pair, shared by all of its pools
+ * @param mintA - First token mint
+ * @param mintB - Second token mint
+ * @returns [tokenPairPda, tokenPairBump]
+ */
+export const findTokenPairPda = async (
   mintA: PublicKey,
   mintB: PublicKey
 ): Promise<[PublicKey, number]> => {
@@ -243,7 +292,7 @@ export const findPoolPda = async (
   }
 
   return PublicKey.findProgramAddress(
-    [Buffer.from("pool"), mintA.toBuffer(), mintB.toBuffer()],
+    [Buffer.from("token_pair"), mintA.toBuffer(), mintB.toBuffer()],
     PROGRAM_ID
   );
 };
@@ -33,6 +33,8 @@ import {
   createAnchorProvider,
   getProgram,
   findPoolPda,
+  findPoolRegistryPda,
+  findTokenPairPda,
   handleSolanaError,
   notifyTransactionSuccess,
   priceToSqrtPriceQ64,
@@ -593,9 +595,14 @@ class SolanaService {
    * Fetch a specific pool by its token mints
    * @param mintA - First token mint
    * @param mintB - Second token mint
+   * @param feeRate - Fee rate of the pool in basis points
    * @returns Pool data
    */
-  async fetchPoolByMints(mintAAddress: string, mintBAddress: string) {
+  async fetchPoolByMints(
+    mintAAddress: string,
+    mintBAddress: string,
+    feeRate: number
+  ) {
     try {
       this.ensureInitialized();
 
@@ -603,7 +610,7 @@ class SolanaService {
       const mintB = new PublicKey(mintBAddress);
 
       // Find the pool PDA
-      const [poolPda] = await findPoolPda(mintA, mintB);
+      const [poolPda] = await findPoolPda(mintA, mintB, feeRate);
 
       // Fetch the pool data
       const poolData = (await this.program!.account.pool.fetch(
@@ -735,7 +742,38 @@ class SolanaService {
       }
 
       // Find pool PDA
-      const [poolPda] = await findPoolPda(canonicalMintA, canonicalMintB);
+      const [poolPda] = await findPoolPda(
+        canonicalMintA,
+        canonicalMintB,
+        feeRate
+      );
+      const [poolRegistryPda] = await findPoolRegistryPda(
+        canonicalMintA,
+        canonicalMintB
+      );
+      const [tokenPairPda] = await findTokenPairPda(
+        canonicalMintA,
+        canonicalMintB
+      );
+
+      // The pair's record must exist before its first pool
+      const preInstructions: web3.TransactionInstruction[] = [];
+      const tokenPairInfo = await this.connection.getAccountInfo(tokenPairPda);
+      if (!tokenPairInfo) {
+        preInstructions.push(
+          await this.program!.methods.createTokenPairHandler(
+            web3.PublicKey.default
+          )
+            .accounts({
+              tokenPair: tokenPairPda,
+              mintA: canonicalMintA,
+              mintB: canonicalMintB,
+              payer: this.wallet.publicKey,
+              systemProgram: web3.SystemProgram.programId,
+            })
+            .instruction()
+        );
+      }
 
       // Create keypairs for token vaults
       const poolVaultAKeypair = Keypair.generate();
@@ -751,7 +789,9 @@ class SolanaService {
       const txSignature = await this.program!.methods.initializePoolHandler(
         initialSqrtPriceQ64,
         feeRate,
-        tickSpacing
+        tickSpacing,
+        0, // protocol fee, in basis points of each swap fee
+        false // immutable parameters
       )
         .accounts({
           pool: poolPda,
@@ -760,11 +800,14 @@ class SolanaService {
           factory: FACTORY_ADDRESS,
           poolVaultA: poolVaultAKeypair.publicKey,
           poolVaultB: poolVaultBKeypair.publicKey,
+          poolRegistry: poolRegistryPda,
+          tokenPair: tokenPairPda,
           payer: this.wallet.publicKey,
           systemProgram: web3.SystemProgram.programId,
           tokenProgram: utils.token.TOKEN_PROGRAM_ID,
           rent: web3.SYSVAR_RENT_PUBKEY,
         })
+        .preInstructions(preInstructions)
         .signers([poolVaultAKeypair, poolVaultBKeypair])
         .rpc();
 
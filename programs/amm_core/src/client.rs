@@ -0,0 +1,354 @@
+//! Off-chain helpers for building amm_core transactions.
+//!
+//! This module is not compiled for the on-chain program.
+
+use crate::constants::{BPS_DENOMINATOR, MAX_TICK, MIN_TICK, VOLUME_WINDOW_SECS};
+use crate::errors::ErrorCode;
+use crate::math;
+use crate::position::PositionData;
+use crate::state::pool::{Pool, SwapResult};
+use crate::tick::TickData;
+use anchor_lang::prelude::*;
+use primitive_types::U256;
+use std::collections::BTreeMap;
+
+/// The tick accounts a swap needs, as estimated by [`estimate_swap_tick_accounts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapTickAccounts {
+    /// Indices of the initialized ticks the swap crosses, in crossing order.
+    pub tick_indices: Vec<i32>,
+    /// The tick PDAs to pass as `remaining_accounts`, in the same order.
+    pub tick_accounts: Vec<Pubkey>,
+    /// Gross input the swap is expected to consume.
+    pub amount_in: u128,
+    /// Output the swap is expected to produce.
+    pub amount_out: u128,
+}
+
+impl SwapTickAccounts {
+    /// Number of tick accounts the swap needs.
+    pub fn count(&self) -> usize {
+        self.tick_accounts.len()
+    }
+}
+
+/// Estimates which tick accounts an exact-input swap of `amount_in` will cross.
+///
+/// The swap is replayed with the on-chain swap loop against a copy of `pool`.
+/// `tick_liquidity_net` maps initialized tick indices to their `liquidity_net`, as read
+/// from the pool's `TickData` accounts. Ticks missing from the map count as zero.
+///
+/// The estimate is exact for the given state, but the pool can move before the
+/// transaction lands. Tick accounts past the ones a swap crosses are ignored, so callers
+/// may append a few more ticks in the swap direction as a buffer.
+///
+/// # Arguments
+///
+/// * `pool` - The current pool state.
+/// * `pool_key` - The pool's address, used to derive the tick PDAs.
+/// * `zero_for_one` - True if swapping token0 for token1.
+/// * `amount_in` - The exact input amount of the swap.
+/// * `sqrt_price_limit_q64` - The price limit the swap will be sent with.
+/// * `tick_liquidity_net` - `liquidity_net` of the pool's initialized ticks.
+pub fn estimate_swap_tick_accounts(
+    pool: &Pool,
+    pool_key: &Pubkey,
+    zero_for_one: bool,
+    amount_in: u64,
+    sqrt_price_limit_q64: u128,
+    tick_liquidity_net: &BTreeMap<i32, i128>,
+) -> Result<SwapTickAccounts> {
+    let mut simulated_pool = pool.clone();
+    let mut tick_indices = Vec::new();
+
+    let result = simulated_pool.swap_with_tick_source(
+        zero_for_one,
+        amount_in as i128,
+        sqrt_price_limit_q64,
+        |tick_index, _, _| {
+            tick_indices.push(tick_index);
+            Ok(tick_liquidity_net.get(&tick_index).copied().unwrap_or(0))
+        },
+    )?;
+
+    let tick_accounts = tick_indices
+        .iter()
+        .map(|&tick_index| TickData::address(pool_key, tick_index))
+        .collect();
+
+    Ok(SwapTickAccounts {
+        tick_indices,
+        tick_accounts,
+        amount_in: result.amount_in,
+        amount_out: result.amount_out,
+    })
+}
+
+/// Quotes a swap, reporting the swap fee split between liquidity providers and the
+/// protocol.
+///
+/// Like [`estimate_swap_tick_accounts`], the swap is replayed with the on-chain swap loop
+/// against a copy of `pool`, so the fee split follows the pool's `protocol_fee` exactly as
+/// the swap would: the protocol's cut of each step's fee is rounded down.
+///
+/// # Arguments
+///
+/// * `pool` - The current pool state.
+/// * `zero_for_one` - True if swapping token0 for token1.
+/// * `amount_specified` - Positive for an exact input, negative for an exact output.
+/// * `sqrt_price_limit_q64` - The price limit the swap will be sent with.
+/// * `tick_liquidity_net` - `liquidity_net` of the pool's initialized ticks.
+pub fn quote_swap(
+    pool: &Pool,
+    zero_for_one: bool,
+    amount_specified: i128,
+    sqrt_price_limit_q64: u128,
+    tick_liquidity_net: &BTreeMap<i32, i128>,
+) -> Result<SwapResult> {
+    pool.clone().swap_with_tick_source(
+        zero_for_one,
+        amount_specified,
+        sqrt_price_limit_q64,
+        |tick_index, _, _| Ok(tick_liquidity_net.get(&tick_index).copied().unwrap_or(0)),
+    )
+}
+
+/// How [`sqrt_price_at_tick`] treats a tick outside `MIN_TICK..=MAX_TICK`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TickBoundsHandling {
+    /// Fail with `InvalidTickRange`, as the program does.
+    #[default]
+    Reject,
+    /// Use the nearest bound, so the price saturates at `MIN_SQRT_PRICE` or
+    /// `MAX_SQRT_PRICE`.
+    Clamp,
+}
+
+/// Returns the sqrt price of `tick`, in Q64.64.
+///
+/// The price comes from the same conversion the program uses for position bounds and tick
+/// crossings, so clients need not replicate its power table. The program returns the same
+/// value from `get_sqrt_price_at_tick`.
+///
+/// # Arguments
+///
+/// * `tick` - The tick index.
+/// * `out_of_range` - What to do if `tick` is past `MIN_TICK` or `MAX_TICK`.
+///
+/// # Errors
+/// * `InvalidTickRange` if `tick` is out of range and `out_of_range` is `Reject`.
+pub fn sqrt_price_at_tick(tick: i32, out_of_range: TickBoundsHandling) -> Result<u128> {
+    let tick = match out_of_range {
+        TickBoundsHandling::Reject => tick,
+        TickBoundsHandling::Clamp => tick.clamp(MIN_TICK, MAX_TICK),
+    };
+    math::tick_to_sqrt_price_q64(tick)
+}
+
+/// What a position is worth at its pool's current price, as valued by [`owner_exposure`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionExposure {
+    /// The position's address.
+    pub position: Pubkey,
+    /// The pool the position belongs to.
+    pub pool: Pubkey,
+    /// Token0 the position's liquidity would pay out if removed now.
+    pub amount0: u128,
+    /// Token1 the position's liquidity would pay out if removed now.
+    pub amount1: u128,
+    /// Token0 owed to the position and not yet collected.
+    pub tokens_owed_0: u64,
+    /// Token1 owed to the position and not yet collected.
+    pub tokens_owed_1: u64,
+}
+
+/// A wallet's total exposure, as computed by [`owner_exposure`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OwnerExposure {
+    /// The valuation of each of the owner's positions.
+    pub positions: Vec<PositionExposure>,
+    /// The owner's total claim on each token mint: the liquidity amounts plus the tokens
+    /// owed, summed over every position.
+    pub totals: BTreeMap<Pubkey, u128>,
+}
+
+/// Values every position of `owner` at its pool's current price and totals the result
+/// per token mint.
+///
+/// `positions` are position accounts as `(address, data)` pairs, for instance every
+/// position read from the program, and `pools` the pool accounts by address. Positions
+/// opened with `mint_position` count if `owner` opened them. Positions with an NFT belong
+/// to the NFT holder, which the position account does not record, so those count whatever
+/// their `owner` field: callers should pass only the ones whose NFT `owner` holds.
+///
+/// Liquidity is valued at what removing it would pay out, rounded down like
+/// `decrease_liquidity`. Fees earned since a position's last update are only counted once
+/// an instruction credits them to `tokens_owed_0`/`tokens_owed_1`.
+///
+/// # Errors
+/// * `InvalidPool` if a counted position's pool is missing from `pools`.
+pub fn owner_exposure(
+    owner: &Pubkey,
+    positions: &[(Pubkey, PositionData)],
+    pools: &BTreeMap<Pubkey, Pool>,
+) -> Result<OwnerExposure> {
+    let mut exposure = OwnerExposure::default();
+    for (position_key, position) in positions {
+        if position.position_mint == Pubkey::default() && position.owner != *owner {
+            continue;
+        }
+        let pool = pools.get(&position.pool).ok_or(ErrorCode::InvalidPool)?;
+        let (amount0, amount1) = math::get_amounts_for_liquidity(
+            pool.sqrt_price_q64,
+            math::tick_to_sqrt_price_q64(position.tick_lower_index)?,
+            math::tick_to_sqrt_price_q64(position.tick_upper_index)?,
+            position.liquidity,
+            false,
+        )?;
+
+        for (mint, amount, owed) in [
+            (pool.token0_mint, amount0, position.tokens_owed_0),
+            (pool.token1_mint, amount1, position.tokens_owed_1),
+        ] {
+            let total = exposure.totals.entry(mint).or_default();
+            *total = total
+                .checked_add(amount)
+                .and_then(|total| total.checked_add(owed as u128))
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        exposure.positions.push(PositionExposure {
+            position: *position_key,
+            pool: position.pool,
+            amount0,
+            amount1,
+            tokens_owed_0: position.tokens_owed_0,
+            tokens_owed_1: position.tokens_owed_1,
+        });
+    }
+    Ok(exposure)
+}
+
+/// Seconds in a 365-day year, the period [`estimated_fee_apr_bps`] annualizes over.
+const SECONDS_PER_YEAR: u128 = 365 * 86_400;
+
+/// How [`estimated_fee_apr_bps`] annualizes the volume a pool has counted in its current
+/// daily window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FeeAprWindow {
+    /// Spread the volume over the time elapsed since the window started, projecting the
+    /// day's pace. Early in the day, the estimate swings with every swap.
+    #[default]
+    Elapsed,
+    /// Count the volume as a whole day's, however much of the day is left: a lower bound
+    /// until the window closes.
+    FullDay,
+}
+
+/// Estimates the yearly fee return of a pool's liquidity providers, in basis points of
+/// `tvl_0`, from the swap volume counted in the daily window containing `now`.
+///
+/// The swap fees on that volume, less the protocol's cut at the pool's current
+/// `protocol_fee`, are annualized as `window` says and divided by `tvl_0`, the value of
+/// the pool's liquidity in token0. The estimate is zero until a swap goes through in the
+/// window of `now`. It assumes liquidity stays as it is and ignores how much of it is in
+/// range, so every provider earns the same rate.
+///
+/// # Errors
+/// * `InvalidInput` if `tvl_0` is zero.
+/// * `MathOverflow` if the estimate does not fit in a `u64`.
+pub fn estimated_fee_apr_bps(
+    pool: &Pool,
+    tvl_0: u128,
+    now: i64,
+    window: FeeAprWindow,
+) -> Result<u64> {
+    if tvl_0 == 0 {
+        return err!(ErrorCode::InvalidInput);
+    }
+    let fees = pool.volume_24h_at(now) as u128 * pool.fee_rate as u128 / BPS_DENOMINATOR;
+    let lp_fees = fees - fees * pool.protocol_fee as u128 / BPS_DENOMINATOR;
+    let window_secs = match window {
+        FeeAprWindow::Elapsed => (now - pool.volume_window_start).clamp(1, VOLUME_WINDOW_SECS),
+        FeeAprWindow::FullDay => VOLUME_WINDOW_SECS,
+    } as u128;
+    // Fees are below 2^64, so the product stays below 2^103.
+    let apr_bps = lp_fees * SECONDS_PER_YEAR * BPS_DENOMINATOR / window_secs / tvl_0;
+    u64::try_from(apr_bps).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+/// Named price ranges for new positions, set as a share of the current price on either
+/// side of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceRangePreset {
+    /// ±0.5%, for pairs that trade close to a peg.
+    Stable,
+    /// ±2%.
+    Narrow,
+    /// ±20%.
+    Wide,
+    /// A range picked by hand. It has no width of its own, so it cannot be turned into
+    /// ticks.
+    Custom,
+}
+
+impl PriceRangePreset {
+    /// Distance of each bound from the current price, in basis points of it. `None` for
+    /// `Custom`.
+    pub fn half_width_bps(self) -> Option<u128> {
+        match self {
+            PriceRangePreset::Stable => Some(50),
+            PriceRangePreset::Narrow => Some(200),
+            PriceRangePreset::Wide => Some(2_000),
+            PriceRangePreset::Custom => None,
+        }
+    }
+
+    /// Returns the `(tick_lower, tick_upper)` range of the preset around
+    /// `sqrt_price_q64`, widened to multiples of `tick_spacing`.
+    ///
+    /// The lower bound is the price times `1 - half_width`, the upper one the price times
+    /// `1 + half_width`. Both are rounded outward, first to whole ticks and then to the
+    /// tick spacing, so the range always covers the preset. Bounds past the usable tick
+    /// range are clamped to it.
+    ///
+    /// # Errors
+    /// * `InvalidPreset` for `Custom`.
+    /// * `RangeTooNarrow` if the preset spans less than one tick spacing, since rounding
+    ///   would then at least double its width.
+    pub fn tick_range(self, sqrt_price_q64: u128, tick_spacing: u16) -> Result<(i32, i32)> {
+        let half_width_bps = self.half_width_bps().ok_or(ErrorCode::InvalidPreset)?;
+        if tick_spacing == 0 {
+            return err!(ErrorCode::InvalidTickSpacing);
+        }
+
+        let tick_lower = math::sqrt_price_q64_to_tick(scale_sqrt_price(
+            sqrt_price_q64,
+            10_000 - half_width_bps,
+        ))?;
+        let sqrt_price_upper = scale_sqrt_price(sqrt_price_q64, 10_000 + half_width_bps);
+        let mut tick_upper = math::sqrt_price_q64_to_tick(sqrt_price_upper)?;
+        if tick_upper < MAX_TICK && math::tick_to_sqrt_price_q64(tick_upper)? < sqrt_price_upper {
+            tick_upper += 1;
+        }
+
+        let spacing = tick_spacing as i32;
+        if tick_upper - tick_lower < spacing {
+            return err!(ErrorCode::RangeTooNarrow);
+        }
+        let min_usable_tick = -(MIN_TICK.abs() / spacing * spacing);
+        let max_usable_tick = MAX_TICK / spacing * spacing;
+        let tick_lower = (tick_lower.div_euclid(spacing) * spacing).max(min_usable_tick);
+        let tick_upper =
+            ((tick_upper + spacing - 1).div_euclid(spacing) * spacing).min(max_usable_tick);
+        Ok((tick_lower, tick_upper))
+    }
+}
+
+/// Scales the price `sqrt_price_q64` represents by `factor_bps / 10_000`, returning the
+/// new sqrt price rounded down.
+fn scale_sqrt_price(sqrt_price_q64: u128, factor_bps: u128) -> u128 {
+    // Square root of the factor, in Q64.64
+    let sqrt_factor_q64 = ((U256::from(factor_bps) << 128) / U256::from(10_000u128)).integer_sqrt();
+    let scaled = (U256::from(sqrt_price_q64) * sqrt_factor_q64) >> 64;
+    scaled.min(U256::from(u128::MAX)).as_u128()
+}
@@ -1,3 +1,5 @@
+use anchor_lang::prelude::*;
+
 /// Fluxa AMM Core Protocol Constants
 ///
 /// This module defines the fundamental protocol parameters and boundaries that govern
@@ -27,15 +29,37 @@ pub const MIN_LIQUIDITY: u128 = 1000;
 ///
 /// Corresponds to the minimum tick and represents the lowest possible
 /// sqrt(price) in Q64.64 fixed-point representation.
-/// √1.0001^MIN_TICK  × Q64  = floor(2^64 / 1.0001^887272)
-pub const MIN_SQRT_PRICE: u128 = 0;
+/// Equal to `math::tick_to_sqrt_price_q64(MIN_TICK)`: √1.0001^MIN_TICK × Q64 is below
+/// one Q64.64 unit, so the conversion bottoms out at 1.
+pub const MIN_SQRT_PRICE: u128 = 1;
 
 /// The maximum square root price limit for swaps
 ///
 /// Corresponds to the maximum tick and represents the highest possible
 /// sqrt(price) in Q64.64 fixed-point representation.
-/// √1.0001^MAX_TICK  × Q64  = floor(1.0001^887272 × 2^64)
-pub const MAX_SQRT_PRICE: u128 = 340269576636625053602161358042262667264;
+/// Equal to `math::tick_to_sqrt_price_q64(MAX_TICK)`, i.e. √1.0001^MAX_TICK × Q64 as
+/// computed from the `POWERS` table.
+pub const MAX_SQRT_PRICE: u128 = 340269576638287422883700525942130004573;
+
+/// The tick and sqrt price bounds of the protocol, as returned by `get_price_bounds`.
+///
+/// Clients should read the bounds from here (or from the instruction) instead of
+/// hardcoding them, so they always agree with the tick-conversion math.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceBounds {
+    pub min_tick: i32,
+    pub max_tick: i32,
+    pub min_sqrt_price_q64: u128,
+    pub max_sqrt_price_q64: u128,
+}
+
+/// The protocol's price bounds.
+pub const PRICE_BOUNDS: PriceBounds = PriceBounds {
+    min_tick: MIN_TICK,
+    max_tick: MAX_TICK,
+    min_sqrt_price_q64: MIN_SQRT_PRICE,
+    max_sqrt_price_q64: MAX_SQRT_PRICE,
+};
 
 /// Standard fee tiers available (in basis points)
 ///
@@ -65,6 +89,14 @@ pub const TICK_SPACING_MEDIUM: i32 = 10;
 /// Price changes of 0.6% (60 * 0.01%) using even coarser granularity.
 pub const TICK_SPACING_HIGH: i32 = 60;
 
+/// Tick spacings a pool can be created with. `migrate_tick_spacing` can later widen a
+/// pool's spacing to a multiple of its current one.
+pub const ALLOWED_TICK_SPACINGS: [u16; 3] = [
+    TICK_SPACING_LOW as u16,
+    TICK_SPACING_MEDIUM as u16,
+    TICK_SPACING_HIGH as u16,
+];
+
 /// Protocol fee denominator
 ///
 /// Used to calculate protocol fees as a fraction of collected trading fees.
@@ -78,6 +110,11 @@ pub const Q64: u128 = 1u128 << 64; // single unified format
 /// BPS Denominator
 pub const BPS_DENOMINATOR: u128 = 10_000; // basis points denominator
 
+/// Fee pips denominator
+///
+/// Fees expressed in pips are hundredths of a basis point, so 3000 pips is 0.3%.
+pub const FEE_PIPS_DENOMINATOR: u128 = 1_000_000;
+
 /// Powers of √1.0001 for binary exponentiation.
 /// Stores `floor((√1.0001)^(2^i) * Q64)` for `i = 0..19`.
 /// `Q64 = 1u128 << 64`.
@@ -105,6 +142,38 @@ pub const POWERS: [u128; 20] = [
     4_468_068_147_273_140_139_091_016_147_737,
 ];
 
-// 1 / log₂(1.0001) in Q64.64 format,
-// floor(1.0 / log2(1.0001) * 2^64)
+// 1 / log₂(√1.0001) in Q64.64 format,
+// floor(1.0 / log2(sqrt(1.0001)) * 2^64)
 pub const INV_LOG2_SQRT_1P0001_Q64: u128 = 0x3627a301d786ca000000;
+
+// ln(2) in Q64.64 format, rounded to nearest,
+// round(ln(2) * 2^64)
+pub const LN_2_Q64: u128 = 0xb17217f7d1cf79ac;
+
+/// Maximum number of pools a `swap_multi_hop` route can go through.
+pub const MAX_SWAP_HOPS: usize = 4;
+
+/// Maximum number of steps the swap loop takes. Each step moves the price to the next
+/// initialized tick or to the price limit, so a swap crosses at most this many ticks.
+pub const MAX_SWAP_ITERATIONS: usize = 64;
+
+/// Maximum number of accounts an instruction walks through in `remaining_accounts`.
+pub const MAX_BATCH_ITERATIONS: usize = 64;
+
+/// Lowest fee rate a pool can be created with or `set_fee_rate` accepts, in basis points.
+pub const MIN_FEE_RATE: u16 = 1;
+
+/// Highest fee rate a pool can be created with or `set_fee_rate` accepts, in basis points.
+pub const MAX_FEE_RATE: u16 = 10_000;
+
+/// Age, in slots, past which a pool's oracle price is considered stale until its authority
+/// configures another limit. About a minute at 400ms slots.
+pub const DEFAULT_MAX_ORACLE_AGE_SLOTS: u64 = 150;
+
+/// Length, in seconds, of the daily window a pool's `volume_24h` counts trades over.
+/// Windows start at multiples of it, at midnight UTC.
+pub const VOLUME_WINDOW_SECS: i64 = 86_400;
+
+/// Lamports of a cancelled order's rent paid to whoever cancels it after it expired, when
+/// that is not its owner. The rest of the rent goes back to the owner.
+pub const ORDER_CANCEL_KEEPER_REWARD_LAMPORTS: u64 = 100_000;
@@ -316,7 +316,8 @@ pub enum ErrorCode {
     /// Returned when an unsupported fee tier is specified
     ///
     /// This error occurs when trying to create a pool with a fee tier that is not
-    /// supported by the protocol. Only specific fee tiers are allowed.
+    /// enabled on the factory config, or with another tick spacing than the one enabled
+    /// for its fee rate.
     #[msg("The specified fee tier is not supported")]
     InvalidFeeTier,
 
@@ -345,4 +346,250 @@ pub enum ErrorCode {
     InvalidVaultMint,
     #[msg("An expected tick was not found or provided.")]
     TickNotFound,
+
+    /// Returned when an exact-output swap would require more input than the caller allows
+    ///
+    /// This is the exact-output counterpart of `SlippageExceeded`, protecting the
+    /// swapper from paying more than `amount_in_maximum` for the requested output.
+    #[msg("Required input amount exceeds the specified maximum")]
+    ExcessiveInputAmount,
+
+    /// Returned when a swap crosses more initialized ticks than tick accounts were supplied
+    ///
+    /// Superseded by `MissingTickAccount`, which names the missing tick. Kept so the
+    /// numeric codes of the variants after it do not change.
+    #[msg("Not enough tick accounts were provided for the ticks crossed by this swap")]
+    InsufficientTickAccounts,
+
+    /// Returned when a supplied tick account is not the PDA of the tick being crossed
+    #[msg("The provided tick account does not match the expected tick PDA")]
+    InvalidTickAccount,
+
+    /// Returned when a pool is registered for a fee tier its pair already has
+    #[msg("A pool with this fee rate is already registered for the pair")]
+    FeeTierAlreadyRegistered,
+
+    /// Returned when a pair already has the maximum number of fee tiers registered
+    #[msg("The pool registry for this pair is full")]
+    PoolRegistryFull,
+
+    /// Returned when a swap needs to cross a tick whose account was not supplied
+    ///
+    /// Tick accounts are passed through `remaining_accounts` in crossing order; the client
+    /// must supply one for every initialized tick the swap moves through. The logged error
+    /// message names the expected tick index.
+    #[msg("A tick account required by this swap was not supplied")]
+    MissingTickAccount,
+
+    /// Returned when the supplied tick accounts are not ordered in the swap direction
+    ///
+    /// Ticks must be strictly descending for token0 -> token1 swaps and strictly ascending
+    /// for token1 -> token0 swaps.
+    #[msg("Tick accounts must be ordered in the swap direction")]
+    InvalidTickAccountOrder,
+
+    /// Returned when an instruction reaches a pool while one of its flash loans is in progress
+    #[msg("A flash loan is in progress on this pool")]
+    FlashLoanActive,
+
+    /// Returned when a flash loan requests zero of both tokens
+    #[msg("Flash loan amount must be greater than zero")]
+    ZeroFlashLoanAmount,
+
+    /// Returned when no executable callback program is passed as the first remaining account
+    #[msg("Flash loan callback program is missing or not executable")]
+    MissingFlashLoanCallbackProgram,
+
+    /// Returned when the pool vaults hold less than the borrowed amounts plus fees after the callback
+    #[msg("Flash loan was not repaid with the fee")]
+    FlashLoanNotRepaid,
+
+    /// Returned when an order book is created with a zero tick size
+    #[msg("Order book tick size must be greater than zero")]
+    InvalidOrderBookTickSize,
+
+    /// Returned when a limit order price is not a multiple of the order book's tick size
+    #[msg("Order price is not a multiple of the order book tick size")]
+    PriceNotAlignedWithTick,
+
+    /// Returned when an order's expiry is not in the future
+    #[msg("Order has already expired")]
+    OrderExpired,
+
+    /// Returned when a limit order is placed for a zero quantity
+    #[msg("Order quantity must be greater than zero")]
+    ZeroOrderQuantity,
+
+    /// Returned when a tick array's start index is not aligned to a full array of ticks
+    #[msg("Tick array start index is not a multiple of the array span or out of range")]
+    InvalidTickArrayStartIndex,
+
+    /// Returned when a tick is looked up in a tick array that does not cover it
+    #[msg("Tick is outside the tick array")]
+    TickNotInTickArray,
+
+    /// Returned when a tick's liquidity is held by a different tick account than the one supplied
+    #[msg("Tick liquidity is held in a different tick account")]
+    TickStorageConflict,
+
+    /// Returned when a protocol fee above 100% of the swap fee is set
+    #[msg("Protocol fee cannot exceed 10000 basis points of the swap fee")]
+    InvalidProtocolFee,
+
+    /// Returned when a TWAP is requested over a zero-second window
+    #[msg("TWAP window must be at least one second")]
+    InvalidTwapWindow,
+
+    /// Returned when an oracle query reaches further back than the oldest stored observation
+    #[msg("Oracle query precedes the oldest observation")]
+    OracleObservationTooOld,
+
+    /// Returned when a match is attempted with a bid priced below the ask
+    #[msg("Bid price is below the ask price")]
+    OrdersDoNotCross,
+
+    /// Returned when the orders passed as the bid and the ask are not on those sides
+    #[msg("Order is not on the expected side of the book")]
+    InvalidOrderSide,
+
+    /// Returned when a tick bitmap word that has already been moved into its own account is initialized again
+    #[msg("Tick bitmap word is already stored in its own account")]
+    TickBitmapWordExists,
+
+    /// Returned when a pool has no room left for more tick bitmap words
+    #[msg("Pool tick bitmap is full; move words out with initialize_bitmap_word")]
+    TickBitmapFull,
+
+    /// Returned when an instruction reads or flips a tick bitmap word whose account was not supplied
+    #[msg("Tick bitmap word account not supplied")]
+    MissingTickBitmapWord,
+
+    /// Returned when crossing a tick would remove more liquidity than the pool has active
+    #[msg("Tick crossing would make pool liquidity negative")]
+    LiquidityUnderflow,
+
+    /// Returned when a multi-hop swap has no hops or more than `MAX_SWAP_HOPS`
+    #[msg("Multi-hop swap must go through 1 to 4 pools")]
+    InvalidHopCount,
+
+    /// Returned when a hop does not start from the previous hop's output token, or repeats a pool
+    #[msg("Hops do not form a valid swap path")]
+    InvalidHopPath,
+
+    /// Returned when a swap would take more than `MAX_SWAP_ITERATIONS` steps, or is given
+    /// more tick accounts than it could cross
+    #[msg("Swap crosses too many ticks")]
+    MaxTickCrossingsExceeded,
+
+    /// Returned when an instruction is passed more than `MAX_BATCH_ITERATIONS` remaining accounts
+    #[msg("Too many accounts passed in remaining_accounts")]
+    MaxBatchSizeExceeded,
+
+    /// Returned when a fee rate is outside `MIN_FEE_RATE..=MAX_FEE_RATE`
+    #[msg("Fee rate is out of bounds")]
+    InvalidFeeRate,
+
+    /// Returned when trading or adding liquidity on a paused pool
+    #[msg("Pool is paused")]
+    PoolPaused,
+
+    /// Returned when an oracle price is out of bounds, from a future slot, or older than the
+    /// pool's current one
+    #[msg("Invalid oracle price")]
+    InvalidOraclePrice,
+
+    /// Returned when an oracle price is older than the pool's `max_oracle_age_slots`
+    #[msg("Oracle price is stale")]
+    OraclePriceStale,
+
+    /// Returned when a fixed-point division or inversion has a zero divisor
+    #[msg("Division by zero")]
+    DivisionByZero,
+
+    /// Returned when a protocol fee curve's breakpoints do not strictly increase or one of
+    /// its shares is above 10000 basis points
+    #[msg("Invalid protocol fee curve")]
+    InvalidProtocolFeeCurve,
+
+    /// Returned when the factory config passed with a pool is not the pool's factory
+    #[msg("Factory config is not the pool's factory")]
+    InvalidFactoryConfig,
+
+    /// Returned when a new position's index is not the next index of its owner's position
+    /// counter
+    #[msg("Position index is not the owner's next position index")]
+    InvalidPositionIndex,
+
+    /// Returned when a position listed in its owner's position index is closed without the
+    /// index account
+    #[msg("Owner position index account required")]
+    OwnerIndexRequired,
+
+    /// Returned when listing a position that is already listed, or unlisting one that is not
+    /// listed or with an account that does not link to it
+    #[msg("Invalid owner position index link")]
+    InvalidOwnerIndexLink,
+
+    /// Returned when the orders of a matching batch are not in price-time priority on their
+    /// side of the book, or an order is passed twice
+    #[msg("Order batch is not sorted best first")]
+    OrderBatchNotSorted,
+
+    /// Returned when a position that already uses the current layout is migrated
+    #[msg("Position already uses the current layout")]
+    PositionAlreadyMigrated,
+
+    /// Returned when changing the fee rate, protocol fee or tick spacing of a pool
+    /// initialized with immutable parameters
+    #[msg("Pool parameters are immutable")]
+    PoolImmutable,
+
+    /// Returned when one account is passed for two of `update_position`'s tick roles that
+    /// stand for different ticks
+    #[msg("Tick account passed for two different ticks")]
+    DuplicateTickAccount,
+
+    /// Returned when resizing an account below the size its data serializes to
+    #[msg("New account size is below its serialized data length")]
+    AccountShrinkTooSmall,
+
+    /// Returned when resizing an account by more than the runtime allows in one
+    /// instruction, or beyond the largest account size
+    #[msg("Account cannot grow by that much in one instruction")]
+    AccountGrowthTooLarge,
+
+    /// Returned when a liquidity histogram's bucket width is zero, not a multiple of the
+    /// pool's tick spacing, or too wide for its buckets to fit in the tick range
+    #[msg("Invalid liquidity histogram bucket width")]
+    InvalidHistogramBucketWidth,
+
+    /// Returned when a liquidity histogram is read after the age its reader tolerates
+    #[msg("Liquidity histogram is stale")]
+    LiquidityHistogramStale,
+
+    /// Returned when reading a liquidity histogram at a tick outside its buckets
+    #[msg("Tick outside the liquidity histogram")]
+    TickOutsideHistogram,
+
+    /// Returned when cancelling an order that has no open quantity left
+    #[msg("Order is already filled")]
+    OrderAlreadyFilled,
+
+    /// Returned when enabling a fee rate the factory config already enables
+    #[msg("Fee tier is already enabled")]
+    FeeTierAlreadyEnabled,
+
+    /// Returned when enabling a fee tier on a factory config whose fee tiers are all taken
+    #[msg("Too many fee tiers enabled")]
+    TooManyFeeTiers,
+
+    /// Returned by `assert_quote` when the live quote differs from the expected output by
+    /// more than the tolerance
+    #[msg("Live quote deviates from the expected output beyond tolerance")]
+    QuoteDeviationExceeded,
+
+    /// Returned by `refresh_protocol_share` for a pool whose protocol fee the factory
+    /// authority set by hand
+    #[msg("Protocol fee is set manually and does not follow the factory curve")]
+    ProtocolFeeManual,
 }
@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::{MAX_SQRT_PRICE, MIN_SQRT_PRICE};
+use crate::flog;
+use crate::instructions::quote_swap_exact_input::quote_exact_input;
+use crate::AssertQuote;
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, AssertQuote<'info>>,
+    amount_in: u64,
+    zero_for_one: bool,
+    expected_out: u64,
+    tolerance_bps: u16,
+) -> Result<()> {
+    // 1. Quote the swap against the live pool, with no price limit
+    let sqrt_price_limit_q64 = if zero_for_one {
+        MIN_SQRT_PRICE
+    } else {
+        MAX_SQRT_PRICE
+    };
+    let quote = quote_exact_input(
+        &ctx.accounts.pool,
+        ctx.remaining_accounts,
+        zero_for_one,
+        amount_in,
+        sqrt_price_limit_q64,
+    )?;
+
+    // 2. Fail the transaction, and the swap bundled after this instruction, if the pool
+    //    moved since the client quoted it
+    quote.ensure_amount_out_within(expected_out, tolerance_bps)?;
+
+    flog!(
+        debug,
+        "quote_asserted",
+        pool = ctx.accounts.pool.key(),
+        amount_out = quote.amount_out,
+        expected_out = expected_out
+    );
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+
+use crate::constants::ORDER_CANCEL_KEEPER_REWARD_LAMPORTS;
+use crate::errors::ErrorCode;
+use crate::flog;
+use crate::state::order_book::OrderSide;
+use crate::CancelOrder;
+
+pub fn handler(ctx: Context<CancelOrder>) -> Result<()> {
+    let clock = Clock::get()?;
+    let accounts = ctx.accounts;
+    let canceller = accounts.canceller.key();
+
+    // 1. Take the order's open quantity off the book
+    let refund =
+        accounts
+            .order_book
+            .cancel_order(&accounts.order, &canceller, clock.unix_timestamp)?;
+
+    // 2. Refund what the order still holds in escrow, signed by the order book PDA
+    let escrow = match accounts.order.side {
+        OrderSide::Bid => &accounts.quote_escrow,
+        OrderSide::Ask => &accounts.base_escrow,
+    };
+    require_keys_eq!(
+        accounts.owner_token_account.mint,
+        escrow.mint,
+        ErrorCode::InvalidOutputMint
+    );
+    if refund > 0 {
+        let order_book_seeds = &[
+            b"order_book".as_ref(),
+            accounts.order_book.pool_id.as_ref(),
+            &[accounts.order_book.bump],
+        ];
+        token::transfer(
+            CpiContext::new_with_signer(
+                accounts.token_program.to_account_info(),
+                Transfer {
+                    from: escrow.to_account_info(),
+                    to: accounts.owner_token_account.to_account_info(),
+                    authority: accounts.order_book.to_account_info(),
+                },
+                &[&order_book_seeds[..]],
+            ),
+            refund,
+        )?;
+    }
+
+    // 3. Pay a keeper cleaning up an expired order out of its rent, then close the order,
+    //    returning the rest of the rent to the owner
+    let order_info = accounts.order.to_account_info();
+    let keeper_reward = if canceller == accounts.order.owner {
+        0
+    } else {
+        ORDER_CANCEL_KEEPER_REWARD_LAMPORTS.min(order_info.lamports())
+    };
+    if keeper_reward > 0 {
+        **order_info.try_borrow_mut_lamports()? -= keeper_reward;
+        **accounts.canceller.try_borrow_mut_lamports()? += keeper_reward;
+    }
+    let order_id = accounts.order.id;
+    accounts.order.close(accounts.owner.to_account_info())?;
+
+    flog!(
+        info,
+        "limit_order_cancelled",
+        order_book = accounts.order_book.key(),
+        order_id = order_id,
+        refund = refund,
+        keeper_reward = keeper_reward
+    );
+    Ok(())
+}
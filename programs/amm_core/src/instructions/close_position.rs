@@ -0,0 +1,63 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::ErrorCode;
+use crate::flog;
+use crate::tick::TickData;
+use crate::ClosePosition;
+
+pub fn handler(ctx: Context<ClosePosition>) -> Result<()> {
+    // 1. The position must be fully withdrawn and collected. Its liquidity has already
+    // been removed from the ticks by decrease_liquidity, so there is no tick
+    // bookkeeping left to undo here.
+    ctx.accounts.position.ensure_closable()?;
+
+    // 2. Unlist the position from the index of the account that minted it.
+    if ctx.accounts.position.in_owner_index {
+        let position_key = ctx.accounts.position.key();
+        let owner_index = ctx
+            .accounts
+            .owner_index
+            .as_mut()
+            .ok_or(ErrorCode::OwnerIndexRequired)?;
+        owner_index.remove(
+            position_key,
+            &mut ctx.accounts.position,
+            ctx.accounts.previous_indexed_position.as_deref_mut(),
+        )?;
+    }
+
+    // 3. Close the optional tick accounts that no position references anymore.
+    let rent_receiver = ctx.accounts.rent_receiver.to_account_info();
+    let tick_lower_closed = close_tick_if_unused(&ctx.accounts.tick_lower, &rent_receiver)?;
+    let tick_upper_closed = close_tick_if_unused(&ctx.accounts.tick_upper, &rent_receiver)?;
+
+    // 4. The position account itself is closed to `rent_receiver` by its `close` constraint.
+    flog!(
+        info,
+        "position_closed",
+        position = ctx.accounts.position.key(),
+        rent_receiver = rent_receiver.key(),
+        tick_lower_closed = tick_lower_closed,
+        tick_upper_closed = tick_upper_closed
+    );
+
+    Ok(())
+}
+
+/// Closes `tick` to `rent_receiver` if it was passed and has no liquidity left.
+///
+/// Ticks still referenced by other positions are left open.
+/// Returns true if the tick account was closed.
+fn close_tick_if_unused<'info>(
+    tick: &Option<AccountLoader<'info, TickData>>,
+    rent_receiver: &AccountInfo<'info>,
+) -> Result<bool> {
+    let Some(tick) = tick else {
+        return Ok(false);
+    };
+    if tick.load()?.liquidity_gross != 0 {
+        return Ok(false);
+    }
+    tick.close(rent_receiver.clone())?;
+    Ok(true)
+}
@@ -0,0 +1,139 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::errors::ErrorCode;
+use crate::flog;
+use crate::position::{CollectFeesEvent, PositionData};
+use crate::state::pool::Pool;
+use crate::tick_array::PositionTicks;
+use crate::CollectFees;
+
+pub fn handler(
+    ctx: Context<CollectFees>,
+    amount_0_requested: u64,
+    amount_1_requested: u64,
+) -> Result<()> {
+    let accounts = ctx.accounts;
+    let ticks = PositionTicks::Single {
+        lower: &accounts.tick_lower,
+        upper: &accounts.tick_upper,
+    };
+    let (amount_0, amount_1) = collect(
+        &accounts.pool,
+        &mut accounts.position,
+        &ticks,
+        amount_0_requested,
+        amount_1_requested,
+    )?;
+
+    // 3. Transfer the fees from the pool vaults to the owner
+    transfer_from_vaults(
+        &accounts.pool,
+        &accounts.token_program,
+        &accounts.token0_vault,
+        &accounts.owner_token0_account,
+        &accounts.token1_vault,
+        &accounts.owner_token1_account,
+        amount_0,
+        amount_1,
+    )
+}
+
+/// Credits the fees `position` earned and deducts up to the requested amounts from them.
+///
+/// Returns the amounts to pay out, which the caller transfers to the owner.
+pub(crate) fn collect<'info>(
+    pool: &Account<'info, Pool>,
+    position: &mut Account<'info, PositionData>,
+    ticks: &PositionTicks<'_, 'info>,
+    amount_0_requested: u64,
+    amount_1_requested: u64,
+) -> Result<(u64, u64)> {
+    pool.ensure_no_flash_loan()?;
+
+    // 1. Credit the fees earned since the position's last update
+    let (tick_lower_data, tick_upper_data) = ticks.load(position, pool.bitmap_tick_spacing)?;
+    position.update_fees(pool, &tick_lower_data, &tick_upper_data)?;
+
+    // 2. Pay out up to the requested amounts
+    let amount_0 = amount_0_requested.min(position.tokens_owed_0);
+    let amount_1 = amount_1_requested.min(position.tokens_owed_1);
+    if amount_0 == 0 && amount_1 == 0 {
+        return err!(ErrorCode::NoFeesToCollect);
+    }
+    position.tokens_owed_0 -= amount_0;
+    position.tokens_owed_1 -= amount_1;
+    flog!(
+        info,
+        "fees_collected",
+        position = position.key(),
+        amount0 = amount_0,
+        amount1 = amount_1
+    );
+    emit!(CollectFeesEvent {
+        pool: pool.key(),
+        position: position.key(),
+        owner: position.owner,
+        amount0: amount_0,
+        amount1: amount_1,
+    });
+
+    Ok((amount_0, amount_1))
+}
+
+/// Transfers `amount_0` and `amount_1` from the pool vaults, signed by the pool PDA.
+///
+/// Zero amounts are skipped.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn transfer_from_vaults<'info>(
+    pool: &Account<'info, Pool>,
+    token_program: &Program<'info, Token>,
+    token0_vault: &Account<'info, TokenAccount>,
+    token0_destination: &Account<'info, TokenAccount>,
+    token1_vault: &Account<'info, TokenAccount>,
+    token1_destination: &Account<'info, TokenAccount>,
+    amount_0: u64,
+    amount_1: u64,
+) -> Result<()> {
+    let fee_rate_bytes = pool.fee_tier.to_le_bytes();
+    let pool_seeds = &[
+        b"pool".as_ref(),
+        pool.token0_mint.as_ref(),
+        pool.token1_mint.as_ref(),
+        fee_rate_bytes.as_ref(),
+        &[pool.bump],
+    ];
+    let signer_seeds = &[&pool_seeds[..]];
+
+    if amount_0 > 0 {
+        token::transfer(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                Transfer {
+                    from: token0_vault.to_account_info(),
+                    to: token0_destination.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount_0,
+        )?;
+    }
+
+    if amount_1 > 0 {
+        token::transfer(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                Transfer {
+                    from: token1_vault.to_account_info(),
+                    to: token1_destination.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount_1,
+        )?;
+    }
+
+    Ok(())
+}
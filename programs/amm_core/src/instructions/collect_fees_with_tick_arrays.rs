@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::instructions::collect_fees::{collect, transfer_from_vaults};
+use crate::tick_array::PositionTicks;
+use crate::CollectFeesWithTickArrays;
+
+pub fn handler(
+    ctx: Context<CollectFeesWithTickArrays>,
+    amount_0_requested: u64,
+    amount_1_requested: u64,
+) -> Result<()> {
+    let accounts = ctx.accounts;
+    let ticks = PositionTicks::Arrays {
+        lower: &accounts.tick_array_lower,
+        upper: &accounts.tick_array_upper,
+    };
+    let (amount_0, amount_1) = collect(
+        &accounts.pool,
+        &mut accounts.position,
+        &ticks,
+        amount_0_requested,
+        amount_1_requested,
+    )?;
+
+    transfer_from_vaults(
+        &accounts.pool,
+        &accounts.token_program,
+        &accounts.token0_vault,
+        &accounts.owner_token0_account,
+        &accounts.token1_vault,
+        &accounts.owner_token1_account,
+        amount_0,
+        amount_1,
+    )
+}
@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::instructions::collect_fees::transfer_from_vaults;
+use crate::CollectProtocolFees;
+
+pub fn handler(ctx: Context<CollectProtocolFees>) -> Result<()> {
+    let (amount_0, amount_1) = ctx.accounts.pool.take_protocol_fees();
+
+    transfer_from_vaults(
+        &ctx.accounts.pool,
+        &ctx.accounts.token_program,
+        &ctx.accounts.token0_vault,
+        &ctx.accounts.token0_destination,
+        &ctx.accounts.token1_vault,
+        &ctx.accounts.token1_destination,
+        amount_0,
+        amount_1,
+    )?;
+
+    flog!(
+        info,
+        "protocol_fees_collected",
+        pool = ctx.accounts.pool.key(),
+        amount0 = amount_0,
+        amount1 = amount_1
+    );
+    Ok(())
+}
@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::CreateTokenPair;
+
+pub fn handler(ctx: Context<CreateTokenPair>, oracle_feed: Pubkey) -> Result<()> {
+    ctx.accounts.token_pair.initialize(
+        ctx.bumps.token_pair,
+        ctx.accounts.mint_a.key(),
+        ctx.accounts.mint_b.key(),
+        oracle_feed,
+    )?;
+
+    flog!(
+        info,
+        "token_pair_created",
+        token_pair = ctx.accounts.token_pair.key(),
+        mint_a = ctx.accounts.mint_a.key(),
+        mint_b = ctx.accounts.mint_b.key(),
+        oracle_feed = oracle_feed
+    );
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::ErrorCode;
+use crate::flog;
+use crate::instructions::collect_fees::transfer_from_vaults;
+use crate::math;
+use crate::position::{DecreaseLiquidityEvent, PositionData};
+use crate::state::pool::Pool;
+use crate::tick_array::PositionTicks;
+use crate::tick_bitmap::{self, TickBitmap};
+use crate::DecreaseLiquidity;
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, DecreaseLiquidity<'info>>,
+    liquidity_delta: u128,
+) -> Result<()> {
+    let tick_bitmaps = tick_bitmap::load_tick_bitmap_accounts(ctx.remaining_accounts)?;
+    let tick_bitmap_loaders: Vec<&AccountLoader<'info, TickBitmap>> = tick_bitmaps.iter().collect();
+    let accounts = ctx.accounts;
+    let ticks = PositionTicks::Single {
+        lower: &accounts.tick_lower,
+        upper: &accounts.tick_upper,
+    };
+    let (amount0_u64, amount1_u64) = withdraw(
+        &mut accounts.pool,
+        &mut accounts.position,
+        &ticks,
+        &tick_bitmap_loaders,
+        liquidity_delta,
+    )?;
+
+    // 5. Transfer the withdrawn tokens from the pool vaults to the owner
+    transfer_from_vaults(
+        &accounts.pool,
+        &accounts.token_program,
+        &accounts.token0_vault,
+        &accounts.owner_token0_account,
+        &accounts.token1_vault,
+        &accounts.owner_token1_account,
+        amount0_u64,
+        amount1_u64,
+    )
+}
+
+/// Removes `liquidity_delta` from `position` and returns the token amounts owed for it.
+///
+/// Fees earned so far are settled into the position first. The caller transfers the
+/// returned amounts to the owner.
+pub(crate) fn withdraw<'info>(
+    pool: &mut Account<'info, Pool>,
+    position: &mut Account<'info, PositionData>,
+    ticks: &PositionTicks<'_, 'info>,
+    tick_bitmap_loaders: &[&AccountLoader<'info, TickBitmap>],
+    liquidity_delta: u128,
+) -> Result<(u64, u64)> {
+    pool.ensure_no_flash_loan()?;
+
+    // Validate liquidity amount
+    if liquidity_delta == 0 {
+        return err!(ErrorCode::ZeroLiquidityDelta);
+    }
+    // A position's liquidity can never go negative
+    if liquidity_delta > position.liquidity {
+        return err!(ErrorCode::PositionLiquidityTooLow);
+    }
+    let liquidity_delta_i128 =
+        i128::try_from(liquidity_delta).map_err(|_| error!(ErrorCode::MathOverflow))?;
+
+    let tick_lower_index = position.tick_lower_index;
+    let tick_upper_index = position.tick_upper_index;
+
+    // 1. Settle the fees earned so far, before the position's liquidity changes.
+    let (tick_lower_data, tick_upper_data) = ticks.load(position, pool.bitmap_tick_spacing)?;
+    position.update_fees(pool, &tick_lower_data, &tick_upper_data)?;
+
+    // 2. Compute the token amounts owed for the removed liquidity at the current price.
+    // Withdrawals round down so the pool never pays out more than it holds.
+    let (amount0_u128, amount1_u128) = math::get_amounts_for_liquidity(
+        pool.sqrt_price_q64,
+        math::tick_to_sqrt_price_q64(tick_lower_index)?,
+        math::tick_to_sqrt_price_q64(tick_upper_index)?,
+        liquidity_delta,
+        false,
+    )?;
+
+    // 3. Remove liquidity from the ticks, the bitmap and (if in range) the pool.
+    let pool_key = pool.key();
+    pool.load_bitmap_words(&pool_key, tick_bitmap_loaders)?;
+    ticks.modify_liquidity(
+        pool,
+        tick_lower_index,
+        tick_upper_index,
+        -liquidity_delta_i128,
+    )?;
+    pool.store_bitmap_words(tick_bitmap_loaders)?;
+
+    // 4. Update the position
+    position.liquidity = position
+        .liquidity
+        .checked_sub(liquidity_delta)
+        .ok_or(ErrorCode::PositionLiquidityTooLow)?;
+    flog!(
+        info,
+        "liquidity_decreased",
+        position = position.key(),
+        liquidity_delta = liquidity_delta,
+        liquidity = position.liquidity,
+        amount0 = amount0_u128,
+        amount1 = amount1_u128
+    );
+
+    let amount0_u64 = u64::try_from(amount0_u128)
+        .map_err(|_| error!(ErrorCode::MathOverflow).with_account_name("amount0_u128"))?;
+    let amount1_u64 = u64::try_from(amount1_u128)
+        .map_err(|_| error!(ErrorCode::MathOverflow).with_account_name("amount1_u128"))?;
+    emit!(DecreaseLiquidityEvent {
+        pool: pool_key,
+        position: position.key(),
+        owner: position.owner,
+        liquidity_delta,
+        liquidity: position.liquidity,
+        amount0: amount0_u64,
+        amount1: amount1_u64,
+    });
+    Ok((amount0_u64, amount1_u64))
+}
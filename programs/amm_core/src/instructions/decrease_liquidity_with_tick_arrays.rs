@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+
+use crate::instructions::collect_fees::transfer_from_vaults;
+use crate::instructions::decrease_liquidity::withdraw;
+use crate::tick_array::PositionTicks;
+use crate::tick_bitmap::{self, TickBitmap};
+use crate::DecreaseLiquidityWithTickArrays;
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, DecreaseLiquidityWithTickArrays<'info>>,
+    liquidity_delta: u128,
+) -> Result<()> {
+    let tick_bitmaps = tick_bitmap::load_tick_bitmap_accounts(ctx.remaining_accounts)?;
+    let tick_bitmap_loaders: Vec<&AccountLoader<'info, TickBitmap>> = tick_bitmaps.iter().collect();
+    let accounts = ctx.accounts;
+    let ticks = PositionTicks::Arrays {
+        lower: &accounts.tick_array_lower,
+        upper: &accounts.tick_array_upper,
+    };
+    let (amount0_u64, amount1_u64) = withdraw(
+        &mut accounts.pool,
+        &mut accounts.position,
+        &ticks,
+        &tick_bitmap_loaders,
+        liquidity_delta,
+    )?;
+
+    transfer_from_vaults(
+        &accounts.pool,
+        &accounts.token_program,
+        &accounts.token0_vault,
+        &accounts.owner_token0_account,
+        &accounts.token1_vault,
+        &accounts.owner_token1_account,
+        amount0_u64,
+        amount1_u64,
+    )
+}
@@ -0,0 +1,18 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::EnableFeeTier;
+
+pub fn handler(ctx: Context<EnableFeeTier>, fee_rate: u16, tick_spacing: u16) -> Result<()> {
+    let factory_config = &mut ctx.accounts.factory_config;
+    factory_config.enable_fee_tier(fee_rate, tick_spacing)?;
+
+    flog!(
+        info,
+        "fee_tier_enabled",
+        factory_config = factory_config.key(),
+        fee_rate = fee_rate,
+        tick_spacing = tick_spacing
+    );
+    Ok(())
+}
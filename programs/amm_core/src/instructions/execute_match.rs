@@ -0,0 +1,76 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+
+use crate::state::order_book::TradeExecuted;
+use crate::ExecuteMatch;
+
+pub fn handler(ctx: Context<ExecuteMatch>) -> Result<()> {
+    let clock = Clock::get()?;
+    let accounts = ctx.accounts;
+
+    // 1. Fill both orders for as much as they have in common
+    let fill = accounts.order_book.match_orders(
+        &mut accounts.bid,
+        &mut accounts.ask,
+        clock.unix_timestamp,
+    )?;
+
+    // 2. Pay out of the escrows, signed by the order book PDA
+    let order_book_seeds = &[
+        b"order_book".as_ref(),
+        accounts.order_book.pool_id.as_ref(),
+        &[accounts.order_book.bump],
+    ];
+    let signer_seeds = &[&order_book_seeds[..]];
+    let payouts = [
+        (
+            &accounts.base_escrow,
+            &accounts.bidder_token0_account,
+            fill.quantity,
+        ),
+        (
+            &accounts.quote_escrow,
+            &accounts.asker_token1_account,
+            fill.quote_amount,
+        ),
+        (
+            &accounts.quote_escrow,
+            &accounts.bidder_token1_account,
+            fill.bid_refund,
+        ),
+    ];
+    for (from, to, amount) in payouts {
+        if amount == 0 {
+            continue;
+        }
+        token::transfer(
+            CpiContext::new_with_signer(
+                accounts.token_program.to_account_info(),
+                Transfer {
+                    from: from.to_account_info(),
+                    to: to.to_account_info(),
+                    authority: accounts.order_book.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )?;
+    }
+
+    emit!(TradeExecuted {
+        order_book: accounts.order_book.key(),
+        bid_order_id: accounts.bid.id,
+        ask_order_id: accounts.ask.id,
+        price: fill.price,
+        quantity: fill.quantity,
+    });
+
+    // 3. Close filled orders, returning their rent to the owners
+    if accounts.bid.is_filled() {
+        accounts.bid.close(accounts.bid_owner.to_account_info())?;
+    }
+    if accounts.ask.is_filled() {
+        accounts.ask.close(accounts.ask_owner.to_account_info())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke;
+use anchor_spl::token::{self, Transfer};
+
+use crate::errors::ErrorCode;
+use crate::flog;
+use crate::FlashLoan;
+
+/// Arguments passed to the borrower's `flash_loan_callback` instruction.
+///
+/// The callback is invoked with Anchor's instruction layout: the 8-byte sighash of
+/// `global:flash_loan_callback` followed by these arguments. Before it returns, the
+/// borrower must transfer `amount_0 + fee_0` of token0 and `amount_1 + fee_1` of token1
+/// back to the pool vaults.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FlashLoanCallbackArgs {
+    pub amount_0: u64,
+    pub amount_1: u64,
+    pub fee_0: u64,
+    pub fee_1: u64,
+}
+
+/// Builds the instruction data for the borrower's `flash_loan_callback`.
+pub fn flash_loan_callback_data(args: &FlashLoanCallbackArgs) -> Result<Vec<u8>> {
+    let mut data = hash(b"global:flash_loan_callback").to_bytes()[..8].to_vec();
+    args.serialize(&mut data)?;
+    Ok(data)
+}
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, FlashLoan<'info>>,
+    amount_token0: u64,
+    amount_token1: u64,
+) -> Result<()> {
+    // 1. Validate the request
+    ctx.accounts.pool.ensure_no_flash_loan()?;
+    ctx.accounts.pool.ensure_not_paused()?;
+    require!(
+        amount_token0 > 0 || amount_token1 > 0,
+        ErrorCode::ZeroFlashLoanAmount
+    );
+    require!(
+        ctx.accounts.pool.liquidity > 0,
+        ErrorCode::InsufficientLiquidity
+    );
+    let (callback_program, callback_accounts) = ctx
+        .remaining_accounts
+        .split_first()
+        .ok_or(ErrorCode::MissingFlashLoanCallbackProgram)?;
+    require!(
+        callback_program.executable,
+        ErrorCode::MissingFlashLoanCallbackProgram
+    );
+
+    let fee_0 = ctx.accounts.pool.flash_fee(amount_token0)?;
+    let fee_1 = ctx.accounts.pool.flash_fee(amount_token1)?;
+    let balance_0_before = ctx.accounts.token0_vault.amount;
+    let balance_1_before = ctx.accounts.token1_vault.amount;
+
+    // 2. Transfer the borrowed amounts to the borrower
+    let pool = &ctx.accounts.pool;
+    let fee_rate_bytes = pool.fee_tier.to_le_bytes();
+    let pool_seeds = &[
+        b"pool".as_ref(),
+        pool.token0_mint.as_ref(),
+        pool.token1_mint.as_ref(),
+        fee_rate_bytes.as_ref(),
+        &[pool.bump],
+    ];
+    let signer_seeds = &[&pool_seeds[..]];
+
+    if amount_token0 > 0 {
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.token0_vault.to_account_info(),
+                    to: ctx.accounts.borrower_token0_account.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount_token0,
+        )?;
+    }
+
+    if amount_token1 > 0 {
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.token1_vault.to_account_info(),
+                    to: ctx.accounts.borrower_token1_account.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount_token1,
+        )?;
+    }
+
+    // 3. Mark the loan as in progress. The pool is written back before the callback so that
+    // any instruction it reaches sees the flag.
+    ctx.accounts.pool.flash_loan_active = 1;
+    ctx.accounts.pool.exit(&crate::ID)?;
+
+    // 4. Invoke the borrower's callback with the remaining accounts
+    let callback_ix = Instruction {
+        program_id: callback_program.key(),
+        accounts: callback_accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data: flash_loan_callback_data(&FlashLoanCallbackArgs {
+            amount_0: amount_token0,
+            amount_1: amount_token1,
+            fee_0,
+            fee_1,
+        })?,
+    };
+    invoke(&callback_ix, ctx.remaining_accounts)?;
+
+    // 5. Check that both vaults were repaid with the fee. The pool is reloaded too, so that
+    // anything the callback wrote to it is not overwritten by the copy held since step 3.
+    ctx.accounts.pool.reload()?;
+    ctx.accounts.token0_vault.reload()?;
+    ctx.accounts.token1_vault.reload()?;
+    let balance_0_after = ctx.accounts.token0_vault.amount;
+    let balance_1_after = ctx.accounts.token1_vault.amount;
+    let required_0 = balance_0_before
+        .checked_add(fee_0)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let required_1 = balance_1_before
+        .checked_add(fee_1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if balance_0_after < required_0 || balance_1_after < required_1 {
+        flog!(
+            error,
+            "flash_loan_not_repaid",
+            pool = ctx.accounts.pool.key(),
+            required0 = required_0,
+            balance0 = balance_0_after,
+            required1 = required_1,
+            balance1 = balance_1_after
+        );
+        return err!(ErrorCode::FlashLoanNotRepaid);
+    }
+
+    // 6. Credit everything paid on top of the principal to liquidity providers
+    let paid_0 = balance_0_after - balance_0_before;
+    let paid_1 = balance_1_after - balance_1_before;
+    let pool = &mut ctx.accounts.pool;
+    pool.accrue_flash_fees(paid_0, paid_1)?;
+    pool.flash_loan_active = 0;
+
+    flog!(
+        info,
+        "flash_loan",
+        pool = pool.key(),
+        amount0 = amount_token0,
+        amount1 = amount_token1,
+        paid0 = paid_0,
+        paid1 = paid_1
+    );
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::InitializeBitmapWord;
+
+pub fn handler(ctx: Context<InitializeBitmapWord>, word_index: i16) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let word = ctx.accounts.pool.externalize_bitmap_word(word_index)?;
+
+    let mut tick_bitmap = ctx.accounts.tick_bitmap.load_init()?;
+    tick_bitmap.word = word;
+    tick_bitmap.pool = pool_key;
+    tick_bitmap.word_index = word_index;
+
+    flog!(
+        debug,
+        "tick_bitmap_word_initialized",
+        tick_bitmap = ctx.accounts.tick_bitmap.key(),
+        pool = pool_key,
+        word_index = word_index
+    );
+
+    Ok(())
+}
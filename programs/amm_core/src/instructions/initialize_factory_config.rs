@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::state::factory_config::ProtocolFeeCurve;
+use crate::InitializeFactoryConfig;
+
+pub fn handler(
+    ctx: Context<InitializeFactoryConfig>,
+    protocol_fee_curve: ProtocolFeeCurve,
+) -> Result<()> {
+    let authority = ctx.accounts.authority.key();
+    let factory_config = &mut ctx.accounts.factory_config;
+    factory_config.initialize(ctx.bumps.factory_config, authority, protocol_fee_curve)?;
+
+    flog!(
+        info,
+        "factory_config_initialized",
+        factory_config = factory_config.key(),
+        authority = authority
+    );
+    Ok(())
+}
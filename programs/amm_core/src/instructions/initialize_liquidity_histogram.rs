@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::InitializeLiquidityHistogram;
+
+pub fn handler(ctx: Context<InitializeLiquidityHistogram>, bucket_ticks: u32) -> Result<()> {
+    let pool = &ctx.accounts.pool;
+    ctx.accounts.liquidity_histogram.load_init()?.initialize(
+        pool.key(),
+        bucket_ticks,
+        pool.tick_spacing,
+    )?;
+
+    flog!(
+        debug,
+        "liquidity_histogram_initialized",
+        liquidity_histogram = ctx.accounts.liquidity_histogram.key(),
+        pool = pool.key(),
+        bucket_ticks = bucket_ticks
+    );
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::instructions::initialize_pool::create_pool;
+use crate::InitializePool;
+
+/// How a new pool's oracle is seeded, see `Pool::seed_observations`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OracleSeedParams {
+    /// Number of observations to seed, from 2 up to `OBSERVATION_CAPACITY`.
+    pub cardinality: u16,
+    /// Seconds between seeded observations.
+    pub interval_secs: u32,
+}
+
+pub fn handler(
+    ctx: Context<InitializePool>,
+    initial_sqrt_price_q64: u128,
+    fee_rate: u16,
+    tick_spacing: u16,
+    immutable_parameters: bool,
+    oracle_seed: OracleSeedParams,
+) -> Result<()> {
+    create_pool(
+        ctx.accounts,
+        &ctx.bumps,
+        initial_sqrt_price_q64,
+        fee_rate,
+        tick_spacing,
+        immutable_parameters,
+    )?;
+
+    // Same instruction as the pool's creation, so no swap can move the price in between
+    ctx.accounts
+        .pool
+        .seed_observations(oracle_seed.cardinality, oracle_seed.interval_secs)?;
+
+    flog!(
+        info,
+        "oracle_pool_initialized",
+        pool = ctx.accounts.pool.key(),
+        observation_cardinality = oracle_seed.cardinality,
+        observation_interval_secs = oracle_seed.interval_secs
+    );
+    Ok(())
+}
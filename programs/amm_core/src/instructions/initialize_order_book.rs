@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::InitializeOrderBook;
+
+pub fn handler(ctx: Context<InitializeOrderBook>, tick_size: u64) -> Result<()> {
+    let pool_id = ctx.accounts.pool.key();
+    ctx.accounts.order_book.initialize(
+        ctx.bumps.order_book,
+        pool_id,
+        tick_size,
+        ctx.accounts.base_escrow.key(),
+        ctx.accounts.quote_escrow.key(),
+    )?;
+
+    flog!(
+        info,
+        "order_book_initialized",
+        order_book = ctx.accounts.order_book.key(),
+        pool = pool_id,
+        tick_size = tick_size
+    );
+    Ok(())
+}
@@ -1,55 +1,136 @@
 use anchor_lang::prelude::*;
 
+use crate::constants::{
+    ALLOWED_TICK_SPACINGS, MAX_FEE_RATE, MAX_SQRT_PRICE, MIN_FEE_RATE, MIN_SQRT_PRICE,
+};
 use crate::errors::ErrorCode;
+use crate::flog;
 use crate::state::pool::*;
-use crate::InitializePool;
+use crate::{InitializePool, InitializePoolBumps};
 
 pub fn handler(
     ctx: Context<InitializePool>,
     initial_sqrt_price_q64: u128,
     fee_rate: u16,
     tick_spacing: u16,
+    immutable_parameters: bool,
 ) -> Result<()> {
-    // Ensure canonical mint order for PDA derivation consistency.
-    // This check reinforces the client-side responsibility.
-    if ctx.accounts.mint_a.key() >= ctx.accounts.mint_b.key() {
-        return err!(ErrorCode::MintsNotInCanonicalOrder);
-    }
-
-    /* msg!(
-        "Initializing new pool for mints: {} and {}",
-        ctx.accounts.mint_a.key(),
-        ctx.accounts.mint_b.key()
-    );
-    */
+    create_pool(
+        ctx.accounts,
+        &ctx.bumps,
+        initial_sqrt_price_q64,
+        fee_rate,
+        tick_spacing,
+        immutable_parameters,
+    )
+}
+
+/// Initializes the pool and records it in the pair's registry and `TokenPair`.
+///
+/// The fee rate and tick spacing must be a fee tier the factory config enables. The pool
+/// takes the protocol share the config's curve gives an empty pool, and the config's
+/// authority as its own.
+pub(crate) fn create_pool(
+    accounts: &mut InitializePool,
+    bumps: &InitializePoolBumps,
+    initial_sqrt_price_q64: u128,
+    fee_rate: u16,
+    tick_spacing: u16,
+    immutable_parameters: bool,
+) -> Result<()> {
+    validate_pool_params(
+        &accounts.mint_a.key(),
+        &accounts.mint_b.key(),
+        initial_sqrt_price_q64,
+        fee_rate,
+        tick_spacing,
+    )?;
+    accounts
+        .factory
+        .ensure_fee_tier_enabled(fee_rate, tick_spacing)?;
 
-    // Anchor provides the bump directly if the PDA account is named in `ctx.bumps`.
+    // Anchor provides the bump directly if the PDA account is named in `bumps`.
     // The `pool` account is named `pool` in the `InitializePool` struct.
-    let bump = ctx.bumps.pool;
+    let bump = bumps.pool;
+    let protocol_fee = accounts.factory.protocol_fee_curve.protocol_share_bps(0);
 
     let params = InitializePoolParams {
         bump,
-        factory: ctx.accounts.factory.key(),
-        token0_mint: ctx.accounts.mint_a.key(), // mint_a is canonically smaller
-        token1_mint: ctx.accounts.mint_b.key(), // mint_b is canonically larger
-        token0_vault: ctx.accounts.pool_vault_a.key(),
-        token1_vault: ctx.accounts.pool_vault_b.key(),
+        factory: accounts.factory.key(),
+        authority: accounts.factory.authority,
+        token0_mint: accounts.mint_a.key(), // mint_a is canonically smaller
+        token1_mint: accounts.mint_b.key(), // mint_b is canonically larger
+        token0_vault: accounts.pool_vault_a.key(),
+        token1_vault: accounts.pool_vault_b.key(),
+        token_pair: accounts.token_pair.key(),
         initial_sqrt_price_q64,
         fee_rate,
+        protocol_fee,
+        immutable_parameters,
         tick_spacing,
+        timestamp: Clock::get()?.unix_timestamp,
     };
 
-    ctx.accounts.pool.initialize(params)?;
+    accounts.pool.initialize(params)?;
 
-    /* msg!("Pool initialized successfully: {}", ctx.accounts.pool.key());
-    msg!(
-        "  Vault A: {}, Vault B: {}",
-        ctx.accounts.pool_vault_a.key(),
-        ctx.accounts.pool_vault_b.key()
+    accounts.pool_registry.register(
+        bumps.pool_registry,
+        accounts.mint_a.key(),
+        accounts.mint_b.key(),
+        fee_rate,
+        accounts.pool.key(),
+    )?;
+    accounts.token_pair.record_pool()?;
+
+    flog!(
+        info,
+        "pool_initialized",
+        pool = accounts.pool.key(),
+        mint_a = accounts.mint_a.key(),
+        mint_b = accounts.mint_b.key(),
+        vault_a = accounts.pool_vault_a.key(),
+        vault_b = accounts.pool_vault_b.key(),
+        sqrt_price_q64 = initial_sqrt_price_q64,
+        fee_rate = fee_rate,
+        tick_spacing = tick_spacing,
+        protocol_fee = protocol_fee,
+        immutable_parameters = immutable_parameters
+    );
+    Ok(())
+}
+
+/// Checks the parameters of a pool about to be created.
+///
+/// A pair has one canonical order, so a pool created with its mints swapped would live at
+/// another PDA and duplicate the pair's pool for that fee rate.
+///
+/// # Errors
+///
+/// * `MintsMustDiffer` - Both mints are the same.
+/// * `MintsNotInCanonicalOrder` - `mint_a` is not smaller than `mint_b`.
+/// * `InvalidInitialPrice` - The price is outside `MIN_SQRT_PRICE..=MAX_SQRT_PRICE`.
+/// * `InvalidFeeRate` - The fee rate is outside `MIN_FEE_RATE..=MAX_FEE_RATE`.
+/// * `InvalidTickSpacing` - The tick spacing is not one of `ALLOWED_TICK_SPACINGS`.
+pub(crate) fn validate_pool_params(
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+    initial_sqrt_price_q64: u128,
+    fee_rate: u16,
+    tick_spacing: u16,
+) -> Result<()> {
+    require_keys_neq!(*mint_a, *mint_b, ErrorCode::MintsMustDiffer);
+    require!(mint_a < mint_b, ErrorCode::MintsNotInCanonicalOrder);
+    require!(
+        (MIN_SQRT_PRICE..=MAX_SQRT_PRICE).contains(&initial_sqrt_price_q64),
+        ErrorCode::InvalidInitialPrice
+    );
+    require!(
+        (MIN_FEE_RATE..=MAX_FEE_RATE).contains(&fee_rate),
+        ErrorCode::InvalidFeeRate
+    );
+    require!(
+        ALLOWED_TICK_SPACINGS.contains(&tick_spacing),
+        ErrorCode::InvalidTickSpacing
     );
-    msg!("  Initial SqrtPriceQ64: {}", initial_sqrt_price_q64);
-    msg!("  Fee Rate (bps): {}", fee_rate);
-    msg!("  Tick Spacing: {}", tick_spacing);
-    */
     Ok(())
 }
@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::InitializeTickArray;
+
+pub fn handler(ctx: Context<InitializeTickArray>, start_tick_index: i32) -> Result<()> {
+    let pool = &ctx.accounts.pool;
+    ctx.accounts.tick_array.load_init()?.initialize(
+        pool.key(),
+        start_tick_index,
+        pool.bitmap_tick_spacing,
+    )?;
+
+    flog!(
+        debug,
+        "tick_array_initialized",
+        tick_array = ctx.accounts.tick_array.key(),
+        pool = pool.key(),
+        start_tick_index = start_tick_index
+    );
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, TokenAccount, Transfer};
+
+use crate::errors::ErrorCode;
+use crate::flog;
+use crate::state::order_book::{Order, OrderSide, TradeExecuted};
+use crate::MatchOrdersBatch;
+
+/// Accounts passed through `remaining_accounts` for a bid: the order, its owner, and the
+/// owner's token0 and token1 accounts.
+const BID_ACCOUNTS: usize = 4;
+/// Accounts passed through `remaining_accounts` for an ask: the order, its owner, and the
+/// owner's token1 account.
+const ASK_ACCOUNTS: usize = 3;
+
+/// An order of the batch with the accounts its fills pay out to.
+struct BatchOrder<'info> {
+    order: Account<'info, Order>,
+    owner: &'info AccountInfo<'info>,
+    /// The bidder's token0 account. Asks receive no token0.
+    token0_account: Option<&'info AccountInfo<'info>>,
+    token1_account: &'info AccountInfo<'info>,
+    /// Token0 and token1 owed to the owner by the batch's fills.
+    token0_owed: u64,
+    token1_owed: u64,
+}
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, MatchOrdersBatch<'info>>,
+    max_matches: u8,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let order_book_key = ctx.accounts.order_book.key();
+
+    // 1. Load the orders, bids before asks, each followed by its payout accounts
+    let mut bids: Vec<BatchOrder<'info>> = Vec::new();
+    let mut asks: Vec<BatchOrder<'info>> = Vec::new();
+    let mut remaining_accounts = ctx.remaining_accounts;
+    while let Some(order_info) = remaining_accounts.first() {
+        let order = Account::<'info, Order>::try_from(order_info)?;
+        require_keys_eq!(
+            order.order_book,
+            order_book_key,
+            anchor_lang::error::ErrorCode::ConstraintHasOne
+        );
+        let account_count = match order.side {
+            OrderSide::Bid => BID_ACCOUNTS,
+            OrderSide::Ask => ASK_ACCOUNTS,
+        };
+        require!(
+            order.side == OrderSide::Ask || asks.is_empty(),
+            ErrorCode::OrderBatchNotSorted
+        );
+        if remaining_accounts.len() < account_count {
+            return err!(anchor_lang::error::ErrorCode::AccountNotEnoughKeys);
+        }
+        let (order_accounts, rest) = remaining_accounts.split_at(account_count);
+        remaining_accounts = rest;
+
+        let owner = &order_accounts[1];
+        require_keys_eq!(owner.key(), order.owner, ErrorCode::UnauthorizedAccess);
+        let token1_account = &order_accounts[account_count - 1];
+        check_payout_account(token1_account, &ctx.accounts.quote_escrow, &order)?;
+        let token0_account = match order.side {
+            OrderSide::Bid => {
+                check_payout_account(&order_accounts[2], &ctx.accounts.base_escrow, &order)?;
+                Some(&order_accounts[2])
+            }
+            OrderSide::Ask => None,
+        };
+        let batch_order = BatchOrder {
+            order,
+            owner,
+            token0_account,
+            token1_account,
+            token0_owed: 0,
+            token1_owed: 0,
+        };
+        match batch_order.order.side {
+            OrderSide::Bid => bids.push(batch_order),
+            OrderSide::Ask => asks.push(batch_order),
+        }
+    }
+
+    // 2. Match the best bids against the best asks while they cross
+    let fills = {
+        let mut bid_orders: Vec<&mut Order> = bids.iter_mut().map(|bid| &mut *bid.order).collect();
+        let mut ask_orders: Vec<&mut Order> = asks.iter_mut().map(|ask| &mut *ask.order).collect();
+        ctx.accounts.order_book.match_batch(
+            &mut bid_orders,
+            &mut ask_orders,
+            max_matches as usize,
+            clock.unix_timestamp,
+        )?
+    };
+    for batch_fill in &fills {
+        let fill = batch_fill.fill;
+        let bid = &mut bids[batch_fill.bid_index];
+        bid.token0_owed = add_owed(bid.token0_owed, fill.quantity)?;
+        bid.token1_owed = add_owed(bid.token1_owed, fill.bid_refund)?;
+        let ask = &mut asks[batch_fill.ask_index];
+        ask.token1_owed = add_owed(ask.token1_owed, fill.quote_amount)?;
+        emit!(TradeExecuted {
+            order_book: order_book_key,
+            bid_order_id: bids[batch_fill.bid_index].order.id,
+            ask_order_id: asks[batch_fill.ask_index].order.id,
+            price: fill.price,
+            quantity: fill.quantity,
+        });
+    }
+
+    // 3. Pay every owner once for all of its order's fills, signed by the order book PDA
+    let order_book = &ctx.accounts.order_book;
+    let order_book_seeds = &[
+        b"order_book".as_ref(),
+        order_book.pool_id.as_ref(),
+        &[order_book.bump],
+    ];
+    let signer_seeds = &[&order_book_seeds[..]];
+    for batch_order in bids.iter().chain(&asks) {
+        let payouts = [
+            (
+                &ctx.accounts.base_escrow,
+                batch_order.token0_account,
+                batch_order.token0_owed,
+            ),
+            (
+                &ctx.accounts.quote_escrow,
+                Some(batch_order.token1_account),
+                batch_order.token1_owed,
+            ),
+        ];
+        for (from, to, amount) in payouts {
+            let Some(to) = to else {
+                continue;
+            };
+            if amount == 0 {
+                continue;
+            }
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: from.to_account_info(),
+                        to: to.clone(),
+                        authority: order_book.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                amount,
+            )?;
+        }
+    }
+
+    // 4. Close filled orders, returning their rent to the owners, and write back the rest,
+    // which Anchor does not persist for `remaining_accounts`
+    for batch_order in bids.into_iter().chain(asks) {
+        if batch_order.order.is_filled() {
+            batch_order.order.close(batch_order.owner.clone())?;
+        } else {
+            batch_order.order.exit(&crate::ID)?;
+        }
+    }
+
+    flog!(
+        info,
+        "orders_matched",
+        order_book = order_book_key,
+        fills = fills.len(),
+        max_matches = max_matches
+    );
+    Ok(())
+}
+
+/// Checks that `account` is a token account of `escrow`'s mint owned by `order`'s owner.
+fn check_payout_account<'info>(
+    account: &'info AccountInfo<'info>,
+    escrow: &Account<TokenAccount>,
+    order: &Order,
+) -> Result<()> {
+    let token_account = Account::<TokenAccount>::try_from(account)?;
+    require_keys_eq!(
+        token_account.mint,
+        escrow.mint,
+        ErrorCode::InvalidOutputMint
+    );
+    require_keys_eq!(
+        token_account.owner,
+        order.owner,
+        ErrorCode::UnauthorizedAccess
+    );
+    Ok(())
+}
+
+fn add_owed(owed: u64, amount: u64) -> Result<u64> {
+    owed.checked_add(amount)
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
@@ -0,0 +1,68 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+
+use crate::errors::ErrorCode;
+use crate::flog;
+use crate::position::PositionData;
+use crate::tick::TickData;
+use crate::MigratePosition;
+
+pub fn handler(ctx: Context<MigratePosition>) -> Result<()> {
+    let pool = &ctx.accounts.pool;
+    let position_info = ctx.accounts.position.to_account_info();
+
+    // 1. Read the position from whichever layout it was written under
+    let stored_len = position_info.data_len();
+    require!(
+        stored_len < PositionData::LEN,
+        ErrorCode::PositionAlreadyMigrated
+    );
+    let mut position = PositionData::try_deserialize_any_layout(&position_info.try_borrow_data()?)?;
+    require_keys_eq!(position.pool, pool.key(), ErrorCode::InvalidPool);
+    require_keys_eq!(
+        ctx.accounts.tick_lower.key(),
+        TickData::address(&pool.key(), position.tick_lower_index),
+        anchor_lang::error::ErrorCode::ConstraintSeeds
+    );
+    require_keys_eq!(
+        ctx.accounts.tick_upper.key(),
+        TickData::address(&pool.key(), position.tick_upper_index),
+        anchor_lang::error::ErrorCode::ConstraintSeeds
+    );
+
+    // 2. Bring the fields the old layout lacked up to date with the pool
+    position.migrate_layout(
+        stored_len,
+        pool,
+        &*ctx.accounts.tick_lower.load()?,
+        &*ctx.accounts.tick_upper.load()?,
+    );
+
+    // 3. Grow the account to the current layout, topping up its rent from the authority
+    let rent_shortfall = Rent::get()?
+        .minimum_balance(PositionData::LEN)
+        .saturating_sub(position_info.lamports());
+    if rent_shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: position_info.clone(),
+                },
+            ),
+            rent_shortfall,
+        )?;
+    }
+    position_info.realloc(PositionData::LEN, true)?;
+    position.try_serialize(&mut &mut position_info.try_borrow_mut_data()?[..])?;
+
+    flog!(
+        info,
+        "position_migrated",
+        pool = pool.key(),
+        position = position_info.key(),
+        stored_len = stored_len
+    );
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::state::pool::TickSpacingChanged;
+use crate::MigrateTickSpacing;
+
+pub fn handler(ctx: Context<MigrateTickSpacing>, new_tick_spacing: u16) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    let old_tick_spacing = pool.tick_spacing;
+    pool.migrate_tick_spacing(new_tick_spacing)?;
+
+    emit!(TickSpacingChanged {
+        pool: pool.key(),
+        old_tick_spacing,
+        new_tick_spacing,
+    });
+    flog!(
+        info,
+        "tick_spacing_migrated",
+        pool = pool.key(),
+        old_tick_spacing = old_tick_spacing,
+        new_tick_spacing = new_tick_spacing
+    );
+    Ok(())
+}
@@ -1,97 +1,339 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
 use crate::constants::{MAX_TICK, MIN_LIQUIDITY, MIN_TICK};
 use crate::errors::ErrorCode;
+use crate::flog;
+use crate::math;
+use crate::position::{MintPositionEvent, OwnerPositionIndex, PositionData};
+use crate::state::pool::Pool;
+use crate::tick::TickData;
+use crate::tick_bitmap::{self, TickBitmap};
 use crate::MintPosition;
 
-pub fn handler(
-    ctx: Context<MintPosition>,
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, MintPosition<'info>>,
     tick_lower_index: i32,
     tick_upper_index: i32,
     liquidity_amount_desired: u128,
+    position_index: u64,
+    amount0_max: u64,
+    amount1_max: u64,
 ) -> Result<()> {
-    // Validate tick indices
-    if tick_lower_index >= tick_upper_index {
-        return err!(ErrorCode::InvalidTickRange);
-    }
-    if tick_lower_index < MIN_TICK || tick_upper_index > MAX_TICK {
-        return err!(ErrorCode::InvalidTickRange);
-    }
+    let owner = ctx.accounts.owner.key();
+    let (amount0, amount1) = open_position(
+        &mut ctx.accounts.pool,
+        &mut ctx.accounts.position,
+        &ctx.accounts.tick_lower,
+        &ctx.accounts.tick_upper,
+        owner,
+        ctx.remaining_accounts,
+        tick_lower_index,
+        tick_upper_index,
+        liquidity_amount_desired,
+        amount0_max,
+        amount1_max,
+    )?;
+    // The accounts constraint checked `position_index` is the counter's next index
+    ctx.accounts.position_counter.take_index()?;
+    ctx.accounts.position.position_index = position_index;
+    list_in_owner_index(&mut ctx.accounts.owner_index, &mut ctx.accounts.position)?;
 
-    // Validate tick alignment with pool's tick_spacing
-    let tick_spacing = ctx.accounts.pool.tick_spacing as i32;
-    if tick_lower_index % tick_spacing != 0 || tick_upper_index % tick_spacing != 0 {
-        return err!(ErrorCode::InvalidTickSpacing);
-    }
+    transfer_to_vaults(
+        &ctx.accounts.token_program,
+        &ctx.accounts.owner,
+        &ctx.accounts.owner_token0_account,
+        &ctx.accounts.token0_vault,
+        &ctx.accounts.owner_token1_account,
+        &ctx.accounts.token1_vault,
+        amount0,
+        amount1,
+    )
+}
 
-    // Validate liquidity amount
-    if liquidity_amount_desired == 0 {
-        return err!(ErrorCode::ZeroLiquidityDelta);
-    }
-    if liquidity_amount_desired < MIN_LIQUIDITY {
-        // Or a more specific error like LiquidityAmountTooLow
-        return err!(ErrorCode::InvalidInput);
-    }
+/// Opens `position` for `owner` with `liquidity_amount_desired` between the two ticks,
+/// initializing the tick accounts if they were just created.
+///
+/// Returns the token amounts the owner must deposit for the liquidity, which the caller
+/// transfers to the pool vaults.
+///
+/// Shared by `mint_position`, `mint_position_with_amounts` and `mint_position_nft`, which
+/// differ only in how the liquidity is chosen, how the position account is derived and
+/// who may use it afterwards.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn open_position<'info>(
+    pool: &mut Account<'info, Pool>,
+    position: &mut Account<'info, PositionData>,
+    tick_lower: &AccountLoader<'info, TickData>,
+    tick_upper: &AccountLoader<'info, TickData>,
+    owner: Pubkey,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    liquidity_amount_desired: u128,
+    amount0_max: u64,
+    amount1_max: u64,
+) -> Result<(u64, u64)> {
+    pool.ensure_no_flash_loan()?;
+    pool.ensure_not_paused()?;
+    validate_mint_params(
+        pool.tick_spacing,
+        tick_lower_index,
+        tick_upper_index,
+        liquidity_amount_desired,
+    )?;
+    let (amount0, amount1) = deposit_amounts(
+        pool.sqrt_price_q64,
+        tick_lower_index,
+        tick_upper_index,
+        liquidity_amount_desired,
+        amount0_max,
+        amount1_max,
+    )?;
 
     // Initialize PositionData
-    ctx.accounts.position.initialize(
-        ctx.accounts.owner.key(),
-        ctx.accounts.pool.key(),
+    position.initialize(
+        owner,
+        pool.key(),
         tick_lower_index,
         tick_upper_index,
         liquidity_amount_desired,
     )?;
-    msg!(
-        "Position account {} initialized for owner {} in pool {}",
-        ctx.accounts.position.key(),
-        ctx.accounts.owner.key(),
-        ctx.accounts.pool.key()
+    position.entry_sqrt_price_q64 = pool.sqrt_price_q64;
+    flog!(
+        info,
+        "position_initialized",
+        position = position.key(),
+        owner = owner,
+        pool = pool.key()
     );
 
     // Initialize TickData if they were newly created by init_if_needed
     // A common check is if a field that initialize() sets is still at its Default::default() value.
     // For zero-copy accounts, we need to load_mut() to modify.
     // The check for initialization needs to be done on the loaded data.
-    let mut tick_lower_data = ctx.accounts.tick_lower.load_mut()?;
+    let mut tick_lower_data = tick_lower.load_mut()?;
     if tick_lower_data.pool == Pubkey::default() {
-        tick_lower_data.initialize(ctx.accounts.pool.key(), tick_lower_index);
-        msg!(
-            "TickLower account {} initialized for index {}",
-            ctx.accounts.tick_lower.to_account_info().key(),
-            tick_lower_index
+        tick_lower_data.initialize(pool.key(), tick_lower_index);
+        flog!(
+            debug,
+            "tick_initialized",
+            tick = tick_lower.to_account_info().key(),
+            index = tick_lower_index
         );
     }
+    drop(tick_lower_data);
     // Drop tick_lower_data to release the mutable borrow before potentially borrowing tick_upper mutably
     // if they happen to be the same account (though unlikely with different seeds).
     // Or, ensure they are distinct if that's a design constraint.
     // For this case, they are distinct due to different tick_index in seeds.
 
-    let mut tick_upper_data = ctx.accounts.tick_upper.load_mut()?;
+    let mut tick_upper_data = tick_upper.load_mut()?;
     if tick_upper_data.pool == Pubkey::default() {
-        tick_upper_data.initialize(ctx.accounts.pool.key(), tick_upper_index);
-        msg!(
-            "TickUpper account {} initialized for index {}",
-            ctx.accounts.tick_upper.to_account_info().key(),
-            tick_upper_index
+        tick_upper_data.initialize(pool.key(), tick_upper_index);
+        flog!(
+            debug,
+            "tick_initialized",
+            tick = tick_upper.to_account_info().key(),
+            index = tick_upper_index
         );
     }
-    // tick_lower_data and tick_upper_data go out of scope here, their changes will be written back on drop.
+    drop(tick_upper_data);
 
-    // Call pool's modify_liquidity logic
+    // Call pool's modify_liquidity logic, with the tick bitmap words stored in their own
+    // accounts (passed through `remaining_accounts`) loaded into the pool.
     // The liquidity_delta is positive as we are adding liquidity.
-    ctx.accounts.pool.modify_liquidity(
+    let tick_bitmaps = tick_bitmap::load_tick_bitmap_accounts(remaining_accounts)?;
+    let tick_bitmap_loaders: Vec<&AccountLoader<'info, TickBitmap>> = tick_bitmaps.iter().collect();
+    let pool_key = pool.key();
+    pool.load_bitmap_words(&pool_key, &tick_bitmap_loaders)?;
+    pool.modify_liquidity(
         tick_lower_index,
         tick_upper_index,
         liquidity_amount_desired as i128, // Cast u128 to i128
-        &ctx.accounts.tick_lower,         // Pass the AccountLoader
-        &ctx.accounts.tick_upper,         // Pass the AccountLoader
+        tick_lower,                       // Pass the AccountLoader
+        tick_upper,                       // Pass the AccountLoader
     )?;
-    msg!(
-        "Pool liquidity updated. New pool liquidity: {}",
-        ctx.accounts.pool.liquidity
+    pool.store_bitmap_words(&tick_bitmap_loaders)?;
+    flog!(
+        info,
+        "pool_liquidity_updated",
+        pool = pool.key(),
+        liquidity = pool.liquidity
+    );
+
+    // Fee accounting starts from the fee growth inside the range at creation
+    let tick_lower_data = tick_lower.load()?;
+    let tick_upper_data = tick_upper.load()?;
+    position.snapshot_fee_growth_inside(pool, &tick_lower_data, &tick_upper_data);
+
+    emit!(MintPositionEvent {
+        pool: pool_key,
+        position: position.key(),
+        owner,
+        tick_lower: tick_lower_index,
+        tick_upper: tick_upper_index,
+        liquidity: liquidity_amount_desired,
+        amount0,
+        amount1,
+    });
+    Ok((amount0, amount1))
+}
+
+/// Returns the token amounts needed to add `liquidity` between the two ticks at the
+/// current price, failing with `SlippageExceeded` if either is above the owner's maximum.
+///
+/// A range above the current price takes only token0, a range below it only token1, and
+/// a range around it both. Deposits round up so the vaults always hold at least what the
+/// position can later withdraw.
+pub(crate) fn deposit_amounts(
+    sqrt_price_current_q64: u128,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    liquidity: u128,
+    amount0_max: u64,
+    amount1_max: u64,
+) -> Result<(u64, u64)> {
+    let (amount0_u128, amount1_u128) = math::get_amounts_for_liquidity(
+        sqrt_price_current_q64,
+        math::tick_to_sqrt_price_q64(tick_lower_index)?,
+        math::tick_to_sqrt_price_q64(tick_upper_index)?,
+        liquidity,
+        true,
+    )?;
+    let amount0 = u64::try_from(amount0_u128)
+        .map_err(|_| error!(ErrorCode::MathOverflow).with_account_name("amount0_u128"))?;
+    let amount1 = u64::try_from(amount1_u128)
+        .map_err(|_| error!(ErrorCode::MathOverflow).with_account_name("amount1_u128"))?;
+    if amount0 > amount0_max || amount1 > amount1_max {
+        return err!(ErrorCode::SlippageExceeded);
+    }
+    Ok((amount0, amount1))
+}
+
+/// Lists `position` in `owner_index`, if the owner passed their position index.
+pub(crate) fn list_in_owner_index<'info>(
+    owner_index: &mut Option<Account<'info, OwnerPositionIndex>>,
+    position: &mut Account<'info, PositionData>,
+) -> Result<()> {
+    let Some(owner_index) = owner_index else {
+        return Ok(());
+    };
+    owner_index.push(position.key(), position)?;
+    flog!(
+        info,
+        "position_listed",
+        position = position.key(),
+        owner_index = owner_index.key(),
+        count = owner_index.count
     );
+    Ok(())
+}
+
+/// Transfers `amount_0` and `amount_1` from the owner's token accounts to the pool vaults,
+/// signed by the owner.
+///
+/// Zero amounts are skipped.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn transfer_to_vaults<'info>(
+    token_program: &Program<'info, Token>,
+    owner: &Signer<'info>,
+    token0_source: &Account<'info, TokenAccount>,
+    token0_vault: &Account<'info, TokenAccount>,
+    token1_source: &Account<'info, TokenAccount>,
+    token1_vault: &Account<'info, TokenAccount>,
+    amount_0: u64,
+    amount_1: u64,
+) -> Result<()> {
+    if amount_0 > 0 {
+        token::transfer(
+            CpiContext::new(
+                token_program.to_account_info(),
+                Transfer {
+                    from: token0_source.to_account_info(),
+                    to: token0_vault.to_account_info(),
+                    authority: owner.to_account_info(),
+                },
+            ),
+            amount_0,
+        )?;
+    }
+
+    if amount_1 > 0 {
+        token::transfer(
+            CpiContext::new(
+                token_program.to_account_info(),
+                Transfer {
+                    from: token1_source.to_account_info(),
+                    to: token1_vault.to_account_info(),
+                    authority: owner.to_account_info(),
+                },
+            ),
+            amount_1,
+        )?;
+    }
 
-    // MVP Simplification: Skip actual token transfers from user to vaults.
+    Ok(())
+}
+
+/// Validates the tick range and liquidity of a new position.
+pub(crate) fn validate_mint_params(
+    tick_spacing: u16,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    liquidity_amount_desired: u128,
+) -> Result<()> {
+    validate_tick_range(tick_spacing, tick_lower_index, tick_upper_index)?;
 
+    // Validate liquidity amount
+    if liquidity_amount_desired == 0 {
+        flog!(error, "zero_liquidity_desired");
+        return err!(ErrorCode::ZeroLiquidityDelta);
+    }
+    if liquidity_amount_desired < MIN_LIQUIDITY {
+        // Or a more specific error like LiquidityAmountTooLow
+        return err!(ErrorCode::InvalidInput);
+    }
+
+    Ok(())
+}
+
+/// Validates a position's tick range against a pool with `tick_spacing`, logging the
+/// offending ticks when it is rejected.
+///
+/// # Errors
+///
+/// * `InvalidTickRange` - The lower tick is not below the upper one, or either lies
+///   outside `MIN_TICK..=MAX_TICK`.
+/// * `InvalidTickSpacing` - Either tick is not a multiple of `tick_spacing`.
+pub(crate) fn validate_tick_range(
+    tick_spacing: u16,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+) -> Result<()> {
+    if tick_lower_index >= tick_upper_index
+        || tick_lower_index < MIN_TICK
+        || tick_upper_index > MAX_TICK
+    {
+        flog!(
+            error,
+            "invalid_tick_range",
+            tick_lower = tick_lower_index,
+            tick_upper = tick_upper_index
+        );
+        return err!(ErrorCode::InvalidTickRange);
+    }
+
+    let spacing = tick_spacing as i32;
+    if tick_lower_index % spacing != 0 || tick_upper_index % spacing != 0 {
+        flog!(
+            error,
+            "unaligned_tick_range",
+            tick_lower = tick_lower_index,
+            tick_upper = tick_upper_index,
+            tick_spacing = tick_spacing
+        );
+        return err!(ErrorCode::InvalidTickSpacing);
+    }
     Ok(())
 }
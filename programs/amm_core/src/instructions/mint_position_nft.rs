@@ -0,0 +1,84 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, spl_token::instruction::AuthorityType, MintTo, SetAuthority};
+
+use crate::flog;
+use crate::instructions::mint_position::{list_in_owner_index, open_position, transfer_to_vaults};
+use crate::MintPositionNft;
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, MintPositionNft<'info>>,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    liquidity_amount_desired: u128,
+    amount0_max: u64,
+    amount1_max: u64,
+) -> Result<()> {
+    let position_bump = ctx.bumps.position;
+    let accounts = ctx.accounts;
+    let (amount0, amount1) = open_position(
+        &mut accounts.pool,
+        &mut accounts.position,
+        &accounts.tick_lower,
+        &accounts.tick_upper,
+        accounts.owner.key(),
+        ctx.remaining_accounts,
+        tick_lower_index,
+        tick_upper_index,
+        liquidity_amount_desired,
+        amount0_max,
+        amount1_max,
+    )?;
+    transfer_to_vaults(
+        &accounts.token_program,
+        &accounts.owner,
+        &accounts.owner_token0_account,
+        &accounts.token0_vault,
+        &accounts.owner_token1_account,
+        &accounts.token1_vault,
+        amount0,
+        amount1,
+    )?;
+    let position_mint = accounts.position_mint.key();
+    accounts.position.position_mint = position_mint;
+    list_in_owner_index(&mut accounts.owner_index, &mut accounts.position)?;
+
+    // Mint the single position token to the owner, then drop the mint authority so the
+    // supply can never exceed one. The position PDA is the mint authority.
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        b"position".as_ref(),
+        position_mint.as_ref(),
+        &[position_bump],
+    ]];
+    token::mint_to(
+        CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            MintTo {
+                mint: accounts.position_mint.to_account_info(),
+                to: accounts.position_token_account.to_account_info(),
+                authority: accounts.position.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        1,
+    )?;
+    token::set_authority(
+        CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            SetAuthority {
+                current_authority: accounts.position.to_account_info(),
+                account_or_mint: accounts.position_mint.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        AuthorityType::MintTokens,
+        None,
+    )?;
+    flog!(
+        info,
+        "position_nft_minted",
+        position = accounts.position.key(),
+        mint = position_mint,
+        owner = accounts.owner.key()
+    );
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::ErrorCode;
+use crate::instructions::mint_position::{list_in_owner_index, open_position, transfer_to_vaults};
+use crate::math;
+use crate::MintPositionWithAmounts;
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, MintPositionWithAmounts<'info>>,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    amount0_desired: u64,
+    amount1_desired: u64,
+    amount0_min: u64,
+    amount1_min: u64,
+) -> Result<()> {
+    // The most liquidity the desired amounts support at the current price
+    let liquidity = math::get_liquidity_for_amounts(
+        ctx.accounts.pool.sqrt_price_q64,
+        math::tick_to_sqrt_price_q64(tick_lower_index)?,
+        math::tick_to_sqrt_price_q64(tick_upper_index)?,
+        amount0_desired as u128,
+        amount1_desired as u128,
+    )?;
+
+    // The deposit for that liquidity never exceeds the desired amounts, so they serve as
+    // the maximums. `MintPositionEvent` reports the amounts actually consumed.
+    let owner = ctx.accounts.owner.key();
+    let (amount0, amount1) = open_position(
+        &mut ctx.accounts.pool,
+        &mut ctx.accounts.position,
+        &ctx.accounts.tick_lower,
+        &ctx.accounts.tick_upper,
+        owner,
+        ctx.remaining_accounts,
+        tick_lower_index,
+        tick_upper_index,
+        liquidity,
+        amount0_desired,
+        amount1_desired,
+    )?;
+    if amount0 < amount0_min || amount1 < amount1_min {
+        return err!(ErrorCode::SlippageExceeded);
+    }
+    // The position's address was derived from the counter's next index
+    ctx.accounts.position.position_index = ctx.accounts.position_counter.take_index()?;
+    list_in_owner_index(&mut ctx.accounts.owner_index, &mut ctx.accounts.position)?;
+
+    transfer_to_vaults(
+        &ctx.accounts.token_program,
+        &ctx.accounts.owner,
+        &ctx.accounts.owner_token0_account,
+        &ctx.accounts.token0_vault,
+        &ctx.accounts.owner_token1_account,
+        &ctx.accounts.token1_vault,
+        amount0,
+        amount1,
+    )
+}
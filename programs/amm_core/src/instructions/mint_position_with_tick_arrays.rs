@@ -0,0 +1,120 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::instructions::mint_position::{
+    deposit_amounts, list_in_owner_index, transfer_to_vaults, validate_mint_params,
+};
+use crate::position::MintPositionEvent;
+use crate::tick_bitmap::{self, TickBitmap};
+use crate::MintPositionWithTickArrays;
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, MintPositionWithTickArrays<'info>>,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    liquidity_amount_desired: u128,
+    position_index: u64,
+    amount0_max: u64,
+    amount1_max: u64,
+) -> Result<()> {
+    let tick_bitmaps = tick_bitmap::load_tick_bitmap_accounts(ctx.remaining_accounts)?;
+    let tick_bitmap_loaders: Vec<&AccountLoader<'info, TickBitmap>> = tick_bitmaps.iter().collect();
+    let accounts = ctx.accounts;
+    accounts.pool.ensure_no_flash_loan()?;
+    accounts.pool.ensure_not_paused()?;
+    validate_mint_params(
+        accounts.pool.tick_spacing,
+        tick_lower_index,
+        tick_upper_index,
+        liquidity_amount_desired,
+    )?;
+    let (amount0, amount1) = deposit_amounts(
+        accounts.pool.sqrt_price_q64,
+        tick_lower_index,
+        tick_upper_index,
+        liquidity_amount_desired,
+        amount0_max,
+        amount1_max,
+    )?;
+
+    // Initialize PositionData
+    accounts.position.initialize(
+        accounts.owner.key(),
+        accounts.pool.key(),
+        tick_lower_index,
+        tick_upper_index,
+        liquidity_amount_desired,
+    )?;
+    accounts.position.entry_sqrt_price_q64 = accounts.pool.sqrt_price_q64;
+    // The accounts constraint checked `position_index` is the counter's next index
+    accounts.position_counter.take_index()?;
+    accounts.position.position_index = position_index;
+    list_in_owner_index(&mut accounts.owner_index, &mut accounts.position)?;
+    flog!(
+        info,
+        "position_initialized",
+        position = accounts.position.key(),
+        owner = accounts.owner.key(),
+        pool = accounts.pool.key()
+    );
+
+    // Add the liquidity to the tick array entries, the bitmap and (if in range) the pool.
+    // The arrays' seeds already tie them to this pool and to the ticks' windows.
+    let pool_key = accounts.pool.key();
+    accounts
+        .pool
+        .load_bitmap_words(&pool_key, &tick_bitmap_loaders)?;
+    accounts.pool.modify_liquidity_in_tick_arrays(
+        tick_lower_index,
+        tick_upper_index,
+        liquidity_amount_desired as i128,
+        &accounts.tick_array_lower,
+        &accounts.tick_array_upper,
+    )?;
+    accounts.pool.store_bitmap_words(&tick_bitmap_loaders)?;
+    flog!(
+        info,
+        "pool_liquidity_updated",
+        pool = accounts.pool.key(),
+        liquidity = accounts.pool.liquidity
+    );
+
+    // Fee accounting starts from the fee growth inside the range at creation
+    let tick_spacing = accounts.pool.bitmap_tick_spacing;
+    let tick_lower_data = *accounts
+        .tick_array_lower
+        .load()?
+        .get_tick(tick_lower_index, tick_spacing)?;
+    let tick_upper_data = *accounts
+        .tick_array_upper
+        .load()?
+        .get_tick(tick_upper_index, tick_spacing)?;
+    accounts.position.snapshot_fee_growth_inside(
+        &accounts.pool,
+        &tick_lower_data,
+        &tick_upper_data,
+    );
+
+    transfer_to_vaults(
+        &accounts.token_program,
+        &accounts.owner,
+        &accounts.owner_token0_account,
+        &accounts.token0_vault,
+        &accounts.owner_token1_account,
+        &accounts.token1_vault,
+        amount0,
+        amount1,
+    )?;
+
+    emit!(MintPositionEvent {
+        pool: pool_key,
+        position: accounts.position.key(),
+        owner: accounts.owner.key(),
+        tick_lower: tick_lower_index,
+        tick_upper: tick_upper_index,
+        liquidity: liquidity_amount_desired,
+        amount0,
+        amount1,
+    });
+    Ok(())
+}
@@ -1,4 +1,44 @@
+pub mod assert_quote;
+pub mod cancel_order;
+pub mod close_position;
+pub mod collect_fees;
+pub mod collect_fees_with_tick_arrays;
+pub mod collect_protocol_fees;
+pub mod create_token_pair;
+pub mod decrease_liquidity;
+pub mod decrease_liquidity_with_tick_arrays;
+pub mod enable_fee_tier;
+pub mod execute_match;
+pub mod flash_loan;
+pub mod initialize_bitmap_word;
+pub mod initialize_factory_config;
+pub mod initialize_liquidity_histogram;
+pub mod initialize_oracle_pool;
+pub mod initialize_order_book;
 pub mod initialize_pool;
+pub mod initialize_tick_array;
+pub mod match_orders_batch;
+pub mod migrate_position;
+pub mod migrate_tick_spacing;
 pub mod mint_position;
+pub mod mint_position_nft;
+pub mod mint_position_with_amounts;
+pub mod mint_position_with_tick_arrays;
+pub mod pause_pool;
+pub mod place_limit_order;
+pub mod quote_swap_exact_input;
+pub mod refresh_liquidity_histogram;
+pub mod refresh_protocol_share;
+pub mod register_owner_index;
+pub mod resize_pool_account;
+pub mod resize_tick_array;
+pub mod set_fee_rate;
+pub mod set_oracle;
+pub mod set_oracle_config;
+pub mod set_protocol_fee;
+pub mod set_protocol_fee_curve;
 pub mod swap_exact_input;
+pub mod swap_exact_output;
+pub mod swap_multi_hop;
+pub mod unpause_pool;
 pub mod update_position;
@@ -0,0 +1,71 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+
+use crate::flog;
+use crate::state::order_book::{Order, OrderSide, PlaceOrderParams};
+use crate::PlaceLimitOrder;
+
+pub fn handler(
+    ctx: Context<PlaceLimitOrder>,
+    side: OrderSide,
+    price: u64,
+    quantity: u64,
+    expires_at: i64,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let order_book = &mut ctx.accounts.order_book;
+
+    // 1. Validate the price and compute what the order must escrow
+    order_book.validate_price(price)?;
+    let escrow_amount = Order::escrow_amount(side, price, quantity)?;
+
+    // 2. Write the order with the next sequential id
+    let id = order_book.order_count;
+    ctx.accounts.order.initialize(PlaceOrderParams {
+        bump: ctx.bumps.order,
+        order_book: order_book.key(),
+        owner: ctx.accounts.owner.key(),
+        id,
+        side,
+        price,
+        quantity,
+        created_at: clock.unix_timestamp,
+        expires_at,
+    })?;
+    order_book.record_order(side, quantity)?;
+
+    // 3. Move the escrowed tokens into the order book's vault
+    let (from, to) = match side {
+        OrderSide::Bid => (
+            ctx.accounts.owner_token1_account.to_account_info(),
+            ctx.accounts.quote_escrow.to_account_info(),
+        ),
+        OrderSide::Ask => (
+            ctx.accounts.owner_token0_account.to_account_info(),
+            ctx.accounts.base_escrow.to_account_info(),
+        ),
+    };
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from,
+                to,
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        ),
+        escrow_amount,
+    )?;
+
+    flog!(
+        info,
+        "limit_order_placed",
+        order_book = order_book.key(),
+        order_id = id,
+        bid = side == OrderSide::Bid,
+        price = price,
+        quantity = quantity,
+        escrow_amount = escrow_amount
+    );
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::ErrorCode;
+use crate::state::pool::{Pool, QuoteResult};
+use crate::tick::TickData;
+use crate::tick_array::{self, TickArray};
+use crate::tick_bitmap::TickBitmap;
+use crate::QuoteSwapExactInput;
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, QuoteSwapExactInput<'info>>,
+    zero_for_one: bool,
+    amount_in: u64,
+    sqrt_price_limit_q64: u128,
+) -> Result<QuoteResult> {
+    quote_exact_input(
+        &ctx.accounts.pool,
+        ctx.remaining_accounts,
+        zero_for_one,
+        amount_in,
+        sqrt_price_limit_q64,
+    )
+}
+
+/// Runs `swap_exact_input_handler`'s computation against a copy of `pool`, reading the
+/// tick, tick array and tick bitmap accounts in `remaining_accounts` without writing them.
+pub(crate) fn quote_exact_input<'info>(
+    pool: &Account<'info, Pool>,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    zero_for_one: bool,
+    amount_in: u64,
+    sqrt_price_limit_q64: u128,
+) -> Result<QuoteResult> {
+    let clock = Clock::get()?;
+    // A quote for a swap that would be refused is refused too.
+    pool.ensure_no_flash_loan()?;
+    pool.ensure_not_paused()?;
+
+    // 1. Load the tick, tick array and tick bitmap accounts passed through `remaining_accounts`
+    let swap_tick_accounts = tick_array::load_swap_tick_accounts(remaining_accounts)?;
+    let tick_loaders_vec: Vec<&AccountLoader<'info, TickData>> =
+        swap_tick_accounts.ticks.iter().collect();
+    let tick_array_loaders_vec: Vec<&AccountLoader<'info, TickArray>> =
+        swap_tick_accounts.tick_arrays.iter().collect();
+    let tick_bitmap_loaders_vec: Vec<&AccountLoader<'info, TickBitmap>> =
+        swap_tick_accounts.tick_bitmaps.iter().collect();
+
+    // 2. Run the swap against a copy of the pool, reading the tick accounts without writing them
+    let mut simulated_pool: Pool = (**pool).clone();
+    let swap_result = simulated_pool.simulate_swap_with_tick_arrays(
+        zero_for_one,
+        amount_in as i128,
+        sqrt_price_limit_q64,
+        &pool.key(),
+        &tick_loaders_vec,
+        &tick_array_loaders_vec,
+        &tick_bitmap_loaders_vec,
+        clock.unix_timestamp,
+    )?;
+
+    Ok(QuoteResult {
+        amount_out: u64::try_from(swap_result.amount_out)
+            .map_err(|_| error!(ErrorCode::MathOverflow))?,
+        amount_in_consumed: u64::try_from(swap_result.amount_in)
+            .map_err(|_| error!(ErrorCode::MathOverflow))?,
+        sqrt_price_after: simulated_pool.sqrt_price_q64,
+        ticks_crossed: swap_result.ticks_crossed,
+        fee_paid: u64::try_from(swap_result.total_fee())
+            .map_err(|_| error!(ErrorCode::MathOverflow))?,
+    })
+}
@@ -0,0 +1,137 @@
+use anchor_lang::prelude::*;
+use std::collections::BTreeMap;
+
+use crate::flog;
+use crate::state::pool::Pool;
+use crate::tick::{self, TickData};
+use crate::tick_array::{self, TickArray};
+use crate::tick_bitmap::{self, TickBitmap};
+use crate::RefreshLiquidityHistogram;
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RefreshLiquidityHistogram<'info>>,
+) -> Result<()> {
+    let pool = &ctx.accounts.pool;
+    let clock = Clock::get()?;
+
+    // 1. Load the tick, tick array and tick bitmap accounts passed through `remaining_accounts`
+    let tick_accounts = tick_array::load_swap_tick_accounts(ctx.remaining_accounts)?;
+    let tick_loaders: Vec<&AccountLoader<'info, TickData>> = tick_accounts.ticks.iter().collect();
+    let tick_array_loaders: Vec<&AccountLoader<'info, TickArray>> =
+        tick_accounts.tick_arrays.iter().collect();
+    let tick_bitmap_loaders: Vec<&AccountLoader<'info, TickBitmap>> =
+        tick_accounts.tick_bitmaps.iter().collect();
+
+    // 2. Read the initialized ticks of the new range from a copy of the pool holding the
+    //    supplied bitmap words, then rebuild the buckets from them
+    let mut histogram = ctx.accounts.liquidity_histogram.load_mut()?;
+    let lower_tick = histogram.lower_tick_around(pool.current_tick);
+    let upper_tick = lower_tick + histogram.span_ticks();
+    let mut bitmap_pool: Pool = (**pool).clone();
+    bitmap_pool.load_bitmap_words(&pool.key(), &tick_bitmap_loaders)?;
+    let ticks = histogram_ticks(
+        &bitmap_pool,
+        &pool.key(),
+        lower_tick,
+        upper_tick,
+        &tick_loaders,
+        &tick_array_loaders,
+    )?;
+    histogram.refresh(
+        pool.current_tick,
+        pool.liquidity,
+        &ticks,
+        clock.unix_timestamp,
+    )?;
+
+    flog!(
+        debug,
+        "liquidity_histogram_refreshed",
+        pool = pool.key(),
+        lower_tick = lower_tick,
+        initialized_ticks = ticks.len(),
+        timestamp = clock.unix_timestamp
+    );
+    Ok(())
+}
+
+/// Returns the index and `liquidity_net` of every initialized tick of `pool` strictly
+/// between `lower_tick` and `upper_tick`, in ascending order, as
+/// `LiquidityHistogram::refresh` expects them.
+///
+/// A tick is read from the supplied tick array covering it if its entry there is
+/// initialized, and from its `TickData` account otherwise. `TickData` accounts must be
+/// passed in ascending order; accounts for ticks outside the range are ignored.
+///
+/// # Errors
+///
+/// * `MissingTickBitmapWord` - A bitmap word covering the range is stored in an account
+///   that `pool` has not loaded.
+/// * `MissingTickAccount` - An initialized tick in the range was not supplied.
+/// * `InvalidTickAccount` - A tick or tick array account belongs to another pool or is
+///   not its PDA.
+/// * `InvalidTickAccountOrder` - The `TickData` accounts are not strictly ascending.
+pub(crate) fn histogram_ticks(
+    pool: &Pool,
+    pool_key: &Pubkey,
+    lower_tick: i32,
+    upper_tick: i32,
+    tick_loaders: &[&AccountLoader<TickData>],
+    tick_array_loaders: &[&AccountLoader<TickArray>],
+) -> Result<Vec<(i32, i128)>> {
+    let first_word = tick_bitmap::word_index_of_tick(lower_tick, pool.bitmap_tick_spacing);
+    let last_word = tick_bitmap::word_index_of_tick(upper_tick - 1, pool.bitmap_tick_spacing);
+    if let Some(&word_index) = pool
+        .external_bitmap_words
+        .iter()
+        .find(|&&word_index| (first_word..=last_word).contains(&word_index))
+    {
+        return Err(tick_bitmap::missing_tick_bitmap_word(word_index));
+    }
+
+    let tick_indices = tick::validate_swap_tick_accounts(tick_loaders, pool_key, false)?;
+    let tick_array_starts = tick_array::validate_swap_tick_arrays(tick_array_loaders, pool_key)?;
+    let bitmap: BTreeMap<i16, u64> =
+        borsh::BorshDeserialize::try_from_slice(&pool.tick_bitmap_data)
+            .expect("Failed to deserialize tick_bitmap_data");
+    let mut next_supplied = 0;
+    let mut ticks = Vec::new();
+    for tick_index in tick_bitmap::initialized_ticks_in_range(
+        &bitmap,
+        lower_tick + 1,
+        upper_tick - 1,
+        pool.bitmap_tick_spacing,
+    ) {
+        let start_tick_index = TickArray::start_tick_index(tick_index, pool.bitmap_tick_spacing);
+        if let Some(array) = tick_array_starts
+            .iter()
+            .position(|&start| start == start_tick_index)
+        {
+            let tick_array = tick_array_loaders[array].load()?;
+            let tick_data = tick_array.get_tick(tick_index, pool.bitmap_tick_spacing)?;
+            if tick_data.initialized != 0 {
+                ticks.push((tick_index, tick_data.liquidity_net));
+                continue;
+            }
+        }
+
+        // Skip supplied ticks below the range or not initialized in the bitmap.
+        while tick_indices
+            .get(next_supplied)
+            .is_some_and(|&supplied| supplied < tick_index)
+        {
+            next_supplied += 1;
+        }
+        if tick_indices.get(next_supplied) != Some(&tick_index) {
+            return Err(tick::missing_tick_account(tick_index));
+        }
+        let tick_data = tick_loaders[next_supplied].load()?;
+        next_supplied += 1;
+        // An empty tick account means the tick's liquidity lives in a tick array.
+        if tick_data.initialized == 0 {
+            return Err(tick::missing_tick_account(tick_index));
+        }
+        ticks.push((tick_index, tick_data.liquidity_net));
+    }
+    Ok(ticks)
+}
@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::RefreshProtocolShare;
+
+pub fn handler(ctx: Context<RefreshProtocolShare>) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    let old_protocol_fee = pool.protocol_fee;
+    let protocol_fee =
+        pool.refresh_protocol_share(&ctx.accounts.factory_config.protocol_fee_curve)?;
+
+    flog!(
+        info,
+        "protocol_share_refreshed",
+        pool = pool.key(),
+        liquidity = pool.liquidity,
+        old_protocol_fee = old_protocol_fee,
+        protocol_fee = protocol_fee
+    );
+    Ok(())
+}
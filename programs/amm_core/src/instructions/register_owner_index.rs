@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::RegisterOwnerIndex;
+
+pub fn handler(ctx: Context<RegisterOwnerIndex>) -> Result<()> {
+    let owner_index = &mut ctx.accounts.owner_index;
+    owner_index.owner = ctx.accounts.owner.key();
+    owner_index.head = Pubkey::default();
+    owner_index.count = 0;
+
+    flog!(
+        info,
+        "owner_index_registered",
+        owner_index = owner_index.key(),
+        owner = owner_index.owner
+    );
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use anchor_lang::solana_program::system_instruction::MAX_PERMITTED_DATA_LENGTH;
+use anchor_lang::system_program::{self, Transfer};
+
+use crate::errors::ErrorCode;
+use crate::flog;
+use crate::state::pool::Pool;
+use crate::ResizePoolAccount;
+
+pub fn handler(ctx: Context<ResizePoolAccount>, new_len: u32) -> Result<()> {
+    let pool_info = ctx.accounts.pool.to_account_info();
+    let old_len = pool_info.data_len();
+    resize_program_account(
+        &pool_info,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        new_len as usize,
+        Pool::LEN,
+    )?;
+
+    flog!(
+        info,
+        "pool_account_resized",
+        pool = pool_info.key(),
+        old_len = old_len,
+        new_len = new_len
+    );
+    Ok(())
+}
+
+/// Checks that an account of `current_len` bytes can be resized to `new_len` in one
+/// instruction.
+///
+/// # Errors
+///
+/// * `AccountShrinkTooSmall` - `new_len` is below `min_len`, the size its data
+///   serializes to.
+/// * `AccountGrowthTooLarge` - `new_len` is more than `MAX_PERMITTED_DATA_INCREASE` bytes
+///   above `current_len`, or above `MAX_PERMITTED_DATA_LENGTH`.
+pub(crate) fn validate_resize(current_len: usize, new_len: usize, min_len: usize) -> Result<()> {
+    if new_len < min_len {
+        flog!(
+            error,
+            "account_shrink_too_small",
+            new_len = new_len,
+            min_len = min_len
+        );
+        return err!(ErrorCode::AccountShrinkTooSmall);
+    }
+    if new_len.saturating_sub(current_len) > MAX_PERMITTED_DATA_INCREASE
+        || new_len as u64 > MAX_PERMITTED_DATA_LENGTH
+    {
+        flog!(
+            error,
+            "account_growth_too_large",
+            current_len = current_len,
+            new_len = new_len
+        );
+        return err!(ErrorCode::AccountGrowthTooLarge);
+    }
+    Ok(())
+}
+
+/// Resizes `account`, owned by this program, to `new_len` bytes and keeps it rent exempt.
+///
+/// Growing the account tops up its rent from `payer` and zero-fills the new tail, which
+/// the account's deserializer ignores. Shrinking it returns the rent it no longer needs
+/// to `payer`.
+pub(crate) fn resize_program_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    new_len: usize,
+    min_len: usize,
+) -> Result<()> {
+    validate_resize(account.data_len(), new_len, min_len)?;
+
+    let rent_exempt_balance = Rent::get()?.minimum_balance(new_len);
+    let balance = account.lamports();
+    if rent_exempt_balance > balance {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            rent_exempt_balance - balance,
+        )?;
+    } else if balance > rent_exempt_balance {
+        // The program owns the account, so it can debit it directly
+        let refund = balance - rent_exempt_balance;
+        **account.try_borrow_mut_lamports()? -= refund;
+        **payer.try_borrow_mut_lamports()? += refund;
+    }
+    account.realloc(new_len, true)?;
+    Ok(())
+}
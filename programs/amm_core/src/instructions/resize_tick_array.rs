@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::ErrorCode;
+use crate::flog;
+use crate::instructions::resize_pool_account::resize_program_account;
+use crate::tick_array::TickArray;
+use crate::ResizeTickArray;
+
+pub fn handler(ctx: Context<ResizeTickArray>, new_len: u32) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    require_keys_eq!(
+        ctx.accounts.tick_array.load()?.pool,
+        pool_key,
+        ErrorCode::InvalidTickAccount
+    );
+
+    let tick_array_info = ctx.accounts.tick_array.to_account_info();
+    let old_len = tick_array_info.data_len();
+    resize_program_account(
+        &tick_array_info,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        new_len as usize,
+        8 + TickArray::LEN,
+    )?;
+
+    flog!(
+        info,
+        "tick_array_resized",
+        pool = pool_key,
+        tick_array = tick_array_info.key(),
+        old_len = old_len,
+        new_len = new_len
+    );
+    Ok(())
+}
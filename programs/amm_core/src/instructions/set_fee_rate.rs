@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::state::pool::FeeRateUpdated;
+use crate::SetFeeRate;
+
+pub fn handler(ctx: Context<SetFeeRate>, new_fee_rate: u16) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    let old_fee_rate = pool.fee_rate;
+    pool.set_fee_rate(new_fee_rate)?;
+
+    emit!(FeeRateUpdated {
+        pool: pool.key(),
+        old_fee_rate,
+        new_fee_rate,
+    });
+    flog!(
+        info,
+        "fee_rate_set",
+        pool = pool.key(),
+        old_fee_rate = old_fee_rate,
+        new_fee_rate = new_fee_rate
+    );
+    Ok(())
+}
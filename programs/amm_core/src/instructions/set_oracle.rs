@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::state::pool::OraclePriceUpdated;
+use crate::SetOracle;
+
+pub fn handler(ctx: Context<SetOracle>, sqrt_price_q64: u128, published_slot: u64) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    pool.set_oracle_price(sqrt_price_q64, published_slot, Clock::get()?.slot)?;
+
+    emit!(OraclePriceUpdated {
+        pool: pool.key(),
+        sqrt_price_q64,
+        published_slot,
+    });
+    flog!(
+        info,
+        "oracle_price_set",
+        pool = pool.key(),
+        sqrt_price_q64 = sqrt_price_q64,
+        published_slot = published_slot
+    );
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::SetOracleConfig;
+
+pub fn handler(
+    ctx: Context<SetOracleConfig>,
+    oracle_authority: Pubkey,
+    max_oracle_age_slots: u64,
+) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    pool.set_oracle_config(oracle_authority, max_oracle_age_slots);
+
+    flog!(
+        info,
+        "oracle_config_set",
+        pool = pool.key(),
+        oracle_authority = oracle_authority,
+        max_oracle_age_slots = max_oracle_age_slots
+    );
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::SetProtocolFee;
+
+pub fn handler(ctx: Context<SetProtocolFee>, protocol_fee: Option<u16>) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    let protocol_fee = match protocol_fee {
+        Some(protocol_fee) => {
+            pool.set_manual_protocol_fee(protocol_fee)?;
+            protocol_fee
+        }
+        None => pool.follow_protocol_fee_curve(&ctx.accounts.factory_config.protocol_fee_curve)?,
+    };
+
+    flog!(
+        info,
+        "protocol_fee_set",
+        pool = pool.key(),
+        protocol_fee = protocol_fee,
+        manual = pool.protocol_fee_manual
+    );
+    Ok(())
+}
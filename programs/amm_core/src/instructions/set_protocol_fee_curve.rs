@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::state::factory_config::ProtocolFeeCurve;
+use crate::SetProtocolFeeCurve;
+
+pub fn handler(
+    ctx: Context<SetProtocolFeeCurve>,
+    protocol_fee_curve: ProtocolFeeCurve,
+) -> Result<()> {
+    let factory_config = &mut ctx.accounts.factory_config;
+    factory_config.set_protocol_fee_curve(protocol_fee_curve)?;
+
+    flog!(
+        info,
+        "protocol_fee_curve_set",
+        factory_config = factory_config.key()
+    );
+    Ok(())
+}
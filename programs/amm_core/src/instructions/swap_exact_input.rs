@@ -2,17 +2,24 @@ use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Transfer};
 
 use crate::errors::ErrorCode;
-use crate::tick::TickData; // Now a zero-copy account
+use crate::flog;
+use crate::math;
+use crate::state::pool::SwapEvent;
+use crate::tick::TickData;
+use crate::tick_array::{self, TickArray};
+use crate::tick_bitmap::TickBitmap;
 use crate::SwapExactInput;
 
 pub fn handler<'info>(
-    ctx: Context<'_, '_, '_, 'info, SwapExactInput<'info>>,
+    ctx: Context<'_, '_, 'info, 'info, SwapExactInput<'info>>,
     amount_in: u64,
     amount_out_minimum: u64,
     sqrt_price_limit_q64: u128,
 ) -> Result<()> {
     let pool = &mut ctx.accounts.pool;
     let clock = Clock::get()?;
+    pool.ensure_no_flash_loan()?;
+    pool.ensure_not_paused()?;
 
     // 1. Determine swap direction (zero_for_one) and validate token mints
     let zero_for_one = if ctx.accounts.user_token_in_account.mint == pool.token0_mint {
@@ -58,43 +65,35 @@ pub fn handler<'info>(
         amount_in,
     )?;
 
-    // 3. Collect provided tick loaders
-    // The Pool::swap method will need to be adapted to accept these.
-    let mut tick_loaders_vec = Vec::new();
-    if let Some(ta) = &ctx.accounts.tick_account_0 {
-        tick_loaders_vec.push(ta);
-    }
-    if let Some(ta) = &ctx.accounts.tick_account_1 {
-        tick_loaders_vec.push(ta);
-    }
-    if let Some(ta) = &ctx.accounts.tick_account_2 {
-        tick_loaders_vec.push(ta);
-    }
+    // 3. Load the tick, tick array and tick bitmap accounts passed through `remaining_accounts`
+    let swap_tick_accounts = tick_array::load_swap_tick_accounts(ctx.remaining_accounts)?;
+    let tick_loaders_vec: Vec<&AccountLoader<'info, TickData>> =
+        swap_tick_accounts.ticks.iter().collect();
     // Convert Vec<&AccountLoader> to &[&AccountLoader] for the call
     let tick_loaders_slice: &[&AccountLoader<'info, TickData>] = &tick_loaders_vec;
+    let tick_array_loaders_vec: Vec<&AccountLoader<'info, TickArray>> =
+        swap_tick_accounts.tick_arrays.iter().collect();
+    let tick_bitmap_loaders_vec: Vec<&AccountLoader<'info, TickBitmap>> =
+        swap_tick_accounts.tick_bitmaps.iter().collect();
 
     // grab the pool key from your &mut reference
     let pool_key = pool.key();
+    let sqrt_price_before = pool.sqrt_price_q64;
 
-    // 4. Call the core swap logic in `pool.swap()`
-    // IMPORTANT: Pool::swap signature and implementation in pool.rs MUST be updated
-    // to accept `amount_specified` as i128, `tick_loaders_slice`, and `current_timestamp`.
-    // It should return (amount0_swapped_abs: u128, amount1_swapped_abs: u128).
-    let (amount0_swapped_abs, amount1_swapped_abs) = pool.swap(
+    // 4. Call the core swap logic
+    let swap_result = pool.swap_with_tick_arrays(
         zero_for_one,
         amount_in as i128, // As per instruction prompt
         sqrt_price_limit_q64,
-        &pool_key,            // Pass the pool's key
-        tick_loaders_slice,   // Pass the tick loaders
+        &pool_key,               // Pass the pool's key
+        tick_loaders_slice,      // Pass the tick loaders
+        &tick_array_loaders_vec, // Pass the tick array loaders
+        &tick_bitmap_loaders_vec,
         clock.unix_timestamp, // Pass current timestamp
     )?;
 
-    // 5. Determine actual `amount_out` and verify against `amount_out_minimum`
-    let amount_out_u128 = if zero_for_one {
-        amount1_swapped_abs // Output is token1
-    } else {
-        amount0_swapped_abs // Output is token0
-    };
+    // 5. Verify the output against `amount_out_minimum`
+    let amount_out_u128 = swap_result.amount_out;
 
     if amount_out_u128 == 0 {
         return err!(ErrorCode::ZeroOutputAmount);
@@ -117,10 +116,12 @@ pub fn handler<'info>(
         )
     };
 
+    let fee_rate_bytes = pool.fee_tier.to_le_bytes();
     let pool_seeds = &[
         b"pool".as_ref(), // Assuming "pool" is the prefix seed
         pool.token0_mint.as_ref(),
         pool.token1_mint.as_ref(),
+        fee_rate_bytes.as_ref(),
         &[pool.bump],
     ];
     let signer_seeds = &[&pool_seeds[..]];
@@ -142,5 +143,29 @@ pub fn handler<'info>(
         amount_out_u64,
     )?;
 
+    emit!(SwapEvent {
+        pool: pool_key,
+        user: ctx.accounts.user_authority.key(),
+        zero_for_one,
+        amount_in,
+        amount_out: amount_out_u64,
+        sqrt_price_before,
+        sqrt_price_after: pool.sqrt_price_q64,
+        fee_paid: u64::try_from(swap_result.total_fee())
+            .map_err(|_| error!(ErrorCode::MathOverflow))?,
+        tick_after: pool.current_tick,
+        price_impact_bps: math::price_impact_bps(sqrt_price_before, pool.sqrt_price_q64)?,
+    });
+    flog!(
+        info,
+        "swap",
+        pool = pool_key,
+        zero_for_one = zero_for_one,
+        amount_in = amount_in,
+        amount_out = amount_out_u64,
+        sqrt_price_q64 = pool.sqrt_price_q64,
+        tick = pool.current_tick
+    );
+
     Ok(())
 }
@@ -0,0 +1,159 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+
+use crate::errors::ErrorCode;
+use crate::flog;
+use crate::math;
+use crate::state::pool::SwapEvent;
+use crate::tick::TickData;
+use crate::tick_array::{self, TickArray};
+use crate::tick_bitmap::TickBitmap;
+use crate::SwapExactOutput;
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SwapExactOutput<'info>>,
+    amount_out: u64,
+    amount_in_maximum: u64,
+    sqrt_price_limit_q64: u128,
+) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    let clock = Clock::get()?;
+    pool.ensure_no_flash_loan()?;
+    pool.ensure_not_paused()?;
+
+    // 1. Determine swap direction (zero_for_one) and validate token mints
+    let zero_for_one = if ctx.accounts.user_token_in_account.mint == pool.token0_mint {
+        require_keys_eq!(
+            ctx.accounts.user_token_out_account.mint,
+            pool.token1_mint,
+            ErrorCode::InvalidOutputMint
+        );
+        true // Swapping token0 for token1
+    } else if ctx.accounts.user_token_in_account.mint == pool.token1_mint {
+        require_keys_eq!(
+            ctx.accounts.user_token_out_account.mint,
+            pool.token0_mint,
+            ErrorCode::InvalidOutputMint
+        );
+        false // Swapping token1 for token0
+    } else {
+        return err!(ErrorCode::InvalidInputMint);
+    };
+
+    // 2. Load the tick, tick array and tick bitmap accounts passed through `remaining_accounts`
+    let swap_tick_accounts = tick_array::load_swap_tick_accounts(ctx.remaining_accounts)?;
+    let tick_loaders_vec: Vec<&AccountLoader<'info, TickData>> =
+        swap_tick_accounts.ticks.iter().collect();
+    let tick_loaders_slice: &[&AccountLoader<'info, TickData>] = &tick_loaders_vec;
+    let tick_array_loaders_vec: Vec<&AccountLoader<'info, TickArray>> =
+        swap_tick_accounts.tick_arrays.iter().collect();
+    let tick_bitmap_loaders_vec: Vec<&AccountLoader<'info, TickBitmap>> =
+        swap_tick_accounts.tick_bitmaps.iter().collect();
+
+    let pool_key = pool.key();
+    let sqrt_price_before = pool.sqrt_price_q64;
+
+    // 3. Run the swap first: the input owed is only known once the output is satisfied.
+    // A negative `amount_specified` selects exact-output accounting in `Pool::swap`.
+    let swap_result = pool.swap_with_tick_arrays(
+        zero_for_one,
+        -(amount_out as i128),
+        sqrt_price_limit_q64,
+        &pool_key,
+        tick_loaders_slice,
+        &tick_array_loaders_vec,
+        &tick_bitmap_loaders_vec,
+        clock.unix_timestamp,
+    )?;
+    let (amount_in_u128, amount_out_u128) = (swap_result.amount_in, swap_result.amount_out);
+
+    // 4. Verify the amounts against the caller's bounds.
+    // The swap may stop short of `amount_out` if the price limit is hit first.
+    if amount_out_u128 == 0 {
+        return err!(ErrorCode::ZeroOutputAmount);
+    }
+    require!(
+        amount_in_u128 <= amount_in_maximum as u128,
+        ErrorCode::ExcessiveInputAmount
+    );
+
+    let amount_in_u64 = u64::try_from(amount_in_u128)
+        .map_err(|_| error!(ErrorCode::MathOverflow).with_account_name("amount_in_u128"))?;
+    let amount_out_u64 = u64::try_from(amount_out_u128)
+        .map_err(|_| error!(ErrorCode::MathOverflow).with_account_name("amount_out_u128"))?;
+
+    // 5. Transfer the required input from user to the appropriate pool vault
+    let pool_destination_vault_info = if zero_for_one {
+        ctx.accounts.token0_vault.to_account_info()
+    } else {
+        ctx.accounts.token1_vault.to_account_info()
+    };
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.user_token_in_account.to_account_info(),
+                to: pool_destination_vault_info,
+                authority: ctx.accounts.user_authority.to_account_info(),
+            },
+        ),
+        amount_in_u64,
+    )?;
+
+    // 6. Transfer the output from the appropriate pool vault to the user
+    let pool_source_vault_info = if zero_for_one {
+        ctx.accounts.token1_vault.to_account_info() // Output is token1
+    } else {
+        ctx.accounts.token0_vault.to_account_info() // Output is token0
+    };
+
+    let fee_rate_bytes = pool.fee_tier.to_le_bytes();
+    let pool_seeds = &[
+        b"pool".as_ref(),
+        pool.token0_mint.as_ref(),
+        pool.token1_mint.as_ref(),
+        fee_rate_bytes.as_ref(),
+        &[pool.bump],
+    ];
+    let signer_seeds = &[&pool_seeds[..]];
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: pool_source_vault_info,
+                to: ctx.accounts.user_token_out_account.to_account_info(),
+                authority: pool.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        amount_out_u64,
+    )?;
+
+    emit!(SwapEvent {
+        pool: pool_key,
+        user: ctx.accounts.user_authority.key(),
+        zero_for_one,
+        amount_in: amount_in_u64,
+        amount_out: amount_out_u64,
+        sqrt_price_before,
+        sqrt_price_after: pool.sqrt_price_q64,
+        fee_paid: u64::try_from(swap_result.total_fee())
+            .map_err(|_| error!(ErrorCode::MathOverflow))?,
+        tick_after: pool.current_tick,
+        price_impact_bps: math::price_impact_bps(sqrt_price_before, pool.sqrt_price_q64)?,
+    });
+    flog!(
+        info,
+        "swap",
+        pool = pool_key,
+        zero_for_one = zero_for_one,
+        amount_in = amount_in_u64,
+        amount_out = amount_out_u64,
+        sqrt_price_q64 = pool.sqrt_price_q64,
+        tick = pool.current_tick
+    );
+
+    Ok(())
+}
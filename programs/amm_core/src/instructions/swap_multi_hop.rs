@@ -0,0 +1,302 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, Transfer};
+
+use crate::constants::MAX_SWAP_HOPS;
+use crate::errors::ErrorCode;
+use crate::flog;
+use crate::math;
+use crate::state::pool::{Pool, SwapEvent, SwapResult};
+use crate::tick::TickData;
+use crate::tick_array::{self, SwapTickAccounts, TickArray};
+use crate::tick_bitmap::TickBitmap;
+use crate::SwapMultiHop;
+
+/// One pool of a multi-hop swap route.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct HopParams {
+    /// The pool to swap through.
+    pub pool: Pubkey,
+    /// Direction of the hop: `true` swaps the pool's token0 for its token1.
+    pub zero_for_one: bool,
+    /// Number of tick, tick array and tick bitmap accounts passed for this hop, after the
+    /// pool and its vaults.
+    pub tick_accounts: u8,
+    /// Price limit of the hop. The hop fails if it reaches the limit before consuming its
+    /// whole input.
+    pub sqrt_price_limit_q64: u128,
+}
+
+/// Accounts passed through `remaining_accounts` for every hop ahead of its tick accounts:
+/// the pool, its token0 vault and its token1 vault.
+const HOP_POOL_ACCOUNTS: usize = 3;
+
+/// A hop that has been swapped and is waiting for its token transfers.
+struct ExecutedHop<'info> {
+    pool: Account<'info, Pool>,
+    input_vault: &'info AccountInfo<'info>,
+    output_vault: &'info AccountInfo<'info>,
+    zero_for_one: bool,
+    amount_in: u64,
+    amount_out: u64,
+    sqrt_price_before: u128,
+    fee_paid: u64,
+}
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SwapMultiHop<'info>>,
+    amount_in: u64,
+    amount_out_minimum: u64,
+    hops: Vec<HopParams>,
+) -> Result<()> {
+    validate_hops(&hops)?;
+    let clock = Clock::get()?;
+
+    // 1. Swap through every pool, piping the output of each hop into the next one
+    let mut remaining_accounts = ctx.remaining_accounts;
+    let mut input_mint = ctx.accounts.user_token_in_account.mint;
+    let mut hop_amount_in = amount_in as u128;
+    let mut executed: Vec<ExecutedHop<'info>> = Vec::with_capacity(hops.len());
+    for hop in &hops {
+        let account_count = HOP_POOL_ACCOUNTS + hop.tick_accounts as usize;
+        if remaining_accounts.len() < account_count {
+            return err!(anchor_lang::error::ErrorCode::AccountNotEnoughKeys);
+        }
+        let (hop_accounts, rest) = remaining_accounts.split_at(account_count);
+        remaining_accounts = rest;
+
+        let pool_info = &hop_accounts[0];
+        require_keys_eq!(pool_info.key(), hop.pool, ErrorCode::InvalidPool);
+        let mut pool = Account::<'info, Pool>::try_from(pool_info)?;
+        require_keys_eq!(pool.address()?, hop.pool, ErrorCode::InvalidPool);
+        let (token0_vault, token1_vault) = (&hop_accounts[1], &hop_accounts[2]);
+        require_keys_eq!(
+            token0_vault.key(),
+            pool.token0_vault,
+            ErrorCode::InvalidTokenVault
+        );
+        require_keys_eq!(
+            token1_vault.key(),
+            pool.token1_vault,
+            ErrorCode::InvalidTokenVault
+        );
+
+        let (hop_input_mint, hop_output_mint) = hop_mints(&pool, hop.zero_for_one);
+        let mint_error = if executed.is_empty() {
+            ErrorCode::InvalidInputMint
+        } else {
+            ErrorCode::InvalidHopPath
+        };
+        require_keys_eq!(hop_input_mint, input_mint, mint_error);
+
+        let tick_accounts =
+            tick_array::load_swap_tick_accounts(&hop_accounts[HOP_POOL_ACCOUNTS..])?;
+        let sqrt_price_before = pool.sqrt_price_q64;
+        let hop_result = swap_hop(
+            &mut pool,
+            hop,
+            hop_amount_in,
+            &tick_accounts,
+            clock.unix_timestamp,
+        )?;
+        let hop_amount_out = hop_result.amount_out;
+        flog!(
+            debug,
+            "swap_hop",
+            pool = hop.pool,
+            zero_for_one = hop.zero_for_one,
+            amount_in = hop_amount_in,
+            amount_out = hop_amount_out
+        );
+
+        let (input_vault, output_vault) = if hop.zero_for_one {
+            (token0_vault, token1_vault)
+        } else {
+            (token1_vault, token0_vault)
+        };
+        executed.push(ExecutedHop {
+            pool,
+            input_vault,
+            output_vault,
+            zero_for_one: hop.zero_for_one,
+            amount_in: to_u64(hop_amount_in)?,
+            amount_out: to_u64(hop_amount_out)?,
+            sqrt_price_before,
+            fee_paid: to_u64(hop_result.total_fee())?,
+        });
+        input_mint = hop_output_mint;
+        hop_amount_in = hop_amount_out;
+    }
+
+    // 2. Check the final output against the user's account and slippage limit
+    require_keys_eq!(
+        ctx.accounts.user_token_out_account.mint,
+        input_mint,
+        ErrorCode::InvalidOutputMint
+    );
+    let amount_out = hop_amount_in;
+    require!(
+        amount_out >= amount_out_minimum as u128,
+        ErrorCode::SlippageExceeded
+    );
+
+    // 3. Move the tokens along the route: the user pays the first pool, every pool pays
+    // the next one, and the last pool pays the user
+    let first_hop = &executed[0];
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.user_token_in_account.to_account_info(),
+                to: first_hop.input_vault.clone(),
+                authority: ctx.accounts.user_authority.to_account_info(),
+            },
+        ),
+        first_hop.amount_in,
+    )?;
+    for (index, hop) in executed.iter().enumerate() {
+        let destination = match executed.get(index + 1) {
+            Some(next_hop) => next_hop.input_vault.clone(),
+            None => ctx.accounts.user_token_out_account.to_account_info(),
+        };
+        transfer_from_pool(
+            &ctx.accounts.token_program,
+            &hop.pool,
+            hop.output_vault,
+            destination,
+            hop.amount_out,
+        )?;
+    }
+
+    // 4. Write back the pools, which Anchor does not persist for `remaining_accounts`
+    for hop in &executed {
+        hop.pool.exit(&crate::ID)?;
+        emit!(SwapEvent {
+            pool: hop.pool.key(),
+            user: ctx.accounts.user_authority.key(),
+            zero_for_one: hop.zero_for_one,
+            amount_in: hop.amount_in,
+            amount_out: hop.amount_out,
+            sqrt_price_before: hop.sqrt_price_before,
+            sqrt_price_after: hop.pool.sqrt_price_q64,
+            fee_paid: hop.fee_paid,
+            tick_after: hop.pool.current_tick,
+            price_impact_bps: math::price_impact_bps(
+                hop.sqrt_price_before,
+                hop.pool.sqrt_price_q64
+            )?,
+        });
+    }
+
+    flog!(
+        info,
+        "swap_multi_hop",
+        hops = hops.len(),
+        amount_in = amount_in,
+        amount_out = amount_out
+    );
+
+    Ok(())
+}
+
+/// Checks that a route has between 1 and `MAX_SWAP_HOPS` hops and goes through each pool
+/// at most once.
+pub fn validate_hops(hops: &[HopParams]) -> Result<()> {
+    require!(
+        !hops.is_empty() && hops.len() <= MAX_SWAP_HOPS,
+        ErrorCode::InvalidHopCount
+    );
+    for (index, hop) in hops.iter().enumerate() {
+        require!(
+            hops[..index]
+                .iter()
+                .all(|previous| previous.pool != hop.pool),
+            ErrorCode::InvalidHopPath
+        );
+    }
+    Ok(())
+}
+
+/// Returns the `(input, output)` mints of a hop through `pool`.
+pub fn hop_mints(pool: &Pool, zero_for_one: bool) -> (Pubkey, Pubkey) {
+    if zero_for_one {
+        (pool.token0_mint, pool.token1_mint)
+    } else {
+        (pool.token1_mint, pool.token0_mint)
+    }
+}
+
+/// Swaps `amount_in` through `pool` for one hop of a route and returns the amounts swapped
+/// and the swap fee.
+///
+/// Fails with `PriceLimitReached` if the hop reaches its price limit, or runs out of
+/// liquidity, before consuming `amount_in`, so that the route never strands more than
+/// rounding dust in an intermediate pool.
+pub fn swap_hop<'info>(
+    pool: &mut Pool,
+    hop: &HopParams,
+    amount_in: u128,
+    tick_accounts: &SwapTickAccounts<'info>,
+    current_timestamp: i64,
+) -> Result<SwapResult> {
+    pool.ensure_no_flash_loan()?;
+    pool.ensure_not_paused()?;
+    let amount_specified =
+        i128::try_from(amount_in).map_err(|_| error!(ErrorCode::MathOverflow))?;
+    let tick_loaders: Vec<&AccountLoader<'info, TickData>> = tick_accounts.ticks.iter().collect();
+    let tick_array_loaders: Vec<&AccountLoader<'info, TickArray>> =
+        tick_accounts.tick_arrays.iter().collect();
+    let tick_bitmap_loaders: Vec<&AccountLoader<'info, TickBitmap>> =
+        tick_accounts.tick_bitmaps.iter().collect();
+
+    let result = pool.swap_with_tick_arrays(
+        hop.zero_for_one,
+        amount_specified,
+        hop.sqrt_price_limit_q64,
+        &hop.pool,
+        &tick_loaders,
+        &tick_array_loaders,
+        &tick_bitmap_loaders,
+        current_timestamp,
+    )?;
+    // Input too small to move the price any further stays in the pool, as it does for
+    // `swap_exact_input`. Anything else left over means the hop stopped early.
+    let stopped_early = result.amount_in < amount_in
+        && (pool.sqrt_price_q64 == hop.sqrt_price_limit_q64 || pool.liquidity == 0);
+    require!(!stopped_early, ErrorCode::PriceLimitReached);
+    require!(result.amount_out > 0, ErrorCode::ZeroOutputAmount);
+    Ok(result)
+}
+
+/// Transfers `amount` out of one of `pool`'s vaults, signed by the pool.
+fn transfer_from_pool<'info>(
+    token_program: &Program<'info, Token>,
+    pool: &Account<'info, Pool>,
+    vault: &AccountInfo<'info>,
+    destination: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let fee_rate_bytes = pool.fee_tier.to_le_bytes();
+    let pool_seeds = &[
+        b"pool".as_ref(),
+        pool.token0_mint.as_ref(),
+        pool.token1_mint.as_ref(),
+        fee_rate_bytes.as_ref(),
+        &[pool.bump],
+    ];
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: vault.clone(),
+                to: destination,
+                authority: pool.to_account_info(),
+            },
+            &[&pool_seeds[..]],
+        ),
+        amount,
+    )
+}
+
+fn to_u64(amount: u128) -> Result<u64> {
+    u64::try_from(amount).map_err(|_| error!(ErrorCode::MathOverflow))
+}
@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+
+use crate::flog;
+use crate::state::pool::PoolPauseUpdated;
+use crate::SetPoolPaused;
+
+pub fn handler(ctx: Context<SetPoolPaused>) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    pool.is_paused = false;
+
+    emit!(PoolPauseUpdated {
+        pool: pool.key(),
+        is_paused: false,
+    });
+    flog!(
+        info,
+        "pool_unpaused",
+        pool = pool.key(),
+        authority = ctx.accounts.authority.key()
+    );
+    Ok(())
+}
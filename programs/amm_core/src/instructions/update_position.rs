@@ -1,111 +1,296 @@
-use crate::constants::{MAX_TICK, MIN_TICK};
 use crate::errors::ErrorCode;
+use crate::flog;
+use crate::instructions::collect_fees::transfer_from_vaults;
+use crate::instructions::mint_position::{validate_mint_params, validate_tick_range};
+use crate::math;
+use crate::state::pool::Pool;
+use crate::tick::TickData;
+use crate::tick_bitmap::{self, TickBitmap};
 use crate::UpdatePosition;
 use anchor_lang::prelude::*;
 
-pub fn handler(
-    ctx: Context<UpdatePosition>,
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, UpdatePosition<'info>>,
     new_tick_lower_index: i32,
     new_tick_upper_index: i32,
+    amount0_min: u64,
+    amount1_min: u64,
 ) -> Result<()> {
+    let tick_bitmaps = tick_bitmap::load_tick_bitmap_accounts(ctx.remaining_accounts)?;
+    let tick_bitmap_loaders: Vec<&AccountLoader<'info, TickBitmap>> = tick_bitmaps.iter().collect();
     let pool = &mut ctx.accounts.pool;
     let position = &mut ctx.accounts.position;
+    pool.ensure_not_paused()?;
+    pool.ensure_no_flash_loan()?;
 
-    // Validate new tick indices
-    if new_tick_lower_index >= new_tick_upper_index {
-        return err!(ErrorCode::InvalidTickRange);
-    }
-    if new_tick_lower_index < MIN_TICK || new_tick_upper_index > MAX_TICK {
-        return err!(ErrorCode::InvalidTickRange);
-    }
-    let tick_spacing = pool.tick_spacing as i32;
-    if new_tick_lower_index % tick_spacing != 0 || new_tick_upper_index % tick_spacing != 0 {
-        return err!(ErrorCode::InvalidTickSpacing);
-    }
+    validate_tick_range(
+        pool.tick_spacing,
+        new_tick_lower_index,
+        new_tick_upper_index,
+    )?;
 
     let old_tick_lower_idx = position.tick_lower_index;
     let old_tick_upper_idx = position.tick_upper_index;
+    check_tick_account_roles([
+        (ctx.accounts.old_tick_lower.key(), old_tick_lower_idx),
+        (ctx.accounts.old_tick_upper.key(), old_tick_upper_idx),
+        (ctx.accounts.new_tick_lower.key(), new_tick_lower_index),
+        (ctx.accounts.new_tick_upper.key(), new_tick_upper_index),
+    ])?;
     let liquidity_to_move = position.liquidity; // This is u128
 
     if liquidity_to_move == 0 {
         // If no liquidity, just update the position's ticks
         position.tick_lower_index = new_tick_lower_index;
         position.tick_upper_index = new_tick_upper_index;
-        msg!(
-            "Position {} ticks updated with zero liquidity.",
-            position.key()
+        position.entry_sqrt_price_q64 = pool.sqrt_price_q64;
+        let new_tick_lower_data = ctx.accounts.new_tick_lower.load()?;
+        let new_tick_upper_data = ctx.accounts.new_tick_upper.load()?;
+        position.snapshot_fee_growth_inside(pool, &new_tick_lower_data, &new_tick_upper_data);
+        flog!(
+            info,
+            "position_ticks_updated",
+            position = position.key(),
+            liquidity = 0
         );
         return Ok(());
     }
 
-    // 1. Remove liquidity from the old range
-    // The liquidity_delta is negative as we are removing liquidity.
-    pool.modify_liquidity(
-        old_tick_lower_idx,
-        old_tick_upper_idx,
-        -(liquidity_to_move as i128), // Cast u128 to i128 and negate
-        &ctx.accounts.old_tick_lower,
-        &ctx.accounts.old_tick_upper,
+    // 1. Settle the fees earned in the old range
+    {
+        let old_tick_lower_data = ctx.accounts.old_tick_lower.load()?;
+        let old_tick_upper_data = ctx.accounts.old_tick_upper.load()?;
+        position.update_fees(pool, &old_tick_lower_data, &old_tick_upper_data)?;
+    }
+
+    // 2. Withdraw the liquidity at the current price and redeposit as much of it as the new
+    //    range can take
+    let amounts = rebalance_amounts(
+        pool.sqrt_price_q64,
+        (old_tick_lower_idx, old_tick_upper_idx),
+        liquidity_to_move,
+        (new_tick_lower_index, new_tick_upper_index),
+    )?;
+    let new_liquidity = amounts.liquidity;
+    validate_mint_params(
+        pool.tick_spacing,
+        new_tick_lower_index,
+        new_tick_upper_index,
+        new_liquidity,
     )?;
-    msg!(
-        "Liquidity removed from old range [{}, {}]",
-        old_tick_lower_idx,
-        old_tick_upper_idx
-    );
 
-    // 2. Update the position's tick boundaries
+    // 3. Check the redeposit against the owner's minimums
+    if amounts.amount0_deposited < amount0_min || amounts.amount1_deposited < amount1_min {
+        return err!(ErrorCode::SlippageExceeded);
+    }
+
+    // 4. Move the liquidity to the new range, re-entering the position at the current price
+    let pool_key = pool.key();
+    pool.load_bitmap_words(&pool_key, &tick_bitmap_loaders)?;
     position.tick_lower_index = new_tick_lower_index;
     position.tick_upper_index = new_tick_upper_index;
+    position.entry_sqrt_price_q64 = pool.sqrt_price_q64;
+    position.liquidity = new_liquidity;
+    move_liquidity(
+        pool,
+        pool_key,
+        liquidity_to_move,
+        new_liquidity,
+        [
+            (old_tick_lower_idx, &ctx.accounts.old_tick_lower),
+            (old_tick_upper_idx, &ctx.accounts.old_tick_upper),
+        ],
+        [
+            (new_tick_lower_index, &ctx.accounts.new_tick_lower),
+            (new_tick_upper_index, &ctx.accounts.new_tick_upper),
+        ],
+    )?;
+    pool.store_bitmap_words(&tick_bitmap_loaders)?;
 
-    // 3. Initialize new TickData if they were newly created by init_if_needed
-    let mut new_tick_lower_data = ctx.accounts.new_tick_lower.load_mut()?;
-    if new_tick_lower_data.pool == Pubkey::default() {
-        // Check if it's uninitialized
-        new_tick_lower_data.initialize(pool.key(), new_tick_lower_index);
-        msg!(
-            "NewTickLower account {} initialized for index {}",
-            ctx.accounts.new_tick_lower.to_account_info().key(),
-            new_tick_lower_index
-        );
+    // 5. Fees in the new range accrue from now on
+    {
+        let new_tick_lower_data = ctx.accounts.new_tick_lower.load()?;
+        let new_tick_upper_data = ctx.accounts.new_tick_upper.load()?;
+        position.snapshot_fee_growth_inside(pool, &new_tick_lower_data, &new_tick_upper_data);
     }
-    drop(new_tick_lower_data); // Release borrow
-
-    let mut new_tick_upper_data = ctx.accounts.new_tick_upper.load_mut()?;
-    if new_tick_upper_data.pool == Pubkey::default() {
-        // Check if it's uninitialized
-        new_tick_upper_data.initialize(pool.key(), new_tick_upper_index);
-        msg!(
-            "NewTickUpper account {} initialized for index {}",
-            ctx.accounts.new_tick_upper.to_account_info().key(),
-            new_tick_upper_index
-        );
+    flog!(
+        info,
+        "position_rebalanced",
+        position = position.key(),
+        liquidity = new_liquidity,
+        amount0 = amounts.amount0_deposited,
+        amount1 = amounts.amount1_deposited,
+        pool_liquidity = pool.liquidity
+    );
+
+    // 6. Return what the new range could not use to the owner
+    transfer_from_vaults(
+        &ctx.accounts.pool,
+        &ctx.accounts.token_program,
+        &ctx.accounts.token0_vault,
+        &ctx.accounts.owner_token0_account,
+        &ctx.accounts.token1_vault,
+        &ctx.accounts.owner_token1_account,
+        amounts.amount0_returned,
+        amounts.amount1_returned,
+    )
+}
+
+/// Token amounts of moving a position's liquidity to a new range, as computed by
+/// `rebalance_amounts`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RebalanceAmounts {
+    /// Liquidity the withdrawn tokens support in the new range.
+    pub liquidity: u128,
+    /// Token0 deposited into the new range.
+    pub amount0_deposited: u64,
+    /// Token1 deposited into the new range.
+    pub amount1_deposited: u64,
+    /// Token0 withdrawn that the new range cannot use.
+    pub amount0_returned: u64,
+    /// Token1 withdrawn that the new range cannot use.
+    pub amount1_returned: u64,
+}
+
+/// Computes how `liquidity` on `old_ticks` moves to `new_ticks` at `sqrt_price_q64`.
+///
+/// The liquidity is withdrawn rounded down, as `decrease_liquidity` does, and the new
+/// range gets the most liquidity the withdrawn tokens support, deposited rounded up, as
+/// `mint_position` does. The deposit never exceeds the withdrawal, so the tokens left over
+/// are returned to the owner. A new range outside the current price takes a single token:
+/// all of the other one is returned.
+///
+/// # Errors
+///
+/// * `InvalidPriceRange` - Either range is empty.
+/// * `MathOverflow` - An amount does not fit in a `u64`.
+pub(crate) fn rebalance_amounts(
+    sqrt_price_q64: u128,
+    old_ticks: (i32, i32),
+    liquidity: u128,
+    new_ticks: (i32, i32),
+) -> Result<RebalanceAmounts> {
+    let (amount0_withdrawn, amount1_withdrawn) = math::get_amounts_for_liquidity(
+        sqrt_price_q64,
+        math::tick_to_sqrt_price_q64(old_ticks.0)?,
+        math::tick_to_sqrt_price_q64(old_ticks.1)?,
+        liquidity,
+        false,
+    )?;
+    let new_sqrt_price_lower_q64 = math::tick_to_sqrt_price_q64(new_ticks.0)?;
+    let new_sqrt_price_upper_q64 = math::tick_to_sqrt_price_q64(new_ticks.1)?;
+    let new_liquidity = math::get_liquidity_for_amounts(
+        sqrt_price_q64,
+        new_sqrt_price_lower_q64,
+        new_sqrt_price_upper_q64,
+        amount0_withdrawn,
+        amount1_withdrawn,
+    )?;
+    let (amount0_deposited, amount1_deposited) = math::get_amounts_for_liquidity(
+        sqrt_price_q64,
+        new_sqrt_price_lower_q64,
+        new_sqrt_price_upper_q64,
+        new_liquidity,
+        true,
+    )?;
+
+    let to_u64 = |amount: u128| u64::try_from(amount).map_err(|_| error!(ErrorCode::MathOverflow));
+    let returned = |withdrawn: u128, deposited: u128| {
+        to_u64(
+            withdrawn
+                .checked_sub(deposited)
+                .ok_or(ErrorCode::MathOverflow)?,
+        )
+    };
+    Ok(RebalanceAmounts {
+        liquidity: new_liquidity,
+        amount0_deposited: to_u64(amount0_deposited)?,
+        amount1_deposited: to_u64(amount1_deposited)?,
+        amount0_returned: returned(amount0_withdrawn, amount0_deposited)?,
+        amount1_returned: returned(amount1_withdrawn, amount1_deposited)?,
+    })
+}
+
+/// Checks the tick accounts passed to `update_position`, each paired with the tick index
+/// of its role: old lower, old upper, new lower and new upper.
+///
+/// One account can fill several roles when they stand for the same tick, e.g. the old and
+/// new lower tick when only the upper boundary moves, or the old upper and new lower tick
+/// when the range shifts by its width. It is then reused for each role.
+///
+/// # Errors
+///
+/// * `DuplicateTickAccount` - An account is passed for two roles with different ticks.
+pub(crate) fn check_tick_account_roles(roles: [(Pubkey, i32); 4]) -> Result<()> {
+    for (i, (key, tick_index)) in roles.iter().enumerate() {
+        for (other_key, other_tick_index) in &roles[i + 1..] {
+            if key == other_key && tick_index != other_tick_index {
+                return err!(ErrorCode::DuplicateTickAccount);
+            }
+        }
     }
-    drop(new_tick_upper_data); // Release borrow
+    Ok(())
+}
 
-    // 4. Add liquidity to the new range
-    // The liquidity_delta is positive.
+/// Removes `old_liquidity` from the old range's ticks and adds `new_liquidity` to the new
+/// range's, each given as its tick index and account, lower first. New tick accounts that were just
+/// created by `init_if_needed` are initialized on the way.
+///
+/// Each tick account is borrowed only while it is updated, so an account shared by an old
+/// and a new role (see `check_tick_account_roles`) sees the removal before the addition.
+pub(crate) fn move_liquidity<'info>(
+    pool: &mut Pool,
+    pool_key: Pubkey,
+    old_liquidity: u128,
+    new_liquidity: u128,
+    old_ticks: [(i32, &AccountLoader<'info, TickData>); 2],
+    new_ticks: [(i32, &AccountLoader<'info, TickData>); 2],
+) -> Result<()> {
+    let [(old_tick_lower_idx, old_tick_lower), (old_tick_upper_idx, old_tick_upper)] = old_ticks;
+    let [(new_tick_lower_idx, new_tick_lower), (new_tick_upper_idx, new_tick_upper)] = new_ticks;
+
+    // The liquidity_delta is negative as we are removing liquidity.
     pool.modify_liquidity(
-        new_tick_lower_index,
-        new_tick_upper_index,
-        liquidity_to_move as i128, // Cast u128 to i128
-        &ctx.accounts.new_tick_lower,
-        &ctx.accounts.new_tick_upper,
+        old_tick_lower_idx,
+        old_tick_upper_idx,
+        -(old_liquidity as i128), // Cast u128 to i128 and negate
+        old_tick_lower,
+        old_tick_upper,
     )?;
-    msg!(
-        "Liquidity added to new range [{}, {}]",
-        new_tick_lower_index,
-        new_tick_upper_index
-    );
-    msg!(
-        "Position {} rebalanced. New pool liquidity: {}",
-        position.key(),
-        pool.liquidity
+    flog!(
+        debug,
+        "liquidity_removed",
+        tick_lower = old_tick_lower_idx,
+        tick_upper = old_tick_upper_idx
     );
 
-    // MVP Simplification: Token transfers are complex.
-    // A full rebalance would calculate token amounts based on current price and new range,
-    // withdraw from vaults, and potentially require user to deposit/withdraw difference.
-    // For hackathon, "ghost-moving" liquidity by just updating ticks is a common simplification.
+    // Initialize new TickData if they were newly created by init_if_needed
+    for (tick_index, tick_loader) in new_ticks {
+        let mut tick_data = tick_loader.load_mut()?;
+        if tick_data.pool == Pubkey::default() {
+            tick_data.initialize(pool_key, tick_index);
+            flog!(
+                debug,
+                "tick_initialized",
+                tick = tick_loader.key(),
+                index = tick_index
+            );
+        }
+    }
 
+    // The liquidity_delta is positive.
+    pool.modify_liquidity(
+        new_tick_lower_idx,
+        new_tick_upper_idx,
+        new_liquidity as i128, // Cast u128 to i128
+        new_tick_lower,
+        new_tick_upper,
+    )?;
+    flog!(
+        debug,
+        "liquidity_added",
+        tick_lower = new_tick_lower_idx,
+        tick_upper = new_tick_upper_idx
+    );
     Ok(())
 }
@@ -1,23 +1,39 @@
 #![allow(unexpected_cfgs)]
 
 use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token::{Mint, Token, TokenAccount};
 use errors::ErrorCode;
-use position::PositionData;
-use state::pool::Pool;
+use instructions::initialize_oracle_pool::OracleSeedParams;
+use instructions::swap_multi_hop::HopParams;
+use liquidity_histogram::LiquidityHistogram;
+use position::{OwnerPositionIndex, PositionCounter, PositionData};
+use state::factory_config::{FactoryConfig, ProtocolFeeCurve};
+use state::order_book::{Order, OrderBook, OrderSide};
+use state::pool::{Pool, QuoteResult};
+use state::pool_registry::PoolRegistry;
+use state::token_pair::TokenPair;
 use tick::TickData;
+use tick_array::TickArray;
+use tick_bitmap::TickBitmap;
 
 // Your program's on-chain ID.
 // Replace with your actual program ID after deployment.
 declare_id!("BrbPGefYKdXgfmZTnasv3dkcE7TfQ82ueBwqmQX1Y8Ly");
 
 // Modules for constants, errors, core math, and state definitions
+#[cfg(not(target_os = "solana"))]
+pub mod client; // Off-chain transaction helpers
 pub mod constants;
 pub mod errors;
+pub mod liquidity_histogram;
+pub mod logging;
 pub mod math;
+pub mod oracle;
 pub mod position; // Defines PositionData
 pub mod state; // Defines Pool state (state::pool::Pool)
 pub mod tick; // Defines TickData
+pub mod tick_array;
 pub mod tick_bitmap;
 
 // Only include entrypoint if not building with no-entrypoint feature
@@ -33,87 +49,2444 @@ pub mod amm_core {
 
     /// Initializes a new liquidity pool for a pair of tokens.
     ///
+    /// A pair can have one pool per fee rate; the pool PDA is derived from
+    /// `[b"pool", mint_a, mint_b, fee_rate.to_le_bytes()]`. Each new pool is recorded in the
+    /// pair's `PoolRegistry`, which is created with the pair's first pool.
+    ///
+    /// The pair's `TokenPair` record must already exist (see `create_token_pair_handler`);
+    /// the pool references it and increments its pool count. The mints must be passed in
+    /// canonical order, `mint_a < mint_b`, or the call fails with `MintsNotInCanonicalOrder`.
+    /// The fee rate and tick spacing must be a fee tier the factory config enables (see
+    /// `enable_fee_tier_handler`), or the call fails with `InvalidFeeTier`.
+    ///
+    /// The pool starts with the protocol share the factory config's curve gives an empty
+    /// pool, and is administered by the factory config's authority, not its creator.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `initial_sqrt_price_q64` - The initial sqrt(price) for the pool, in Q64.64 format,
+    ///                              within `MIN_SQRT_PRICE..=MAX_SQRT_PRICE`.
+    /// * `fee_rate` - The fee rate for swaps in this pool, in basis points (e.g., 30 for 0.3%),
+    ///                within `MIN_FEE_RATE..=MAX_FEE_RATE`.
+    /// * `tick_spacing` - The spacing between usable ticks in this pool, the one the factory
+    ///                    config enables for `fee_rate`.
+    /// * `immutable_parameters` - When set, the fee rate, protocol fee and tick spacing can
+    ///   never change: `set_fee_rate_handler`, `set_protocol_fee_handler`,
+    ///   `refresh_protocol_share_handler` and `migrate_tick_spacing_handler` fail with
+    ///   `PoolImmutable`. The choice itself cannot be changed later.
+    pub fn initialize_pool_handler(
+        ctx: Context<InitializePool>,
+        initial_sqrt_price_q64: u128,
+        fee_rate: u16,
+        tick_spacing: u16,
+        immutable_parameters: bool,
+    ) -> Result<()> {
+        instructions::initialize_pool::handler(
+            ctx,
+            initial_sqrt_price_q64,
+            fee_rate,
+            tick_spacing,
+            immutable_parameters,
+        )
+    }
+
+    /// Initializes a new liquidity pool, as `initialize_pool_handler` does, and seeds its
+    /// oracle in the same instruction.
+    ///
+    /// The oracle is back-filled with `oracle_seed.cardinality` observations at the initial
+    /// price, `oracle_seed.interval_secs` apart, so TWAPs over up to
+    /// `(cardinality - 1) * interval_secs` seconds can be read from creation instead of
+    /// after the pool has traded for that long. Seeding in the creating instruction leaves
+    /// no window for a swap to move the price first.
+    ///
     /// # Arguments
     ///
     /// * `ctx` - The context containing all necessary accounts.
     /// * `initial_sqrt_price_q64` - The initial sqrt(price) for the pool, in Q64.64 format.
     /// * `fee_rate` - The fee rate for swaps in this pool, in basis points (e.g., 30 for 0.3%).
     /// * `tick_spacing` - The spacing between usable ticks in this pool.
-    pub fn initialize_pool_handler(
+    /// * `immutable_parameters` - When set, the fee rate, protocol fee and tick spacing can
+    ///   never change.
+    /// * `oracle_seed` - The number of observations to seed, from 2 up to
+    ///   `OBSERVATION_CAPACITY`, and the non-zero interval between them. Fails with
+    ///   `OracleCardinalityTooLarge` above the capacity, `InvalidObservationCardinality`
+    ///   below 2 and `InvalidInput` for a zero interval.
+    pub fn initialize_oracle_pool_handler(
         ctx: Context<InitializePool>,
         initial_sqrt_price_q64: u128,
         fee_rate: u16,
         tick_spacing: u16,
+        immutable_parameters: bool,
+        oracle_seed: OracleSeedParams,
+    ) -> Result<()> {
+        instructions::initialize_oracle_pool::handler(
+            ctx,
+            initial_sqrt_price_q64,
+            fee_rate,
+            tick_spacing,
+            immutable_parameters,
+            oracle_seed,
+        )
+    }
+
+    /// Creates a new concentrated liquidity position or adds liquidity to an existing one.
+    ///
+    /// Each position takes the next index from the owner's `PositionCounter` for the pool,
+    /// so an owner can open several positions over the same range.
+    ///
+    /// The owner deposits the token amounts the liquidity is worth at the current price:
+    /// only token0 for a range above it, only token1 for a range below it, and both for a
+    /// range around it. The instruction fails with `SlippageExceeded` if either amount is
+    /// above the owner's maximum.
+    ///
+    /// Owners who registered an `OwnerPositionIndex` can pass it to list the position.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `tick_lower_index` - The lower tick boundary of the position.
+    /// * `tick_upper_index` - The upper tick boundary of the position.
+    /// * `liquidity_amount_desired` - The amount of liquidity to add to this position.
+    /// * `position_index` - The owner's next position index in the pool, read from their
+    ///   position counter.
+    /// * `amount0_max` - The most token0 the owner will deposit for the liquidity.
+    /// * `amount1_max` - The most token1 the owner will deposit for the liquidity.
+    pub fn mint_position_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MintPosition<'info>>,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        liquidity_amount_desired: u128,
+        position_index: u64,
+        amount0_max: u64,
+        amount1_max: u64,
+    ) -> Result<()> {
+        instructions::mint_position::handler(
+            ctx,
+            tick_lower_index,
+            tick_upper_index,
+            liquidity_amount_desired,
+            position_index,
+            amount0_max,
+            amount1_max,
+        )
+    }
+
+    /// Swaps an exact amount of an input token for an output token.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `amount_in` - The exact amount of the input token to swap.
+    /// * `amount_out_minimum` - The minimum amount of the output token the swapper is willing to receive.
+    /// * `sqrt_price_limit_q64` - A price limit for the swap. If the price moves beyond this limit,
+    ///                            the swap will not consume the entire input amount.
+    ///
+    /// The `TickData` accounts of every initialized tick the swap crosses must be passed as
+    /// `remaining_accounts`, in crossing order: descending tick index for token0 -> token1,
+    /// ascending for token1 -> token0. Extra ticks the swap does not reach are ignored, and a
+    /// crossed tick that was not supplied fails with `MissingTickAccount`.
+    ///
+    /// Ticks stored in tick arrays are supplied by passing the `TickArray` accounts covering
+    /// them instead, in any order and mixed with the `TickData` accounts.
+    ///
+    /// Bitmap words moved out of the pool by `initialize_bitmap_word_handler` that the swap
+    /// reaches must be passed as well, as their `TickBitmap` accounts; a swap running into a
+    /// word that was not supplied fails with `MissingTickBitmapWord`.
+    pub fn swap_exact_input_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SwapExactInput<'info>>,
+        amount_in: u64,
+        amount_out_minimum: u64,
+        sqrt_price_limit_q64: u128,
+    ) -> Result<()> {
+        instructions::swap_exact_input::handler(
+            ctx,
+            amount_in,
+            amount_out_minimum,
+            sqrt_price_limit_q64,
+        )
+    }
+
+    /// Quotes `swap_exact_input_handler` without executing it.
+    ///
+    /// Runs the full swap computation, tick crossings included, against a copy of the pool
+    /// and returns the result as return data, so clients can simulate the instruction
+    /// instead of reimplementing the swap math. No account is written, so all of them may
+    /// be passed read-only.
+    ///
+    /// Tick, tick array and tick bitmap accounts are passed as `remaining_accounts`
+    /// exactly as for `swap_exact_input_handler`. Given the same pool state and accounts,
+    /// the quote matches the swap.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing the pool.
+    /// * `zero_for_one` - True to quote token0 -> token1, false for token1 -> token0.
+    /// * `amount_in` - The exact amount of the input token to swap.
+    /// * `sqrt_price_limit_q64` - The price limit the swap would be sent with.
+    pub fn quote_swap_exact_input_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, QuoteSwapExactInput<'info>>,
+        zero_for_one: bool,
+        amount_in: u64,
+        sqrt_price_limit_q64: u128,
+    ) -> Result<QuoteResult> {
+        instructions::quote_swap_exact_input::handler(
+            ctx,
+            zero_for_one,
+            amount_in,
+            sqrt_price_limit_q64,
+        )
+    }
+
+    /// Fails the transaction unless a live quote of an exact-input swap is within
+    /// `tolerance_bps` of `expected_out`.
+    ///
+    /// Meant to be placed right before `swap_exact_input_handler` in the same transaction:
+    /// the quote is computed at execution time, as `quote_swap_exact_input_handler` does with
+    /// no price limit, so a price moved by an earlier transaction makes the whole bundle
+    /// fail instead of the swap filling at a stale quote.
+    ///
+    /// Tick, tick array and tick bitmap accounts are passed as `remaining_accounts`
+    /// exactly as for `swap_exact_input_handler`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing the pool.
+    /// * `amount_in` - The exact amount of the input token the swap will spend.
+    /// * `zero_for_one` - True for a token0 -> token1 swap, false for token1 -> token0.
+    /// * `expected_out` - The output the client quoted.
+    /// * `tolerance_bps` - The largest deviation from `expected_out` accepted, either way,
+    ///   in basis points of `expected_out`. At most 10000.
+    pub fn assert_quote_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AssertQuote<'info>>,
+        amount_in: u64,
+        zero_for_one: bool,
+        expected_out: u64,
+        tolerance_bps: u16,
+    ) -> Result<()> {
+        instructions::assert_quote::handler(
+            ctx,
+            amount_in,
+            zero_for_one,
+            expected_out,
+            tolerance_bps,
+        )
+    }
+
+    /// Swaps as much input token as needed to receive an exact amount of output token.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `amount_out` - The exact amount of the output token to receive.
+    /// * `amount_in_maximum` - The maximum amount of the input token the swapper is willing to spend.
+    /// * `sqrt_price_limit_q64` - A price limit for the swap. If the price reaches this limit,
+    ///                            the swap stops and may return less than `amount_out`.
+    ///
+    /// Tick accounts are passed as `remaining_accounts`, as for `swap_exact_input_handler`.
+    pub fn swap_exact_output_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SwapExactOutput<'info>>,
+        amount_out: u64,
+        amount_in_maximum: u64,
+        sqrt_price_limit_q64: u128,
+    ) -> Result<()> {
+        instructions::swap_exact_output::handler(
+            ctx,
+            amount_out,
+            amount_in_maximum,
+            sqrt_price_limit_q64,
+        )
+    }
+
+    /// Moves an existing concentrated liquidity position to new tick boundaries.
+    ///
+    /// The position's liquidity is withdrawn from its old range at the current price and
+    /// the withdrawn tokens are redeposited into the new range, as much of them as it can
+    /// take. The new range generally holds the tokens in another proportion, so the
+    /// position's liquidity changes, and the tokens the new range cannot use are returned
+    /// to the owner's token accounts. Fees earned in the old range are credited to the
+    /// position first.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `new_tick_lower_index` - The new lower tick boundary for the position.
+    /// * `new_tick_upper_index` - The new upper tick boundary for the position.
+    /// * `amount0_min` - The minimum amount of token0 redeposited into the new range.
+    /// * `amount1_min` - The minimum amount of token1 redeposited into the new range.
+    pub fn update_position_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdatePosition<'info>>,
+        new_tick_lower_index: i32,
+        new_tick_upper_index: i32,
+        amount0_min: u64,
+        amount1_min: u64,
+    ) -> Result<()> {
+        instructions::update_position::handler(
+            ctx,
+            new_tick_lower_index,
+            new_tick_upper_index,
+            amount0_min,
+            amount1_min,
+        )
+    }
+
+    /// Removes liquidity from an existing position and returns the underlying tokens to the owner.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `liquidity_delta` - The amount of liquidity to remove. Must not exceed the position's liquidity.
+    pub fn decrease_liquidity_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DecreaseLiquidity<'info>>,
+        liquidity_delta: u128,
     ) -> Result<()> {
-        instructions::initialize_pool::handler(ctx, initial_sqrt_price_q64, fee_rate, tick_spacing)
+        instructions::decrease_liquidity::handler(ctx, liquidity_delta)
     }
 
-    /// Creates a new concentrated liquidity position or adds liquidity to an existing one.
-    ///
-    /// # Arguments
-    ///
-    /// * `ctx` - The context containing all necessary accounts.
-    /// * `tick_lower_index` - The lower tick boundary of the position.
-    /// * `tick_upper_index` - The upper tick boundary of the position.
-    /// * `liquidity_amount_desired` - The amount of liquidity to add to this position.
-    pub fn mint_position_handler(
-        ctx: Context<MintPosition>,
-        tick_lower_index: i32,
-        tick_upper_index: i32,
-        liquidity_amount_desired: u128,
-    ) -> Result<()> {
-        instructions::mint_position::handler(
-            ctx,
-            tick_lower_index,
-            tick_upper_index,
-            liquidity_amount_desired,
-        )
-    }
+    /// Closes an empty position and sends its rent to `rent_receiver`.
+    ///
+    /// The position must have no liquidity and no uncollected tokens. The position's
+    /// `TickData` accounts can optionally be passed to close them in the same instruction;
+    /// each one is closed only if no position references it anymore.
+    ///
+    /// A position listed in an `OwnerPositionIndex` is unlisted, which requires the index
+    /// and, unless the position is its head, the listed position linking to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    pub fn close_position_handler(ctx: Context<ClosePosition>) -> Result<()> {
+        instructions::close_position::handler(ctx)
+    }
+
+    /// Collects the swap fees earned by a position.
+    ///
+    /// Fees earned since the position's last update are credited first, then up to the
+    /// requested amounts of the position's owed tokens are transferred to the owner.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `amount_0_requested` - The maximum amount of token0 to collect.
+    /// * `amount_1_requested` - The maximum amount of token1 to collect.
+    pub fn collect_fees_handler(
+        ctx: Context<CollectFees>,
+        amount_0_requested: u64,
+        amount_1_requested: u64,
+    ) -> Result<()> {
+        instructions::collect_fees::handler(ctx, amount_0_requested, amount_1_requested)
+    }
+
+    /// Lends tokens from the pool vaults for the duration of a callback.
+    ///
+    /// The borrowed amounts are transferred to the borrower, then the callback program passed
+    /// as the first remaining account is invoked with the other remaining accounts. By the
+    /// time it returns, both vaults must hold their original balances plus the pool's
+    /// `flash_fee_bps` on each borrowed amount. The fee is credited to liquidity providers
+    /// like swap fees.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `amount_token0` - The amount of token0 to borrow.
+    /// * `amount_token1` - The amount of token1 to borrow.
+    pub fn flash_loan_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FlashLoan<'info>>,
+        amount_token0: u64,
+        amount_token1: u64,
+    ) -> Result<()> {
+        instructions::flash_loan::handler(ctx, amount_token0, amount_token1)
+    }
+
+    /// Returns the protocol's tick and sqrt price bounds.
+    ///
+    /// Lets clients and other programs read `MIN_TICK`, `MAX_TICK`, `MIN_SQRT_PRICE` and
+    /// `MAX_SQRT_PRICE` from the deployed program instead of hardcoding them.
+    pub fn get_price_bounds(_ctx: Context<GetPriceBounds>) -> Result<constants::PriceBounds> {
+        Ok(constants::PRICE_BOUNDS)
+    }
+
+    /// Returns the sqrt price of a tick, in Q64.64, as the program computes it for position
+    /// bounds and tick crossings.
+    ///
+    /// Lets clients and other programs price ticks without replicating the conversion's
+    /// power table. Off-chain Rust clients can call `client::sqrt_price_at_tick` instead.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context, which reads no state
+    /// * `tick` - The tick index, between `MIN_TICK` and `MAX_TICK`
+    pub fn get_sqrt_price_at_tick(_ctx: Context<GetSqrtPriceAtTick>, tick: i32) -> Result<u128> {
+        math::tick_to_sqrt_price_q64(tick)
+    }
+
+    /// Creates the limit order book of a pool, along with its token escrow vaults.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `tick_size` - Price increment orders must align with, in `ORDER_PRICE_SCALE` units.
+    pub fn initialize_order_book_handler(
+        ctx: Context<InitializeOrderBook>,
+        tick_size: u64,
+    ) -> Result<()> {
+        instructions::initialize_order_book::handler(ctx, tick_size)
+    }
+
+    /// Places a limit order, escrowing token1 for bids or token0 for asks.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `side` - Whether the order buys (`Bid`) or sells (`Ask`) token0.
+    /// * `price` - Limit price in token1 per token0, scaled by `ORDER_PRICE_SCALE`.
+    ///             Must be a multiple of the order book's tick size.
+    /// * `quantity` - Token0 quantity of the order.
+    /// * `expires_at` - Unix timestamp after which the order can no longer fill, or 0 for no expiry.
+    pub fn place_limit_order_handler(
+        ctx: Context<PlaceLimitOrder>,
+        side: OrderSide,
+        price: u64,
+        quantity: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        instructions::place_limit_order::handler(ctx, side, price, quantity, expires_at)
+    }
+
+    /// Creates a tick array holding `TICK_ARRAY_SIZE` ticks of a pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `start_tick_index` - The index of the first tick in the array. Must be a multiple of
+    ///                        `TICK_ARRAY_SIZE * bitmap_tick_spacing`.
+    pub fn initialize_tick_array_handler(
+        ctx: Context<InitializeTickArray>,
+        start_tick_index: i32,
+    ) -> Result<()> {
+        instructions::initialize_tick_array::handler(ctx, start_tick_index)
+    }
+
+    /// Creates a concentrated liquidity position whose ticks are stored in tick arrays.
+    ///
+    /// Behaves like `mint_position_handler`, but reads and writes the boundary ticks in the
+    /// tick arrays covering them, which must already be initialized. Both ticks can fall in
+    /// the same array, in which case it is passed for both accounts.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `tick_lower_index` - The lower tick boundary of the position.
+    /// * `tick_upper_index` - The upper tick boundary of the position.
+    /// * `liquidity_amount_desired` - The amount of liquidity to add to this position.
+    /// * `position_index` - The owner's next position index in the pool, read from their
+    ///   position counter.
+    /// * `amount0_max` - The most token0 the owner will deposit for the liquidity.
+    /// * `amount1_max` - The most token1 the owner will deposit for the liquidity.
+    pub fn mint_position_with_tick_arrays_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MintPositionWithTickArrays<'info>>,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        liquidity_amount_desired: u128,
+        position_index: u64,
+        amount0_max: u64,
+        amount1_max: u64,
+    ) -> Result<()> {
+        instructions::mint_position_with_tick_arrays::handler(
+            ctx,
+            tick_lower_index,
+            tick_upper_index,
+            liquidity_amount_desired,
+            position_index,
+            amount0_max,
+            amount1_max,
+        )
+    }
+
+    /// Removes liquidity from a position whose ticks are stored in tick arrays.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `liquidity_delta` - The amount of liquidity to remove. Must not exceed the position's liquidity.
+    pub fn decrease_liquidity_with_tick_arrays_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DecreaseLiquidityWithTickArrays<'info>>,
+        liquidity_delta: u128,
+    ) -> Result<()> {
+        instructions::decrease_liquidity_with_tick_arrays::handler(ctx, liquidity_delta)
+    }
+
+    /// Collects the swap fees earned by a position whose ticks are stored in tick arrays.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `amount_0_requested` - The maximum amount of token0 to collect.
+    /// * `amount_1_requested` - The maximum amount of token1 to collect.
+    pub fn collect_fees_with_tick_arrays_handler(
+        ctx: Context<CollectFeesWithTickArrays>,
+        amount_0_requested: u64,
+        amount_1_requested: u64,
+    ) -> Result<()> {
+        instructions::collect_fees_with_tick_arrays::handler(
+            ctx,
+            amount_0_requested,
+            amount_1_requested,
+        )
+    }
+
+    /// Sets the share of swap fees kept by the protocol. Only the factory config authority can
+    /// call this.
+    ///
+    /// A share given here takes the pool off the factory's protocol fee curve:
+    /// `refresh_protocol_share_handler` fails with `ProtocolFeeManual` until this is called
+    /// again without a share, which puts the pool back on the curve at once. Fails on a pool
+    /// initialized with immutable parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `protocol_fee` - The protocol's share, in basis points of each swap fee, or `None`
+    ///   to follow the factory curve again.
+    pub fn set_protocol_fee_handler(
+        ctx: Context<SetProtocolFee>,
+        protocol_fee: Option<u16>,
+    ) -> Result<()> {
+        instructions::set_protocol_fee::handler(ctx, protocol_fee)
+    }
+
+    /// Transfers all accrued protocol fees from the pool vaults and resets the counters.
+    /// Only the factory config authority can call this.
+    ///
+    /// The fees go to the authority's associated token accounts, which are created, paid
+    /// for by the authority, when missing. A side with nothing accrued is skipped, so a
+    /// second call in a row transfers nothing and succeeds.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    pub fn collect_protocol_fees_handler(ctx: Context<CollectProtocolFees>) -> Result<()> {
+        instructions::collect_protocol_fees::handler(ctx)
+    }
+
+    /// Returns the pool's time-weighted average tick over the last `seconds_ago` seconds.
+    ///
+    /// The mean is taken over the pool's oracle observations, which are recorded at the
+    /// start of every swap, and is rounded towards negative infinity.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing the pool account
+    /// * `seconds_ago` - Length of the averaging window, in seconds
+    pub fn get_twap(ctx: Context<GetTwap>, seconds_ago: u32) -> Result<i32> {
+        ctx.accounts
+            .pool
+            .get_twap(Clock::get()?.unix_timestamp, seconds_ago)
+    }
+
+    /// Returns whether the pool's current tick lies within the position's range, in which
+    /// case the position's liquidity is active and earning fees.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing the position and its pool
+    pub fn is_position_in_range(ctx: Context<IsPositionInRange>) -> Result<bool> {
+        Ok(ctx.accounts.position.is_in_range(&ctx.accounts.pool))
+    }
+
+    /// Matches a crossing bid and ask on an order book.
+    ///
+    /// Fills both orders for the smaller of their remaining quantities at the midpoint of
+    /// their prices, rounded down to the book's tick size. The bidder receives token0 from
+    /// the base escrow and any token1 it escrowed above the match price; the asker receives
+    /// token1 from the quote escrow. Filled orders are closed and their rent returned to
+    /// their owners. Anyone can call this.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    pub fn execute_match_handler(ctx: Context<ExecuteMatch>) -> Result<()> {
+        instructions::execute_match::handler(ctx)
+    }
+
+    /// Cancels a limit order, refunding what it still holds in escrow to its owner and
+    /// closing it.
+    ///
+    /// The owner can cancel at any time. Once the order has expired anyone can, and a
+    /// canceller other than the owner is paid `ORDER_CANCEL_KEEPER_REWARD_LAMPORTS` out of
+    /// the order's rent; the rest of the rent goes back to the owner.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    pub fn cancel_order_handler(ctx: Context<CancelOrder>) -> Result<()> {
+        instructions::cancel_order::handler(ctx)
+    }
+
+    /// Moves a word of the pool's tick bitmap into its own account.
+    ///
+    /// The pool stores its bitmap inline, up to a fixed number of bytes. Once that is
+    /// exhausted, further words are created here, one `TickBitmap` account per word of 64
+    /// compressed ticks. Instructions touching ticks in a moved word, or swaps crossing it,
+    /// must then pass its account in `remaining_accounts`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `word_index` - The index of the bitmap word, i.e. the compressed tick divided by 64.
+    pub fn initialize_bitmap_word_handler(
+        ctx: Context<InitializeBitmapWord>,
+        word_index: i16,
+    ) -> Result<()> {
+        instructions::initialize_bitmap_word::handler(ctx, word_index)
+    }
+
+    /// Creates the `TokenPair` record of a pair of mints.
+    ///
+    /// Each pair is created once, with its mints in canonical order (`mint_a < mint_b`), and
+    /// must exist before any pool of the pair is initialized. The record is a PDA of
+    /// `[b"token_pair", mint_a, mint_b]`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `oracle_feed` - The price feed suggested as the default oracle for the pair's pools,
+    ///                   or `Pubkey::default()` for none.
+    pub fn create_token_pair_handler(
+        ctx: Context<CreateTokenPair>,
+        oracle_feed: Pubkey,
+    ) -> Result<()> {
+        instructions::create_token_pair::handler(ctx, oracle_feed)
+    }
+
+    /// Swaps an exact amount of input token through a route of up to four pools.
+    ///
+    /// The output of each hop is the input of the next one, and the final output is checked
+    /// against `amount_out_minimum`. A hop that reaches its price limit before consuming its
+    /// input fails, and if any hop fails the whole route is rolled back. Intermediate tokens
+    /// move from pool to pool without passing through the user's accounts, and amounts stay
+    /// in base units throughout, whatever the decimals of each mint.
+    ///
+    /// For each hop, in order, `remaining_accounts` holds the pool, its token0 and token1
+    /// vaults, then the hop's `tick_accounts` tick, tick array and tick bitmap accounts, as
+    /// for `swap_exact_input_handler`. Pools are checked against their PDA seeds.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `amount_in` - The exact amount of input token to swap.
+    /// * `amount_out_minimum` - The minimum amount of the final output token to receive.
+    /// * `hops` - The pools of the route, with the direction and price limit of each hop.
+    pub fn swap_multi_hop_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SwapMultiHop<'info>>,
+        amount_in: u64,
+        amount_out_minimum: u64,
+        hops: Vec<HopParams>,
+    ) -> Result<()> {
+        instructions::swap_multi_hop::handler(ctx, amount_in, amount_out_minimum, hops)
+    }
+
+    /// Changes the fee rate charged on swaps. Only the factory config authority can call this.
+    ///
+    /// The pool's address and tick spacing stay those it was created with. Fails while a
+    /// flash loan on the pool is in progress, and on a pool initialized with immutable
+    /// parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `new_fee_rate` - The new fee rate, in basis points, between `MIN_FEE_RATE` and
+    ///   `MAX_FEE_RATE`.
+    pub fn set_fee_rate_handler(ctx: Context<SetFeeRate>, new_fee_rate: u16) -> Result<()> {
+        instructions::set_fee_rate::handler(ctx, new_fee_rate)
+    }
+
+    /// Widens the tick spacing of a pool. Only the factory config authority can call it.
+    /// Pools initialized with immutable parameters keep their spacing.
+    ///
+    /// New positions must then be aligned to the new spacing. Existing positions keep
+    /// their boundaries and liquidity, and swaps keep crossing them, until their owner
+    /// moves them with `update_position_handler`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `new_tick_spacing` - The new tick spacing, a strict multiple of the current one.
+    pub fn migrate_tick_spacing_handler(
+        ctx: Context<MigrateTickSpacing>,
+        new_tick_spacing: u16,
+    ) -> Result<()> {
+        instructions::migrate_tick_spacing::handler(ctx, new_tick_spacing)
+    }
+
+    /// Migrates a position written under an earlier `PositionData` layout to the current
+    /// one, during a coordinated upgrade. Only the factory config authority can call it.
+    ///
+    /// The account is grown to the current size, with the authority topping up its rent.
+    /// Fields the old layout lacked start out empty, and a position written before
+    /// positions tracked their fees starts earning them from the pool's current fee growth.
+    /// A position without an entry price is given the pool's current sqrt price.
+    /// The position can then be collected from, withdrawn from and closed as usual.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    pub fn migrate_position_handler(ctx: Context<MigratePosition>) -> Result<()> {
+        instructions::migrate_position::handler(ctx)
+    }
+
+    /// Resizes a pool account to `new_len` bytes ahead of a layout change, during a
+    /// coordinated upgrade. Only the factory config authority can call it.
+    ///
+    /// Growing the account zero-fills the new bytes and tops up its rent from the
+    /// authority; shrinking it refunds the rent it no longer needs. The account can grow by
+    /// at most `MAX_PERMITTED_DATA_INCREASE` bytes per call and cannot shrink below
+    /// `Pool::LEN`. The pool keeps serving swaps and liquidity changes at any size.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `new_len` - The account's new size in bytes, discriminator included.
+    pub fn resize_pool_account_handler(
+        ctx: Context<ResizePoolAccount>,
+        new_len: u32,
+    ) -> Result<()> {
+        instructions::resize_pool_account::handler(ctx, new_len)
+    }
+
+    /// Resizes one of a pool's tick arrays to `new_len` bytes, like
+    /// `resize_pool_account_handler`. Only the factory config authority can call it.
+    ///
+    /// The account cannot shrink below `8 + TickArray::LEN`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `new_len` - The account's new size in bytes, discriminator included.
+    pub fn resize_tick_array_handler(ctx: Context<ResizeTickArray>, new_len: u32) -> Result<()> {
+        instructions::resize_tick_array::handler(ctx, new_len)
+    }
+
+    /// Creates the liquidity histogram of a pool. Only the factory config authority can
+    /// call it.
+    ///
+    /// The histogram stays empty, and stale for every reader, until
+    /// `refresh_liquidity_histogram_handler` first runs.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `bucket_ticks` - The width of each of the `HISTOGRAM_BUCKETS` buckets. Must be a
+    ///                    non-zero multiple of the pool's tick spacing.
+    pub fn initialize_liquidity_histogram_handler(
+        ctx: Context<InitializeLiquidityHistogram>,
+        bucket_ticks: u32,
+    ) -> Result<()> {
+        instructions::initialize_liquidity_histogram::handler(ctx, bucket_ticks)
+    }
+
+    /// Recenters a pool's liquidity histogram on the current price and recomputes its
+    /// buckets. Anyone can call it.
+    ///
+    /// Every initialized tick inside the histogram's new range must be supplied through
+    /// `remaining_accounts`, either as its `TickData` account, in ascending order, or
+    /// through the tick array holding it. The `TickBitmap` accounts of the bitmap words
+    /// covering the range must be supplied too, if the pool has moved them out.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    pub fn refresh_liquidity_histogram_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RefreshLiquidityHistogram<'info>>,
+    ) -> Result<()> {
+        instructions::refresh_liquidity_histogram::handler(ctx)
+    }
+
+    /// Pauses a pool. Only the factory config authority can call it.
+    ///
+    /// While paused, swaps, flash loans, `mint_position_handler` and
+    /// `update_position_handler` fail with `PoolPaused`. `decrease_liquidity_handler` and
+    /// `collect_fees_handler` keep working so liquidity providers can exit.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    pub fn pause_pool_handler(ctx: Context<SetPoolPaused>) -> Result<()> {
+        instructions::pause_pool::handler(ctx)
+    }
+
+    /// Unpauses a pool paused with `pause_pool_handler`. Only the factory config authority
+    /// can call it.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    pub fn unpause_pool_handler(ctx: Context<SetPoolPaused>) -> Result<()> {
+        instructions::unpause_pool::handler(ctx)
+    }
+
+    /// Publishes an external oracle price (e.g. read from Pyth or Switchboard) for the
+    /// pool. Only the pool's oracle authority can call it.
+    ///
+    /// Unlike `sqrt_price_q64`, this price cannot be moved by trading in the same
+    /// transaction, so downstream programs such as the risk engine read it instead of the
+    /// spot price. Prices older than the pool's `max_oracle_age_slots`, or older than the
+    /// stored one, are rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `sqrt_price_q64` - The oracle's sqrt price, in Q64.64.
+    /// * `published_slot` - The slot at which the oracle published the price.
+    pub fn set_oracle_handler(
+        ctx: Context<SetOracle>,
+        sqrt_price_q64: u128,
+        published_slot: u64,
+    ) -> Result<()> {
+        instructions::set_oracle::handler(ctx, sqrt_price_q64, published_slot)
+    }
+
+    /// Sets who can publish the pool's oracle price and how many slots it stays fresh.
+    /// Only the factory config authority can call this.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `oracle_authority` - The account allowed to call `set_oracle_handler`.
+    /// * `max_oracle_age_slots` - Age, in slots, past which the oracle price is stale.
+    pub fn set_oracle_config_handler(
+        ctx: Context<SetOracleConfig>,
+        oracle_authority: Pubkey,
+        max_oracle_age_slots: u64,
+    ) -> Result<()> {
+        instructions::set_oracle_config::handler(ctx, oracle_authority, max_oracle_age_slots)
+    }
+
+    /// Creates the factory config, the PDA pools name as their `factory` to follow its
+    /// protocol fee curve. Only the program's upgrade authority may create it, and it becomes
+    /// the config's governance authority.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `protocol_fee_curve` - The protocol's share of swap fees by pool liquidity.
+    pub fn initialize_factory_config_handler(
+        ctx: Context<InitializeFactoryConfig>,
+        protocol_fee_curve: ProtocolFeeCurve,
+    ) -> Result<()> {
+        instructions::initialize_factory_config::handler(ctx, protocol_fee_curve)
+    }
+
+    /// Replaces the factory config's protocol fee curve. Only the config authority can call
+    /// this.
+    ///
+    /// Pools keep their current share until `refresh_protocol_share_handler` runs for them,
+    /// and protocol fees accrued before then are not recomputed.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `protocol_fee_curve` - The new protocol share of swap fees by pool liquidity.
+    pub fn set_protocol_fee_curve_handler(
+        ctx: Context<SetProtocolFeeCurve>,
+        protocol_fee_curve: ProtocolFeeCurve,
+    ) -> Result<()> {
+        instructions::set_protocol_fee_curve::handler(ctx, protocol_fee_curve)
+    }
+
+    /// Lets pools be created at a fee rate, with the tick spacing pools at that rate use.
+    /// Only the factory config authority can call this.
+    ///
+    /// A fee rate keeps the tick spacing it is first enabled with, so every pool of a pair
+    /// at that rate quotes the same ticks.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `fee_rate` - The fee rate, in basis points, within `MIN_FEE_RATE..=MAX_FEE_RATE`.
+    /// * `tick_spacing` - The tick spacing of pools at `fee_rate`, one of
+    ///   `ALLOWED_TICK_SPACINGS`.
+    pub fn enable_fee_tier_handler(
+        ctx: Context<EnableFeeTier>,
+        fee_rate: u16,
+        tick_spacing: u16,
+    ) -> Result<()> {
+        instructions::enable_fee_tier::handler(ctx, fee_rate, tick_spacing)
+    }
+
+    /// Sets a pool's protocol fee from its factory's protocol fee curve, for the pool's
+    /// current active liquidity.
+    ///
+    /// Permissionless, so keepers can crank it as liquidity changes instead of evaluating the
+    /// curve on every swap. The share it sets applies to later swaps only. Pools whose share
+    /// was set with `set_protocol_fee_handler` keep it and fail with `ProtocolFeeManual`, as
+    /// do pools initialized with immutable parameters, with `PoolImmutable`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    pub fn refresh_protocol_share_handler(ctx: Context<RefreshProtocolShare>) -> Result<()> {
+        instructions::refresh_protocol_share::handler(ctx)
+    }
+
+    /// Opens a position represented by an NFT, minted to the owner with a supply of one.
+    ///
+    /// The position account is derived from the NFT mint rather than from the owner and
+    /// range, so a wallet can hold several positions over the same range. Whoever holds
+    /// the NFT can update the position, remove its liquidity, collect its fees and close it.
+    ///
+    /// Owners who registered an `OwnerPositionIndex` can pass it to list the position. It
+    /// stays listed under them if the NFT changes hands.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `tick_lower_index` - The lower tick boundary of the position.
+    /// * `tick_upper_index` - The upper tick boundary of the position.
+    /// * `liquidity_amount_desired` - The amount of liquidity to add to this position.
+    /// * `amount0_max` - The most token0 the owner will deposit for the liquidity.
+    /// * `amount1_max` - The most token1 the owner will deposit for the liquidity.
+    pub fn mint_position_nft_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MintPositionNft<'info>>,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        liquidity_amount_desired: u128,
+        amount0_max: u64,
+        amount1_max: u64,
+    ) -> Result<()> {
+        instructions::mint_position_nft::handler(
+            ctx,
+            tick_lower_index,
+            tick_upper_index,
+            liquidity_amount_desired,
+            amount0_max,
+            amount1_max,
+        )
+    }
+
+    /// Opens a position from the token amounts the owner wants to deposit rather than a
+    /// liquidity amount.
+    ///
+    /// The position receives the most liquidity both desired amounts cover at the current
+    /// price, so one of them is usually consumed only in part. `MintPositionEvent` reports
+    /// the amounts actually deposited. The instruction fails with `SlippageExceeded` if
+    /// either is below the owner's minimum.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `tick_lower_index` - The lower tick boundary of the position.
+    /// * `tick_upper_index` - The upper tick boundary of the position.
+    /// * `amount0_desired` - The most token0 the owner will deposit.
+    /// * `amount1_desired` - The most token1 the owner will deposit.
+    /// * `amount0_min` - The least token0 the owner accepts depositing.
+    /// * `amount1_min` - The least token1 the owner accepts depositing.
+    ///
+    /// The position takes the owner's next position index in the pool, so its address is
+    /// derived like `mint_position_handler`'s with the index read from the owner's
+    /// position counter.
+    pub fn mint_position_with_amounts_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MintPositionWithAmounts<'info>>,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        amount0_desired: u64,
+        amount1_desired: u64,
+        amount0_min: u64,
+        amount1_min: u64,
+    ) -> Result<()> {
+        instructions::mint_position_with_amounts::handler(
+            ctx,
+            tick_lower_index,
+            tick_upper_index,
+            amount0_desired,
+            amount1_desired,
+            amount0_min,
+            amount1_min,
+        )
+    }
+
+    /// Creates the owner's `OwnerPositionIndex`, an on-chain list of the positions they
+    /// choose to list so other programs can iterate over them.
+    ///
+    /// Listing is opt-in: positions are listed only when minted with the index account,
+    /// which saves owners who do not need it the extra account and writes.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    pub fn register_owner_index_handler(ctx: Context<RegisterOwnerIndex>) -> Result<()> {
+        instructions::register_owner_index::handler(ctx)
+    }
+
+    /// Matches the crossing orders of an order book in one batch, as a crank.
+    ///
+    /// Repeatedly fills the best remaining bid against the best remaining ask, as
+    /// `execute_match_handler` does for a single pair, until `max_matches` fills are made or
+    /// the best bid and ask no longer cross. Each fill emits a `TradeExecuted` event, every
+    /// owner is paid once for all of its order's fills, and filled orders are closed. Orders
+    /// keep their fills when the batch stops early, so the crank can call again with what
+    /// remains. Anyone can call this.
+    ///
+    /// `remaining_accounts` holds the bids, then the asks, each sorted best first: by price,
+    /// then oldest first. Each bid is followed by its owner and the owner's token0 and
+    /// token1 accounts, and each ask by its owner and the owner's token1 account.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context containing all necessary accounts.
+    /// * `max_matches` - The most fills to make, bounding the instruction's compute units.
+    pub fn match_orders_batch_handler<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MatchOrdersBatch<'info>>,
+        max_matches: u8,
+    ) -> Result<()> {
+        instructions::match_orders_batch::handler(ctx, max_matches)
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    liquidity_amount_desired: u128,
+    position_index: u64
+)]
+pub struct MintPosition<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = PositionData::LEN,
+        seeds = [
+            b"position".as_ref(),
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            tick_lower_index.to_le_bytes().as_ref(),
+            tick_upper_index.to_le_bytes().as_ref(),
+            position_index.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub position: Account<'info, PositionData>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + TickData::LEN,
+        seeds = [
+            b"tick".as_ref(),
+            pool.key().as_ref(),
+            tick_lower_index.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub tick_lower: AccountLoader<'info, TickData>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + TickData::LEN,
+        seeds = [
+            b"tick".as_ref(),
+            pool.key().as_ref(),
+            tick_upper_index.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub tick_upper: AccountLoader<'info, TickData>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>, // Needed for init and init_if_needed
+
+    /// Counts the owner's positions in the pool and supplies the new position's index.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PositionCounter::LEN,
+        seeds = [b"position_counter".as_ref(), pool.key().as_ref(), owner.key().as_ref()],
+        bump,
+        constraint = position_counter.next_index == position_index @ ErrorCode::InvalidPositionIndex
+    )]
+    pub position_counter: Account<'info, PositionCounter>,
+
+    #[account(
+        mut,
+        constraint = token0_vault.key() == pool.token0_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token0_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = token1_vault.key() == pool.token1_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token1_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token0_account.mint == pool.token0_mint @ ErrorCode::InvalidInputMint
+    )]
+    pub owner_token0_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token1_account.mint == pool.token1_mint @ ErrorCode::InvalidInputMint
+    )]
+    pub owner_token1_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// The owner's position index, passed to list the new position in it.
+    #[account(
+        mut,
+        seeds = [b"owner_index".as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub owner_index: Option<Account<'info, OwnerPositionIndex>>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount_in: u64, amount_out_minimum: u64, sqrt_price_limit_q64: u128)]
+pub struct SwapExactInput<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        constraint = token0_vault.key() == pool.token0_vault @ ErrorCode::InvalidTokenVault,
+        constraint = token0_vault.mint == pool.token0_mint @ ErrorCode::InvalidVaultMint
+    )]
+    pub token0_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = token1_vault.key() == pool.token1_vault @ ErrorCode::InvalidTokenVault,
+        constraint = token1_vault.mint == pool.token1_mint @ ErrorCode::InvalidVaultMint
+    )]
+    pub token1_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user_token_in_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user_token_out_account: Account<'info, TokenAccount>,
+
+    pub user_authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    // The tick accounts crossed by the swap are passed through `remaining_accounts`,
+    // one `TickData` PDA per initialized tick, in the order the price reaches them.
+    // Any number of ticks may be supplied; they are validated in `Pool::swap`.
+    // Use `client::estimate_swap_tick_accounts` to find them off-chain.
+}
+
+#[derive(Accounts)]
+pub struct QuoteSwapExactInput<'info> {
+    pub pool: Account<'info, Pool>,
+    // The tick, tick array and tick bitmap accounts the swap would reach are passed
+    // through `remaining_accounts`, as for `SwapExactInput`, and only read.
+}
+
+#[derive(Accounts)]
+pub struct AssertQuote<'info> {
+    pub pool: Account<'info, Pool>,
+    // The tick, tick array and tick bitmap accounts the swap would reach are passed
+    // through `remaining_accounts`, as for `QuoteSwapExactInput`.
+}
+
+#[derive(Accounts)]
+#[instruction(amount_out: u64, amount_in_maximum: u64, sqrt_price_limit_q64: u128)]
+pub struct SwapExactOutput<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        constraint = token0_vault.key() == pool.token0_vault @ ErrorCode::InvalidTokenVault,
+        constraint = token0_vault.mint == pool.token0_mint @ ErrorCode::InvalidVaultMint
+    )]
+    pub token0_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = token1_vault.key() == pool.token1_vault @ ErrorCode::InvalidTokenVault,
+        constraint = token1_vault.mint == pool.token1_mint @ ErrorCode::InvalidVaultMint
+    )]
+    pub token1_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user_token_in_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user_token_out_account: Account<'info, TokenAccount>,
+
+    pub user_authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    // Tick accounts are passed through `remaining_accounts`, as for SwapExactInput.
+}
+
+#[derive(Accounts)]
+pub struct InitializeOrderBook<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = OrderBook::LEN,
+        seeds = [b"order_book".as_ref(), pool.key().as_ref()],
+        bump
+    )]
+    pub order_book: Account<'info, OrderBook>,
+
+    // Anchor checks the owner and discriminator, so the order book always points to a pool.
+    pub pool: Account<'info, Pool>,
+
+    #[account(address = pool.token0_mint @ ErrorCode::InvalidInputMint)]
+    pub token0_mint: Account<'info, Mint>,
+
+    #[account(address = pool.token1_mint @ ErrorCode::InvalidInputMint)]
+    pub token1_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"order_escrow".as_ref(), order_book.key().as_ref(), token0_mint.key().as_ref()],
+        bump,
+        token::mint = token0_mint,
+        token::authority = order_book,
+    )]
+    pub base_escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"order_escrow".as_ref(), order_book.key().as_ref(), token1_mint.key().as_ref()],
+        bump,
+        token::mint = token1_mint,
+        token::authority = order_book,
+    )]
+    pub quote_escrow: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceLimitOrder<'info> {
+    #[account(mut, has_one = base_escrow, has_one = quote_escrow)]
+    pub order_book: Account<'info, OrderBook>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Order::LEN,
+        seeds = [
+            b"order".as_ref(),
+            order_book.key().as_ref(),
+            order_book.order_count.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub order: Account<'info, Order>,
+
+    #[account(mut)]
+    pub base_escrow: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub quote_escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token0_account.mint == base_escrow.mint @ ErrorCode::InvalidInputMint
+    )]
+    pub owner_token0_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token1_account.mint == quote_escrow.mint @ ErrorCode::InvalidInputMint
+    )]
+    pub owner_token1_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct GetPriceBounds<'info> {
+    // The bounds are constants, so no state is read. The generated CPI client needs at
+    // least one account to carry the 'info lifetime.
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetSqrtPriceAtTick<'info> {
+    // Like `GetPriceBounds`, no state is read.
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FlashLoan<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        constraint = token0_vault.key() == pool.token0_vault @ ErrorCode::InvalidTokenVault,
+        constraint = token0_vault.mint == pool.token0_mint @ ErrorCode::InvalidVaultMint
+    )]
+    pub token0_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = token1_vault.key() == pool.token1_vault @ ErrorCode::InvalidTokenVault,
+        constraint = token1_vault.mint == pool.token1_mint @ ErrorCode::InvalidVaultMint
+    )]
+    pub token1_vault: Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = borrower_token0_account.mint == pool.token0_mint @ ErrorCode::InvalidInputMint)]
+    pub borrower_token0_account: Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = borrower_token1_account.mint == pool.token1_mint @ ErrorCode::InvalidInputMint)]
+    pub borrower_token1_account: Account<'info, TokenAccount>,
+
+    pub borrower: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    // The borrower's callback program is passed as the first remaining account, followed
+    // by the accounts its `flash_loan_callback` instruction expects.
+}
+
+#[derive(Accounts)]
+#[instruction(initial_sqrt_price_q64: u128, fee_rate: u16, tick_spacing: u16)]
+pub struct InitializePool<'info> {
+    #[account(
+        init,
+        payer = payer,
+        // Seeds for the Pool PDA, with the mints in canonical order (mint_a.key < mint_b.key)
+        // as `mint_b`'s constraint enforces.
+        // The fee rate is part of the seeds so that a pair can have one pool per fee tier.
+        seeds = [
+            b"pool".as_ref(),
+            mint_a.key().as_ref(), // Smaller address
+            mint_b.key().as_ref(), // Larger address
+            fee_rate.to_le_bytes().as_ref()
+        ],
+        bump,
+        space = Pool::LEN
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: mint_a and mint_b are validated by being used in PDA seeds & token::mint constraint.
+    pub mint_a: Account<'info, Mint>,
+    /// Must be larger than `mint_a`, so a pair's pools are always derived from the same seeds.
+    #[account(constraint = mint_a.key() < mint_b.key() @ ErrorCode::MintsNotInCanonicalOrder)]
+    pub mint_b: Account<'info, Mint>,
+
+    /// The factory config, which must enable the pool's fee tier. Recorded on the pool as
+    /// its `factory`.
+    #[account(seeds = [b"factory_config".as_ref()], bump = factory.bump)]
+    pub factory: Account<'info, FactoryConfig>,
+
+    #[account(
+        init,
+        payer = payer,
+        token::mint = mint_a,
+        token::authority = pool, // The `pool` account (PDA) is the authority
+    )]
+    pub pool_vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = payer,
+        token::mint = mint_b,
+        token::authority = pool, // The `pool` account (PDA) is the authority
+    )]
+    pub pool_vault_b: Account<'info, TokenAccount>,
+
+    // Lists the pools of every fee tier for this pair; created with the pair's first pool.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PoolRegistry::LEN,
+        seeds = [
+            b"pool_registry".as_ref(),
+            mint_a.key().as_ref(),
+            mint_b.key().as_ref()
+        ],
+        bump
+    )]
+    pub pool_registry: Account<'info, PoolRegistry>,
+
+    // The pair's record, created beforehand with `create_token_pair`.
+    #[account(
+        mut,
+        seeds = [
+            b"token_pair".as_ref(),
+            mint_a.key().as_ref(),
+            mint_b.key().as_ref()
+        ],
+        bump = token_pair.bump
+    )]
+    pub token_pair: Account<'info, TokenPair>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>, // Anchor uses Rent sysvar for `init` to ensure rent exemption.
+}
+
+#[derive(Accounts)]
+#[instruction(new_tick_lower_index: i32, new_tick_upper_index: i32)]
+pub struct UpdatePosition<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        // Constraint: Ensure the signer holds the position
+        // Or, for risk engine integration, the signer might be the risk engine's PDA
+        // For MVP, owner signing is simpler.
+        constraint = position.is_held_by(&owner.key(), position_token_account.as_deref()) @ ErrorCode::UnauthorizedAccess,
+        constraint = position.pool == pool.key() @ ErrorCode::InvalidPool
+    )]
+    pub position: Account<'info, PositionData>,
+
+    // Old Ticks (may or may not be needed depending on how you handle liquidity removal)
+    // For MVP, we might assume they are loaded if needed by modify_liquidity
+    // Or, for a simpler MVP, the modify_liquidity for removal might not need them if it just updates net liquidity.
+    // However, to correctly update liquidity_gross, they are needed.
+
+    #[account(
+        mut, // TickData needs to be mutable
+        seeds = [b"tick".as_ref(), pool.key().as_ref(), position.tick_lower_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub old_tick_lower: AccountLoader<'info, TickData>,
+
+    #[account(
+        mut, // TickData needs to be mutable
+        seeds = [b"tick".as_ref(), pool.key().as_ref(), position.tick_upper_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub old_tick_upper: AccountLoader<'info, TickData>,
+
+    // New Ticks
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + TickData::LEN,
+        seeds = [b"tick".as_ref(), pool.key().as_ref(), new_tick_lower_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub new_tick_lower: AccountLoader<'info, TickData>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + TickData::LEN,
+        seeds = [b"tick".as_ref(), pool.key().as_ref(), new_tick_upper_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub new_tick_upper: AccountLoader<'info, TickData>,
+
+    #[account(
+        mut,
+        constraint = token0_vault.key() == pool.token0_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token0_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = token1_vault.key() == pool.token1_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token1_vault: Account<'info, TokenAccount>,
+
+    /// Receives the token0 the new range cannot use.
+    #[account(
+        mut,
+        constraint = owner_token0_account.mint == pool.token0_mint @ ErrorCode::InvalidOutputMint
+    )]
+    pub owner_token0_account: Account<'info, TokenAccount>,
+
+    /// Receives the token1 the new range cannot use.
+    #[account(
+        mut,
+        constraint = owner_token1_account.mint == pool.token1_mint @ ErrorCode::InvalidOutputMint
+    )]
+    pub owner_token1_account: Account<'info, TokenAccount>,
+
+    // Signer: Could be the position owner or the risk engine PDA
+    pub owner: Signer<'info>, // For MVP, position owner triggers
+
+    #[account(mut)]
+    pub payer: Signer<'info>, // To pay for new tick accounts if created
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+
+    /// The owner's token account holding the position NFT. Only needed for positions
+    /// minted with `mint_position_nft`.
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(liquidity_delta: u128)]
+pub struct DecreaseLiquidity<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        constraint = position.is_held_by(&owner.key(), position_token_account.as_deref()) @ ErrorCode::UnauthorizedAccess,
+        constraint = position.pool == pool.key() @ ErrorCode::InvalidPool
+    )]
+    pub position: Account<'info, PositionData>,
+
+    #[account(
+        mut,
+        seeds = [b"tick".as_ref(), pool.key().as_ref(), position.tick_lower_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub tick_lower: AccountLoader<'info, TickData>,
+
+    #[account(
+        mut,
+        seeds = [b"tick".as_ref(), pool.key().as_ref(), position.tick_upper_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub tick_upper: AccountLoader<'info, TickData>,
+
+    #[account(
+        mut,
+        constraint = token0_vault.key() == pool.token0_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token0_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = token1_vault.key() == pool.token1_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token1_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token0_account.mint == pool.token0_mint @ ErrorCode::InvalidOutputMint
+    )]
+    pub owner_token0_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token1_account.mint == pool.token1_mint @ ErrorCode::InvalidOutputMint
+    )]
+    pub owner_token1_account: Account<'info, TokenAccount>,
+
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// The owner's token account holding the position NFT. Only needed for positions
+    /// minted with `mint_position_nft`.
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct CollectFees<'info> {
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        constraint = position.is_held_by(&owner.key(), position_token_account.as_deref()) @ ErrorCode::UnauthorizedAccess,
+        constraint = position.pool == pool.key() @ ErrorCode::InvalidPool
+    )]
+    pub position: Account<'info, PositionData>,
+
+    #[account(
+        seeds = [b"tick".as_ref(), pool.key().as_ref(), position.tick_lower_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub tick_lower: AccountLoader<'info, TickData>,
+
+    #[account(
+        seeds = [b"tick".as_ref(), pool.key().as_ref(), position.tick_upper_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub tick_upper: AccountLoader<'info, TickData>,
+
+    #[account(
+        mut,
+        constraint = token0_vault.key() == pool.token0_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token0_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = token1_vault.key() == pool.token1_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token1_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token0_account.mint == pool.token0_mint @ ErrorCode::InvalidOutputMint
+    )]
+    pub owner_token0_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token1_account.mint == pool.token1_mint @ ErrorCode::InvalidOutputMint
+    )]
+    pub owner_token1_account: Account<'info, TokenAccount>,
+
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// The owner's token account holding the position NFT. Only needed for positions
+    /// minted with `mint_position_nft`.
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        constraint = position.is_held_by(&owner.key(), position_token_account.as_deref()) @ ErrorCode::UnauthorizedAccess,
+        constraint = position.pool == pool.key() @ ErrorCode::InvalidPool,
+        close = rent_receiver
+    )]
+    pub position: Account<'info, PositionData>,
+
+    #[account(
+        mut,
+        seeds = [b"tick".as_ref(), pool.key().as_ref(), position.tick_lower_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub tick_lower: Option<AccountLoader<'info, TickData>>,
+
+    #[account(
+        mut,
+        seeds = [b"tick".as_ref(), pool.key().as_ref(), position.tick_upper_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub tick_upper: Option<AccountLoader<'info, TickData>>,
+
+    pub owner: Signer<'info>,
+
+    /// CHECK: Any account chosen by the owner to receive the reclaimed rent.
+    #[account(mut)]
+    pub rent_receiver: UncheckedAccount<'info>,
+
+    /// The owner's token account holding the position NFT. Only needed for positions
+    /// minted with `mint_position_nft`.
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// The position index of the account that minted the position. Only needed if the
+    /// position is listed in it.
+    #[account(
+        mut,
+        seeds = [b"owner_index".as_ref(), position.owner.as_ref()],
+        bump
+    )]
+    pub owner_index: Option<Account<'info, OwnerPositionIndex>>,
+
+    /// The listed position linking to this one in `owner_index`. Only needed if the
+    /// position is listed but is not the index's head.
+    #[account(mut)]
+    pub previous_indexed_position: Option<Account<'info, PositionData>>,
+}
+
+#[derive(Accounts)]
+#[instruction(start_tick_index: i32)]
+pub struct InitializeTickArray<'info> {
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + TickArray::LEN,
+        seeds = [
+            b"tick_array".as_ref(),
+            pool.key().as_ref(),
+            start_tick_index.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub tick_array: AccountLoader<'info, TickArray>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    liquidity_amount_desired: u128,
+    position_index: u64
+)]
+pub struct MintPositionWithTickArrays<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = PositionData::LEN,
+        seeds = [
+            b"position".as_ref(),
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            tick_lower_index.to_le_bytes().as_ref(),
+            tick_upper_index.to_le_bytes().as_ref(),
+            position_index.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub position: Account<'info, PositionData>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array".as_ref(),
+            pool.key().as_ref(),
+            TickArray::start_tick_index(tick_lower_index, pool.bitmap_tick_spacing).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub tick_array_lower: AccountLoader<'info, TickArray>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array".as_ref(),
+            pool.key().as_ref(),
+            TickArray::start_tick_index(tick_upper_index, pool.bitmap_tick_spacing).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub tick_array_upper: AccountLoader<'info, TickArray>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    /// Counts the owner's positions in the pool and supplies the new position's index.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PositionCounter::LEN,
+        seeds = [b"position_counter".as_ref(), pool.key().as_ref(), owner.key().as_ref()],
+        bump,
+        constraint = position_counter.next_index == position_index @ ErrorCode::InvalidPositionIndex
+    )]
+    pub position_counter: Account<'info, PositionCounter>,
+
+    #[account(
+        mut,
+        constraint = token0_vault.key() == pool.token0_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token0_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = token1_vault.key() == pool.token1_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token1_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token0_account.mint == pool.token0_mint @ ErrorCode::InvalidInputMint
+    )]
+    pub owner_token0_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token1_account.mint == pool.token1_mint @ ErrorCode::InvalidInputMint
+    )]
+    pub owner_token1_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// The owner's position index, passed to list the new position in it.
+    #[account(
+        mut,
+        seeds = [b"owner_index".as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub owner_index: Option<Account<'info, OwnerPositionIndex>>,
+}
+
+#[derive(Accounts)]
+#[instruction(liquidity_delta: u128)]
+pub struct DecreaseLiquidityWithTickArrays<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        constraint = position.is_held_by(&owner.key(), position_token_account.as_deref()) @ ErrorCode::UnauthorizedAccess,
+        constraint = position.pool == pool.key() @ ErrorCode::InvalidPool
+    )]
+    pub position: Account<'info, PositionData>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array".as_ref(),
+            pool.key().as_ref(),
+            TickArray::start_tick_index(position.tick_lower_index, pool.bitmap_tick_spacing).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub tick_array_lower: AccountLoader<'info, TickArray>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array".as_ref(),
+            pool.key().as_ref(),
+            TickArray::start_tick_index(position.tick_upper_index, pool.bitmap_tick_spacing).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub tick_array_upper: AccountLoader<'info, TickArray>,
+
+    #[account(
+        mut,
+        constraint = token0_vault.key() == pool.token0_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token0_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = token1_vault.key() == pool.token1_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token1_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token0_account.mint == pool.token0_mint @ ErrorCode::InvalidOutputMint
+    )]
+    pub owner_token0_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token1_account.mint == pool.token1_mint @ ErrorCode::InvalidOutputMint
+    )]
+    pub owner_token1_account: Account<'info, TokenAccount>,
+
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// The owner's token account holding the position NFT. Only needed for positions
+    /// minted with `mint_position_nft`.
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct CollectFeesWithTickArrays<'info> {
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        constraint = position.is_held_by(&owner.key(), position_token_account.as_deref()) @ ErrorCode::UnauthorizedAccess,
+        constraint = position.pool == pool.key() @ ErrorCode::InvalidPool
+    )]
+    pub position: Account<'info, PositionData>,
+
+    #[account(
+        seeds = [
+            b"tick_array".as_ref(),
+            pool.key().as_ref(),
+            TickArray::start_tick_index(position.tick_lower_index, pool.bitmap_tick_spacing).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub tick_array_lower: AccountLoader<'info, TickArray>,
+
+    #[account(
+        seeds = [
+            b"tick_array".as_ref(),
+            pool.key().as_ref(),
+            TickArray::start_tick_index(position.tick_upper_index, pool.bitmap_tick_spacing).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub tick_array_upper: AccountLoader<'info, TickArray>,
+
+    #[account(
+        mut,
+        constraint = token0_vault.key() == pool.token0_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token0_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = token1_vault.key() == pool.token1_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token1_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token0_account.mint == pool.token0_mint @ ErrorCode::InvalidOutputMint
+    )]
+    pub owner_token0_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token1_account.mint == pool.token1_mint @ ErrorCode::InvalidOutputMint
+    )]
+    pub owner_token1_account: Account<'info, TokenAccount>,
+
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// The owner's token account holding the position NFT. Only needed for positions
+    /// minted with `mint_position_nft`.
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct SetProtocolFee<'info> {
+    #[account(
+        mut,
+        constraint = pool.factory == factory_config.key() @ ErrorCode::InvalidFactoryConfig
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// The factory config the pool was created by. Its authority administers the pool.
+    #[account(
+        seeds = [b"factory_config".as_ref()],
+        bump = factory_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CollectProtocolFees<'info> {
+    #[account(
+        mut,
+        constraint = pool.factory == factory_config.key() @ ErrorCode::InvalidFactoryConfig
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// The factory config the pool was created by. Its authority administers the pool.
+    #[account(
+        seeds = [b"factory_config".as_ref()],
+        bump = factory_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    #[account(
+        mut,
+        constraint = token0_vault.key() == pool.token0_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token0_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = token1_vault.key() == pool.token1_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token1_vault: Account<'info, TokenAccount>,
+
+    #[account(address = pool.token0_mint @ ErrorCode::InvalidOutputMint)]
+    pub token0_mint: Account<'info, Mint>,
+
+    #[account(address = pool.token1_mint @ ErrorCode::InvalidOutputMint)]
+    pub token1_mint: Account<'info, Mint>,
+
+    /// The authority's token0 associated token account, created if missing.
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = token0_mint,
+        associated_token::authority = authority
+    )]
+    pub token0_destination: Account<'info, TokenAccount>,
+
+    /// The authority's token1 associated token account, created if missing.
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = token1_mint,
+        associated_token::authority = authority
+    )]
+    pub token1_destination: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetTwap<'info> {
+    pub pool: Account<'info, Pool>,
+}
+
+#[derive(Accounts)]
+pub struct IsPositionInRange<'info> {
+    #[account(constraint = position.pool == pool.key() @ ErrorCode::InvalidPool)]
+    pub position: Account<'info, PositionData>,
+    pub pool: Account<'info, Pool>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteMatch<'info> {
+    #[account(mut, has_one = base_escrow, has_one = quote_escrow)]
+    pub order_book: Account<'info, OrderBook>,
+
+    #[account(mut, has_one = order_book)]
+    pub bid: Account<'info, Order>,
+
+    #[account(mut, has_one = order_book)]
+    pub ask: Account<'info, Order>,
+
+    #[account(mut)]
+    pub base_escrow: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub quote_escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = bidder_token0_account.mint == base_escrow.mint @ ErrorCode::InvalidOutputMint,
+        constraint = bidder_token0_account.owner == bid.owner @ ErrorCode::UnauthorizedAccess
+    )]
+    pub bidder_token0_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = bidder_token1_account.mint == quote_escrow.mint @ ErrorCode::InvalidOutputMint,
+        constraint = bidder_token1_account.owner == bid.owner @ ErrorCode::UnauthorizedAccess
+    )]
+    pub bidder_token1_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = asker_token1_account.mint == quote_escrow.mint @ ErrorCode::InvalidOutputMint,
+        constraint = asker_token1_account.owner == ask.owner @ ErrorCode::UnauthorizedAccess
+    )]
+    pub asker_token1_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Only receives the bid's rent once it is filled; must be the bid's owner.
+    #[account(mut, address = bid.owner @ ErrorCode::UnauthorizedAccess)]
+    pub bid_owner: UncheckedAccount<'info>,
+
+    /// CHECK: Only receives the ask's rent once it is filled; must be the ask's owner.
+    #[account(mut, address = ask.owner @ ErrorCode::UnauthorizedAccess)]
+    pub ask_owner: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    #[account(mut, has_one = base_escrow, has_one = quote_escrow)]
+    pub order_book: Account<'info, OrderBook>,
+
+    #[account(mut, has_one = order_book, has_one = owner @ ErrorCode::UnauthorizedAccess)]
+    pub order: Account<'info, Order>,
+
+    #[account(mut)]
+    pub base_escrow: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub quote_escrow: Account<'info, TokenAccount>,
+
+    // Token1 for a bid, token0 for an ask; the handler checks the mint against the side.
+    #[account(
+        mut,
+        constraint = owner_token_account.owner == order.owner @ ErrorCode::UnauthorizedAccess
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Only receives the order's rent; must be the order's owner.
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    // The owner, or anyone once the order has expired.
+    #[account(mut)]
+    pub canceller: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(word_index: i16)]
+pub struct InitializeBitmapWord<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + TickBitmap::LEN,
+        seeds = [
+            b"tick_bitmap".as_ref(),
+            pool.key().as_ref(),
+            word_index.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub tick_bitmap: AccountLoader<'info, TickBitmap>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateTokenPair<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = TokenPair::LEN,
+        seeds = [
+            b"token_pair".as_ref(),
+            mint_a.key().as_ref(),
+            mint_b.key().as_ref()
+        ],
+        bump
+    )]
+    pub token_pair: Account<'info, TokenPair>,
+
+    pub mint_a: Account<'info, Mint>,
+    pub mint_b: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SwapMultiHop<'info> {
+    #[account(mut)]
+    pub user_token_in_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user_token_out_account: Account<'info, TokenAccount>,
+
+    pub user_authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    // The pools, vaults and tick accounts of every hop are passed through
+    // `remaining_accounts`; see `swap_multi_hop_handler`.
+}
+
+#[derive(Accounts)]
+pub struct SetFeeRate<'info> {
+    #[account(
+        mut,
+        constraint = pool.factory == factory_config.key() @ ErrorCode::InvalidFactoryConfig
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// The factory config the pool was created by. Its authority administers the pool.
+    #[account(
+        seeds = [b"factory_config".as_ref()],
+        bump = factory_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateTickSpacing<'info> {
+    #[account(
+        mut,
+        constraint = pool.factory == factory_config.key() @ ErrorCode::InvalidFactoryConfig
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// The factory config the pool was created by. Its authority administers the pool.
+    #[account(
+        seeds = [b"factory_config".as_ref()],
+        bump = factory_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePosition<'info> {
+    #[account(
+        constraint = pool.factory == factory_config.key() @ ErrorCode::InvalidFactoryConfig
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// The factory config the pool was created by. Its authority administers the pool.
+    #[account(
+        seeds = [b"factory_config".as_ref()],
+        bump = factory_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    /// CHECK: A position of the pool under an earlier layout, which Anchor cannot
+    /// deserialize; read and checked by the handler.
+    #[account(mut, owner = crate::ID)]
+    pub position: UncheckedAccount<'info>,
+
+    /// The position's lower tick, checked against its PDA by the handler.
+    pub tick_lower: AccountLoader<'info, TickData>,
+
+    /// The position's upper tick, checked against its PDA by the handler.
+    pub tick_upper: AccountLoader<'info, TickData>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResizePoolAccount<'info> {
+    #[account(
+        mut,
+        constraint = pool.factory == factory_config.key() @ ErrorCode::InvalidFactoryConfig
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// The factory config the pool was created by. Its authority administers the pool.
+    #[account(
+        seeds = [b"factory_config".as_ref()],
+        bump = factory_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    /// The factory config authority, which pays for a larger account and is refunded for a
+    /// smaller one.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResizeTickArray<'info> {
+    #[account(
+        constraint = pool.factory == factory_config.key() @ ErrorCode::InvalidFactoryConfig
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// The factory config the pool was created by. Its authority administers the pool.
+    #[account(
+        seeds = [b"factory_config".as_ref()],
+        bump = factory_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    /// A tick array of the pool, checked by the handler.
+    #[account(mut)]
+    pub tick_array: AccountLoader<'info, TickArray>,
+
+    /// The factory config authority, which pays for a larger account and is refunded for a
+    /// smaller one.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeLiquidityHistogram<'info> {
+    #[account(
+        constraint = pool.factory == factory_config.key() @ ErrorCode::InvalidFactoryConfig
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// The factory config the pool was created by. Its authority administers the pool.
+    #[account(
+        seeds = [b"factory_config".as_ref()],
+        bump = factory_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + LiquidityHistogram::LEN,
+        seeds = [b"liquidity_histogram".as_ref(), pool.key().as_ref()],
+        bump
+    )]
+    pub liquidity_histogram: AccountLoader<'info, LiquidityHistogram>,
+
+    /// The factory config authority, which pays for the histogram account.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefreshLiquidityHistogram<'info> {
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"liquidity_histogram".as_ref(), pool.key().as_ref()],
+        bump
+    )]
+    pub liquidity_histogram: AccountLoader<'info, LiquidityHistogram>,
+    // The tick, tick array and tick bitmap accounts covering the histogram's range are
+    // passed through `remaining_accounts` and only read.
+}
+
+#[derive(Accounts)]
+pub struct SetPoolPaused<'info> {
+    #[account(
+        mut,
+        constraint = pool.factory == factory_config.key() @ ErrorCode::InvalidFactoryConfig
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// The factory config the pool was created by. Its authority administers the pool.
+    #[account(
+        seeds = [b"factory_config".as_ref()],
+        bump = factory_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetOracle<'info> {
+    #[account(mut, has_one = oracle_authority @ ErrorCode::UnauthorizedAccess)]
+    pub pool: Account<'info, Pool>,
+
+    pub oracle_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetOracleConfig<'info> {
+    #[account(
+        mut,
+        constraint = pool.factory == factory_config.key() @ ErrorCode::InvalidFactoryConfig
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// The factory config the pool was created by. Its authority administers the pool.
+    #[account(
+        seeds = [b"factory_config".as_ref()],
+        bump = factory_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
 
-    /// Swaps an exact amount of an input token for an output token.
-    ///
-    /// # Arguments
-    ///
-    /// * `ctx` - The context containing all necessary accounts.
-    /// * `amount_in` - The exact amount of the input token to swap.
-    /// * `amount_out_minimum` - The minimum amount of the output token the swapper is willing to receive.
-    /// * `sqrt_price_limit_q64` - A price limit for the swap. If the price moves beyond this limit,
-    ///                            the swap will not consume the entire input amount.
-    pub fn swap_exact_input_handler<'info>(
-        ctx: Context<'_, '_, '_, 'info, SwapExactInput<'info>>,
-        amount_in: u64,
-        amount_out_minimum: u64,
-        sqrt_price_limit_q64: u128,
-    ) -> Result<()> {
-        instructions::swap_exact_input::handler(
-            ctx,
-            amount_in,
-            amount_out_minimum,
-            sqrt_price_limit_q64,
-        )
-    }
+    pub authority: Signer<'info>,
+}
 
-    /// Updates an existing concentrated liquidity position's tick boundaries.
-    ///
-    /// # Arguments
-    ///
-    /// * `ctx` - The context containing all necessary accounts.
-    /// * `new_tick_lower_index` - The new lower tick boundary for the position.
-    /// * `new_tick_upper_index` - The new upper tick boundary for the position.
-    pub fn update_position_handler(
-        ctx: Context<UpdatePosition>,
-        new_tick_lower_index: i32,
-        new_tick_upper_index: i32,
-    ) -> Result<()> {
-        instructions::update_position::handler(ctx, new_tick_lower_index, new_tick_upper_index)
-    }
+#[derive(Accounts)]
+pub struct InitializeFactoryConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = FactoryConfig::LEN,
+        seeds = [b"factory_config".as_ref()],
+        bump
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// The program's ProgramData account, naming the upgrade authority.
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = anchor_lang::solana_program::bpf_loader_upgradeable::ID,
+        constraint = program_data.upgrade_authority_address == Some(authority.key())
+            @ ErrorCode::UnauthorizedAccess
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetProtocolFeeCurve<'info> {
+    #[account(
+        mut,
+        seeds = [b"factory_config".as_ref()],
+        bump = factory_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EnableFeeTier<'info> {
+    #[account(
+        mut,
+        seeds = [b"factory_config".as_ref()],
+        bump = factory_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefreshProtocolShare<'info> {
+    #[account(
+        mut,
+        constraint = pool.factory == factory_config.key() @ ErrorCode::InvalidFactoryConfig
+    )]
+    pub pool: Account<'info, Pool>,
 
-    // Potentially add decrease_liquidity_handler and collect_fees_handler for MVP+
+    #[account(seeds = [b"factory_config".as_ref()], bump = factory_config.bump)]
+    pub factory_config: Account<'info, FactoryConfig>,
 }
 
 #[derive(Accounts)]
 #[instruction(tick_lower_index: i32, tick_upper_index: i32)]
-pub struct MintPosition<'info> {
+pub struct MintPositionNft<'info> {
     #[account(mut)]
     pub pool: Account<'info, Pool>,
 
@@ -121,21 +2494,34 @@ pub struct MintPosition<'info> {
         init,
         payer = payer,
         space = PositionData::LEN,
-        seeds = [
-            b"position".as_ref(),
-            pool.key().as_ref(),
-            owner.key().as_ref(),
-            tick_lower_index.to_le_bytes().as_ref(),
-            tick_upper_index.to_le_bytes().as_ref()
-        ],
+        seeds = [b"position".as_ref(), position_mint.key().as_ref()],
         bump
     )]
     pub position: Account<'info, PositionData>,
 
+    /// Mint of the position NFT, a new keypair. The position is its mint authority until
+    /// the single token is minted.
+    #[account(
+        init,
+        payer = payer,
+        mint::decimals = 0,
+        mint::authority = position
+    )]
+    pub position_mint: Account<'info, Mint>,
+
+    /// The owner's associated token account for the position NFT.
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = position_mint,
+        associated_token::authority = owner
+    )]
+    pub position_token_account: Account<'info, TokenAccount>,
+
     #[account(
         init_if_needed,
         payer = payer,
-        space = TickData::LEN,
+        space = 8 + TickData::LEN,
         seeds = [
             b"tick".as_ref(),
             pool.key().as_ref(),
@@ -148,7 +2534,7 @@ pub struct MintPosition<'info> {
     #[account(
         init_if_needed,
         payer = payer,
-        space = TickData::LEN,
+        space = 8 + TickData::LEN,
         seeds = [
             b"tick".as_ref(),
             pool.key().as_ref(),
@@ -158,174 +2544,189 @@ pub struct MintPosition<'info> {
     )]
     pub tick_upper: AccountLoader<'info, TickData>,
 
-    #[account(mut)]
     pub owner: Signer<'info>,
 
     #[account(mut)]
     pub payer: Signer<'info>,
 
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>, // Needed for init and init_if_needed
-}
-
-#[derive(Accounts)]
-#[instruction(amount_in: u64, amount_out_minimum: u64, sqrt_price_limit_q64: u128)]
-pub struct SwapExactInput<'info> {
-    #[account(mut)]
-    pub pool: Account<'info, Pool>,
+    pub rent: Sysvar<'info, Rent>,
 
     #[account(
         mut,
-        constraint = token0_vault.key() == pool.token0_vault @ ErrorCode::InvalidTokenVault,
-        constraint = token0_vault.mint == pool.token0_mint @ ErrorCode::InvalidVaultMint
+        constraint = token0_vault.key() == pool.token0_vault @ ErrorCode::InvalidTokenVault
     )]
     pub token0_vault: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        constraint = token1_vault.key() == pool.token1_vault @ ErrorCode::InvalidTokenVault,
-        constraint = token1_vault.mint == pool.token1_mint @ ErrorCode::InvalidVaultMint
+        constraint = token1_vault.key() == pool.token1_vault @ ErrorCode::InvalidTokenVault
     )]
     pub token1_vault: Account<'info, TokenAccount>,
 
-    #[account(mut)]
-    pub user_token_in_account: Account<'info, TokenAccount>,
-
-    #[account(mut)]
-    pub user_token_out_account: Account<'info, TokenAccount>,
-
-    pub user_authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = owner_token0_account.mint == pool.token0_mint @ ErrorCode::InvalidInputMint
+    )]
+    pub owner_token0_account: Account<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    #[account(
+        mut,
+        constraint = owner_token1_account.mint == pool.token1_mint @ ErrorCode::InvalidInputMint
+    )]
+    pub owner_token1_account: Account<'info, TokenAccount>,
 
-    // For an MVP, pass a fixed number of tick accounts.
-    // The client is responsible for providing the correct tick accounts
-    // that are expected to be crossed during this swap.
-    // These should be PDAs for initialized ticks.
-    // The Pool::swap method will need to be able to load() these.
-    // More advanced: use remaining_accounts to pass a dynamic list.
-    // Note: The actual number of tick accounts needed depends on the swap's price impact.
-    // For a hackathon/MVP, 3-5 might be a reasonable fixed number to start with.
-    // Ensure these are ordered correctly if Pool::swap expects a certain order.
-    pub tick_account_0: Option<AccountLoader<'info, TickData>>,
-    pub tick_account_1: Option<AccountLoader<'info, TickData>>,
-    pub tick_account_2: Option<AccountLoader<'info, TickData>>,
-    // Add more if needed, e.g., tick_account_3, tick_account_4
+    /// The owner's position index, passed to list the new position in it.
+    #[account(
+        mut,
+        seeds = [b"owner_index".as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub owner_index: Option<Account<'info, OwnerPositionIndex>>,
 }
 
 #[derive(Accounts)]
-#[instruction(initial_sqrt_price_q64: u128, fee_rate: u16, tick_spacing: u16)]
-pub struct InitializePool<'info> {
+#[instruction(
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    amount0_desired: u64,
+    amount1_desired: u64,
+    amount0_min: u64,
+    amount1_min: u64
+)]
+pub struct MintPositionWithAmounts<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    /// Counts the owner's positions in the pool and supplies the new position's index.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PositionCounter::LEN,
+        seeds = [b"position_counter".as_ref(), pool.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position_counter: Account<'info, PositionCounter>,
+
     #[account(
         init,
         payer = payer,
-        // Seeds for the Pool PDA.
-        // IMPORTANT: mint_a and mint_b keys MUST be provided in canonical order (e.g., mint_a.key < mint_b.key).
-        // The client is responsible for ensuring this order before calling the instruction.
+        space = PositionData::LEN,
         seeds = [
-            b"pool".as_ref(),
-            mint_a.key().as_ref(), // Smaller address
-            mint_b.key().as_ref()  // Larger address
+            b"position".as_ref(),
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            tick_lower_index.to_le_bytes().as_ref(),
+            tick_upper_index.to_le_bytes().as_ref(),
+            position_counter.next_index.to_le_bytes().as_ref()
         ],
-        bump,
-        space = Pool::LEN
+        bump
     )]
-    pub pool: Account<'info, Pool>,
-
-    /// CHECK: mint_a and mint_b are validated by being used in PDA seeds & token::mint constraint.
-    /// Client must ensure mint_a.key() < mint_b.key() for canonical pool PDA.
-    pub mint_a: Account<'info, Mint>,
-    pub mint_b: Account<'info, Mint>,
-
-    /// The factory that created this pool.
-    /// For MVP, this can be any account, e.g., payer or system_program.
-    /// CHECK: This account is unchecked
-    /// CHECK: For MVP, factory is not strictly validated beyond being a provided account.
-    pub factory: UncheckedAccount<'info>,
+    pub position: Account<'info, PositionData>,
 
     #[account(
-        init,
+        init_if_needed,
         payer = payer,
-        token::mint = mint_a,
-        token::authority = pool, // The `pool` account (PDA) is the authority
+        space = 8 + TickData::LEN,
+        seeds = [
+            b"tick".as_ref(),
+            pool.key().as_ref(),
+            tick_lower_index.to_le_bytes().as_ref()
+        ],
+        bump
     )]
-    pub pool_vault_a: Account<'info, TokenAccount>,
+    pub tick_lower: AccountLoader<'info, TickData>,
 
     #[account(
-        init,
+        init_if_needed,
         payer = payer,
-        token::mint = mint_b,
-        token::authority = pool, // The `pool` account (PDA) is the authority
+        space = 8 + TickData::LEN,
+        seeds = [
+            b"tick".as_ref(),
+            pool.key().as_ref(),
+            tick_upper_index.to_le_bytes().as_ref()
+        ],
+        bump
     )]
-    pub pool_vault_b: Account<'info, TokenAccount>,
+    pub tick_upper: AccountLoader<'info, TickData>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
 
     #[account(mut)]
     pub payer: Signer<'info>,
 
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>, // Anchor uses Rent sysvar for `init` to ensure rent exemption.
-}
-
-#[derive(Accounts)]
-#[instruction(new_tick_lower_index: i32, new_tick_upper_index: i32)]
-pub struct UpdatePosition<'info> {
-    #[account(mut)]
-    pub pool: Account<'info, Pool>,
+    pub rent: Sysvar<'info, Rent>, // Needed for init and init_if_needed
 
     #[account(
         mut,
-        // Constraint: Ensure the signer is the owner of the position
-        // Or, for risk engine integration, the signer might be the risk engine's PDA
-        // For MVP, owner signing is simpler.
-        has_one = owner 
+        constraint = token0_vault.key() == pool.token0_vault @ ErrorCode::InvalidTokenVault
     )]
-    pub position: Account<'info, PositionData>,
+    pub token0_vault: Account<'info, TokenAccount>,
 
-    // Old Ticks (may or may not be needed depending on how you handle liquidity removal)
-    // For MVP, we might assume they are loaded if needed by modify_liquidity
-    // Or, for a simpler MVP, the modify_liquidity for removal might not need them if it just updates net liquidity.
-    // However, to correctly update liquidity_gross, they are needed.
+    #[account(
+        mut,
+        constraint = token1_vault.key() == pool.token1_vault @ ErrorCode::InvalidTokenVault
+    )]
+    pub token1_vault: Account<'info, TokenAccount>,
 
     #[account(
-        mut, // TickData needs to be mutable
-        seeds = [b"tick".as_ref(), pool.key().as_ref(), position.tick_lower_index.to_le_bytes().as_ref()],
-        bump
+        mut,
+        constraint = owner_token0_account.mint == pool.token0_mint @ ErrorCode::InvalidInputMint
     )]
-    pub old_tick_lower: AccountLoader<'info, TickData>,
+    pub owner_token0_account: Account<'info, TokenAccount>,
 
     #[account(
-        mut, // TickData needs to be mutable
-        seeds = [b"tick".as_ref(), pool.key().as_ref(), position.tick_upper_index.to_le_bytes().as_ref()],
-        bump
+        mut,
+        constraint = owner_token1_account.mint == pool.token1_mint @ ErrorCode::InvalidInputMint
     )]
-    pub old_tick_upper: AccountLoader<'info, TickData>,
+    pub owner_token1_account: Account<'info, TokenAccount>,
 
-    // New Ticks
+    pub token_program: Program<'info, Token>,
+
+    /// The owner's position index, passed to list the new position in it.
     #[account(
-        init_if_needed,
-        payer = payer,
-        space = TickData::LEN,
-        seeds = [b"tick".as_ref(), pool.key().as_ref(), new_tick_lower_index.to_le_bytes().as_ref()],
+        mut,
+        seeds = [b"owner_index".as_ref(), owner.key().as_ref()],
         bump
     )]
-    pub new_tick_lower: AccountLoader<'info, TickData>,
+    pub owner_index: Option<Account<'info, OwnerPositionIndex>>,
+}
 
+#[derive(Accounts)]
+pub struct RegisterOwnerIndex<'info> {
     #[account(
-        init_if_needed,
+        init,
         payer = payer,
-        space = TickData::LEN,
-        seeds = [b"tick".as_ref(), pool.key().as_ref(), new_tick_upper_index.to_le_bytes().as_ref()],
+        space = OwnerPositionIndex::LEN,
+        seeds = [b"owner_index".as_ref(), owner.key().as_ref()],
         bump
     )]
-    pub new_tick_upper: AccountLoader<'info, TickData>,
+    pub owner_index: Account<'info, OwnerPositionIndex>,
 
-    // Signer: Could be the position owner or the risk engine PDA
-    pub owner: Signer<'info>, // For MVP, position owner triggers
+    pub owner: Signer<'info>,
 
     #[account(mut)]
-    pub payer: Signer<'info>, // To pay for new tick accounts if created
+    pub payer: Signer<'info>,
 
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct MatchOrdersBatch<'info> {
+    #[account(mut, has_one = base_escrow, has_one = quote_escrow)]
+    pub order_book: Account<'info, OrderBook>,
+
+    #[account(mut)]
+    pub base_escrow: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub quote_escrow: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    // The orders and the accounts they pay out to are passed through `remaining_accounts`;
+    // see `match_orders_batch_handler`.
 }
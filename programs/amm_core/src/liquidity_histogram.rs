@@ -0,0 +1,218 @@
+use crate::constants::{MAX_TICK, MIN_TICK};
+use crate::errors::ErrorCode;
+/// A coarse snapshot of a pool's liquidity around its current price.
+///
+/// Reading the liquidity at prices away from the current one means walking the pool's
+/// initialized ticks, which takes one account per tick. A `LiquidityHistogram` sums that
+/// walk into `HISTOGRAM_BUCKETS` buckets once, so programs sizing positions or estimating
+/// depth read a single account instead. Anyone can refresh it with the pool's ticks; the
+/// refresh time lets readers refuse a snapshot older than they can tolerate.
+use anchor_lang::prelude::*;
+
+/// Number of buckets in a `LiquidityHistogram`.
+pub const HISTOGRAM_BUCKETS: usize = 64;
+
+/// Bucket of the current tick after a refresh. Buckets below it cover lower prices.
+pub const HISTOGRAM_CURRENT_BUCKET: usize = HISTOGRAM_BUCKETS / 2;
+
+/// Active liquidity of a pool, averaged over `HISTOGRAM_BUCKETS` equal tick ranges
+/// around the price of the last refresh.
+///
+/// Accounts of this type are PDAs derived from the pool,
+/// `[b"liquidity_histogram", pool]`.
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct LiquidityHistogram {
+    /// Entry `i` is the liquidity active over ticks
+    /// `[lower_tick + i * bucket_ticks, lower_tick + (i + 1) * bucket_ticks)`, averaged
+    /// over the ticks of the bucket.
+    pub liquidity: [u128; HISTOGRAM_BUCKETS], // offset 0
+    /// pool pubkey
+    pub pool: Pubkey, // offset 1024
+    /// Unix timestamp of the last refresh, `i64::MIN` until the first one
+    pub updated_at: i64, // offset 1056
+    /// first tick of the lowest bucket
+    pub lower_tick: i32, // offset 1064
+    /// width of each bucket, a multiple of the pool's tick spacing
+    pub bucket_ticks: u32, // offset 1068..1072
+}
+
+impl LiquidityHistogram {
+    /// Total size of the fields: 64 * 16 (liquidity) + 32 (pool) + 8 (updated_at) + 4 (lower_tick) + 4 (bucket_ticks) = 1072 bytes.
+    /// Anchor's `#[account(zero_copy)]` handles the 8-byte discriminator separately.
+    pub const LEN: usize = HISTOGRAM_BUCKETS * 16 + 32 + 8 + 4 + 4;
+
+    /// Derives the PDA of the liquidity histogram of `pool`.
+    pub fn address(pool: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"liquidity_histogram".as_ref(), pool.as_ref()],
+            &crate::ID,
+        )
+        .0
+    }
+
+    /// Initializes an empty histogram that has never been refreshed.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The pubkey of the pool this histogram belongs to.
+    /// * `bucket_ticks` - The width of each bucket. Must be a non-zero multiple of
+    ///   `tick_spacing`, and the buckets together must not span more than the tick range.
+    /// * `tick_spacing` - The pool's tick spacing.
+    pub fn initialize(&mut self, pool: Pubkey, bucket_ticks: u32, tick_spacing: u16) -> Result<()> {
+        require!(
+            tick_spacing > 0
+                && bucket_ticks > 0
+                && bucket_ticks.is_multiple_of(tick_spacing as u32)
+                && bucket_ticks as u64 * HISTOGRAM_BUCKETS as u64 <= (MAX_TICK - MIN_TICK) as u64,
+            ErrorCode::InvalidHistogramBucketWidth
+        );
+        self.liquidity = [0; HISTOGRAM_BUCKETS];
+        self.pool = pool;
+        self.updated_at = i64::MIN;
+        self.lower_tick = 0;
+        self.bucket_ticks = bucket_ticks;
+        Ok(())
+    }
+
+    /// Returns the first tick of the lowest bucket when the histogram is centered on
+    /// `current_tick`: the current tick's bucket is `HISTOGRAM_CURRENT_BUCKET`, and bucket
+    /// boundaries are multiples of `bucket_ticks`.
+    pub fn lower_tick_around(&self, current_tick: i32) -> i32 {
+        let bucket_ticks = self.bucket_ticks as i64;
+        (current_tick as i64).div_euclid(bucket_ticks) as i32 * self.bucket_ticks as i32
+            - (HISTOGRAM_CURRENT_BUCKET as i64 * bucket_ticks) as i32
+    }
+
+    /// Returns the number of ticks covered by all the buckets together.
+    pub fn span_ticks(&self) -> i32 {
+        (HISTOGRAM_BUCKETS as u32 * self.bucket_ticks) as i32
+    }
+
+    /// Returns the tick just past the highest bucket.
+    pub fn upper_tick(&self) -> i32 {
+        self.lower_tick + self.span_ticks()
+    }
+
+    /// Recenters the histogram on `current_tick` and recomputes every bucket.
+    ///
+    /// # Arguments
+    ///
+    /// * `current_tick` - The pool's current tick.
+    /// * `liquidity` - The pool's active liquidity at `current_tick`.
+    /// * `ticks` - The index and `liquidity_net` of every initialized tick strictly
+    ///   between the new `lower_tick` and `upper_tick`, in ascending order.
+    /// * `timestamp` - The refresh time.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidTickAccountOrder` - The ticks are not strictly ascending inside the range.
+    /// * `LiquidityUnderflow` - The ticks do not add up to a non-negative liquidity
+    ///   everywhere, meaning some of the pool's ticks are missing.
+    /// * `MathOverflow` - A bucket's liquidity does not fit in a `u128`.
+    pub fn refresh(
+        &mut self,
+        current_tick: i32,
+        liquidity: u128,
+        ticks: &[(i32, i128)],
+        timestamp: i64,
+    ) -> Result<()> {
+        let lower_tick = self.lower_tick_around(current_tick);
+        let bucket_ticks = self.bucket_ticks as i64;
+        let upper_tick = lower_tick as i64 + self.span_ticks() as i64;
+
+        let mut previous = lower_tick as i64;
+        for &(tick, _) in ticks {
+            require!(
+                tick as i64 > previous && (tick as i64) < upper_tick,
+                ErrorCode::InvalidTickAccountOrder
+            );
+            previous = tick as i64;
+        }
+
+        // Liquidity at `lower_tick`: the ticks crossed moving down from the current tick
+        // stop contributing their `liquidity_net`.
+        let mut active = i128::try_from(liquidity).map_err(|_| error!(ErrorCode::MathOverflow))?;
+        for &(_, liquidity_net) in ticks.iter().filter(|(tick, _)| *tick <= current_tick) {
+            active = active
+                .checked_sub(liquidity_net)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        // Walk up from `lower_tick`, adding `active * ticks covered` to each bucket the
+        // segment between two initialized ticks overlaps.
+        let mut tick_weighted = [0u128; HISTOGRAM_BUCKETS];
+        let mut segment_start = lower_tick as i64;
+        let boundaries = ticks
+            .iter()
+            .map(|&(tick, liquidity_net)| (tick as i64, liquidity_net))
+            .chain(std::iter::once((upper_tick, 0)));
+        for (segment_end, liquidity_net) in boundaries {
+            let segment_liquidity =
+                u128::try_from(active).map_err(|_| error!(ErrorCode::LiquidityUnderflow))?;
+            while segment_start < segment_end {
+                let bucket = ((segment_start - lower_tick as i64) / bucket_ticks) as usize;
+                let bucket_end = lower_tick as i64 + (bucket as i64 + 1) * bucket_ticks;
+                let covered_end = segment_end.min(bucket_end);
+                tick_weighted[bucket] = segment_liquidity
+                    .checked_mul((covered_end - segment_start) as u128)
+                    .and_then(|weighted| tick_weighted[bucket].checked_add(weighted))
+                    .ok_or(ErrorCode::MathOverflow)?;
+                segment_start = covered_end;
+            }
+            active = active
+                .checked_add(liquidity_net)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        for (bucket, weighted) in self.liquidity.iter_mut().zip(tick_weighted) {
+            *bucket = weighted / bucket_ticks as u128;
+        }
+        self.lower_tick = lower_tick;
+        self.updated_at = timestamp;
+        Ok(())
+    }
+
+    /// Fails with `LiquidityHistogramStale` unless the histogram was refreshed at most
+    /// `max_age_secs` before `now`. A histogram never refreshed is always stale.
+    pub fn ensure_fresh(&self, now: i64, max_age_secs: i64) -> Result<()> {
+        require!(
+            self.updated_at != i64::MIN && now.saturating_sub(self.updated_at) <= max_age_secs,
+            ErrorCode::LiquidityHistogramStale
+        );
+        Ok(())
+    }
+
+    /// Returns the bucket covering `tick`, or `None` if it lies outside the histogram.
+    pub fn bucket_of(&self, tick: i32) -> Option<usize> {
+        if tick < self.lower_tick || tick >= self.upper_tick() {
+            return None;
+        }
+        Some(((tick - self.lower_tick) as u32 / self.bucket_ticks) as usize)
+    }
+
+    /// Estimates the depth a swap between `tick_a` and `tick_b` would meet, as the lowest
+    /// bucket liquidity between them, both included. Fails with
+    /// `LiquidityHistogramStale` as [`Self::ensure_fresh`] does, and with
+    /// `TickOutsideHistogram` if either tick lies outside the histogram.
+    pub fn min_liquidity_between(
+        &self,
+        tick_a: i32,
+        tick_b: i32,
+        now: i64,
+        max_age_secs: i64,
+    ) -> Result<u128> {
+        self.ensure_fresh(now, max_age_secs)?;
+        let (Some(bucket_a), Some(bucket_b)) = (self.bucket_of(tick_a), self.bucket_of(tick_b))
+        else {
+            return err!(ErrorCode::TickOutsideHistogram);
+        };
+        let (first, last) = (bucket_a.min(bucket_b), bucket_a.max(bucket_b));
+        Ok(self.liquidity[first..=last]
+            .iter()
+            .copied()
+            .min()
+            .unwrap_or(0))
+    }
+}
@@ -0,0 +1,57 @@
+//! Structured, feature-gated logging for on-chain handlers.
+//!
+//! Formatting and emitting `msg!` lines is one of the more expensive things a handler does,
+//! so `flog!` only emits the levels enabled through cargo features:
+//!
+//! * `error` - always emitted.
+//! * `info`  - emitted when the `log_info` feature is enabled.
+//! * `debug` - emitted when the `log_debug` feature is enabled (implies `log_info`).
+//!
+//! Both features are off by default, which is the profile used for mainnet builds.
+//!
+//! Every line has the form `level=<level> event=<event> key=value ...` so that the keeper's
+//! log parser can split on spaces and `=` without knowing the layout of each message.
+//! Values must therefore `Display` without spaces (integers, pubkeys, bools).
+//!
+//! The feature checks use `cfg!`, which is evaluated in the crate that invokes the macro.
+//! Any crate calling `flog!` must declare its own `log_info` / `log_debug` features.
+//! Disabled levels still type-check their arguments but are removed by the optimizer.
+//!
+//! # Example
+//!
+//! ```ignore
+//! flog!(info, "position_initialized", position = position.key(), owner = owner.key());
+//! // => "level=info event=position_initialized position=<pubkey> owner=<pubkey>"
+//! ```
+
+/// Emits a structured `key=value` log line at the given level.
+///
+/// See the [module docs](crate::logging) for the line format and the features controlling
+/// each level.
+#[macro_export]
+macro_rules! flog {
+    (error, $event:literal $(, $key:ident = $val:expr)* $(,)?) => {
+        $crate::__flog_emit!("error", $event $(, $key = $val)*)
+    };
+    (info, $event:literal $(, $key:ident = $val:expr)* $(,)?) => {
+        if cfg!(feature = "log_info") {
+            $crate::__flog_emit!("info", $event $(, $key = $val)*)
+        }
+    };
+    (debug, $event:literal $(, $key:ident = $val:expr)* $(,)?) => {
+        if cfg!(feature = "log_debug") {
+            $crate::__flog_emit!("debug", $event $(, $key = $val)*)
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __flog_emit {
+    ($level:literal, $event:literal $(, $key:ident = $val:expr)*) => {
+        ::anchor_lang::prelude::msg!(
+            concat!("level=", $level, " event=", $event $(, " ", stringify!($key), "={}")*)
+            $(, $val)*
+        )
+    };
+}
@@ -29,6 +29,7 @@ use anchor_lang::prelude::*;
 use primitive_types::U256;
 
 #[inline(always)]
+#[allow(dead_code)]
 pub(crate) fn mul_fixed(a: u128, b: u128) -> u128 {
     let a_lo = a as u64 as u128; // Lower 64 bits of a
     let a_hi = (a >> 64) as u64 as u128; // Upper 64 bits of a
@@ -49,6 +50,27 @@ pub(crate) fn mul_fixed(a: u128, b: u128) -> u128 {
     (high << 64) | (mid as u64 as u128)
 }
 
+/// Multiplies two Q64.64 fixed-point numbers, checking for overflow
+///
+/// Unlike `mul_fixed`, this function computes the full 256-bit product and returns `None`
+/// when the product, shifted back down to Q64.64, does not fit in 128 bits.
+///
+/// # Arguments
+/// * `a` - The first Q64.64 fixed-point number
+/// * `b` - The second Q64.64 fixed-point number
+///
+/// # Returns
+/// * `Option<u128>` - The product as a Q64.64 fixed-point number, or `None` on overflow
+#[inline(always)]
+pub(crate) fn mul_fixed_checked(a: u128, b: u128) -> Option<u128> {
+    let product = (U256::from(a) * U256::from(b)) >> 64;
+    if product > U256::from(u128::MAX) {
+        None
+    } else {
+        Some(product.as_u128())
+    }
+}
+
 /// Divides two Q64.64 fixed-point numbers
 ///
 /// This function performs division of two Q64.64 fixed-point numbers
@@ -73,6 +95,7 @@ pub(crate) fn mul_fixed(a: u128, b: u128) -> u128 {
 /// assert_eq!(result, 0x00000000000000020000000000000000);
 /// ```
 #[inline(always)]
+#[allow(dead_code)]
 pub(crate) fn div_fixed(a: u128, b: u128) -> u128 {
     // Check for division by zero
     debug_assert!(b != 0, "Division by zero: div_fixed() divisor is zero");
@@ -82,6 +105,53 @@ pub(crate) fn div_fixed(a: u128, b: u128) -> u128 {
     (a_u256 / U256::from(b)).as_u128()
 }
 
+/// Divides two Q64.64 fixed-point numbers, checking for overflow
+///
+/// Returns `None` instead of panicking when the divisor is zero or the quotient does not
+/// fit in 128 bits.
+///
+/// # Arguments
+/// * `a` - The dividend (Q64.64 fixed-point number)
+/// * `b` - The divisor (Q64.64 fixed-point number)
+///
+/// # Returns
+/// * `Option<u128>` - The quotient as a Q64.64 fixed-point number, or `None` on overflow
+#[inline(always)]
+pub(crate) fn div_fixed_checked(a: u128, b: u128) -> Option<u128> {
+    if b == 0 {
+        return None;
+    }
+    let quotient = (U256::from(a) << 64) / U256::from(b);
+    if quotient > U256::from(u128::MAX) {
+        None
+    } else {
+        Some(quotient.as_u128())
+    }
+}
+
+/// Divides two Q64.64 fixed-point numbers, returning an error instead of panicking
+///
+/// This is the variant used on instruction paths; `div_fixed` panics on the same inputs.
+///
+/// # Arguments
+/// * `a` - The dividend (Q64.64 fixed-point number)
+/// * `b` - The divisor (Q64.64 fixed-point number)
+///
+/// # Returns
+/// * `Result<u128, ErrorCode>` - The quotient as a Q64.64 fixed-point number
+///
+/// # Errors
+/// * `DivisionByZero` - If the divisor is zero
+/// * `MathOverflow` - If the quotient does not fit in 128 bits, e.g. 1.0 divided by the
+///   smallest nonzero value is 2^128
+#[inline(always)]
+pub(crate) fn try_div_fixed(a: u128, b: u128) -> std::result::Result<u128, ErrorCode> {
+    if b == 0 {
+        return Err(ErrorCode::DivisionByZero);
+    }
+    div_fixed_checked(a, b).ok_or(ErrorCode::MathOverflow)
+}
+
 /// Inverts a Q64.64 fixed-point number
 ///
 /// This function calculates the reciprocal (1/x) of a Q64.64 fixed-point number
@@ -103,11 +173,28 @@ pub(crate) fn div_fixed(a: u128, b: u128) -> u128 {
 /// let result = invert_fixed(x); // 0.5 in Q64.64
 /// ```
 #[inline(always)]
+#[allow(dead_code)]
 pub(crate) fn invert_fixed(x: u128) -> u128 {
     // 1.0 / x
     div_fixed(Q64, x)
 }
 
+/// Inverts a Q64.64 fixed-point number, returning an error instead of panicking
+///
+/// # Arguments
+/// * `x` - The Q64.64 fixed-point number to invert
+///
+/// # Returns
+/// * `Result<u128, ErrorCode>` - The reciprocal as a Q64.64 fixed-point number
+///
+/// # Errors
+/// * `DivisionByZero` - If `x` is zero
+/// * `MathOverflow` - If the reciprocal does not fit in 128 bits, i.e. `x` is 1
+#[inline(always)]
+pub(crate) fn try_invert_fixed(x: u128) -> std::result::Result<u128, ErrorCode> {
+    try_div_fixed(Q64, x)
+}
+
 /// Performs binary exponentiation using a precomputed table
 ///
 /// This function calculates the result of raising a value to a power using
@@ -118,7 +205,8 @@ pub(crate) fn invert_fixed(x: u128) -> u128 {
 /// * `exp` - The exponent to raise the base to
 ///
 /// # Returns
-/// * `u128` - The result of the exponentiation in fixed-point format
+/// * `Option<u128>` - The result of the exponentiation in fixed-point format, or `None`
+///   if it overflows
 ///
 /// # Panics
 /// This function will panic if the exponent is greater than the length of the table.
@@ -131,7 +219,7 @@ pub(crate) fn invert_fixed(x: u128) -> u128 {
 /// let result = binary_pow(&table, exp); // Result of exponentiation
 /// ```
 #[inline(always)]
-pub(crate) fn binary_pow(table: &[u128], mut exp: u32) -> u128 {
+pub(crate) fn binary_pow(table: &[u128], mut exp: u32) -> Option<u128> {
     // The original debug_assert was: exp < table.len(). This is incorrect.
     // `exp` is the exponent itself, `i` is the index into the table.
 
@@ -139,7 +227,7 @@ pub(crate) fn binary_pow(table: &[u128], mut exp: u32) -> u128 {
     let mut i = 0;
 
     if exp == 0 {
-        return Q64; // base^0 = 1.0
+        return Some(Q64); // base^0 = 1.0
     }
 
     while exp > 0 {
@@ -152,12 +240,12 @@ pub(crate) fn binary_pow(table: &[u128], mut exp: u32) -> u128 {
             );
         }
         if exp & 1 == 1 {
-            result = mul_fixed(result, table[i]);
+            result = mul_fixed_checked(result, table[i])?;
         }
         exp >>= 1;
         i += 1;
     }
-    result
+    Some(result)
 }
 
 /// Calculates the square root of a fixed-point number using the Babylonian method
@@ -183,8 +271,25 @@ pub(crate) fn binary_pow(table: &[u128], mut exp: u32) -> u128 {
 #[inline(always)]
 #[allow(dead_code)]
 pub(crate) fn babylonian_sqrt(x: u128) -> u128 {
+    babylonian_sqrt_checked(x).expect("Overflow in babylonian_sqrt()")
+}
+
+/// Calculates the square root of a fixed-point number, checking for overflow
+///
+/// Runs the same iterations as `babylonian_sqrt`, but returns `None` instead of panicking
+/// if an intermediate division overflows.
+///
+/// # Arguments
+/// * `x` - The fixed-point number to calculate the square root of
+///
+/// # Returns
+/// * `Option<u128>` - The square root of the input in fixed-point format, or `None` on
+///   overflow
+#[inline(always)]
+#[allow(dead_code)]
+pub(crate) fn babylonian_sqrt_checked(x: u128) -> Option<u128> {
     if x == 0 {
-        return 0;
+        return Some(0);
     }
 
     // Initial guess. Q64 (1.0) is a common starting point.
@@ -222,11 +327,11 @@ pub(crate) fn babylonian_sqrt(x: u128) -> u128 {
         if res_q64 == 0 {
             break;
         } // Avoid division by zero if guess collapses
-        let term_q64 = div_fixed(x, res_q64);
+        let term_q64 = div_fixed_checked(x, res_q64)?;
         // Average: (res + x/res) / 2, using U256 for the sum to prevent overflow
         res_q64 = ((U256::from(res_q64) + U256::from(term_q64)) >> 1).as_u128();
     }
-    res_q64
+    Some(res_q64)
 }
 
 /// Performs integer division with rounding up
@@ -265,6 +370,204 @@ pub(crate) fn round_up_div(a: u128, b: u128) -> u128 {
     }
 }
 
+/// Calculates `a * b / denom`, rounded down
+///
+/// The product is kept in 256 bits, so only the quotient needs to fit in a `u128`.
+///
+/// # Arguments
+/// * `a` - The first factor
+/// * `b` - The second factor
+/// * `denom` - The divisor
+///
+/// # Returns
+/// * `Result<u128, ProgramError>` - The quotient, or `MathOverflow` if it exceeds
+///   `u128::MAX` or `denom` is zero
+pub fn mul_div_floor(a: u128, b: u128, denom: u128) -> Result<u128> {
+    if denom == 0 {
+        return Err(ErrorCode::MathOverflow.into());
+    }
+    let quotient = U256::from(a) * U256::from(b) / U256::from(denom);
+    if quotient > U256::from(u128::MAX) {
+        return Err(ErrorCode::MathOverflow.into());
+    }
+    Ok(quotient.as_u128())
+}
+
+/// Calculates `a * b / denom`, rounded up
+///
+/// The product is kept in 256 bits, so only the quotient needs to fit in a `u128`.
+///
+/// # Arguments
+/// * `a` - The first factor
+/// * `b` - The second factor
+/// * `denom` - The divisor
+///
+/// # Returns
+/// * `Result<u128, ProgramError>` - The quotient, or `MathOverflow` if it exceeds
+///   `u128::MAX` or `denom` is zero
+pub fn mul_div_ceil(a: u128, b: u128, denom: u128) -> Result<u128> {
+    if denom == 0 {
+        return Err(ErrorCode::MathOverflow.into());
+    }
+    let (mut quotient, remainder) = (U256::from(a) * U256::from(b)).div_mod(U256::from(denom));
+    if !remainder.is_zero() {
+        quotient += U256::one();
+    }
+    if quotient > U256::from(u128::MAX) {
+        return Err(ErrorCode::MathOverflow.into());
+    }
+    Ok(quotient.as_u128())
+}
+
+/// Number of terms of the atanh series summed by `ln_fixed`.
+const LN_SERIES_TERMS: i64 = 13;
+/// Number of terms of the Taylor series summed by `exp_fixed`.
+const EXP_SERIES_TERMS: i64 = 18;
+/// √2 as a Q1.127 number, rounded down.
+const SQRT_2_Q127: u128 = 0xb504f333f9de6484597d89b3754abe9f;
+
+/// Calculates the natural logarithm of a Q64.64 fixed-point number
+///
+/// The input is reduced to `x = 2^k * m` with `m` in `[√½, √2)`, so that
+/// `ln(x) = k * ln(2) + 2 * atanh(s)` with `s = (m - 1) / (m + 1)` and `|s| < 0.172`. The
+/// atanh series is summed to its 13th term, after which the remaining terms are below
+/// 2^-66. The absolute error of the result stays below 1e-17 for every input, which is a
+/// relative error below 1e-12 for inputs outside `[0.99999, 1.00001]`.
+///
+/// # Arguments
+/// * `x` - The Q64.64 fixed-point number, which must be positive
+///
+/// # Returns
+/// * `Result<i128, ProgramError>` - ln(x) as a signed Q64.64 number, or `InvalidInput` if
+///   `x` is zero
+pub fn ln_fixed(x: u128) -> Result<i128> {
+    if x == 0 {
+        return Err(ErrorCode::InvalidInput.into());
+    }
+
+    // Mantissa in [1, 2), as a Q1.127 number, and the power of two taken out of it.
+    let msb = 127 - x.leading_zeros();
+    let mut k = msb as i128 - 64;
+    let mantissa = U256::from(x << (127 - msb));
+    // Halve mantissas above √2 so the series converges quickly on both sides of 1.
+    let one = if mantissa >= U256::from(SQRT_2_Q127) {
+        k += 1;
+        U256::one() << 128
+    } else {
+        U256::one() << 127
+    };
+    let (difference, negative) = if mantissa >= one {
+        (mantissa - one, false)
+    } else {
+        (one - mantissa, true)
+    };
+    let s_magnitude = ((difference << 64) / (mantissa + one)).as_u128() as i128;
+    let s = FixedQ64::from_raw(if negative { -s_magnitude } else { s_magnitude });
+
+    // atanh(s) = s * (1 + s²/3 + s⁴/5 + ...), by Horner's rule in s².
+    let s_squared = s * s;
+    let mut series = FixedQ64::ONE / FixedQ64::from_i64(2 * LN_SERIES_TERMS - 1);
+    for n in (0..LN_SERIES_TERMS - 1).rev() {
+        series = FixedQ64::ONE / FixedQ64::from_i64(2 * n + 1) + s_squared * series;
+    }
+    let ln_mantissa = s * series * FixedQ64::from_i64(2);
+
+    Ok(k * LN_2_Q64 as i128 + ln_mantissa.raw())
+}
+
+/// Calculates e raised to a signed Q64.64 fixed-point number
+///
+/// The exponent is reduced to `x = k * ln(2) + r` with `|r| <= ln(2) / 2`, so that
+/// `exp(x) = 2^k * exp(r)`. The Taylor series of `exp(r)` is summed to its 18th term, after
+/// which the remaining terms are below 2^-70. The relative error of the result stays below
+/// 1e-15 while it is at least 2^-20; smaller results lose relative precision as they
+/// approach the Q64.64 resolution of 2^-64.
+///
+/// # Arguments
+/// * `x` - The exponent, as a signed Q64.64 number
+///
+/// # Returns
+/// * `Result<u128, ProgramError>` - exp(x) as a Q64.64 number, rounded down to zero for
+///   exponents below about -44.4, or `MathOverflow` if it is 2^64 or more (exponents above
+///   about 44.4)
+pub fn exp_fixed(x: i128) -> Result<u128> {
+    let ln_2 = LN_2_Q64 as i128;
+    // Anything outside these bounds overflows or rounds to zero; checking first keeps the
+    // reduction below from overflowing.
+    if x > 65 * ln_2 {
+        return Err(ErrorCode::MathOverflow.into());
+    }
+    if x < -129 * ln_2 {
+        return Ok(0);
+    }
+
+    // k = round(x / ln(2))
+    let k = if x >= 0 {
+        (x + ln_2 / 2) / ln_2
+    } else {
+        (x - ln_2 / 2) / ln_2
+    };
+    let r = FixedQ64::from_raw(x - k * ln_2);
+
+    // exp(r) = 1 + r * (1 + r/2 * (1 + r/3 * (...))), by Horner's rule.
+    let mut series = FixedQ64::ONE;
+    for n in (1..=EXP_SERIES_TERMS).rev() {
+        series = FixedQ64::ONE + r * series / FixedQ64::from_i64(n);
+    }
+    let exp_r = series.raw() as u128;
+
+    if k >= 0 {
+        let shift = k as u32;
+        if exp_r.leading_zeros() < shift {
+            return Err(ErrorCode::MathOverflow.into());
+        }
+        Ok(exp_r << shift)
+    } else {
+        Ok(exp_r.checked_shr((-k) as u32).unwrap_or(0))
+    }
+}
+
+/// Raises a Q64.64 fixed-point number to a signed, possibly fractional, Q64.64 power
+///
+/// Computes `exp(exponent * ln(base))` with `exp_fixed` and `ln_fixed`, for powers such as
+/// `1.0001^(tick / 2)` that `binary_pow` cannot take. An error of `δ` in `ln(base)` becomes
+/// a relative error of about `|exponent| * δ` in the result, on top of that of `exp_fixed`:
+/// for `|exponent * ln(base)| <= 40` and results of at least 2^-20 it stays below 1e-12
+/// while `|exponent| <= 1_000`, and below 1e-10 up to `|exponent| = 100_000`.
+///
+/// # Arguments
+/// * `base` - The base, as a Q64.64 number
+/// * `exponent` - The exponent, as a signed Q64.64 number
+///
+/// # Returns
+/// * `Result<u128, ProgramError>` - base^exponent as a Q64.64 number, rounded down to zero
+///   when below the Q64.64 resolution. `0^0` is 1 and `0^exponent` is 0 for a positive
+///   exponent.
+///
+/// # Errors
+/// * `InvalidInput` - `base` is zero and `exponent` negative
+/// * `MathOverflow` - The result is 2^64 or more
+pub fn pow_fixed(base: u128, exponent: i128) -> Result<u128> {
+    if exponent == 0 {
+        return Ok(Q64);
+    }
+    if base == 0 {
+        return if exponent > 0 {
+            Ok(0)
+        } else {
+            Err(ErrorCode::InvalidInput.into())
+        };
+    }
+
+    let ln_base = FixedQ64::from_raw(ln_fixed(base)?);
+    match ln_base.checked_mul(FixedQ64::from_raw(exponent)) {
+        Some(power) => exp_fixed(power.raw()),
+        // Far beyond the range exp_fixed can represent either way
+        None if (ln_base.raw() < 0) != (exponent < 0) => Ok(0),
+        None => Err(ErrorCode::MathOverflow.into()),
+    }
+}
+
 /// Clamps a u128 value between a minimum and maximum value
 ///
 /// This function ensures that the input value `x` is within the range [min, max].
@@ -368,10 +671,10 @@ pub fn tick_to_sqrt_price_q64(tick: i32) -> Result<u128> {
     // If abs_tick is MAX_TICK (887272), i_max is 19. POWERS table has length 20 (indices 0-19).
     // The panic inside binary_pow will handle if abs_tick is unexpectedly too large for the table.
 
-    let sqrt_price_abs_tick = binary_pow(&POWERS, abs_tick);
+    let sqrt_price_abs_tick = binary_pow(&POWERS, abs_tick).ok_or(ErrorCode::MathOverflow)?;
 
     let final_sqrt_price = if tick < 0 {
-        invert_fixed(sqrt_price_abs_tick)
+        try_invert_fixed(sqrt_price_abs_tick)?
     } else {
         sqrt_price_abs_tick
     };
@@ -383,10 +686,25 @@ pub fn tick_to_sqrt_price_q64(tick: i32) -> Result<u128> {
     Ok(final_sqrt_price)
 }
 
+/// Number of fractional bits of the base-2 logarithm computed by `sqrt_price_q64_to_tick`.
+///
+/// One tick is log₂(√1.0001) ≈ 2^-13.8 in log₂ terms, so 24 bits place the estimate within a
+/// thousandth of a tick of the exact logarithm.
+const LOG2_FRACTION_BITS: u32 = 24;
+
 /// Converts a sqrt price in Q64.64 fixed-point format to its corresponding tick index
 ///
-/// The function calculates the tick index corresponding to a given sqrt price
-/// using the inverse of the formula: sqrt(price) = 1.0001^(tick/2)
+/// Returns the largest tick whose `tick_to_sqrt_price_q64` is at most `sqrt_price_q64`,
+/// so that `tick_to_sqrt_price_q64(tick) <= sqrt_price_q64 < tick_to_sqrt_price_q64(tick + 1)`.
+///
+/// The tick is computed directly as log_√1.0001 of the price: the base-2 logarithm is
+/// read from the position of the leading bit and expanded bit by bit for its fraction,
+/// then scaled by `INV_LOG2_SQRT_1P0001_Q64`. The logarithm is taken of the price one
+/// Q64.64 unit above `sqrt_price_q64`, because `tick_to_sqrt_price_q64` rounds down: near
+/// `MIN_TICK`, where one unit spans thousands of ticks, this lands on the last tick that
+/// rounds down to the price instead of the first. The estimate is then checked against
+/// `tick_to_sqrt_price_q64` and moved by one tick if it sits on the wrong side of an
+/// exact tick price.
 ///
 /// # Arguments
 /// * `sqrt_price` - The sqrt price in Q64.64 format to convert
@@ -400,45 +718,41 @@ pub fn tick_to_sqrt_price_q64(tick: i32) -> Result<u128> {
 /// let result = sqrt_price_q64_to_tick(sqrt_price); // Resulting tick index
 ///
 pub fn sqrt_price_q64_to_tick(sqrt_price_q64: u128) -> Result<i32> {
-    // Handle edge cases for sqrt_price_q64
-    // If sqrt_price is 0, log is undefined. Price 0 implies tick is -infinity.
+    // A zero price has no logarithm; it sits below every tick.
     if sqrt_price_q64 == 0 {
-        // This case needs careful consideration based on protocol design.
-        // Typically, price shouldn't be zero. If it can be, map to MIN_TICK or error.
-        return Ok(MIN_TICK); // Or Err(ErrorCode::PriceOutOfRange.into())
+        return Ok(MIN_TICK);
     }
-
-    // Based on constants.rs, MIN_SQRT_PRICE is 0, MAX_SQRT_PRICE is large.
-    // Clamping/checking against these might be useful if sqrt_price_q64 can be outside them.
-    // However, valid sqrt_price_q64 should correspond to a tick within [MIN_TICK, MAX_TICK].
-
-    if sqrt_price_q64 == Q64 {
-        // 1.0
-        return Ok(0);
+    // MIN_SQRT_PRICE and MAX_SQRT_PRICE are the sqrt prices of MIN_TICK and MAX_TICK, so
+    // any sqrt_price_q64 between them maps to a tick within [MIN_TICK, MAX_TICK].
+    if sqrt_price_q64 >= MAX_SQRT_PRICE {
+        return Ok(MAX_TICK);
     }
 
-    // Binary search for the tick `i` such that `tick_to_sqrt_price_q64(i)` is closest to `sqrt_price_q64`.
-    // We want the largest tick `i` such that `sqrtP(i) <= sqrt_price_q64`.
-    let mut low = MIN_TICK;
-    let mut high = MAX_TICK;
-    let mut ans = MIN_TICK; // Default to MIN_TICK
-
-    while low <= high {
-        // Calculate mid carefully to avoid overflow with i32
-        let mid = low + (high - low) / 2;
-
-        let mid_sqrt_price = tick_to_sqrt_price_q64(mid)?;
-
-        if mid_sqrt_price <= sqrt_price_q64 {
-            ans = mid; // mid is a potential candidate
-            low = mid.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
-        } else {
-            high = mid.checked_sub(1).ok_or(ErrorCode::MathOverflow)?;
+    // log₂ of the price above, in fixed point with LOG2_FRACTION_BITS fractional bits,
+    // rounded down.
+    let x = sqrt_price_q64 + 1;
+    let msb = 127 - x.leading_zeros();
+    let mut log2 = (msb as i128 - 64) << LOG2_FRACTION_BITS;
+    // Mantissa in [1, 2), as a Q1.127 number.
+    let mut mantissa = U256::from(x << (127 - msb));
+    for bit in (0..LOG2_FRACTION_BITS).rev() {
+        mantissa = (mantissa * mantissa) >> 127;
+        if mantissa.bit(128) {
+            log2 |= 1 << bit;
+            mantissa >>= 1;
         }
     }
 
-    // ans should be the floor tick. Clamp to be absolutely sure, though binary search should maintain bounds.
-    Ok(ans.clamp(MIN_TICK, MAX_TICK))
+    // log_√1.0001 = log₂ / log₂(√1.0001), rounded down.
+    let estimate = (log2 * INV_LOG2_SQRT_1P0001_Q64 as i128) >> (LOG2_FRACTION_BITS + 64);
+    let mut tick = (estimate as i32).clamp(MIN_TICK, MAX_TICK);
+
+    if tick_to_sqrt_price_q64(tick)? > sqrt_price_q64 {
+        tick -= 1;
+    } else if tick < MAX_TICK && tick_to_sqrt_price_q64(tick + 1)? <= sqrt_price_q64 {
+        tick += 1;
+    }
+    Ok(tick)
 }
 
 /// Calculates the amount of token 0 corresponding to a price range and liquidity
@@ -477,8 +791,8 @@ pub fn get_amount_0_delta(
     }
 
     // Formula: ΔX = L * (1/sqrt_P_lower - 1/sqrt_P_upper)
-    let inv_sqrt_lower_q64 = invert_fixed(sqrt_price_lower_q64);
-    let inv_sqrt_upper_q64 = invert_fixed(sqrt_price_upper_q64);
+    let inv_sqrt_lower_q64 = try_invert_fixed(sqrt_price_lower_q64)?;
+    let inv_sqrt_upper_q64 = try_invert_fixed(sqrt_price_upper_q64)?;
 
     // (1/sqrt_P_lower - 1/sqrt_P_upper) can be negative if order is wrong, but we checked.
     let diff_inv_sqrt_q64 = inv_sqrt_lower_q64
@@ -589,8 +903,8 @@ pub fn get_liquidity_for_amount0(
     }
 
     // Formula: L = amount0 / (1/sqrt_P_lower - 1/sqrt_P_upper)
-    let inv_sqrt_lower_q64 = invert_fixed(sqrt_price_lower_q64);
-    let inv_sqrt_upper_q64 = invert_fixed(sqrt_price_upper_q64);
+    let inv_sqrt_lower_q64 = try_invert_fixed(sqrt_price_lower_q64)?;
+    let inv_sqrt_upper_q64 = try_invert_fixed(sqrt_price_upper_q64)?;
     let diff_inv_sqrt_q64 = inv_sqrt_lower_q64
         .checked_sub(inv_sqrt_upper_q64)
         .ok_or(ErrorCode::MathOverflow)?;
@@ -661,6 +975,51 @@ pub fn get_liquidity_for_amount1(
     Ok(liquidity_u256.as_u128())
 }
 
+/// Calculates the most liquidity a position can get from the given token amounts
+///
+/// Which tokens the range needs depends on where the current price sits: a range above it
+/// takes only token 0, a range below it only token 1, and a range around it both. In the
+/// last case the liquidity is the smaller of what each amount supports, so the other
+/// token is left over. Depositing the result with `get_amounts_for_liquidity`, rounded up,
+/// never takes more than the given amounts.
+///
+/// # Arguments
+/// * `sqrt_price_current_q64` - The current sqrt price in Q64.64 format
+/// * `sqrt_price_lower_q64` - The lower sqrt price bound in Q64.64 format
+/// * `sqrt_price_upper_q64` - The upper sqrt price bound in Q64.64 format
+/// * `amount_0` - The most token 0 available
+/// * `amount_1` - The most token 1 available
+///
+/// # Returns
+/// * `Result<u128, ProgramError>` - The liquidity, rounded down, or `InvalidPriceRange` if
+///   the lower bound is not below the upper bound
+pub fn get_liquidity_for_amounts(
+    sqrt_price_current_q64: u128,
+    sqrt_price_lower_q64: u128,
+    sqrt_price_upper_q64: u128,
+    amount_0: u128,
+    amount_1: u128,
+) -> Result<u128> {
+    if sqrt_price_lower_q64 >= sqrt_price_upper_q64 {
+        return Err(ErrorCode::InvalidPriceRange.into());
+    }
+
+    if sqrt_price_current_q64 <= sqrt_price_lower_q64 {
+        // Price below range: only token 0 is deposited
+        get_liquidity_for_amount0(sqrt_price_lower_q64, sqrt_price_upper_q64, amount_0)
+    } else if sqrt_price_current_q64 < sqrt_price_upper_q64 {
+        // Price inside range: the scarcer token bounds the liquidity
+        let liquidity_0 =
+            get_liquidity_for_amount0(sqrt_price_current_q64, sqrt_price_upper_q64, amount_0)?;
+        let liquidity_1 =
+            get_liquidity_for_amount1(sqrt_price_lower_q64, sqrt_price_current_q64, amount_1)?;
+        Ok(liquidity_0.min(liquidity_1))
+    } else {
+        // Price above range: only token 1 is deposited
+        get_liquidity_for_amount1(sqrt_price_lower_q64, sqrt_price_upper_q64, amount_1)
+    }
+}
+
 /// Calculates the next sqrt price after adding a specified amount of token 0 to the pool
 ///
 /// This function computes the next sqrt price based on the current sqrt price,
@@ -756,3 +1115,598 @@ pub fn compute_next_sqrt_price_from_amount1_in(
 
     Ok(next_sqrt_price_q64)
 }
+
+/// Calculates the next sqrt price after removing a specified amount of token 0 from the pool
+///
+/// This is the exact-output counterpart of `compute_next_sqrt_price_from_amount0_in`.
+/// Removing token 0 moves the price up, so the result is rounded up to guarantee the
+/// pool always receives enough input for the requested output.
+///
+/// # Arguments
+/// * `sqrt_price_current_q64` - The current sqrt price in Q64.64 format
+/// * `liquidity` - The current liquidity in the pool
+/// * `amount_0_out` - The amount of token 0 being removed from the pool
+///
+/// # Returns
+/// * `Result<u128, ProgramError>` - The calculated next sqrt price or an error
+///
+/// # Example
+///
+/// let sqrt_price_current_q64: u128 = ...; // Current sqrt price in Q64.64 format
+/// let liquidity: u128 = ...; // Current liquidity
+/// let amount_0_out: u128 = ...; // Amount of token 0 to remove
+/// let result = compute_next_sqrt_price_from_amount0_out(sqrt_price_current_q64, liquidity, amount_0_out);
+///
+pub fn compute_next_sqrt_price_from_amount0_out(
+    sqrt_price_current_q64: u128,
+    liquidity: u128,
+    amount_0_out: u128,
+) -> Result<u128> {
+    if liquidity == 0 {
+        return Err(ErrorCode::InsufficientLiquidity.into());
+    }
+    if amount_0_out == 0 {
+        return Ok(sqrt_price_current_q64);
+    }
+
+    // Formula: sqrt_P_next = (L * sqrt_P_curr) / (L - amount_out * sqrt_P_curr)
+    let num_term_u256 = U256::from(liquidity) * U256::from(sqrt_price_current_q64);
+    let den_term1_u256 = U256::from(liquidity) << 64;
+    let den_term2_u256 = U256::from(amount_0_out) * U256::from(sqrt_price_current_q64);
+
+    // The pool cannot give out more token 0 than the liquidity holds above the current price.
+    if den_term1_u256 <= den_term2_u256 {
+        return Err(ErrorCode::InsufficientLiquidity.into());
+    }
+    let den_diff_u256 = den_term1_u256 - den_term2_u256;
+
+    let numerator_u256 = num_term_u256 << 64;
+    let mut next_sqrt_price_u256 = numerator_u256 / den_diff_u256;
+    if !(numerator_u256 % den_diff_u256).is_zero() {
+        next_sqrt_price_u256 += U256::one();
+    }
+
+    if next_sqrt_price_u256 > U256::from(MAX_SQRT_PRICE) {
+        return Err(ErrorCode::PriceOutOfRange.into());
+    }
+    Ok(next_sqrt_price_u256.as_u128())
+}
+
+/// Calculates the next sqrt price after removing a specified amount of token 1 from the pool
+///
+/// This is the exact-output counterpart of `compute_next_sqrt_price_from_amount1_in`.
+/// Removing token 1 moves the price down, so the price delta is rounded up to guarantee the
+/// pool always receives enough input for the requested output.
+///
+/// # Arguments
+/// * `sqrt_price_current_q64` - The current sqrt price in Q64.64 format
+/// * `liquidity` - The current liquidity in the pool
+/// * `amount_1_out` - The amount of token 1 being removed from the pool
+///
+/// # Returns
+/// * `Result<u128, ProgramError>` - The calculated next sqrt price or an error
+///
+/// # Example
+///
+/// let sqrt_price_current_q64: u128 = ...; // Current sqrt price in Q64.64 format
+/// let liquidity: u128 = ...; // Current liquidity
+/// let amount_1_out: u128 = ...; // Amount of token 1 to remove
+/// let result = compute_next_sqrt_price_from_amount1_out(sqrt_price_current_q64, liquidity, amount_1_out);
+///
+pub fn compute_next_sqrt_price_from_amount1_out(
+    sqrt_price_current_q64: u128,
+    liquidity: u128,
+    amount_1_out: u128,
+) -> Result<u128> {
+    if liquidity == 0 {
+        return Err(ErrorCode::InsufficientLiquidity.into());
+    }
+    if amount_1_out == 0 {
+        return Ok(sqrt_price_current_q64);
+    }
+
+    // Formula: sqrt_P_next = sqrt_P_current - amount1_out / L
+    let numerator_u256 = U256::from(amount_1_out) << 64;
+    let mut term_q64_u256 = numerator_u256 / U256::from(liquidity);
+    if !(numerator_u256 % U256::from(liquidity)).is_zero() {
+        term_q64_u256 += U256::one();
+    }
+
+    if term_q64_u256 >= U256::from(sqrt_price_current_q64) {
+        return Err(ErrorCode::InsufficientLiquidity.into());
+    }
+
+    Ok(sqrt_price_current_q64 - term_q64_u256.as_u128())
+}
+
+/// Calculates the token amounts represented by a liquidity amount over a tick range
+///
+/// Depending on where the current price sits relative to the range, the liquidity is
+/// held entirely in token 0 (price below range), entirely in token 1 (price above range),
+/// or split between both (price inside range).
+///
+/// # Arguments
+/// * `sqrt_price_current_q64` - The current pool sqrt price in Q64.64 format
+/// * `sqrt_price_lower_q64` - The sqrt price at the lower tick of the range
+/// * `sqrt_price_upper_q64` - The sqrt price at the upper tick of the range
+/// * `liquidity` - The amount of liquidity
+/// * `round_up` - Whether to round up the results (deposits) or down (withdrawals)
+///
+/// # Returns
+/// * `Result<(u128, u128), ProgramError>` - The `(amount_0, amount_1)` pair or an error
+///
+/// # Example
+///
+/// let (amount_0, amount_1) = get_amounts_for_liquidity(sqrt_price, sqrt_lower, sqrt_upper, liquidity, false)?;
+///
+pub fn get_amounts_for_liquidity(
+    sqrt_price_current_q64: u128,
+    sqrt_price_lower_q64: u128,
+    sqrt_price_upper_q64: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<(u128, u128)> {
+    if sqrt_price_lower_q64 >= sqrt_price_upper_q64 {
+        return Err(ErrorCode::InvalidPriceRange.into());
+    }
+
+    if sqrt_price_current_q64 <= sqrt_price_lower_q64 {
+        // Price below range: position is entirely token 0
+        let amount_0 = get_amount_0_delta(
+            sqrt_price_lower_q64,
+            sqrt_price_upper_q64,
+            liquidity,
+            round_up,
+        )?;
+        Ok((amount_0, 0))
+    } else if sqrt_price_current_q64 < sqrt_price_upper_q64 {
+        // Price inside range: position holds both tokens
+        let amount_0 = get_amount_0_delta(
+            sqrt_price_current_q64,
+            sqrt_price_upper_q64,
+            liquidity,
+            round_up,
+        )?;
+        let amount_1 = get_amount_1_delta(
+            sqrt_price_lower_q64,
+            sqrt_price_current_q64,
+            liquidity,
+            round_up,
+        )?;
+        Ok((amount_0, amount_1))
+    } else {
+        // Price above range: position is entirely token 1
+        let amount_1 = get_amount_1_delta(
+            sqrt_price_lower_q64,
+            sqrt_price_upper_q64,
+            liquidity,
+            round_up,
+        )?;
+        Ok((0, amount_1))
+    }
+}
+
+/// The result of a single swap step, as computed by [`compute_swap_step`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapStep {
+    /// The sqrt price after the step, in Q64.64 format
+    pub sqrt_price_next: u128,
+    /// The input amount used to move the price, excluding the fee
+    pub amount_in: u128,
+    /// The output amount produced by the step
+    pub amount_out: u128,
+    /// The fee taken from the input
+    pub fee_amount: u128,
+}
+
+/// Computes a single exact-input swap step within one liquidity range
+///
+/// The swap direction is implied by the prices: a target below the current price swaps
+/// token 0 for token 1, a target above swaps token 1 for token 0. The step moves the
+/// price towards the target and stops there if the remaining input would take it further.
+///
+/// `amount_in + fee_amount` never exceeds `amount_remaining`. When the target is not
+/// reached, the whole remaining input is consumed and the part not used to move the
+/// price is taken as the fee.
+///
+/// # Arguments
+/// * `sqrt_price_current_q64` - The current sqrt price in Q64.64 format
+/// * `sqrt_price_target_q64` - The sqrt price the step cannot move past, in Q64.64 format
+/// * `liquidity` - The liquidity available for the step
+/// * `amount_remaining` - The gross input amount left to swap, including the fee
+/// * `fee_pips` - The fee rate in hundredths of a basis point
+///
+/// # Returns
+/// * `Result<SwapStep, ProgramError>` - The step result or an error
+///
+/// # Example
+///
+/// let step = compute_swap_step(sqrt_price, sqrt_price_at_next_tick, liquidity, amount_remaining, 3000)?;
+///
+pub fn compute_swap_step(
+    sqrt_price_current_q64: u128,
+    sqrt_price_target_q64: u128,
+    liquidity: u128,
+    amount_remaining: u64,
+    fee_pips: u32,
+) -> Result<SwapStep> {
+    compute_swap_step_u128(
+        sqrt_price_current_q64,
+        sqrt_price_target_q64,
+        liquidity,
+        amount_remaining as u128,
+        fee_pips,
+    )
+}
+
+/// [`compute_swap_step`] for amounts wider than `u64`, as used by the pool's swap loop
+pub(crate) fn compute_swap_step_u128(
+    sqrt_price_current_q64: u128,
+    sqrt_price_target_q64: u128,
+    liquidity: u128,
+    amount_remaining: u128,
+    fee_pips: u32,
+) -> Result<SwapStep> {
+    let fee_pips = fee_pips as u128;
+    if fee_pips >= FEE_PIPS_DENOMINATOR {
+        return Err(ErrorCode::InvalidFeeTier.into());
+    }
+    let fee_complement = FEE_PIPS_DENOMINATOR - fee_pips;
+    let zero_for_one = sqrt_price_current_q64 >= sqrt_price_target_q64;
+
+    // Input available to move the price once the fee is set aside, rounded down
+    let amount_remaining_less_fee =
+        mul_div_floor(amount_remaining, fee_complement, FEE_PIPS_DENOMINATOR)?;
+
+    // Input needed to reach the target, rounded up in the pool's favour
+    let amount_in_to_target = if zero_for_one {
+        get_amount_0_delta(
+            sqrt_price_target_q64,
+            sqrt_price_current_q64,
+            liquidity,
+            true,
+        )?
+    } else {
+        get_amount_1_delta(
+            sqrt_price_current_q64,
+            sqrt_price_target_q64,
+            liquidity,
+            true,
+        )?
+    };
+
+    let (sqrt_price_next, amount_in, fee_amount) =
+        if amount_remaining_less_fee >= amount_in_to_target {
+            // The target is reached; the fee is charged on the input actually used
+            let fee_amount = mul_div_ceil(amount_in_to_target, fee_pips, fee_complement)?;
+            (sqrt_price_target_q64, amount_in_to_target, fee_amount)
+        } else {
+            // The input runs out first; whatever is not used to move the price is the fee
+            let sqrt_price_next = if zero_for_one {
+                compute_next_sqrt_price_from_amount0_in(
+                    sqrt_price_current_q64,
+                    liquidity,
+                    amount_remaining_less_fee,
+                )?
+            } else {
+                compute_next_sqrt_price_from_amount1_in(
+                    sqrt_price_current_q64,
+                    liquidity,
+                    amount_remaining_less_fee,
+                )?
+            };
+            (
+                sqrt_price_next,
+                amount_remaining_less_fee,
+                amount_remaining - amount_remaining_less_fee,
+            )
+        };
+
+    // Output for the price move, rounded down in the pool's favour
+    let amount_out = if zero_for_one {
+        get_amount_1_delta(sqrt_price_next, sqrt_price_current_q64, liquidity, false)?
+    } else {
+        get_amount_0_delta(sqrt_price_current_q64, sqrt_price_next, liquidity, false)?
+    };
+
+    Ok(SwapStep {
+        sqrt_price_next,
+        amount_in,
+        amount_out,
+        fee_amount,
+    })
+}
+
+/// Calculates the fee growth per unit of liquidity produced by a fee amount
+///
+/// # Arguments
+/// * `fee_amount` - The fee collected, in token units
+/// * `liquidity` - The active liquidity the fee is shared between
+///
+/// # Returns
+/// * `Result<u128, ProgramError>` - The fee growth in Q64.64 format, or 0 without liquidity
+pub fn get_fee_growth_delta_q64(fee_amount: u128, liquidity: u128) -> Result<u128> {
+    if liquidity == 0 {
+        return Ok(0);
+    }
+    mul_div_floor(fee_amount, Q64, liquidity)
+}
+
+/// Calculates the fee growth per unit of liquidity inside a tick range
+///
+/// This is the single source of the fee-growth-inside computation; fee collection and
+/// position rebalancing both go through it. Fee growth counters only ever increase and
+/// are allowed to wrap, so all arithmetic here is wrapping and only differences of its
+/// results are meaningful.
+///
+/// A tick's fee growth outside is the growth on the side of the tick away from the
+/// current tick, and `TickData::cross` flips it whenever the price crosses the tick. So
+/// the lower tick's value is the growth below the range only while `tick_current` is at
+/// or above it, and the upper tick's value is the growth above the range only while
+/// `tick_current` is below it; otherwise the growth is the global growth minus the
+/// tick's value. A current tick equal to `tick_upper` is above the range.
+///
+/// # Arguments
+/// * `tick_lower` - The lower tick of the range
+/// * `tick_upper` - The upper tick of the range
+/// * `tick_current` - The pool's current tick
+/// * `fee_growth_global_q64` - The pool's global fee growth for the token
+/// * `fee_growth_outside_lower_q64` - The lower tick's fee growth outside for the token
+/// * `fee_growth_outside_upper_q64` - The upper tick's fee growth outside for the token
+///
+/// # Returns
+/// * `u128` - The fee growth inside the range in Q64.64 format
+pub fn get_fee_growth_inside(
+    tick_lower: i32,
+    tick_upper: i32,
+    tick_current: i32,
+    fee_growth_global_q64: u128,
+    fee_growth_outside_lower_q64: u128,
+    fee_growth_outside_upper_q64: u128,
+) -> u128 {
+    // Fee growth below the lower tick
+    let fee_growth_below = if tick_current >= tick_lower {
+        fee_growth_outside_lower_q64
+    } else {
+        fee_growth_global_q64.wrapping_sub(fee_growth_outside_lower_q64)
+    };
+
+    // Fee growth above the upper tick
+    let fee_growth_above = if tick_current < tick_upper {
+        fee_growth_outside_upper_q64
+    } else {
+        fee_growth_global_q64.wrapping_sub(fee_growth_outside_upper_q64)
+    };
+
+    fee_growth_global_q64
+        .wrapping_sub(fee_growth_below)
+        .wrapping_sub(fee_growth_above)
+}
+
+/// Calculates the fees earned by an amount of liquidity over a fee growth interval
+///
+/// # Arguments
+/// * `liquidity` - The liquidity that earned the fees
+/// * `fee_growth_inside_q64` - The current fee growth inside the range
+/// * `fee_growth_inside_last_q64` - The fee growth inside the range at the last update
+///
+/// # Returns
+/// * `Result<u64, ProgramError>` - The fees earned, rounded down, or an error
+pub fn get_fees_earned(
+    liquidity: u128,
+    fee_growth_inside_q64: u128,
+    fee_growth_inside_last_q64: u128,
+) -> Result<u64> {
+    let fee_growth_delta_q64 = fee_growth_inside_q64.wrapping_sub(fee_growth_inside_last_q64);
+    let fees = mul_div_floor(liquidity, fee_growth_delta_q64, Q64)?;
+    u64::try_from(fees).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+/// Calculates the price impact of a move from one sqrt price to another, in basis points
+///
+/// The impact is the relative change of the price (not of the sqrt price),
+/// `|P_after - P_before| / P_before`, whichever way the price moved. It is rounded down
+/// and saturates at `u64::MAX` for moves multiplying the price by 2^64 or more.
+///
+/// # Arguments
+/// * `sqrt_price_before_q64` - The sqrt price before the move, in Q64.64
+/// * `sqrt_price_after_q64` - The sqrt price after the move, in Q64.64
+///
+/// # Returns
+/// * `Result<u64, ProgramError>` - The impact in basis points, or `MathOverflow` if
+///   `sqrt_price_before_q64` is zero
+pub fn price_impact_bps(sqrt_price_before_q64: u128, sqrt_price_after_q64: u128) -> Result<u64> {
+    if sqrt_price_before_q64 == 0 {
+        return Err(ErrorCode::MathOverflow.into());
+    }
+    // Ratio of the sqrt prices in Q64.64. Past 2^32 its square, the price ratio, no longer
+    // fits the result.
+    let sqrt_ratio_q64 =
+        (U256::from(sqrt_price_after_q64) << 64) / U256::from(sqrt_price_before_q64);
+    if sqrt_ratio_q64 >= U256::one() << 96 {
+        return Ok(u64::MAX);
+    }
+    let price_ratio_q128 = sqrt_ratio_q64 * sqrt_ratio_q64;
+    let one_q128 = U256::one() << 128;
+    let change_q128 = if price_ratio_q128 >= one_q128 {
+        price_ratio_q128 - one_q128
+    } else {
+        one_q128 - price_ratio_q128
+    };
+    let impact_bps = (change_q128 * U256::from(BPS_DENOMINATOR)) >> 128;
+    Ok(if impact_bps > U256::from(u64::MAX) {
+        u64::MAX
+    } else {
+        impact_bps.as_u64()
+    })
+}
+
+/// A signed Q64.64 fixed-point number
+///
+/// Wraps an `i128` holding the value scaled by 2^64, for quantities that can go negative
+/// (percentages, accelerations, differences of prices). The operators saturate at
+/// `FixedQ64::MIN` and `FixedQ64::MAX` instead of panicking, and every operation has a
+/// `checked_*` counterpart that returns `None` instead. Products and quotients are
+/// truncated toward zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FixedQ64(i128);
+
+impl FixedQ64 {
+    /// 0.0
+    pub const ZERO: Self = Self(0);
+    /// 1.0
+    pub const ONE: Self = Self(1 << 64);
+    /// The most negative representable value, -2^63.
+    pub const MIN: Self = Self(i128::MIN);
+    /// The largest representable value, just below 2^63.
+    pub const MAX: Self = Self(i128::MAX);
+
+    /// Creates a value from its raw representation, scaled by 2^64.
+    pub const fn from_raw(raw: i128) -> Self {
+        Self(raw)
+    }
+
+    /// Returns the raw representation, scaled by 2^64.
+    pub const fn raw(self) -> i128 {
+        self.0
+    }
+
+    /// Converts an integer. Every `i64` is exactly representable.
+    pub const fn from_i64(value: i64) -> Self {
+        Self((value as i128) << 64)
+    }
+
+    /// Returns the integer part, truncated toward zero.
+    pub const fn trunc_to_i128(self) -> i128 {
+        self.0 / (1 << 64)
+    }
+
+    /// Converts to the nearest `f64`. Meant for tests and logs, not on-chain arithmetic.
+    pub fn to_f64_lossy(self) -> f64 {
+        self.0 as f64 / Q64 as f64
+    }
+
+    /// Adds two values, returning `None` on overflow.
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    /// Subtracts `rhs`, returning `None` on overflow.
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self)
+    }
+
+    /// Multiplies two values, returning `None` on overflow.
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let magnitude =
+            (U256::from(self.0.unsigned_abs()) * U256::from(rhs.0.unsigned_abs())) >> 64;
+        Self::from_magnitude(magnitude, (self.0 < 0) != (rhs.0 < 0))
+    }
+
+    /// Divides by `rhs`, returning `None` if `rhs` is zero or the quotient overflows.
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        if rhs.0 == 0 {
+            return None;
+        }
+        let magnitude =
+            (U256::from(self.0.unsigned_abs()) << 64) / U256::from(rhs.0.unsigned_abs());
+        Self::from_magnitude(magnitude, (self.0 < 0) != (rhs.0 < 0))
+    }
+
+    /// Negates the value, returning `None` for `FixedQ64::MIN`.
+    pub fn checked_neg(self) -> Option<Self> {
+        self.0.checked_neg().map(Self)
+    }
+
+    /// Returns the absolute value, or `None` for `FixedQ64::MIN`.
+    pub fn checked_abs(self) -> Option<Self> {
+        self.0.checked_abs().map(Self)
+    }
+
+    /// Returns the absolute value, saturating `FixedQ64::MIN` to `FixedQ64::MAX`.
+    pub fn abs(self) -> Self {
+        Self(self.0.saturating_abs())
+    }
+
+    /// Returns -1.0, 0.0 or 1.0 according to the sign of the value.
+    pub fn signum(self) -> Self {
+        Self(self.0.signum() << 64)
+    }
+
+    /// Returns the bound a result of the given sign saturates to.
+    fn saturated(negative: bool) -> Self {
+        if negative {
+            Self::MIN
+        } else {
+            Self::MAX
+        }
+    }
+
+    /// Applies a sign to a magnitude, returning `None` if the result is out of range.
+    fn from_magnitude(magnitude: U256, negative: bool) -> Option<Self> {
+        let limit = if negative {
+            i128::MIN.unsigned_abs()
+        } else {
+            i128::MAX as u128
+        };
+        if magnitude > U256::from(limit) {
+            return None;
+        }
+        let magnitude = magnitude.as_u128() as i128;
+        Some(Self(if negative {
+            magnitude.wrapping_neg()
+        } else {
+            magnitude
+        }))
+    }
+}
+
+impl std::ops::Add for FixedQ64 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl std::ops::Sub for FixedQ64 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl std::ops::Mul for FixedQ64 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.checked_mul(rhs)
+            .unwrap_or_else(|| Self::saturated((self.0 < 0) != (rhs.0 < 0)))
+    }
+}
+
+impl std::ops::Div for FixedQ64 {
+    type Output = Self;
+
+    /// Dividing by zero saturates toward the sign of the dividend, and gives zero for 0 / 0.
+    fn div(self, rhs: Self) -> Self {
+        if rhs.0 == 0 {
+            return if self.0 == 0 {
+                Self::ZERO
+            } else {
+                Self::saturated(self.0 < 0)
+            };
+        }
+        self.checked_div(rhs)
+            .unwrap_or_else(|| Self::saturated((self.0 < 0) != (rhs.0 < 0)))
+    }
+}
+
+impl std::ops::Neg for FixedQ64 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(self.0.saturating_neg())
+    }
+}
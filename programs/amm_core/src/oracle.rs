@@ -0,0 +1,76 @@
+use anchor_lang::prelude::*;
+/// Time-weighted price oracle of a pool.
+///
+/// The pool accumulates its current tick, and the inverse of its active liquidity, over
+/// time. Subtracting the accumulators at two points in time and dividing by the seconds
+/// between them gives the arithmetic mean tick (a geometric mean price) over that window,
+/// which a single block cannot move far without holding the price for the whole window.
+///
+/// The accumulators are recorded into a ring buffer of `Observation`s at most once per
+/// timestamp, at the start of every swap, so each observation captures the state that
+/// held since the previous one.
+use primitive_types::U256;
+
+/// Number of observations kept by a pool.
+pub const OBSERVATION_CAPACITY: usize = 16;
+
+/// The pool's oracle accumulators at a point in time.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Observation {
+    /// Unix timestamp of the observation.
+    pub timestamp: i64,
+    /// Sum of the pool's current tick over every second up to `timestamp`.
+    pub tick_cumulative: i64,
+    /// Sum of `1 / liquidity` over every second up to `timestamp`, in Q64.64.
+    pub seconds_per_liquidity_cumulative_q64: u128,
+}
+
+impl Observation {
+    /// Size of a serialized observation in bytes.
+    pub const LEN: usize = 8 + 8 + 16;
+
+    /// Returns the observation at `timestamp`, assuming `tick` and `liquidity` held since
+    /// this one. `timestamp` must not precede `self.timestamp`.
+    ///
+    /// Both accumulators wrap on overflow; only differences between observations are
+    /// meaningful.
+    pub fn transform(&self, timestamp: i64, tick: i32, liquidity: u128) -> Observation {
+        let elapsed = timestamp - self.timestamp;
+        Observation {
+            timestamp,
+            tick_cumulative: self
+                .tick_cumulative
+                .wrapping_add((tick as i64).wrapping_mul(elapsed)),
+            seconds_per_liquidity_cumulative_q64: self
+                .seconds_per_liquidity_cumulative_q64
+                .wrapping_add(((elapsed as u128) << 64) / liquidity.max(1)),
+        }
+    }
+
+    /// Returns the observation at `timestamp`, interpolated between `before` and `after`.
+    ///
+    /// `timestamp` must lie between the timestamps of the two observations, and `before`
+    /// must be strictly older than `after`.
+    pub fn interpolate(before: &Observation, after: &Observation, timestamp: i64) -> Observation {
+        let elapsed = (timestamp - before.timestamp) as i128;
+        let span = (after.timestamp - before.timestamp) as i128;
+        let tick_delta = after.tick_cumulative.wrapping_sub(before.tick_cumulative) as i128;
+        let seconds_per_liquidity_delta = after
+            .seconds_per_liquidity_cumulative_q64
+            .wrapping_sub(before.seconds_per_liquidity_cumulative_q64);
+        Observation {
+            timestamp,
+            // The tick is constant between two observations, so this division is exact.
+            tick_cumulative: before
+                .tick_cumulative
+                .wrapping_add((tick_delta * elapsed / span) as i64),
+            seconds_per_liquidity_cumulative_q64: before
+                .seconds_per_liquidity_cumulative_q64
+                .wrapping_add(
+                    (U256::from(seconds_per_liquidity_delta) * U256::from(elapsed as u128)
+                        / U256::from(span as u128))
+                    .as_u128(),
+                ),
+        }
+    }
+}
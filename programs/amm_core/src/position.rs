@@ -4,22 +4,28 @@
 /// price ranges, defined by a lower and upper tick. The `PositionData` account
 /// stores all relevant information for a single user's position in a particular pool.
 use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
 
 use crate::errors::ErrorCode;
+use crate::math;
+use crate::state::pool::Pool;
+use crate::tick::TickData;
 
 /// Represents the state of a user's concentrated liquidity position.
 ///
 /// For the MVP, this struct focuses on the core attributes of a position:
-/// ownership, the associated pool, the tick boundaries, and the amount of liquidity.
-/// Fields related to NFT representation, fee growth snapshots, and owed tokens
-/// are omitted for simplification as per the MVP scope.
+/// ownership, the associated pool, the tick boundaries, the amount of liquidity, the
+/// fee growth snapshots, the tokens owed to the owner and the price it was opened at.
 ///
-/// Accounts of this type are typically PDAs derived from elements like the owner's
-/// key, the pool key, and tick indices to ensure uniqueness.
+/// Positions opened with `mint_position` are PDAs of `[b"position", pool, owner,
+/// tick_lower_index, tick_upper_index, position_index]` and stay with their owner. Positions opened with
+/// `mint_position_nft` are PDAs of `[b"position", position_mint]` and belong to whoever
+/// holds the position NFT.
 #[account]
 #[derive(Default, Debug)]
 pub struct PositionData {
-    /// The public key of the account that owns this position.
+    /// The public key of the account that owns this position. For a position with an NFT,
+    /// the account that minted it; the current owner is the NFT holder.
     pub owner: Pubkey,
     /// The public key of the liquidity pool this position belongs to.
     pub pool: Pubkey,
@@ -33,21 +39,234 @@ pub struct PositionData {
     /// This is an abstract measure and its relation to token amounts depends
     /// on the price range (tick_lower_index to tick_upper_index).
     pub liquidity: u128,
-    // MVP Simplification:
-    // - nft_id: Pubkey (or u64 if it's an ID for an off-chain NFT)
-    // - fee_growth_inside_0_last_x64: u128
-    // - fee_growth_inside_1_last_x64: u128
-    // - tokens_owed_0: u64
-    // - tokens_owed_1: u64
+    /// Amount of token0 owed to the owner and not yet collected.
+    pub tokens_owed_0: u64,
+    /// Amount of token1 owed to the owner and not yet collected.
+    pub tokens_owed_1: u64,
+    /// Token0 fee growth inside the position's range as of its last fee update, in Q64.64.
+    pub fee_growth_inside_0_last_q64: u128,
+    /// Token1 fee growth inside the position's range as of its last fee update, in Q64.64.
+    pub fee_growth_inside_1_last_q64: u128,
+    /// Mint of the position NFT, or the default pubkey if the position has none.
+    pub position_mint: Pubkey,
+    /// Index of the position among those its owner opened in the pool with
+    /// `mint_position`, taken from the owner's `PositionCounter`. Zero for positions with
+    /// an NFT.
+    pub position_index: u64,
+    /// True if the position is listed in its owner's `OwnerPositionIndex`.
+    pub in_owner_index: bool,
+    /// The position listed after this one in its owner's `OwnerPositionIndex`, or the
+    /// default pubkey if it is the last one or not listed.
+    pub next_in_owner_index: Pubkey,
+    /// The pool's sqrt price when the position was opened or last moved to a new range, in
+    /// Q64.64. Impermanent loss is measured against it. For a position migrated from a layout without it, the pool's
+    /// sqrt price at the migration.
+    pub entry_sqrt_price_q64: u128,
+}
+
+/// Counts the positions an owner has opened in a pool with `mint_position`, a PDA of
+/// `[b"position_counter", pool, owner]`.
+///
+/// The count is the index of the owner's next position, so the owner's positions in the
+/// pool are the ones at indices `0..next_index` that have not been closed.
+#[account]
+#[derive(Default, Debug)]
+pub struct PositionCounter {
+    /// Index the owner's next position in the pool takes.
+    pub next_index: u64,
+}
+
+impl PositionCounter {
+    /// Discriminator (8) + next_index (8)
+    pub const LEN: usize = 8 + 8;
+
+    /// Returns the index for a new position and advances the counter past it.
+    pub fn take_index(&mut self) -> Result<u64> {
+        let index = self.next_index;
+        self.next_index = index.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(index)
+    }
+}
+
+/// The positions an owner chose to list for other programs, a PDA of
+/// `[b"owner_index", owner]` created with `register_owner_index`.
+///
+/// The positions form a linked list: `head` is the most recently listed one and each
+/// position's `next_in_owner_index` points to the one listed before it, down to the
+/// default pubkey. A program can read an owner's positions by following the links from
+/// `head`. Positions are listed when minted with the index account and unlisted when
+/// closed. A position with an NFT stays listed under the owner who minted it after the
+/// NFT changes hands, so readers should check who holds the NFT.
+#[account]
+#[derive(Default, Debug)]
+pub struct OwnerPositionIndex {
+    /// The owner whose positions are listed.
+    pub owner: Pubkey,
+    /// The most recently listed position, or the default pubkey if none is listed.
+    pub head: Pubkey,
+    /// Number of listed positions.
+    pub count: u64,
+}
+
+impl OwnerPositionIndex {
+    /// Discriminator (8) + owner (32) + head (32) + count (8)
+    pub const LEN: usize = 8 + 32 + 32 + 8;
+
+    /// Derives the PDA of `owner`'s position index.
+    pub fn address(owner: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"owner_index".as_ref(), owner.as_ref()], &crate::ID).0
+    }
+
+    /// Lists `position`, at address `position_key`, at the head of the index.
+    pub fn push(&mut self, position_key: Pubkey, position: &mut PositionData) -> Result<()> {
+        require!(!position.in_owner_index, ErrorCode::InvalidOwnerIndexLink);
+        position.in_owner_index = true;
+        position.next_in_owner_index = self.head;
+        self.head = position_key;
+        self.count = self.count.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Unlists `position`, at address `position_key`.
+    ///
+    /// Unless the position is the head, `previous` must be the listed position whose
+    /// `next_in_owner_index` is `position_key`; it is relinked past the position. Fails
+    /// with `InvalidOwnerIndexLink` if the position is not listed or `previous` does not
+    /// link to it.
+    pub fn remove(
+        &mut self,
+        position_key: Pubkey,
+        position: &mut PositionData,
+        previous: Option<&mut PositionData>,
+    ) -> Result<()> {
+        require!(position.in_owner_index, ErrorCode::InvalidOwnerIndexLink);
+        if self.head == position_key {
+            self.head = position.next_in_owner_index;
+        } else {
+            let previous = previous.ok_or(ErrorCode::InvalidOwnerIndexLink)?;
+            require!(
+                previous.in_owner_index
+                    && previous.owner == position.owner
+                    && previous.next_in_owner_index == position_key,
+                ErrorCode::InvalidOwnerIndexLink
+            );
+            previous.next_in_owner_index = position.next_in_owner_index;
+        }
+        position.in_owner_index = false;
+        position.next_in_owner_index = Pubkey::default();
+        self.count = self.count.checked_sub(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Emitted when a position is minted.
+#[event]
+pub struct MintPositionEvent {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    /// Liquidity the position was minted with.
+    pub liquidity: u128,
+    /// Token0 deposited for the liquidity.
+    pub amount0: u64,
+    /// Token1 deposited for the liquidity.
+    pub amount1: u64,
+}
+
+/// Emitted when liquidity is removed from a position.
+#[event]
+pub struct DecreaseLiquidityEvent {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    /// Liquidity removed.
+    pub liquidity_delta: u128,
+    /// Liquidity left in the position.
+    pub liquidity: u128,
+    /// Token0 paid out for the removed liquidity.
+    pub amount0: u64,
+    /// Token1 paid out for the removed liquidity.
+    pub amount1: u64,
+}
+
+/// Emitted when a position's owner collects fees.
+#[event]
+pub struct CollectFeesEvent {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    /// Token0 fees paid out.
+    pub amount0: u64,
+    /// Token1 fees paid out.
+    pub amount1: u64,
 }
 
 impl PositionData {
-    /// Discriminator (8) + owner (32) + pool (32) + tick_lower_index (4) + tick_upper_index (4) + liquidity (16)
+    /// Discriminator (8) + owner (32) + pool (32) + tick_lower_index (4) + tick_upper_index (4) +
+    /// liquidity (16) + tokens_owed_0 (8) + tokens_owed_1 (8) + fee_growth_inside_0_last_q64 (16) +
+    /// fee_growth_inside_1_last_q64 (16) + position_mint (32) + position_index (8) +
+    /// in_owner_index (1) + next_in_owner_index (32) + entry_sqrt_price_q64 (16)
     /// Note: Anchor adds 8 bytes for the discriminator.
-    pub const LEN: usize = 8 + 32 + 32 + 4 + 4 + 16;
+    pub const LEN: usize = 8 + 32 + 32 + 4 + 4 + 16 + 8 + 8 + 16 + 16 + 32 + 8 + 1 + 32 + 16;
+
+    /// Offset at which the fee growth snapshots end. Positions stored in fewer bytes were
+    /// written before positions tracked their fees.
+    pub const FEE_GROWTH_SNAPSHOTS_END: usize = 8 + 32 + 32 + 4 + 4 + 16 + 8 + 8 + 16 + 16;
+
+    /// Offset at which the entry sqrt price ends. Positions stored in fewer bytes were
+    /// written before positions recorded the price they were opened at.
+    pub const ENTRY_SQRT_PRICE_END: usize = Self::LEN;
+
+    /// Reads a position stored under the current layout or an earlier one.
+    ///
+    /// Fields have only ever been appended to `PositionData`, so an older account holds a
+    /// prefix of the current layout. The fields it lacks read as zero: no tokens owed, no
+    /// fee growth snapshots, no NFT, index zero, not listed in an owner index and no entry
+    /// price.
+    pub fn try_deserialize_any_layout(data: &[u8]) -> Result<Self> {
+        let mut padded = data.to_vec();
+        if padded.len() < Self::LEN {
+            padded.resize(Self::LEN, 0);
+        }
+        Self::try_deserialize(&mut padded.as_slice())
+    }
+
+    /// Brings a position read with `try_deserialize_any_layout` from an account of
+    /// `stored_len` bytes up to date with its pool.
+    ///
+    /// A position written before positions tracked their fees has no fee growth snapshots,
+    /// and would otherwise be credited every fee its range earned since the pool was
+    /// created. Its fee accounting starts at the pool's current fee growth instead.
+    ///
+    /// A position written before positions recorded their entry price is given the pool's
+    /// current sqrt price, so its impermanent loss is measured from the migration on.
+    ///
+    /// # Arguments
+    /// * `stored_len` - The size of the account the position was read from.
+    /// * `pool` - The pool the position belongs to.
+    /// * `tick_lower_data` - The data of the position's lower tick.
+    /// * `tick_upper_data` - The data of the position's upper tick.
+    pub fn migrate_layout(
+        &mut self,
+        stored_len: usize,
+        pool: &Pool,
+        tick_lower_data: &TickData,
+        tick_upper_data: &TickData,
+    ) {
+        if stored_len < Self::FEE_GROWTH_SNAPSHOTS_END {
+            self.snapshot_fee_growth_inside(pool, tick_lower_data, tick_upper_data);
+        }
+        if stored_len < Self::ENTRY_SQRT_PRICE_END {
+            self.entry_sqrt_price_q64 = pool.sqrt_price_q64;
+        }
+    }
 
     /// Initializes a new position with the provided parameters.
     ///
+    /// The entry sqrt price is left at zero for the instruction handler to record.
+    ///
     /// # Arguments
     /// * `owner` - The public key of the position's owner.
     /// * `pool` - The public key of the pool this position is for.
@@ -74,6 +293,128 @@ impl PositionData {
         self.tick_lower_index = tick_lower_index;
         self.tick_upper_index = tick_upper_index;
         self.liquidity = liquidity;
+        self.tokens_owed_0 = 0;
+        self.tokens_owed_1 = 0;
+        self.fee_growth_inside_0_last_q64 = 0;
+        self.fee_growth_inside_1_last_q64 = 0;
+        self.position_mint = Pubkey::default();
+        self.position_index = 0;
+        self.in_owner_index = false;
+        self.next_in_owner_index = Pubkey::default();
+        self.entry_sqrt_price_q64 = 0;
         Ok(())
     }
+
+    /// Credits the fees earned inside the position's range since its last update.
+    ///
+    /// Fees are added to `tokens_owed_0`/`tokens_owed_1` and the fee growth snapshots are
+    /// moved to the current fee growth inside the range. Every instruction that changes a
+    /// position's liquidity or range, or collects its fees, must call this first so fees
+    /// are neither lost nor counted twice.
+    ///
+    /// # Arguments
+    /// * `pool` - The pool the position belongs to.
+    /// * `tick_lower_data` - The data of the position's lower tick.
+    /// * `tick_upper_data` - The data of the position's upper tick.
+    pub fn update_fees(
+        &mut self,
+        pool: &Pool,
+        tick_lower_data: &TickData,
+        tick_upper_data: &TickData,
+    ) -> Result<()> {
+        let (fee_growth_inside_0_q64, fee_growth_inside_1_q64) = pool.fee_growth_inside(
+            self.tick_lower_index,
+            tick_lower_data,
+            self.tick_upper_index,
+            tick_upper_data,
+        );
+
+        let fees_0 = math::get_fees_earned(
+            self.liquidity,
+            fee_growth_inside_0_q64,
+            self.fee_growth_inside_0_last_q64,
+        )?;
+        let fees_1 = math::get_fees_earned(
+            self.liquidity,
+            fee_growth_inside_1_q64,
+            self.fee_growth_inside_1_last_q64,
+        )?;
+        self.tokens_owed_0 = self
+            .tokens_owed_0
+            .checked_add(fees_0)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.tokens_owed_1 = self
+            .tokens_owed_1
+            .checked_add(fees_1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        self.fee_growth_inside_0_last_q64 = fee_growth_inside_0_q64;
+        self.fee_growth_inside_1_last_q64 = fee_growth_inside_1_q64;
+        Ok(())
+    }
+
+    /// Starts fee accounting for the position's current range without crediting any fees.
+    ///
+    /// Used once liquidity is placed in a new range, after `update_fees` has settled the
+    /// fees of the previous one.
+    ///
+    /// # Arguments
+    /// * `pool` - The pool the position belongs to.
+    /// * `tick_lower_data` - The data of the position's lower tick.
+    /// * `tick_upper_data` - The data of the position's upper tick.
+    pub fn snapshot_fee_growth_inside(
+        &mut self,
+        pool: &Pool,
+        tick_lower_data: &TickData,
+        tick_upper_data: &TickData,
+    ) {
+        let (fee_growth_inside_0_q64, fee_growth_inside_1_q64) = pool.fee_growth_inside(
+            self.tick_lower_index,
+            tick_lower_data,
+            self.tick_upper_index,
+            tick_upper_data,
+        );
+        self.fee_growth_inside_0_last_q64 = fee_growth_inside_0_q64;
+        self.fee_growth_inside_1_last_q64 = fee_growth_inside_1_q64;
+    }
+
+    /// Checks that the position can be closed.
+    ///
+    /// A position can only be closed once all of its liquidity has been removed and
+    /// all tokens owed to the owner have been collected.
+    ///
+    /// # Errors
+    /// Returns `ErrorCode::PositionNotEmpty` if the position still has liquidity or
+    /// owed tokens.
+    pub fn ensure_closable(&self) -> Result<()> {
+        if self.liquidity != 0 || self.tokens_owed_0 != 0 || self.tokens_owed_1 != 0 {
+            return err!(ErrorCode::PositionNotEmpty);
+        }
+        Ok(())
+    }
+
+    /// Returns whether `signer` may modify the position, collect its fees or close it.
+    ///
+    /// A position without an NFT is held by its `owner`. A position with one is held by
+    /// the owner of `position_token_account`, which must hold the position's NFT.
+    pub fn is_held_by(
+        &self,
+        signer: &Pubkey,
+        position_token_account: Option<&TokenAccount>,
+    ) -> bool {
+        if self.position_mint == Pubkey::default() {
+            return self.owner == *signer;
+        }
+        position_token_account.is_some_and(|token_account| {
+            token_account.mint == self.position_mint
+                && token_account.owner == *signer
+                && token_account.amount == 1
+        })
+    }
+
+    /// Returns whether the pool's current tick lies within `[tick_lower_index,
+    /// tick_upper_index)`, i.e. whether the position's liquidity is active and earning fees.
+    pub fn is_in_range(&self, pool: &Pool) -> bool {
+        pool.current_tick >= self.tick_lower_index && pool.current_tick < self.tick_upper_index
+    }
 }
@@ -0,0 +1,183 @@
+use crate::constants::{ALLOWED_TICK_SPACINGS, BPS_DENOMINATOR, MAX_FEE_RATE, MIN_FEE_RATE};
+use crate::errors::ErrorCode;
+use anchor_lang::prelude::*;
+
+/// Number of liquidity breakpoints in a [`ProtocolFeeCurve`].
+pub const PROTOCOL_FEE_CURVE_BREAKPOINTS: usize = 4;
+
+/// The protocol's share of swap fees as a step function of a pool's active liquidity.
+///
+/// A pool below the first breakpoint uses the first share. Each breakpoint it reaches moves it
+/// to the next share, so a pool at or above the last breakpoint uses the last one.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct ProtocolFeeCurve {
+    /// Active liquidity thresholds, in strictly increasing order.
+    pub tvl_breakpoints: [u128; PROTOCOL_FEE_CURVE_BREAKPOINTS],
+    /// Protocol share of each swap fee, in basis points, for each liquidity range.
+    pub protocol_share_bps: [u16; PROTOCOL_FEE_CURVE_BREAKPOINTS + 1],
+}
+
+impl ProtocolFeeCurve {
+    /// Size of a serialized `ProtocolFeeCurve`.
+    pub const LEN: usize = 16 * PROTOCOL_FEE_CURVE_BREAKPOINTS // tvl_breakpoints
+        + 2 * (PROTOCOL_FEE_CURVE_BREAKPOINTS + 1); // protocol_share_bps
+
+    /// Fails with `InvalidProtocolFeeCurve` unless the breakpoints strictly increase and
+    /// every share is at most 10000 basis points.
+    pub fn validate(&self) -> Result<()> {
+        let increasing = self
+            .tvl_breakpoints
+            .windows(2)
+            .all(|pair| pair[0] < pair[1]);
+        let shares_in_range = self
+            .protocol_share_bps
+            .iter()
+            .all(|&share| share as u128 <= BPS_DENOMINATOR);
+        require!(
+            increasing && shares_in_range,
+            ErrorCode::InvalidProtocolFeeCurve
+        );
+        Ok(())
+    }
+
+    /// Returns the protocol share, in basis points, for a pool with `liquidity` active.
+    pub fn protocol_share_bps(&self, liquidity: u128) -> u16 {
+        let range = self
+            .tvl_breakpoints
+            .iter()
+            .take_while(|&&breakpoint| liquidity >= breakpoint)
+            .count();
+        self.protocol_share_bps[range]
+    }
+}
+
+/// Most fee tiers a [`FactoryConfig`] can enable.
+pub const MAX_FEE_TIERS: usize = 8;
+
+/// A fee rate pools can be created with, and the tick spacing every pool at that rate uses.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct FeeTier {
+    /// Fee rate, in basis points.
+    pub fee_rate: u16,
+    /// Tick spacing of pools at `fee_rate`.
+    pub tick_spacing: u16,
+}
+
+impl FeeTier {
+    /// Size of a serialized `FeeTier`.
+    pub const LEN: usize = 2 + 2;
+}
+
+/// Protocol-wide settings for pools, a PDA of `[b"factory_config"]`.
+///
+/// Every pool is created with this account as its `factory`, at one of the fee tiers it
+/// enables, and follows its protocol fee curve, applied with `refresh_protocol_share`.
+#[account]
+#[derive(Default, Debug)]
+pub struct FactoryConfig {
+    /// Bump seed for PDA.
+    pub bump: u8,
+    /// Governance authority allowed to change the settings.
+    pub authority: Pubkey,
+    /// The protocol's share of swap fees by pool liquidity.
+    pub protocol_fee_curve: ProtocolFeeCurve,
+    /// Number of populated entries in `fee_tiers`.
+    pub fee_tier_count: u8,
+    /// Fee tiers pools can be created with, in the order they were enabled.
+    pub fee_tiers: [FeeTier; MAX_FEE_TIERS],
+}
+
+impl FactoryConfig {
+    /// The size of the FactoryConfig account in bytes.
+    pub const LEN: usize = 8 // discriminator
+        + 1 // bump
+        + 32 // authority
+        + ProtocolFeeCurve::LEN // protocol_fee_curve
+        + 1 // fee_tier_count
+        + MAX_FEE_TIERS * FeeTier::LEN; // fee_tiers
+
+    /// Initializes the config with its governance `authority` and protocol fee curve, and
+    /// no fee tier enabled.
+    ///
+    /// # Arguments
+    /// * `bump` - The bump seed for the config's PDA.
+    /// * `authority` - The account allowed to change the settings.
+    /// * `protocol_fee_curve` - The protocol's share of swap fees by pool liquidity.
+    pub fn initialize(
+        &mut self,
+        bump: u8,
+        authority: Pubkey,
+        protocol_fee_curve: ProtocolFeeCurve,
+    ) -> Result<()> {
+        protocol_fee_curve.validate()?;
+        self.bump = bump;
+        self.authority = authority;
+        self.protocol_fee_curve = protocol_fee_curve;
+        self.fee_tier_count = 0;
+        self.fee_tiers = [FeeTier::default(); MAX_FEE_TIERS];
+        Ok(())
+    }
+
+    /// Replaces the protocol fee curve. Pools pick it up on their next
+    /// `refresh_protocol_share`.
+    pub fn set_protocol_fee_curve(&mut self, protocol_fee_curve: ProtocolFeeCurve) -> Result<()> {
+        protocol_fee_curve.validate()?;
+        self.protocol_fee_curve = protocol_fee_curve;
+        Ok(())
+    }
+
+    /// Returns the enabled fee tiers, in the order they were enabled.
+    pub fn fee_tiers(&self) -> &[FeeTier] {
+        &self.fee_tiers[..self.fee_tier_count as usize]
+    }
+
+    /// Returns the tick spacing of pools at `fee_rate`, if that fee rate is enabled.
+    pub fn tick_spacing_for(&self, fee_rate: u16) -> Option<u16> {
+        self.fee_tiers()
+            .iter()
+            .find(|tier| tier.fee_rate == fee_rate)
+            .map(|tier| tier.tick_spacing)
+    }
+
+    /// Lets pools be created at `fee_rate`, with `tick_spacing`. A fee rate keeps the tick
+    /// spacing it is first enabled with.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidFeeRate` - The fee rate is outside `MIN_FEE_RATE..=MAX_FEE_RATE`.
+    /// * `InvalidTickSpacing` - The tick spacing is not one of `ALLOWED_TICK_SPACINGS`.
+    /// * `FeeTierAlreadyEnabled` - The fee rate is already enabled.
+    /// * `TooManyFeeTiers` - `MAX_FEE_TIERS` fee tiers are already enabled.
+    pub fn enable_fee_tier(&mut self, fee_rate: u16, tick_spacing: u16) -> Result<()> {
+        require!(
+            (MIN_FEE_RATE..=MAX_FEE_RATE).contains(&fee_rate),
+            ErrorCode::InvalidFeeRate
+        );
+        require!(
+            ALLOWED_TICK_SPACINGS.contains(&tick_spacing),
+            ErrorCode::InvalidTickSpacing
+        );
+        require!(
+            self.tick_spacing_for(fee_rate).is_none(),
+            ErrorCode::FeeTierAlreadyEnabled
+        );
+        let count = self.fee_tier_count as usize;
+        require!(count < MAX_FEE_TIERS, ErrorCode::TooManyFeeTiers);
+
+        self.fee_tiers[count] = FeeTier {
+            fee_rate,
+            tick_spacing,
+        };
+        self.fee_tier_count += 1;
+        Ok(())
+    }
+
+    /// Fails with `InvalidFeeTier` unless `fee_rate` is enabled with `tick_spacing`.
+    pub fn ensure_fee_tier_enabled(&self, fee_rate: u16, tick_spacing: u16) -> Result<()> {
+        require!(
+            self.tick_spacing_for(fee_rate) == Some(tick_spacing),
+            ErrorCode::InvalidFeeTier
+        );
+        Ok(())
+    }
+}
@@ -1 +1,5 @@
+pub mod factory_config;
+pub mod order_book;
 pub mod pool;
+pub mod pool_registry;
+pub mod token_pair;
@@ -0,0 +1,430 @@
+use crate::errors::ErrorCode;
+use anchor_lang::prelude::*;
+
+/// Scale of limit order prices: a price of `ORDER_PRICE_SCALE` means one token1 unit per
+/// token0 unit.
+pub const ORDER_PRICE_SCALE: u128 = 1_000_000;
+
+/// Side of a limit order. Token0 is the base token and token1 the quote token.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum OrderSide {
+    /// Buys token0 with token1.
+    #[default]
+    Bid,
+    /// Sells token0 for token1.
+    Ask,
+}
+
+/// Limit order book attached to a pool, a PDA of `[b"order_book", pool]`.
+///
+/// Token escrowed by open orders is held in two vaults owned by the order book, PDAs of
+/// `[b"order_escrow", order_book, mint]`.
+#[account]
+#[derive(Default, Debug)]
+pub struct OrderBook {
+    /// Bump seed for PDA.
+    pub bump: u8,
+    /// The pool this order book trades against.
+    pub pool_id: Pubkey,
+    /// Orders must be priced at a multiple of this, in `ORDER_PRICE_SCALE` units.
+    pub tick_size: u64,
+    /// Number of orders placed so far. The next order gets this as its `id`.
+    pub order_count: u64,
+    /// Total token0 quantity of open bids.
+    pub bid_volume: u64,
+    /// Total token0 quantity of open asks.
+    pub ask_volume: u64,
+    /// Escrow vault for token0 (base), funded by asks.
+    pub base_escrow: Pubkey,
+    /// Escrow vault for token1 (quote), funded by bids.
+    pub quote_escrow: Pubkey,
+}
+
+impl OrderBook {
+    /// The size of the OrderBook account in bytes.
+    pub const LEN: usize = 8 // discriminator
+        + 1 // bump
+        + 32 // pool_id
+        + 8 // tick_size
+        + 8 // order_count
+        + 8 // bid_volume
+        + 8 // ask_volume
+        + 32 // base_escrow
+        + 32; // quote_escrow
+
+    /// Initializes the order book.
+    ///
+    /// # Arguments
+    /// * `bump` - The bump seed for the order book's PDA.
+    /// * `pool_id` - The pool the order book trades against.
+    /// * `tick_size` - Price increment orders must align with. Must be non-zero.
+    /// * `base_escrow` - Escrow vault for token0.
+    /// * `quote_escrow` - Escrow vault for token1.
+    pub fn initialize(
+        &mut self,
+        bump: u8,
+        pool_id: Pubkey,
+        tick_size: u64,
+        base_escrow: Pubkey,
+        quote_escrow: Pubkey,
+    ) -> Result<()> {
+        require!(tick_size > 0, ErrorCode::InvalidOrderBookTickSize);
+
+        self.bump = bump;
+        self.pool_id = pool_id;
+        self.tick_size = tick_size;
+        self.order_count = 0;
+        self.bid_volume = 0;
+        self.ask_volume = 0;
+        self.base_escrow = base_escrow;
+        self.quote_escrow = quote_escrow;
+        Ok(())
+    }
+
+    /// Fails unless `price` is a non-zero multiple of `tick_size`.
+    pub fn validate_price(&self, price: u64) -> Result<()> {
+        require!(price > 0, ErrorCode::InvalidPrice);
+        require!(
+            price.is_multiple_of(self.tick_size),
+            ErrorCode::PriceNotAlignedWithTick
+        );
+        Ok(())
+    }
+
+    /// Records a new open order and returns its id.
+    pub fn record_order(&mut self, side: OrderSide, quantity: u64) -> Result<u64> {
+        let volume = match side {
+            OrderSide::Bid => &mut self.bid_volume,
+            OrderSide::Ask => &mut self.ask_volume,
+        };
+        *volume = volume
+            .checked_add(quantity)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let id = self.order_count;
+        self.order_count = id.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(id)
+    }
+
+    /// Price a crossing bid and ask trade at: their midpoint, rounded down to a multiple
+    /// of `tick_size`.
+    ///
+    /// Both prices are multiples of `tick_size`, so the match price never leaves the
+    /// `[ask_price, bid_price]` range.
+    pub fn match_price(&self, bid_price: u64, ask_price: u64) -> Result<u64> {
+        require!(bid_price >= ask_price, ErrorCode::OrdersDoNotCross);
+        let midpoint = ((bid_price as u128 + ask_price as u128) / 2) as u64;
+        Ok(midpoint - midpoint % self.tick_size)
+    }
+
+    /// Fills a bid against an ask for as much as both have remaining, and returns the
+    /// token amounts the fill moves out of the escrows.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidOrderSide` - `bid` is not a bid or `ask` is not an ask.
+    /// * `OrderExpired` - Either order expired before `now`.
+    /// * `OrdersDoNotCross` - The bid is priced below the ask.
+    pub fn match_orders(&mut self, bid: &mut Order, ask: &mut Order, now: i64) -> Result<Fill> {
+        require!(
+            bid.side == OrderSide::Bid && ask.side == OrderSide::Ask,
+            ErrorCode::InvalidOrderSide
+        );
+        require!(
+            !bid.is_expired(now) && !ask.is_expired(now),
+            ErrorCode::OrderExpired
+        );
+        let price = self.match_price(bid.price, ask.price)?;
+        let quantity = bid.remaining_quantity().min(ask.remaining_quantity());
+        require!(quantity > 0, ErrorCode::ZeroOrderQuantity);
+
+        // Release the share of the bid's escrow that covers this quantity at its own price.
+        // Summed over all fills of the bid, this is exactly what it escrowed.
+        let bid_escrow_before =
+            Order::escrow_amount(OrderSide::Bid, bid.price, bid.filled_quantity)?;
+        bid.fill(quantity)?;
+        ask.fill(quantity)?;
+        let bid_escrow_after =
+            Order::escrow_amount(OrderSide::Bid, bid.price, bid.filled_quantity)?;
+        let released = bid_escrow_after - bid_escrow_before;
+
+        // The asker is paid at the match price, rounded down; the rest goes back to the bidder.
+        let quote_amount = (quantity as u128 * price as u128 / ORDER_PRICE_SCALE) as u64;
+        self.bid_volume = self
+            .bid_volume
+            .checked_sub(quantity)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.ask_volume = self
+            .ask_volume
+            .checked_sub(quantity)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(Fill {
+            price,
+            quantity,
+            quote_amount,
+            bid_refund: released - quote_amount,
+        })
+    }
+
+    /// Cancels the open remainder of `order`, cancelled by `canceller` at `now`, and
+    /// returns the escrowed tokens to refund to its owner: token1 for bids, token0 for
+    /// asks.
+    ///
+    /// The owner can cancel an order at any time. Once the order has expired, anyone can,
+    /// so stale orders do not hold escrow and rent forever.
+    ///
+    /// # Errors
+    ///
+    /// * `UnauthorizedAccess` - `canceller` is not the owner and the order has not expired.
+    /// * `OrderAlreadyFilled` - Nothing is left of the order to cancel.
+    pub fn cancel_order(&mut self, order: &Order, canceller: &Pubkey, now: i64) -> Result<u64> {
+        require!(
+            *canceller == order.owner || order.is_expired(now),
+            ErrorCode::UnauthorizedAccess
+        );
+        let remaining = order.remaining_quantity();
+        require!(remaining > 0, ErrorCode::OrderAlreadyFilled);
+
+        let volume = match order.side {
+            OrderSide::Bid => &mut self.bid_volume,
+            OrderSide::Ask => &mut self.ask_volume,
+        };
+        *volume = volume
+            .checked_sub(remaining)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        // Fills released the escrow of the filled quantity, so what is left is the escrow
+        // of the whole order less that.
+        let escrowed = Order::escrow_amount(order.side, order.price, order.quantity)?;
+        let released = Order::escrow_amount(order.side, order.price, order.filled_quantity)?;
+        Ok(escrowed - released)
+    }
+
+    /// Matches the best bids against the best asks, one fill at a time, until `max_matches`
+    /// fills are made or the best remaining bid and ask no longer cross.
+    ///
+    /// `bids` must be sorted best first, by price from highest to lowest and then by id, and
+    /// `asks` by price from lowest to highest and then by id. Filled and expired orders are
+    /// skipped. Each fill is applied to its orders and to the book's volumes as it is made,
+    /// so the book is consistent wherever the batch stops and the next batch picks up from
+    /// the orders' remaining quantities.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidOrderSide` - An order in `bids` is not a bid or one in `asks` is not an ask.
+    /// * `OrderBatchNotSorted` - The orders of a side are not in priority order, or an order
+    ///   is passed twice.
+    pub fn match_batch(
+        &mut self,
+        bids: &mut [&mut Order],
+        asks: &mut [&mut Order],
+        max_matches: usize,
+        now: i64,
+    ) -> Result<Vec<BatchFill>> {
+        for (side, orders) in [(OrderSide::Bid, &*bids), (OrderSide::Ask, &*asks)] {
+            require!(
+                orders.iter().all(|order| order.side == side),
+                ErrorCode::InvalidOrderSide
+            );
+            require!(
+                orders
+                    .windows(2)
+                    .all(|pair| pair[0].has_priority_over(pair[1])),
+                ErrorCode::OrderBatchNotSorted
+            );
+        }
+
+        let mut fills = Vec::new();
+        let (mut bid_index, mut ask_index) = (0, 0);
+        while fills.len() < max_matches {
+            bid_index += bids[bid_index..]
+                .iter()
+                .take_while(|bid| !bid.can_fill(now))
+                .count();
+            ask_index += asks[ask_index..]
+                .iter()
+                .take_while(|ask| !ask.can_fill(now))
+                .count();
+            if bid_index == bids.len()
+                || ask_index == asks.len()
+                || bids[bid_index].price < asks[ask_index].price
+            {
+                break;
+            }
+            let fill = self.match_orders(bids[bid_index], asks[ask_index], now)?;
+            fills.push(BatchFill {
+                bid_index,
+                ask_index,
+                fill,
+            });
+        }
+        Ok(fills)
+    }
+}
+
+/// A fill made by [`OrderBook::match_batch`] between `bids[bid_index]` and
+/// `asks[ask_index]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchFill {
+    pub bid_index: usize,
+    pub ask_index: usize,
+    pub fill: Fill,
+}
+
+/// Result of matching a bid against an ask.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fill {
+    /// Price the orders traded at, scaled by `ORDER_PRICE_SCALE`.
+    pub price: u64,
+    /// Token0 quantity traded, moved from the base escrow to the bidder.
+    pub quantity: u64,
+    /// Token1 paid for the quantity, moved from the quote escrow to the asker.
+    pub quote_amount: u64,
+    /// Token1 the bid escrowed above the match price, returned from the quote escrow to
+    /// the bidder.
+    pub bid_refund: u64,
+}
+
+/// Emitted when a bid and an ask are matched.
+#[event]
+pub struct TradeExecuted {
+    pub order_book: Pubkey,
+    pub bid_order_id: u64,
+    pub ask_order_id: u64,
+    /// Fill price in token1 per token0, scaled by `ORDER_PRICE_SCALE`.
+    pub price: u64,
+    /// Token0 quantity filled.
+    pub quantity: u64,
+}
+
+/// A limit order, a PDA of `[b"order", order_book, id.to_le_bytes()]`.
+#[account]
+#[derive(Default, Debug)]
+pub struct Order {
+    /// Bump seed for PDA.
+    pub bump: u8,
+    /// The order book the order was placed on.
+    pub order_book: Pubkey,
+    /// The account that placed the order.
+    pub owner: Pubkey,
+    /// Sequential id within the order book.
+    pub id: u64,
+    /// Whether the order buys or sells token0.
+    pub side: OrderSide,
+    /// Limit price in token1 per token0, scaled by `ORDER_PRICE_SCALE`.
+    pub price: u64,
+    /// Token0 quantity of the order.
+    pub quantity: u64,
+    /// Token0 quantity filled so far.
+    pub filled_quantity: u64,
+    /// Unix timestamp the order was placed at.
+    pub created_at: i64,
+    /// Unix timestamp after which the order can no longer fill. Zero for no expiry.
+    pub expires_at: i64,
+}
+
+/// Parameters for placing a new order.
+#[derive(Clone, Copy, Debug)]
+pub struct PlaceOrderParams {
+    pub bump: u8,
+    pub order_book: Pubkey,
+    pub owner: Pubkey,
+    pub id: u64,
+    pub side: OrderSide,
+    pub price: u64,
+    pub quantity: u64,
+    pub created_at: i64,
+    pub expires_at: i64,
+}
+
+impl Order {
+    /// The size of the Order account in bytes.
+    pub const LEN: usize = 8 // discriminator
+        + 1 // bump
+        + 32 // order_book
+        + 32 // owner
+        + 8 // id
+        + 1 // side
+        + 8 // price
+        + 8 // quantity
+        + 8 // filled_quantity
+        + 8 // created_at
+        + 8; // expires_at
+
+    /// Initializes a new, unfilled order.
+    ///
+    /// Fails with `OrderExpired` if `expires_at` is set and not after `created_at`.
+    pub fn initialize(&mut self, params: PlaceOrderParams) -> Result<()> {
+        require!(params.quantity > 0, ErrorCode::ZeroOrderQuantity);
+        require!(
+            params.expires_at == 0 || params.expires_at > params.created_at,
+            ErrorCode::OrderExpired
+        );
+
+        self.bump = params.bump;
+        self.order_book = params.order_book;
+        self.owner = params.owner;
+        self.id = params.id;
+        self.side = params.side;
+        self.price = params.price;
+        self.quantity = params.quantity;
+        self.filled_quantity = 0;
+        self.created_at = params.created_at;
+        self.expires_at = params.expires_at;
+        Ok(())
+    }
+
+    /// Token0 quantity still open.
+    pub fn remaining_quantity(&self) -> u64 {
+        self.quantity - self.filled_quantity
+    }
+
+    /// Whether the order has been completely filled.
+    pub fn is_filled(&self) -> bool {
+        self.filled_quantity == self.quantity
+    }
+
+    /// Whether the order has an expiry and `now` is past it.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at != 0 && now > self.expires_at
+    }
+
+    /// Whether the order is still open for fills at `now`.
+    pub fn can_fill(&self, now: i64) -> bool {
+        !self.is_filled() && !self.is_expired(now)
+    }
+
+    /// Whether the order fills before `other`, an order on the same side: bids at higher
+    /// prices and asks at lower prices go first, then older orders at the same price.
+    pub fn has_priority_over(&self, other: &Order) -> bool {
+        let better_price = match self.side {
+            OrderSide::Bid => self.price > other.price,
+            OrderSide::Ask => self.price < other.price,
+        };
+        better_price || (self.price == other.price && self.id < other.id)
+    }
+
+    /// Records a fill of `quantity`, which must not exceed the remaining quantity.
+    pub fn fill(&mut self, quantity: u64) -> Result<()> {
+        require!(
+            quantity <= self.remaining_quantity(),
+            ErrorCode::InvalidInput
+        );
+        self.filled_quantity += quantity;
+        Ok(())
+    }
+
+    /// Amount of tokens to escrow for an order: token1 for bids, token0 for asks.
+    ///
+    /// Bids escrow `quantity * price / ORDER_PRICE_SCALE`, rounded up in the book's favour.
+    pub fn escrow_amount(side: OrderSide, price: u64, quantity: u64) -> Result<u64> {
+        match side {
+            OrderSide::Bid => {
+                let amount = (quantity as u128 * price as u128).div_ceil(ORDER_PRICE_SCALE);
+                u64::try_from(amount).map_err(|_| error!(ErrorCode::MathOverflow))
+            }
+            OrderSide::Ask => Ok(quantity),
+        }
+    }
+}
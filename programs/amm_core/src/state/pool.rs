@@ -1,15 +1,25 @@
 use crate::constants::BPS_DENOMINATOR;
-use crate::constants::MAX_SQRT_PRICE;
+use crate::constants::{
+    DEFAULT_MAX_ORACLE_AGE_SLOTS, MAX_FEE_RATE, MAX_SQRT_PRICE, MAX_SWAP_ITERATIONS, MAX_TICK,
+    MIN_FEE_RATE, MIN_SQRT_PRICE, MIN_TICK, VOLUME_WINDOW_SECS,
+};
 use crate::errors::ErrorCode;
+use crate::flog;
 use crate::math;
-use crate::tick::TickData;
-use crate::tick_bitmap;
+use crate::oracle::{Observation, OBSERVATION_CAPACITY};
+use crate::state::factory_config::ProtocolFeeCurve;
+use crate::tick::{self, TickData};
+use crate::tick_array::{self, TickArray};
+use crate::tick_bitmap::{self, TickBitmap};
 use anchor_lang::prelude::{AccountLoader, *}; // Added AccountLoader
-use std::collections::BTreeMap; // MIN_SQRT_PRICE is 0, handled by direct check
+use std::collections::BTreeMap;
 
 /// Maximum expected size for the serialized tick_bitmap_data in bytes.
 const MAX_SERIALIZED_BITMAP_BYTES: usize = 1280; // Based on original LEN: (2+8)*128
 
+/// Maximum number of tick bitmap words a pool can move out into `TickBitmap` accounts.
+pub const MAX_EXTERNAL_BITMAP_WORDS: usize = 256;
+
 /// Defines the state for a liquidity pool in the Fluxa AMM.
 ///
 /// For the MVP, this struct holds the core attributes necessary for pool
@@ -20,8 +30,7 @@ const MAX_SERIALIZED_BITMAP_BYTES: usize = 1280; // Based on original LEN: (2+8)
 pub struct Pool {
     /// Bump seed for PDA.
     pub bump: u8,
-    /// The factory that created this pool.
-    /// Can be a placeholder (e.g., system_program) for MVP if no factory instruction.
+    /// The factory config that created this pool, whose authority administers it.
     pub factory: Pubkey,
     /// The mint address of the first token (token0).
     pub token0_mint: Pubkey,
@@ -31,7 +40,8 @@ pub struct Pool {
     pub token0_vault: Pubkey,
     /// The vault holding token1 for this pool.
     pub token1_vault: Pubkey,
-    /// Fee rate in basis points (e.g., 30 for 0.3%).
+    /// Fee rate in basis points (e.g., 30 for 0.3%). The authority can change it with
+    /// `set_fee_rate`.
     pub fee_rate: u16,
     /// The spacing between usable ticks.
     pub tick_spacing: u16,
@@ -41,10 +51,77 @@ pub struct Pool {
     pub current_tick: i32,
     /// The total active liquidity within the current tick's price range.
     pub liquidity: u128,
+    /// Total token0 fees earned per unit of liquidity over the pool's lifetime, in Q64.64.
+    pub fee_growth_global_0_q64: u128,
+    /// Total token1 fees earned per unit of liquidity over the pool's lifetime, in Q64.64.
+    pub fee_growth_global_1_q64: u128,
+    /// Fee charged on flash loans, in basis points of the borrowed amount.
+    /// Defaults to `fee_rate`.
+    pub flash_fee_bps: u16,
+    /// Set to 1 while a flash loan is in progress, 0 otherwise.
+    pub flash_loan_active: u8,
+    /// The factory config's authority when the pool was created. Admin instructions check
+    /// the config's current authority instead.
+    pub authority: Pubkey,
+    /// Share of each swap fee kept by the protocol, in basis points of the fee.
+    pub protocol_fee: u16,
+    /// Token0 protocol fees accrued and not yet collected.
+    pub protocol_fees_owed_a: u64,
+    /// Token1 protocol fees accrued and not yet collected.
+    pub protocol_fees_owed_b: u64,
+    /// Index in `observations` of the most recent oracle observation.
+    pub observation_index: u16,
+    /// Number of populated entries in `observations`.
+    pub observation_count: u16,
+    /// Timestamp of the most recent oracle observation.
+    pub last_observation_timestamp: i64,
+    /// Sum of `current_tick` over every second up to `last_observation_timestamp`.
+    pub tick_cumulative: i64,
+    /// Sum of `1 / liquidity` over every second up to `last_observation_timestamp`, in Q64.64.
+    pub seconds_per_liquidity_cumulative_q64: u128,
+    /// Ring buffer of oracle observations, the most recent at `observation_index`.
+    pub observations: [Observation; OBSERVATION_CAPACITY],
+    /// The `TokenPair` record of the pool's mints.
+    pub token_pair: Pubkey,
+    /// Fee rate the pool was created with, in basis points. It is part of the pool's PDA
+    /// seeds and its key in the `PoolRegistry`, so it never changes, unlike `fee_rate`.
+    pub fee_tier: u16,
+    /// While set, swaps, flash loans and new liquidity are rejected. Liquidity providers
+    /// can still withdraw and collect fees.
+    pub is_paused: bool,
+    /// Account allowed to publish oracle prices with `set_oracle`. Defaults to `authority`.
+    pub oracle_authority: Pubkey,
+    /// Latest external (e.g. Pyth or Switchboard) sqrt price, in Q64.64. Zero until the
+    /// oracle authority first publishes one.
+    pub oracle_sqrt_price_q64: u128,
+    /// Slot at which `oracle_sqrt_price_q64` was published by its source.
+    pub oracle_price_slot: u64,
+    /// Age, in slots, past which `oracle_sqrt_price_q64` is stale.
+    pub max_oracle_age_slots: u64,
+    /// Chosen at initialization and never changed. While set, the fee rate, protocol fee
+    /// and tick spacing are fixed: `set_fee_rate`, `set_protocol_fee`,
+    /// `refresh_protocol_share` and `migrate_tick_spacing` are rejected.
+    pub immutable_parameters: bool,
+    /// Token0 traded through swaps, in either direction, since `volume_window_start`.
+    pub volume_24h: u64,
+    /// Start of the daily window `volume_24h` counts trades over, a multiple of
+    /// `VOLUME_WINDOW_SECS`. The first swap of a later day resets the count.
+    pub volume_window_start: i64,
+    /// Set when the factory authority chose the protocol fee with `set_protocol_fee`.
+    /// `refresh_protocol_share` leaves such a pool alone until it is returned to the
+    /// factory curve.
+    pub protocol_fee_manual: bool,
+    /// Spacing the pool was created with, by which the tick bitmap, `TickBitmap` words and
+    /// tick arrays are laid out. `migrate_tick_spacing` only widens `tick_spacing`, whose
+    /// ticks are all ticks of this spacing, so liquidity added before a migration is still
+    /// found by swaps.
+    pub bitmap_tick_spacing: u16,
+    /// Indices of the tick bitmap words stored in `TickBitmap` accounts instead of
+    /// `tick_bitmap_data`, in ascending order.
+    pub external_bitmap_words: Vec<i16>,
     /// Stores initialized tick data directly for MVP simplicity.
     /// Serialized BTreeMap<i16, u64> mapping compressed_tick_word_index to the bitmap.
     pub tick_bitmap_data: Vec<u8>,
-    // MVP Simplification: Skipping fee_growth_global_..., protocol_fees_..., oracle_...
 }
 
 /// Parameters for initializing a new pool.
@@ -52,13 +129,139 @@ pub struct Pool {
 pub struct InitializePoolParams {
     pub bump: u8,
     pub factory: Pubkey,
+    pub authority: Pubkey,
     pub token0_mint: Pubkey,
     pub token1_mint: Pubkey,
     pub token0_vault: Pubkey,
     pub token1_vault: Pubkey,
+    pub token_pair: Pubkey,
     pub initial_sqrt_price_q64: u128,
     pub fee_rate: u16,
+    pub protocol_fee: u16,
+    pub immutable_parameters: bool,
     pub tick_spacing: u16,
+    pub timestamp: i64,
+}
+
+/// Emitted for every swap through a pool, including each hop of a multi-hop swap.
+#[event]
+pub struct SwapEvent {
+    pub pool: Pubkey,
+    /// The account that signed the swap.
+    pub user: Pubkey,
+    /// Direction of the swap: `true` swaps token0 for token1.
+    pub zero_for_one: bool,
+    /// Input paid into the pool, fees included.
+    pub amount_in: u64,
+    /// Output paid out of the pool.
+    pub amount_out: u64,
+    /// Pool sqrt price before the swap, in Q64.64.
+    pub sqrt_price_before: u128,
+    /// Pool sqrt price after the swap, in Q64.64.
+    pub sqrt_price_after: u128,
+    /// Swap fee charged on the input, liquidity provider and protocol parts together.
+    pub fee_paid: u64,
+    /// Pool's current tick after the swap.
+    pub tick_after: i32,
+    /// Realized price impact, the relative move of the price from `sqrt_price_before` to
+    /// `sqrt_price_after`, in basis points. See `math::price_impact_bps`.
+    pub price_impact_bps: u64,
+}
+
+/// Emitted when a pool's authority changes its fee rate.
+#[event]
+pub struct FeeRateUpdated {
+    pub pool: Pubkey,
+    /// Fee rate before the update, in basis points.
+    pub old_fee_rate: u16,
+    /// Fee rate after the update, in basis points.
+    pub new_fee_rate: u16,
+}
+
+/// Emitted when a pool is paused or unpaused.
+#[event]
+pub struct PoolPauseUpdated {
+    pub pool: Pubkey,
+    /// Whether the pool is paused after the update.
+    pub is_paused: bool,
+}
+
+/// Emitted when a pool's oracle authority publishes a price.
+#[event]
+pub struct OraclePriceUpdated {
+    pub pool: Pubkey,
+    /// The published sqrt price, in Q64.64.
+    pub sqrt_price_q64: u128,
+    /// The slot at which the oracle published the price.
+    pub published_slot: u64,
+}
+
+/// Emitted when a pool's authority migrates it to a wider tick spacing.
+#[event]
+pub struct TickSpacingChanged {
+    pub pool: Pubkey,
+    /// Tick spacing before the migration.
+    pub old_tick_spacing: u16,
+    /// Tick spacing after the migration.
+    pub new_tick_spacing: u16,
+}
+
+/// Amounts moved by a run of the swap loop.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwapResult {
+    /// Input consumed, fees included.
+    pub amount_in: u128,
+    /// Output produced.
+    pub amount_out: u128,
+    /// Part of the swap fee credited to liquidity providers, in the input token.
+    pub lp_fee: u128,
+    /// Part of the swap fee accrued to the protocol, in the input token.
+    pub protocol_fee: u128,
+    /// Number of initialized ticks crossed.
+    pub ticks_crossed: u32,
+}
+
+impl SwapResult {
+    /// The whole swap fee, LP and protocol parts together.
+    pub fn total_fee(&self) -> u128 {
+        self.lp_fee + self.protocol_fee
+    }
+}
+
+/// Outcome of an exact-input swap, as returned by `quote_swap_exact_input_handler`.
+///
+/// The numbers are those `swap_exact_input_handler` would produce against the same pool
+/// state and tick accounts.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuoteResult {
+    /// Output the swap would pay out.
+    pub amount_out: u64,
+    /// Input the swap would consume, fees included. Less than the amount quoted if the
+    /// swap stops at the price limit.
+    pub amount_in_consumed: u64,
+    /// Pool sqrt price after the swap, in Q64.64.
+    pub sqrt_price_after: u128,
+    /// Number of initialized ticks the swap would cross.
+    pub ticks_crossed: u32,
+    /// Swap fee paid, LP and protocol parts together, in the input token.
+    pub fee_paid: u64,
+}
+
+impl QuoteResult {
+    /// Fails with `QuoteDeviationExceeded` if `amount_out` differs from `expected_out`, in
+    /// either direction, by more than `tolerance_bps` basis points of `expected_out`.
+    pub fn ensure_amount_out_within(&self, expected_out: u64, tolerance_bps: u16) -> Result<()> {
+        require!(
+            tolerance_bps as u128 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidInput
+        );
+        let deviation = self.amount_out.abs_diff(expected_out) as u128;
+        require!(
+            deviation * BPS_DENOMINATOR <= expected_out as u128 * tolerance_bps as u128,
+            ErrorCode::QuoteDeviationExceeded
+        );
+        Ok(())
+    }
 }
 
 impl<'info> Pool {
@@ -75,6 +278,33 @@ impl<'info> Pool {
         + 16 // sqrt_price_q64
         + 4 // current_tick
         + 16 // liquidity
+        + 16 // fee_growth_global_0_q64
+        + 16 // fee_growth_global_1_q64
+        + 2 // flash_fee_bps
+        + 1 // flash_loan_active
+        + 32 // authority
+        + 2 // protocol_fee
+        + 8 // protocol_fees_owed_a
+        + 8 // protocol_fees_owed_b
+        + 2 // observation_index
+        + 2 // observation_count
+        + 8 // last_observation_timestamp
+        + 8 // tick_cumulative
+        + 16 // seconds_per_liquidity_cumulative_q64
+        + Observation::LEN * OBSERVATION_CAPACITY // observations
+        + 32 // token_pair
+        + 2 // fee_tier
+        + 1 // is_paused
+        + 32 // oracle_authority
+        + 16 // oracle_sqrt_price_q64
+        + 8 // oracle_price_slot
+        + 8 // max_oracle_age_slots
+        + 1 // immutable_parameters
+        + 8 // volume_24h
+        + 8 // volume_window_start
+        + 1 // protocol_fee_manual
+        + 2 // bitmap_tick_spacing
+        + 4 + 2 * MAX_EXTERNAL_BITMAP_WORDS // external_bitmap_words: Vec<i16>
         + 4 + MAX_SERIALIZED_BITMAP_BYTES; // tick_bitmap_data: Vec<u8> (4 for len + data)
 
     /// Initializes the state of a new pool.
@@ -82,23 +312,32 @@ impl<'info> Pool {
     /// # Arguments
     /// * `bump` - The bump seed for the pool's PDA.
     /// * `factory` - The Pubkey of the factory that created this pool.
+    /// * `authority` - The factory config's authority.
     /// * `token0_mint` - Mint of the first token.
     /// * `token1_mint` - Mint of the second token.
     /// * `token0_vault` - Vault for the first token.
     /// * `token1_vault` - Vault for the second token.
+    /// * `token_pair` - The `TokenPair` record of the two mints.
     /// * `initial_sqrt_price_q64` - The initial sqrt price for the pool.
     /// * `fee_rate` - The fee rate for swaps in this pool, in basis points.
+    /// * `protocol_fee` - The protocol's share, in basis points of each swap fee.
+    /// * `immutable_parameters` - Whether the fee rate, protocol fee and tick spacing are
+    ///   fixed for the pool's lifetime.
     /// * `tick_spacing` - The tick spacing for this pool.
+    /// * `timestamp` - The current blockchain timestamp, recorded as the first oracle observation.
     pub fn initialize(&mut self, params: InitializePoolParams) -> Result<()> {
         if params.token0_mint == params.token1_mint {
             return err!(ErrorCode::MintsMustDiffer);
         }
-        if params.initial_sqrt_price_q64 == 0 || params.initial_sqrt_price_q64 > MAX_SQRT_PRICE {
+        if !(MIN_SQRT_PRICE..=MAX_SQRT_PRICE).contains(&params.initial_sqrt_price_q64) {
             return err!(ErrorCode::InvalidInitialPrice);
         }
         if params.tick_spacing == 0 {
             return err!(ErrorCode::InvalidTickSpacing);
         }
+        if params.protocol_fee as u128 > BPS_DENOMINATOR {
+            return err!(ErrorCode::InvalidProtocolFee);
+        }
 
         self.bump = params.bump;
         self.factory = params.factory;
@@ -106,17 +345,634 @@ impl<'info> Pool {
         self.token1_mint = params.token1_mint;
         self.token0_vault = params.token0_vault;
         self.token1_vault = params.token1_vault;
+        self.token_pair = params.token_pair;
         self.fee_rate = params.fee_rate;
+        self.fee_tier = params.fee_rate;
         self.tick_spacing = params.tick_spacing;
+        self.bitmap_tick_spacing = params.tick_spacing;
         self.sqrt_price_q64 = params.initial_sqrt_price_q64;
         self.current_tick = math::sqrt_price_q64_to_tick(params.initial_sqrt_price_q64)?;
         self.liquidity = 0;
+        self.fee_growth_global_0_q64 = 0;
+        self.fee_growth_global_1_q64 = 0;
+        self.flash_fee_bps = params.fee_rate;
+        self.flash_loan_active = 0;
+        self.is_paused = false;
+        self.authority = params.authority;
+        self.oracle_authority = params.authority;
+        self.oracle_sqrt_price_q64 = 0;
+        self.oracle_price_slot = 0;
+        self.max_oracle_age_slots = DEFAULT_MAX_ORACLE_AGE_SLOTS;
+        self.immutable_parameters = params.immutable_parameters;
+        self.volume_24h = 0;
+        self.volume_window_start = volume_window_start(params.timestamp);
+        self.protocol_fee = params.protocol_fee;
+        self.protocol_fee_manual = false;
+        self.protocol_fees_owed_a = 0;
+        self.protocol_fees_owed_b = 0;
+        self.observation_index = 0;
+        self.observation_count = 1;
+        self.last_observation_timestamp = params.timestamp;
+        self.tick_cumulative = 0;
+        self.seconds_per_liquidity_cumulative_q64 = 0;
+        self.observations = [Observation::default(); OBSERVATION_CAPACITY];
+        self.observations[0] = self.latest_observation();
+        self.external_bitmap_words = Vec::new();
         self.tick_bitmap_data = borsh::to_vec(&BTreeMap::<i16, u64>::new())
             .expect("Failed to serialize empty BTreeMap");
 
         Ok(())
     }
 
+    /// The oracle accumulators as of the most recent observation.
+    fn latest_observation(&self) -> Observation {
+        Observation {
+            timestamp: self.last_observation_timestamp,
+            tick_cumulative: self.tick_cumulative,
+            seconds_per_liquidity_cumulative_q64: self.seconds_per_liquidity_cumulative_q64,
+        }
+    }
+
+    /// Records an oracle observation at `timestamp`, accumulating the current tick and
+    /// liquidity over the time since the previous one.
+    ///
+    /// Must be called before the price moves. Does nothing if an observation was already
+    /// recorded at or after `timestamp`, so the first swap of a block sets its observation.
+    pub fn write_observation(&mut self, timestamp: i64) {
+        if timestamp <= self.last_observation_timestamp {
+            return;
+        }
+        let observation =
+            self.latest_observation()
+                .transform(timestamp, self.current_tick, self.liquidity);
+        self.observation_index =
+            ((self.observation_index as usize + 1) % OBSERVATION_CAPACITY) as u16;
+        self.observation_count = (self.observation_count + 1).min(OBSERVATION_CAPACITY as u16);
+        self.observations[self.observation_index as usize] = observation;
+        self.last_observation_timestamp = observation.timestamp;
+        self.tick_cumulative = observation.tick_cumulative;
+        self.seconds_per_liquidity_cumulative_q64 =
+            observation.seconds_per_liquidity_cumulative_q64;
+    }
+
+    /// Back-fills the oracle of a newly created pool to `cardinality` observations,
+    /// `interval_secs` apart and ending with its first one, as if the pool had been at its
+    /// current tick and liquidity all along.
+    ///
+    /// TWAPs over up to `(cardinality - 1) * interval_secs` seconds are then available from
+    /// creation, at the initial price. They are only as meaningful as that price, which the
+    /// pool's creator chose.
+    ///
+    /// # Errors
+    ///
+    /// * `OracleCardinalityTooLarge` - `cardinality` exceeds `OBSERVATION_CAPACITY`.
+    /// * `InvalidObservationCardinality` - `cardinality` is below 2, or the pool has
+    ///   recorded observations since its first.
+    /// * `InvalidInput` - `interval_secs` is zero.
+    pub fn seed_observations(&mut self, cardinality: u16, interval_secs: u32) -> Result<()> {
+        require!(
+            cardinality as usize <= OBSERVATION_CAPACITY,
+            ErrorCode::OracleCardinalityTooLarge
+        );
+        require!(
+            cardinality > 1 && self.observation_count == 1,
+            ErrorCode::InvalidObservationCardinality
+        );
+        require!(interval_secs > 0, ErrorCode::InvalidInput);
+
+        // Each seeded observation is the first one less the time until it
+        let first = self.latest_observation();
+        for index in 0..cardinality as usize {
+            let elapsed = interval_secs as i64 * (cardinality as usize - 1 - index) as i64;
+            self.observations[index] = Observation {
+                timestamp: first.timestamp - elapsed,
+                tick_cumulative: first
+                    .tick_cumulative
+                    .wrapping_sub((self.current_tick as i64).wrapping_mul(elapsed)),
+                seconds_per_liquidity_cumulative_q64: first
+                    .seconds_per_liquidity_cumulative_q64
+                    .wrapping_sub(((elapsed as u128) << 64) / self.liquidity.max(1)),
+            };
+        }
+        self.observation_index = cardinality - 1;
+        self.observation_count = cardinality;
+        Ok(())
+    }
+
+    /// Returns the oracle accumulators `seconds_ago` seconds before `timestamp`.
+    ///
+    /// Points after the most recent observation are extrapolated from the current tick and
+    /// liquidity; older points are interpolated between the two observations around them.
+    ///
+    /// # Errors
+    ///
+    /// * `OracleObservationTooOld` - The point precedes the oldest stored observation.
+    pub fn observe(&self, timestamp: i64, seconds_ago: u32) -> Result<Observation> {
+        let target = timestamp - seconds_ago as i64;
+        let latest = self.latest_observation();
+        if target >= latest.timestamp {
+            return Ok(latest.transform(target, self.current_tick, self.liquidity));
+        }
+
+        let mut after = latest;
+        for age in 1..self.observation_count as usize {
+            let index = (self.observation_index as usize + OBSERVATION_CAPACITY - age)
+                % OBSERVATION_CAPACITY;
+            let before = self.observations[index];
+            if before.timestamp <= target {
+                return Ok(Observation::interpolate(&before, &after, target));
+            }
+            after = before;
+        }
+        err!(ErrorCode::OracleObservationTooOld)
+    }
+
+    /// Returns the arithmetic mean tick over the `seconds_ago` seconds before `timestamp`,
+    /// rounded towards negative infinity.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidTwapWindow` - `seconds_ago` is zero.
+    /// * `OracleObservationTooOld` - The window starts before the oldest stored observation.
+    pub fn get_twap(&self, timestamp: i64, seconds_ago: u32) -> Result<i32> {
+        require!(seconds_ago > 0, ErrorCode::InvalidTwapWindow);
+        let end = self.observe(timestamp, 0)?;
+        let start = self.observe(timestamp, seconds_ago)?;
+        let tick_delta = end.tick_cumulative.wrapping_sub(start.tick_cumulative);
+        Ok(tick_delta.div_euclid(seconds_ago as i64) as i32)
+    }
+
+    /// Moves tick bitmap word `word_index` out of the pool account and returns its bits, to
+    /// be stored in the word's `TickBitmap` account.
+    ///
+    /// # Errors
+    ///
+    /// * `TickWordIndexOutOfBounds` - The word covers no tick between `MIN_TICK` and `MAX_TICK`.
+    /// * `TickBitmapWordExists` - The word has already been moved out.
+    /// * `TickBitmapFull` - `MAX_EXTERNAL_BITMAP_WORDS` words have already been moved out.
+    pub fn externalize_bitmap_word(&mut self, word_index: i16) -> Result<u64> {
+        require!(
+            (tick_bitmap::word_index_of_tick(MIN_TICK, self.bitmap_tick_spacing)
+                ..=tick_bitmap::word_index_of_tick(MAX_TICK, self.bitmap_tick_spacing))
+                .contains(&word_index),
+            ErrorCode::TickWordIndexOutOfBounds
+        );
+        let position = match self.external_bitmap_words.binary_search(&word_index) {
+            Ok(_) => return err!(ErrorCode::TickBitmapWordExists),
+            Err(position) => position,
+        };
+        require!(
+            self.external_bitmap_words.len() < MAX_EXTERNAL_BITMAP_WORDS,
+            ErrorCode::TickBitmapFull
+        );
+        self.external_bitmap_words.insert(position, word_index);
+
+        let mut map: BTreeMap<i16, u64> =
+            borsh::BorshDeserialize::try_from_slice(&self.tick_bitmap_data)
+                .expect("Failed to deserialize tick_bitmap_data");
+        let word = map.remove(&word_index).unwrap_or(0);
+        self.tick_bitmap_data = borsh::to_vec(&map).expect("Failed to serialize tick_bitmap_data");
+        Ok(word)
+    }
+
+    /// Moves the words of the supplied `TickBitmap` accounts into `tick_bitmap_data`, so
+    /// the bitmap logic can read and flip them, until `store_bitmap_words` moves them back.
+    ///
+    /// While loaded, a word is off `external_bitmap_words`. Words still listed there are
+    /// the ones an instruction did not supply, which it must not read or flip.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidTickAccount` - An account belongs to another pool, is not its word's PDA,
+    ///   holds a word the pool has not moved out, or is supplied twice.
+    pub fn load_bitmap_words(
+        &mut self,
+        pool_key: &Pubkey,
+        tick_bitmap_loaders: &[&AccountLoader<'info, TickBitmap>],
+    ) -> Result<()> {
+        let mut map: BTreeMap<i16, u64> =
+            borsh::BorshDeserialize::try_from_slice(&self.tick_bitmap_data)
+                .expect("Failed to deserialize tick_bitmap_data");
+        for tick_bitmap_loader in tick_bitmap_loaders {
+            let tick_bitmap = tick_bitmap_loader.load()?;
+            require_keys_eq!(tick_bitmap.pool, *pool_key, ErrorCode::InvalidTickAccount);
+            require_keys_eq!(
+                tick_bitmap_loader.key(),
+                TickBitmap::address(pool_key, tick_bitmap.word_index),
+                ErrorCode::InvalidTickAccount
+            );
+            let position = self
+                .external_bitmap_words
+                .binary_search(&tick_bitmap.word_index)
+                .map_err(|_| error!(ErrorCode::InvalidTickAccount))?;
+            self.external_bitmap_words.remove(position);
+            if tick_bitmap.word != 0 {
+                map.insert(tick_bitmap.word_index, tick_bitmap.word);
+            }
+        }
+        self.tick_bitmap_data = borsh::to_vec(&map).expect("Failed to serialize tick_bitmap_data");
+        Ok(())
+    }
+
+    /// Moves the words loaded by `load_bitmap_words` back into their accounts.
+    ///
+    /// Fails with `TickBitmapFull` if the words left in `tick_bitmap_data` do not fit in
+    /// the pool account; `initialize_bitmap_word` can then move some of them out.
+    pub fn store_bitmap_words(
+        &mut self,
+        tick_bitmap_loaders: &[&AccountLoader<'info, TickBitmap>],
+    ) -> Result<()> {
+        let mut map: BTreeMap<i16, u64> =
+            borsh::BorshDeserialize::try_from_slice(&self.tick_bitmap_data)
+                .expect("Failed to deserialize tick_bitmap_data");
+        for tick_bitmap_loader in tick_bitmap_loaders {
+            let mut tick_bitmap = tick_bitmap_loader.load_mut()?;
+            tick_bitmap.word = map.remove(&tick_bitmap.word_index).unwrap_or(0);
+            let position = self
+                .external_bitmap_words
+                .binary_search(&tick_bitmap.word_index)
+                .unwrap_err();
+            self.external_bitmap_words
+                .insert(position, tick_bitmap.word_index);
+        }
+        self.tick_bitmap_data = borsh::to_vec(&map).expect("Failed to serialize tick_bitmap_data");
+        require!(
+            self.tick_bitmap_data.len() <= MAX_SERIALIZED_BITMAP_BYTES,
+            ErrorCode::TickBitmapFull
+        );
+        Ok(())
+    }
+
+    /// Returns the first word in the swap direction, starting from the current tick's,
+    /// that is stored in an account not loaded, if the swap could reach it before
+    /// `sqrt_price_limit_q64`. Also returns the price the swap must stop at before
+    /// reading that word.
+    fn unloaded_bitmap_word_ahead(
+        &self,
+        zero_for_one: bool,
+        sqrt_price_limit_q64: u128,
+    ) -> Result<Option<(i16, u128)>> {
+        let current_word =
+            tick_bitmap::word_index_of_tick(self.current_tick, self.bitmap_tick_spacing);
+        let word_index = if zero_for_one {
+            self.external_bitmap_words
+                .iter()
+                .rev()
+                .find(|&&word_index| word_index <= current_word)
+        } else {
+            self.external_bitmap_words
+                .iter()
+                .find(|&&word_index| word_index >= current_word)
+        };
+        let Some(&word_index) = word_index else {
+            return Ok(None);
+        };
+        if word_index == current_word {
+            return Err(tick_bitmap::missing_tick_bitmap_word(word_index));
+        }
+
+        // Going down, the swap may cross the lowest tick of the word above it. Going up, it
+        // must stay below the word's lowest tick, which it would otherwise cross unseen.
+        let stop_sqrt_price_q64 = if zero_for_one {
+            math::tick_to_sqrt_price_q64(tick_bitmap::word_start_tick(
+                word_index + 1,
+                self.bitmap_tick_spacing,
+            ))?
+        } else {
+            math::tick_to_sqrt_price_q64(tick_bitmap::word_start_tick(
+                word_index,
+                self.bitmap_tick_spacing,
+            ))? - 1
+        };
+        let reachable = if zero_for_one {
+            stop_sqrt_price_q64 > sqrt_price_limit_q64
+        } else {
+            stop_sqrt_price_q64 < sqrt_price_limit_q64
+        };
+        Ok(reachable.then_some((word_index, stop_sqrt_price_q64)))
+    }
+
+    /// Derives the address of this pool from its seeds,
+    /// `[b"pool", token0_mint, token1_mint, fee_tier.to_le_bytes(), bump]`.
+    pub fn address(&self) -> Result<Pubkey> {
+        Pubkey::create_program_address(
+            &[
+                b"pool".as_ref(),
+                self.token0_mint.as_ref(),
+                self.token1_mint.as_ref(),
+                self.fee_tier.to_le_bytes().as_ref(),
+                &[self.bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| error!(ErrorCode::InvalidPool))
+    }
+
+    /// Fails with `FlashLoanActive` while a flash loan on this pool is in progress.
+    pub fn ensure_no_flash_loan(&self) -> Result<()> {
+        require!(self.flash_loan_active == 0, ErrorCode::FlashLoanActive);
+        Ok(())
+    }
+
+    /// Fails with `PoolImmutable` if the pool was initialized with immutable parameters.
+    pub fn ensure_parameters_mutable(&self) -> Result<()> {
+        require!(!self.immutable_parameters, ErrorCode::PoolImmutable);
+        Ok(())
+    }
+
+    /// Fails with `PoolPaused` while the pool is paused.
+    pub fn ensure_not_paused(&self) -> Result<()> {
+        require!(!self.is_paused, ErrorCode::PoolPaused);
+        Ok(())
+    }
+
+    /// Fee owed on a flash loan of `amount`, rounded up in the pool's favour.
+    pub fn flash_fee(&self, amount: u64) -> Result<u64> {
+        let fee = (amount as u128 * self.flash_fee_bps as u128).div_ceil(BPS_DENOMINATOR);
+        u64::try_from(fee).map_err(|_| error!(ErrorCode::MathOverflow))
+    }
+
+    /// Credits flash loan fees to in-range liquidity providers, as swap fees are.
+    ///
+    /// # Arguments
+    /// * `paid_0` - Token0 paid on top of the borrowed amount.
+    /// * `paid_1` - Token1 paid on top of the borrowed amount.
+    pub fn accrue_flash_fees(&mut self, paid_0: u64, paid_1: u64) -> Result<()> {
+        self.fee_growth_global_0_q64 =
+            self.fee_growth_global_0_q64
+                .wrapping_add(math::get_fee_growth_delta_q64(
+                    paid_0 as u128,
+                    self.liquidity,
+                )?);
+        self.fee_growth_global_1_q64 =
+            self.fee_growth_global_1_q64
+                .wrapping_add(math::get_fee_growth_delta_q64(
+                    paid_1 as u128,
+                    self.liquidity,
+                )?);
+        Ok(())
+    }
+
+    /// Sets the share of swap fees kept by the protocol.
+    ///
+    /// # Arguments
+    /// * `protocol_fee` - The protocol's share, in basis points of each swap fee.
+    pub fn set_protocol_fee(&mut self, protocol_fee: u16) -> Result<()> {
+        self.ensure_parameters_mutable()?;
+        require!(
+            protocol_fee as u128 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidProtocolFee
+        );
+        self.protocol_fee = protocol_fee;
+        Ok(())
+    }
+
+    /// Sets the share of swap fees kept by the protocol by hand, taking the pool off the
+    /// factory curve until `follow_protocol_fee_curve` puts it back.
+    ///
+    /// # Arguments
+    /// * `protocol_fee` - The protocol's share, in basis points of each swap fee.
+    pub fn set_manual_protocol_fee(&mut self, protocol_fee: u16) -> Result<()> {
+        self.set_protocol_fee(protocol_fee)?;
+        self.protocol_fee_manual = true;
+        Ok(())
+    }
+
+    /// Puts a pool whose protocol fee was set by hand back on `curve`, and returns the
+    /// share it now takes.
+    pub fn follow_protocol_fee_curve(&mut self, curve: &ProtocolFeeCurve) -> Result<u16> {
+        self.ensure_parameters_mutable()?;
+        self.protocol_fee_manual = false;
+        self.refresh_protocol_share(curve)
+    }
+
+    /// Sets the share of swap fees kept by the protocol from `curve`, for the pool's active
+    /// liquidity, and returns it.
+    ///
+    /// Protocol fees and fee growth already accrued are left as they are; only later swaps
+    /// are split with the new share. Fails with `ProtocolFeeManual` while the share is set
+    /// by hand.
+    pub fn refresh_protocol_share(&mut self, curve: &ProtocolFeeCurve) -> Result<u16> {
+        require!(!self.protocol_fee_manual, ErrorCode::ProtocolFeeManual);
+        let protocol_fee = curve.protocol_share_bps(self.liquidity);
+        self.set_protocol_fee(protocol_fee)?;
+        Ok(protocol_fee)
+    }
+
+    /// Sets the fee rate charged on swaps.
+    ///
+    /// `fee_tier`, and with it the pool's address, keeps the rate the pool was created with.
+    ///
+    /// # Arguments
+    /// * `fee_rate` - The new fee rate, in basis points, between `MIN_FEE_RATE` and
+    ///   `MAX_FEE_RATE`.
+    pub fn set_fee_rate(&mut self, fee_rate: u16) -> Result<()> {
+        self.ensure_parameters_mutable()?;
+        self.ensure_no_flash_loan()?;
+        require!(
+            (MIN_FEE_RATE..=MAX_FEE_RATE).contains(&fee_rate),
+            ErrorCode::InvalidFeeRate
+        );
+        self.fee_rate = fee_rate;
+        Ok(())
+    }
+
+    /// Widens the spacing between usable ticks.
+    ///
+    /// Only the ticks new positions and `update_position` may use change: the tick bitmap,
+    /// tick arrays and tick bitmap words stay laid out by `bitmap_tick_spacing`, so the
+    /// liquidity of existing positions keeps being crossed by swaps. Those positions stay
+    /// valid on their old boundaries until their owners move them onto the new spacing with
+    /// `update_position`.
+    ///
+    /// # Arguments
+    /// * `new_tick_spacing` - The new spacing, a strict multiple of the current one.
+    ///
+    /// # Errors
+    /// * `PoolImmutable` - The pool was initialized with immutable parameters.
+    /// * `InvalidTickSpacing` - `new_tick_spacing` is not a multiple of the current spacing
+    ///   larger than it.
+    pub fn migrate_tick_spacing(&mut self, new_tick_spacing: u16) -> Result<()> {
+        self.ensure_parameters_mutable()?;
+        self.ensure_no_flash_loan()?;
+        require!(
+            new_tick_spacing > self.tick_spacing
+                && new_tick_spacing.is_multiple_of(self.tick_spacing),
+            ErrorCode::InvalidTickSpacing
+        );
+        self.tick_spacing = new_tick_spacing;
+        Ok(())
+    }
+
+    /// Sets who publishes the pool's oracle price and how old that price may get.
+    ///
+    /// # Arguments
+    /// * `oracle_authority` - The account allowed to call `set_oracle`.
+    /// * `max_oracle_age_slots` - Age, in slots, past which the oracle price is stale.
+    pub fn set_oracle_config(&mut self, oracle_authority: Pubkey, max_oracle_age_slots: u64) {
+        self.oracle_authority = oracle_authority;
+        self.max_oracle_age_slots = max_oracle_age_slots;
+    }
+
+    /// Records an external oracle price.
+    ///
+    /// # Arguments
+    /// * `sqrt_price_q64` - The oracle's sqrt price, in Q64.64.
+    /// * `published_slot` - The slot at which the oracle published the price.
+    /// * `current_slot` - The current slot.
+    ///
+    /// # Errors
+    /// * `InvalidOraclePrice` - The price is outside `MIN_SQRT_PRICE..=MAX_SQRT_PRICE`, or
+    ///   `published_slot` is in the future or older than the stored price.
+    /// * `OraclePriceStale` - The price is older than `max_oracle_age_slots`.
+    pub fn set_oracle_price(
+        &mut self,
+        sqrt_price_q64: u128,
+        published_slot: u64,
+        current_slot: u64,
+    ) -> Result<()> {
+        require!(
+            (MIN_SQRT_PRICE..=MAX_SQRT_PRICE).contains(&sqrt_price_q64)
+                && published_slot <= current_slot
+                && published_slot >= self.oracle_price_slot,
+            ErrorCode::InvalidOraclePrice
+        );
+        require!(
+            current_slot - published_slot <= self.max_oracle_age_slots,
+            ErrorCode::OraclePriceStale
+        );
+        self.oracle_sqrt_price_q64 = sqrt_price_q64;
+        self.oracle_price_slot = published_slot;
+        Ok(())
+    }
+
+    /// The oracle price, if one was published and is not stale at `current_slot`.
+    ///
+    /// Returns `None` if the oracle authority never published a price.
+    ///
+    /// # Errors
+    /// * `OraclePriceStale` - The price is older than `max_oracle_age_slots`.
+    pub fn oracle_sqrt_price(&self, current_slot: u64) -> Result<Option<u128>> {
+        if self.oracle_sqrt_price_q64 == 0 {
+            return Ok(None);
+        }
+        require!(
+            current_slot.saturating_sub(self.oracle_price_slot) <= self.max_oracle_age_slots,
+            ErrorCode::OraclePriceStale
+        );
+        Ok(Some(self.oracle_sqrt_price_q64))
+    }
+
+    /// Adds `amount_0` of token0 to the day's swap volume, first resetting it if
+    /// `timestamp` falls in a later daily window than the one counted so far.
+    pub fn record_volume(&mut self, amount_0: u64, timestamp: i64) {
+        let window_start = volume_window_start(timestamp);
+        if window_start > self.volume_window_start {
+            self.volume_window_start = window_start;
+            self.volume_24h = 0;
+        }
+        self.volume_24h = self.volume_24h.saturating_add(amount_0);
+    }
+
+    /// The token0 swap volume of the daily window containing `timestamp`.
+    ///
+    /// Zero if no swap has gone through the pool in that window yet, even though
+    /// `volume_24h` still holds the count of an earlier one.
+    pub fn volume_24h_at(&self, timestamp: i64) -> u64 {
+        if volume_window_start(timestamp) > self.volume_window_start {
+            0
+        } else {
+            self.volume_24h
+        }
+    }
+
+    /// Moves the protocol's cut of a swap fee into the owed counters and returns the rest,
+    /// which goes to liquidity providers. The cut is rounded down.
+    ///
+    /// # Arguments
+    /// * `fee` - The fee charged on a swap step, in the input token.
+    /// * `zero_for_one` - Whether the input token is token0.
+    fn accrue_protocol_fee(&mut self, fee: u128, zero_for_one: bool) -> Result<u128> {
+        let protocol_cut = math::mul_div_floor(fee, self.protocol_fee as u128, BPS_DENOMINATOR)?;
+        if protocol_cut == 0 {
+            return Ok(fee);
+        }
+        let owed = if zero_for_one {
+            &mut self.protocol_fees_owed_a
+        } else {
+            &mut self.protocol_fees_owed_b
+        };
+        *owed = u64::try_from(protocol_cut)
+            .ok()
+            .and_then(|cut| owed.checked_add(cut))
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(fee - protocol_cut)
+    }
+
+    /// Resets the protocol fee counters and returns the token0 and token1 amounts owed.
+    pub fn take_protocol_fees(&mut self) -> (u64, u64) {
+        let owed = (self.protocol_fees_owed_a, self.protocol_fees_owed_b);
+        self.protocol_fees_owed_a = 0;
+        self.protocol_fees_owed_b = 0;
+        owed
+    }
+
+    /// Sets the fee growth outside a tick that is about to become initialized.
+    ///
+    /// By convention, all fee growth before a tick is initialized happened below it, so
+    /// the outside values start at the global growth when the current tick is at or above
+    /// the tick, and at zero otherwise.
+    fn init_tick_fee_growth_outside(
+        &self,
+        tick_index: i32,
+        liquidity_delta: i128,
+        tick_data: &mut TickData,
+    ) {
+        if tick_data.liquidity_gross != 0 || liquidity_delta <= 0 {
+            return;
+        }
+        if tick_index <= self.current_tick {
+            tick_data.fee_growth_outside_0_q64 = self.fee_growth_global_0_q64;
+            tick_data.fee_growth_outside_1_q64 = self.fee_growth_global_1_q64;
+        } else {
+            tick_data.fee_growth_outside_0_q64 = 0;
+            tick_data.fee_growth_outside_1_q64 = 0;
+        }
+    }
+
+    /// Returns the token0 and token1 fee growth inside a tick range, in Q64.64.
+    ///
+    /// # Arguments
+    /// * `tick_lower_index` - The lower tick boundary of the range.
+    /// * `tick_lower_data` - The data of the lower tick.
+    /// * `tick_upper_index` - The upper tick boundary of the range.
+    /// * `tick_upper_data` - The data of the upper tick.
+    pub fn fee_growth_inside(
+        &self,
+        tick_lower_index: i32,
+        tick_lower_data: &TickData,
+        tick_upper_index: i32,
+        tick_upper_data: &TickData,
+    ) -> (u128, u128) {
+        let fee_growth_inside_0_q64 = math::get_fee_growth_inside(
+            tick_lower_index,
+            tick_upper_index,
+            self.current_tick,
+            self.fee_growth_global_0_q64,
+            tick_lower_data.fee_growth_outside_0_q64,
+            tick_upper_data.fee_growth_outside_0_q64,
+        );
+        let fee_growth_inside_1_q64 = math::get_fee_growth_inside(
+            tick_lower_index,
+            tick_upper_index,
+            self.current_tick,
+            self.fee_growth_global_1_q64,
+            tick_lower_data.fee_growth_outside_1_q64,
+            tick_upper_data.fee_growth_outside_1_q64,
+        );
+        (fee_growth_inside_0_q64, fee_growth_inside_1_q64)
+    }
+
     /// Updates a tick's state after a liquidity change and flips its status in the bitmap.
     ///
     /// # Arguments
@@ -131,16 +987,34 @@ impl<'info> Pool {
         is_upper_tick: bool,
         tick_data: &mut TickData, // Changed to take &mut TickData directly
     ) -> Result<()> {
+        let word_index = tick_bitmap::word_index_of_tick(tick_index, self.bitmap_tick_spacing);
+        if self
+            .external_bitmap_words
+            .binary_search(&word_index)
+            .is_ok()
+        {
+            return Err(tick_bitmap::missing_tick_bitmap_word(word_index));
+        }
         let mut map: BTreeMap<i16, u64> =
             borsh::BorshDeserialize::try_from_slice(&self.tick_bitmap_data)
                 .expect("Failed to deserialize tick_bitmap_data");
 
+        // An initialized tick whose account holds no liquidity has its liquidity in another
+        // account (a `TickData` PDA or a `TickArray`), which must be used instead.
+        if tick_data.liquidity_gross == 0
+            && liquidity_delta > 0
+            && tick_bitmap::is_tick_initialized(&map, tick_index, self.bitmap_tick_spacing)?
+        {
+            return err!(ErrorCode::TickStorageConflict);
+        }
+
+        self.init_tick_fee_growth_outside(tick_index, liquidity_delta, tick_data);
         tick_data.update_on_liquidity_change(liquidity_delta, is_upper_tick)?;
 
         tick_bitmap::flip_tick_initialized_status(
             &mut map,
             tick_index,
-            self.tick_spacing,
+            self.bitmap_tick_spacing,
             tick_data.initialized != 0,
         )?;
         self.tick_bitmap_data = borsh::to_vec(&map).expect("Failed to serialize tick_bitmap_data");
@@ -182,6 +1056,61 @@ impl<'info> Pool {
             &mut tick_upper_data,
         )?;
 
+        self.update_active_liquidity(tick_lower_index, tick_upper_index, liquidity_delta)
+    }
+
+    /// Modifies liquidity for a given range whose ticks are stored in tick arrays.
+    ///
+    /// # Arguments
+    /// * `tick_lower_index` - The lower tick boundary of the position.
+    /// * `tick_upper_index` - The upper tick boundary of the position.
+    /// * `liquidity_delta` - The change in liquidity (positive to add, negative to remove).
+    /// * `tick_array_lower_loader` - The tick array holding the lower tick.
+    /// * `tick_array_upper_loader` - The tick array holding the upper tick. Can be the same
+    ///   account as `tick_array_lower_loader`.
+    pub fn modify_liquidity_in_tick_arrays(
+        &mut self,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        liquidity_delta: i128,
+        tick_array_lower_loader: &AccountLoader<'info, TickArray>,
+        tick_array_upper_loader: &AccountLoader<'info, TickArray>,
+    ) -> Result<()> {
+        // Each array is borrowed only while its tick is updated, so both ticks can share one.
+        {
+            let mut tick_array_lower = tick_array_lower_loader.load_mut()?;
+            let tick_lower_data =
+                tick_array_lower.get_tick_mut(tick_lower_index, self.bitmap_tick_spacing)?;
+            self._process_tick_liquidity_change(
+                tick_lower_index,
+                liquidity_delta,
+                false,
+                tick_lower_data,
+            )?;
+        }
+        {
+            let mut tick_array_upper = tick_array_upper_loader.load_mut()?;
+            let tick_upper_data =
+                tick_array_upper.get_tick_mut(tick_upper_index, self.bitmap_tick_spacing)?;
+            self._process_tick_liquidity_change(
+                tick_upper_index,
+                liquidity_delta,
+                true,
+                tick_upper_data,
+            )?;
+        }
+
+        self.update_active_liquidity(tick_lower_index, tick_upper_index, liquidity_delta)
+    }
+
+    /// Applies `liquidity_delta` to the pool's active liquidity if the current tick is
+    /// inside `[tick_lower_index, tick_upper_index)`.
+    fn update_active_liquidity(
+        &mut self,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        liquidity_delta: i128,
+    ) -> Result<()> {
         // If the current price is within the modified range, update pool's active liquidity
         if self.current_tick >= tick_lower_index && self.current_tick < tick_upper_index {
             if liquidity_delta > 0 {
@@ -219,20 +1148,22 @@ impl<'info> Pool {
                 .expect("Failed to deserialize tick_bitmap_data for test");
 
         // Update the lower tick
+        self.init_tick_fee_growth_outside(tick_lower_index, liquidity_delta, tick_lower_data);
         tick_lower_data.update_on_liquidity_change(liquidity_delta, false)?;
         tick_bitmap::flip_tick_initialized_status(
             &mut map,
             tick_lower_index,
-            self.tick_spacing,
+            self.bitmap_tick_spacing,
             tick_lower_data.initialized != 0,
         )?;
 
         // Update the upper tick
+        self.init_tick_fee_growth_outside(tick_upper_index, liquidity_delta, tick_upper_data);
         tick_upper_data.update_on_liquidity_change(liquidity_delta, true)?;
         tick_bitmap::flip_tick_initialized_status(
             &mut map,
             tick_upper_index,
-            self.tick_spacing,
+            self.bitmap_tick_spacing,
             tick_upper_data.initialized != 0,
         )?;
 
@@ -262,126 +1193,135 @@ impl<'info> Pool {
     /// * `sqrt_price_current_q64` - The current sqrt price.
     /// * `sqrt_price_target_q64` - The target sqrt price for this step (e.g., next tick or price limit).
     /// * `step_liquidity` - The liquidity available for this step.
-    /// * `amount_remaining_gross_input` - The gross amount of input token remaining to be swapped.
+    /// * `amount_remaining` - For exact input, the gross amount of input token remaining to be swapped.
+    ///   For exact output, the amount of output token remaining to be received.
     /// * `fee_rate_bps` - The fee rate in basis points.
     /// * `zero_for_one` - True if swapping token0 for token1, false otherwise.
+    /// * `exact_input` - True if `amount_remaining` is an input amount, false if it is an output amount.
     ///
     /// # Returns
-    /// A tuple: `(gross_amount_in_consumed, net_amount_out_produced, next_sqrt_price_q64)`
+    /// A tuple: `(gross_amount_in_consumed, net_amount_out_produced, next_sqrt_price_q64, fee_amount)`,
+    /// where `fee_amount` is the part of `gross_amount_in_consumed` taken as the fee.
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn swap_step(
         &self,
         sqrt_price_current_q64: u128,
         sqrt_price_target_q64: u128,
         step_liquidity: u128,
-        amount_remaining_gross_input: u128,
+        amount_remaining: u128,
         fee_rate_bps: u16,
         zero_for_one: bool,
-    ) -> Result<(u128, u128, u128)> {
+        exact_input: bool,
+    ) -> Result<(u128, u128, u128, u128)> {
+        // Nothing to trade against: the price moves freely to the target, across the gap
+        // to the next initialized tick or to the price limit.
         if step_liquidity == 0 {
-            return Ok((0, 0, sqrt_price_current_q64));
+            return Ok((0, 0, sqrt_price_target_q64, 0));
         }
 
-        let exact_input = true; // For MVP, assuming exact input
+        let fee_rate_u128 = fee_rate_bps as u128;
+        let fee_complement = BPS_DENOMINATOR
+            .checked_sub(fee_rate_u128)
+            .ok_or(ErrorCode::MathOverflow)?;
 
         let gross_amount_in_consumed: u128;
         let net_amount_out_produced: u128;
         let next_sqrt_price_q64: u128;
+        let fee_amount: u128;
 
         if exact_input {
-            // Calculate net input after fee
-            let fee_rate_u128 = fee_rate_bps as u128;
-            let net_amount_remaining_input = amount_remaining_gross_input
-                .checked_mul(
-                    BPS_DENOMINATOR
-                        .checked_sub(fee_rate_u128)
-                        .ok_or(ErrorCode::MathOverflow)?,
-                )
-                .ok_or(ErrorCode::MathOverflow)?
-                .checked_div(BPS_DENOMINATOR)
-                .ok_or(ErrorCode::MathOverflow)?; // floor division
-
-            // Calculate max net input to reach target price
-            let max_net_input_to_reach_target = if zero_for_one {
-                // Swapping token0 for token1, price decreases. Target is lower or equal.
-                math::get_amount_0_delta(
-                    sqrt_price_target_q64,  // lower bound for delta calc
-                    sqrt_price_current_q64, // upper bound for delta calc
+            let step = math::compute_swap_step_u128(
+                sqrt_price_current_q64,
+                sqrt_price_target_q64,
+                step_liquidity,
+                amount_remaining,
+                fee_rate_bps as u32 * 100, // basis points to pips
+            )?;
+            gross_amount_in_consumed = step
+                .amount_in
+                .checked_add(step.fee_amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+            net_amount_out_produced = step.amount_out;
+            next_sqrt_price_q64 = step.sqrt_price_next;
+            fee_amount = step.fee_amount;
+        } else {
+            let amount_remaining_output = amount_remaining;
+            // Calculate max output obtainable before reaching the target price
+            let max_output_to_reach_target = if zero_for_one {
+                math::get_amount_1_delta(
+                    sqrt_price_target_q64,
+                    sqrt_price_current_q64,
                     step_liquidity,
-                    true, // round up input
+                    false, // round down output
                 )?
             } else {
-                // Swapping token1 for token0, price increases. Target is higher or equal.
-                math::get_amount_1_delta(
-                    sqrt_price_current_q64, // lower bound for delta calc
-                    sqrt_price_target_q64,  // upper bound for delta calc
+                math::get_amount_0_delta(
+                    sqrt_price_current_q64,
+                    sqrt_price_target_q64,
                     step_liquidity,
-                    true, // round up input
+                    false, // round down output
                 )?
             };
 
-            if net_amount_remaining_input >= max_net_input_to_reach_target {
-                // Can reach target price
-                let net_amount_in_consumed = max_net_input_to_reach_target;
-                gross_amount_in_consumed = math::round_up_div(
-                    net_amount_in_consumed
-                        .checked_mul(BPS_DENOMINATOR)
-                        .ok_or(ErrorCode::MathOverflow)?,
-                    BPS_DENOMINATOR
-                        .checked_sub(fee_rate_u128)
-                        .ok_or(ErrorCode::MathOverflow)?,
-                );
+            if amount_remaining_output >= max_output_to_reach_target {
+                // Target price is reached before the requested output is satisfied
                 next_sqrt_price_q64 = sqrt_price_target_q64;
             } else {
-                // Cannot reach target price, limited by remaining input
-                let net_amount_in_consumed = net_amount_remaining_input;
-                gross_amount_in_consumed = amount_remaining_gross_input; // All remaining gross input is consumed
-
+                // Requested output is satisfied within this step
                 next_sqrt_price_q64 = if zero_for_one {
-                    math::compute_next_sqrt_price_from_amount0_in(
+                    math::compute_next_sqrt_price_from_amount1_out(
                         sqrt_price_current_q64,
                         step_liquidity,
-                        net_amount_in_consumed, // Use net amount for price calculation
+                        amount_remaining_output,
                     )?
                 } else {
-                    math::compute_next_sqrt_price_from_amount1_in(
+                    math::compute_next_sqrt_price_from_amount0_out(
                         sqrt_price_current_q64,
                         step_liquidity,
-                        net_amount_in_consumed, // Use net amount for price calculation
+                        amount_remaining_output,
                     )?
                 };
             }
 
-            // Calculate net_amount_out_produced based on the price change and liquidity
-            net_amount_out_produced = if zero_for_one {
-                math::get_amount_1_delta(
-                    next_sqrt_price_q64,    // new lower bound
-                    sqrt_price_current_q64, // old upper bound
+            // When the output is satisfied within the step, the next price was rounded so that
+            // the move covers the full remaining output; otherwise the step yields its maximum.
+            net_amount_out_produced = if next_sqrt_price_q64 == sqrt_price_target_q64 {
+                max_output_to_reach_target.min(amount_remaining_output)
+            } else {
+                amount_remaining_output
+            };
+
+            // Input required for the price move is rounded up in the pool's favour
+            let net_amount_in_required = if zero_for_one {
+                math::get_amount_0_delta(
+                    next_sqrt_price_q64,
+                    sqrt_price_current_q64,
                     step_liquidity,
-                    false, // round down output
+                    true, // round up input
                 )?
             } else {
-                math::get_amount_0_delta(
-                    sqrt_price_current_q64, // old lower bound
-                    next_sqrt_price_q64,    // new upper bound
+                math::get_amount_1_delta(
+                    sqrt_price_current_q64,
+                    next_sqrt_price_q64,
                     step_liquidity,
-                    false, // round down output
+                    true, // round up input
                 )?
             };
-        } else {
-            // TODO: Implement exact output logic if needed for future versions
-            return err!(ErrorCode::InvalidInput); // Placeholder for not implemented
+            gross_amount_in_consumed =
+                math::mul_div_ceil(net_amount_in_required, BPS_DENOMINATOR, fee_complement)?;
+            fee_amount = gross_amount_in_consumed - net_amount_in_required;
         }
 
         // If no input was consumed, no output should be produced, and price doesn't change.
         if gross_amount_in_consumed == 0 {
-            return Ok((0, 0, sqrt_price_current_q64));
+            return Ok((0, 0, sqrt_price_current_q64, 0));
         }
 
         Ok((
             gross_amount_in_consumed,
             net_amount_out_produced,
             next_sqrt_price_q64,
+            fee_amount,
         ))
     }
 
@@ -389,56 +1329,315 @@ impl<'info> Pool {
     ///
     /// # Arguments
     /// * `zero_for_one` - True if swapping token0 for token1, false otherwise.
-    /// * `amount_specified` - The gross amount of input token to swap. Must be positive.
+    /// * `amount_specified` - Positive for exact input (the gross amount of input token to swap),
+    ///   negative for exact output (the amount of output token to receive).
     /// * `sqrt_price_limit_q64` - The price limit for the swap.
-    /// * `tick_loaders` - A slice of `AccountLoader` for `TickData` accounts expected to be crossed.
+    /// * `tick_loaders` - `TickData` accounts for the initialized ticks the swap crosses,
+    ///   ordered in the swap direction. Extra ticks the swap does not reach are ignored.
     /// * `current_timestamp` - The current blockchain timestamp.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingTickAccount` - The swap crossed a tick whose account was not supplied.
+    /// * `InvalidTickAccount` - A tick account is not a tick PDA of this pool.
+    /// * `InvalidTickAccountOrder` - The tick accounts are not ordered in the swap direction.
     pub fn swap(
         // Removed shadowed 'info lifetime
         &mut self,
         zero_for_one: bool,
-        amount_specified: i128, // For exact_input, this will be positive.
+        amount_specified: i128, // Positive for exact input, negative for exact output.
         sqrt_price_limit_q64: u128,
         pool_key: &Pubkey, // Pass the pool's own key for validation
         tick_loaders: &[&AccountLoader<'info, TickData>],
-        _current_timestamp: i64, // Parameter included, but not used in this MVP logic
+        current_timestamp: i64,
     ) -> Result<(u128, u128)> {
-        if amount_specified <= 0 {
-            // For swap_exact_input, amount_specified should be positive.
-            // If it could be negative (e.g. for swap_exact_output), this check would change.
-            if amount_specified == 0 {
-                return Ok((0, 0));
+        let result = self.swap_with_tick_arrays(
+            zero_for_one,
+            amount_specified,
+            sqrt_price_limit_q64,
+            pool_key,
+            tick_loaders,
+            &[],
+            &[],
+            current_timestamp,
+        )?;
+        Ok((result.amount_in, result.amount_out))
+    }
+
+    /// Executes a swap whose crossed ticks may be stored in tick arrays.
+    ///
+    /// A crossed tick is read from the supplied tick array covering it if that entry is
+    /// initialized, and from the supplied `TickData` accounts otherwise. Returns the amounts
+    /// swapped and the swap fee.
+    ///
+    /// # Arguments
+    /// * `zero_for_one` - True if swapping token0 for token1, false otherwise.
+    /// * `amount_specified` - Positive for exact input, negative for exact output.
+    /// * `sqrt_price_limit_q64` - The price limit for the swap.
+    /// * `tick_loaders` - `TickData` accounts, ordered in the swap direction.
+    /// * `tick_array_loaders` - `TickArray` accounts, in any order.
+    /// * `tick_bitmap_loaders` - `TickBitmap` accounts of the bitmap words the swap walks
+    ///   through, among those the pool has moved out, in any order.
+    /// * `current_timestamp` - The current blockchain timestamp, recorded as an oracle
+    ///   observation before the price moves.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingTickAccount` - The swap crossed a tick that no supplied account holds.
+    /// * `MissingTickBitmapWord` - The swap reached a bitmap word whose account was not supplied.
+    /// * `InvalidTickAccount` - A tick, tick array or tick bitmap account is not a PDA of this pool.
+    /// * `InvalidTickAccountOrder` - The `TickData` accounts are not ordered in the swap direction.
+    /// * `MaxTickCrossingsExceeded` - More than `MAX_SWAP_ITERATIONS` tick accounts were
+    ///   supplied, or the swap needs more than `MAX_SWAP_ITERATIONS` steps.
+    #[allow(clippy::too_many_arguments)]
+    pub fn swap_with_tick_arrays(
+        &mut self,
+        zero_for_one: bool,
+        amount_specified: i128,
+        sqrt_price_limit_q64: u128,
+        pool_key: &Pubkey,
+        tick_loaders: &[&AccountLoader<'info, TickData>],
+        tick_array_loaders: &[&AccountLoader<'info, TickArray>],
+        tick_bitmap_loaders: &[&AccountLoader<'info, TickBitmap>],
+        current_timestamp: i64,
+    ) -> Result<SwapResult> {
+        self.run_swap_with_tick_arrays(
+            zero_for_one,
+            amount_specified,
+            sqrt_price_limit_q64,
+            pool_key,
+            tick_loaders,
+            tick_array_loaders,
+            tick_bitmap_loaders,
+            current_timestamp,
+            true,
+        )
+    }
+
+    /// Runs the same swap as [`Pool::swap_with_tick_arrays`] without writing to any of
+    /// the supplied accounts, so they may be passed read-only.
+    ///
+    /// Only `self` is updated, exactly as the swap would update it: callers quoting a swap
+    /// should run this on a copy of the pool. Crossed ticks are read instead of flipped,
+    /// and bitmap words are not moved back into their accounts.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Pool::swap_with_tick_arrays`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn simulate_swap_with_tick_arrays(
+        &mut self,
+        zero_for_one: bool,
+        amount_specified: i128,
+        sqrt_price_limit_q64: u128,
+        pool_key: &Pubkey,
+        tick_loaders: &[&AccountLoader<'info, TickData>],
+        tick_array_loaders: &[&AccountLoader<'info, TickArray>],
+        tick_bitmap_loaders: &[&AccountLoader<'info, TickBitmap>],
+        current_timestamp: i64,
+    ) -> Result<SwapResult> {
+        self.run_swap_with_tick_arrays(
+            zero_for_one,
+            amount_specified,
+            sqrt_price_limit_q64,
+            pool_key,
+            tick_loaders,
+            tick_array_loaders,
+            tick_bitmap_loaders,
+            current_timestamp,
+            false,
+        )
+    }
+
+    /// Swap shared by [`Pool::swap_with_tick_arrays`] and
+    /// [`Pool::simulate_swap_with_tick_arrays`]. Crossed ticks and bitmap words are written
+    /// back to their accounts only if `write_accounts` is set; crossing a copy of a tick
+    /// yields the same `liquidity_net`, so both runs compute the same swap.
+    #[allow(clippy::too_many_arguments)]
+    fn run_swap_with_tick_arrays(
+        &mut self,
+        zero_for_one: bool,
+        amount_specified: i128,
+        sqrt_price_limit_q64: u128,
+        pool_key: &Pubkey,
+        tick_loaders: &[&AccountLoader<'info, TickData>],
+        tick_array_loaders: &[&AccountLoader<'info, TickArray>],
+        tick_bitmap_loaders: &[&AccountLoader<'info, TickBitmap>],
+        current_timestamp: i64,
+        write_accounts: bool,
+    ) -> Result<SwapResult> {
+        // Every supplied tick account is one the swap expects to cross.
+        require!(
+            tick_loaders.len() <= MAX_SWAP_ITERATIONS,
+            ErrorCode::MaxTickCrossingsExceeded
+        );
+        self.write_observation(current_timestamp);
+        self.load_bitmap_words(pool_key, tick_bitmap_loaders)?;
+        let unloaded_word = self.unloaded_bitmap_word_ahead(zero_for_one, sqrt_price_limit_q64)?;
+        let sqrt_price_limit_q64 = unloaded_word.map_or(sqrt_price_limit_q64, |(_, stop)| stop);
+        let tick_indices = tick::validate_swap_tick_accounts(tick_loaders, pool_key, zero_for_one)?;
+        let tick_array_starts =
+            tick_array::validate_swap_tick_arrays(tick_array_loaders, pool_key)?;
+        let tick_spacing = self.bitmap_tick_spacing;
+        let mut next_supplied = 0;
+        let result = self.swap_with_tick_source(
+            zero_for_one,
+            amount_specified,
+            sqrt_price_limit_q64,
+            |tick_index, fee_growth_global_0_q64, fee_growth_global_1_q64| {
+                let start_tick_index = TickArray::start_tick_index(tick_index, tick_spacing);
+                if let Some(array) = tick_array_starts
+                    .iter()
+                    .position(|&start| start == start_tick_index)
+                {
+                    let mut tick_copy;
+                    let mut tick_array;
+                    let tick_data: &mut TickData = if write_accounts {
+                        tick_array = tick_array_loaders[array].load_mut()?;
+                        tick_array.get_tick_mut(tick_index, tick_spacing)?
+                    } else {
+                        tick_copy = *tick_array_loaders[array]
+                            .load()?
+                            .get_tick(tick_index, tick_spacing)?;
+                        &mut tick_copy
+                    };
+                    if tick_data.initialized != 0 {
+                        return Ok(
+                            tick_data.cross(fee_growth_global_0_q64, fee_growth_global_1_q64)
+                        );
+                    }
+                }
+
+                // Skip supplied ticks the swap moved past without crossing them.
+                while tick_indices.get(next_supplied).is_some_and(|&supplied| {
+                    if zero_for_one {
+                        supplied > tick_index
+                    } else {
+                        supplied < tick_index
+                    }
+                }) {
+                    next_supplied += 1;
+                }
+                if tick_indices.get(next_supplied) != Some(&tick_index) {
+                    return Err(tick::missing_tick_account(tick_index));
+                }
+                let tick_loader = tick_loaders[next_supplied];
+                next_supplied += 1;
+                let mut tick_copy;
+                let mut tick_ref;
+                let tick_data: &mut TickData = if write_accounts {
+                    tick_ref = tick_loader.load_mut()?;
+                    &mut tick_ref
+                } else {
+                    tick_copy = *tick_loader.load()?;
+                    &mut tick_copy
+                };
+                // An empty tick account means the tick's liquidity lives in a tick array.
+                if tick_data.initialized == 0 {
+                    return Err(tick::missing_tick_account(tick_index));
+                }
+                Ok(tick_data.cross(fee_growth_global_0_q64, fee_growth_global_1_q64))
+            },
+        )?;
+        if write_accounts {
+            self.store_bitmap_words(tick_bitmap_loaders)?;
+        }
+        let amount_0 = if zero_for_one {
+            result.amount_in
+        } else {
+            result.amount_out
+        };
+        self.record_volume(
+            u64::try_from(amount_0).unwrap_or(u64::MAX),
+            current_timestamp,
+        );
+
+        // Stopping short of an unloaded word is only an error if the swap had more to do.
+        if let Some((word_index, stop_sqrt_price_q64)) = unloaded_word {
+            let completed = if amount_specified > 0 {
+                result.amount_in
             } else {
-                return err!(ErrorCode::InvalidInput); // Or a more specific error
+                result.amount_out
+            } == amount_specified.unsigned_abs();
+            if !completed && self.sqrt_price_q64 == stop_sqrt_price_q64 {
+                return Err(tick_bitmap::missing_tick_bitmap_word(word_index));
             }
         }
-        let amount_to_swap_gross: u128 = amount_specified.unsigned_abs();
+        Ok(result)
+    }
 
-        if amount_to_swap_gross == 0 {
-            return Ok((0, 0));
+    /// Runs the swap loop, crossing ticks through `cross_tick`.
+    ///
+    /// `cross_tick` is called once per initialized tick crossed, in crossing order, with the
+    /// tick index and the pool's global token0 and token1 fee growth at the crossing. It
+    /// returns that tick's `liquidity_net`. This lets off-chain code replay a swap against
+    /// fetched tick data with the exact on-chain logic.
+    ///
+    /// Returns the amounts swapped and the split of the swap fee between liquidity providers
+    /// and the protocol. Fails with `MaxTickCrossingsExceeded` if the swap needs more than
+    /// `MAX_SWAP_ITERATIONS` steps.
+    pub(crate) fn swap_with_tick_source<F>(
+        &mut self,
+        zero_for_one: bool,
+        amount_specified: i128,
+        sqrt_price_limit_q64: u128,
+        mut cross_tick: F,
+    ) -> Result<SwapResult>
+    where
+        F: FnMut(i32, u128, u128) -> Result<i128>,
+    {
+        if amount_specified == 0 {
+            return Ok(SwapResult::default());
         }
+        let exact_input = amount_specified > 0;
 
         let mut total_amount_in_gross: u128 = 0;
         let mut total_amount_out_net: u128 = 0;
-        let mut amount_remaining_gross = amount_to_swap_gross;
+        let mut total_lp_fee: u128 = 0;
+        let mut total_protocol_fee: u128 = 0;
+        // Input remaining for exact input, output remaining for exact output.
+        let mut amount_remaining = amount_specified.unsigned_abs();
         let mut current_sqrt_price_q64 = self.sqrt_price_q64;
+        // Tick whose range the current price is in. After crossing tick T downwards the price
+        // sits exactly on T, but T's liquidity is no longer active, so the tick is T - 1.
         let mut current_tick_effective = self.current_tick;
+        // Set once a step moves the price without crossing; the tick is then re-derived from it.
+        let mut tick_from_price = false;
+        let mut iterations = 0;
+        let mut ticks_crossed = 0;
 
-        while amount_remaining_gross > 0 {
+        while amount_remaining > 0 {
             if (zero_for_one && current_sqrt_price_q64 <= sqrt_price_limit_q64)
                 || (!zero_for_one && current_sqrt_price_q64 >= sqrt_price_limit_q64)
             {
                 break; // Price limit reached
             }
+            // Checked before the step touches any state, so a failing swap never leaves a
+            // step half applied.
+            if iterations == MAX_SWAP_ITERATIONS {
+                flog!(
+                    error,
+                    "max_tick_crossings_exceeded",
+                    tick = current_tick_effective,
+                    amount_remaining = amount_remaining
+                );
+                return err!(ErrorCode::MaxTickCrossingsExceeded);
+            }
+            iterations += 1;
 
             let current_tick_bitmap: BTreeMap<i16, u64> =
                 borsh::BorshDeserialize::try_from_slice(&self.tick_bitmap_data)
                     .expect("Failed to deserialize tick_bitmap for swap");
 
+            // The current tick is already active, so an upward search starts one tick above it.
             let next_initialized_tick_index_opt = tick_bitmap::next_initialized_tick(
                 &current_tick_bitmap,
-                current_tick_effective,
-                self.tick_spacing,
+                if zero_for_one {
+                    current_tick_effective
+                } else {
+                    current_tick_effective + 1
+                },
+                self.bitmap_tick_spacing,
                 zero_for_one,
             )?;
 
@@ -458,14 +1657,17 @@ impl<'info> Pool {
                 sqrt_price_at_next_tick_q64.min(sqrt_price_limit_q64)
             };
 
-            let (step_gross_in, step_net_out, next_step_sqrt_price_q64) = self.swap_step(
-                current_sqrt_price_q64,
-                sqrt_price_target_for_step_q64,
-                self.liquidity,
-                amount_remaining_gross,
-                self.fee_rate,
-                zero_for_one,
-            )?;
+            let step_start_sqrt_price_q64 = current_sqrt_price_q64;
+            let (step_gross_in, step_net_out, next_step_sqrt_price_q64, step_fee) = self
+                .swap_step(
+                    current_sqrt_price_q64,
+                    sqrt_price_target_for_step_q64,
+                    self.liquidity,
+                    amount_remaining,
+                    self.fee_rate,
+                    zero_for_one,
+                    exact_input,
+                )?;
 
             total_amount_in_gross = total_amount_in_gross
                 .checked_add(step_gross_in)
@@ -473,76 +1675,122 @@ impl<'info> Pool {
             total_amount_out_net = total_amount_out_net
                 .checked_add(step_net_out)
                 .ok_or(ErrorCode::MathOverflow)?;
-            amount_remaining_gross = amount_remaining_gross
-                .checked_sub(step_gross_in)
+            amount_remaining = amount_remaining
+                .checked_sub(if exact_input {
+                    step_gross_in
+                } else {
+                    step_net_out
+                })
                 .ok_or(ErrorCode::MathOverflow)?;
             current_sqrt_price_q64 = next_step_sqrt_price_q64;
 
-            // If no gross input was consumed in this step, it means no progress was made on the amount.
-            // This can happen if, for example, the target price for the step was the current price,
-            // or if liquidity for the step was zero (though self.liquidity is constant here for MVP).
-            // Break to prevent an infinite loop if amount_remaining_gross is still > 0 (which is implied by the while loop condition).
-            if step_gross_in == 0 {
-                break;
+            // Fees are shared by the liquidity active during the step, in the input token,
+            // after the protocol takes its cut.
+            let lp_fee = self.accrue_protocol_fee(step_fee, zero_for_one)?;
+            total_lp_fee += lp_fee;
+            total_protocol_fee += step_fee - lp_fee;
+            let fee_growth_delta_q64 = math::get_fee_growth_delta_q64(lp_fee, self.liquidity)?;
+            if zero_for_one {
+                self.fee_growth_global_0_q64 = self
+                    .fee_growth_global_0_q64
+                    .wrapping_add(fee_growth_delta_q64);
+            } else {
+                self.fee_growth_global_1_q64 = self
+                    .fee_growth_global_1_q64
+                    .wrapping_add(fee_growth_delta_q64);
             }
 
-            if current_sqrt_price_q64 == sqrt_price_at_next_tick_q64
-                && next_initialized_tick_index_opt.is_some()
-            {
-                let next_tick_idx = next_initialized_tick_index_opt.unwrap();
-                let mut found_tick_loader: Option<&AccountLoader<'info, TickData>> = None;
-
-                for loader in tick_loaders.iter() {
-                    // Peek at the index without fully loading if possible, or load and check.
-                    // For AccountLoader, we need to load to access its fields.
-                    // This assumes the client provides the correct tick accounts.
-                    // A more robust system might involve deriving the PDA for the tick
-                    // and ensuring the provided account matches that PDA.
-                    let tick_data_ref = loader.load()?;
-                    if tick_data_ref.index == next_tick_idx && tick_data_ref.pool == *pool_key {
-                        found_tick_loader = Some(loader);
-                        break;
-                    }
-                }
+            // A tick is crossed whenever the price reaches it, even by a step that consumed
+            // nothing because the price already sat on the tick.
+            let crossed_tick_idx_opt = next_initialized_tick_index_opt
+                .filter(|_| current_sqrt_price_q64 == sqrt_price_at_next_tick_q64);
+            if let Some(next_tick_idx) = crossed_tick_idx_opt {
+                let liquidity_net_change = cross_tick(
+                    next_tick_idx,
+                    self.fee_growth_global_0_q64,
+                    self.fee_growth_global_1_q64,
+                )?;
+                ticks_crossed += 1;
+
+                flog!(
+                    debug,
+                    "tick_crossed",
+                    tick = next_tick_idx,
+                    liquidity_net = liquidity_net_change,
+                    liquidity = self.liquidity
+                );
 
-                if let Some(tick_loader) = found_tick_loader {
-                    let tick_data = tick_loader.load()?; // Load again or use already loaded ref
-                    let liquidity_net_change = tick_data.liquidity_net;
-
-                    msg!(
-                        "Crossed tick {}, liquidity_net: {}. Current pool liquidity: {}",
-                        next_tick_idx,
-                        liquidity_net_change,
-                        self.liquidity
-                    );
-
-                    // Update pool liquidity based on liquidity_net_change
-                    // If zero_for_one (price decreasing), liquidity_net is subtracted.
-                    // If !zero_for_one (price increasing), liquidity_net is added.
-                    self.liquidity = (self.liquidity as i128)
-                        .checked_add(if zero_for_one {
-                            -liquidity_net_change
-                        } else {
-                            liquidity_net_change
-                        })
+                // Update pool liquidity based on liquidity_net_change
+                // If zero_for_one (price decreasing), liquidity_net is subtracted.
+                // If !zero_for_one (price increasing), liquidity_net is added.
+                let liquidity_delta = if zero_for_one {
+                    liquidity_net_change
+                        .checked_neg()
                         .ok_or(ErrorCode::MathOverflow)?
-                        as u128;
                 } else {
-                    // Critical: A tick indicated by the bitmap was not provided by the client.
-                    // This is an error condition as the swap cannot proceed accurately.
-                    return err!(ErrorCode::TickNotFound);
-                }
+                    liquidity_net_change
+                };
+                self.liquidity = if liquidity_delta >= 0 {
+                    self.liquidity
+                        .checked_add(liquidity_delta as u128)
+                        .ok_or(ErrorCode::MathOverflow)?
+                } else {
+                    // More liquidity leaving than is active means the tick bookkeeping is
+                    // inconsistent; continuing would price the rest of the swap against a
+                    // wrapped-around liquidity.
+                    match self.liquidity.checked_sub(liquidity_delta.unsigned_abs()) {
+                        Some(liquidity) => liquidity,
+                        None => {
+                            flog!(
+                                error,
+                                "liquidity_underflow",
+                                tick = next_tick_idx,
+                                liquidity = self.liquidity,
+                                liquidity_delta = liquidity_delta
+                            );
+                            return err!(ErrorCode::LiquidityUnderflow);
+                        }
+                    }
+                };
 
-                current_tick_effective = next_tick_idx;
+                current_tick_effective = if zero_for_one {
+                    next_tick_idx - 1
+                } else {
+                    next_tick_idx
+                };
+                tick_from_price = false;
+            } else if step_gross_in == 0 {
+                // If no gross input was consumed in this step, it means no progress was made on the amount.
+                // This can happen if the target price for the step was the current price, or if
+                // the step crossed a range without liquidity to the price limit.
+                // Break to prevent an infinite loop if amount_remaining is still > 0 (which is implied by the while loop condition).
+                tick_from_price |= current_sqrt_price_q64 != step_start_sqrt_price_q64;
+                break;
             } else {
                 // Did not reach the next tick, or no next tick, or hit price limit
-                // The loop will break if amount_remaining_gross is 0 or price limit is hit.
+                // The loop will break if amount_remaining is 0 or price limit is hit.
+                tick_from_price = true;
             }
         }
 
         self.sqrt_price_q64 = current_sqrt_price_q64;
-        self.current_tick = math::sqrt_price_q64_to_tick(self.sqrt_price_q64)?;
+        self.current_tick = if tick_from_price {
+            math::sqrt_price_q64_to_tick(self.sqrt_price_q64)?
+        } else {
+            current_tick_effective
+        };
 
-        Ok((total_amount_in_gross, total_amount_out_net))
+        Ok(SwapResult {
+            amount_in: total_amount_in_gross,
+            amount_out: total_amount_out_net,
+            lp_fee: total_lp_fee,
+            protocol_fee: total_protocol_fee,
+            ticks_crossed,
+        })
     }
 }
+
+/// Start of the daily volume window containing `timestamp`.
+fn volume_window_start(timestamp: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(VOLUME_WINDOW_SECS)
+}
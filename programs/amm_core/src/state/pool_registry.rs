@@ -0,0 +1,86 @@
+use crate::errors::ErrorCode;
+use anchor_lang::prelude::*;
+
+/// Maximum number of fee tiers that can be registered for a single token pair.
+pub const MAX_FEE_TIERS_PER_PAIR: usize = 8;
+
+/// Lists every pool created for a token pair, one entry per fee tier.
+///
+/// Pools are PDAs of `[b"pool", mint_a, mint_b, fee_rate]`, so each pair can have one pool
+/// per fee rate. The registry is a PDA of `[b"pool_registry", mint_a, mint_b]` and lets
+/// clients enumerate all tiers of a pair with a single account fetch.
+#[account]
+#[derive(Default, Debug)]
+pub struct PoolRegistry {
+    /// Bump seed for PDA.
+    pub bump: u8,
+    /// The canonically smaller mint of the pair.
+    pub mint_a: Pubkey,
+    /// The canonically larger mint of the pair.
+    pub mint_b: Pubkey,
+    /// One entry per pool created for this pair, in creation order.
+    pub entries: Vec<PoolRegistryEntry>,
+}
+
+/// A single (pair, fee tier) pool in a [`PoolRegistry`].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct PoolRegistryEntry {
+    /// Fee rate of the pool, in basis points.
+    pub fee_rate: u16,
+    /// The pool account.
+    pub pool: Pubkey,
+}
+
+impl PoolRegistry {
+    /// Size of a serialized `PoolRegistryEntry`: fee_rate (2) + pool (32).
+    const ENTRY_LEN: usize = 2 + 32;
+
+    /// The size of the PoolRegistry account in bytes.
+    pub const LEN: usize = 8 // discriminator
+        + 1 // bump
+        + 32 // mint_a
+        + 32 // mint_b
+        + 4 + MAX_FEE_TIERS_PER_PAIR * Self::ENTRY_LEN; // entries: Vec (4 for len + data)
+
+    /// Records a newly created pool for the pair.
+    ///
+    /// Initializes the registry on its first use.
+    ///
+    /// # Arguments
+    /// * `bump` - The bump seed for the registry's PDA.
+    /// * `mint_a` - The canonically smaller mint of the pair.
+    /// * `mint_b` - The canonically larger mint of the pair.
+    /// * `fee_rate` - The fee rate of the new pool, in basis points.
+    /// * `pool` - The new pool account.
+    pub fn register(
+        &mut self,
+        bump: u8,
+        mint_a: Pubkey,
+        mint_b: Pubkey,
+        fee_rate: u16,
+        pool: Pubkey,
+    ) -> Result<()> {
+        if self.mint_a == Pubkey::default() {
+            self.bump = bump;
+            self.mint_a = mint_a;
+            self.mint_b = mint_b;
+        }
+        if self.pool_for_fee_rate(fee_rate).is_some() {
+            return err!(ErrorCode::FeeTierAlreadyRegistered);
+        }
+        if self.entries.len() >= MAX_FEE_TIERS_PER_PAIR {
+            return err!(ErrorCode::PoolRegistryFull);
+        }
+
+        self.entries.push(PoolRegistryEntry { fee_rate, pool });
+        Ok(())
+    }
+
+    /// Returns the pool registered for `fee_rate`, if any.
+    pub fn pool_for_fee_rate(&self, fee_rate: u16) -> Option<Pubkey> {
+        self.entries
+            .iter()
+            .find(|entry| entry.fee_rate == fee_rate)
+            .map(|entry| entry.pool)
+    }
+}
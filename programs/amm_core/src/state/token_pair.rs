@@ -0,0 +1,72 @@
+use crate::errors::ErrorCode;
+use anchor_lang::prelude::*;
+
+/// Pair-level record shared by every pool of a token pair.
+///
+/// The pair is a PDA of `[b"token_pair", mint_a, mint_b]` with the mints in canonical
+/// order (`mint_a < mint_b`), so each pair has exactly one record. It is created once with
+/// `create_token_pair`, and every pool of the pair must reference it when initialized.
+#[account]
+#[derive(Default, Debug)]
+pub struct TokenPair {
+    /// Bump seed for PDA.
+    pub bump: u8,
+    /// The canonically smaller mint of the pair.
+    pub mint_a: Pubkey,
+    /// The canonically larger mint of the pair.
+    pub mint_b: Pubkey,
+    /// Price feed suggested by the pair's creator as the default oracle for its pools.
+    /// `Pubkey::default()` if none was given.
+    pub oracle_feed: Pubkey,
+    /// True once the pair has been created with its mints in canonical order.
+    pub is_canonical: bool,
+    /// Number of pools initialized for the pair, across all fee tiers.
+    pub pool_count: u32,
+}
+
+impl TokenPair {
+    /// The size of the TokenPair account in bytes.
+    pub const LEN: usize = 8 // discriminator
+        + 1 // bump
+        + 32 // mint_a
+        + 32 // mint_b
+        + 32 // oracle_feed
+        + 1 // is_canonical
+        + 4; // pool_count
+
+    /// Initializes the record of a new pair.
+    ///
+    /// # Arguments
+    /// * `bump` - The bump seed for the pair's PDA.
+    /// * `mint_a` - The canonically smaller mint of the pair.
+    /// * `mint_b` - The canonically larger mint of the pair.
+    /// * `oracle_feed` - The suggested default oracle feed, or `Pubkey::default()`.
+    pub fn initialize(
+        &mut self,
+        bump: u8,
+        mint_a: Pubkey,
+        mint_b: Pubkey,
+        oracle_feed: Pubkey,
+    ) -> Result<()> {
+        if mint_a >= mint_b {
+            return err!(ErrorCode::MintsNotInCanonicalOrder);
+        }
+
+        self.bump = bump;
+        self.mint_a = mint_a;
+        self.mint_b = mint_b;
+        self.oracle_feed = oracle_feed;
+        self.is_canonical = true;
+        self.pool_count = 0;
+        Ok(())
+    }
+
+    /// Records a newly initialized pool of the pair.
+    pub fn record_pool(&mut self) -> Result<()> {
+        self.pool_count = self
+            .pool_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
@@ -10,9 +10,8 @@ use anchor_lang::prelude::*;
 
 /// Represents the state of an initialized tick.
 ///
-/// For the MVP, this struct focuses on core liquidity tracking.
-/// Fee growth and oracle-related fields are omitted for simplification as per
-/// the MVP scope.
+/// For the MVP, this struct focuses on core liquidity and fee growth tracking.
+/// Oracle-related fields are omitted for simplification as per the MVP scope.
 ///
 /// Accounts of this type would typically be PDAs derived from the pool
 /// and the tick index.
@@ -20,27 +19,45 @@ use anchor_lang::prelude::*;
 #[repr(C)]
 #[derive(Debug, Default)]
 pub struct TickData {
-    // MVP Simplification: Skipping fee_growth_outside_... and oracle fields.
+    // MVP Simplification: Skipping oracle fields.
     /// total gross liquidity (16-byte align)
     pub liquidity_gross: u128, // offset 0
     /// net liquidity change        (16-byte align)
     pub liquidity_net: i128, // offset 16
+    /// token0 fee growth on the other side of this tick from the current tick, Q64.64
+    pub fee_growth_outside_0_q64: u128, // offset 32
+    /// token1 fee growth on the other side of this tick from the current tick, Q64.64
+    pub fee_growth_outside_1_q64: u128, // offset 48
     /// pool pubkey                (1-byte align)
-    pub pool: Pubkey, // offset 32
+    pub pool: Pubkey, // offset 64
     /// the index                  (4-byte align)
-    pub index: i32, // offset 64
+    pub index: i32, // offset 96
     /// initialized flag           (1-byte align)
-    pub initialized: u8, // offset 68
-    // split the 59 bytes into two chunks ≤ 32
-    pub _padding0: [u8; 32], // offset 69..100
-    pub _padding1: [u8; 27], // offset 101..127
+    pub initialized: u8, // offset 100
+    pub _padding: [u8; 27], // offset 101..127
 }
 
 impl TickData {
-    /// Total size of the fields: 16 (liquidity_gross) + 16 (liquidity_net) + 32 (pool) + 4 (index) + 1 (initialized) + 32 (_padding0) + 27 (_padding1) = 128 bytes.
+    /// Total size of the fields: 16 (liquidity_gross) + 16 (liquidity_net) + 16 (fee_growth_outside_0_q64) + 16 (fee_growth_outside_1_q64) + 32 (pool) + 4 (index) + 1 (initialized) + 27 (_padding) = 128 bytes.
     /// Anchor's `#[account(zero_copy)]` handles the 8-byte discriminator separately.
     pub const LEN: usize = 128;
 
+    /// Derives the PDA of the tick account for `tick_index` in `pool`.
+    ///
+    /// Seeds: `[b"tick", pool, tick_index.to_le_bytes()]`, matching the `tick_*` accounts
+    /// initialized by `mint_position` and `update_position`.
+    pub fn address(pool: &Pubkey, tick_index: i32) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                b"tick".as_ref(),
+                pool.as_ref(),
+                tick_index.to_le_bytes().as_ref(),
+            ],
+            &crate::ID,
+        )
+        .0
+    }
+
     /// Initializes a new tick with default values.
     ///
     /// # Arguments
@@ -52,9 +69,26 @@ impl TickData {
         self.index = index;
         self.liquidity_gross = 0;
         self.liquidity_net = 0;
+        self.fee_growth_outside_0_q64 = 0;
+        self.fee_growth_outside_1_q64 = 0;
         self.initialized = 0; // 0 for false
-        self._padding0 = [0; 32];
-        self._padding1 = [0; 27];
+        self._padding = [0; 27];
+    }
+
+    /// Updates the tick when a swap crosses it and returns its `liquidity_net`.
+    ///
+    /// The fee growth outside the tick flips to the other side of the current price.
+    ///
+    /// # Arguments
+    ///
+    /// * `fee_growth_global_0_q64` - The pool's global token0 fee growth at the crossing.
+    /// * `fee_growth_global_1_q64` - The pool's global token1 fee growth at the crossing.
+    pub fn cross(&mut self, fee_growth_global_0_q64: u128, fee_growth_global_1_q64: u128) -> i128 {
+        self.fee_growth_outside_0_q64 =
+            fee_growth_global_0_q64.wrapping_sub(self.fee_growth_outside_0_q64);
+        self.fee_growth_outside_1_q64 =
+            fee_growth_global_1_q64.wrapping_sub(self.fee_growth_outside_1_q64);
+        self.liquidity_net
     }
 
     /// Updates the tick's liquidity values when a position referencing this tick changes.
@@ -94,3 +128,68 @@ impl TickData {
         Ok(())
     }
 }
+
+/// Wraps the `TickData` accounts passed through `remaining_accounts` as loaders.
+///
+/// The order is preserved: swaps expect the ticks in the order they will be crossed.
+/// Fails with Anchor's owner/discriminator errors if an account is not a `TickData` account.
+pub fn load_tick_accounts<'info>(
+    accounts: &'info [AccountInfo<'info>],
+) -> Result<Vec<AccountLoader<'info, TickData>>> {
+    accounts.iter().map(AccountLoader::try_from).collect()
+}
+
+/// Validates the tick accounts supplied to a swap and returns their tick indices.
+///
+/// Every account must be the tick PDA of `pool_key` for the index it stores, and the
+/// indices must be strictly ordered in the swap direction: descending when `zero_for_one`,
+/// ascending otherwise.
+///
+/// # Errors
+///
+/// * `InvalidTickAccount` - An account belongs to another pool or is not the tick's PDA.
+/// * `InvalidTickAccountOrder` - The ticks are not strictly ordered in the swap direction.
+pub fn validate_swap_tick_accounts(
+    tick_loaders: &[&AccountLoader<TickData>],
+    pool_key: &Pubkey,
+    zero_for_one: bool,
+) -> Result<Vec<i32>> {
+    let mut tick_indices: Vec<i32> = Vec::with_capacity(tick_loaders.len());
+    for tick_loader in tick_loaders {
+        let tick_index = {
+            let tick_data = tick_loader.load()?;
+            require_keys_eq!(tick_data.pool, *pool_key, ErrorCode::InvalidTickAccount);
+            tick_data.index
+        };
+        require_keys_eq!(
+            tick_loader.key(),
+            TickData::address(pool_key, tick_index),
+            ErrorCode::InvalidTickAccount
+        );
+        if let Some(&previous) = tick_indices.last() {
+            let ordered = if zero_for_one {
+                tick_index < previous
+            } else {
+                tick_index > previous
+            };
+            require!(ordered, ErrorCode::InvalidTickAccountOrder);
+        }
+        tick_indices.push(tick_index);
+    }
+    Ok(tick_indices)
+}
+
+/// Builds a `MissingTickAccount` error whose message names the tick that was not supplied.
+pub fn missing_tick_account(tick_index: i32) -> Error {
+    Error::from(AnchorError {
+        error_name: ErrorCode::MissingTickAccount.name(),
+        error_code_number: ErrorCode::MissingTickAccount.into(),
+        error_msg: format!(
+            "{} (expected tick index {})",
+            ErrorCode::MissingTickAccount,
+            tick_index
+        ),
+        error_origin: None,
+        compared_values: None,
+    })
+}
@@ -0,0 +1,274 @@
+use crate::constants::{MAX_BATCH_ITERATIONS, MAX_TICK, MIN_TICK};
+use crate::errors::ErrorCode;
+use crate::position::PositionData;
+use crate::state::pool::Pool;
+use crate::tick::TickData;
+use crate::tick_bitmap::TickBitmap;
+/// Fixed windows of ticks stored in a single account.
+///
+/// A `TickArray` holds `TICK_ARRAY_SIZE` consecutive usable ticks of a pool, starting at
+/// a multiple of `TICK_ARRAY_SIZE * tick_spacing`. Wide positions and swaps crossing many
+/// ticks then pass one account per window instead of one `TickData` account per tick.
+///
+/// Tick arrays and the per-tick `TickData` PDAs can coexist in a pool, but the liquidity
+/// of any given tick lives in exactly one of them. The pool's tick bitmap enforces this:
+/// liquidity can only be added to an empty tick entry while the tick is uninitialized.
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+/// Number of ticks held by a `TickArray`.
+pub const TICK_ARRAY_SIZE: usize = 64;
+
+/// A window of `TICK_ARRAY_SIZE` ticks of a pool.
+///
+/// Accounts of this type are PDAs derived from the pool and the start tick index,
+/// `[b"tick_array", pool, start_tick_index.to_le_bytes()]`.
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct TickArray {
+    /// Entry `i` is tick `start_tick_index + i * tick_spacing`.
+    pub ticks: [TickData; TICK_ARRAY_SIZE], // offset 0
+    /// pool pubkey
+    pub pool: Pubkey, // offset 8192
+    /// index of the first tick in the array
+    pub start_tick_index: i32, // offset 8224
+    pub _padding: [u8; 12], // offset 8228..8240
+}
+
+impl TickArray {
+    /// Total size of the fields: 64 * 128 (ticks) + 32 (pool) + 4 (start_tick_index) + 12 (_padding) = 8240 bytes.
+    /// Anchor's `#[account(zero_copy)]` handles the 8-byte discriminator separately.
+    pub const LEN: usize = TICK_ARRAY_SIZE * TickData::LEN + 32 + 4 + 12;
+
+    /// Returns the start index of the tick array holding `tick_index`.
+    pub fn start_tick_index(tick_index: i32, tick_spacing: u16) -> i32 {
+        let ticks_per_array = TICK_ARRAY_SIZE as i32 * tick_spacing as i32;
+        tick_index.div_euclid(ticks_per_array) * ticks_per_array
+    }
+
+    /// Derives the PDA of the tick array starting at `start_tick_index` in `pool`.
+    pub fn address(pool: &Pubkey, start_tick_index: i32) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                b"tick_array".as_ref(),
+                pool.as_ref(),
+                start_tick_index.to_le_bytes().as_ref(),
+            ],
+            &crate::ID,
+        )
+        .0
+    }
+
+    /// Initializes an empty tick array.
+    ///
+    /// Every entry is initialized as an empty tick of `pool` at its index.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The pubkey of the pool this tick array belongs to.
+    /// * `start_tick_index` - The index of the first tick. Must be a multiple of
+    ///   `TICK_ARRAY_SIZE * tick_spacing` and the array must overlap `MIN_TICK..=MAX_TICK`.
+    /// * `tick_spacing` - The pool's tick spacing.
+    pub fn initialize(
+        &mut self,
+        pool: Pubkey,
+        start_tick_index: i32,
+        tick_spacing: u16,
+    ) -> Result<()> {
+        require!(tick_spacing > 0, ErrorCode::InvalidTickSpacing);
+        let ticks_per_array = TICK_ARRAY_SIZE as i32 * tick_spacing as i32;
+        if Self::start_tick_index(start_tick_index, tick_spacing) != start_tick_index
+            || start_tick_index > MAX_TICK
+            || start_tick_index + ticks_per_array <= MIN_TICK
+        {
+            return err!(ErrorCode::InvalidTickArrayStartIndex);
+        }
+
+        self.pool = pool;
+        self.start_tick_index = start_tick_index;
+        self._padding = [0; 12];
+        for (i, tick) in self.ticks.iter_mut().enumerate() {
+            tick.initialize(pool, start_tick_index + i as i32 * tick_spacing as i32);
+        }
+        Ok(())
+    }
+
+    /// Returns the position of `tick_index` within `ticks`.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidTickSpacing` - The tick is not a multiple of `tick_spacing`.
+    /// * `TickNotInTickArray` - The tick lies outside this array.
+    fn tick_offset(&self, tick_index: i32, tick_spacing: u16) -> Result<usize> {
+        require!(
+            tick_spacing > 0 && tick_index % tick_spacing as i32 == 0,
+            ErrorCode::InvalidTickSpacing
+        );
+        if Self::start_tick_index(tick_index, tick_spacing) != self.start_tick_index {
+            return err!(ErrorCode::TickNotInTickArray);
+        }
+        Ok(((tick_index - self.start_tick_index) / tick_spacing as i32) as usize)
+    }
+
+    /// Returns true if `tick_index` falls inside this array.
+    pub fn contains(&self, tick_index: i32, tick_spacing: u16) -> bool {
+        tick_spacing > 0
+            && Self::start_tick_index(tick_index, tick_spacing) == self.start_tick_index
+    }
+
+    /// Returns the entry for `tick_index`, checking that it is aligned and in bounds.
+    pub fn get_tick(&self, tick_index: i32, tick_spacing: u16) -> Result<&TickData> {
+        let offset = self.tick_offset(tick_index, tick_spacing)?;
+        Ok(&self.ticks[offset])
+    }
+
+    /// Returns the mutable entry for `tick_index`, checking that it is aligned and in bounds.
+    pub fn get_tick_mut(&mut self, tick_index: i32, tick_spacing: u16) -> Result<&mut TickData> {
+        let offset = self.tick_offset(tick_index, tick_spacing)?;
+        Ok(&mut self.ticks[offset])
+    }
+}
+
+/// The tick accounts passed to a swap, split by kind.
+pub struct SwapTickAccounts<'info> {
+    /// `TickData` accounts, in the order they were passed.
+    pub ticks: Vec<AccountLoader<'info, TickData>>,
+    /// `TickArray` accounts, in the order they were passed.
+    pub tick_arrays: Vec<AccountLoader<'info, TickArray>>,
+    /// `TickBitmap` accounts, in the order they were passed.
+    pub tick_bitmaps: Vec<AccountLoader<'info, TickBitmap>>,
+}
+
+/// Wraps the accounts passed to a swap through `remaining_accounts` as loaders.
+///
+/// Tick arrays and tick bitmap words are recognized by their discriminator; every other
+/// account must be a `TickData` account. The relative order of the `TickData` accounts is
+/// preserved, as swaps expect them in the order they will be crossed. Tick arrays and tick
+/// bitmap words can come in any order.
+///
+/// Fails with `MaxBatchSizeExceeded` if more than `MAX_BATCH_ITERATIONS` accounts are passed.
+pub fn load_swap_tick_accounts<'info>(
+    accounts: &'info [AccountInfo<'info>],
+) -> Result<SwapTickAccounts<'info>> {
+    require!(
+        accounts.len() <= MAX_BATCH_ITERATIONS,
+        ErrorCode::MaxBatchSizeExceeded
+    );
+    let mut swap_tick_accounts = SwapTickAccounts {
+        ticks: Vec::new(),
+        tick_arrays: Vec::new(),
+        tick_bitmaps: Vec::new(),
+    };
+    for account in accounts {
+        let data = account.try_borrow_data()?;
+        let is_tick_array = data.starts_with(TickArray::DISCRIMINATOR);
+        let is_tick_bitmap = data.starts_with(TickBitmap::DISCRIMINATOR);
+        drop(data);
+        if is_tick_array {
+            swap_tick_accounts
+                .tick_arrays
+                .push(AccountLoader::try_from(account)?);
+        } else if is_tick_bitmap {
+            swap_tick_accounts
+                .tick_bitmaps
+                .push(AccountLoader::try_from(account)?);
+        } else {
+            swap_tick_accounts
+                .ticks
+                .push(AccountLoader::try_from(account)?);
+        }
+    }
+    Ok(swap_tick_accounts)
+}
+
+/// Validates the tick arrays supplied to a swap and returns their start tick indices.
+///
+/// # Errors
+///
+/// * `InvalidTickAccount` - An array belongs to another pool or is not the array's PDA.
+pub fn validate_swap_tick_arrays(
+    tick_array_loaders: &[&AccountLoader<TickArray>],
+    pool_key: &Pubkey,
+) -> Result<Vec<i32>> {
+    tick_array_loaders
+        .iter()
+        .map(|tick_array_loader| {
+            let start_tick_index = {
+                let tick_array = tick_array_loader.load()?;
+                require_keys_eq!(tick_array.pool, *pool_key, ErrorCode::InvalidTickAccount);
+                tick_array.start_tick_index
+            };
+            require_keys_eq!(
+                tick_array_loader.key(),
+                TickArray::address(pool_key, start_tick_index),
+                ErrorCode::InvalidTickAccount
+            );
+            Ok(start_tick_index)
+        })
+        .collect()
+}
+
+/// The accounts holding a position's lower and upper ticks.
+pub enum PositionTicks<'a, 'info> {
+    /// One `TickData` PDA per tick.
+    Single {
+        lower: &'a AccountLoader<'info, TickData>,
+        upper: &'a AccountLoader<'info, TickData>,
+    },
+    /// The tick arrays holding each tick. Both can be the same account.
+    Arrays {
+        lower: &'a AccountLoader<'info, TickArray>,
+        upper: &'a AccountLoader<'info, TickArray>,
+    },
+}
+
+impl<'info> PositionTicks<'_, 'info> {
+    /// Returns copies of the position's lower and upper tick data.
+    ///
+    /// Fails with `TickStorageConflict` if the position has liquidity but one of the ticks
+    /// holds none, meaning the position's liquidity lives in other tick accounts.
+    pub fn load(&self, position: &PositionData, tick_spacing: u16) -> Result<(TickData, TickData)> {
+        let (lower, upper) = match self {
+            PositionTicks::Single { lower, upper } => (*lower.load()?, *upper.load()?),
+            PositionTicks::Arrays { lower, upper } => (
+                *lower
+                    .load()?
+                    .get_tick(position.tick_lower_index, tick_spacing)?,
+                *upper
+                    .load()?
+                    .get_tick(position.tick_upper_index, tick_spacing)?,
+            ),
+        };
+        if position.liquidity > 0 && (lower.liquidity_gross == 0 || upper.liquidity_gross == 0) {
+            return err!(ErrorCode::TickStorageConflict);
+        }
+        Ok((lower, upper))
+    }
+
+    /// Applies `liquidity_delta` to the position's range through `pool`.
+    pub fn modify_liquidity(
+        &self,
+        pool: &mut Pool,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        liquidity_delta: i128,
+    ) -> Result<()> {
+        match self {
+            PositionTicks::Single { lower, upper } => pool.modify_liquidity(
+                tick_lower_index,
+                tick_upper_index,
+                liquidity_delta,
+                lower,
+                upper,
+            ),
+            PositionTicks::Arrays { lower, upper } => pool.modify_liquidity_in_tick_arrays(
+                tick_lower_index,
+                tick_upper_index,
+                liquidity_delta,
+                lower,
+                upper,
+            ),
+        }
+    }
+}
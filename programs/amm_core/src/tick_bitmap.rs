@@ -1,3 +1,4 @@
+use crate::constants::MAX_BATCH_ITERATIONS;
 use crate::errors::ErrorCode;
 use anchor_lang::prelude::*;
 use std::collections::BTreeMap;
@@ -218,12 +219,30 @@ pub fn is_tick_initialized(
 /// assert_eq!(next_tick, None);
 /// ```
 /// # Note
-/// This function searches for the next initialized tick in the bitmap.
+/// The search visits only populated words, so its cost grows with the number of populated
+/// words between `current_tick_approx` and the result, not with the distance between them.
 pub fn next_initialized_tick(
     tick_bitmap: &BTreeMap<i16, u64>,
     current_tick_approx: i32,
     tick_spacing: u16,
     search_lte: bool,
+) -> Result<Option<i32>> {
+    next_initialized_tick_counting_lookups(
+        tick_bitmap,
+        current_tick_approx,
+        tick_spacing,
+        search_lte,
+        &mut 0,
+    )
+}
+
+/// [`next_initialized_tick`], adding the number of bitmap words it reads to `word_lookups`.
+pub(crate) fn next_initialized_tick_counting_lookups(
+    tick_bitmap: &BTreeMap<i16, u64>,
+    current_tick_approx: i32,
+    tick_spacing: u16,
+    search_lte: bool,
+    word_lookups: &mut u32,
 ) -> Result<Option<i32>> {
     let tick_spacing_i32 = tick_spacing as i32;
     if tick_spacing_i32 <= 0 {
@@ -269,62 +288,175 @@ pub fn next_initialized_tick(
     let (search_ref_word_idx, search_ref_bit_pos) =
         get_word_index_and_bit_pos(compressed_search_start_tick_ref)?;
 
-    if search_lte {
-        // 1. Search current word, downwards from current_bit_pos
-        if let Some(word_val) = tick_bitmap.get(&search_ref_word_idx) {
-            if let Some(found_bit_pos) =
-                next_initialized_bit_in_word(*word_val, search_ref_bit_pos, true)
-            {
-                let found_compressed_tick =
-                    search_ref_word_idx as i32 * WORD_SIZE as i32 + found_bit_pos as i32;
-                return Ok(Some(decompress_tick(found_compressed_tick, tick_spacing)));
-            }
-        }
-
-        // 2. Search preceding words (lower word indices)
-        // BTreeMap iterators go from smallest key to largest.
-        // `range(..current_word_idx).rev()` gets keys < current_word_idx, in descending order.
-        for (&word_idx, &word_val) in tick_bitmap.range(..search_ref_word_idx).rev() {
-            // word_val cannot be 0 because we remove zero words in flip_tick.
-            // Search the entire word from MSB (WORD_SIZE - 1) downwards.
-            if let Some(found_bit_pos) =
-                next_initialized_bit_in_word(word_val, (WORD_SIZE - 1) as u8, true)
-            {
-                let found_compressed_tick =
-                    word_idx as i32 * WORD_SIZE as i32 + found_bit_pos as i32;
-                return Ok(Some(decompress_tick(found_compressed_tick, tick_spacing)));
-            }
-        }
+    // Only populated words are keys of the map, so ranging over the keys jumps straight from
+    // one populated word to the next however many empty words lie between them. The first
+    // word yielded may be the reference word itself, which is only searched from the
+    // reference bit onwards.
+    let words: Box<dyn Iterator<Item = (&i16, &u64)>> = if search_lte {
+        Box::new(tick_bitmap.range(..=search_ref_word_idx).rev())
     } else {
-        // search_gte (search upwards)
-        // 1. Search current word, upwards from current_bit_pos
-        if let Some(word_val) = tick_bitmap.get(&search_ref_word_idx) {
-            if let Some(found_bit_pos) =
-                next_initialized_bit_in_word(*word_val, search_ref_bit_pos, false)
-            {
-                let found_compressed_tick =
-                    search_ref_word_idx as i32 * WORD_SIZE as i32 + found_bit_pos as i32;
-                return Ok(Some(decompress_tick(found_compressed_tick, tick_spacing)));
-            }
-        }
+        Box::new(tick_bitmap.range(search_ref_word_idx..))
+    };
 
-        // 2. Search succeeding words (higher word indices)
-        // `range((current_word_idx + 1)..)` gets keys > current_word_idx, in ascending order.
-        let start_next_word_idx = match search_ref_word_idx.checked_add(1) {
-            Some(idx) => idx,
-            None => return Ok(None), // current_word_idx is i16::MAX, no succeeding words
+    for (&word_idx, &word_val) in words {
+        *word_lookups += 1;
+        let start_bit_pos = if word_idx == search_ref_word_idx {
+            search_ref_bit_pos
+        } else if search_lte {
+            (WORD_SIZE - 1) as u8
+        } else {
+            0
         };
-
-        for (&word_idx, &word_val) in tick_bitmap.range(start_next_word_idx..) {
-            // word_val cannot be 0.
-            // Search the entire word from LSB (0) upwards.
-            if let Some(found_bit_pos) = next_initialized_bit_in_word(word_val, 0, false) {
-                let found_compressed_tick =
-                    word_idx as i32 * WORD_SIZE as i32 + found_bit_pos as i32;
-                return Ok(Some(decompress_tick(found_compressed_tick, tick_spacing)));
-            }
+        if let Some(found_bit_pos) =
+            next_initialized_bit_in_word(word_val, start_bit_pos, search_lte)
+        {
+            let found_compressed_tick = word_idx as i32 * WORD_SIZE as i32 + found_bit_pos as i32;
+            return Ok(Some(decompress_tick(found_compressed_tick, tick_spacing)));
         }
     }
 
     Ok(None) // No initialized tick found in the search direction
 }
+
+/// Iterates over the initialized ticks between `tick_lower` and `tick_upper`, both inclusive,
+/// in ascending order.
+///
+/// The bounds need not be aligned to `tick_spacing`. Yields nothing when the range is empty
+/// or `tick_spacing` is zero. Like [`next_initialized_tick`], only populated words are read.
+///
+/// # Example
+///
+/// let mut bitmap = BTreeMap::new();
+/// flip_tick_initialized_status(&mut bitmap, -60, 60, true)?;
+/// flip_tick_initialized_status(&mut bitmap, 120, 60, true)?;
+/// let ticks: Vec<i32> = initialized_ticks_in_range(&bitmap, -100, 100, 60).collect();
+/// assert_eq!(ticks, vec![-60]);
+///
+pub fn initialized_ticks_in_range(
+    tick_bitmap: &BTreeMap<i16, u64>,
+    tick_lower: i32,
+    tick_upper: i32,
+    tick_spacing: u16,
+) -> impl Iterator<Item = i32> + '_ {
+    // Compressed bounds of the aligned ticks inside the range: ceil(lower / spacing) and
+    // floor(upper / spacing). An empty or invalid range leaves `first > last`.
+    let (first, last) = if tick_spacing == 0 {
+        (1, 0)
+    } else {
+        let tick_spacing = tick_spacing as i64;
+        (
+            -(-(tick_lower as i64)).div_euclid(tick_spacing),
+            (tick_upper as i64).div_euclid(tick_spacing),
+        )
+    };
+    let word_index_of = |compressed_tick: i64| {
+        compressed_tick
+            .div_euclid(WORD_SIZE as i64)
+            .clamp(i16::MIN as i64, i16::MAX as i64) as i16
+    };
+    let words = if first <= last {
+        Some(tick_bitmap.range(word_index_of(first)..=word_index_of(last)))
+    } else {
+        None
+    };
+
+    words
+        .into_iter()
+        .flatten()
+        .flat_map(|(&word_idx, &word_val)| {
+            let mut remaining = word_val;
+            std::iter::from_fn(move || {
+                if remaining == 0 {
+                    return None;
+                }
+                let bit_pos = remaining.trailing_zeros() as i64;
+                remaining &= remaining - 1;
+                Some(word_idx as i64 * WORD_SIZE as i64 + bit_pos)
+            })
+        })
+        .skip_while(move |&compressed_tick| compressed_tick < first)
+        .take_while(move |&compressed_tick| compressed_tick <= last)
+        .map(move |compressed_tick| decompress_tick(compressed_tick as i32, tick_spacing))
+}
+
+/// Returns the index of the bitmap word covering `tick`, which need not be aligned to
+/// `tick_spacing`.
+pub fn word_index_of_tick(tick: i32, tick_spacing: u16) -> i16 {
+    let compressed_tick = tick.div_euclid(tick_spacing as i32);
+    compressed_tick
+        .div_euclid(WORD_SIZE as i32)
+        .clamp(i16::MIN as i32, i16::MAX as i32) as i16
+}
+
+/// Returns the lowest tick covered by bitmap word `word_index`.
+pub fn word_start_tick(word_index: i16, tick_spacing: u16) -> i32 {
+    word_index as i32 * WORD_SIZE as i32 * tick_spacing as i32
+}
+
+/// One word of a pool's tick bitmap, stored in its own account.
+///
+/// A pool keeps its bitmap words inline until the pool account runs out of room for them.
+/// Words can then be moved into `TickBitmap` accounts, PDAs of
+/// `[b"tick_bitmap", pool, word_index.to_le_bytes()]`, with `initialize_bitmap_word`. The
+/// pool lists the words it has moved out, and instructions that read or flip a listed
+/// word must pass its account.
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct TickBitmap {
+    /// Bit `i` is set if compressed tick `word_index * 64 + i` is initialized.
+    pub word: u64, // offset 0
+    /// pool pubkey
+    pub pool: Pubkey, // offset 8
+    /// index of the word in the pool's bitmap
+    pub word_index: i16, // offset 40
+    pub _padding: [u8; 6], // offset 42..48
+}
+
+impl TickBitmap {
+    /// Total size of the fields: 8 (word) + 32 (pool) + 2 (word_index) + 6 (_padding) = 48 bytes.
+    /// Anchor's `#[account(zero_copy)]` handles the 8-byte discriminator separately.
+    pub const LEN: usize = 8 + 32 + 2 + 6;
+
+    /// Derives the PDA of bitmap word `word_index` in `pool`.
+    pub fn address(pool: &Pubkey, word_index: i16) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                b"tick_bitmap".as_ref(),
+                pool.as_ref(),
+                word_index.to_le_bytes().as_ref(),
+            ],
+            &crate::ID,
+        )
+        .0
+    }
+}
+
+/// Builds a `MissingTickBitmapWord` error whose message names the word that was not supplied.
+pub fn missing_tick_bitmap_word(word_index: i16) -> Error {
+    Error::from(AnchorError {
+        error_name: ErrorCode::MissingTickBitmapWord.name(),
+        error_code_number: ErrorCode::MissingTickBitmapWord.into(),
+        error_msg: format!(
+            "{} (expected word index {})",
+            ErrorCode::MissingTickBitmapWord,
+            word_index
+        ),
+        error_origin: None,
+        compared_values: None,
+    })
+}
+
+/// Wraps the `TickBitmap` accounts passed through `remaining_accounts` as loaders.
+///
+/// Fails with Anchor's owner/discriminator errors if an account is not a `TickBitmap`, and
+/// with `MaxBatchSizeExceeded` if more than `MAX_BATCH_ITERATIONS` accounts are passed.
+pub fn load_tick_bitmap_accounts<'info>(
+    accounts: &'info [AccountInfo<'info>],
+) -> Result<Vec<AccountLoader<'info, TickBitmap>>> {
+    require!(
+        accounts.len() <= MAX_BATCH_ITERATIONS,
+        ErrorCode::MaxBatchSizeExceeded
+    );
+    accounts.iter().map(AccountLoader::try_from).collect()
+}
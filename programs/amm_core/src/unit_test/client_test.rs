@@ -0,0 +1,639 @@
+use crate::client::{
+    estimate_swap_tick_accounts, estimated_fee_apr_bps, owner_exposure, quote_swap,
+    sqrt_price_at_tick, FeeAprWindow, PriceRangePreset, TickBoundsHandling,
+};
+use crate::constants::{MAX_SQRT_PRICE, MAX_TICK, MIN_SQRT_PRICE, MIN_TICK};
+use crate::errors::ErrorCode;
+use crate::math;
+use crate::position::PositionData;
+use crate::state::pool::{InitializePoolParams, Pool};
+use crate::tick::TickData;
+use crate::tick_bitmap::flip_tick_initialized_status;
+
+use anchor_lang::prelude::*;
+use std::collections::BTreeMap;
+
+const WIDE_LIQUIDITY: i128 = 1_000_000_000;
+const NARROW_LIQUIDITY: i128 = 500_000_000;
+
+/// Pool at tick 0 with a wide position on [-120, 120] and a narrow one on [-60, 60].
+/// Returns the pool and the `liquidity_net` of its initialized ticks.
+fn setup_pool_with_two_positions() -> (Pool, BTreeMap<i32, i128>) {
+    let mut pool = Pool::default();
+    pool.initialize(InitializePoolParams {
+        bump: 1,
+        factory: Pubkey::new_unique(),
+        authority: Pubkey::new_unique(),
+        token0_mint: Pubkey::new_unique(),
+        token1_mint: Pubkey::new_unique(),
+        token0_vault: Pubkey::new_unique(),
+        token1_vault: Pubkey::new_unique(),
+        token_pair: Pubkey::new_unique(),
+        initial_sqrt_price_q64: math::tick_to_sqrt_price_q64(0).unwrap(),
+        fee_rate: 30,
+        protocol_fee: 0,
+        immutable_parameters: false,
+        tick_spacing: 60,
+        timestamp: 0,
+    })
+    .unwrap();
+    pool.liquidity = (WIDE_LIQUIDITY + NARROW_LIQUIDITY) as u128;
+
+    let tick_liquidity_net = BTreeMap::from([
+        (-120, WIDE_LIQUIDITY),
+        (-60, NARROW_LIQUIDITY),
+        (60, -NARROW_LIQUIDITY),
+        (120, -WIDE_LIQUIDITY),
+    ]);
+    let mut bitmap: BTreeMap<i16, u64> =
+        borsh::BorshDeserialize::try_from_slice(&pool.tick_bitmap_data).unwrap();
+    for &tick_index in tick_liquidity_net.keys() {
+        flip_tick_initialized_status(&mut bitmap, tick_index, pool.tick_spacing, true).unwrap();
+    }
+    pool.tick_bitmap_data = borsh::to_vec(&bitmap).unwrap();
+
+    (pool, tick_liquidity_net)
+}
+
+mod estimate_swap_tick_accounts_tests {
+    use super::*;
+
+    #[test]
+    fn test_small_swap_needs_no_tick_accounts() {
+        let (pool, tick_liquidity_net) = setup_pool_with_two_positions();
+        let pool_key = Pubkey::new_unique();
+
+        let estimate =
+            estimate_swap_tick_accounts(&pool, &pool_key, true, 1_000, 1, &tick_liquidity_net)
+                .unwrap();
+
+        assert_eq!(estimate.count(), 0);
+        assert_eq!(estimate.amount_in, 1_000);
+        assert!(estimate.amount_out > 0);
+    }
+
+    #[test]
+    fn test_zero_for_one_crosses_ticks_in_order() {
+        let (pool, tick_liquidity_net) = setup_pool_with_two_positions();
+        let pool_key = Pubkey::new_unique();
+        let limit = math::tick_to_sqrt_price_q64(-180).unwrap();
+
+        let estimate = estimate_swap_tick_accounts(
+            &pool,
+            &pool_key,
+            true,
+            u64::MAX,
+            limit,
+            &tick_liquidity_net,
+        )
+        .unwrap();
+
+        assert_eq!(estimate.tick_indices, vec![-60, -120]);
+        assert_eq!(
+            estimate.tick_accounts,
+            vec![
+                TickData::address(&pool_key, -60),
+                TickData::address(&pool_key, -120)
+            ]
+        );
+        // Liquidity runs out below tick -120, so the input is only partially consumed.
+        assert!(estimate.amount_in < u64::MAX as u128);
+    }
+
+    #[test]
+    fn test_one_for_zero_crosses_ticks_in_order() {
+        let (pool, tick_liquidity_net) = setup_pool_with_two_positions();
+        let pool_key = Pubkey::new_unique();
+        let limit = math::tick_to_sqrt_price_q64(180).unwrap();
+
+        let estimate = estimate_swap_tick_accounts(
+            &pool,
+            &pool_key,
+            false,
+            u64::MAX,
+            limit,
+            &tick_liquidity_net,
+        )
+        .unwrap();
+
+        assert_eq!(estimate.tick_indices, vec![60, 120]);
+        assert_eq!(estimate.count(), 2);
+    }
+
+    #[test]
+    fn test_estimate_stops_at_price_limit() {
+        let (pool, tick_liquidity_net) = setup_pool_with_two_positions();
+        let pool_key = Pubkey::new_unique();
+        let limit = math::tick_to_sqrt_price_q64(-90).unwrap();
+
+        let estimate = estimate_swap_tick_accounts(
+            &pool,
+            &pool_key,
+            true,
+            u64::MAX,
+            limit,
+            &tick_liquidity_net,
+        )
+        .unwrap();
+
+        assert_eq!(estimate.tick_indices, vec![-60]);
+    }
+
+    #[test]
+    fn test_estimate_does_not_modify_pool() {
+        let (pool, tick_liquidity_net) = setup_pool_with_two_positions();
+        let sqrt_price_before = pool.sqrt_price_q64;
+        let liquidity_before = pool.liquidity;
+
+        estimate_swap_tick_accounts(
+            &pool,
+            &Pubkey::new_unique(),
+            true,
+            u64::MAX,
+            1,
+            &tick_liquidity_net,
+        )
+        .unwrap();
+
+        assert_eq!(pool.sqrt_price_q64, sqrt_price_before);
+        assert_eq!(pool.liquidity, liquidity_before);
+    }
+}
+
+mod quote_swap_tests {
+    use super::*;
+
+    /// Exact input that stays between ticks -60 and 60.
+    const SMALL_AMOUNT: i128 = 1_000_000;
+
+    #[test]
+    fn test_quote_splits_fee_by_protocol_fee() {
+        let (mut pool, tick_liquidity_net) = setup_pool_with_two_positions();
+        let total = quote_swap(&pool, true, SMALL_AMOUNT, 1, &tick_liquidity_net).unwrap();
+        assert_eq!(total.protocol_fee, 0);
+        assert!(total.lp_fee > 0);
+
+        pool.set_protocol_fee(2_500).unwrap();
+        let quote = quote_swap(&pool, true, SMALL_AMOUNT, 1, &tick_liquidity_net).unwrap();
+
+        assert_eq!(quote.total_fee(), total.lp_fee);
+        assert_eq!(quote.protocol_fee, total.lp_fee * 2_500 / 10_000);
+        assert_eq!(quote.amount_in, total.amount_in);
+        assert_eq!(quote.amount_out, total.amount_out);
+    }
+
+    #[test]
+    fn test_quote_matches_fees_accrued_by_swap() {
+        let (mut pool, tick_liquidity_net) = setup_pool_with_two_positions();
+        pool.set_protocol_fee(1_000).unwrap();
+        let quote = quote_swap(&pool, true, SMALL_AMOUNT, 1, &tick_liquidity_net).unwrap();
+
+        let liquidity = pool.liquidity;
+        let (amount_in, amount_out) = pool
+            .swap(true, SMALL_AMOUNT, 1, &Pubkey::new_unique(), &[], 0)
+            .unwrap();
+
+        assert_eq!((quote.amount_in, quote.amount_out), (amount_in, amount_out));
+        assert_eq!(quote.protocol_fee, pool.protocol_fees_owed_a as u128);
+        assert_eq!(pool.protocol_fees_owed_b, 0);
+        // The LP fee is credited through fee growth, which rounds down by at most one unit.
+        let credited = math::get_fees_earned(liquidity, pool.fee_growth_global_0_q64, 0).unwrap();
+        assert!(quote.lp_fee - credited as u128 <= 1);
+    }
+
+    #[test]
+    fn test_quote_sums_fees_across_crossed_ticks() {
+        let (mut pool, tick_liquidity_net) = setup_pool_with_two_positions();
+        let limit = math::tick_to_sqrt_price_q64(150).unwrap();
+        let total = quote_swap(&pool, false, u64::MAX as i128, limit, &tick_liquidity_net).unwrap();
+
+        pool.set_protocol_fee(10_000).unwrap();
+        let quote = quote_swap(&pool, false, u64::MAX as i128, limit, &tick_liquidity_net).unwrap();
+
+        assert_eq!(quote.lp_fee, 0);
+        assert_eq!(quote.protocol_fee, total.total_fee());
+        // The swap takes three steps, crossing ticks 60 and 120.
+        assert!(quote.protocol_fee > 0);
+    }
+
+    #[test]
+    fn test_quote_exact_output() {
+        let (mut pool, tick_liquidity_net) = setup_pool_with_two_positions();
+        pool.set_protocol_fee(5_000).unwrap();
+        let quote = quote_swap(&pool, false, -SMALL_AMOUNT, u128::MAX, &tick_liquidity_net);
+        let quote = quote.unwrap();
+
+        assert_eq!(quote.amount_out, SMALL_AMOUNT as u128);
+        assert!(quote.amount_in > quote.amount_out);
+        assert!(quote.lp_fee >= quote.protocol_fee);
+        assert!(quote.lp_fee - quote.protocol_fee <= 1);
+    }
+
+    #[test]
+    fn test_quote_does_not_modify_pool() {
+        let (mut pool, tick_liquidity_net) = setup_pool_with_two_positions();
+        pool.set_protocol_fee(1_000).unwrap();
+
+        quote_swap(&pool, true, SMALL_AMOUNT, 1, &tick_liquidity_net).unwrap();
+
+        assert_eq!(pool.protocol_fees_owed_a, 0);
+        assert_eq!(pool.fee_growth_global_0_q64, 0);
+    }
+}
+
+mod price_range_preset_tests {
+    use super::*;
+    use crate::constants::MAX_TICK;
+    use crate::errors::ErrorCode;
+
+    const PRESETS: [PriceRangePreset; 3] = [
+        PriceRangePreset::Stable,
+        PriceRangePreset::Narrow,
+        PriceRangePreset::Wide,
+    ];
+
+    /// Tick offsets of each preset's bounds from the current tick, with a tick spacing of
+    /// one: log₁.₀₀₀₁(1 ∓ half width), rounded outward.
+    const TICK_OFFSETS: [(i32, i32); 3] = [(-51, 50), (-203, 199), (-2232, 1824)];
+
+    fn sqrt_price_at(tick: i32) -> u128 {
+        math::tick_to_sqrt_price_q64(tick).unwrap()
+    }
+
+    #[test]
+    fn test_preset_widths() {
+        for (preset, (lower, upper)) in PRESETS.into_iter().zip(TICK_OFFSETS) {
+            assert_eq!(
+                preset.tick_range(sqrt_price_at(0), 1).unwrap(),
+                (lower, upper),
+                "{preset:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_preset_width_does_not_depend_on_price() {
+        for current_tick in [-50_000, -60, 23_027, 400_000] {
+            for (preset, (lower, upper)) in PRESETS.into_iter().zip(TICK_OFFSETS) {
+                assert_eq!(
+                    preset.tick_range(sqrt_price_at(current_tick), 1).unwrap(),
+                    (current_tick + lower, current_tick + upper),
+                    "{preset:?} at tick {current_tick}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_preset_bounds_cover_the_preset_prices() {
+        let sqrt_price = sqrt_price_at(1_234) + 12_345;
+        for preset in PRESETS {
+            let half_width = preset.half_width_bps().unwrap() as f64 / 10_000.0;
+            let (lower, upper) = preset.tick_range(sqrt_price, 1).unwrap();
+            let price = |sqrt_price: u128| (sqrt_price as f64 / 2f64.powi(64)).powi(2);
+            let current = price(sqrt_price);
+
+            assert!(price(sqrt_price_at(lower)) <= current * (1.0 - half_width));
+            assert!(price(sqrt_price_at(lower + 1)) > current * (1.0 - half_width));
+            assert!(price(sqrt_price_at(upper)) >= current * (1.0 + half_width));
+            assert!(price(sqrt_price_at(upper - 1)) < current * (1.0 + half_width));
+        }
+    }
+
+    #[test]
+    fn test_preset_ranges_widen_to_tick_spacing() {
+        assert_eq!(
+            PriceRangePreset::Stable
+                .tick_range(sqrt_price_at(0), 10)
+                .unwrap(),
+            (-60, 50)
+        );
+        assert_eq!(
+            PriceRangePreset::Stable
+                .tick_range(sqrt_price_at(0), 60)
+                .unwrap(),
+            (-60, 60)
+        );
+        assert_eq!(
+            PriceRangePreset::Narrow
+                .tick_range(sqrt_price_at(0), 60)
+                .unwrap(),
+            (-240, 240)
+        );
+        assert_eq!(
+            PriceRangePreset::Wide
+                .tick_range(sqrt_price_at(0), 60)
+                .unwrap(),
+            (-2280, 1860)
+        );
+        // Off a multiple of the spacing, both bounds still move outward
+        assert_eq!(
+            PriceRangePreset::Narrow
+                .tick_range(sqrt_price_at(100), 60)
+                .unwrap(),
+            (-120, 300)
+        );
+    }
+
+    #[test]
+    fn test_preset_narrower_than_tick_spacing() {
+        assert_eq!(
+            PriceRangePreset::Stable
+                .tick_range(sqrt_price_at(0), 200)
+                .unwrap_err(),
+            ErrorCode::RangeTooNarrow.into()
+        );
+        assert_eq!(
+            PriceRangePreset::Narrow
+                .tick_range(sqrt_price_at(0), 200)
+                .unwrap(),
+            (-400, 200)
+        );
+    }
+
+    #[test]
+    fn test_preset_range_is_clamped_to_usable_ticks() {
+        let max_usable_tick = MAX_TICK / 60 * 60;
+        let (lower, upper) = PriceRangePreset::Wide
+            .tick_range(sqrt_price_at(MAX_TICK - 100), 60)
+            .unwrap();
+        assert_eq!(upper, max_usable_tick);
+        assert_eq!(lower, (MAX_TICK - 100 - 2232).div_euclid(60) * 60);
+
+        let (lower, _) = PriceRangePreset::Wide
+            .tick_range(sqrt_price_at(-MAX_TICK + 100), 60)
+            .unwrap();
+        assert_eq!(lower, -max_usable_tick);
+    }
+
+    #[test]
+    fn test_custom_preset_has_no_range() {
+        assert_eq!(PriceRangePreset::Custom.half_width_bps(), None);
+        assert_eq!(
+            PriceRangePreset::Custom
+                .tick_range(sqrt_price_at(0), 60)
+                .unwrap_err(),
+            ErrorCode::InvalidPreset.into()
+        );
+    }
+}
+
+mod owner_exposure_tests {
+    use super::*;
+
+    fn position(owner: Pubkey, pool: Pubkey, tick_lower: i32, tick_upper: i32) -> PositionData {
+        let mut position = PositionData::default();
+        position
+            .initialize(owner, pool, tick_lower, tick_upper, 1_000_000_000)
+            .unwrap();
+        position
+    }
+
+    /// Two pools at tick 0 sharing token0, with the owner's positions on both: in range,
+    /// below and above the price, one with owed tokens, plus a position of another owner.
+    fn setup() -> (Pubkey, Vec<(Pubkey, PositionData)>, BTreeMap<Pubkey, Pool>) {
+        let owner = Pubkey::new_unique();
+        let (pool_a, _) = setup_pool_with_two_positions();
+        let (mut pool_b, _) = setup_pool_with_two_positions();
+        pool_b.token0_mint = pool_a.token0_mint;
+        let (pool_a_key, pool_b_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let mut owed = position(owner, pool_b_key, -60, 60);
+        owed.tokens_owed_0 = 1_500;
+        owed.tokens_owed_1 = 2_500;
+        let positions = vec![
+            (Pubkey::new_unique(), position(owner, pool_a_key, -120, 120)),
+            (
+                Pubkey::new_unique(),
+                position(owner, pool_a_key, -240, -120),
+            ),
+            (Pubkey::new_unique(), position(owner, pool_a_key, 120, 240)),
+            (Pubkey::new_unique(), owed),
+            (
+                Pubkey::new_unique(),
+                position(Pubkey::new_unique(), pool_a_key, -60, 60),
+            ),
+        ];
+        let pools = BTreeMap::from([(pool_a_key, pool_a), (pool_b_key, pool_b)]);
+        (owner, positions, pools)
+    }
+
+    #[test]
+    fn test_totals_reconcile_with_position_valuations() {
+        let (owner, positions, pools) = setup();
+        let exposure = owner_exposure(&owner, &positions, &pools).unwrap();
+
+        // The other owner's position is left out
+        assert_eq!(exposure.positions.len(), 4);
+        let mut expected_totals: BTreeMap<Pubkey, u128> = BTreeMap::new();
+        for valued in &exposure.positions {
+            let (_, position) = positions
+                .iter()
+                .find(|(key, _)| *key == valued.position)
+                .unwrap();
+            let pool = &pools[&position.pool];
+            let (amount0, amount1) = math::get_amounts_for_liquidity(
+                pool.sqrt_price_q64,
+                math::tick_to_sqrt_price_q64(position.tick_lower_index).unwrap(),
+                math::tick_to_sqrt_price_q64(position.tick_upper_index).unwrap(),
+                position.liquidity,
+                false,
+            )
+            .unwrap();
+            assert_eq!((valued.amount0, valued.amount1), (amount0, amount1));
+            assert_eq!(valued.pool, position.pool);
+
+            *expected_totals.entry(pool.token0_mint).or_default() +=
+                amount0 + valued.tokens_owed_0 as u128;
+            *expected_totals.entry(pool.token1_mint).or_default() +=
+                amount1 + valued.tokens_owed_1 as u128;
+        }
+        assert_eq!(exposure.totals, expected_totals);
+        // The pools share token0 but not token1
+        assert_eq!(exposure.totals.len(), 3);
+    }
+
+    #[test]
+    fn test_out_of_range_positions_hold_one_token() {
+        let (owner, positions, pools) = setup();
+        let exposure = owner_exposure(&owner, &positions, &pools).unwrap();
+
+        // Below the price: token1 only. Above the price: token0 only.
+        assert_eq!(exposure.positions[1].amount0, 0);
+        assert!(exposure.positions[1].amount1 > 0);
+        assert!(exposure.positions[2].amount0 > 0);
+        assert_eq!(exposure.positions[2].amount1, 0);
+        assert_eq!(exposure.positions[3].tokens_owed_0, 1_500);
+        assert_eq!(exposure.positions[3].tokens_owed_1, 2_500);
+    }
+
+    #[test]
+    fn test_nft_positions_count_whatever_their_owner() {
+        let (owner, mut positions, pools) = setup();
+        positions[4].1.position_mint = Pubkey::new_unique();
+
+        let exposure = owner_exposure(&owner, &positions, &pools).unwrap();
+        assert_eq!(exposure.positions.len(), 5);
+        assert_eq!(exposure.positions[4].position, positions[4].0);
+    }
+
+    #[test]
+    fn test_no_positions() {
+        let (_, positions, pools) = setup();
+        let exposure = owner_exposure(&Pubkey::new_unique(), &positions, &pools).unwrap();
+        assert!(exposure.positions.is_empty());
+        assert!(exposure.totals.is_empty());
+    }
+
+    #[test]
+    fn test_missing_pool_fails() {
+        let (owner, positions, mut pools) = setup();
+        let pool_b_key = positions[3].1.pool;
+        pools.remove(&pool_b_key);
+        assert_eq!(
+            owner_exposure(&owner, &positions, &pools).unwrap_err(),
+            ErrorCode::InvalidPool.into()
+        );
+    }
+}
+
+mod sqrt_price_at_tick_tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_math_across_ticks() {
+        let ticks = [
+            MIN_TICK,
+            MIN_TICK + 1,
+            -443_000,
+            -100_000,
+            -887,
+            -60,
+            -1,
+            0,
+            1,
+            60,
+            887,
+            100_000,
+            443_000,
+            MAX_TICK - 1,
+            MAX_TICK,
+        ];
+        for tick in ticks {
+            let expected = math::tick_to_sqrt_price_q64(tick).unwrap();
+            for handling in [TickBoundsHandling::Reject, TickBoundsHandling::Clamp] {
+                assert_eq!(
+                    sqrt_price_at_tick(tick, handling).unwrap(),
+                    expected,
+                    "{tick}"
+                );
+            }
+        }
+        assert_eq!(
+            sqrt_price_at_tick(0, TickBoundsHandling::default()).unwrap(),
+            1u128 << 64
+        );
+    }
+
+    #[test]
+    fn test_bounds_give_bound_prices() {
+        assert_eq!(
+            sqrt_price_at_tick(MIN_TICK, TickBoundsHandling::Reject).unwrap(),
+            MIN_SQRT_PRICE
+        );
+        assert_eq!(
+            sqrt_price_at_tick(MAX_TICK, TickBoundsHandling::Reject).unwrap(),
+            MAX_SQRT_PRICE
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_ticks_are_rejected_or_clamped() {
+        for (tick, bound) in [
+            (MIN_TICK - 1, MIN_TICK),
+            (i32::MIN, MIN_TICK),
+            (MAX_TICK + 1, MAX_TICK),
+            (i32::MAX, MAX_TICK),
+        ] {
+            assert_eq!(
+                sqrt_price_at_tick(tick, TickBoundsHandling::Reject).unwrap_err(),
+                ErrorCode::InvalidTickRange.into()
+            );
+            assert_eq!(
+                sqrt_price_at_tick(tick, TickBoundsHandling::Clamp).unwrap(),
+                math::tick_to_sqrt_price_q64(bound).unwrap()
+            );
+        }
+    }
+}
+
+mod estimated_fee_apr_tests {
+    use super::*;
+
+    const NOON: i64 = 43_200;
+    const TVL_0: u128 = 1_000_000_000;
+
+    /// The test pool, 30 bps fee and no protocol cut, after `volume` of token0 traded
+    /// during the first half of day zero.
+    fn pool_with_volume(volume: u64) -> Pool {
+        let (mut pool, _) = setup_pool_with_two_positions();
+        pool.record_volume(volume, NOON);
+        pool
+    }
+
+    #[test]
+    fn test_higher_volume_yields_higher_apr() {
+        let quiet = pool_with_volume(10_000_000);
+        let busy = pool_with_volume(100_000_000);
+        for window in [FeeAprWindow::Elapsed, FeeAprWindow::FullDay] {
+            let quiet_apr = estimated_fee_apr_bps(&quiet, TVL_0, NOON, window).unwrap();
+            let busy_apr = estimated_fee_apr_bps(&busy, TVL_0, NOON, window).unwrap();
+            assert!(quiet_apr > 0);
+            assert!(busy_apr > quiet_apr);
+        }
+    }
+
+    #[test]
+    fn test_apr_annualizes_the_window() {
+        // 100M traded at 30 bps is 300k of fees a day on a 1B TVL: 0.03% a day, 10.95% a year
+        let pool = pool_with_volume(100_000_000);
+        assert_eq!(
+            estimated_fee_apr_bps(&pool, TVL_0, NOON, FeeAprWindow::FullDay).unwrap(),
+            1_095
+        );
+        // Half a day in, the same volume projects twice as much
+        assert_eq!(
+            estimated_fee_apr_bps(&pool, TVL_0, NOON, FeeAprWindow::Elapsed).unwrap(),
+            2_190
+        );
+    }
+
+    #[test]
+    fn test_protocol_cut_lowers_apr() {
+        let mut pool = pool_with_volume(100_000_000);
+        pool.protocol_fee = 2_500;
+        assert_eq!(
+            estimated_fee_apr_bps(&pool, TVL_0, NOON, FeeAprWindow::FullDay).unwrap(),
+            821
+        );
+    }
+
+    #[test]
+    fn test_apr_is_zero_without_volume_in_the_window() {
+        let pool = pool_with_volume(100_000_000);
+        let next_day = NOON + 86_400;
+        assert_eq!(
+            estimated_fee_apr_bps(&pool, TVL_0, next_day, FeeAprWindow::Elapsed).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_zero_tvl_is_rejected() {
+        let pool = pool_with_volume(100_000_000);
+        assert_eq!(
+            estimated_fee_apr_bps(&pool, 0, NOON, FeeAprWindow::Elapsed).unwrap_err(),
+            ErrorCode::InvalidInput.into()
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use crate::constants::*;
+use crate::math::{sqrt_price_q64_to_tick, tick_to_sqrt_price_q64};
+
+mod price_bounds_tests {
+    use super::*;
+
+    #[test]
+    fn test_price_bounds_match_constants() {
+        assert_eq!(
+            PRICE_BOUNDS,
+            PriceBounds {
+                min_tick: MIN_TICK,
+                max_tick: MAX_TICK,
+                min_sqrt_price_q64: MIN_SQRT_PRICE,
+                max_sqrt_price_q64: MAX_SQRT_PRICE,
+            }
+        );
+    }
+
+    #[test]
+    fn test_tick_bounds_are_symmetric() {
+        assert_eq!(MIN_TICK, -MAX_TICK);
+    }
+
+    #[test]
+    fn test_sqrt_price_bounds_match_tick_conversion() {
+        assert_eq!(tick_to_sqrt_price_q64(MIN_TICK).unwrap(), MIN_SQRT_PRICE);
+        assert_eq!(tick_to_sqrt_price_q64(MAX_TICK).unwrap(), MAX_SQRT_PRICE);
+    }
+
+    #[test]
+    fn test_max_sqrt_price_converts_back_to_max_tick() {
+        assert_eq!(sqrt_price_q64_to_tick(MAX_SQRT_PRICE).unwrap(), MAX_TICK);
+        assert_eq!(
+            sqrt_price_q64_to_tick(MAX_SQRT_PRICE - 1).unwrap(),
+            MAX_TICK - 1
+        );
+    }
+
+    #[test]
+    fn test_min_sqrt_price_converts_back_to_a_tick_at_that_price() {
+        // Sqrt prices of the lowest ticks all round to MIN_SQRT_PRICE in Q64.64, so the
+        // conversion returns the highest of them; it must still map back to the bound.
+        let tick = sqrt_price_q64_to_tick(MIN_SQRT_PRICE).unwrap();
+        assert!((MIN_TICK..=MAX_TICK).contains(&tick));
+        assert_eq!(tick_to_sqrt_price_q64(tick).unwrap(), MIN_SQRT_PRICE);
+    }
+
+    #[test]
+    fn test_ticks_outside_bounds_are_rejected() {
+        assert!(tick_to_sqrt_price_q64(MIN_TICK - 1).is_err());
+        assert!(tick_to_sqrt_price_q64(MAX_TICK + 1).is_err());
+    }
+}
@@ -0,0 +1,405 @@
+use crate::errors::ErrorCode;
+use crate::state::factory_config::{FactoryConfig, FeeTier, ProtocolFeeCurve, MAX_FEE_TIERS};
+use crate::state::pool::{InitializePoolParams, Pool};
+use crate::{
+    EnableFeeTier, EnableFeeTierBumps, RefreshProtocolShare, RefreshProtocolShareBumps,
+    SetProtocolFeeCurve, SetProtocolFeeCurveBumps,
+};
+
+use anchor_lang::prelude::*;
+use std::collections::BTreeSet;
+
+const BREAKPOINTS: [u128; 4] = [1_000, 10_000, 100_000, 1_000_000];
+const SHARES: [u16; 5] = [0, 500, 1_000, 1_500, 2_000];
+
+fn curve() -> ProtocolFeeCurve {
+    ProtocolFeeCurve {
+        tvl_breakpoints: BREAKPOINTS,
+        protocol_share_bps: SHARES,
+    }
+}
+
+fn factory_config_key() -> Pubkey {
+    Pubkey::find_program_address(&[b"factory_config"], &crate::ID).0
+}
+
+fn factory_config(authority: Pubkey) -> FactoryConfig {
+    let (_, bump) = Pubkey::find_program_address(&[b"factory_config"], &crate::ID);
+    let mut config = FactoryConfig::default();
+    config.initialize(bump, authority, curve()).unwrap();
+    config
+}
+
+fn pool(factory: Pubkey) -> Pool {
+    let mut pool = Pool::default();
+    pool.initialize(InitializePoolParams {
+        bump: 1,
+        factory,
+        token0_mint: Pubkey::new_unique(),
+        token1_mint: Pubkey::new_unique(),
+        token0_vault: Pubkey::new_unique(),
+        token1_vault: Pubkey::new_unique(),
+        token_pair: Pubkey::new_unique(),
+        authority: Pubkey::new_unique(),
+        initial_sqrt_price_q64: 1u128 << 64,
+        fee_rate: 30,
+        protocol_fee: 0,
+        immutable_parameters: false,
+        tick_spacing: 60,
+        timestamp: 0,
+    })
+    .unwrap();
+    pool
+}
+
+fn leak_account(
+    key: Pubkey,
+    is_signer: bool,
+    owner: &Pubkey,
+    data: Vec<u8>,
+) -> AccountInfo<'static> {
+    AccountInfo::new(
+        Box::leak(Box::new(key)),
+        is_signer,
+        true,
+        Box::leak(Box::new(1_000_000_000u64)),
+        Box::leak(data.into_boxed_slice()),
+        Box::leak(Box::new(*owner)),
+        false,
+        0,
+    )
+}
+
+fn program_account<T: AccountSerialize>(key: Pubkey, account: &T) -> AccountInfo<'static> {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    leak_account(key, false, &crate::ID, data)
+}
+
+mod protocol_fee_curve_tests {
+    use super::*;
+
+    #[test]
+    fn test_share_at_each_breakpoint_boundary() {
+        let curve = curve();
+        assert_eq!(curve.protocol_share_bps(0), SHARES[0]);
+        for (i, breakpoint) in BREAKPOINTS.into_iter().enumerate() {
+            assert_eq!(curve.protocol_share_bps(breakpoint - 1), SHARES[i]);
+            assert_eq!(curve.protocol_share_bps(breakpoint), SHARES[i + 1]);
+            assert_eq!(curve.protocol_share_bps(breakpoint + 1), SHARES[i + 1]);
+        }
+        assert_eq!(curve.protocol_share_bps(u128::MAX), SHARES[4]);
+    }
+
+    #[test]
+    fn test_validate_accepts_full_share() {
+        let curve = ProtocolFeeCurve {
+            protocol_share_bps: [10_000; 5],
+            ..curve()
+        };
+        curve.validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_curves() {
+        let invalid = [
+            // Equal breakpoints
+            ProtocolFeeCurve {
+                tvl_breakpoints: [1_000, 1_000, 100_000, 1_000_000],
+                ..curve()
+            },
+            // Decreasing breakpoints
+            ProtocolFeeCurve {
+                tvl_breakpoints: [1_000, 10_000, 1_000_000, 100_000],
+                ..curve()
+            },
+            // Share above 100% of the fee
+            ProtocolFeeCurve {
+                protocol_share_bps: [0, 500, 1_000, 1_500, 10_001],
+                ..curve()
+            },
+        ];
+        for curve in invalid {
+            assert_eq!(
+                curve.validate().unwrap_err(),
+                ErrorCode::InvalidProtocolFeeCurve.into()
+            );
+        }
+    }
+}
+
+mod factory_config_tests {
+    use super::*;
+
+    #[test]
+    fn test_initialize_records_config() {
+        let authority = Pubkey::new_unique();
+        let mut config = FactoryConfig::default();
+        config.initialize(254, authority, curve()).unwrap();
+
+        assert_eq!(config.bump, 254);
+        assert_eq!(config.authority, authority);
+        assert_eq!(config.protocol_fee_curve, curve());
+        assert!(config.fee_tiers().is_empty());
+    }
+
+    #[test]
+    fn test_initialize_rejects_invalid_curve() {
+        let mut config = FactoryConfig::default();
+        let invalid = ProtocolFeeCurve {
+            tvl_breakpoints: [0; 4],
+            ..curve()
+        };
+        assert_eq!(
+            config
+                .initialize(1, Pubkey::new_unique(), invalid)
+                .unwrap_err(),
+            ErrorCode::InvalidProtocolFeeCurve.into()
+        );
+    }
+
+    #[test]
+    fn test_set_protocol_fee_curve() {
+        let mut config = factory_config(Pubkey::new_unique());
+        let flat = ProtocolFeeCurve {
+            protocol_share_bps: [2_500; 5],
+            ..curve()
+        };
+        config.set_protocol_fee_curve(flat).unwrap();
+        assert_eq!(config.protocol_fee_curve, flat);
+
+        let invalid = ProtocolFeeCurve {
+            protocol_share_bps: [10_001; 5],
+            ..curve()
+        };
+        assert_eq!(
+            config.set_protocol_fee_curve(invalid).unwrap_err(),
+            ErrorCode::InvalidProtocolFeeCurve.into()
+        );
+        assert_eq!(config.protocol_fee_curve, flat);
+    }
+
+    #[test]
+    fn test_only_authority_can_set_curve() {
+        let authority = Pubkey::new_unique();
+        let config = program_account(factory_config_key(), &factory_config(authority));
+
+        for (signer, expected) in [
+            (authority, None),
+            (
+                Pubkey::new_unique(),
+                Some(ErrorCode::UnauthorizedAccess.into()),
+            ),
+        ] {
+            let accounts: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+                config.clone(),
+                leak_account(signer, true, &System::id(), Vec::new()),
+            ]));
+            let result = SetProtocolFeeCurve::try_accounts(
+                &crate::ID,
+                &mut &accounts[..],
+                &[],
+                &mut SetProtocolFeeCurveBumps {},
+                &mut BTreeSet::new(),
+            );
+            assert_eq!(result.err(), expected);
+        }
+    }
+}
+
+mod fee_tier_tests {
+    use super::*;
+
+    #[test]
+    fn test_enabled_tiers_map_fee_rate_to_tick_spacing() {
+        let mut config = factory_config(Pubkey::new_unique());
+        config.enable_fee_tier(5, 10).unwrap();
+        config.enable_fee_tier(30, 60).unwrap();
+
+        assert_eq!(
+            config.fee_tiers(),
+            &[
+                FeeTier {
+                    fee_rate: 5,
+                    tick_spacing: 10
+                },
+                FeeTier {
+                    fee_rate: 30,
+                    tick_spacing: 60
+                },
+            ]
+        );
+        assert_eq!(config.tick_spacing_for(30), Some(60));
+        assert_eq!(config.tick_spacing_for(100), None);
+    }
+
+    #[test]
+    fn test_pool_needs_an_enabled_tier_at_its_tick_spacing() {
+        let mut config = factory_config(Pubkey::new_unique());
+        assert_eq!(
+            config.ensure_fee_tier_enabled(30, 60).unwrap_err(),
+            ErrorCode::InvalidFeeTier.into()
+        );
+
+        config.enable_fee_tier(30, 60).unwrap();
+        config.ensure_fee_tier_enabled(30, 60).unwrap();
+        assert_eq!(
+            config.ensure_fee_tier_enabled(30, 10).unwrap_err(),
+            ErrorCode::InvalidFeeTier.into()
+        );
+    }
+
+    #[test]
+    fn test_fee_rate_keeps_its_first_tick_spacing() {
+        let mut config = factory_config(Pubkey::new_unique());
+        config.enable_fee_tier(30, 60).unwrap();
+
+        for tick_spacing in [60, 10] {
+            assert_eq!(
+                config.enable_fee_tier(30, tick_spacing).unwrap_err(),
+                ErrorCode::FeeTierAlreadyEnabled.into()
+            );
+        }
+        assert_eq!(config.fee_tiers().len(), 1);
+    }
+
+    #[test]
+    fn test_out_of_bounds_tiers_are_rejected() {
+        let mut config = factory_config(Pubkey::new_unique());
+        assert_eq!(
+            config.enable_fee_tier(0, 60).unwrap_err(),
+            ErrorCode::InvalidFeeRate.into()
+        );
+        assert_eq!(
+            config.enable_fee_tier(30, 7).unwrap_err(),
+            ErrorCode::InvalidTickSpacing.into()
+        );
+        assert!(config.fee_tiers().is_empty());
+    }
+
+    #[test]
+    fn test_tiers_are_limited() {
+        let mut config = factory_config(Pubkey::new_unique());
+        for fee_rate in 1..=MAX_FEE_TIERS as u16 {
+            config.enable_fee_tier(fee_rate, 1).unwrap();
+        }
+        assert_eq!(
+            config.enable_fee_tier(100, 1).unwrap_err(),
+            ErrorCode::TooManyFeeTiers.into()
+        );
+    }
+
+    #[test]
+    fn test_only_authority_can_enable_tiers() {
+        let authority = Pubkey::new_unique();
+        let config = program_account(factory_config_key(), &factory_config(authority));
+
+        for (signer, expected) in [
+            (authority, None),
+            (
+                Pubkey::new_unique(),
+                Some(ErrorCode::UnauthorizedAccess.into()),
+            ),
+        ] {
+            let accounts: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+                config.clone(),
+                leak_account(signer, true, &System::id(), Vec::new()),
+            ]));
+            let result = EnableFeeTier::try_accounts(
+                &crate::ID,
+                &mut &accounts[..],
+                &[],
+                &mut EnableFeeTierBumps {},
+                &mut BTreeSet::new(),
+            );
+            assert_eq!(result.err(), expected);
+        }
+    }
+}
+
+mod refresh_protocol_share_tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_follows_curve_at_each_breakpoint() {
+        let curve = curve();
+        let mut pool = pool(factory_config_key());
+        for (i, breakpoint) in BREAKPOINTS.into_iter().enumerate() {
+            pool.liquidity = breakpoint - 1;
+            assert_eq!(pool.refresh_protocol_share(&curve).unwrap(), SHARES[i]);
+            assert_eq!(pool.protocol_fee, SHARES[i]);
+
+            pool.liquidity = breakpoint;
+            assert_eq!(pool.refresh_protocol_share(&curve).unwrap(), SHARES[i + 1]);
+            assert_eq!(pool.protocol_fee, SHARES[i + 1]);
+        }
+    }
+
+    #[test]
+    fn test_new_pool_keeps_all_fees_for_lps() {
+        let mut pool = pool(factory_config_key());
+        assert_eq!(pool.liquidity, 0);
+        assert_eq!(pool.refresh_protocol_share(&curve()).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_refresh_leaves_manual_share_alone() {
+        let curve = curve();
+        let mut pool = pool(factory_config_key());
+        pool.liquidity = BREAKPOINTS[1];
+        pool.set_manual_protocol_fee(2_500).unwrap();
+        assert!(pool.protocol_fee_manual);
+
+        assert_eq!(
+            pool.refresh_protocol_share(&curve).unwrap_err(),
+            ErrorCode::ProtocolFeeManual.into()
+        );
+        assert_eq!(pool.protocol_fee, 2_500);
+
+        // Back on the curve, the share follows it at once and on later refreshes
+        assert_eq!(pool.follow_protocol_fee_curve(&curve).unwrap(), SHARES[2]);
+        assert!(!pool.protocol_fee_manual);
+        pool.liquidity = BREAKPOINTS[3];
+        assert_eq!(pool.refresh_protocol_share(&curve).unwrap(), SHARES[4]);
+    }
+
+    #[test]
+    fn test_immutable_pool_cannot_leave_or_rejoin_curve() {
+        let mut pool = pool(factory_config_key());
+        pool.immutable_parameters = true;
+        assert_eq!(
+            pool.set_manual_protocol_fee(2_500).unwrap_err(),
+            ErrorCode::PoolImmutable.into()
+        );
+        assert!(!pool.protocol_fee_manual);
+        assert_eq!(
+            pool.follow_protocol_fee_curve(&curve()).unwrap_err(),
+            ErrorCode::PoolImmutable.into()
+        );
+    }
+
+    /// Resolves the `RefreshProtocolShare` accounts for a pool created by `factory`.
+    fn resolve_refresh(factory: Pubkey) -> Result<()> {
+        let accounts: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+            program_account(Pubkey::new_unique(), &pool(factory)),
+            program_account(factory_config_key(), &factory_config(Pubkey::new_unique())),
+        ]));
+        RefreshProtocolShare::try_accounts(
+            &crate::ID,
+            &mut &accounts[..],
+            &[],
+            &mut RefreshProtocolShareBumps {},
+            &mut BTreeSet::new(),
+        )
+        .map(|_| ())
+    }
+
+    #[test]
+    fn test_refresh_requires_pool_factory() {
+        resolve_refresh(factory_config_key()).unwrap();
+        assert_eq!(
+            resolve_refresh(Pubkey::new_unique()).unwrap_err(),
+            ErrorCode::InvalidFactoryConfig.into()
+        );
+    }
+}
@@ -25,13 +25,18 @@ mod pool_initialize_tests {
         InitializePoolParams {
             bump: 255,
             factory: new_pubkey(1),
+            authority: new_pubkey(6),
             token0_mint: new_pubkey(2), // Typically mint_a (smaller key)
             token1_mint: new_pubkey(3), // Typically mint_b (larger key)
             token0_vault: new_pubkey(4),
             token1_vault: new_pubkey(5),
+            token_pair: new_pubkey(7),
             initial_sqrt_price_q64: Q64_ONE, // Corresponds to price 1.0
             fee_rate: 30,                    // e.g., 0.3%
+            protocol_fee: 0,
+            immutable_parameters: false,
             tick_spacing: 60,
+            timestamp: 0,
         }
     }
 
@@ -47,10 +52,13 @@ mod pool_initialize_tests {
 
         assert_eq!(pool.bump, 255);
         assert_eq!(pool.factory, new_pubkey(1));
+        assert_eq!(pool.authority, new_pubkey(6));
+        assert_eq!(pool.protocol_fee, 0);
         assert_eq!(pool.token0_mint, new_pubkey(2));
         assert_eq!(pool.token1_mint, new_pubkey(3));
         assert_eq!(pool.token0_vault, new_pubkey(4));
         assert_eq!(pool.token1_vault, new_pubkey(5));
+        assert_eq!(pool.token_pair, new_pubkey(7));
         assert_eq!(pool.fee_rate, 30);
         assert_eq!(pool.tick_spacing, 60);
         assert_eq!(pool.sqrt_price_q64, Q64_ONE);
@@ -135,6 +143,28 @@ mod pool_initialize_tests {
         assert_eq!(result.err().unwrap(), ErrorCode::InvalidTickSpacing.into());
     }
 
+    #[test]
+    fn test_pool_initialize_sets_protocol_fee() {
+        let mut pool = Pool::default();
+        let mut params = get_default_params();
+        params.protocol_fee = 2_500;
+
+        pool.initialize(params).unwrap();
+        assert_eq!(pool.protocol_fee, 2_500);
+        assert_eq!(pool.protocol_fees_owed_a, 0);
+        assert_eq!(pool.protocol_fees_owed_b, 0);
+    }
+
+    #[test]
+    fn test_pool_initialize_error_invalid_protocol_fee() {
+        let mut pool = Pool::default();
+        let mut params = get_default_params();
+        params.protocol_fee = 10_001;
+
+        let result = pool.initialize(params);
+        assert_eq!(result.err().unwrap(), ErrorCode::InvalidProtocolFee.into());
+    }
+
     #[test]
     fn test_pool_initialize_current_tick_calculation() {
         let mut pool = Pool::default();
@@ -154,3 +184,83 @@ mod pool_initialize_tests {
         assert_eq!(pool2.current_tick, expected_tick_price_0_25);
     }
 }
+
+mod validate_pool_params_tests {
+    use super::*;
+    use crate::constants::{ALLOWED_TICK_SPACINGS, MAX_FEE_RATE, MIN_SQRT_PRICE};
+    use crate::instructions::initialize_pool::validate_pool_params;
+
+    fn validate(
+        mint_a: u8,
+        mint_b: u8,
+        sqrt_price_q64: u128,
+        fee_rate: u16,
+        tick_spacing: u16,
+    ) -> Result<()> {
+        validate_pool_params(
+            &new_pubkey(mint_a),
+            &new_pubkey(mint_b),
+            sqrt_price_q64,
+            fee_rate,
+            tick_spacing,
+        )
+    }
+
+    #[test]
+    fn test_canonical_order_is_accepted() {
+        validate(2, 3, Q64_ONE, 30, 60).unwrap();
+    }
+
+    #[test]
+    fn test_reversed_mints_are_rejected() {
+        // The same pair in the other order would derive another pool PDA
+        assert_eq!(
+            validate(3, 2, Q64_ONE, 30, 60).unwrap_err(),
+            ErrorCode::MintsNotInCanonicalOrder.into()
+        );
+    }
+
+    #[test]
+    fn test_identical_mints_are_rejected() {
+        assert_eq!(
+            validate(2, 2, Q64_ONE, 30, 60).unwrap_err(),
+            ErrorCode::MintsMustDiffer.into()
+        );
+    }
+
+    #[test]
+    fn test_price_must_be_within_bounds() {
+        validate(2, 3, MIN_SQRT_PRICE, 30, 60).unwrap();
+        validate(2, 3, MAX_SQRT_PRICE, 30, 60).unwrap();
+        for sqrt_price_q64 in [0, MIN_SQRT_PRICE - 1, MAX_SQRT_PRICE + 1] {
+            assert_eq!(
+                validate(2, 3, sqrt_price_q64, 30, 60).unwrap_err(),
+                ErrorCode::InvalidInitialPrice.into()
+            );
+        }
+    }
+
+    #[test]
+    fn test_fee_rate_must_be_within_bounds() {
+        validate(2, 3, Q64_ONE, MAX_FEE_RATE, 60).unwrap();
+        for fee_rate in [0, MAX_FEE_RATE + 1] {
+            assert_eq!(
+                validate(2, 3, Q64_ONE, fee_rate, 60).unwrap_err(),
+                ErrorCode::InvalidFeeRate.into()
+            );
+        }
+    }
+
+    #[test]
+    fn test_tick_spacing_must_be_allowed() {
+        for tick_spacing in ALLOWED_TICK_SPACINGS {
+            validate(2, 3, Q64_ONE, 30, tick_spacing).unwrap();
+        }
+        for tick_spacing in [0, 30, 120] {
+            assert_eq!(
+                validate(2, 3, Q64_ONE, 30, tick_spacing).unwrap_err(),
+                ErrorCode::InvalidTickSpacing.into()
+            );
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use super::tick_array_test::{tick_array_loader, tick_bitmap_loader, tick_loader};
+use crate::errors::ErrorCode;
+use crate::instructions::refresh_liquidity_histogram::histogram_ticks;
+use crate::liquidity_histogram::*;
+use crate::math;
+use crate::state::pool::{InitializePoolParams, Pool};
+use crate::tick::TickData;
+use crate::tick_array::TickArray;
+use crate::tick_bitmap;
+
+use anchor_lang::prelude::*;
+use std::collections::BTreeMap;
+
+const TICK_SPACING: u16 = 60;
+const BUCKET_TICKS: u32 = 120;
+const NOW: i64 = 1_000_000;
+
+/// Position ranges and liquidity. Some bounds fall inside a bucket, and the last position
+/// has both ticks outside the histogram around tick 0.
+const POSITIONS: [(i32, i32, u128); 5] = [
+    (-600, 600, 1 << 60),
+    (-180, 60, 3 << 58),
+    (240, 1_200, 1 << 59),
+    (-3_000, -2_400, 1 << 58),
+    (-6_000, 6_000, 1 << 57),
+];
+
+/// A pool at tick 0 holding `POSITIONS`, with the `TickData` account of each of their ticks.
+struct PoolWithTicks {
+    pool: Pool,
+    pool_key: Pubkey,
+    ticks: BTreeMap<i32, AccountLoader<'static, TickData>>,
+}
+
+impl PoolWithTicks {
+    fn new() -> Self {
+        let mut pool = Pool::default();
+        pool.initialize(InitializePoolParams {
+            bump: 1,
+            factory: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            token0_mint: Pubkey::new_unique(),
+            token1_mint: Pubkey::new_unique(),
+            token0_vault: Pubkey::new_unique(),
+            token1_vault: Pubkey::new_unique(),
+            token_pair: Pubkey::new_unique(),
+            initial_sqrt_price_q64: 1u128 << 64,
+            fee_rate: 30,
+            protocol_fee: 0,
+            immutable_parameters: false,
+            tick_spacing: TICK_SPACING,
+            timestamp: 0,
+        })
+        .unwrap();
+        let pool_key = Pubkey::new_unique();
+        let mut ticks = BTreeMap::new();
+        for (tick_lower, tick_upper, liquidity) in POSITIONS {
+            for tick in [tick_lower, tick_upper] {
+                ticks
+                    .entry(tick)
+                    .or_insert_with(|| tick_loader(&pool_key, tick));
+            }
+            pool.modify_liquidity(
+                tick_lower,
+                tick_upper,
+                liquidity as i128,
+                &ticks[&tick_lower],
+                &ticks[&tick_upper],
+            )
+            .unwrap();
+        }
+        Self {
+            pool,
+            pool_key,
+            ticks,
+        }
+    }
+
+    fn tick_loaders(&self) -> Vec<&AccountLoader<'static, TickData>> {
+        self.ticks.values().collect()
+    }
+
+    /// Refreshes `histogram` from the pool, passing every tick account.
+    fn refresh(&self, histogram: &mut LiquidityHistogram) -> Result<()> {
+        let lower_tick = histogram.lower_tick_around(self.pool.current_tick);
+        let ticks = histogram_ticks(
+            &self.pool,
+            &self.pool_key,
+            lower_tick,
+            lower_tick + histogram.span_ticks(),
+            &self.tick_loaders(),
+            &[],
+        )?;
+        histogram.refresh(self.pool.current_tick, self.pool.liquidity, &ticks, NOW)
+    }
+}
+
+fn new_histogram(bucket_ticks: u32) -> LiquidityHistogram {
+    let mut histogram: LiquidityHistogram = bytemuck::Zeroable::zeroed();
+    histogram
+        .initialize(Pubkey::new_unique(), bucket_ticks, TICK_SPACING)
+        .unwrap();
+    histogram
+}
+
+/// Liquidity of the positions active at each tick of every bucket, averaged per bucket.
+fn expected_buckets(
+    positions: &[(i32, i32, u128)],
+    histogram: &LiquidityHistogram,
+) -> [u128; HISTOGRAM_BUCKETS] {
+    let mut buckets = [0u128; HISTOGRAM_BUCKETS];
+    for (bucket, expected) in buckets.iter_mut().enumerate() {
+        let first = histogram.lower_tick + bucket as i32 * histogram.bucket_ticks as i32;
+        let total: u128 = (first..first + histogram.bucket_ticks as i32)
+            .map(|tick| {
+                positions
+                    .iter()
+                    .filter(|(lower, upper, _)| *lower <= tick && tick < *upper)
+                    .map(|(_, _, liquidity)| liquidity)
+                    .sum::<u128>()
+            })
+            .sum();
+        *expected = total / histogram.bucket_ticks as u128;
+    }
+    buckets
+}
+
+mod initialize_tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_width_must_be_a_multiple_of_tick_spacing() {
+        let mut histogram: LiquidityHistogram = bytemuck::Zeroable::zeroed();
+        for bucket_ticks in [0, 90] {
+            assert_eq!(
+                histogram
+                    .initialize(Pubkey::new_unique(), bucket_ticks, TICK_SPACING)
+                    .unwrap_err(),
+                ErrorCode::InvalidHistogramBucketWidth.into()
+            );
+        }
+        histogram
+            .initialize(Pubkey::new_unique(), 180, TICK_SPACING)
+            .unwrap();
+        assert_eq!(histogram.bucket_ticks, 180);
+    }
+
+    #[test]
+    fn test_buckets_cannot_span_more_than_the_tick_range() {
+        let mut histogram: LiquidityHistogram = bytemuck::Zeroable::zeroed();
+        let widest = 27_720; // 64 buckets span 1_774_080 ticks, within MIN_TICK..MAX_TICK
+        histogram
+            .initialize(Pubkey::new_unique(), widest, TICK_SPACING)
+            .unwrap();
+        assert_eq!(
+            histogram
+                .initialize(Pubkey::new_unique(), widest + 60, TICK_SPACING)
+                .unwrap_err(),
+            ErrorCode::InvalidHistogramBucketWidth.into()
+        );
+    }
+
+    #[test]
+    fn test_new_histogram_is_stale() {
+        let histogram = new_histogram(BUCKET_TICKS);
+        assert_eq!(
+            histogram.ensure_fresh(NOW, i64::MAX).unwrap_err(),
+            ErrorCode::LiquidityHistogramStale.into()
+        );
+    }
+}
+
+mod refresh_tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets_match_position_liquidity() {
+        let pool = PoolWithTicks::new();
+        let mut histogram = new_histogram(BUCKET_TICKS);
+        pool.refresh(&mut histogram).unwrap();
+
+        assert_eq!(histogram.lower_tick, -3_840);
+        assert_eq!(histogram.upper_tick(), 3_840);
+        assert_eq!(histogram.updated_at, NOW);
+        assert_eq!(
+            histogram.liquidity,
+            expected_buckets(&POSITIONS, &histogram)
+        );
+        // Bucket [0, 120) is half covered by the position ending at tick 60
+        assert_eq!(
+            histogram.liquidity[HISTOGRAM_CURRENT_BUCKET],
+            (1 << 60) + (1 << 57) + (3 << 58) / 2
+        );
+    }
+
+    #[test]
+    fn test_current_bucket_follows_swaps() {
+        let mut pool = PoolWithTicks::new();
+        let mut histogram = new_histogram(TICK_SPACING as u32);
+
+        // Swap down across ticks -180 and -600
+        pool.pool
+            .swap(
+                true,
+                i64::MAX as i128,
+                math::tick_to_sqrt_price_q64(-1_000).unwrap(),
+                &pool.pool_key,
+                &[&pool.ticks[&-180], &pool.ticks[&-600]],
+                0,
+            )
+            .unwrap();
+        assert_eq!(pool.pool.current_tick, -1_000);
+        pool.refresh(&mut histogram).unwrap();
+
+        assert_eq!(histogram.bucket_of(-1_000), Some(HISTOGRAM_CURRENT_BUCKET));
+        // One tick spacing per bucket: the current bucket holds exactly the pool's liquidity
+        assert_eq!(
+            histogram.liquidity[HISTOGRAM_CURRENT_BUCKET],
+            pool.pool.liquidity
+        );
+        assert_eq!(
+            histogram.liquidity,
+            expected_buckets(&POSITIONS, &histogram)
+        );
+    }
+
+    #[test]
+    fn test_ticks_are_read_from_tick_arrays() {
+        let mut pool = PoolWithTicks::new();
+        let array_start = TickArray::start_tick_index(300, TICK_SPACING);
+        let tick_array = tick_array_loader(&pool.pool_key, array_start);
+        pool.pool
+            .modify_liquidity_in_tick_arrays(300, 480, 1 << 58, &tick_array, &tick_array)
+            .unwrap();
+
+        let mut histogram = new_histogram(BUCKET_TICKS);
+        let lower_tick = histogram.lower_tick_around(pool.pool.current_tick);
+        let ticks = histogram_ticks(
+            &pool.pool,
+            &pool.pool_key,
+            lower_tick,
+            lower_tick + histogram.span_ticks(),
+            &pool.tick_loaders(),
+            &[&tick_array],
+        )
+        .unwrap();
+        histogram
+            .refresh(pool.pool.current_tick, pool.pool.liquidity, &ticks, NOW)
+            .unwrap();
+
+        let mut positions = POSITIONS.to_vec();
+        positions.push((300, 480, 1 << 58));
+        assert_eq!(
+            histogram.liquidity,
+            expected_buckets(&positions, &histogram)
+        );
+    }
+
+    #[test]
+    fn test_missing_tick_is_rejected() {
+        let pool = PoolWithTicks::new();
+        let histogram = new_histogram(BUCKET_TICKS);
+        let lower_tick = histogram.lower_tick_around(pool.pool.current_tick);
+        let tick_loaders: Vec<_> = pool
+            .ticks
+            .iter()
+            .filter(|(&tick, _)| tick != 240)
+            .map(|(_, tick_loader)| tick_loader)
+            .collect();
+        assert_eq!(
+            histogram_ticks(
+                &pool.pool,
+                &pool.pool_key,
+                lower_tick,
+                lower_tick + histogram.span_ticks(),
+                &tick_loaders,
+                &[],
+            )
+            .unwrap_err(),
+            ErrorCode::MissingTickAccount.into()
+        );
+    }
+
+    #[test]
+    fn test_ticks_outside_the_histogram_are_not_needed() {
+        let pool = PoolWithTicks::new();
+        let histogram = new_histogram(BUCKET_TICKS);
+        let lower_tick = histogram.lower_tick_around(pool.pool.current_tick);
+        let tick_loaders: Vec<_> = pool
+            .ticks
+            .iter()
+            .filter(|(&tick, _)| tick.abs() < 6_000)
+            .map(|(_, tick_loader)| tick_loader)
+            .collect();
+        let ticks = histogram_ticks(
+            &pool.pool,
+            &pool.pool_key,
+            lower_tick,
+            lower_tick + histogram.span_ticks(),
+            &tick_loaders,
+            &[],
+        )
+        .unwrap();
+        assert!(ticks.iter().all(|&(tick, _)| tick.abs() < 3_840));
+    }
+
+    #[test]
+    fn test_external_bitmap_word_must_be_supplied() {
+        let mut pool = PoolWithTicks::new();
+        let word_index = tick_bitmap::word_index_of_tick(-3_000, TICK_SPACING);
+        let word = pool.pool.externalize_bitmap_word(word_index).unwrap();
+        let histogram = new_histogram(BUCKET_TICKS);
+        let lower_tick = histogram.lower_tick_around(pool.pool.current_tick);
+        assert_eq!(
+            histogram_ticks(
+                &pool.pool,
+                &pool.pool_key,
+                lower_tick,
+                lower_tick + histogram.span_ticks(),
+                &pool.tick_loaders(),
+                &[],
+            )
+            .unwrap_err(),
+            ErrorCode::MissingTickBitmapWord.into()
+        );
+
+        let tick_bitmap = tick_bitmap_loader(&pool.pool_key, word_index, word);
+        let pool_key = pool.pool_key;
+        pool.pool
+            .load_bitmap_words(&pool_key, &[&tick_bitmap])
+            .unwrap();
+        pool.refresh(&mut new_histogram(BUCKET_TICKS)).unwrap();
+    }
+
+    #[test]
+    fn test_unordered_ticks_are_rejected() {
+        let mut histogram = new_histogram(BUCKET_TICKS);
+        assert_eq!(
+            histogram
+                .refresh(0, 1 << 60, &[(120, 1 << 60), (-120, 1 << 60)], NOW)
+                .unwrap_err(),
+            ErrorCode::InvalidTickAccountOrder.into()
+        );
+        // The lowest bucket's first tick is not strictly inside the range
+        assert_eq!(
+            histogram
+                .refresh(0, 0, &[(-3_840, 1 << 60)], NOW)
+                .unwrap_err(),
+            ErrorCode::InvalidTickAccountOrder.into()
+        );
+    }
+
+    #[test]
+    fn test_inconsistent_ticks_are_rejected() {
+        let mut histogram = new_histogram(BUCKET_TICKS);
+        // A tick adding liquidity below the price with none active at the price
+        assert_eq!(
+            histogram
+                .refresh(0, 0, &[(-120, 1 << 60)], NOW)
+                .unwrap_err(),
+            ErrorCode::LiquidityUnderflow.into()
+        );
+    }
+}
+
+mod staleness_tests {
+    use super::*;
+
+    fn refreshed_histogram() -> LiquidityHistogram {
+        let mut histogram = new_histogram(BUCKET_TICKS);
+        PoolWithTicks::new().refresh(&mut histogram).unwrap();
+        histogram
+    }
+
+    #[test]
+    fn test_histogram_is_fresh_up_to_max_age() {
+        let histogram = refreshed_histogram();
+        histogram.ensure_fresh(NOW, 0).unwrap();
+        histogram.ensure_fresh(NOW + 60, 60).unwrap();
+        assert_eq!(
+            histogram.ensure_fresh(NOW + 61, 60).unwrap_err(),
+            ErrorCode::LiquidityHistogramStale.into()
+        );
+    }
+
+    #[test]
+    fn test_depth_estimate_is_the_lowest_bucket_between_ticks() {
+        let histogram = refreshed_histogram();
+        // Buckets from [-720, -600) to [0, 120): the lowest is outside the position at -600
+        let depth = histogram.min_liquidity_between(0, -700, NOW, 60).unwrap();
+        assert_eq!(depth, (1 << 57));
+        assert_eq!(
+            histogram.min_liquidity_between(0, 59, NOW, 60).unwrap(),
+            histogram.liquidity[HISTOGRAM_CURRENT_BUCKET]
+        );
+    }
+
+    #[test]
+    fn test_depth_estimate_is_gated_on_staleness_and_range() {
+        let histogram = refreshed_histogram();
+        assert_eq!(
+            histogram
+                .min_liquidity_between(0, 600, NOW + 61, 60)
+                .unwrap_err(),
+            ErrorCode::LiquidityHistogramStale.into()
+        );
+        assert_eq!(
+            histogram
+                .min_liquidity_between(0, 3_840, NOW, 60)
+                .unwrap_err(),
+            ErrorCode::TickOutsideHistogram.into()
+        );
+    }
+}
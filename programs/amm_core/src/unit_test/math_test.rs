@@ -1,4 +1,5 @@
 use crate::constants::*;
+use crate::errors::ErrorCode;
 use crate::math::*;
 use proptest::prelude::*;
 
@@ -11,6 +12,11 @@ const Q64_QUARTER: u128 = 0x0000000000000000_4000000000000000; // 0.25 in Q64.64
 const Q64_FOUR: u128 = 0x0000000000000004_0000000000000000; // 4.0 in Q64.64
 const Q64_MAX: u128 = 0xFFFFFFFFFFFFFFFF_0000000000000000; // Max representable value in Q64.64 (just under 2^64)
 
+/// Lowest tick from which a Q64.64 sqrt price pins down the tick to within one. Below it
+/// one unit of sqrt price spans several ticks, so a sqrt price only identifies the highest
+/// of them.
+const MIN_RESOLVED_TICK: i32 = -703158;
+
 /// Helper function to convert f64 to Q64.64 fixed-point for testing
 fn float_to_q64(val: f64) -> u128 {
     let integer_part = val.trunc() as u128;
@@ -39,6 +45,7 @@ fn assert_q64_approx_eq(a: u128, b: u128, epsilon_bits: u8) {
 /// Comprehensive tests for mul_fixed function
 mod mul_fixed_tests {
     use super::*;
+    use primitive_types::U256;
 
     #[test]
     fn test_mul_fixed_basic() {
@@ -146,12 +153,42 @@ mod mul_fixed_tests {
             assert!((result_float - expected_float).abs() < 0.000001,
                     "Float comparison failed: {result_float} vs {expected_float}");
         }
+
+        #[test]
+        fn test_mul_fixed_checked_none_exactly_on_overflow(a in any::<u128>(), b in any::<u128>()) {
+            // None exactly when the high 128 bits of the shifted 256-bit product are set
+            let shifted = (U256::from(a) * U256::from(b)) >> 64;
+            let overflows = !(shifted >> 128).is_zero();
+
+            match mul_fixed_checked(a, b) {
+                None => assert!(overflows, "Unexpected overflow for {a:x} * {b:x}"),
+                Some(result) => {
+                    assert!(!overflows, "Missed overflow for {a:x} * {b:x}");
+                    assert_eq!(U256::from(result), shifted);
+                }
+            }
+        }
+
+        #[test]
+        fn test_mul_fixed_checked_matches_mul_fixed(a in 0..(1u128 << 96), b in 0..(1u128 << 96)) {
+            // Operands below 2^96 never overflow and agree with mul_fixed
+            assert_eq!(mul_fixed_checked(a, b), Some(mul_fixed(a, b)));
+        }
+    }
+
+    #[test]
+    fn test_mul_fixed_checked_overflow() {
+        assert_eq!(mul_fixed_checked(Q64_MAX, Q64_MAX), None);
+        assert_eq!(mul_fixed_checked(u128::MAX, Q64_TWO), None);
+        assert_eq!(mul_fixed_checked(u128::MAX, Q64_ONE), Some(u128::MAX));
+        assert_eq!(mul_fixed_checked(Q64_MAX / 2, Q64_TWO), Some(Q64_MAX));
     }
 }
 
 /// Comprehensive tests for div_fixed function
 mod div_fixed_tests {
     use super::*;
+    use primitive_types::U256;
 
     #[test]
     fn test_div_fixed_basic() {
@@ -247,6 +284,54 @@ mod div_fixed_tests {
             assert!(relative_error < 0.00001,
                     "Float division inconsistency: {result_float:?} vs {expected_float:?}, rel error: {relative_error:?}"); // Changed {} to {:?}
         }
+
+        #[test]
+        fn test_div_fixed_checked_matches_div_fixed(a in any::<u128>(), b in 1..u128::MAX) {
+            // Whenever the quotient fits, the checked division agrees with div_fixed
+            let quotient = (U256::from(a) << 64) / U256::from(b);
+            match div_fixed_checked(a, b) {
+                None => assert!(quotient > U256::from(u128::MAX)),
+                Some(result) => assert_eq!(result, div_fixed(a, b)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_div_fixed_checked_overflow() {
+        assert_eq!(div_fixed_checked(Q64_ONE, 0), None);
+        assert_eq!(div_fixed_checked(u128::MAX, Q64_HALF), None);
+        assert_eq!(div_fixed_checked(Q64_ONE, 1), None);
+        assert_eq!(div_fixed_checked(Q64_ONE, 2), Some(1u128 << 127));
+        assert_eq!(div_fixed_checked(Q64_TWO, Q64_HALF), Some(Q64_FOUR));
+    }
+
+    #[test]
+    fn test_try_div_fixed() {
+        assert_eq!(try_div_fixed(Q64_TWO, Q64_HALF).unwrap(), Q64_FOUR);
+        assert_eq!(try_div_fixed(Q64_ZERO, Q64_ONE).unwrap(), Q64_ZERO);
+        assert!(matches!(
+            try_div_fixed(Q64_ONE, 0),
+            Err(ErrorCode::DivisionByZero)
+        ));
+        assert!(matches!(
+            try_div_fixed(0, 0),
+            Err(ErrorCode::DivisionByZero)
+        ));
+    }
+
+    #[test]
+    fn test_try_div_fixed_by_minimal_q64_representation_overflows() {
+        // (Q64_ONE << 64) / 1 is 2^128, which div_fixed panics casting to u128
+        assert!(matches!(
+            try_div_fixed(Q64_ONE, 1),
+            Err(ErrorCode::MathOverflow)
+        ));
+        assert!(matches!(
+            try_div_fixed(u128::MAX, Q64_HALF),
+            Err(ErrorCode::MathOverflow)
+        ));
+        // The largest quotient that still fits
+        assert_eq!(try_div_fixed(Q64_ONE, 2).unwrap(), 1u128 << 127);
     }
 }
 
@@ -270,6 +355,19 @@ mod invert_fixed_tests {
         invert_fixed(0);
     }
 
+    #[test]
+    fn test_try_invert_fixed() {
+        assert_eq!(try_invert_fixed(Q64_ONE).unwrap(), Q64_ONE);
+        assert_eq!(try_invert_fixed(Q64_HALF).unwrap(), invert_fixed(Q64_HALF));
+        assert!(matches!(
+            try_invert_fixed(0),
+            Err(ErrorCode::DivisionByZero)
+        ));
+        // 1 / 2^-64 is 2^64, just past the largest Q64.64 value
+        assert!(matches!(try_invert_fixed(1), Err(ErrorCode::MathOverflow)));
+        assert_eq!(try_invert_fixed(2).unwrap(), 1u128 << 127);
+    }
+
     #[test]
     fn test_invert_fixed_precision() {
         // Test precision for various values
@@ -379,11 +477,11 @@ mod binary_pow_tests {
         let power_table = create_test_power_table(2.0, 10);
 
         // Test various exponents
-        assert_eq!(binary_pow(&power_table, 0), Q64_ONE); // 2^0 = 1
-        assert_eq!(binary_pow(&power_table, 1), float_to_q64(2.0)); // 2^1 = 2
-        assert_eq!(binary_pow(&power_table, 2), float_to_q64(4.0)); // 2^2 = 4
-        assert_eq!(binary_pow(&power_table, 3), float_to_q64(8.0)); // 2^3 = 8
-        assert_eq!(binary_pow(&power_table, 4), float_to_q64(16.0)); // 2^4 = 16
+        assert_eq!(binary_pow(&power_table, 0).unwrap(), Q64_ONE); // 2^0 = 1
+        assert_eq!(binary_pow(&power_table, 1).unwrap(), float_to_q64(2.0)); // 2^1 = 2
+        assert_eq!(binary_pow(&power_table, 2).unwrap(), float_to_q64(4.0)); // 2^2 = 4
+        assert_eq!(binary_pow(&power_table, 3).unwrap(), float_to_q64(8.0)); // 2^3 = 8
+        assert_eq!(binary_pow(&power_table, 4).unwrap(), float_to_q64(16.0)); // 2^4 = 16
     }
 
     #[test]
@@ -403,7 +501,7 @@ mod binary_pow_tests {
             let power_table = create_test_power_table(*base, 10);
 
             for exp in 0..8 {
-                let result = binary_pow(&power_table, exp);
+                let result = binary_pow(&power_table, exp).unwrap();
                 let expected = float_to_q64(base.powi(exp as i32));
 
                 // Allow for small differences due to floating-point precision
@@ -425,16 +523,22 @@ mod binary_pow_tests {
         let power_table = create_test_power_table(2.0, 8);
 
         // 5 = 4 + 1 = 2^2 + 2^0, so 2^5 = 2^4 * 2^1
-        let pow_5 = binary_pow(&power_table, 5);
-        let pow_4_times_1 = mul_fixed(binary_pow(&power_table, 4), binary_pow(&power_table, 1));
+        let pow_5 = binary_pow(&power_table, 5).unwrap();
+        let pow_4_times_1 = mul_fixed(
+            binary_pow(&power_table, 4).unwrap(),
+            binary_pow(&power_table, 1).unwrap(),
+        );
 
         assert_eq!(pow_5, pow_4_times_1);
 
         // 7 = 4 + 2 + 1 = 2^2 + 2^1 + 2^0
-        let pow_7 = binary_pow(&power_table, 7);
+        let pow_7 = binary_pow(&power_table, 7).unwrap();
         let pow_components = mul_fixed(
-            mul_fixed(binary_pow(&power_table, 4), binary_pow(&power_table, 2)),
-            binary_pow(&power_table, 1),
+            mul_fixed(
+                binary_pow(&power_table, 4).unwrap(),
+                binary_pow(&power_table, 2).unwrap(),
+            ),
+            binary_pow(&power_table, 1).unwrap(),
         );
 
         assert_eq!(pow_7, pow_components);
@@ -449,13 +553,21 @@ mod binary_pow_tests {
         let test_exponents = [15, 16, 23, 31];
 
         for exp in test_exponents.iter() {
-            let result = binary_pow(&power_table, *exp);
+            let result = binary_pow(&power_table, *exp).unwrap();
             let expected = float_to_q64(1.0001f64.powi(*exp as i32));
 
             // Use larger epsilon for larger exponents
             assert_q64_approx_eq(result, expected, 16);
         }
     }
+
+    #[test]
+    fn test_binary_pow_overflow_returns_none() {
+        // Squaring a value close to 2^64 does not fit in Q64.64
+        let power_table = [Q64_MAX, Q64_MAX];
+        assert_eq!(binary_pow(&power_table, 1), Some(Q64_MAX));
+        assert_eq!(binary_pow(&power_table, 3), None);
+    }
 }
 
 /// Comprehensive tests for babylonian_sqrt function
@@ -596,6 +708,20 @@ mod babylonian_sqrt_tests {
                    "Square root monotonicity violated: sqrt({}) = {} should be <= sqrt({}) = {}",
                    smaller, q64_to_float(sqrt_a), larger, q64_to_float(sqrt_b));
         }
+
+        #[test]
+        fn test_babylonian_sqrt_checked_never_overflows(x in any::<u128>()) {
+            // Every Q64.64 input has a representable root, and both variants agree on it
+            assert_eq!(babylonian_sqrt_checked(x), Some(babylonian_sqrt(x)));
+        }
+    }
+
+    #[test]
+    fn test_babylonian_sqrt_checked_extremes() {
+        assert_eq!(babylonian_sqrt_checked(Q64_ZERO), Some(Q64_ZERO));
+        assert_eq!(babylonian_sqrt_checked(Q64_ONE), Some(Q64_ONE));
+        assert!(babylonian_sqrt_checked(1).is_some());
+        assert!(babylonian_sqrt_checked(u128::MAX).is_some());
     }
 }
 
@@ -667,6 +793,275 @@ mod round_up_div_tests {
     }
 }
 
+/// Tests for mul_div_floor and mul_div_ceil
+mod mul_div_tests {
+    use super::*;
+    use primitive_types::U256;
+
+    #[test]
+    fn test_mul_div_basic() {
+        assert_eq!(mul_div_floor(6, 7, 3).unwrap(), 14);
+        assert_eq!(mul_div_ceil(6, 7, 3).unwrap(), 14);
+        assert_eq!(mul_div_floor(10, 10, 3).unwrap(), 33);
+        assert_eq!(mul_div_ceil(10, 10, 3).unwrap(), 34);
+        assert_eq!(mul_div_floor(0, u128::MAX, 1).unwrap(), 0);
+        assert_eq!(mul_div_ceil(0, u128::MAX, 1).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_mul_div_keeps_256_bit_product() {
+        // The products overflow u128, the quotients do not
+        assert_eq!(
+            mul_div_floor(u128::MAX, u128::MAX, u128::MAX).unwrap(),
+            u128::MAX
+        );
+        assert_eq!(
+            mul_div_ceil(u128::MAX, u128::MAX, u128::MAX).unwrap(),
+            u128::MAX
+        );
+        assert_eq!(mul_div_floor(u128::MAX, Q64, Q64).unwrap(), u128::MAX);
+        assert_eq!(
+            mul_div_floor(1 << 100, 1 << 100, 1 << 120).unwrap(),
+            1 << 80
+        );
+        assert_eq!(
+            mul_div_floor(u128::MAX, u128::MAX - 1, u128::MAX).unwrap(),
+            u128::MAX - 1
+        );
+    }
+
+    #[test]
+    fn test_mul_div_floor_overflow_boundary() {
+        assert_eq!(mul_div_floor(u128::MAX, 2, 2).unwrap(), u128::MAX);
+        assert_eq!(
+            mul_div_floor(u128::MAX, 2, 1).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+        assert_eq!(
+            mul_div_floor(u128::MAX, u128::MAX, u128::MAX - 1).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn test_mul_div_ceil_overflow_boundary() {
+        // a * b = 2 * u128::MAX + 1, whose floor quotient by 2 is u128::MAX and whose
+        // ceiling is one more
+        let a = 7;
+        let b = ((U256::from(u128::MAX) * U256::from(2) + U256::one()) / U256::from(7)).as_u128();
+        assert_eq!(mul_div_floor(a, b, 2).unwrap(), u128::MAX);
+        assert_eq!(
+            mul_div_ceil(a, b, 2).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn test_mul_div_zero_denominator() {
+        assert_eq!(
+            mul_div_floor(1, 1, 0).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+        assert_eq!(
+            mul_div_ceil(0, 0, 0).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_mul_div_matches_u256(a in any::<u128>(), b in any::<u128>(), denom in 1..=u128::MAX) {
+            let (quotient, remainder) = (U256::from(a) * U256::from(b)).div_mod(U256::from(denom));
+            if quotient > U256::from(u128::MAX) {
+                prop_assert!(mul_div_floor(a, b, denom).is_err());
+                prop_assert!(mul_div_ceil(a, b, denom).is_err());
+            } else {
+                let floor = mul_div_floor(a, b, denom).unwrap();
+                prop_assert_eq!(floor, quotient.as_u128());
+                match mul_div_ceil(a, b, denom) {
+                    Ok(ceil) => prop_assert_eq!(ceil, floor + !remainder.is_zero() as u128),
+                    Err(_) => prop_assert!(floor == u128::MAX && !remainder.is_zero()),
+                }
+            }
+        }
+    }
+}
+
+/// Tests for ln_fixed and exp_fixed
+mod ln_exp_fixed_tests {
+    use super::*;
+
+    /// e in Q64.64, rounded down.
+    const Q64_E: u128 = 0x2_b7e151628aed2a6a;
+
+    /// Asserts that `actual` is within `relative_error` of `expected`.
+    fn assert_relative_eq(actual: u128, expected: u128, relative_error: f64) {
+        let error = actual.abs_diff(expected) as f64 / expected as f64;
+        assert!(
+            error <= relative_error,
+            "{actual:x} vs {expected:x}: relative error {error:e}"
+        );
+    }
+
+    #[test]
+    fn test_ln_fixed_known_values() {
+        assert_eq!(ln_fixed(Q64_ONE).unwrap(), 0);
+        assert!(ln_fixed(Q64_TWO).unwrap().abs_diff(LN_2_Q64 as i128) <= 16);
+        assert!(ln_fixed(Q64_HALF).unwrap().abs_diff(-(LN_2_Q64 as i128)) <= 16);
+        assert!(ln_fixed(Q64_E).unwrap().abs_diff(Q64_ONE as i128) <= 16);
+        // The extremes of the Q64.64 range: ln(2^-64) and ln(2^64)
+        assert!(ln_fixed(1).unwrap().abs_diff(-64 * LN_2_Q64 as i128) <= 64);
+        assert!(ln_fixed(u128::MAX).unwrap().abs_diff(64 * LN_2_Q64 as i128) <= 64);
+    }
+
+    #[test]
+    fn test_ln_fixed_of_zero_is_invalid() {
+        assert_eq!(ln_fixed(0).unwrap_err(), ErrorCode::InvalidInput.into());
+    }
+
+    #[test]
+    fn test_exp_fixed_known_values() {
+        assert_eq!(exp_fixed(0).unwrap(), Q64_ONE);
+        assert_relative_eq(exp_fixed(LN_2_Q64 as i128).unwrap(), Q64_TWO, 1e-15);
+        assert_relative_eq(exp_fixed(-(LN_2_Q64 as i128)).unwrap(), Q64_HALF, 1e-15);
+        assert_relative_eq(exp_fixed(Q64_ONE as i128).unwrap(), Q64_E, 1e-15);
+        assert_relative_eq(
+            exp_fixed(-(Q64_ONE as i128)).unwrap(),
+            float_to_q64(std::f64::consts::E.recip()),
+            1e-12,
+        );
+    }
+
+    #[test]
+    fn test_exp_fixed_range() {
+        // exp(44) is about 1.3e19, just below the 2^64 limit of Q64.64
+        assert!(exp_fixed(44 * Q64_ONE as i128).is_ok());
+        assert_eq!(
+            exp_fixed(45 * Q64_ONE as i128).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+        assert_eq!(
+            exp_fixed(i128::MAX).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+        // exp(-45) is below the 2^-64 resolution
+        assert_eq!(exp_fixed(-45 * Q64_ONE as i128).unwrap(), 0);
+        assert_eq!(exp_fixed(i128::MIN).unwrap(), 0);
+        assert!(exp_fixed(-40 * Q64_ONE as i128).unwrap() > 0);
+    }
+
+    proptest! {
+        #[test]
+        fn test_exp_of_ln_is_identity(x in (Q64_ONE >> 20)..=(Q64_ONE << 20)) {
+            let round_trip = exp_fixed(ln_fixed(x).unwrap()).unwrap();
+            let error = round_trip.abs_diff(x) as f64 / x as f64;
+            prop_assert!(error <= 1e-12, "x = {:x}, exp(ln(x)) = {:x}", x, round_trip);
+        }
+
+        #[test]
+        fn test_ln_fixed_matches_f64(x in 1u128..) {
+            let expected = (x as f64 / Q64_ONE as f64).ln();
+            let actual = ln_fixed(x).unwrap() as f64 / Q64_ONE as f64;
+            // f64 resolves ln(x) to about 1e-14 across the Q64.64 range
+            prop_assert!((actual - expected).abs() <= 1e-13, "x = {:x}", x);
+        }
+    }
+}
+
+/// Tests for pow_fixed
+mod pow_fixed_tests {
+    use super::*;
+
+    /// 1.0001 in Q64.64, rounded down.
+    const Q64_TICK_BASE: u128 = (10_001 << 64) / 10_000;
+
+    fn relative_error(actual: u128, expected: f64) -> f64 {
+        (q64_to_float(actual) - expected).abs() / expected
+    }
+
+    #[test]
+    fn test_pow_fixed_known_values() {
+        assert_eq!(pow_fixed(Q64_FOUR, Q64_HALF as i128).unwrap(), Q64_TWO);
+        assert_eq!(pow_fixed(Q64_TWO, -(Q64_ONE as i128)).unwrap(), Q64_HALF);
+        assert_eq!(
+            pow_fixed(Q64_TWO, 3 * Q64_ONE as i128).unwrap(),
+            8 * Q64_ONE
+        );
+        assert!(relative_error(pow_fixed(Q64_QUARTER, -(Q64_HALF as i128)).unwrap(), 2.0) < 1e-15);
+        // A fractional power of a fraction: 0.25^1.5
+        assert!(
+            relative_error(pow_fixed(Q64_QUARTER, 3 * Q64_HALF as i128).unwrap(), 0.125) < 1e-15
+        );
+        assert_eq!(pow_fixed(Q64_TWO + 12_345, 0).unwrap(), Q64_ONE);
+        assert_eq!(pow_fixed(Q64_ONE, i128::MAX).unwrap(), Q64_ONE);
+    }
+
+    #[test]
+    fn test_pow_fixed_of_zero() {
+        assert_eq!(pow_fixed(Q64_ZERO, 0).unwrap(), Q64_ONE);
+        assert_eq!(pow_fixed(Q64_ZERO, Q64_HALF as i128).unwrap(), 0);
+        assert_eq!(
+            pow_fixed(Q64_ZERO, -(Q64_HALF as i128)).unwrap_err(),
+            ErrorCode::InvalidInput.into()
+        );
+    }
+
+    #[test]
+    fn test_pow_fixed_range() {
+        assert_eq!(
+            pow_fixed(Q64_TWO, 64 * Q64_ONE as i128).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+        assert_eq!(
+            pow_fixed(Q64_TWO, i128::MAX).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+        assert_eq!(pow_fixed(Q64_HALF, 70 * Q64_ONE as i128).unwrap(), 0);
+        assert_eq!(pow_fixed(Q64_HALF, i128::MAX).unwrap(), 0);
+        assert_eq!(pow_fixed(Q64_TWO, i128::MIN).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_half_tick_powers_match_tick_sqrt_prices() {
+        for tick in [-200_000, -4_055, -1, 1, 60, 4_055, 200_000] {
+            let exponent = ((tick as i128) << 64) / 2;
+            let power = pow_fixed(Q64_TICK_BASE, exponent).unwrap();
+            let sqrt_price = tick_to_sqrt_price_q64(tick).unwrap();
+            let error = power.abs_diff(sqrt_price) as f64 / sqrt_price as f64;
+            assert!(error < 1e-10, "tick {tick}: relative error {error:e}");
+        }
+    }
+
+    proptest! {
+        /// Over bases in [2^-20, 2^20] and exponents in [-1_000, 1_000] whose results lie
+        /// in [2^-20, e^40]. Bases are drawn evenly across binades, and exponents from the
+        /// log of the result they lead to.
+        #[test]
+        fn test_pow_fixed_matches_f64(
+            mantissa in Q64_ONE..Q64_TWO,
+            binade in 0u32..40,
+            ln_power in -13.8f64..40.0,
+        ) {
+            let base = (mantissa << binade) >> 20;
+            let base_f64 = q64_to_float(base);
+            let exponent_f64 = ln_power / base_f64.ln();
+            prop_assume!(exponent_f64.is_finite() && exponent_f64.abs() <= 1_000.0);
+            let exponent = (exponent_f64 * Q64_ONE as f64) as i128;
+
+            let expected = base_f64.powf(exponent as f64 / Q64_ONE as f64);
+            let actual = pow_fixed(base, exponent).unwrap();
+            let error = relative_error(actual, expected);
+            prop_assert!(
+                error <= 1e-12,
+                "{}^{}: relative error {:e}",
+                base_f64,
+                exponent_f64,
+                error
+            );
+        }
+    }
+}
+
 /// Comprehensive tests for clamp_u128 function
 mod clamp_u128_tests {
     use super::*;
@@ -1202,17 +1597,13 @@ mod tick_to_sqrt_price_q64_tests {
         }
 
         #[test]
-        fn test_tick_to_sqrt_price_q64_round_trip(tick in MIN_TICK..MAX_TICK) {
+        fn test_tick_to_sqrt_price_q64_round_trip(tick in MIN_RESOLVED_TICK..MAX_TICK) {
             // Test that converting tick -> sqrt price -> tick gives the original tick
             // Verifies consistency between tick_to_sqrt_price_q64 and sqrt_price_q64_to_tick
             let sqrt_price = tick_to_sqrt_price_q64(tick).unwrap();
             let round_trip_tick = sqrt_price_q64_to_tick(sqrt_price).unwrap();
 
-            // Due to floating-point precision and binary search approximation,
-            // and plateaus in tick_to_sqrt_price_q64 at extreme ticks,
-            // the round trip might differ significantly for those extremes.
-            // Max observed diff was ~13k for MIN_TICK.
-            assert!((round_trip_tick - tick).abs() <= 14000, // Very large tolerance for extreme tick plateaus
+            assert!((round_trip_tick - tick).abs() <= 1,
                 "Round trip tick conversion failed: {} -> {} -> {}",
                 tick, q64_to_float(sqrt_price), round_trip_tick);
         }
@@ -1506,21 +1897,50 @@ mod sqrt_price_q64_to_tick_tests {
         }
 
         #[test]
-        fn test_sqrt_price_q64_to_tick_round_trip(tick in MIN_TICK..MAX_TICK) {
-            // Test that converting tick -> sqrt price -> tick gives either the original tick
-            // or a tick that is very close to it (due to precision limitations)
+        fn test_sqrt_price_q64_to_tick_round_trip(tick in MIN_RESOLVED_TICK..MAX_TICK) {
+            // Test that converting tick -> sqrt price -> tick gives the original tick
             let sqrt_price = tick_to_sqrt_price_q64(tick).unwrap();
 
             // Convert back to a tick
             let round_trip_tick = sqrt_price_q64_to_tick(sqrt_price).unwrap();
 
-            // Because of floating-point precision issues and binary search approximation,
-            // and plateaus at extreme ticks, allow a large tolerance.
-            // Max observed diff was ~13k for MIN_TICK.
-            assert!((tick - round_trip_tick).abs() <= 14000, // Very large tolerance
-                "Round trip conversion should preserve tick value closely: {} -> {} -> {}",
+            assert!((tick - round_trip_tick).abs() <= 1,
+                "Round trip conversion should preserve tick value: {} -> {} -> {}",
                 tick, q64_to_float(sqrt_price), round_trip_tick);
         }
+
+        #[test]
+        fn test_sqrt_price_q64_to_tick_is_floor_of_tick_prices(
+            tick in MIN_TICK..MAX_TICK,
+            offset in -1i128..=1,
+        ) {
+            // Around every tick price, including below MIN_RESOLVED_TICK, the result is the
+            // highest tick whose price does not exceed the input.
+            let sqrt_price = (tick_to_sqrt_price_q64(tick).unwrap() as i128 + offset).max(1) as u128;
+            let result = sqrt_price_q64_to_tick(sqrt_price).unwrap();
+
+            assert!(tick_to_sqrt_price_q64(result).unwrap() <= sqrt_price,
+                "P({}) exceeds {}", result, sqrt_price);
+            assert!(tick_to_sqrt_price_q64(result + 1).unwrap() > sqrt_price,
+                "P({}) does not exceed {}", result + 1, sqrt_price);
+        }
+    }
+
+    #[test]
+    fn test_sqrt_price_q64_to_tick_below_resolved_range_returns_highest_tick() {
+        // Every tick from MIN_TICK up to some tick below MIN_RESOLVED_TICK rounds to the
+        // minimum sqrt price; the conversion returns the last of them.
+        let tick = sqrt_price_q64_to_tick(MIN_SQRT_PRICE).unwrap();
+        assert_eq!(tick_to_sqrt_price_q64(tick).unwrap(), MIN_SQRT_PRICE);
+        assert!(tick_to_sqrt_price_q64(tick + 1).unwrap() > MIN_SQRT_PRICE);
+        assert_eq!(tick_to_sqrt_price_q64(MIN_TICK).unwrap(), MIN_SQRT_PRICE);
+
+        // From MIN_RESOLVED_TICK up, at most two ticks share a price.
+        for tick in MIN_RESOLVED_TICK..MIN_RESOLVED_TICK + 100 {
+            let sqrt_price = tick_to_sqrt_price_q64(tick).unwrap();
+            let round_trip_tick = sqrt_price_q64_to_tick(sqrt_price).unwrap();
+            assert!(round_trip_tick == tick || round_trip_tick == tick + 1);
+        }
     }
 }
 
@@ -2332,7 +2752,613 @@ mod compute_next_sqrt_price_from_amount1_in_tests {
     }
 }
 
+/// Tests for get_amounts_for_liquidity
+mod get_amounts_for_liquidity_tests {
+    use super::*;
+
+    #[test]
+    fn test_get_amounts_for_liquidity_price_below_range() {
+        let (amount_0, amount_1) =
+            get_amounts_for_liquidity(Q64_HALF, Q64_ONE, Q64_TWO, Q64_ONE, false).unwrap();
+        let expected_0 = get_amount_0_delta(Q64_ONE, Q64_TWO, Q64_ONE, false).unwrap();
+        assert_eq!(amount_0, expected_0);
+        assert_eq!(amount_1, 0);
+    }
+
+    #[test]
+    fn test_get_amounts_for_liquidity_price_above_range() {
+        let (amount_0, amount_1) =
+            get_amounts_for_liquidity(Q64_FOUR, Q64_ONE, Q64_TWO, Q64_ONE, false).unwrap();
+        let expected_1 = get_amount_1_delta(Q64_ONE, Q64_TWO, Q64_ONE, false).unwrap();
+        assert_eq!(amount_0, 0);
+        assert_eq!(amount_1, expected_1);
+    }
+
+    #[test]
+    fn test_get_amounts_for_liquidity_price_in_range() {
+        let current = float_to_q64(1.5);
+        let (amount_0, amount_1) =
+            get_amounts_for_liquidity(current, Q64_ONE, Q64_TWO, Q64_ONE, false).unwrap();
+        assert_eq!(
+            amount_0,
+            get_amount_0_delta(current, Q64_TWO, Q64_ONE, false).unwrap()
+        );
+        assert_eq!(
+            amount_1,
+            get_amount_1_delta(Q64_ONE, current, Q64_ONE, false).unwrap()
+        );
+        assert!(amount_0 > 0 && amount_1 > 0);
+    }
+
+    #[test]
+    fn test_get_amounts_for_liquidity_invalid_range() {
+        assert!(get_amounts_for_liquidity(Q64_ONE, Q64_TWO, Q64_ONE, Q64_ONE, false).is_err());
+        assert!(get_amounts_for_liquidity(Q64_ONE, Q64_ONE, Q64_ONE, Q64_ONE, false).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_get_amounts_for_liquidity_round_up_never_less(
+            current_f in 0.5f64..2.0,
+            liquidity in 1u128..1_000_000_000_000u128,
+        ) {
+            let current = float_to_q64(current_f);
+            let lower = float_to_q64(0.75);
+            let upper = float_to_q64(1.5);
+            let (down_0, down_1) = get_amounts_for_liquidity(current, lower, upper, liquidity, false).unwrap();
+            let (up_0, up_1) = get_amounts_for_liquidity(current, lower, upper, liquidity, true).unwrap();
+            prop_assert!(up_0 >= down_0 && up_0 - down_0 <= 1);
+            prop_assert!(up_1 >= down_1 && up_1 - down_1 <= 1);
+        }
+    }
+}
+
+/// Tests for get_liquidity_for_amounts and its round trip through the amount deltas
+mod get_liquidity_for_amounts_tests {
+    use super::*;
+
+    #[test]
+    fn test_get_liquidity_for_amounts_price_below_range_uses_token0() {
+        let liquidity =
+            get_liquidity_for_amounts(Q64_HALF, Q64_ONE, Q64_TWO, Q64_ONE, Q64_ONE).unwrap();
+        assert_eq!(
+            liquidity,
+            get_liquidity_for_amount0(Q64_ONE, Q64_TWO, Q64_ONE).unwrap()
+        );
+        // Token 1 plays no part
+        assert_eq!(
+            get_liquidity_for_amounts(Q64_HALF, Q64_ONE, Q64_TWO, Q64_ONE, 0).unwrap(),
+            liquidity
+        );
+    }
+
+    #[test]
+    fn test_get_liquidity_for_amounts_price_above_range_uses_token1() {
+        let liquidity =
+            get_liquidity_for_amounts(Q64_FOUR, Q64_ONE, Q64_TWO, Q64_ONE, Q64_ONE).unwrap();
+        assert_eq!(
+            liquidity,
+            get_liquidity_for_amount1(Q64_ONE, Q64_TWO, Q64_ONE).unwrap()
+        );
+        assert_eq!(
+            get_liquidity_for_amounts(Q64_FOUR, Q64_ONE, Q64_TWO, 0, Q64_ONE).unwrap(),
+            liquidity
+        );
+    }
+
+    #[test]
+    fn test_get_liquidity_for_amounts_price_in_range_takes_the_smaller() {
+        let current = float_to_q64(1.5);
+        let from_0 = get_liquidity_for_amount0(current, Q64_TWO, Q64_ONE).unwrap();
+        let from_1 = get_liquidity_for_amount1(Q64_ONE, current, Q64_ONE).unwrap();
+        let liquidity =
+            get_liquidity_for_amounts(current, Q64_ONE, Q64_TWO, Q64_ONE, Q64_ONE).unwrap();
+        assert_eq!(liquidity, from_0.min(from_1));
+
+        // Without one of the tokens, no liquidity can be added inside the range
+        assert_eq!(
+            get_liquidity_for_amounts(current, Q64_ONE, Q64_TWO, Q64_ONE, 0).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_get_liquidity_for_amounts_price_at_bounds() {
+        // At the lower bound the range is entirely token 0, at the upper bound token 1
+        assert_eq!(
+            get_liquidity_for_amounts(Q64_ONE, Q64_ONE, Q64_TWO, Q64_ONE, 0).unwrap(),
+            get_liquidity_for_amount0(Q64_ONE, Q64_TWO, Q64_ONE).unwrap()
+        );
+        assert_eq!(
+            get_liquidity_for_amounts(Q64_TWO, Q64_ONE, Q64_TWO, 0, Q64_ONE).unwrap(),
+            get_liquidity_for_amount1(Q64_ONE, Q64_TWO, Q64_ONE).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_liquidity_for_amounts_invalid_range() {
+        assert_eq!(
+            get_liquidity_for_amounts(Q64_ONE, Q64_TWO, Q64_ONE, Q64_ONE, Q64_ONE).unwrap_err(),
+            ErrorCode::InvalidPriceRange.into()
+        );
+        assert_eq!(
+            get_liquidity_for_amounts(Q64_ONE, Q64_ONE, Q64_ONE, Q64_ONE, Q64_ONE).unwrap_err(),
+            ErrorCode::InvalidPriceRange.into()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_get_liquidity_for_amounts_round_trip(
+            current_tick in -100_000i32..100_000,
+            lower_tick in -100_000i32..100_000,
+            width in 1i32..50_000,
+            amount_0 in 0u128..1_000_000_000_000_000u128,
+            amount_1 in 0u128..1_000_000_000_000_000u128,
+        ) {
+            let current = tick_to_sqrt_price_q64(current_tick).unwrap();
+            let lower = tick_to_sqrt_price_q64(lower_tick).unwrap();
+            let upper = tick_to_sqrt_price_q64(lower_tick + width).unwrap();
+            let liquidity = get_liquidity_for_amounts(current, lower, upper, amount_0, amount_1).unwrap();
+
+            // The deposit the liquidity requires, rounded in the pool's favour, never
+            // exceeds what was offered
+            let (needed_0, needed_1) =
+                get_amounts_for_liquidity(current, lower, upper, liquidity, true).unwrap();
+            prop_assert!(needed_0 <= amount_0, "{needed_0} > {amount_0}");
+            prop_assert!(needed_1 <= amount_1, "{needed_1} > {amount_1}");
+
+            // ...and one more unit of liquidity would exceed it on the binding side
+            let (over_0, over_1) =
+                get_amounts_for_liquidity(current, lower, upper, liquidity + 1, true).unwrap();
+            prop_assert!(over_0 > amount_0 || over_1 > amount_1);
+        }
+
+        #[test]
+        fn test_get_liquidity_for_amounts_matches_amount_deltas(
+            current_tick in -100_000i32..100_000,
+            lower_tick in -100_000i32..100_000,
+            width in 1i32..50_000,
+            amount_0 in 1u128..1_000_000_000_000_000u128,
+            amount_1 in 1u128..1_000_000_000_000_000u128,
+        ) {
+            let current = tick_to_sqrt_price_q64(current_tick).unwrap();
+            let lower = tick_to_sqrt_price_q64(lower_tick).unwrap();
+            let upper = tick_to_sqrt_price_q64(lower_tick + width).unwrap();
+            let liquidity = get_liquidity_for_amounts(current, lower, upper, amount_0, amount_1).unwrap();
+
+            if current <= lower {
+                prop_assert!(get_amount_0_delta(lower, upper, liquidity, true).unwrap() <= amount_0);
+                prop_assert!(get_amount_0_delta(lower, upper, liquidity + 1, true).unwrap() > amount_0);
+            } else if current >= upper {
+                prop_assert!(get_amount_1_delta(lower, upper, liquidity, true).unwrap() <= amount_1);
+                prop_assert!(get_amount_1_delta(lower, upper, liquidity + 1, true).unwrap() > amount_1);
+            } else {
+                let needed_0 = get_amount_0_delta(current, upper, liquidity, true).unwrap();
+                let needed_1 = get_amount_1_delta(lower, current, liquidity, true).unwrap();
+                prop_assert!(needed_0 <= amount_0 && needed_1 <= amount_1);
+            }
+        }
+    }
+}
+
+/// Tests for the exact-output next sqrt price functions
+mod compute_next_sqrt_price_from_amount_out_tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_next_sqrt_price_from_amount0_out_basic() {
+        let liquidity = 1_000_000u128;
+        let result = compute_next_sqrt_price_from_amount0_out(Q64_ONE, liquidity, 1_000).unwrap();
+        // Price should increase when removing token0
+        assert!(result > Q64_ONE);
+        // Removing that much token0 must cost at least as much as the delta implies
+        let amount_0 = get_amount_0_delta(Q64_ONE, result, liquidity, false).unwrap();
+        assert!(amount_0 >= 1_000);
+    }
+
+    #[test]
+    fn test_compute_next_sqrt_price_from_amount1_out_basic() {
+        let liquidity = 1_000_000u128;
+        let result = compute_next_sqrt_price_from_amount1_out(Q64_ONE, liquidity, 1_000).unwrap();
+        // Price should decrease when removing token1
+        assert!(result < Q64_ONE);
+        let amount_1 = get_amount_1_delta(result, Q64_ONE, liquidity, false).unwrap();
+        assert!(amount_1 >= 1_000);
+    }
+
+    #[test]
+    fn test_compute_next_sqrt_price_from_amount_out_zero_amount() {
+        assert_eq!(
+            compute_next_sqrt_price_from_amount0_out(Q64_ONE, Q64_ONE, 0).unwrap(),
+            Q64_ONE
+        );
+        assert_eq!(
+            compute_next_sqrt_price_from_amount1_out(Q64_ONE, Q64_ONE, 0).unwrap(),
+            Q64_ONE
+        );
+    }
+
+    #[test]
+    fn test_compute_next_sqrt_price_from_amount_out_zero_liquidity() {
+        assert!(compute_next_sqrt_price_from_amount0_out(Q64_ONE, 0, 1).is_err());
+        assert!(compute_next_sqrt_price_from_amount1_out(Q64_ONE, 0, 1).is_err());
+    }
+
+    #[test]
+    fn test_compute_next_sqrt_price_from_amount_out_exceeds_reserves() {
+        // With L = 1000 at price 1.0 there are only 1000 units of either token on each side
+        assert!(compute_next_sqrt_price_from_amount0_out(Q64_ONE, 1_000, 1_000).is_err());
+        assert!(compute_next_sqrt_price_from_amount1_out(Q64_ONE, 1_000, 1_000).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_compute_next_sqrt_price_from_amount_out_covers_output(
+            sqrt_price_f in 0.5f64..2.0,
+            liquidity in 1_000_000u128..1_000_000_000_000u128,
+            amount_out in 1u128..1_000u128,
+        ) {
+            let sqrt_price_q64 = float_to_q64(sqrt_price_f);
+
+            let next_up = compute_next_sqrt_price_from_amount0_out(sqrt_price_q64, liquidity, amount_out).unwrap();
+            prop_assert!(next_up > sqrt_price_q64);
+            // get_amount_0_delta floors both inverted prices, so allow one unit of slack
+            prop_assert!(get_amount_0_delta(sqrt_price_q64, next_up, liquidity, false).unwrap() + 1 >= amount_out);
+
+            let next_down = compute_next_sqrt_price_from_amount1_out(sqrt_price_q64, liquidity, amount_out).unwrap();
+            prop_assert!(next_down < sqrt_price_q64);
+            prop_assert!(get_amount_1_delta(next_down, sqrt_price_q64, liquidity, false).unwrap() >= amount_out);
+        }
+    }
+}
+
+/// Tests for the single swap step computation
+mod compute_swap_step_tests {
+    use super::*;
+
+    const LIQUIDITY: u128 = 1_000_000_000_000;
+    const FEE_PIPS: u32 = 3000; // 0.3%
+
+    #[test]
+    fn test_compute_swap_step_reaches_target_zero_for_one() {
+        let target = float_to_q64(0.99);
+        let step = compute_swap_step(Q64_ONE, target, LIQUIDITY, u64::MAX, FEE_PIPS).unwrap();
+
+        assert_eq!(step.sqrt_price_next, target);
+        assert_eq!(
+            step.amount_in,
+            get_amount_0_delta(target, Q64_ONE, LIQUIDITY, true).unwrap()
+        );
+        assert_eq!(
+            step.amount_out,
+            get_amount_1_delta(target, Q64_ONE, LIQUIDITY, false).unwrap()
+        );
+        assert_eq!(
+            step.fee_amount,
+            round_up_div(
+                step.amount_in * FEE_PIPS as u128,
+                1_000_000 - FEE_PIPS as u128
+            )
+        );
+    }
+
+    #[test]
+    fn test_compute_swap_step_reaches_target_one_for_zero() {
+        let target = float_to_q64(1.01);
+        let step = compute_swap_step(Q64_ONE, target, LIQUIDITY, u64::MAX, FEE_PIPS).unwrap();
+
+        assert_eq!(step.sqrt_price_next, target);
+        assert_eq!(
+            step.amount_in,
+            get_amount_1_delta(Q64_ONE, target, LIQUIDITY, true).unwrap()
+        );
+        assert_eq!(
+            step.amount_out,
+            get_amount_0_delta(Q64_ONE, target, LIQUIDITY, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_compute_swap_step_partial_consumes_all_input() {
+        let target = float_to_q64(0.5);
+        let amount_remaining = 1_000_000u64;
+        let step =
+            compute_swap_step(Q64_ONE, target, LIQUIDITY, amount_remaining, FEE_PIPS).unwrap();
+
+        assert!(step.sqrt_price_next < Q64_ONE && step.sqrt_price_next > target);
+        assert_eq!(step.amount_in + step.fee_amount, amount_remaining as u128);
+        assert_eq!(step.fee_amount, 3000); // 0.3% of 1_000_000
+        assert!(step.amount_out > 0 && step.amount_out < step.amount_in);
+    }
+
+    #[test]
+    fn test_compute_swap_step_zero_fee() {
+        let target = float_to_q64(0.5);
+        let step = compute_swap_step(Q64_ONE, target, LIQUIDITY, 1_000_000, 0).unwrap();
+        assert_eq!(step.fee_amount, 0);
+        assert_eq!(step.amount_in, 1_000_000);
+    }
+
+    #[test]
+    fn test_compute_swap_step_zero_liquidity_moves_to_target() {
+        let target = float_to_q64(0.5);
+        let step = compute_swap_step(Q64_ONE, target, 0, 1_000, FEE_PIPS).unwrap();
+        assert_eq!(step.sqrt_price_next, target);
+        assert_eq!(
+            (step.amount_in, step.amount_out, step.fee_amount),
+            (0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_compute_swap_step_at_target_is_noop() {
+        let step = compute_swap_step(Q64_ONE, Q64_ONE, LIQUIDITY, 1_000, FEE_PIPS).unwrap();
+        assert_eq!(step.sqrt_price_next, Q64_ONE);
+        assert_eq!(
+            (step.amount_in, step.amount_out, step.fee_amount),
+            (0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_compute_swap_step_rejects_full_fee() {
+        assert!(compute_swap_step(Q64_ONE, Q64_HALF, LIQUIDITY, 1_000, 1_000_000).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_compute_swap_step_never_exceeds_amount_remaining(
+            target_f in 0.5f64..2.0,
+            liquidity in 1u128..1_000_000_000_000_000u128,
+            amount_remaining in 0u64..u64::MAX,
+            fee_pips in 0u32..100_000,
+        ) {
+            let target = float_to_q64(target_f);
+            let step = compute_swap_step(Q64_ONE, target, liquidity, amount_remaining, fee_pips).unwrap();
+            prop_assert!(step.amount_in + step.fee_amount <= amount_remaining as u128);
+        }
+
+        #[test]
+        fn test_compute_swap_step_caps_at_target(
+            target_f in 0.5f64..2.0,
+            liquidity in 1u128..1_000_000_000_000_000u128,
+            amount_remaining in 0u64..u64::MAX,
+        ) {
+            let target = float_to_q64(target_f);
+            let step = compute_swap_step(Q64_ONE, target, liquidity, amount_remaining, FEE_PIPS).unwrap();
+            if target <= Q64_ONE {
+                prop_assert!(step.sqrt_price_next >= target && step.sqrt_price_next <= Q64_ONE);
+            } else {
+                prop_assert!(step.sqrt_price_next <= target && step.sqrt_price_next >= Q64_ONE);
+            }
+        }
+
+        #[test]
+        fn test_compute_swap_step_output_monotonic_in_input(
+            target_f in 0.5f64..2.0,
+            liquidity in 1u128..1_000_000_000_000_000u128,
+            amount_a in 0u64..u64::MAX,
+            amount_b in 0u64..u64::MAX,
+        ) {
+            let target = float_to_q64(target_f);
+            let (smaller, larger) = (amount_a.min(amount_b), amount_a.max(amount_b));
+            let step_small = compute_swap_step(Q64_ONE, target, liquidity, smaller, FEE_PIPS).unwrap();
+            let step_large = compute_swap_step(Q64_ONE, target, liquidity, larger, FEE_PIPS).unwrap();
+            prop_assert!(step_small.amount_out <= step_large.amount_out);
+        }
+    }
+}
+
+/// Tests for the fee growth accounting functions
+mod fee_growth_tests {
+    use super::*;
+
+    const GLOBAL: u128 = 1_000;
+    const OUTSIDE_LOWER: u128 = 100;
+    const OUTSIDE_UPPER: u128 = 300;
+
+    #[test]
+    fn test_get_fee_growth_inside_current_in_range() {
+        // below = outside_lower, above = outside_upper
+        assert_eq!(
+            get_fee_growth_inside(-60, 60, 0, GLOBAL, OUTSIDE_LOWER, OUTSIDE_UPPER),
+            GLOBAL - OUTSIDE_LOWER - OUTSIDE_UPPER
+        );
+    }
+
+    #[test]
+    fn test_get_fee_growth_inside_current_below_range() {
+        // Both outside values count growth above their tick, so the lower one is larger:
+        // below = global - outside_lower, above = outside_upper
+        assert_eq!(
+            get_fee_growth_inside(-60, 60, -120, GLOBAL, OUTSIDE_UPPER, OUTSIDE_LOWER),
+            OUTSIDE_UPPER - OUTSIDE_LOWER
+        );
+    }
+
+    #[test]
+    fn test_get_fee_growth_inside_current_above_range() {
+        // below = outside_lower, above = global - outside_upper
+        assert_eq!(
+            get_fee_growth_inside(-60, 60, 60, GLOBAL, OUTSIDE_LOWER, OUTSIDE_UPPER),
+            OUTSIDE_UPPER - OUTSIDE_LOWER
+        );
+    }
+
+    #[test]
+    fn test_get_fee_growth_inside_wraps() {
+        // Differences of wrapped values stay correct across the u128 boundary
+        let before = get_fee_growth_inside(-60, 60, 0, u128::MAX - 10, 0, 0);
+        let after = get_fee_growth_inside(-60, 60, 0, 20, 0, 0);
+        assert_eq!(after.wrapping_sub(before), 31);
+    }
+
+    #[test]
+    fn test_get_fee_growth_delta_q64() {
+        assert_eq!(get_fee_growth_delta_q64(3, 2).unwrap(), Q64_ONE + Q64_HALF);
+        assert_eq!(get_fee_growth_delta_q64(1_000, 0).unwrap(), 0);
+        assert!(get_fee_growth_delta_q64(u128::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn test_get_fee_growth_delta_q64_overflow_boundary() {
+        // fee << 64 no longer fits in u128 from 2^64 on, only the quotient has to
+        assert_eq!(
+            get_fee_growth_delta_q64(u64::MAX as u128, 1).unwrap(),
+            (u64::MAX as u128) << 64
+        );
+        assert_eq!(
+            get_fee_growth_delta_q64(1 << 64, 1).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+        assert_eq!(
+            get_fee_growth_delta_q64(1 << 100, 1 << 100).unwrap(),
+            Q64_ONE
+        );
+        assert_eq!(
+            get_fee_growth_delta_q64(u128::MAX, u128::MAX).unwrap(),
+            Q64_ONE
+        );
+    }
+
+    #[test]
+    fn test_get_fees_earned_overflow_boundary() {
+        assert_eq!(get_fees_earned(u128::MAX, 1, 0).unwrap(), u64::MAX);
+        assert_eq!(get_fees_earned(1 << 100, 1 << 27, 0).unwrap(), 1 << 63);
+        assert_eq!(
+            get_fees_earned(1 << 100, 1 << 28, 0).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn test_get_fees_earned() {
+        assert_eq!(get_fees_earned(1_000, Q64_TWO, Q64_ONE).unwrap(), 1_000);
+        assert_eq!(get_fees_earned(1_000, Q64_ONE, Q64_ONE).unwrap(), 0);
+        // Growth that wrapped since the last snapshot
+        assert_eq!(
+            get_fees_earned(1_000, Q64_HALF, u128::MAX - Q64_HALF + 1).unwrap(),
+            1_000
+        );
+        assert!(get_fees_earned(u128::MAX, Q64_TWO, 0).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_fee_growth_inside_plus_outside_is_global(
+            tick_current in -200i32..200,
+            global in any::<u128>(),
+            outside_lower in any::<u128>(),
+            outside_upper in any::<u128>(),
+        ) {
+            // below + inside + above always adds up to the global growth
+            let inside = get_fee_growth_inside(-60, 60, tick_current, global, outside_lower, outside_upper);
+            let below = if tick_current >= -60 { outside_lower } else { global.wrapping_sub(outside_lower) };
+            let above = if tick_current < 60 { outside_upper } else { global.wrapping_sub(outside_upper) };
+            prop_assert_eq!(below.wrapping_add(inside).wrapping_add(above), global);
+        }
+
+        #[test]
+        fn test_fees_earned_round_trip(
+            liquidity in 1u128..1_000_000_000_000u128,
+            fee_amount in 0u128..1_000_000_000u128,
+        ) {
+            // Distributing a fee over liquidity and paying it back never creates tokens
+            let delta = get_fee_growth_delta_q64(fee_amount, liquidity).unwrap();
+            let earned = get_fees_earned(liquidity, delta, 0).unwrap() as u128;
+            prop_assert!(earned <= fee_amount);
+            prop_assert!(fee_amount - earned <= 1);
+        }
+    }
+}
+
 /// Integration tests combining multiple AMM functions
+mod price_impact_tests {
+    use super::*;
+
+    /// `|P_after / P_before - 1|` in basis points, in floating point.
+    fn reference_impact_bps(sqrt_price_before_q64: u128, sqrt_price_after_q64: u128) -> f64 {
+        let sqrt_ratio = q64_to_float(sqrt_price_after_q64) / q64_to_float(sqrt_price_before_q64);
+        (sqrt_ratio * sqrt_ratio - 1.0).abs() * 10_000.0
+    }
+
+    #[test]
+    fn test_price_impact_bps_no_move() {
+        assert_eq!(price_impact_bps(Q64_ONE, Q64_ONE).unwrap(), 0);
+        assert_eq!(price_impact_bps(MAX_SQRT_PRICE, MAX_SQRT_PRICE).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_price_impact_bps_measures_the_price_not_the_sqrt_price() {
+        // Halving the sqrt price quarters the price: a 75% drop
+        assert_eq!(price_impact_bps(Q64_ONE, Q64_HALF).unwrap(), 7_500);
+        // Doubling it quadruples the price: a 300% rise
+        assert_eq!(price_impact_bps(Q64_ONE, Q64_TWO).unwrap(), 30_000);
+        assert_eq!(price_impact_bps(Q64_TWO, Q64_FOUR).unwrap(), 30_000);
+    }
+
+    #[test]
+    fn test_price_impact_bps_one_tick_is_one_bp() {
+        // Each tick moves the price by 0.01%
+        let sqrt_price = tick_to_sqrt_price_q64(0).unwrap();
+        let up = tick_to_sqrt_price_q64(1).unwrap();
+        let impact = price_impact_bps(sqrt_price, up).unwrap();
+        assert!(impact <= 1, "impact {impact}");
+    }
+
+    #[test]
+    fn test_price_impact_bps_saturates() {
+        assert_eq!(price_impact_bps(1, MAX_SQRT_PRICE).unwrap(), u64::MAX);
+        // A drop is bounded by 100%
+        let impact = price_impact_bps(MAX_SQRT_PRICE, 1).unwrap();
+        assert!((9_999..=10_000).contains(&impact), "impact {impact}");
+    }
+
+    #[test]
+    fn test_price_impact_bps_rejects_zero_price() {
+        assert_eq!(
+            price_impact_bps(0, Q64_ONE).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_price_impact_bps_matches_reference(
+            tick_before in -400_000i32..400_000,
+            tick_move in -50_000i32..50_000,
+        ) {
+            let before = tick_to_sqrt_price_q64(tick_before).unwrap();
+            let after = tick_to_sqrt_price_q64(tick_before + tick_move).unwrap();
+            let impact = price_impact_bps(before, after).unwrap() as f64;
+            let reference = reference_impact_bps(before, after);
+            // Rounded down, within floating point error of the reference
+            prop_assert!(
+                (impact - reference).abs() <= 1.0 + reference * 1e-9,
+                "impact {} reference {}", impact, reference
+            );
+        }
+
+        #[test]
+        fn test_price_impact_bps_grows_with_the_move(
+            tick_before in -400_000i32..400_000,
+            small_move in 1i32..10_000,
+            extra_move in 1i32..10_000,
+            up in proptest::bool::ANY,
+        ) {
+            let direction = if up { 1 } else { -1 };
+            let before = tick_to_sqrt_price_q64(tick_before).unwrap();
+            let small = tick_to_sqrt_price_q64(tick_before + direction * small_move).unwrap();
+            let large =
+                tick_to_sqrt_price_q64(tick_before + direction * (small_move + extra_move)).unwrap();
+            prop_assert!(
+                price_impact_bps(before, large).unwrap() >= price_impact_bps(before, small).unwrap()
+            );
+        }
+    }
+}
+
 mod amm_integration_tests {
     use super::*;
 
@@ -2491,3 +3517,119 @@ mod amm_integration_tests {
         }
     }
 }
+
+/// Tests for the signed FixedQ64 type
+mod fixed_q64_tests {
+    use super::*;
+
+    /// Raw values within ±2^90 (about ±2^26 as numbers), so products of two never overflow
+    fn bounded() -> impl Strategy<Value = FixedQ64> {
+        (-(1i128 << 90)..(1i128 << 90)).prop_map(FixedQ64::from_raw)
+    }
+
+    #[test]
+    fn test_fixed_q64_basic() {
+        let two = FixedQ64::from_i64(2);
+        let three = FixedQ64::from_i64(3);
+        let minus_three = FixedQ64::from_i64(-3);
+
+        assert_eq!(two + three, FixedQ64::from_i64(5));
+        assert_eq!(two - three, FixedQ64::from_i64(-1));
+        assert_eq!(two * minus_three, FixedQ64::from_i64(-6));
+        assert_eq!(minus_three / two, FixedQ64::from_raw(-(3i128 << 63)));
+        assert_eq!((minus_three / two).to_f64_lossy(), -1.5);
+        assert_eq!(-minus_three, three);
+        assert_eq!(minus_three.abs(), three);
+        assert_eq!(minus_three.signum(), FixedQ64::from_i64(-1));
+        assert_eq!(FixedQ64::ZERO.signum(), FixedQ64::ZERO);
+        assert_eq!(two.signum(), FixedQ64::ONE);
+        assert_eq!((minus_three / two).trunc_to_i128(), -1);
+    }
+
+    #[test]
+    fn test_fixed_q64_saturates() {
+        let two = FixedQ64::from_i64(2);
+
+        assert_eq!(FixedQ64::MAX + FixedQ64::ONE, FixedQ64::MAX);
+        assert_eq!(FixedQ64::MIN - FixedQ64::ONE, FixedQ64::MIN);
+        assert_eq!(FixedQ64::MAX * two, FixedQ64::MAX);
+        assert_eq!(FixedQ64::MAX * -two, FixedQ64::MIN);
+        assert_eq!(FixedQ64::MIN * -two, FixedQ64::MAX);
+        assert_eq!(FixedQ64::MAX / FixedQ64::from_raw(1), FixedQ64::MAX);
+        assert_eq!(FixedQ64::ONE / FixedQ64::ZERO, FixedQ64::MAX);
+        assert_eq!(-FixedQ64::ONE / FixedQ64::ZERO, FixedQ64::MIN);
+        assert_eq!(FixedQ64::ZERO / FixedQ64::ZERO, FixedQ64::ZERO);
+        assert_eq!(-FixedQ64::MIN, FixedQ64::MAX);
+        assert_eq!(FixedQ64::MIN.abs(), FixedQ64::MAX);
+    }
+
+    #[test]
+    fn test_fixed_q64_checked_returns_none_on_overflow() {
+        let two = FixedQ64::from_i64(2);
+
+        assert_eq!(FixedQ64::MAX.checked_add(FixedQ64::ONE), None);
+        assert_eq!(FixedQ64::MIN.checked_sub(FixedQ64::ONE), None);
+        assert_eq!(FixedQ64::MAX.checked_mul(two), None);
+        assert_eq!(FixedQ64::ONE.checked_div(FixedQ64::ZERO), None);
+        assert_eq!(FixedQ64::MAX.checked_div(FixedQ64::from_raw(1)), None);
+        assert_eq!(FixedQ64::MIN.checked_neg(), None);
+        assert_eq!(FixedQ64::MIN.checked_abs(), None);
+
+        // -2^63 is representable, 2^63 is not
+        let half_min = FixedQ64::from_i64(i64::MIN / 2);
+        assert_eq!(half_min.checked_mul(two), Some(FixedQ64::MIN));
+        assert_eq!(half_min.checked_mul(-two), None);
+    }
+
+    proptest! {
+        #[test]
+        fn test_fixed_q64_distributive(a in bounded(), b in bounded(), c in bounded()) {
+            // a * (b + c) = a * b + a * c, up to one truncation per product
+            let left = a * (b + c);
+            let right = a * b + a * c;
+            prop_assert!((left - right).abs() <= FixedQ64::from_raw(2),
+                "{:?} vs {:?}", left, right);
+        }
+
+        #[test]
+        fn test_fixed_q64_mul_div_sign(a in bounded(), b in bounded()) {
+            // The sign of a product or quotient is the product of the signs
+            let product = a * b;
+            prop_assert!(product == FixedQ64::ZERO || product.signum() == a.signum() * b.signum());
+            prop_assert_eq!((-a) * b, -(a * b));
+            prop_assert_eq!(a * (-b), -(a * b));
+
+            if b != FixedQ64::ZERO {
+                let quotient = a / b;
+                prop_assert!(quotient == FixedQ64::ZERO || quotient.signum() == a.signum() * b.signum());
+                prop_assert_eq!((-a) / b, -quotient);
+            }
+        }
+
+        #[test]
+        fn test_fixed_q64_abs_signum(a in any::<i128>()) {
+            // |a| * signum(a) = a for everything but MIN, whose absolute value saturates
+            let a = FixedQ64::from_raw(a);
+            prop_assume!(a != FixedQ64::MIN);
+            prop_assert_eq!(a.abs() * a.signum(), a);
+            prop_assert!(a.abs() >= FixedQ64::ZERO);
+        }
+
+        #[test]
+        fn test_fixed_q64_operators_match_checked(a in any::<i128>(), b in any::<i128>()) {
+            // Whenever the checked operation succeeds the operator gives the same value
+            let (a, b) = (FixedQ64::from_raw(a), FixedQ64::from_raw(b));
+            if let Some(sum) = a.checked_add(b) { prop_assert_eq!(a + b, sum); }
+            if let Some(difference) = a.checked_sub(b) { prop_assert_eq!(a - b, difference); }
+            if let Some(product) = a.checked_mul(b) { prop_assert_eq!(a * b, product); }
+            if let Some(quotient) = a.checked_div(b) { prop_assert_eq!(a / b, quotient); }
+        }
+
+        #[test]
+        fn test_fixed_q64_from_i64_round_trip(value in any::<i64>()) {
+            let fixed = FixedQ64::from_i64(value);
+            prop_assert_eq!(fixed.trunc_to_i128(), value as i128);
+            prop_assert_eq!(fixed.to_f64_lossy(), value as f64);
+        }
+    }
+}
@@ -1,7 +1,19 @@
+pub mod client_test;
+pub mod constants_test;
+pub mod factory_config_test;
 pub mod initialize_pool_test;
+pub mod liquidity_histogram_test;
 pub mod math_test;
+pub mod order_book_test;
 pub mod position_test;
+pub mod tick_array_test;
 pub mod tick_bitmap_test;
 pub mod tick_test;
 
+pub mod pause_pool_test;
+pub mod pool_registry_test;
 pub mod pool_test;
+pub mod resize_account_test;
+pub mod swap_multi_hop_test;
+pub mod token_pair_test;
+pub mod update_position_test;
@@ -0,0 +1,669 @@
+use crate::errors::ErrorCode;
+use crate::state::order_book::*;
+
+use anchor_lang::prelude::*;
+use proptest::prelude::*;
+
+const TICK_SIZE: u64 = 100;
+
+fn order_book() -> OrderBook {
+    let mut order_book = OrderBook::default();
+    order_book
+        .initialize(
+            1,
+            Pubkey::new_unique(),
+            TICK_SIZE,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        )
+        .unwrap();
+    order_book
+}
+
+fn place_order_params(side: OrderSide, expires_at: i64) -> PlaceOrderParams {
+    PlaceOrderParams {
+        bump: 1,
+        order_book: Pubkey::new_unique(),
+        owner: Pubkey::new_unique(),
+        id: 0,
+        side,
+        price: 2 * ORDER_PRICE_SCALE as u64,
+        quantity: 1_000,
+        created_at: 1_000,
+        expires_at,
+    }
+}
+
+mod initialize_order_book_tests {
+    use super::*;
+
+    #[test]
+    fn test_initialize_stores_pool_and_tick_size() {
+        let pool_id = Pubkey::new_unique();
+        let mut order_book = OrderBook::default();
+        order_book
+            .initialize(
+                7,
+                pool_id,
+                TICK_SIZE,
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+            )
+            .unwrap();
+
+        assert_eq!(order_book.bump, 7);
+        assert_eq!(order_book.pool_id, pool_id);
+        assert_eq!(order_book.tick_size, TICK_SIZE);
+        assert_eq!(order_book.order_count, 0);
+        assert_eq!(order_book.bid_volume, 0);
+        assert_eq!(order_book.ask_volume, 0);
+    }
+
+    #[test]
+    fn test_zero_tick_size_is_rejected() {
+        let result = OrderBook::default().initialize(
+            1,
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            ErrorCode::InvalidOrderBookTickSize.into()
+        );
+    }
+}
+
+mod place_limit_order_tests {
+    use super::*;
+
+    #[test]
+    fn test_aligned_price_is_accepted() {
+        let order_book = order_book();
+        assert!(order_book.validate_price(TICK_SIZE).is_ok());
+        assert!(order_book.validate_price(37 * TICK_SIZE).is_ok());
+    }
+
+    #[test]
+    fn test_misaligned_price_is_rejected() {
+        let order_book = order_book();
+        assert_eq!(
+            order_book.validate_price(TICK_SIZE + 1).unwrap_err(),
+            ErrorCode::PriceNotAlignedWithTick.into()
+        );
+    }
+
+    #[test]
+    fn test_zero_price_is_rejected() {
+        assert_eq!(
+            order_book().validate_price(0).unwrap_err(),
+            ErrorCode::InvalidPrice.into()
+        );
+    }
+
+    #[test]
+    fn test_order_ids_increment_and_volumes_track_sides() {
+        let mut order_book = order_book();
+
+        assert_eq!(order_book.record_order(OrderSide::Bid, 500).unwrap(), 0);
+        assert_eq!(order_book.record_order(OrderSide::Ask, 300).unwrap(), 1);
+        assert_eq!(order_book.record_order(OrderSide::Bid, 200).unwrap(), 2);
+
+        assert_eq!(order_book.order_count, 3);
+        assert_eq!(order_book.bid_volume, 700);
+        assert_eq!(order_book.ask_volume, 300);
+    }
+
+    #[test]
+    fn test_volume_overflow_is_rejected() {
+        let mut order_book = order_book();
+        order_book.ask_volume = u64::MAX;
+
+        assert_eq!(
+            order_book.record_order(OrderSide::Ask, 1).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+        assert_eq!(order_book.order_count, 0);
+    }
+
+    #[test]
+    fn test_order_initialize_writes_fields() {
+        let params = place_order_params(OrderSide::Ask, 0);
+        let mut order = Order::default();
+        order.initialize(params).unwrap();
+
+        assert_eq!(order.order_book, params.order_book);
+        assert_eq!(order.owner, params.owner);
+        assert_eq!(order.side, OrderSide::Ask);
+        assert_eq!(order.price, params.price);
+        assert_eq!(order.quantity, params.quantity);
+        assert_eq!(order.filled_quantity, 0);
+        assert_eq!(order.created_at, params.created_at);
+        assert_eq!(order.expires_at, 0);
+    }
+
+    #[test]
+    fn test_expired_order_is_rejected() {
+        for expires_at in [999, 1_000] {
+            let result =
+                Order::default().initialize(place_order_params(OrderSide::Bid, expires_at));
+            assert_eq!(result.unwrap_err(), ErrorCode::OrderExpired.into());
+        }
+        assert!(Order::default()
+            .initialize(place_order_params(OrderSide::Bid, 1_001))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_zero_quantity_is_rejected() {
+        let params = PlaceOrderParams {
+            quantity: 0,
+            ..place_order_params(OrderSide::Bid, 0)
+        };
+        assert_eq!(
+            Order::default().initialize(params).unwrap_err(),
+            ErrorCode::ZeroOrderQuantity.into()
+        );
+    }
+
+    #[test]
+    fn test_escrow_amounts() {
+        let price = 2 * ORDER_PRICE_SCALE as u64 + 500_000; // 2.5 token1 per token0
+        assert_eq!(
+            Order::escrow_amount(OrderSide::Bid, price, 1_000).unwrap(),
+            2_500
+        );
+        assert_eq!(
+            Order::escrow_amount(OrderSide::Ask, price, 1_000).unwrap(),
+            1_000
+        );
+        // Fractional quote amounts round up.
+        assert_eq!(Order::escrow_amount(OrderSide::Bid, price, 1).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_bid_escrow_overflow_is_rejected() {
+        assert_eq!(
+            Order::escrow_amount(OrderSide::Bid, u64::MAX, u64::MAX).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+    }
+}
+
+const NOW: i64 = 2_000;
+
+/// Places an order of `quantity` at `price` on `order_book`.
+fn place(order_book: &mut OrderBook, side: OrderSide, price: u64, quantity: u64) -> Order {
+    let id = order_book.record_order(side, quantity).unwrap();
+    let mut order = Order::default();
+    order
+        .initialize(PlaceOrderParams {
+            id,
+            side,
+            price,
+            quantity,
+            ..place_order_params(side, 0)
+        })
+        .unwrap();
+    order
+}
+
+mod execute_match_tests {
+    use super::*;
+
+    #[test]
+    fn test_match_price_is_midpoint_rounded_down_to_tick() {
+        let order_book = order_book();
+        assert_eq!(order_book.match_price(1_000, 1_000).unwrap(), 1_000);
+        assert_eq!(order_book.match_price(1_200, 1_000).unwrap(), 1_100);
+        // Midpoint 1_150 rounds down to 1_100.
+        assert_eq!(order_book.match_price(1_300, 1_000).unwrap(), 1_100);
+        assert_eq!(
+            order_book.match_price(900, 1_000).unwrap_err(),
+            ErrorCode::OrdersDoNotCross.into()
+        );
+    }
+
+    #[test]
+    fn test_partial_fill_leaves_larger_order_open() {
+        let mut order_book = order_book();
+        let scale = ORDER_PRICE_SCALE as u64;
+        let mut bid = place(&mut order_book, OrderSide::Bid, 3 * scale, 1_000);
+        let mut ask = place(&mut order_book, OrderSide::Ask, 2 * scale, 400);
+
+        let fill = order_book.match_orders(&mut bid, &mut ask, NOW).unwrap();
+
+        assert_eq!(fill.price, 2 * scale + scale / 2);
+        assert_eq!(fill.quantity, 400);
+        assert_eq!(fill.quote_amount, 1_000);
+        // The bid escrowed 3 per unit and paid 2.5.
+        assert_eq!(fill.bid_refund, 200);
+        assert_eq!(bid.remaining_quantity(), 600);
+        assert!(!bid.is_filled());
+        assert!(ask.is_filled());
+        assert_eq!(order_book.bid_volume, 600);
+        assert_eq!(order_book.ask_volume, 0);
+    }
+
+    #[test]
+    fn test_fills_release_exactly_the_bid_escrow() {
+        let mut order_book = order_book();
+        // 1.5 token1 per token0: each unit escrows a fractional quote amount.
+        let price = ORDER_PRICE_SCALE as u64 + 500_000;
+        let quantity = 7;
+        let mut bid = place(&mut order_book, OrderSide::Bid, price, quantity);
+        let escrowed = Order::escrow_amount(OrderSide::Bid, price, quantity).unwrap();
+
+        let mut released = 0;
+        for _ in 0..quantity {
+            let mut ask = place(&mut order_book, OrderSide::Ask, price, 1);
+            let fill = order_book.match_orders(&mut bid, &mut ask, NOW).unwrap();
+            released += fill.quote_amount + fill.bid_refund;
+        }
+
+        assert!(bid.is_filled());
+        assert_eq!(released, escrowed);
+        assert_eq!(order_book.bid_volume, 0);
+    }
+
+    #[test]
+    fn test_orders_that_do_not_cross_are_rejected() {
+        let mut order_book = order_book();
+        let mut bid = place(&mut order_book, OrderSide::Bid, 1_000, 10);
+        let mut ask = place(&mut order_book, OrderSide::Ask, 1_100, 10);
+
+        assert_eq!(
+            order_book
+                .match_orders(&mut bid, &mut ask, NOW)
+                .unwrap_err(),
+            ErrorCode::OrdersDoNotCross.into()
+        );
+        assert_eq!(bid.filled_quantity, 0);
+        assert_eq!(ask.filled_quantity, 0);
+    }
+
+    #[test]
+    fn test_orders_on_the_wrong_side_are_rejected() {
+        let mut order_book = order_book();
+        let mut bid = place(&mut order_book, OrderSide::Bid, 1_000, 10);
+        let mut other_bid = place(&mut order_book, OrderSide::Bid, 1_000, 10);
+
+        assert_eq!(
+            order_book
+                .match_orders(&mut bid, &mut other_bid, NOW)
+                .unwrap_err(),
+            ErrorCode::InvalidOrderSide.into()
+        );
+    }
+
+    #[test]
+    fn test_expired_orders_are_rejected() {
+        let mut order_book = order_book();
+        let mut bid = place(&mut order_book, OrderSide::Bid, 1_000, 10);
+        let mut ask = place(&mut order_book, OrderSide::Ask, 1_000, 10);
+        ask.expires_at = NOW - 1;
+
+        assert_eq!(
+            order_book
+                .match_orders(&mut bid, &mut ask, NOW)
+                .unwrap_err(),
+            ErrorCode::OrderExpired.into()
+        );
+
+        ask.expires_at = NOW;
+        assert!(order_book.match_orders(&mut bid, &mut ask, NOW).is_ok());
+    }
+}
+
+mod cancel_order_tests {
+    use super::*;
+
+    #[test]
+    fn test_owner_cancel_refunds_escrow_and_clears_volume() {
+        let mut order_book = order_book();
+        let scale = ORDER_PRICE_SCALE as u64;
+        let bid = place(&mut order_book, OrderSide::Bid, 3 * scale, 1_000);
+        let ask = place(&mut order_book, OrderSide::Ask, 4 * scale, 500);
+
+        assert_eq!(
+            order_book.cancel_order(&bid, &bid.owner, NOW).unwrap(),
+            3_000
+        );
+        assert_eq!(order_book.bid_volume, 0);
+        assert_eq!(order_book.ask_volume, 500);
+
+        assert_eq!(order_book.cancel_order(&ask, &ask.owner, NOW).unwrap(), 500);
+        assert_eq!(order_book.ask_volume, 0);
+    }
+
+    #[test]
+    fn test_cancel_after_partial_fills_refunds_exactly_the_rest_of_the_escrow() {
+        let mut order_book = order_book();
+        // 1.5 token1 per token0: each unit escrows a fractional quote amount.
+        let price = ORDER_PRICE_SCALE as u64 + 500_000;
+        let quantity = 7;
+        let mut bid = place(&mut order_book, OrderSide::Bid, price, quantity);
+        let escrowed = Order::escrow_amount(OrderSide::Bid, price, quantity).unwrap();
+
+        let mut released = 0;
+        for _ in 0..3 {
+            let mut ask = place(&mut order_book, OrderSide::Ask, price, 1);
+            let fill = order_book.match_orders(&mut bid, &mut ask, NOW).unwrap();
+            released += fill.quote_amount + fill.bid_refund;
+        }
+        let refund = order_book.cancel_order(&bid, &bid.owner, NOW).unwrap();
+
+        assert_eq!(released + refund, escrowed);
+        assert_eq!(order_book.bid_volume, 0);
+    }
+
+    #[test]
+    fn test_others_cannot_cancel_an_open_order() {
+        let mut order_book = order_book();
+        let mut ask = place(&mut order_book, OrderSide::Ask, 1_000, 10);
+        ask.expires_at = NOW;
+
+        assert_eq!(
+            order_book
+                .cancel_order(&ask, &Pubkey::new_unique(), NOW)
+                .unwrap_err(),
+            ErrorCode::UnauthorizedAccess.into()
+        );
+        assert_eq!(order_book.ask_volume, 10);
+
+        ask.expires_at = 0;
+        assert_eq!(
+            order_book
+                .cancel_order(&ask, &Pubkey::new_unique(), NOW)
+                .unwrap_err(),
+            ErrorCode::UnauthorizedAccess.into()
+        );
+    }
+
+    #[test]
+    fn test_anyone_can_cancel_an_expired_order() {
+        let mut order_book = order_book();
+        let mut ask = place(&mut order_book, OrderSide::Ask, 1_000, 10);
+        ask.expires_at = NOW - 1;
+
+        assert_eq!(
+            order_book
+                .cancel_order(&ask, &Pubkey::new_unique(), NOW)
+                .unwrap(),
+            10
+        );
+        assert_eq!(order_book.ask_volume, 0);
+    }
+
+    #[test]
+    fn test_filled_order_cannot_be_cancelled() {
+        let mut order_book = order_book();
+        let mut bid = place(&mut order_book, OrderSide::Bid, 1_000, 10);
+        let mut ask = place(&mut order_book, OrderSide::Ask, 1_000, 10);
+        order_book.match_orders(&mut bid, &mut ask, NOW).unwrap();
+
+        assert_eq!(
+            order_book.cancel_order(&ask, &ask.owner, NOW).unwrap_err(),
+            ErrorCode::OrderAlreadyFilled.into()
+        );
+    }
+}
+
+mod match_batch_tests {
+    use super::*;
+
+    /// Borrows every order of `orders` for `OrderBook::match_batch`.
+    fn refs(orders: &mut [Order]) -> Vec<&mut Order> {
+        orders.iter_mut().collect()
+    }
+
+    /// `(bid id, ask id, price, quantity)` of each fill, in the order they were made.
+    fn fill_ids(bids: &[Order], asks: &[Order], fills: &[BatchFill]) -> Vec<(u64, u64, u64, u64)> {
+        fills
+            .iter()
+            .map(|batch_fill| {
+                (
+                    bids[batch_fill.bid_index].id,
+                    asks[batch_fill.ask_index].id,
+                    batch_fill.fill.price,
+                    batch_fill.fill.quantity,
+                )
+            })
+            .collect()
+    }
+
+    /// An order of the reference matcher.
+    struct ReferenceOrder {
+        id: u64,
+        side: OrderSide,
+        price: u64,
+        remaining: u64,
+        expired: bool,
+    }
+
+    /// Matches `orders` the slow way: scans the whole book for the best open bid and ask
+    /// before every fill, and stops once they no longer cross. Returns the fills as
+    /// `(bid id, ask id, price, quantity)`.
+    fn reference_match(orders: &mut [ReferenceOrder]) -> Vec<(u64, u64, u64, u64)> {
+        let mut fills = Vec::new();
+        loop {
+            let open = |order: &&ReferenceOrder, side| {
+                order.side == side && order.remaining > 0 && !order.expired
+            };
+            let best_bid = orders
+                .iter()
+                .filter(|order| open(order, OrderSide::Bid))
+                .max_by_key(|order| (order.price, std::cmp::Reverse(order.id)))
+                .map(|order| order.id);
+            let best_ask = orders
+                .iter()
+                .filter(|order| open(order, OrderSide::Ask))
+                .min_by_key(|order| (order.price, order.id))
+                .map(|order| order.id);
+            let (Some(bid_id), Some(ask_id)) = (best_bid, best_ask) else {
+                break;
+            };
+            let (bid_price, bid_remaining) = (
+                orders[bid_id as usize].price,
+                orders[bid_id as usize].remaining,
+            );
+            let (ask_price, ask_remaining) = (
+                orders[ask_id as usize].price,
+                orders[ask_id as usize].remaining,
+            );
+            if bid_price < ask_price {
+                break;
+            }
+            let price = (bid_price + ask_price) / 2 / TICK_SIZE * TICK_SIZE;
+            let quantity = bid_remaining.min(ask_remaining);
+            orders[bid_id as usize].remaining -= quantity;
+            orders[ask_id as usize].remaining -= quantity;
+            fills.push((bid_id, ask_id, price, quantity));
+        }
+        fills
+    }
+
+    #[test]
+    fn test_batch_fills_best_orders_first() {
+        let mut order_book = order_book();
+        let mut bids = vec![
+            place(&mut order_book, OrderSide::Bid, 1_300, 10),
+            place(&mut order_book, OrderSide::Bid, 1_100, 10),
+        ];
+        let mut asks = vec![
+            place(&mut order_book, OrderSide::Ask, 1_000, 15),
+            place(&mut order_book, OrderSide::Ask, 1_200, 10),
+        ];
+
+        let fills = order_book
+            .match_batch(&mut refs(&mut bids), &mut refs(&mut asks), 10, NOW)
+            .unwrap();
+
+        // The best bid fills against the best ask, then what is left of the ask fills
+        // against the next bid. The second ask does not cross the second bid.
+        assert_eq!(
+            fill_ids(&bids, &asks, &fills),
+            vec![(0, 2, 1_100, 10), (1, 2, 1_000, 5)]
+        );
+        assert!(bids[0].is_filled() && asks[0].is_filled());
+        assert_eq!(bids[1].remaining_quantity(), 5);
+        assert_eq!(asks[1].remaining_quantity(), 10);
+        assert_eq!(order_book.bid_volume, 5);
+        assert_eq!(order_book.ask_volume, 10);
+    }
+
+    #[test]
+    fn test_batch_stops_at_max_matches_and_resumes() {
+        let mut order_book = order_book();
+        let mut bids: Vec<Order> = (0..3)
+            .map(|_| place(&mut order_book, OrderSide::Bid, 1_000, 10))
+            .collect();
+        let mut asks = vec![place(&mut order_book, OrderSide::Ask, 1_000, 30)];
+
+        let fills = order_book
+            .match_batch(&mut refs(&mut bids), &mut refs(&mut asks), 2, NOW)
+            .unwrap();
+        assert_eq!(fills.len(), 2);
+        assert!(bids[0].is_filled() && bids[1].is_filled());
+        assert_eq!(bids[2].filled_quantity, 0);
+        assert_eq!(asks[0].remaining_quantity(), 10);
+        assert_eq!(order_book.bid_volume, 10);
+        assert_eq!(order_book.ask_volume, 10);
+
+        // The next batch skips the filled bids and picks up from the partly filled ask
+        let fills = order_book
+            .match_batch(&mut refs(&mut bids), &mut refs(&mut asks), 2, NOW)
+            .unwrap();
+        assert_eq!(fill_ids(&bids, &asks, &fills), vec![(2, 3, 1_000, 10)]);
+        assert_eq!(order_book.bid_volume, 0);
+        assert_eq!(order_book.ask_volume, 0);
+    }
+
+    #[test]
+    fn test_batch_skips_expired_orders() {
+        let mut order_book = order_book();
+        let mut bids = vec![
+            place(&mut order_book, OrderSide::Bid, 1_200, 10),
+            place(&mut order_book, OrderSide::Bid, 1_000, 10),
+        ];
+        bids[0].expires_at = NOW - 1;
+        let mut asks = vec![place(&mut order_book, OrderSide::Ask, 1_000, 10)];
+
+        let fills = order_book
+            .match_batch(&mut refs(&mut bids), &mut refs(&mut asks), 10, NOW)
+            .unwrap();
+
+        assert_eq!(fill_ids(&bids, &asks, &fills), vec![(1, 2, 1_000, 10)]);
+        assert_eq!(bids[0].filled_quantity, 0);
+        assert_eq!(order_book.bid_volume, 10);
+    }
+
+    #[test]
+    fn test_batch_out_of_priority_order_is_rejected() {
+        let mut order_book = order_book();
+        let mut bids = vec![
+            place(&mut order_book, OrderSide::Bid, 1_000, 10),
+            place(&mut order_book, OrderSide::Bid, 1_100, 10),
+        ];
+        let mut asks = vec![place(&mut order_book, OrderSide::Ask, 1_000, 10)];
+        let result = order_book.match_batch(&mut refs(&mut bids), &mut refs(&mut asks), 10, NOW);
+        assert_eq!(result.unwrap_err(), ErrorCode::OrderBatchNotSorted.into());
+
+        // At the same price, the older order goes first
+        bids[0].price = 1_100;
+        bids.swap(0, 1);
+        let result = order_book.match_batch(&mut refs(&mut bids), &mut refs(&mut asks), 10, NOW);
+        assert_eq!(result.unwrap_err(), ErrorCode::OrderBatchNotSorted.into());
+        assert_eq!(order_book.bid_volume, 20);
+    }
+
+    #[test]
+    fn test_batch_with_order_passed_twice_is_rejected() {
+        let mut order_book = order_book();
+        let mut bid = place(&mut order_book, OrderSide::Bid, 1_000, 10);
+        let mut bid_copy = place(&mut order_book, OrderSide::Bid, 1_000, 10);
+        bid_copy.id = bid.id;
+        let mut ask = place(&mut order_book, OrderSide::Ask, 1_000, 20);
+
+        let result =
+            order_book.match_batch(&mut [&mut bid, &mut bid_copy], &mut [&mut ask], 10, NOW);
+        assert_eq!(result.unwrap_err(), ErrorCode::OrderBatchNotSorted.into());
+    }
+
+    #[test]
+    fn test_batch_with_order_on_the_wrong_side_is_rejected() {
+        let mut order_book = order_book();
+        let mut bid = place(&mut order_book, OrderSide::Bid, 1_000, 10);
+        let mut other_bid = place(&mut order_book, OrderSide::Bid, 1_000, 10);
+
+        let result = order_book.match_batch(&mut [&mut bid], &mut [&mut other_bid], 10, NOW);
+        assert_eq!(result.unwrap_err(), ErrorCode::InvalidOrderSide.into());
+    }
+
+    proptest! {
+        #[test]
+        fn test_batches_match_like_reference_matcher(
+            flow in prop::collection::vec(
+                (any::<bool>(), 1..=20u64, 1..=1_000u64, prop::bool::weighted(0.1)),
+                1..24,
+            ),
+            max_matches in 1..5usize,
+        ) {
+            // Place the order flow on the book, and on the reference matcher
+            let mut order_book = order_book();
+            let mut orders = Vec::new();
+            let mut reference = Vec::new();
+            for (is_bid, price_ticks, quantity, expired) in flow {
+                let side = if is_bid { OrderSide::Bid } else { OrderSide::Ask };
+                let price = price_ticks * TICK_SIZE;
+                let mut order = place(&mut order_book, side, price, quantity);
+                if expired {
+                    order.expires_at = NOW - 1;
+                }
+                reference.push(ReferenceOrder {
+                    id: order.id,
+                    side,
+                    price,
+                    remaining: quantity,
+                    expired,
+                });
+                orders.push(order);
+            }
+            let expected_fills = reference_match(&mut reference);
+
+            // Crank batches of `max_matches` until no fill is left, passing each side best
+            // first and dropping the orders a batch filled, as their accounts are closed
+            let (mut bids, mut asks): (Vec<Order>, Vec<Order>) =
+                orders.into_iter().partition(|order| order.side == OrderSide::Bid);
+            bids.sort_by_key(|order| (std::cmp::Reverse(order.price), order.id));
+            asks.sort_by_key(|order| (order.price, order.id));
+            let mut fills = Vec::new();
+            loop {
+                let batch = order_book
+                    .match_batch(&mut refs(&mut bids), &mut refs(&mut asks), max_matches, NOW)
+                    .unwrap();
+                prop_assert!(batch.len() <= max_matches);
+                fills.extend(fill_ids(&bids, &asks, &batch));
+
+                // The book's volumes always add up to the open orders, wherever it stopped
+                let volume = |orders: &[Order]| -> u64 {
+                    orders.iter().map(Order::remaining_quantity).sum()
+                };
+                prop_assert_eq!(order_book.bid_volume, volume(&bids));
+                prop_assert_eq!(order_book.ask_volume, volume(&asks));
+
+                bids.retain(|order| !order.is_filled());
+                asks.retain(|order| !order.is_filled());
+                if batch.len() < max_matches {
+                    break;
+                }
+            }
+
+            prop_assert_eq!(fills, expected_fills);
+            for order in bids.iter().chain(&asks) {
+                prop_assert_eq!(order.remaining_quantity(), reference[order.id as usize].remaining);
+            }
+        }
+    }
+}
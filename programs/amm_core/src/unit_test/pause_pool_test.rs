@@ -0,0 +1,283 @@
+use crate::errors::ErrorCode;
+use crate::instructions::collect_fees::collect;
+use crate::instructions::decrease_liquidity::withdraw;
+use crate::instructions::swap_multi_hop::{swap_hop, HopParams};
+use crate::instructions::{pause_pool, unpause_pool};
+use crate::math;
+use crate::position::PositionData;
+use crate::state::factory_config::FactoryConfig;
+use crate::state::pool::{InitializePoolParams, Pool};
+use crate::tick_array::{PositionTicks, SwapTickAccounts};
+use crate::unit_test::tick_array_test::tick_loader;
+use crate::{SetPoolPaused, SetPoolPausedBumps};
+
+use anchor_lang::prelude::*;
+use std::collections::BTreeSet;
+
+const TICK_LOWER: i32 = -600;
+const TICK_UPPER: i32 = 600;
+const LIQUIDITY: u128 = 1 << 64;
+
+fn leak_account(
+    key: Pubkey,
+    is_signer: bool,
+    owner: &Pubkey,
+    data: Vec<u8>,
+) -> AccountInfo<'static> {
+    AccountInfo::new(
+        Box::leak(Box::new(key)),
+        is_signer,
+        true,
+        Box::leak(Box::new(1_000_000_000u64)),
+        Box::leak(data.into_boxed_slice()),
+        Box::leak(Box::new(*owner)),
+        false,
+        0,
+    )
+}
+
+fn program_account<T: AccountSerialize>(account: &T) -> &'static AccountInfo<'static> {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    Box::leak(Box::new(leak_account(
+        Pubkey::new_unique(),
+        false,
+        &crate::ID,
+        data,
+    )))
+}
+
+/// Pool at tick 0 owned by `authority` and `factory`.
+fn create_pool(authority: Pubkey, factory: Pubkey) -> Pool {
+    let mut pool = Pool::default();
+    pool.initialize(InitializePoolParams {
+        bump: 1,
+        factory,
+        authority,
+        token0_mint: Pubkey::new_unique(),
+        token1_mint: Pubkey::new_unique(),
+        token0_vault: Pubkey::new_unique(),
+        token1_vault: Pubkey::new_unique(),
+        token_pair: Pubkey::new_unique(),
+        initial_sqrt_price_q64: 1u128 << 64,
+        fee_rate: 30,
+        protocol_fee: 0,
+        immutable_parameters: false,
+        tick_spacing: 60,
+        timestamp: 0,
+    })
+    .unwrap();
+    pool
+}
+
+/// The factory config account, administered by `authority`.
+fn factory_config_account(authority: Pubkey) -> AccountInfo<'static> {
+    let (key, bump) = Pubkey::find_program_address(&[b"factory_config"], &crate::ID);
+    let config = FactoryConfig {
+        bump,
+        authority,
+        ..FactoryConfig::default()
+    };
+    let mut data = Vec::new();
+    config.try_serialize(&mut data).unwrap();
+    leak_account(key, false, &crate::ID, data)
+}
+
+/// Resolves the `SetPoolPaused` accounts for a pool created with `pool_authority`, whose
+/// factory config is now administered by `config_authority`, signed (or not) by `signer`.
+fn resolve_set_pool_paused(
+    pool_authority: Pubkey,
+    config_authority: Pubkey,
+    signer: Pubkey,
+    is_signer: bool,
+) -> Result<SetPoolPaused<'static>> {
+    let factory = Pubkey::find_program_address(&[b"factory_config"], &crate::ID).0;
+    let accounts: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+        program_account(&create_pool(pool_authority, factory)).clone(),
+        factory_config_account(config_authority),
+        leak_account(signer, is_signer, &System::id(), Vec::new()),
+    ]));
+    SetPoolPaused::try_accounts(
+        &crate::ID,
+        &mut &accounts[..],
+        &[],
+        &mut SetPoolPausedBumps {},
+        &mut BTreeSet::new(),
+    )
+}
+
+/// A swap of token0 for token1 that stays inside the position's range.
+fn swap(pool: &mut Pool) -> Result<u128> {
+    let hop = HopParams {
+        pool: Pubkey::new_unique(),
+        zero_for_one: true,
+        tick_accounts: 0,
+        sqrt_price_limit_q64: math::tick_to_sqrt_price_q64(TICK_LOWER / 2).unwrap(),
+    };
+    let tick_accounts = SwapTickAccounts {
+        ticks: Vec::new(),
+        tick_arrays: Vec::new(),
+        tick_bitmaps: Vec::new(),
+    };
+    swap_hop(pool, &hop, 1 << 40, &tick_accounts, 0).map(|result| result.amount_out)
+}
+
+mod pause_accounts_tests {
+    use super::*;
+
+    #[test]
+    fn test_only_factory_config_authority_can_pause() {
+        let authority = Pubkey::new_unique();
+        assert!(resolve_set_pool_paused(authority, authority, authority, true).is_ok());
+        assert_eq!(
+            resolve_set_pool_paused(authority, authority, Pubkey::new_unique(), true).err(),
+            Some(ErrorCode::UnauthorizedAccess.into())
+        );
+
+        // The authority recorded on the pool does not count once the config's authority changes
+        let (creator, governance) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(resolve_set_pool_paused(creator, governance, governance, true).is_ok());
+        assert_eq!(
+            resolve_set_pool_paused(creator, governance, creator, true).err(),
+            Some(ErrorCode::UnauthorizedAccess.into())
+        );
+    }
+
+    #[test]
+    fn test_pause_requires_signature() {
+        let authority = Pubkey::new_unique();
+        assert_eq!(
+            resolve_set_pool_paused(authority, authority, authority, false).err(),
+            Some(anchor_lang::error::ErrorCode::AccountNotSigner.into())
+        );
+    }
+
+    #[test]
+    fn test_pause_and_unpause_handlers_flip_flag() {
+        let authority = Pubkey::new_unique();
+        let mut accounts = resolve_set_pool_paused(authority, authority, authority, true).unwrap();
+        assert!(!accounts.pool.is_paused);
+
+        pause_pool::handler(Context::new(
+            &crate::ID,
+            &mut accounts,
+            &[],
+            SetPoolPausedBumps {},
+        ))
+        .unwrap();
+        assert!(accounts.pool.is_paused);
+        assert_eq!(
+            accounts.pool.ensure_not_paused().unwrap_err(),
+            ErrorCode::PoolPaused.into()
+        );
+
+        unpause_pool::handler(Context::new(
+            &crate::ID,
+            &mut accounts,
+            &[],
+            SetPoolPausedBumps {},
+        ))
+        .unwrap();
+        assert!(!accounts.pool.is_paused);
+        assert!(accounts.pool.ensure_not_paused().is_ok());
+    }
+}
+
+mod paused_pool_tests {
+    use super::*;
+
+    /// A pool with one position of `LIQUIDITY` over `TICK_LOWER..TICK_UPPER` that has
+    /// earned fees from a swap.
+    struct PoolWithPosition {
+        pool: Account<'static, Pool>,
+        position: Account<'static, PositionData>,
+        tick_lower: AccountLoader<'static, crate::tick::TickData>,
+        tick_upper: AccountLoader<'static, crate::tick::TickData>,
+    }
+
+    impl PoolWithPosition {
+        fn new() -> Self {
+            let mut pool: Account<Pool> = Account::try_from(program_account(&create_pool(
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+            )))
+            .unwrap();
+            let mut position_data = PositionData::default();
+            position_data
+                .initialize(
+                    Pubkey::new_unique(),
+                    pool.key(),
+                    TICK_LOWER,
+                    TICK_UPPER,
+                    LIQUIDITY,
+                )
+                .unwrap();
+            let position = Account::try_from(program_account(&position_data)).unwrap();
+            let tick_lower = tick_loader(&pool.key(), TICK_LOWER);
+            let tick_upper = tick_loader(&pool.key(), TICK_UPPER);
+            pool.modify_liquidity(
+                TICK_LOWER,
+                TICK_UPPER,
+                LIQUIDITY as i128,
+                &tick_lower,
+                &tick_upper,
+            )
+            .unwrap();
+            assert_eq!(pool.liquidity, LIQUIDITY);
+            swap(&mut pool).unwrap();
+
+            Self {
+                pool,
+                position,
+                tick_lower,
+                tick_upper,
+            }
+        }
+    }
+
+    #[test]
+    fn test_paused_pool_rejects_swaps() {
+        let mut state = PoolWithPosition::new();
+        state.pool.is_paused = true;
+        let sqrt_price_q64 = state.pool.sqrt_price_q64;
+
+        assert_eq!(
+            swap(&mut state.pool).unwrap_err(),
+            ErrorCode::PoolPaused.into()
+        );
+        assert_eq!(state.pool.sqrt_price_q64, sqrt_price_q64);
+    }
+
+    #[test]
+    fn test_paused_pool_allows_fee_collection_and_withdrawal() {
+        let mut state = PoolWithPosition::new();
+        state.pool.is_paused = true;
+        let ticks = PositionTicks::Single {
+            lower: &state.tick_lower,
+            upper: &state.tick_upper,
+        };
+
+        let (amount_0, amount_1) =
+            collect(&state.pool, &mut state.position, &ticks, u64::MAX, u64::MAX).unwrap();
+        assert!(amount_0 > 0);
+        assert_eq!(amount_1, 0);
+
+        let (amount_0, amount_1) =
+            withdraw(&mut state.pool, &mut state.position, &ticks, &[], LIQUIDITY).unwrap();
+        assert!(amount_0 > 0 && amount_1 > 0);
+        assert_eq!(state.position.liquidity, 0);
+        assert_eq!(state.pool.liquidity, 0);
+    }
+
+    #[test]
+    fn test_unpaused_pool_accepts_swaps_again() {
+        let mut state = PoolWithPosition::new();
+        state.pool.is_paused = true;
+        assert!(swap(&mut state.pool).is_err());
+
+        state.pool.is_paused = false;
+        let sqrt_price_q64 = state.pool.sqrt_price_q64;
+        assert!(swap(&mut state.pool).unwrap() > 0);
+        assert!(state.pool.sqrt_price_q64 < sqrt_price_q64);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::errors::ErrorCode;
+use crate::state::pool_registry::{PoolRegistry, MAX_FEE_TIERS_PER_PAIR};
+
+use anchor_lang::prelude::*;
+
+mod pool_registry_tests {
+    use super::*;
+
+    #[test]
+    fn test_register_initializes_registry() {
+        let mut registry = PoolRegistry::default();
+        let (mint_a, mint_b, pool) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+
+        registry.register(254, mint_a, mint_b, 30, pool).unwrap();
+
+        assert_eq!(registry.bump, 254);
+        assert_eq!(registry.mint_a, mint_a);
+        assert_eq!(registry.mint_b, mint_b);
+        assert_eq!(registry.pool_for_fee_rate(30), Some(pool));
+    }
+
+    #[test]
+    fn test_multiple_fee_tiers_coexist() {
+        let mut registry = PoolRegistry::default();
+        let (mint_a, mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let low_tier_pool = Pubkey::new_unique();
+        let high_tier_pool = Pubkey::new_unique();
+
+        registry
+            .register(1, mint_a, mint_b, 5, low_tier_pool)
+            .unwrap();
+        registry
+            .register(1, mint_a, mint_b, 30, high_tier_pool)
+            .unwrap();
+
+        assert_eq!(registry.entries.len(), 2);
+        assert_eq!(registry.pool_for_fee_rate(5), Some(low_tier_pool));
+        assert_eq!(registry.pool_for_fee_rate(30), Some(high_tier_pool));
+        assert_eq!(registry.pool_for_fee_rate(100), None);
+    }
+
+    #[test]
+    fn test_register_duplicate_fee_tier_fails() {
+        let mut registry = PoolRegistry::default();
+        let (mint_a, mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        registry
+            .register(1, mint_a, mint_b, 30, Pubkey::new_unique())
+            .unwrap();
+
+        let result = registry.register(1, mint_a, mint_b, 30, Pubkey::new_unique());
+        assert_eq!(
+            result.unwrap_err(),
+            ErrorCode::FeeTierAlreadyRegistered.into()
+        );
+    }
+
+    #[test]
+    fn test_register_fails_when_full() {
+        let mut registry = PoolRegistry::default();
+        let (mint_a, mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        for fee_rate in 0..MAX_FEE_TIERS_PER_PAIR as u16 {
+            registry
+                .register(1, mint_a, mint_b, fee_rate, Pubkey::new_unique())
+                .unwrap();
+        }
+
+        let result = registry.register(1, mint_a, mint_b, 1000, Pubkey::new_unique());
+        assert_eq!(result.unwrap_err(), ErrorCode::PoolRegistryFull.into());
+    }
+
+    #[test]
+    fn test_full_registry_fits_in_account_space() {
+        let mut registry = PoolRegistry::default();
+        let (mint_a, mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        for fee_rate in 0..MAX_FEE_TIERS_PER_PAIR as u16 {
+            registry
+                .register(1, mint_a, mint_b, fee_rate, Pubkey::new_unique())
+                .unwrap();
+        }
+
+        let serialized = borsh::to_vec(&registry).unwrap();
+        assert_eq!(serialized.len() + 8, PoolRegistry::LEN);
+    }
+}
@@ -72,13 +72,18 @@ fn default_initialize_pool_params() -> InitializePoolParams {
     InitializePoolParams {
         bump: 1,
         factory: Pubkey::new_unique(),
+        authority: Pubkey::new_unique(),
         token0_mint: Pubkey::new_unique(),
         token1_mint: Pubkey::new_unique(),
         token0_vault: Pubkey::new_unique(),
         token1_vault: Pubkey::new_unique(),
+        token_pair: Pubkey::new_unique(),
         initial_sqrt_price_q64: float_to_q64(1.0),
         fee_rate: 30, // 0.3%
+        protocol_fee: 0,
+        immutable_parameters: false,
         tick_spacing: 60,
+        timestamp: 0,
     }
 }
 
@@ -424,6 +429,45 @@ mod modify_liquidity_tests {
             }
         }
     }
+
+    #[test]
+    fn test_new_ticks_start_fee_growth_outside_below_current_tick() {
+        let (mut pool, mut tick_lower_acc, mut tick_upper_acc, tl, tu) = setup_pool_and_ticks();
+        pool.fee_growth_global_0_q64 = 500;
+        pool.fee_growth_global_1_q64 = 700;
+
+        pool.modify_liquidity_for_test(tl, tu, 1000, &mut tick_lower_acc, &mut tick_upper_acc)
+            .unwrap();
+
+        // Lower tick is at or below the current tick: all past growth counts as below it
+        assert_eq!(tick_lower_acc.data.fee_growth_outside_0_q64, 500);
+        assert_eq!(tick_lower_acc.data.fee_growth_outside_1_q64, 700);
+        // Upper tick is above the current tick
+        assert_eq!(tick_upper_acc.data.fee_growth_outside_0_q64, 0);
+        assert_eq!(tick_upper_acc.data.fee_growth_outside_1_q64, 0);
+        // So a fresh range has no fee growth inside
+        assert_eq!(
+            pool.fee_growth_inside(tl, &tick_lower_acc, tu, &tick_upper_acc),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn test_fee_growth_outside_kept_for_already_initialized_ticks() {
+        let (mut pool, mut tick_lower_acc, mut tick_upper_acc, tl, tu) = setup_pool_and_ticks();
+        pool.modify_liquidity_for_test(tl, tu, 1000, &mut tick_lower_acc, &mut tick_upper_acc)
+            .unwrap();
+
+        pool.fee_growth_global_0_q64 = 500;
+        pool.modify_liquidity_for_test(tl, tu, 1000, &mut tick_lower_acc, &mut tick_upper_acc)
+            .unwrap();
+
+        assert_eq!(tick_lower_acc.data.fee_growth_outside_0_q64, 0);
+        assert_eq!(
+            pool.fee_growth_inside(tl, &tick_lower_acc, tu, &tick_upper_acc),
+            (500, 0)
+        );
+    }
 }
 
 mod swap_step_tests {
@@ -437,8 +481,8 @@ mod swap_step_tests {
         let liq = float_to_q64(1000.0);
         let gross_in_rem = float_to_q64(100.0);
 
-        let (gross_in, net_out, next_p) = pool
-            .swap_step(cur_p, tar_p, liq, gross_in_rem, pool.fee_rate, true)
+        let (gross_in, net_out, next_p, _) = pool
+            .swap_step(cur_p, tar_p, liq, gross_in_rem, pool.fee_rate, true, true)
             .unwrap();
         assert_eq!(next_p, tar_p);
         assert!(gross_in > 0 && gross_in < gross_in_rem);
@@ -453,8 +497,8 @@ mod swap_step_tests {
         let liq = float_to_q64(1000.0);
         let gross_in_rem = float_to_q64(1.0); // Small input
 
-        let (gross_in, net_out, next_p) = pool
-            .swap_step(cur_p, tar_p, liq, gross_in_rem, pool.fee_rate, true)
+        let (gross_in, net_out, next_p, _) = pool
+            .swap_step(cur_p, tar_p, liq, gross_in_rem, pool.fee_rate, true, true)
             .unwrap();
         assert_eq!(gross_in, gross_in_rem);
         assert!(next_p < cur_p && next_p > tar_p);
@@ -469,8 +513,8 @@ mod swap_step_tests {
         let liq = float_to_q64(1000.0);
         let gross_in_rem = float_to_q64(100.4); // Adjusted to ensure target is reached after 0.3% fee
 
-        let (gross_in, net_out, next_p) = pool
-            .swap_step(cur_p, tar_p, liq, gross_in_rem, pool.fee_rate, false)
+        let (gross_in, net_out, next_p, _) = pool
+            .swap_step(cur_p, tar_p, liq, gross_in_rem, pool.fee_rate, false, true)
             .unwrap();
         assert_q64_approx_eq(
             next_p,
@@ -483,16 +527,97 @@ mod swap_step_tests {
     }
 
     #[test]
-    fn test_swap_step_zero_liquidity() {
+    fn test_swap_step_zero_liquidity_moves_to_target() {
         let pool = create_default_pool();
         let cur_p = float_to_q64(1.0);
         let tar_p = float_to_q64(1.1);
-        let (gross_in, net_out, next_p) = pool
-            .swap_step(cur_p, tar_p, 0, float_to_q64(10.0), pool.fee_rate, false)
+        let (gross_in, net_out, next_p, _) = pool
+            .swap_step(
+                cur_p,
+                tar_p,
+                0,
+                float_to_q64(10.0),
+                pool.fee_rate,
+                false,
+                true,
+            )
             .unwrap();
         assert_eq!(gross_in, 0);
         assert_eq!(net_out, 0);
-        assert_eq!(next_p, cur_p);
+        assert_eq!(next_p, tar_p);
+    }
+
+    #[test]
+    fn test_swap_step_exact_output_satisfied_before_target() {
+        let pool = create_default_pool();
+        let cur_p = float_to_q64(1.1);
+        let tar_p = float_to_q64(1.0);
+        let liq = float_to_q64(1000.0);
+        let out_rem = float_to_q64(1.0); // Small output
+
+        let (gross_in, net_out, next_p, _) = pool
+            .swap_step(cur_p, tar_p, liq, out_rem, pool.fee_rate, true, false)
+            .unwrap();
+        assert_eq!(net_out, out_rem);
+        assert!(next_p < cur_p && next_p > tar_p);
+        assert!(gross_in > 0);
+    }
+
+    #[test]
+    fn test_swap_step_exact_output_reaches_target() {
+        let pool = create_default_pool();
+        let cur_p = float_to_q64(1.0);
+        let tar_p = float_to_q64(1.1);
+        let liq = float_to_q64(1000.0);
+        let out_rem = float_to_q64(1000.0); // More than the step can provide
+
+        let (gross_in, net_out, next_p, _) = pool
+            .swap_step(cur_p, tar_p, liq, out_rem, pool.fee_rate, false, false)
+            .unwrap();
+        assert_eq!(next_p, tar_p);
+        assert!(net_out > 0 && net_out < out_rem);
+        assert!(gross_in > 0);
+    }
+
+    #[test]
+    fn test_swap_step_exact_output_large_input_keeps_fee_precision() {
+        let pool = create_default_pool();
+        let cur_p = float_to_q64(1.0);
+        let tar_p = float_to_q64(2.0);
+        // The input to reach the target is 2^120, so grossing it up by the fee takes a
+        // product above u128::MAX
+        let liq = 1u128 << 120;
+
+        let (gross_in, _, next_p, fee) = pool
+            .swap_step(cur_p, tar_p, liq, u128::MAX, pool.fee_rate, false, false)
+            .unwrap();
+        assert_eq!(next_p, tar_p);
+        let net_in = gross_in - fee;
+        assert_eq!(net_in, 1 << 120);
+        assert_eq!(
+            gross_in,
+            math::mul_div_ceil(
+                net_in,
+                BPS_DENOMINATOR,
+                BPS_DENOMINATOR - pool.fee_rate as u128
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_swap_step_exact_output_at_full_fee_rate_is_rejected() {
+        let pool = create_default_pool();
+        let result = pool.swap_step(
+            float_to_q64(1.0),
+            float_to_q64(1.1),
+            float_to_q64(1000.0),
+            float_to_q64(1.0),
+            MAX_FEE_RATE,
+            false,
+            false,
+        );
+        assert_eq!(result.unwrap_err(), ErrorCode::MathOverflow.into());
     }
 
     proptest! {
@@ -518,9 +643,9 @@ mod swap_step_tests {
             let liq = float_to_q64(liq_f);
             let gross_in_rem = float_to_q64(gross_in_rem_f);
 
-            let res = pool.swap_step(cur_p, tar_p, liq, gross_in_rem, fee_bps, z4o);
+            let res = pool.swap_step(cur_p, tar_p, liq, gross_in_rem, fee_bps, z4o, true);
             prop_assume!(res.is_ok());
-            let (gross_in, net_out, next_p) = res.unwrap();
+            let (gross_in, net_out, next_p, _) = res.unwrap();
 
             prop_assert!(gross_in <= gross_in_rem);
             if liq > 0 && gross_in_rem > 0 && cur_p != tar_p { // if any swap can happen
@@ -604,6 +729,37 @@ mod swap_tests {
         );
     }
 
+    #[test]
+    fn test_swap_price_impact_grows_with_size() {
+        let impact_of = |amount: f64| {
+            let mut pool = setup_pool_for_swap_with_ticks();
+            let sqrt_price_before = pool.sqrt_price_q64;
+            let amount = float_to_q64(amount);
+            pool.swap(
+                true,
+                amount.try_into().unwrap(),
+                float_to_q64(0.999),
+                &Pubkey::new_unique(),
+                &[],
+                0,
+            )
+            .unwrap();
+            let impact = math::price_impact_bps(sqrt_price_before, pool.sqrt_price_q64).unwrap();
+            let sqrt_ratio = q64_to_float(pool.sqrt_price_q64) / q64_to_float(sqrt_price_before);
+            let reference = (1.0 - sqrt_ratio * sqrt_ratio) * 10_000.0;
+            assert!(
+                (impact as f64 - reference).abs() <= 1.0,
+                "impact {impact} reference {reference}"
+            );
+            impact
+        };
+
+        let small = impact_of(1.0);
+        let large = impact_of(10.0);
+        assert!(small > 0);
+        assert!(large > small, "large {large} small {small}");
+    }
+
     #[test]
     fn test_swap_z4o_hits_price_limit() {
         let mut pool = setup_pool_for_swap_with_ticks();
@@ -705,4 +861,2464 @@ mod swap_tests {
             prop_assert_eq!(pool.current_tick, math::sqrt_price_q64_to_tick(pool.sqrt_price_q64).unwrap());
         }
     }
+
+    #[test]
+    fn test_swap_exact_output_zero_for_one() {
+        let mut pool = setup_pool_for_swap_with_ticks();
+        let limit = float_to_q64(0.999);
+        let amount_out = float_to_q64(5.0);
+        let initial_p = pool.sqrt_price_q64;
+        let pool_key = Pubkey::new_unique();
+
+        let (total_in, total_out) = pool
+            .swap(true, -(amount_out as i128), limit, &pool_key, &[], 0)
+            .unwrap();
+        assert_eq!(total_out, amount_out);
+        assert!(total_in > total_out); // Price ~1.0 plus fee
+        assert!(pool.sqrt_price_q64 < initial_p && pool.sqrt_price_q64 >= limit);
+    }
+
+    #[test]
+    fn test_swap_exact_output_stops_at_price_limit() {
+        let mut pool = setup_pool_for_swap_with_ticks();
+        let limit = pool.sqrt_price_q64 - 100;
+        let amount_out = float_to_q64(1000.0);
+        let pool_key = Pubkey::new_unique();
+
+        let (_total_in, total_out) = pool
+            .swap(true, -(amount_out as i128), limit, &pool_key, &[], 0)
+            .unwrap();
+        assert!(total_out < amount_out);
+        assert_eq!(pool.sqrt_price_q64, limit);
+    }
+
+    proptest! {
+        #[test]
+        fn proptest_swap_exact_output_round_trip(
+            amount_f in 0.1f64..5.0, // Small enough to stay clear of the price limit
+            z4o in proptest::bool::ANY,
+        ) {
+            let limit = if z4o { float_to_q64(0.999) } else { float_to_q64(1.001) };
+            let pool_key = Pubkey::new_unique();
+
+            // Exact input first, then ask exact output for what exact input produced.
+            let mut pool_in = setup_pool_for_swap_with_ticks();
+            let amount_in = float_to_q64(amount_f);
+            let (exact_in_consumed, exact_in_out) = pool_in
+                .swap(z4o, amount_in as i128, limit, &pool_key, &[], 0)
+                .unwrap();
+            prop_assume!(exact_in_consumed == amount_in && exact_in_out > 0);
+
+            let mut pool_out = setup_pool_for_swap_with_ticks();
+            let (exact_out_in, exact_out_out) = pool_out
+                .swap(z4o, -(exact_in_out as i128), limit, &pool_key, &[], 0)
+                .unwrap();
+
+            // Exact output delivers the same amount for (up to rounding) the same input.
+            prop_assert_eq!(exact_out_out, exact_in_out);
+            // Both paths round prices in the pool's favour; one Q64.64 ulp of sqrt price
+            // is worth liquidity / 2^64 input units, hence the relative tolerance.
+            prop_assert!(exact_out_in.abs_diff(amount_in) * 1_000_000_000 <= amount_in);
+            prop_assert!(
+                pool_out.sqrt_price_q64.abs_diff(pool_in.sqrt_price_q64) * 1_000_000_000
+                    <= pool_in.sqrt_price_q64
+            );
+        }
+    }
+
+    #[test]
+    fn test_swap_without_tick_accounts_fails_when_crossing() {
+        let mut pool = setup_pool_for_swap_with_ticks();
+        let large_amount = float_to_q64(1_000_000.0) as i128;
+        let limit = math::tick_to_sqrt_price_q64(-120).unwrap();
+        let pool_key = Pubkey::new_unique();
+
+        let result = pool.swap(true, large_amount, limit, &pool_key, &[], 0);
+        assert_eq!(result.unwrap_err(), ErrorCode::MissingTickAccount.into());
+    }
+
+    #[test]
+    fn test_swap_crosses_multiple_ticks_in_order() {
+        let mut pool = setup_pool_for_swap_with_ticks(); // Ticks -60, 60 and 120 initialized
+        let initial_liq = pool.liquidity;
+        let large_amount = float_to_q64(1_000_000.0) as i128;
+        let limit = math::tick_to_sqrt_price_q64(120).unwrap();
+        let liquidity_net = BTreeMap::from([(60, -((initial_liq / 2) as i128)), (120, 0)]);
+
+        let mut crossed = Vec::new();
+        pool.swap_with_tick_source(false, large_amount, limit, |tick_index, _, _| {
+            crossed.push(tick_index);
+            Ok(liquidity_net[&tick_index])
+        })
+        .unwrap();
+
+        assert_eq!(crossed, vec![60, 120]);
+        assert_eq!(pool.liquidity, initial_liq - initial_liq / 2);
+        assert_eq!(pool.sqrt_price_q64, limit);
+        assert_eq!(pool.current_tick, 120);
+    }
+
+    #[test]
+    fn test_swap_down_to_tick_and_back_crosses_it_once_each_way() {
+        let mut pool = setup_pool_for_swap_with_ticks();
+        let initial_liq = pool.liquidity;
+        let large_amount = float_to_q64(1_000_000.0) as i128;
+        let liquidity_net_at_neg_60 = (initial_liq / 4) as i128;
+        let price_at_neg_60 = math::tick_to_sqrt_price_q64(-60).unwrap();
+
+        // Stop exactly on tick -60: it is crossed and its liquidity leaves the active range.
+        let mut crossed = Vec::new();
+        pool.swap_with_tick_source(true, large_amount, price_at_neg_60, |tick_index, _, _| {
+            crossed.push(tick_index);
+            Ok(liquidity_net_at_neg_60)
+        })
+        .unwrap();
+        assert_eq!(crossed, vec![-60]);
+        assert_eq!(pool.sqrt_price_q64, price_at_neg_60);
+        assert_eq!(pool.current_tick, -61);
+        assert_eq!(pool.liquidity, initial_liq - initial_liq / 4);
+
+        // Moving back up re-crosses tick -60 first, even though the price already sits on it.
+        crossed.clear();
+        let limit = math::tick_to_sqrt_price_q64(-30).unwrap();
+        pool.swap_with_tick_source(false, large_amount, limit, |tick_index, _, _| {
+            crossed.push(tick_index);
+            Ok(liquidity_net_at_neg_60)
+        })
+        .unwrap();
+        assert_eq!(crossed, vec![-60]);
+        assert_eq!(pool.liquidity, initial_liq);
+    }
+
+    #[test]
+    fn test_swap_accrues_fee_growth_in_input_token() {
+        let mut pool = setup_pool_for_swap_with_ticks();
+        let limit = float_to_q64(0.999);
+        let amount = float_to_q64(10.0);
+        let liquidity = pool.liquidity;
+
+        let (total_in, _) = pool
+            .swap(true, amount as i128, limit, &Pubkey::new_unique(), &[], 0)
+            .unwrap();
+
+        assert!(pool.fee_growth_global_0_q64 > 0);
+        assert_eq!(pool.fee_growth_global_1_q64, 0);
+        // The growth pays the 0.3% fee back to the active liquidity. Rounding the growth
+        // down loses at most one unit per 2^64 of liquidity.
+        let fees = math::get_fees_earned(liquidity, pool.fee_growth_global_0_q64, 0).unwrap();
+        let expected_fee = total_in - total_in * (10_000 - 30) / 10_000;
+        assert!(fees as u128 <= expected_fee);
+        assert!(expected_fee - fees as u128 <= (liquidity >> 64) + 1);
+    }
+
+    #[test]
+    fn test_swap_passes_fee_growth_to_crossed_ticks() {
+        let mut pool = setup_pool_for_swap_with_ticks();
+        let large_amount = float_to_q64(1_000_000.0) as i128;
+        let limit = math::tick_to_sqrt_price_q64(120).unwrap();
+
+        let mut growth_at_crossings = Vec::new();
+        pool.swap_with_tick_source(false, large_amount, limit, |_, growth_0, growth_1| {
+            growth_at_crossings.push((growth_0, growth_1));
+            Ok(0)
+        })
+        .unwrap();
+
+        // Token1 is the input, so only its growth increases, and it does so between crossings
+        assert_eq!(growth_at_crossings.len(), 2);
+        assert!(growth_at_crossings
+            .iter()
+            .all(|&(growth_0, _)| growth_0 == 0));
+        assert!(growth_at_crossings[0].1 > 0);
+        assert!(growth_at_crossings[1].1 > growth_at_crossings[0].1);
+        assert_eq!(growth_at_crossings[1].1, pool.fee_growth_global_1_q64);
+    }
+
+    /// Gross input and output of an exact input step that reaches `to` from `from`.
+    fn step_to(from: u128, to: u128, liquidity: u128, amount_remaining: u128) -> (u128, u128) {
+        // 30 bps in hundredths of a basis point
+        let step =
+            math::compute_swap_step_u128(from, to, liquidity, amount_remaining, 3_000).unwrap();
+        assert_eq!(step.sqrt_price_next, to);
+        (step.amount_in + step.fee_amount, step.amount_out)
+    }
+
+    /// Swaps token1 in up to the price of `limit_tick` with more input than it takes.
+    /// Returns the crossed ticks and the amounts swapped.
+    fn swap_up_to(
+        pool: &mut Pool,
+        limit_tick: i32,
+        liquidity_net: &BTreeMap<i32, i128>,
+    ) -> (Vec<i32>, u128, u128) {
+        let limit = math::tick_to_sqrt_price_q64(limit_tick).unwrap();
+        let mut crossed = Vec::new();
+        let result = pool
+            .swap_with_tick_source(
+                false,
+                float_to_q64(1_000_000.0) as i128,
+                limit,
+                |tick_index, _, _| {
+                    crossed.push(tick_index);
+                    Ok(liquidity_net[&tick_index])
+                },
+            )
+            .unwrap();
+        assert_eq!(pool.sqrt_price_q64, limit);
+        assert_eq!(pool.current_tick, limit_tick);
+        (crossed, result.amount_in, result.amount_out)
+    }
+
+    #[test]
+    fn test_exact_input_stops_at_limit_before_next_tick() {
+        let mut pool = setup_pool_for_swap_with_ticks();
+        let liquidity = pool.liquidity;
+        let start = pool.sqrt_price_q64;
+
+        let (crossed, total_in, total_out) = swap_up_to(&mut pool, 30, &BTreeMap::new());
+
+        let limit = math::tick_to_sqrt_price_q64(30).unwrap();
+        assert!(crossed.is_empty());
+        assert_eq!(
+            (total_in, total_out),
+            step_to(start, limit, liquidity, float_to_q64(1_000_000.0))
+        );
+        assert_eq!(pool.liquidity, liquidity);
+    }
+
+    #[test]
+    fn test_exact_input_stops_at_limit_after_crossing() {
+        let mut pool = setup_pool_for_swap_with_ticks();
+        let liquidity = pool.liquidity;
+        let start = pool.sqrt_price_q64;
+        let liquidity_net = BTreeMap::from([(60, (liquidity / 2) as i128)]);
+
+        let (crossed, total_in, total_out) = swap_up_to(&mut pool, 90, &liquidity_net);
+
+        // One step to tick 60 over the initial liquidity, then one to the limit, mid-way
+        // to tick 120, over the liquidity tick 60 added.
+        let at_60 = math::tick_to_sqrt_price_q64(60).unwrap();
+        let limit = math::tick_to_sqrt_price_q64(90).unwrap();
+        let amount = float_to_q64(1_000_000.0);
+        let (in_1, out_1) = step_to(start, at_60, liquidity, amount);
+        let (in_2, out_2) = step_to(at_60, limit, liquidity + liquidity / 2, amount - in_1);
+        assert_eq!(crossed, vec![60]);
+        assert_eq!((total_in, total_out), (in_1 + in_2, out_1 + out_2));
+        assert_eq!(pool.liquidity, liquidity + liquidity / 2);
+    }
+
+    #[test]
+    fn test_exact_input_exhausted_before_limit() {
+        let mut pool = setup_pool_for_swap_with_ticks();
+        let limit = math::tick_to_sqrt_price_q64(120).unwrap();
+        let amount = float_to_q64(1.0);
+
+        let result = pool
+            .swap_with_tick_source(false, amount as i128, limit, |_, _, _| {
+                panic!("no tick should be crossed")
+            })
+            .unwrap();
+
+        assert_eq!(result.amount_in, amount);
+        assert!(pool.sqrt_price_q64 > float_to_q64(1.0));
+        assert!(pool.sqrt_price_q64 < math::tick_to_sqrt_price_q64(60).unwrap());
+        assert_eq!(
+            pool.current_tick,
+            math::sqrt_price_q64_to_tick(pool.sqrt_price_q64).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_swap_crosses_zero_liquidity_gap() {
+        let mut pool = setup_pool_for_swap_with_ticks();
+        let liquidity = pool.liquidity;
+        let start = pool.sqrt_price_q64;
+        // All liquidity leaves at tick 60 and returns at tick 120.
+        let liquidity_net = BTreeMap::from([(60, -(liquidity as i128)), (120, liquidity as i128)]);
+
+        let mut growth_at_crossings = Vec::new();
+        let limit = math::tick_to_sqrt_price_q64(180).unwrap();
+        let result = pool
+            .swap_with_tick_source(
+                false,
+                float_to_q64(1_000_000.0) as i128,
+                limit,
+                |tick_index, _, growth_1| {
+                    growth_at_crossings.push((tick_index, growth_1));
+                    Ok(liquidity_net[&tick_index])
+                },
+            )
+            .unwrap();
+
+        // The price jumps the gap for free, so input is only spent on either side of it.
+        let amount = float_to_q64(1_000_000.0);
+        let at_60 = math::tick_to_sqrt_price_q64(60).unwrap();
+        let at_120 = math::tick_to_sqrt_price_q64(120).unwrap();
+        let (in_1, out_1) = step_to(start, at_60, liquidity, amount);
+        let (in_2, out_2) = step_to(at_120, limit, liquidity, amount - in_1);
+        assert_eq!(
+            (result.amount_in, result.amount_out),
+            (in_1 + in_2, out_1 + out_2)
+        );
+        assert_eq!(result.ticks_crossed, 2);
+        assert_eq!(pool.sqrt_price_q64, limit);
+        assert_eq!(pool.current_tick, 180);
+        assert_eq!(pool.liquidity, liquidity);
+
+        // No fees accrue inside the gap.
+        assert_eq!(growth_at_crossings[0].0, 60);
+        assert_eq!(growth_at_crossings[1].0, 120);
+        assert_eq!(growth_at_crossings[0].1, growth_at_crossings[1].1);
+    }
+
+    #[test]
+    fn test_swap_ending_in_zero_liquidity_gap_moves_to_limit() {
+        let mut pool = setup_pool_for_swap_with_ticks();
+        let liquidity = pool.liquidity;
+        let start = pool.sqrt_price_q64;
+        let liquidity_net = BTreeMap::from([(60, -(liquidity as i128))]);
+
+        let (crossed, total_in, total_out) = swap_up_to(&mut pool, 90, &liquidity_net);
+
+        let at_60 = math::tick_to_sqrt_price_q64(60).unwrap();
+        assert_eq!(crossed, vec![60]);
+        assert_eq!(
+            (total_in, total_out),
+            step_to(start, at_60, liquidity, float_to_q64(1_000_000.0))
+        );
+        assert_eq!(pool.liquidity, 0);
+    }
+
+    #[test]
+    fn test_swap_down_into_empty_range_moves_to_limit() {
+        let mut pool = setup_pool_for_swap_with_ticks();
+        let liquidity = pool.liquidity;
+        let limit = math::tick_to_sqrt_price_q64(-600).unwrap();
+
+        // All liquidity sits above tick -60, with no initialized tick below it.
+        let result = pool
+            .swap_with_tick_source(
+                true,
+                float_to_q64(1_000_000.0) as i128,
+                limit,
+                |tick_index, _, _| {
+                    assert_eq!(tick_index, -60);
+                    Ok(liquidity as i128)
+                },
+            )
+            .unwrap();
+
+        assert_eq!(result.ticks_crossed, 1);
+        assert_eq!(pool.liquidity, 0);
+        assert_eq!(pool.sqrt_price_q64, limit);
+        assert_eq!(pool.current_tick, -600);
+    }
+
+    proptest! {
+        #[test]
+        fn proptest_exact_input_reaches_any_limit_across_gap(limit_tick in 1i32..400) {
+            let mut pool = setup_pool_for_swap_with_ticks();
+            let liquidity = pool.liquidity;
+            let liquidity_net =
+                BTreeMap::from([(60, -(liquidity as i128)), (120, liquidity as i128)]);
+
+            let (crossed, total_in, _) = swap_up_to(&mut pool, limit_tick, &liquidity_net);
+
+            let expected_crossed: Vec<i32> =
+                [60, 120].into_iter().filter(|&tick| tick <= limit_tick).collect();
+            prop_assert_eq!(crossed, expected_crossed);
+            let in_gap = (60..120).contains(&limit_tick);
+            prop_assert_eq!(pool.liquidity, if in_gap { 0 } else { liquidity });
+            prop_assert!(total_in > 0);
+        }
+    }
+}
+
+mod fee_growth_inside_tests {
+    use super::*;
+
+    const BACKGROUND_LIQUIDITY: i128 = 1_000_000_000_000_000;
+    const POSITION_LIQUIDITY: i128 = 500_000_000_000_000;
+
+    /// Pool at tick 0 with fee growth already accrued and liquidity on [-600, 600), so
+    /// the price can move anywhere between the ticks used below.
+    fn setup() -> (Pool, BTreeMap<i32, TickData>) {
+        let mut pool = create_default_pool();
+        pool.fee_growth_global_0_q64 = 1_000 << 64;
+        pool.fee_growth_global_1_q64 = 2_000 << 64;
+        let mut ticks = BTreeMap::new();
+        add_liquidity(&mut pool, &mut ticks, -600, 600, BACKGROUND_LIQUIDITY);
+        (pool, ticks)
+    }
+
+    fn add_liquidity(
+        pool: &mut Pool,
+        ticks: &mut BTreeMap<i32, TickData>,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: i128,
+    ) {
+        let mut lower = ticks.remove(&tick_lower).unwrap_or_default();
+        let mut upper = ticks.remove(&tick_upper).unwrap_or_default();
+        pool.modify_liquidity_for_test(
+            tick_lower,
+            tick_upper,
+            liquidity_delta,
+            &mut lower,
+            &mut upper,
+        )
+        .unwrap();
+        ticks.insert(tick_lower, lower);
+        ticks.insert(tick_upper, upper);
+    }
+
+    /// Swaps until the price reaches `tick`, crossing the ticks on the way. Returns the
+    /// crossed ticks with the global fee growth at each crossing.
+    fn swap_to(
+        pool: &mut Pool,
+        ticks: &mut BTreeMap<i32, TickData>,
+        tick: i32,
+    ) -> Vec<(i32, u128, u128)> {
+        let limit = math::tick_to_sqrt_price_q64(tick).unwrap();
+        let zero_for_one = limit < pool.sqrt_price_q64;
+        let mut crossings = Vec::new();
+        pool.swap_with_tick_source(
+            zero_for_one,
+            i64::MAX as i128,
+            limit,
+            |tick_index, growth_0, growth_1| {
+                crossings.push((tick_index, growth_0, growth_1));
+                Ok(ticks
+                    .get_mut(&tick_index)
+                    .unwrap()
+                    .cross(growth_0, growth_1))
+            },
+        )
+        .unwrap();
+        assert_eq!(pool.sqrt_price_q64, limit);
+        crossings
+    }
+
+    fn inside(
+        pool: &Pool,
+        ticks: &BTreeMap<i32, TickData>,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> (u128, u128) {
+        pool.fee_growth_inside(
+            tick_lower,
+            &ticks[&tick_lower],
+            tick_upper,
+            &ticks[&tick_upper],
+        )
+    }
+
+    fn growth_since(pool: &Pool, growth_0: u128, growth_1: u128) -> (u128, u128) {
+        (
+            pool.fee_growth_global_0_q64.wrapping_sub(growth_0),
+            pool.fee_growth_global_1_q64.wrapping_sub(growth_1),
+        )
+    }
+
+    #[test]
+    fn test_range_above_the_price_counts_only_growth_while_inside() {
+        let (mut pool, mut ticks) = setup();
+        // The new ticks are above the price, so their outside growth starts at zero even
+        // though fees accrued before
+        add_liquidity(&mut pool, &mut ticks, 60, 180, POSITION_LIQUIDITY);
+        assert_eq!(ticks[&60].fee_growth_outside_0_q64, 0);
+        assert_eq!(ticks[&180].fee_growth_outside_1_q64, 0);
+
+        // 1. Price below the range: no growth inside, however much accrues
+        swap_to(&mut pool, &mut ticks, -300);
+        swap_to(&mut pool, &mut ticks, 0);
+        assert!(pool.current_tick < 60);
+        assert_eq!(inside(&pool, &ticks, 60, 180), (0, 0));
+
+        // 2. Price inside: the growth since the price entered the range
+        let crossings = swap_to(&mut pool, &mut ticks, 120);
+        assert_eq!(crossings.len(), 1);
+        let (_, enter_0, enter_1) = crossings[0];
+        swap_to(&mut pool, &mut ticks, 90);
+        swap_to(&mut pool, &mut ticks, 150);
+        assert_eq!(
+            inside(&pool, &ticks, 60, 180),
+            growth_since(&pool, enter_0, enter_1)
+        );
+
+        // 3. Price on the upper tick counts as above the range, and growth above it does
+        // not count
+        let crossings = swap_to(&mut pool, &mut ticks, 180);
+        assert_eq!(crossings.len(), 1);
+        let (_, exit_0, exit_1) = crossings[0];
+        assert_eq!(pool.current_tick, 180);
+        let inside_at_exit = (exit_0.wrapping_sub(enter_0), exit_1.wrapping_sub(enter_1));
+        assert_eq!(inside(&pool, &ticks, 60, 180), inside_at_exit);
+        swap_to(&mut pool, &mut ticks, 300);
+        swap_to(&mut pool, &mut ticks, 240);
+        assert_eq!(inside(&pool, &ticks, 60, 180), inside_at_exit);
+
+        // 4. Back below through the range: only the growth while passing through is added
+        let crossings = swap_to(&mut pool, &mut ticks, 0);
+        assert_eq!(
+            crossings
+                .iter()
+                .map(|&(tick, _, _)| tick)
+                .collect::<Vec<_>>(),
+            vec![180, 60]
+        );
+        let (_, reenter_0, reenter_1) = crossings[0];
+        let (_, leave_0, leave_1) = crossings[1];
+        assert!(pool.current_tick < 60);
+        assert_eq!(
+            inside(&pool, &ticks, 60, 180),
+            (
+                inside_at_exit
+                    .0
+                    .wrapping_add(leave_0.wrapping_sub(reenter_0)),
+                inside_at_exit
+                    .1
+                    .wrapping_add(leave_1.wrapping_sub(reenter_1)),
+            )
+        );
+    }
+
+    #[test]
+    fn test_range_below_the_price_counts_only_growth_while_inside() {
+        let (mut pool, mut ticks) = setup();
+        // The new ticks are below the price, so all past growth counts as below them
+        add_liquidity(&mut pool, &mut ticks, -180, -60, POSITION_LIQUIDITY);
+        assert_eq!(
+            ticks[&-60].fee_growth_outside_0_q64,
+            pool.fee_growth_global_0_q64
+        );
+        assert_eq!(inside(&pool, &ticks, -180, -60), (0, 0));
+
+        // 1. Price above the range
+        swap_to(&mut pool, &mut ticks, 300);
+        swap_to(&mut pool, &mut ticks, 0);
+        assert_eq!(inside(&pool, &ticks, -180, -60), (0, 0));
+
+        // 2. Price inside, entering from above
+        let crossings = swap_to(&mut pool, &mut ticks, -120);
+        assert_eq!(crossings.len(), 1);
+        let (_, enter_0, enter_1) = crossings[0];
+        swap_to(&mut pool, &mut ticks, -90);
+        swap_to(&mut pool, &mut ticks, -150);
+        assert_eq!(
+            inside(&pool, &ticks, -180, -60),
+            growth_since(&pool, enter_0, enter_1)
+        );
+
+        // 3. Price below the range
+        let crossings = swap_to(&mut pool, &mut ticks, -300);
+        assert_eq!(crossings.len(), 1);
+        let (_, exit_0, exit_1) = crossings[0];
+        let inside_at_exit = (exit_0.wrapping_sub(enter_0), exit_1.wrapping_sub(enter_1));
+        assert_eq!(inside(&pool, &ticks, -180, -60), inside_at_exit);
+        swap_to(&mut pool, &mut ticks, -240);
+        swap_to(&mut pool, &mut ticks, -360);
+        assert_eq!(inside(&pool, &ticks, -180, -60), inside_at_exit);
+    }
+
+    #[test]
+    fn test_price_on_the_lower_tick_is_on_the_side_it_came_from() {
+        let (mut pool, mut ticks) = setup();
+        add_liquidity(&mut pool, &mut ticks, 60, 180, POSITION_LIQUIDITY);
+
+        // Reached from below, the lower tick is crossed and the range is active
+        let crossings = swap_to(&mut pool, &mut ticks, 60);
+        assert_eq!(crossings.len(), 1);
+        let (_, enter_0, enter_1) = crossings[0];
+        assert_eq!(pool.current_tick, 60);
+        assert_eq!(inside(&pool, &ticks, 60, 180), (0, 0));
+
+        // Reached from above, it is crossed back and the range is inactive
+        swap_to(&mut pool, &mut ticks, 120);
+        let crossings = swap_to(&mut pool, &mut ticks, 60);
+        assert_eq!(crossings.len(), 1);
+        let (_, leave_0, leave_1) = crossings[0];
+        assert_eq!(pool.current_tick, 59);
+        let inside_at_exit = (leave_0.wrapping_sub(enter_0), leave_1.wrapping_sub(enter_1));
+        assert!(inside_at_exit.0 > 0 && inside_at_exit.1 > 0);
+        assert_eq!(inside(&pool, &ticks, 60, 180), inside_at_exit);
+
+        swap_to(&mut pool, &mut ticks, 0);
+        assert_eq!(inside(&pool, &ticks, 60, 180), inside_at_exit);
+    }
+
+    /// Adjacent ranges tiling the background range, so one of them is always active.
+    const ADJACENT_RANGES: [(i32, i32); 3] = [(-600, -180), (-180, 60), (60, 600)];
+
+    proptest! {
+        #[test]
+        fn test_fees_of_adjacent_positions_sum_to_global_growth(
+            targets in prop::collection::vec(-590i32..590, 1..8),
+        ) {
+            let (mut pool, mut ticks) = setup();
+            for (tick_lower, tick_upper) in ADJACENT_RANGES {
+                add_liquidity(&mut pool, &mut ticks, tick_lower, tick_upper, POSITION_LIQUIDITY);
+            }
+            let snapshots: Vec<(u128, u128)> = ADJACENT_RANGES
+                .iter()
+                .map(|&(tick_lower, tick_upper)| inside(&pool, &ticks, tick_lower, tick_upper))
+                .collect();
+            let (global_0, global_1) = (pool.fee_growth_global_0_q64, pool.fee_growth_global_1_q64);
+
+            for target in targets {
+                if math::tick_to_sqrt_price_q64(target).unwrap() == pool.sqrt_price_q64 {
+                    continue;
+                }
+                swap_to(&mut pool, &mut ticks, target);
+            }
+
+            // The growth inside the ranges, wrapping like the counters, adds up exactly
+            let (growth_0, growth_1) = growth_since(&pool, global_0, global_1);
+            let liquidity = POSITION_LIQUIDITY as u128;
+            let mut growth_inside = (0u128, 0u128);
+            let mut fees = (0u128, 0u128);
+            for (&(tick_lower, tick_upper), &(last_0, last_1)) in
+                ADJACENT_RANGES.iter().zip(&snapshots)
+            {
+                let (inside_0, inside_1) = inside(&pool, &ticks, tick_lower, tick_upper);
+                growth_inside.0 = growth_inside.0.wrapping_add(inside_0.wrapping_sub(last_0));
+                growth_inside.1 = growth_inside.1.wrapping_add(inside_1.wrapping_sub(last_1));
+                fees.0 += math::get_fees_earned(liquidity, inside_0, last_0).unwrap() as u128;
+                fees.1 += math::get_fees_earned(liquidity, inside_1, last_1).unwrap() as u128;
+            }
+            prop_assert_eq!(growth_inside, (growth_0, growth_1));
+
+            // and so do the fees, up to rounding each position down
+            let expected_0 = math::get_fees_earned(liquidity, growth_0, 0).unwrap() as u128;
+            let expected_1 = math::get_fees_earned(liquidity, growth_1, 0).unwrap() as u128;
+            let max_rounding = ADJACENT_RANGES.len() as u128;
+            prop_assert!(fees.0 <= expected_0 && expected_0 - fees.0 < max_rounding);
+            prop_assert!(fees.1 <= expected_1 && expected_1 - fees.1 < max_rounding);
+        }
+    }
+}
+
+mod swap_tick_account_tests {
+    use super::*;
+    use crate::tick_bitmap::flip_tick_initialized_status;
+    use anchor_lang::Discriminator;
+
+    const TICK_SPACING: i32 = 60;
+    const LIQUIDITY: u128 = 1 << 80;
+    /// Each initialized tick is the lower tick of a position holding this much liquidity.
+    const TICK_LIQUIDITY_NET: i128 = (LIQUIDITY / 16) as i128;
+
+    /// Pool at tick 0 with initialized ticks at -60, -120, ..., -480 and 60, 120, ..., 480.
+    fn setup_pool_with_many_ticks() -> Pool {
+        let mut pool = create_default_pool();
+        pool.tick_spacing = TICK_SPACING as u16;
+        pool.fee_rate = 30;
+        pool.current_tick = 0;
+        pool.sqrt_price_q64 = math::tick_to_sqrt_price_q64(0).unwrap();
+        pool.liquidity = LIQUIDITY;
+
+        let mut bitmap: BTreeMap<i16, u64> =
+            borsh::BorshDeserialize::try_from_slice(&pool.tick_bitmap_data).unwrap();
+        for k in 1..=8 {
+            for tick_index in [-TICK_SPACING * k, TICK_SPACING * k] {
+                flip_tick_initialized_status(&mut bitmap, tick_index, pool.tick_spacing, true)
+                    .unwrap();
+            }
+        }
+        pool.tick_bitmap_data = borsh::to_vec(&bitmap).unwrap();
+        pool
+    }
+
+    /// Builds a writable `TickData` account for `tick_index` at its PDA under `pool_key`.
+    fn tick_account(pool_key: &Pubkey, tick_index: i32) -> &'static AccountInfo<'static> {
+        // Crossing downwards subtracts liquidity_net, crossing upwards adds it.
+        let liquidity_net = if tick_index < 0 {
+            TICK_LIQUIDITY_NET
+        } else {
+            -TICK_LIQUIDITY_NET
+        };
+        let tick = ActualTickData {
+            liquidity_gross: liquidity_net.unsigned_abs(),
+            liquidity_net,
+            pool: *pool_key,
+            index: tick_index,
+            initialized: 1,
+            ..Default::default()
+        };
+        // Zero-copy loads need the data after the 8-byte discriminator to be 16-byte aligned,
+        // so the buffer is carved out of u128 words starting 8 bytes in.
+        let words: &'static mut [u128] =
+            Box::leak(vec![0u128; 1 + (8 + ActualTickData::LEN) / 16].into_boxed_slice());
+        let data = &mut bytemuck::cast_slice_mut::<u128, u8>(words)[8..];
+        data[..8].copy_from_slice(ActualTickData::DISCRIMINATOR);
+        data[8..].copy_from_slice(bytemuck::bytes_of(&tick));
+
+        let key = Box::leak(Box::new(ActualTickData::address(pool_key, tick_index)));
+        let lamports = Box::leak(Box::new(1_000_000_000u64));
+        Box::leak(Box::new(AccountInfo::new(
+            key,
+            false,
+            true,
+            lamports,
+            data,
+            &crate::ID,
+            false,
+            0,
+        )))
+    }
+
+    fn tick_loaders(
+        pool_key: &Pubkey,
+        tick_indices: &[i32],
+    ) -> Vec<AccountLoader<'static, ActualTickData>> {
+        tick_indices
+            .iter()
+            .map(|&tick_index| AccountLoader::try_from(tick_account(pool_key, tick_index)).unwrap())
+            .collect()
+    }
+
+    /// Swaps token0 for token1 down to just past `crossed` initialized ticks.
+    fn swap_down_crossing(
+        pool: &mut Pool,
+        pool_key: &Pubkey,
+        crossed: i32,
+        loaders: &[AccountLoader<'static, ActualTickData>],
+    ) -> Result<(u128, u128)> {
+        let limit = math::tick_to_sqrt_price_q64(-TICK_SPACING * crossed - 30).unwrap();
+        let loader_refs: Vec<&AccountLoader<ActualTickData>> = loaders.iter().collect();
+        pool.swap(true, 1i128 << 100, limit, pool_key, &loader_refs, 0)
+    }
+
+    fn descending_ticks(count: i32) -> Vec<i32> {
+        (1..=count).map(|k| -TICK_SPACING * k).collect()
+    }
+
+    fn assert_crosses_ticks(crossed: i32) {
+        let mut pool = setup_pool_with_many_ticks();
+        let pool_key = Pubkey::new_unique();
+        let loaders = tick_loaders(&pool_key, &descending_ticks(crossed));
+
+        swap_down_crossing(&mut pool, &pool_key, crossed, &loaders).unwrap();
+
+        assert!(pool.current_tick < -TICK_SPACING * crossed);
+        assert_eq!(
+            pool.liquidity,
+            LIQUIDITY - crossed as u128 * TICK_LIQUIDITY_NET as u128
+        );
+        // Every crossed tick had its fee growth outside flipped to the global value at its
+        // crossing, which grows as the swap moves further down.
+        let mut previous_outside = 0;
+        for loader in &loaders {
+            let outside = loader.load().unwrap().fee_growth_outside_0_q64;
+            assert!(outside > previous_outside);
+            assert!(outside <= pool.fee_growth_global_0_q64);
+            previous_outside = outside;
+        }
+    }
+
+    #[test]
+    fn test_swap_crossing_no_ticks() {
+        assert_crosses_ticks(0);
+    }
+
+    #[test]
+    fn test_swap_crossing_three_ticks() {
+        assert_crosses_ticks(3);
+    }
+
+    #[test]
+    fn test_swap_crossing_eight_ticks() {
+        assert_crosses_ticks(8);
+    }
+
+    #[test]
+    fn test_swap_one_for_zero_crosses_ascending_ticks() {
+        let mut pool = setup_pool_with_many_ticks();
+        let pool_key = Pubkey::new_unique();
+        let ticks: Vec<i32> = (1..=3).map(|k| TICK_SPACING * k).collect();
+        let loaders = tick_loaders(&pool_key, &ticks);
+        let loader_refs: Vec<&AccountLoader<ActualTickData>> = loaders.iter().collect();
+        let limit = math::tick_to_sqrt_price_q64(3 * TICK_SPACING + 30).unwrap();
+
+        pool.swap(false, 1i128 << 100, limit, &pool_key, &loader_refs, 0)
+            .unwrap();
+
+        assert!(pool.current_tick >= 3 * TICK_SPACING);
+        assert_eq!(pool.liquidity, LIQUIDITY - 3 * TICK_LIQUIDITY_NET as u128);
+    }
+
+    #[test]
+    fn test_extra_tick_accounts_are_ignored() {
+        let mut pool = setup_pool_with_many_ticks();
+        let pool_key = Pubkey::new_unique();
+        let loaders = tick_loaders(&pool_key, &descending_ticks(5));
+
+        swap_down_crossing(&mut pool, &pool_key, 3, &loaders).unwrap();
+
+        assert_eq!(pool.liquidity, LIQUIDITY - 3 * TICK_LIQUIDITY_NET as u128);
+        assert_eq!(loaders[3].load().unwrap().fee_growth_outside_0_q64, 0);
+    }
+
+    #[test]
+    fn test_missing_tick_account_names_expected_tick() {
+        let mut pool = setup_pool_with_many_ticks();
+        let pool_key = Pubkey::new_unique();
+        let loaders = tick_loaders(&pool_key, &descending_ticks(2));
+
+        let err = swap_down_crossing(&mut pool, &pool_key, 3, &loaders).unwrap_err();
+
+        assert_eq!(err, ErrorCode::MissingTickAccount.into());
+        let Error::AnchorError(anchor_error) = err else {
+            panic!("expected an AnchorError");
+        };
+        assert!(anchor_error.error_msg.contains("expected tick index -180"));
+    }
+
+    #[test]
+    fn test_skipped_tick_account_is_missing() {
+        let mut pool = setup_pool_with_many_ticks();
+        let pool_key = Pubkey::new_unique();
+        // Tick -120 is crossed but not supplied.
+        let loaders = tick_loaders(&pool_key, &[-60, -180]);
+
+        let err = swap_down_crossing(&mut pool, &pool_key, 3, &loaders).unwrap_err();
+
+        assert_eq!(err, ErrorCode::MissingTickAccount.into());
+    }
+
+    #[test]
+    fn test_tick_accounts_out_of_order_are_rejected() {
+        let mut pool = setup_pool_with_many_ticks();
+        let pool_key = Pubkey::new_unique();
+        let loaders = tick_loaders(&pool_key, &[-120, -60, -180]);
+
+        let err = swap_down_crossing(&mut pool, &pool_key, 3, &loaders).unwrap_err();
+
+        assert_eq!(err, ErrorCode::InvalidTickAccountOrder.into());
+    }
+
+    #[test]
+    fn test_duplicate_tick_accounts_are_rejected() {
+        let mut pool = setup_pool_with_many_ticks();
+        let pool_key = Pubkey::new_unique();
+        let loaders = tick_loaders(&pool_key, &[-60, -60]);
+
+        let err = swap_down_crossing(&mut pool, &pool_key, 1, &loaders).unwrap_err();
+
+        assert_eq!(err, ErrorCode::InvalidTickAccountOrder.into());
+    }
+
+    #[test]
+    fn test_tick_account_of_another_pool_is_rejected() {
+        let mut pool = setup_pool_with_many_ticks();
+        let pool_key = Pubkey::new_unique();
+        let loaders = tick_loaders(&Pubkey::new_unique(), &[-60]);
+
+        let err = swap_down_crossing(&mut pool, &pool_key, 1, &loaders).unwrap_err();
+
+        assert_eq!(err, ErrorCode::InvalidTickAccount.into());
+    }
+
+    #[test]
+    fn test_crossing_tick_with_excess_negative_net_underflows_cleanly() {
+        let mut pool = setup_pool_with_many_ticks();
+        let pool_key = Pubkey::new_unique();
+        // Corrupted bookkeeping: tick -60 removes more liquidity than the pool has active.
+        pool.liquidity = TICK_LIQUIDITY_NET as u128 / 2;
+        let loaders = tick_loaders(&pool_key, &descending_ticks(1));
+
+        let err = swap_down_crossing(&mut pool, &pool_key, 1, &loaders).unwrap_err();
+
+        assert_eq!(err, ErrorCode::LiquidityUnderflow.into());
+        assert_eq!(pool.liquidity, TICK_LIQUIDITY_NET as u128 / 2);
+    }
+}
+
+mod flash_loan_tests {
+    use super::*;
+    use crate::instructions::flash_loan::{flash_loan_callback_data, FlashLoanCallbackArgs};
+
+    #[test]
+    fn test_flash_fee_defaults_to_swap_fee_rate() {
+        let pool = create_default_pool();
+        assert_eq!(pool.flash_fee_bps, pool.fee_rate);
+        assert_eq!(pool.flash_loan_active, 0);
+    }
+
+    #[test]
+    fn test_flash_fee_rounds_up() {
+        let mut pool = create_default_pool();
+        pool.flash_fee_bps = 30;
+        assert_eq!(pool.flash_fee(1_000_000).unwrap(), 3_000);
+        // 0.3% of 1 is rounded up to a whole token unit.
+        assert_eq!(pool.flash_fee(1).unwrap(), 1);
+        assert_eq!(pool.flash_fee(0).unwrap(), 0);
+
+        pool.flash_fee_bps = 0;
+        assert_eq!(pool.flash_fee(1_000_000).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_flash_fee_on_max_amount_does_not_overflow() {
+        let mut pool = create_default_pool();
+        pool.flash_fee_bps = 10_000;
+        assert_eq!(pool.flash_fee(u64::MAX).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn test_flash_fees_accrue_to_fee_growth() {
+        let mut pool = create_default_pool();
+        pool.liquidity = 1 << 40;
+
+        pool.accrue_flash_fees(1 << 20, 3 << 20).unwrap();
+
+        // fee / liquidity in Q64.64: 2^20 / 2^40 = 2^-20.
+        assert_eq!(pool.fee_growth_global_0_q64, 1 << 44);
+        assert_eq!(pool.fee_growth_global_1_q64, 3 << 44);
+    }
+
+    #[test]
+    fn test_active_flash_loan_blocks_pool_instructions() {
+        let mut pool = create_default_pool();
+        assert!(pool.ensure_no_flash_loan().is_ok());
+
+        pool.flash_loan_active = 1;
+
+        assert_eq!(
+            pool.ensure_no_flash_loan().unwrap_err(),
+            ErrorCode::FlashLoanActive.into()
+        );
+    }
+
+    #[test]
+    fn test_callback_data_uses_anchor_instruction_layout() {
+        let args = FlashLoanCallbackArgs {
+            amount_0: 100,
+            amount_1: 200,
+            fee_0: 1,
+            fee_1: 2,
+        };
+
+        let data = flash_loan_callback_data(&args).unwrap();
+
+        let sighash = anchor_lang::solana_program::hash::hash(b"global:flash_loan_callback");
+        assert_eq!(&data[..8], &sighash.to_bytes()[..8]);
+        assert_eq!(
+            FlashLoanCallbackArgs::try_from_slice(&data[8..]).unwrap(),
+            args
+        );
+    }
+}
+
+mod protocol_fee_tests {
+    use super::*;
+    use crate::state::factory_config::ProtocolFeeCurve;
+    use crate::tick_bitmap::flip_tick_initialized_status;
+
+    /// With exactly 2^64 of active liquidity, fee growth represents every fee unit exactly,
+    /// so the LP share read back from it involves no rounding.
+    const LIQUIDITY: u128 = 1 << 64;
+
+    /// Pool at tick 0 with initialized ticks at -60, -120, 60 and 120, none of which
+    /// changes the active liquidity when crossed.
+    fn setup_pool(protocol_fee: u16) -> Pool {
+        let mut pool = create_default_pool();
+        pool.liquidity = LIQUIDITY;
+        pool.set_protocol_fee(protocol_fee).unwrap();
+        let mut bitmap: BTreeMap<i16, u64> =
+            borsh::BorshDeserialize::try_from_slice(&pool.tick_bitmap_data).unwrap();
+        for tick_index in [-120, -60, 60, 120] {
+            flip_tick_initialized_status(&mut bitmap, tick_index, pool.tick_spacing, true).unwrap();
+        }
+        pool.tick_bitmap_data = borsh::to_vec(&bitmap).unwrap();
+        pool
+    }
+
+    /// Swaps to tick -150 or 150 and returns the number of steps taken. From tick 0, this
+    /// crosses two initialized ticks.
+    fn swap_to_limit(pool: &mut Pool, zero_for_one: bool) -> usize {
+        let limit_tick = if zero_for_one { -150 } else { 150 };
+        let limit = math::tick_to_sqrt_price_q64(limit_tick).unwrap();
+        let mut crossings = 0;
+        pool.swap_with_tick_source(zero_for_one, i64::MAX as i128, limit, |_, _, _| {
+            crossings += 1;
+            Ok(0)
+        })
+        .unwrap();
+        crossings + 1
+    }
+
+    /// Total fee paid by `swap_to_limit` from tick 0, measured without a protocol fee.
+    fn total_fee(zero_for_one: bool) -> u64 {
+        let mut pool = setup_pool(0);
+        swap_to_limit(&mut pool, zero_for_one);
+        assert_eq!(pool.protocol_fees_owed_a, 0);
+        assert_eq!(pool.protocol_fees_owed_b, 0);
+        let growth = if zero_for_one {
+            pool.fee_growth_global_0_q64
+        } else {
+            pool.fee_growth_global_1_q64
+        };
+        math::get_fees_earned(LIQUIDITY, growth, 0).unwrap()
+    }
+
+    #[test]
+    fn test_protocol_fee_defaults_to_zero() {
+        let pool = create_default_pool();
+        assert_eq!(pool.protocol_fee, 0);
+        assert_eq!(pool.protocol_fees_owed_a, 0);
+        assert_eq!(pool.protocol_fees_owed_b, 0);
+    }
+
+    #[test]
+    fn test_set_protocol_fee_bounds() {
+        let mut pool = create_default_pool();
+        pool.set_protocol_fee(10_000).unwrap();
+        assert_eq!(pool.protocol_fee, 10_000);
+        assert_eq!(
+            pool.set_protocol_fee(10_001).unwrap_err(),
+            ErrorCode::InvalidProtocolFee.into()
+        );
+        assert_eq!(pool.protocol_fee, 10_000);
+    }
+
+    #[test]
+    fn test_lp_fees_plus_protocol_fees_equal_total_fee() {
+        for zero_for_one in [true, false] {
+            let total_fee = total_fee(zero_for_one);
+            assert!(total_fee > 0);
+
+            for protocol_fee in [1_000u16, 2_500, 10_000] {
+                let mut pool = setup_pool(protocol_fee);
+                let steps = swap_to_limit(&mut pool, zero_for_one);
+                assert_eq!(steps, 3);
+
+                let (growth, owed, other_growth, other_owed) = if zero_for_one {
+                    (
+                        pool.fee_growth_global_0_q64,
+                        pool.protocol_fees_owed_a,
+                        pool.fee_growth_global_1_q64,
+                        pool.protocol_fees_owed_b,
+                    )
+                } else {
+                    (
+                        pool.fee_growth_global_1_q64,
+                        pool.protocol_fees_owed_b,
+                        pool.fee_growth_global_0_q64,
+                        pool.protocol_fees_owed_a,
+                    )
+                };
+                let lp_fees = math::get_fees_earned(LIQUIDITY, growth, 0).unwrap();
+
+                assert_eq!(lp_fees + owed, total_fee, "protocol fee {protocol_fee}");
+                // The cut is rounded down once per swap step.
+                let expected_owed = total_fee * protocol_fee as u64 / 10_000;
+                assert!(owed <= expected_owed && expected_owed - owed <= steps as u64);
+                // Fees are only charged in the input token.
+                assert_eq!(other_growth, 0);
+                assert_eq!(other_owed, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_take_protocol_fees_resets_counters() {
+        let mut pool = setup_pool(2_500);
+        swap_to_limit(&mut pool, true);
+        swap_to_limit(&mut pool, false);
+        let owed = (pool.protocol_fees_owed_a, pool.protocol_fees_owed_b);
+        assert!(owed.0 > 0 && owed.1 > 0);
+
+        assert_eq!(pool.take_protocol_fees(), owed);
+        assert_eq!(pool.protocol_fees_owed_a, 0);
+        assert_eq!(pool.protocol_fees_owed_b, 0);
+        assert_eq!(pool.take_protocol_fees(), (0, 0));
+    }
+
+    #[test]
+    fn test_refreshed_share_applies_to_later_swaps_only() {
+        // Every share tier is 50%, so refreshing moves the pool from 10% to 50%.
+        let curve = ProtocolFeeCurve {
+            tvl_breakpoints: [1, 2, 3, 4],
+            protocol_share_bps: [5_000; 5],
+        };
+        let mut pool = setup_pool(1_000);
+        let mut reference = setup_pool(1_000);
+        swap_to_limit(&mut pool, true);
+        swap_to_limit(&mut reference, true);
+
+        let owed_a = pool.protocol_fees_owed_a;
+        let growth_0 = pool.fee_growth_global_0_q64;
+        assert!(owed_a > 0);
+        assert_eq!(pool.refresh_protocol_share(&curve).unwrap(), 5_000);
+        assert_eq!(pool.protocol_fee, 5_000);
+        assert_eq!(pool.protocol_fees_owed_a, owed_a);
+        assert_eq!(pool.fee_growth_global_0_q64, growth_0);
+
+        // The next swap splits its fee exactly as if the share had been set directly.
+        reference.set_protocol_fee(5_000).unwrap();
+        swap_to_limit(&mut pool, false);
+        swap_to_limit(&mut reference, false);
+        assert!(pool.protocol_fees_owed_b > 0);
+        assert_eq!(pool.protocol_fees_owed_a, owed_a);
+        assert_eq!(pool.protocol_fees_owed_b, reference.protocol_fees_owed_b);
+        assert_eq!(
+            pool.fee_growth_global_1_q64,
+            reference.fee_growth_global_1_q64
+        );
+    }
+}
+
+mod oracle_tests {
+    use super::*;
+    use crate::oracle::OBSERVATION_CAPACITY;
+
+    const START: i64 = 1_000;
+
+    /// Pool at tick 0 initialized at `START`, with no initialized ticks.
+    fn setup_pool() -> Pool {
+        let mut pool = Pool::default();
+        pool.initialize(InitializePoolParams {
+            timestamp: START,
+            ..default_initialize_pool_params()
+        })
+        .unwrap();
+        pool.liquidity = 1 << 64;
+        pool
+    }
+
+    /// Swaps to the price of `tick` at `timestamp` and returns the pool's new current tick.
+    fn swap_to_tick(pool: &mut Pool, tick: i32, timestamp: i64) -> i32 {
+        let limit = math::tick_to_sqrt_price_q64(tick).unwrap();
+        let zero_for_one = limit < pool.sqrt_price_q64;
+        pool.swap(
+            zero_for_one,
+            i64::MAX as i128,
+            limit,
+            &Pubkey::new_unique(),
+            &[],
+            timestamp,
+        )
+        .unwrap();
+        assert_eq!(pool.sqrt_price_q64, limit);
+        pool.current_tick
+    }
+
+    #[test]
+    fn test_initialize_records_first_observation() {
+        let pool = setup_pool();
+        assert_eq!(pool.observation_index, 0);
+        assert_eq!(pool.observation_count, 1);
+        assert_eq!(pool.last_observation_timestamp, START);
+        assert_eq!(pool.tick_cumulative, 0);
+        assert_eq!(pool.seconds_per_liquidity_cumulative_q64, 0);
+        assert_eq!(pool.observations[0].timestamp, START);
+    }
+
+    #[test]
+    fn test_swap_accumulates_before_price_moves() {
+        let mut pool = setup_pool();
+        pool.current_tick = 42; // Tick held since initialization, as the oracle sees it.
+        swap_to_tick(&mut pool, 600, START + 10);
+
+        assert_eq!(pool.observation_index, 1);
+        assert_eq!(pool.observation_count, 2);
+        assert_eq!(pool.last_observation_timestamp, START + 10);
+        assert_eq!(pool.tick_cumulative, 42 * 10);
+        // 10 seconds over 2^64 of liquidity.
+        assert_eq!(pool.seconds_per_liquidity_cumulative_q64, 10);
+        assert_eq!(pool.observations[1].tick_cumulative, pool.tick_cumulative);
+    }
+
+    #[test]
+    fn test_one_observation_per_timestamp() {
+        let mut pool = setup_pool();
+        let tick_1 = swap_to_tick(&mut pool, 600, START + 10);
+        swap_to_tick(&mut pool, -600, START + 10);
+
+        assert_eq!(pool.observation_count, 2);
+        assert_eq!(pool.tick_cumulative, 0);
+
+        // The second swap's tick never held: the next observation accrues its own tick.
+        let tick_2 = pool.current_tick;
+        swap_to_tick(&mut pool, 0, START + 15);
+        assert_ne!(tick_1, tick_2);
+        assert_eq!(pool.tick_cumulative, tick_2 as i64 * 5);
+    }
+
+    #[test]
+    fn test_twap_after_several_swaps() {
+        let mut pool = setup_pool();
+        let tick_a = swap_to_tick(&mut pool, 600, START + 10);
+        let tick_b = swap_to_tick(&mut pool, -300, START + 30);
+        let tick_c = swap_to_tick(&mut pool, 1200, START + 60);
+        let now = START + 100;
+
+        // [START + 10, now]: tick_a for 20s, tick_b for 30s, tick_c for 40s.
+        let expected = (tick_a as i64 * 20 + tick_b as i64 * 30 + tick_c as i64 * 40) / 90;
+        assert_eq!(pool.get_twap(now, 90).unwrap() as i64, expected);
+
+        // The whole history adds the initial tick 0 for 10s.
+        let expected = (tick_a as i64 * 20 + tick_b as i64 * 30 + tick_c as i64 * 40) / 100;
+        assert_eq!(pool.get_twap(now, 100).unwrap() as i64, expected);
+
+        // A window starting between two observations: tick_a for 5s of it.
+        let expected = (tick_a as i64 * 5 + tick_b as i64 * 30 + tick_c as i64 * 40) / 75;
+        assert_eq!(pool.get_twap(now, 75).unwrap() as i64, expected);
+
+        // A window after the last swap sees only the current tick.
+        assert_eq!(pool.get_twap(now, 40).unwrap(), tick_c);
+    }
+
+    #[test]
+    fn test_twap_rounds_towards_negative_infinity() {
+        let mut pool = setup_pool();
+        let tick = swap_to_tick(&mut pool, -61, START + 10);
+        assert!(tick < 0);
+        swap_to_tick(&mut pool, 0, START + 13);
+
+        // tick for 3s and 0 for 1s.
+        let expected = (tick as i64 * 3).div_euclid(4);
+        assert_eq!(pool.get_twap(START + 14, 4).unwrap() as i64, expected);
+        assert!((expected as f64) < tick as f64 * 3.0 / 4.0);
+    }
+
+    #[test]
+    fn test_twap_of_unchanged_price_is_current_tick() {
+        let mut pool = setup_pool();
+        pool.current_tick = -887;
+        assert_eq!(pool.get_twap(START + 3_600, 3_600).unwrap(), -887);
+    }
+
+    #[test]
+    fn test_twap_rejects_invalid_windows() {
+        let mut pool = setup_pool();
+        swap_to_tick(&mut pool, 600, START + 10);
+
+        assert_eq!(
+            pool.get_twap(START + 20, 0).unwrap_err(),
+            ErrorCode::InvalidTwapWindow.into()
+        );
+        assert_eq!(
+            pool.get_twap(START + 20, 21).unwrap_err(),
+            ErrorCode::OracleObservationTooOld.into()
+        );
+        assert!(pool.get_twap(START + 20, 20).is_ok());
+    }
+
+    #[test]
+    fn test_observations_ring_buffer_wraps() {
+        let mut pool = setup_pool();
+        let swaps = OBSERVATION_CAPACITY as i64 + 4;
+        for i in 1..=swaps {
+            let tick = if i % 2 == 0 { 600 } else { -600 };
+            swap_to_tick(&mut pool, tick, START + i * 10);
+        }
+
+        assert_eq!(pool.observation_count as usize, OBSERVATION_CAPACITY);
+        assert_eq!(
+            pool.observation_index as usize,
+            swaps as usize % OBSERVATION_CAPACITY
+        );
+
+        // The oldest remaining observation is the one written OBSERVATION_CAPACITY - 1
+        // swaps before the last.
+        let now = START + swaps * 10;
+        let oldest = (OBSERVATION_CAPACITY as u32 - 1) * 10;
+        assert!(pool.get_twap(now, oldest).is_ok());
+        assert_eq!(
+            pool.get_twap(now, oldest + 1).unwrap_err(),
+            ErrorCode::OracleObservationTooOld.into()
+        );
+    }
+}
+
+mod oracle_seeding_tests {
+    use super::*;
+    use crate::oracle::OBSERVATION_CAPACITY;
+
+    const START: i64 = 1_000;
+    const TICK: i32 = -1_200;
+
+    /// Pool held at `TICK` since it was initialized at `START`, as the oracle sees it.
+    fn setup_pool() -> Pool {
+        let mut pool = Pool::default();
+        pool.initialize(InitializePoolParams {
+            timestamp: START,
+            ..default_initialize_pool_params()
+        })
+        .unwrap();
+        pool.liquidity = 3 << 64;
+        pool.current_tick = TICK;
+        pool
+    }
+
+    #[test]
+    fn test_seeding_ends_with_first_observation() {
+        let mut pool = setup_pool();
+        let first = pool.observations[0];
+        pool.seed_observations(8, 60).unwrap();
+
+        assert_eq!(pool.observation_count, 8);
+        assert_eq!(pool.observation_index, 7);
+        assert_eq!(pool.observations[7], first);
+        assert_eq!(pool.last_observation_timestamp, START);
+        assert_eq!(pool.tick_cumulative, 0);
+        assert_eq!(pool.seconds_per_liquidity_cumulative_q64, 0);
+    }
+
+    #[test]
+    fn test_seeded_observations_accrue_current_tick_and_liquidity() {
+        let mut pool = setup_pool();
+        pool.seed_observations(OBSERVATION_CAPACITY as u16, 30)
+            .unwrap();
+
+        for pair in pool.observations.windows(2) {
+            assert_eq!(pair[1].timestamp - pair[0].timestamp, 30);
+            assert_eq!(
+                pair[1].tick_cumulative - pair[0].tick_cumulative,
+                TICK as i64 * 30
+            );
+            // 30 seconds over 3 * 2^64 of liquidity, in Q64.64.
+            assert_eq!(
+                pair[1]
+                    .seconds_per_liquidity_cumulative_q64
+                    .wrapping_sub(pair[0].seconds_per_liquidity_cumulative_q64),
+                10
+            );
+        }
+    }
+
+    #[test]
+    fn test_twap_available_over_seeded_window() {
+        let mut pool = setup_pool();
+        pool.seed_observations(5, 600).unwrap();
+
+        let window = 4 * 600;
+        assert_eq!(pool.get_twap(START, window).unwrap(), TICK);
+        assert_eq!(
+            pool.get_twap(START, window + 1).unwrap_err(),
+            ErrorCode::OracleObservationTooOld.into()
+        );
+        // Later, the window spans the seeded and the elapsed time alike.
+        assert_eq!(pool.get_twap(START + 100, window + 100).unwrap(), TICK);
+    }
+
+    #[test]
+    fn test_swaps_keep_writing_after_seeded_observations() {
+        let mut pool = setup_pool();
+        pool.seed_observations(4, 10).unwrap();
+
+        let limit = math::tick_to_sqrt_price_q64(600).unwrap();
+        pool.swap(
+            false,
+            i64::MAX as i128,
+            limit,
+            &Pubkey::new_unique(),
+            &[],
+            START + 10,
+        )
+        .unwrap();
+
+        assert_eq!(pool.observation_count, 5);
+        assert_eq!(pool.observation_index, 4);
+        assert_eq!(pool.observations[4].timestamp, START + 10);
+        assert_eq!(pool.tick_cumulative, TICK as i64 * 10);
+        // The seeded observations still reach 40 seconds back.
+        assert_eq!(pool.get_twap(START + 10, 40).unwrap(), TICK);
+    }
+
+    #[test]
+    fn test_seeding_rejects_invalid_arguments() {
+        let mut pool = setup_pool();
+        assert_eq!(
+            pool.seed_observations(OBSERVATION_CAPACITY as u16 + 1, 60)
+                .unwrap_err(),
+            ErrorCode::OracleCardinalityTooLarge.into()
+        );
+        assert_eq!(
+            pool.seed_observations(1, 60).unwrap_err(),
+            ErrorCode::InvalidObservationCardinality.into()
+        );
+        assert_eq!(
+            pool.seed_observations(4, 0).unwrap_err(),
+            ErrorCode::InvalidInput.into()
+        );
+        assert_eq!(pool.observation_count, 1);
+    }
+
+    #[test]
+    fn test_seeding_only_before_first_write() {
+        let mut pool = setup_pool();
+        pool.write_observation(START + 5);
+        assert_eq!(
+            pool.seed_observations(4, 60).unwrap_err(),
+            ErrorCode::InvalidObservationCardinality.into()
+        );
+
+        let mut seeded = setup_pool();
+        seeded.seed_observations(4, 60).unwrap();
+        assert_eq!(
+            seeded.seed_observations(4, 60).unwrap_err(),
+            ErrorCode::InvalidObservationCardinality.into()
+        );
+    }
+}
+
+mod swap_iteration_limit_tests {
+    use super::*;
+    use crate::tick_bitmap::flip_tick_initialized_status;
+
+    /// Pool at tick 0 with `count` initialized ticks at 60, 120, ..., none of which
+    /// changes the active liquidity when crossed.
+    fn setup_pool(count: i32) -> Pool {
+        let mut pool = create_default_pool();
+        pool.liquidity = 1 << 64;
+        let mut bitmap: BTreeMap<i16, u64> =
+            borsh::BorshDeserialize::try_from_slice(&pool.tick_bitmap_data).unwrap();
+        for k in 1..=count {
+            flip_tick_initialized_status(&mut bitmap, 60 * k, pool.tick_spacing, true).unwrap();
+        }
+        pool.tick_bitmap_data = borsh::to_vec(&bitmap).unwrap();
+        pool
+    }
+
+    /// Swaps up to `limit_tick` and returns the result with the number of ticks crossed.
+    fn swap_up_to(pool: &mut Pool, limit_tick: i32) -> (Result<()>, usize) {
+        let limit = math::tick_to_sqrt_price_q64(limit_tick).unwrap();
+        let mut crossings = 0;
+        let result = pool
+            .swap_with_tick_source(false, i64::MAX as i128, limit, |_, _, _| {
+                crossings += 1;
+                Ok(0)
+            })
+            .map(|_| ());
+        (result, crossings)
+    }
+
+    #[test]
+    fn test_swap_at_iteration_limit_succeeds() {
+        let max = MAX_SWAP_ITERATIONS as i32;
+        let mut pool = setup_pool(max);
+
+        let (result, crossings) = swap_up_to(&mut pool, 60 * max);
+        result.unwrap();
+        assert_eq!(crossings, MAX_SWAP_ITERATIONS);
+        assert_eq!(pool.current_tick, 60 * max);
+    }
+
+    #[test]
+    fn test_swap_over_iteration_limit_is_rejected() {
+        let max = MAX_SWAP_ITERATIONS as i32;
+        let mut pool = setup_pool(max);
+
+        // Crossing every tick takes all the steps; reaching the limit past them needs one more.
+        let (result, crossings) = swap_up_to(&mut pool, 60 * (max + 1));
+        assert_eq!(
+            result.unwrap_err(),
+            ErrorCode::MaxTickCrossingsExceeded.into()
+        );
+        assert_eq!(crossings, MAX_SWAP_ITERATIONS);
+    }
+}
+
+mod fee_rate_tests {
+    use super::*;
+    use crate::state::factory_config::FactoryConfig;
+    use crate::{SetFeeRate, SetFeeRateBumps};
+    use std::collections::BTreeSet;
+
+    pub(super) fn leak_account(
+        key: Pubkey,
+        is_signer: bool,
+        owner: &Pubkey,
+        data: Vec<u8>,
+    ) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            is_signer,
+            true,
+            Box::leak(Box::new(1_000_000_000u64)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(*owner)),
+            false,
+            0,
+        )
+    }
+
+    pub(super) fn factory_config_key() -> Pubkey {
+        Pubkey::find_program_address(&[b"factory_config"], &crate::ID).0
+    }
+
+    /// The factory config account, administered by `authority`.
+    pub(super) fn factory_config_account(authority: Pubkey) -> AccountInfo<'static> {
+        let (key, bump) = Pubkey::find_program_address(&[b"factory_config"], &crate::ID);
+        let config = FactoryConfig {
+            bump,
+            authority,
+            ..FactoryConfig::default()
+        };
+        let mut data = Vec::new();
+        config.try_serialize(&mut data).unwrap();
+        leak_account(key, false, &crate::ID, data)
+    }
+
+    /// Resolves the `SetFeeRate` accounts for a pool whose factory config is administered
+    /// by `config_authority`, signed (or not) by `signer`.
+    fn resolve_set_fee_rate(
+        config_authority: Pubkey,
+        signer: Pubkey,
+        is_signer: bool,
+    ) -> Result<()> {
+        let mut pool = create_default_pool();
+        pool.factory = factory_config_key();
+        let mut data = Vec::new();
+        pool.try_serialize(&mut data).unwrap();
+
+        let accounts: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+            leak_account(Pubkey::new_unique(), false, &crate::ID, data),
+            factory_config_account(config_authority),
+            leak_account(signer, is_signer, &System::id(), Vec::new()),
+        ]));
+        SetFeeRate::try_accounts(
+            &crate::ID,
+            &mut &accounts[..],
+            &[],
+            &mut SetFeeRateBumps {},
+            &mut BTreeSet::new(),
+        )
+        .map(|_| ())
+    }
+
+    #[test]
+    fn test_set_fee_rate_updates_rate_only() {
+        let mut pool = create_default_pool();
+        let (fee_tier, tick_spacing) = (pool.fee_tier, pool.tick_spacing);
+        assert_eq!(fee_tier, pool.fee_rate);
+
+        pool.set_fee_rate(100).unwrap();
+        assert_eq!(pool.fee_rate, 100);
+        // The PDA seed and the tick spacing stay those of the pool's creation
+        assert_eq!(pool.fee_tier, fee_tier);
+        assert_eq!(pool.tick_spacing, tick_spacing);
+    }
+
+    #[test]
+    fn test_set_fee_rate_bounds() {
+        let mut pool = create_default_pool();
+        pool.set_fee_rate(MIN_FEE_RATE).unwrap();
+        assert_eq!(pool.fee_rate, MIN_FEE_RATE);
+        pool.set_fee_rate(MAX_FEE_RATE).unwrap();
+        assert_eq!(pool.fee_rate, MAX_FEE_RATE);
+
+        for fee_rate in [0, MAX_FEE_RATE + 1, u16::MAX] {
+            assert_eq!(
+                pool.set_fee_rate(fee_rate).unwrap_err(),
+                ErrorCode::InvalidFeeRate.into()
+            );
+        }
+        assert_eq!(pool.fee_rate, MAX_FEE_RATE);
+    }
+
+    #[test]
+    fn test_set_fee_rate_rejected_during_flash_loan() {
+        let mut pool = create_default_pool();
+        let fee_rate = pool.fee_rate;
+        pool.flash_loan_active = 1;
+        assert_eq!(
+            pool.set_fee_rate(100).unwrap_err(),
+            ErrorCode::FlashLoanActive.into()
+        );
+        assert_eq!(pool.fee_rate, fee_rate);
+    }
+
+    #[test]
+    fn test_set_fee_rate_requires_factory_config_authority() {
+        let authority = Pubkey::new_unique();
+        assert!(resolve_set_fee_rate(authority, authority, true).is_ok());
+        assert_eq!(
+            resolve_set_fee_rate(authority, Pubkey::new_unique(), true).unwrap_err(),
+            ErrorCode::UnauthorizedAccess.into()
+        );
+    }
+
+    #[test]
+    fn test_set_fee_rate_requires_pool_of_factory_config() {
+        let authority = Pubkey::new_unique();
+        let mut data = Vec::new();
+        create_default_pool().try_serialize(&mut data).unwrap();
+        let accounts: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+            leak_account(Pubkey::new_unique(), false, &crate::ID, data),
+            factory_config_account(authority),
+            leak_account(authority, true, &System::id(), Vec::new()),
+        ]));
+        assert_eq!(
+            SetFeeRate::try_accounts(
+                &crate::ID,
+                &mut &accounts[..],
+                &[],
+                &mut SetFeeRateBumps {},
+                &mut BTreeSet::new(),
+            )
+            .err(),
+            Some(ErrorCode::InvalidFactoryConfig.into())
+        );
+    }
+
+    #[test]
+    fn test_set_fee_rate_requires_authority_signature() {
+        let authority = Pubkey::new_unique();
+        assert_eq!(
+            resolve_set_fee_rate(authority, authority, false).unwrap_err(),
+            anchor_lang::error::ErrorCode::AccountNotSigner.into()
+        );
+    }
+}
+
+mod tick_spacing_migration_tests {
+    use super::fee_rate_tests::{factory_config_account, factory_config_key, leak_account};
+    use super::*;
+    use crate::instructions::mint_position::validate_mint_params;
+    use crate::tick_bitmap::next_initialized_tick;
+    use crate::{MigrateTickSpacing, MigrateTickSpacingBumps};
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_migrate_tick_spacing_to_multiple() {
+        for new_tick_spacing in [120, 180, 600] {
+            let mut pool = create_default_pool();
+            let (sqrt_price_q64, current_tick) = (pool.sqrt_price_q64, pool.current_tick);
+            pool.migrate_tick_spacing(new_tick_spacing).unwrap();
+            assert_eq!(pool.tick_spacing, new_tick_spacing);
+            assert_eq!(pool.sqrt_price_q64, sqrt_price_q64);
+            assert_eq!(pool.current_tick, current_tick);
+        }
+    }
+
+    #[test]
+    fn test_migrate_tick_spacing_rejects_non_multiples() {
+        let mut pool = create_default_pool();
+        assert_eq!(pool.tick_spacing, 60);
+        for new_tick_spacing in [0, 1, 30, 60, 90, 150] {
+            assert_eq!(
+                pool.migrate_tick_spacing(new_tick_spacing).unwrap_err(),
+                ErrorCode::InvalidTickSpacing.into()
+            );
+        }
+        assert_eq!(pool.tick_spacing, 60);
+    }
+
+    #[test]
+    fn test_mint_after_migration_must_align_to_new_spacing() {
+        let mut pool = create_default_pool();
+        validate_mint_params(pool.tick_spacing, -60, 60, MIN_LIQUIDITY).unwrap();
+
+        pool.migrate_tick_spacing(120).unwrap();
+        assert_eq!(
+            validate_mint_params(pool.tick_spacing, -60, 60, MIN_LIQUIDITY).unwrap_err(),
+            ErrorCode::InvalidTickSpacing.into()
+        );
+        validate_mint_params(pool.tick_spacing, -120, 240, MIN_LIQUIDITY).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_tick_spacing_keeps_initialized_ticks() {
+        let mut pool = create_default_pool();
+        let (mut tick_lower, mut tick_upper) =
+            (ActualTickData::default(), ActualTickData::default());
+        pool.modify_liquidity_for_test(-60, 60, 1_000, &mut tick_lower, &mut tick_upper)
+            .unwrap();
+        pool.externalize_bitmap_word(5).unwrap();
+
+        pool.migrate_tick_spacing(120).unwrap();
+        assert_eq!((pool.tick_spacing, pool.bitmap_tick_spacing), (120, 60));
+        assert_eq!(pool.external_bitmap_words, vec![5]);
+
+        // Ticks off the new spacing are still found, and freed, under the old layout
+        let bitmap: BTreeMap<i16, u64> =
+            borsh::BorshDeserialize::try_from_slice(&pool.tick_bitmap_data).unwrap();
+        assert_eq!(
+            next_initialized_tick(&bitmap, 1, pool.bitmap_tick_spacing, false).unwrap(),
+            Some(60)
+        );
+        assert_eq!(
+            next_initialized_tick(&bitmap, 0, pool.bitmap_tick_spacing, true).unwrap(),
+            Some(-60)
+        );
+        pool.modify_liquidity_for_test(-60, 60, -1_000, &mut tick_lower, &mut tick_upper)
+            .unwrap();
+        let bitmap: BTreeMap<i16, u64> =
+            borsh::BorshDeserialize::try_from_slice(&pool.tick_bitmap_data).unwrap();
+        assert!(bitmap.values().all(|word| *word == 0));
+    }
+
+    #[test]
+    fn test_migrate_tick_spacing_rejected_during_flash_loan() {
+        let mut pool = create_default_pool();
+        pool.flash_loan_active = 1;
+        assert_eq!(
+            pool.migrate_tick_spacing(120).unwrap_err(),
+            ErrorCode::FlashLoanActive.into()
+        );
+        assert_eq!(pool.tick_spacing, 60);
+    }
+
+    #[test]
+    fn test_migrate_tick_spacing_requires_factory_config_authority() {
+        let authority = Pubkey::new_unique();
+        let resolve = |signer: Pubkey, is_signer: bool| {
+            let mut pool = create_default_pool();
+            pool.factory = factory_config_key();
+            let mut data = Vec::new();
+            pool.try_serialize(&mut data).unwrap();
+            let accounts: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+                leak_account(Pubkey::new_unique(), false, &crate::ID, data),
+                factory_config_account(authority),
+                leak_account(signer, is_signer, &System::id(), Vec::new()),
+            ]));
+            MigrateTickSpacing::try_accounts(
+                &crate::ID,
+                &mut &accounts[..],
+                &[],
+                &mut MigrateTickSpacingBumps {},
+                &mut BTreeSet::new(),
+            )
+            .map(|_| ())
+        };
+
+        assert!(resolve(authority, true).is_ok());
+        assert_eq!(
+            resolve(Pubkey::new_unique(), true).unwrap_err(),
+            ErrorCode::UnauthorizedAccess.into()
+        );
+        assert_eq!(
+            resolve(authority, false).unwrap_err(),
+            anchor_lang::error::ErrorCode::AccountNotSigner.into()
+        );
+    }
+}
+
+mod immutable_parameters_tests {
+    use super::*;
+    use crate::state::factory_config::{ProtocolFeeCurve, PROTOCOL_FEE_CURVE_BREAKPOINTS};
+
+    fn pool(immutable_parameters: bool) -> Pool {
+        let mut pool = Pool::default();
+        pool.initialize(InitializePoolParams {
+            immutable_parameters,
+            ..default_initialize_pool_params()
+        })
+        .unwrap();
+        pool
+    }
+
+    /// Runs every parameter-changing method on `pool`: setting the fee rate, the protocol
+    /// fee, refreshing the protocol share from a curve, and widening the tick spacing.
+    fn mutate_parameters(pool: &mut Pool) -> [Result<()>; 4] {
+        let curve = ProtocolFeeCurve {
+            protocol_share_bps: [2_000; PROTOCOL_FEE_CURVE_BREAKPOINTS + 1],
+            ..ProtocolFeeCurve::default()
+        };
+        [
+            pool.set_fee_rate(100),
+            pool.set_protocol_fee(1_000),
+            pool.refresh_protocol_share(&curve).map(|_| ()),
+            pool.migrate_tick_spacing(120),
+        ]
+    }
+
+    #[test]
+    fn test_initialize_records_immutable_parameters() {
+        assert!(!pool(false).immutable_parameters);
+        assert!(pool(true).immutable_parameters);
+    }
+
+    #[test]
+    fn test_mutable_pool_accepts_parameter_changes() {
+        let mut pool = pool(false);
+        for result in mutate_parameters(&mut pool) {
+            result.unwrap();
+        }
+        assert_eq!(pool.fee_rate, 100);
+        assert_eq!(pool.protocol_fee, 2_000);
+        assert_eq!(pool.tick_spacing, 120);
+        assert!(!pool.immutable_parameters);
+    }
+
+    #[test]
+    fn test_immutable_pool_rejects_parameter_changes() {
+        let mut pool = pool(true);
+        let (fee_rate, protocol_fee, tick_spacing) =
+            (pool.fee_rate, pool.protocol_fee, pool.tick_spacing);
+        for result in mutate_parameters(&mut pool) {
+            assert_eq!(result.unwrap_err(), ErrorCode::PoolImmutable.into());
+        }
+        assert_eq!(pool.fee_rate, fee_rate);
+        assert_eq!(pool.protocol_fee, protocol_fee);
+        assert_eq!(pool.tick_spacing, tick_spacing);
+        assert!(pool.immutable_parameters);
+    }
+
+    #[test]
+    fn test_immutable_pool_still_takes_oracle_config() {
+        let mut pool = pool(true);
+        let oracle_authority = Pubkey::new_unique();
+        pool.set_oracle_config(oracle_authority, 10);
+        assert_eq!(pool.oracle_authority, oracle_authority);
+        assert_eq!(pool.max_oracle_age_slots, 10);
+        assert!(pool.immutable_parameters);
+    }
+}
+
+mod collect_protocol_fees_tests {
+    use super::fee_rate_tests::{factory_config_account, factory_config_key, leak_account};
+    use super::*;
+    use crate::instructions::collect_protocol_fees;
+    use crate::{CollectProtocolFees, CollectProtocolFeesBumps};
+    use anchor_lang::solana_program::program_option::COption;
+    use anchor_lang::solana_program::program_pack::Pack;
+    use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken};
+    use anchor_spl::token::{spl_token, Token};
+    use std::collections::BTreeSet;
+    use std::sync::Once;
+
+    /// `init_if_needed` reads the rent sysvar even when the account exists, which the
+    /// default off-chain syscall stubs do not provide.
+    struct RentStub;
+
+    impl anchor_lang::solana_program::program_stubs::SyscallStubs for RentStub {
+        fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+            // SAFETY: `Rent::get` passes a pointer to a `Rent`.
+            unsafe { *(var_addr as *mut Rent) = Rent::default() };
+            anchor_lang::solana_program::entrypoint::SUCCESS
+        }
+    }
+
+    fn install_rent_stub() {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            anchor_lang::solana_program::program_stubs::set_syscall_stubs(Box::new(RentStub));
+        });
+    }
+
+    fn mint_account(key: Pubkey) -> AccountInfo<'static> {
+        let mint = spl_token::state::Mint {
+            is_initialized: true,
+            ..Default::default()
+        };
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        mint.pack_into_slice(&mut data);
+        leak_account(key, false, &spl_token::ID, data)
+    }
+
+    fn token_account(
+        key: Pubkey,
+        mint: Pubkey,
+        owner: Pubkey,
+        delegate: Option<Pubkey>,
+    ) -> AccountInfo<'static> {
+        let token_account = spl_token::state::Account {
+            mint,
+            owner,
+            amount: 1_000,
+            delegate: delegate.into(),
+            delegated_amount: if delegate.is_some() { 1_000 } else { 0 },
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            close_authority: COption::None,
+        };
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        token_account.pack_into_slice(&mut data);
+        leak_account(key, false, &spl_token::ID, data)
+    }
+
+    fn program(key: Pubkey) -> AccountInfo<'static> {
+        let mut account = leak_account(key, false, &Pubkey::default(), Vec::new());
+        account.executable = true;
+        account
+    }
+
+    /// Serializes `pool` as a pool of the factory config, administered by `pool.authority`.
+    fn pool_data(pool: &Pool) -> Vec<u8> {
+        let mut pool = pool.clone();
+        pool.factory = factory_config_key();
+        let mut data = Vec::new();
+        pool.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    /// Resolves the `CollectProtocolFees` accounts of `pool`, signed by `signer`, with the
+    /// signer's associated token accounts as destinations. `delegate` is set on both.
+    fn resolve(
+        pool: &Pool,
+        signer: Pubkey,
+        delegate: Option<Pubkey>,
+    ) -> Result<CollectProtocolFees<'static>> {
+        install_rent_stub();
+        let accounts: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+            leak_account(Pubkey::new_unique(), false, &crate::ID, pool_data(pool)),
+            factory_config_account(pool.authority),
+            token_account(
+                pool.token0_vault,
+                pool.token0_mint,
+                Pubkey::new_unique(),
+                None,
+            ),
+            token_account(
+                pool.token1_vault,
+                pool.token1_mint,
+                Pubkey::new_unique(),
+                None,
+            ),
+            mint_account(pool.token0_mint),
+            mint_account(pool.token1_mint),
+            token_account(
+                get_associated_token_address(&signer, &pool.token0_mint),
+                pool.token0_mint,
+                signer,
+                delegate,
+            ),
+            token_account(
+                get_associated_token_address(&signer, &pool.token1_mint),
+                pool.token1_mint,
+                signer,
+                delegate,
+            ),
+            leak_account(signer, true, &System::id(), Vec::new()),
+            program(Token::id()),
+            program(AssociatedToken::id()),
+            program(System::id()),
+        ]));
+        CollectProtocolFees::try_accounts(
+            &crate::ID,
+            &mut &accounts[..],
+            &[],
+            &mut CollectProtocolFeesBumps {},
+            &mut BTreeSet::new(),
+        )
+    }
+
+    fn collect(accounts: &mut CollectProtocolFees<'static>) -> Result<()> {
+        collect_protocol_fees::handler(Context::new(
+            &crate::ID,
+            accounts,
+            &[],
+            CollectProtocolFeesBumps {},
+        ))
+    }
+
+    #[test]
+    fn test_collects_one_side_then_nothing() {
+        let mut pool = create_default_pool();
+        pool.protocol_fees_owed_a = 500;
+        let mut accounts = resolve(&pool, pool.authority, None).unwrap();
+
+        collect(&mut accounts).unwrap();
+        assert_eq!(accounts.pool.protocol_fees_owed_a, 0);
+        assert_eq!(accounts.pool.protocol_fees_owed_b, 0);
+
+        // Nothing left to transfer, which is not an error
+        collect(&mut accounts).unwrap();
+        assert_eq!(accounts.pool.protocol_fees_owed_a, 0);
+    }
+
+    #[test]
+    fn test_existing_ata_with_delegate_is_accepted() {
+        let mut pool = create_default_pool();
+        pool.protocol_fees_owed_a = 500;
+        pool.protocol_fees_owed_b = 700;
+        let mut accounts = resolve(&pool, pool.authority, Some(Pubkey::new_unique())).unwrap();
+
+        collect(&mut accounts).unwrap();
+        assert_eq!(accounts.pool.protocol_fees_owed_a, 0);
+        assert_eq!(accounts.pool.protocol_fees_owed_b, 0);
+    }
+
+    #[test]
+    fn test_requires_factory_config_authority() {
+        let pool = create_default_pool();
+        assert_eq!(
+            resolve(&pool, Pubkey::new_unique(), None).err(),
+            Some(ErrorCode::UnauthorizedAccess.into())
+        );
+    }
+
+    #[test]
+    fn test_destination_must_be_authority_ata() {
+        install_rent_stub();
+        let pool = create_default_pool();
+        let stranger = Pubkey::new_unique();
+        let accounts: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+            leak_account(Pubkey::new_unique(), false, &crate::ID, pool_data(&pool)),
+            factory_config_account(pool.authority),
+            token_account(
+                pool.token0_vault,
+                pool.token0_mint,
+                Pubkey::new_unique(),
+                None,
+            ),
+            token_account(
+                pool.token1_vault,
+                pool.token1_mint,
+                Pubkey::new_unique(),
+                None,
+            ),
+            mint_account(pool.token0_mint),
+            mint_account(pool.token1_mint),
+            // Someone else's token account for the right mint
+            token_account(Pubkey::new_unique(), pool.token0_mint, stranger, None),
+            token_account(
+                get_associated_token_address(&pool.authority, &pool.token1_mint),
+                pool.token1_mint,
+                pool.authority,
+                None,
+            ),
+            leak_account(pool.authority, true, &System::id(), Vec::new()),
+            program(Token::id()),
+            program(AssociatedToken::id()),
+            program(System::id()),
+        ]));
+        match CollectProtocolFees::try_accounts(
+            &crate::ID,
+            &mut &accounts[..],
+            &[],
+            &mut CollectProtocolFeesBumps {},
+            &mut BTreeSet::new(),
+        ) {
+            Err(anchor_lang::prelude::Error::AnchorError(details)) => assert_eq!(
+                details.error_code_number,
+                u32::from(anchor_lang::error::ErrorCode::ConstraintTokenOwner)
+            ),
+            _ => panic!("expected a token owner constraint error"),
+        }
+    }
+}
+
+mod oracle_price_tests {
+    use super::fee_rate_tests::leak_account;
+    use super::*;
+    use crate::{SetOracle, SetOracleBumps};
+    use std::collections::BTreeSet;
+
+    const SLOT: u64 = 10_000;
+
+    #[test]
+    fn test_new_pool_has_no_oracle_price() {
+        let pool = create_default_pool();
+        assert_eq!(pool.oracle_authority, pool.authority);
+        assert_eq!(pool.max_oracle_age_slots, DEFAULT_MAX_ORACLE_AGE_SLOTS);
+        assert_eq!(pool.oracle_sqrt_price(SLOT).unwrap(), None);
+    }
+
+    #[test]
+    fn test_set_oracle_price_is_read_back_until_stale() {
+        let mut pool = create_default_pool();
+        let price = float_to_q64(1.5);
+        pool.set_oracle_price(price, SLOT - 10, SLOT).unwrap();
+        assert_eq!(pool.oracle_price_slot, SLOT - 10);
+
+        let last_fresh_slot = SLOT - 10 + DEFAULT_MAX_ORACLE_AGE_SLOTS;
+        assert_eq!(pool.oracle_sqrt_price(SLOT).unwrap(), Some(price));
+        assert_eq!(
+            pool.oracle_sqrt_price(last_fresh_slot).unwrap(),
+            Some(price)
+        );
+        assert_eq!(
+            pool.oracle_sqrt_price(last_fresh_slot + 1).unwrap_err(),
+            ErrorCode::OraclePriceStale.into()
+        );
+        // The spot price is left alone
+        assert_eq!(pool.sqrt_price_q64, float_to_q64(1.0));
+    }
+
+    #[test]
+    fn test_set_oracle_price_rejects_stale_price() {
+        let mut pool = create_default_pool();
+        pool.set_oracle_config(pool.oracle_authority, 5);
+        assert_eq!(
+            pool.set_oracle_price(float_to_q64(1.5), SLOT - 6, SLOT)
+                .unwrap_err(),
+            ErrorCode::OraclePriceStale.into()
+        );
+        assert_eq!(pool.oracle_sqrt_price(SLOT).unwrap(), None);
+        pool.set_oracle_price(float_to_q64(1.5), SLOT - 5, SLOT)
+            .unwrap();
+        assert_eq!(
+            pool.oracle_sqrt_price(SLOT).unwrap(),
+            Some(float_to_q64(1.5))
+        );
+    }
+
+    #[test]
+    fn test_set_oracle_price_rejects_invalid_prices() {
+        let mut pool = create_default_pool();
+        pool.set_oracle_price(float_to_q64(1.5), SLOT - 1, SLOT)
+            .unwrap();
+        for (price, published_slot) in [
+            (0, SLOT),
+            (MAX_SQRT_PRICE + 1, SLOT),
+            // Published in the future
+            (float_to_q64(2.0), SLOT + 1),
+            // Older than the stored price
+            (float_to_q64(2.0), SLOT - 2),
+        ] {
+            assert_eq!(
+                pool.set_oracle_price(price, published_slot, SLOT)
+                    .unwrap_err(),
+                ErrorCode::InvalidOraclePrice.into()
+            );
+        }
+        assert_eq!(pool.oracle_sqrt_price_q64, float_to_q64(1.5));
+        assert_eq!(pool.oracle_price_slot, SLOT - 1);
+    }
+
+    #[test]
+    fn test_set_oracle_requires_oracle_authority() {
+        let oracle_authority = Pubkey::new_unique();
+        let mut pool = create_default_pool();
+        pool.set_oracle_config(oracle_authority, DEFAULT_MAX_ORACLE_AGE_SLOTS);
+        let mut data = Vec::new();
+        pool.try_serialize(&mut data).unwrap();
+
+        let resolve = |signer: Pubkey| {
+            let accounts: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+                leak_account(Pubkey::new_unique(), false, &crate::ID, data.clone()),
+                leak_account(signer, true, &System::id(), Vec::new()),
+            ]));
+            SetOracle::try_accounts(
+                &crate::ID,
+                &mut &accounts[..],
+                &[],
+                &mut SetOracleBumps {},
+                &mut BTreeSet::new(),
+            )
+            .map(|_| ())
+        };
+        assert!(resolve(oracle_authority).is_ok());
+        // Not even the pool authority can publish prices once another oracle authority is set
+        assert_eq!(
+            resolve(pool.authority).unwrap_err(),
+            ErrorCode::UnauthorizedAccess.into()
+        );
+    }
+}
+
+mod mint_deposit_tests {
+    use super::*;
+    use crate::instructions::mint_position::deposit_amounts;
+
+    const LIQUIDITY: u128 = 1_000_000_000_000;
+
+    /// Token amounts `withdraw` pays out for `liquidity` at the pool's price.
+    fn withdrawal_amounts(
+        pool: &Pool,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+    ) -> (u64, u64) {
+        let (amount0, amount1) = math::get_amounts_for_liquidity(
+            pool.sqrt_price_q64,
+            math::tick_to_sqrt_price_q64(tick_lower).unwrap(),
+            math::tick_to_sqrt_price_q64(tick_upper).unwrap(),
+            liquidity,
+            false,
+        )
+        .unwrap();
+        (amount0 as u64, amount1 as u64)
+    }
+
+    #[test]
+    fn test_range_above_price_deposits_only_token0() {
+        let pool = create_default_pool();
+        let (amount0, amount1) =
+            deposit_amounts(pool.sqrt_price_q64, 60, 600, LIQUIDITY, u64::MAX, u64::MAX).unwrap();
+        assert!(amount0 > 0);
+        assert_eq!(amount1, 0);
+    }
+
+    #[test]
+    fn test_range_below_price_deposits_only_token1() {
+        let pool = create_default_pool();
+        let (amount0, amount1) = deposit_amounts(
+            pool.sqrt_price_q64,
+            -600,
+            -60,
+            LIQUIDITY,
+            u64::MAX,
+            u64::MAX,
+        )
+        .unwrap();
+        assert_eq!(amount0, 0);
+        assert!(amount1 > 0);
+    }
+
+    #[test]
+    fn test_range_around_price_deposits_both_tokens() {
+        let pool = create_default_pool();
+        let (amount0, amount1) = deposit_amounts(
+            pool.sqrt_price_q64,
+            -600,
+            600,
+            LIQUIDITY,
+            u64::MAX,
+            u64::MAX,
+        )
+        .unwrap();
+        // At price 1.0 a symmetric range holds equal amounts of each token
+        assert!(amount0 > 0 && amount1 > 0);
+        assert!(amount0.abs_diff(amount1) <= 1);
+    }
+
+    #[test]
+    fn test_deposit_above_maximum_is_rejected() {
+        let pool = create_default_pool();
+        let (amount0, amount1) = deposit_amounts(
+            pool.sqrt_price_q64,
+            -600,
+            600,
+            LIQUIDITY,
+            u64::MAX,
+            u64::MAX,
+        )
+        .unwrap();
+
+        // Exactly the required amounts are accepted
+        assert_eq!(
+            deposit_amounts(pool.sqrt_price_q64, -600, 600, LIQUIDITY, amount0, amount1).unwrap(),
+            (amount0, amount1)
+        );
+        assert_eq!(
+            deposit_amounts(
+                pool.sqrt_price_q64,
+                -600,
+                600,
+                LIQUIDITY,
+                amount0 - 1,
+                amount1
+            )
+            .unwrap_err(),
+            ErrorCode::SlippageExceeded.into()
+        );
+        assert_eq!(
+            deposit_amounts(
+                pool.sqrt_price_q64,
+                -600,
+                600,
+                LIQUIDITY,
+                amount0,
+                amount1 - 1
+            )
+            .unwrap_err(),
+            ErrorCode::SlippageExceeded.into()
+        );
+        // A range above the price needs no token1, so a zero maximum is enough
+        deposit_amounts(pool.sqrt_price_q64, 60, 600, LIQUIDITY, u64::MAX, 0).unwrap();
+    }
+
+    #[test]
+    fn test_vault_balances_equal_deposits_minus_withdrawals() {
+        let pool = create_default_pool();
+        let ranges = [(60, 600), (-600, -60), (-600, 600), (-120, 1_200)];
+        let (mut vault0, mut vault1) = (0u64, 0u64);
+        let (mut deposited0, mut deposited1) = (0u64, 0u64);
+        for &(lower, upper) in &ranges {
+            let (amount0, amount1) = deposit_amounts(
+                pool.sqrt_price_q64,
+                lower,
+                upper,
+                LIQUIDITY,
+                u64::MAX,
+                u64::MAX,
+            )
+            .unwrap();
+            vault0 += amount0;
+            vault1 += amount1;
+            deposited0 += amount0;
+            deposited1 += amount1;
+        }
+
+        // Withdraw each position in four parts, as separate decrease_liquidity calls would
+        let (mut withdrawn0, mut withdrawn1) = (0u64, 0u64);
+        for &(lower, upper) in &ranges {
+            for _ in 0..4 {
+                let (amount0, amount1) = withdrawal_amounts(&pool, lower, upper, LIQUIDITY / 4);
+                vault0 = vault0.checked_sub(amount0).expect("vault0 overdrawn");
+                vault1 = vault1.checked_sub(amount1).expect("vault1 overdrawn");
+                withdrawn0 += amount0;
+                withdrawn1 += amount1;
+            }
+        }
+
+        assert_eq!(vault0, deposited0 - withdrawn0);
+        assert_eq!(vault1, deposited1 - withdrawn1);
+        // Only rounding dust is left behind: at most one unit per token per withdrawal
+        let withdrawals = (ranges.len() * 4) as u64;
+        assert!(vault0 <= withdrawals && vault1 <= withdrawals);
+    }
+
+    proptest! {
+        #[test]
+        fn proptest_deposit_covers_withdrawal(
+            current_tick in -1_000i32..1_000,
+            lower in -200i32..200,
+            width in 1i32..200,
+            liquidity in MIN_LIQUIDITY..1_000_000_000_000_000u128,
+        ) {
+            let mut pool = create_default_pool();
+            pool.sqrt_price_q64 = math::tick_to_sqrt_price_q64(current_tick * 10).unwrap();
+            let (tick_lower, tick_upper) = (lower * 60, (lower + width) * 60);
+
+            let (deposit0, deposit1) = deposit_amounts(
+                pool.sqrt_price_q64,
+                tick_lower,
+                tick_upper,
+                liquidity,
+                u64::MAX,
+                u64::MAX,
+            )
+            .unwrap();
+            let (withdraw0, withdraw1) = withdrawal_amounts(&pool, tick_lower, tick_upper, liquidity);
+            prop_assert!(withdraw0 <= deposit0 && deposit0 - withdraw0 <= 1);
+            prop_assert!(withdraw1 <= deposit1 && deposit1 - withdraw1 <= 1);
+        }
+    }
+}
+
+mod volume_tests {
+    use super::*;
+
+    /// Noon on the pool's first day.
+    const NOON: i64 = VOLUME_WINDOW_SECS / 2;
+
+    fn pool_with_liquidity() -> Pool {
+        let mut pool = create_default_pool();
+        pool.liquidity = float_to_q64(10000.0);
+        pool
+    }
+
+    #[test]
+    fn test_new_pool_starts_counting_at_its_day() {
+        let mut params = default_initialize_pool_params();
+        params.timestamp = 3 * VOLUME_WINDOW_SECS + NOON;
+        let mut pool = Pool::default();
+        pool.initialize(params).unwrap();
+        assert_eq!(pool.volume_24h, 0);
+        assert_eq!(pool.volume_window_start, 3 * VOLUME_WINDOW_SECS);
+    }
+
+    #[test]
+    fn test_swaps_count_token0_in_both_directions() {
+        let mut pool = pool_with_liquidity();
+        let (amount_in, _) = pool
+            .swap(
+                true,
+                1_000_000,
+                float_to_q64(0.999),
+                &Pubkey::new_unique(),
+                &[],
+                NOON,
+            )
+            .unwrap();
+        assert_eq!(pool.volume_24h as u128, amount_in);
+
+        // Token1 in, token0 out
+        let (_, amount_out) = pool
+            .swap(
+                false,
+                1_000_000,
+                float_to_q64(1.001),
+                &Pubkey::new_unique(),
+                &[],
+                NOON + 60,
+            )
+            .unwrap();
+        assert_eq!(pool.volume_24h as u128, amount_in + amount_out);
+        assert_eq!(pool.volume_24h_at(NOON + 60), pool.volume_24h);
+    }
+
+    #[test]
+    fn test_first_swap_of_a_new_day_resets_the_volume() {
+        let mut pool = pool_with_liquidity();
+        pool.record_volume(5_000, NOON);
+        pool.record_volume(7_000, VOLUME_WINDOW_SECS - 1);
+        assert_eq!(pool.volume_24h, 12_000);
+
+        // Until a swap lands in the next day, readers see no volume for it
+        let next_day = VOLUME_WINDOW_SECS + NOON;
+        assert_eq!(pool.volume_24h_at(VOLUME_WINDOW_SECS - 1), 12_000);
+        assert_eq!(pool.volume_24h_at(next_day), 0);
+
+        pool.record_volume(1_000, next_day);
+        assert_eq!(pool.volume_24h, 1_000);
+        assert_eq!(pool.volume_window_start, VOLUME_WINDOW_SECS);
+        assert_eq!(pool.volume_24h_at(next_day), 1_000);
+    }
+
+    #[test]
+    fn test_volume_saturates() {
+        let mut pool = pool_with_liquidity();
+        pool.record_volume(u64::MAX - 1, NOON);
+        pool.record_volume(10, NOON);
+        assert_eq!(pool.volume_24h, u64::MAX);
+    }
+}
+
+mod quote_tolerance_tests {
+    use super::*;
+    use crate::state::pool::QuoteResult;
+
+    fn quote_paying(amount_out: u64) -> QuoteResult {
+        QuoteResult {
+            amount_out,
+            amount_in_consumed: 1_000_000,
+            sqrt_price_after: 1u128 << 64,
+            ticks_crossed: 0,
+            fee_paid: 3_000,
+        }
+    }
+
+    #[test]
+    fn test_quote_within_tolerance_either_way_passes() {
+        // 50 bps of 1_000_000 is 5_000
+        quote_paying(1_000_000)
+            .ensure_amount_out_within(1_000_000, 0)
+            .unwrap();
+        quote_paying(995_000)
+            .ensure_amount_out_within(1_000_000, 50)
+            .unwrap();
+        quote_paying(1_005_000)
+            .ensure_amount_out_within(1_000_000, 50)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_quote_beyond_tolerance_is_rejected() {
+        for amount_out in [994_999, 1_005_001] {
+            assert_eq!(
+                quote_paying(amount_out)
+                    .ensure_amount_out_within(1_000_000, 50)
+                    .unwrap_err(),
+                ErrorCode::QuoteDeviationExceeded.into()
+            );
+        }
+        // A zero tolerance accepts the expected output only
+        assert_eq!(
+            quote_paying(999_999)
+                .ensure_amount_out_within(1_000_000, 0)
+                .unwrap_err(),
+            ErrorCode::QuoteDeviationExceeded.into()
+        );
+    }
+
+    #[test]
+    fn test_tolerance_above_100_percent_is_rejected() {
+        assert_eq!(
+            quote_paying(1_000_000)
+                .ensure_amount_out_within(1_000_000, 10_001)
+                .unwrap_err(),
+            ErrorCode::InvalidInput.into()
+        );
+        quote_paying(0)
+            .ensure_amount_out_within(1_000_000, 10_000)
+            .unwrap();
+    }
 }
@@ -995,4 +995,706 @@ mod position_tests {
             Ok(())
         }
     }
+
+    /// Tests for is_in_range
+    mod position_in_range_tests {
+        use super::*;
+        use crate::state::pool::Pool;
+
+        fn position(tick_lower: i32, tick_upper: i32) -> PositionData {
+            let mut position = PositionData::default();
+            position
+                .initialize(
+                    Pubkey::new_unique(),
+                    Pubkey::new_unique(),
+                    tick_lower,
+                    tick_upper,
+                    1000,
+                )
+                .unwrap();
+            position
+        }
+
+        fn pool_at(current_tick: i32) -> Pool {
+            Pool {
+                current_tick,
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn test_position_above_current_tick_is_out_of_range() {
+            assert!(!position(60, 120).is_in_range(&pool_at(0)));
+            assert!(!position(60, 120).is_in_range(&pool_at(59)));
+        }
+
+        #[test]
+        fn test_position_around_current_tick_is_in_range() {
+            assert!(position(-60, 60).is_in_range(&pool_at(0)));
+            // The lower bound is inclusive
+            assert!(position(-60, 60).is_in_range(&pool_at(-60)));
+            assert!(position(-60, 60).is_in_range(&pool_at(59)));
+        }
+
+        #[test]
+        fn test_position_below_current_tick_is_out_of_range() {
+            // The upper bound is exclusive
+            assert!(!position(-120, -60).is_in_range(&pool_at(-60)));
+            assert!(!position(-120, -60).is_in_range(&pool_at(0)));
+        }
+    }
+
+    /// Tests for the close_position preconditions
+    mod position_close_tests {
+        use super::*;
+
+        fn empty_position() -> Result<PositionData> {
+            let mut position = PositionData::default();
+            let owner = create_test_pubkey("3rTXd8nRJqiKHiLGkPAuaALpGHKxLvPKvSJ5F5gTr3Z2");
+            let pool = create_test_pubkey("7Z6YgXBdQG7dRnQwA1TbMsJTSBMsyzTF6NXJ8Lee7Eks");
+            position.initialize(owner, pool, -10, 10, 0)?;
+            Ok(position)
+        }
+
+        fn assert_position_not_empty(result: Result<()>) {
+            match result {
+                Err(Error::AnchorError(e)) => {
+                    assert_eq!(
+                        e.error_code_number,
+                        ErrorCode::PositionNotEmpty as u32 + 6000
+                    )
+                }
+                _ => panic!("Expected AnchorError(PositionNotEmpty), got {result:?}"),
+            }
+        }
+
+        #[test]
+        fn test_empty_position_is_closable() -> Result<()> {
+            let position = empty_position()?;
+            position.ensure_closable()
+        }
+
+        #[test]
+        fn test_position_with_liquidity_is_not_closable() -> Result<()> {
+            let mut position = empty_position()?;
+            position.liquidity = 1;
+            assert_position_not_empty(position.ensure_closable());
+            Ok(())
+        }
+
+        #[test]
+        fn test_position_with_tokens_owed_is_not_closable() -> Result<()> {
+            let mut position = empty_position()?;
+            position.tokens_owed_0 = 1;
+            assert_position_not_empty(position.ensure_closable());
+
+            position.tokens_owed_0 = 0;
+            position.tokens_owed_1 = 1;
+            assert_position_not_empty(position.ensure_closable());
+            Ok(())
+        }
+
+        #[test]
+        fn test_initialize_clears_tokens_owed() -> Result<()> {
+            let mut position = empty_position()?;
+            position.tokens_owed_0 = 5;
+            position.tokens_owed_1 = 7;
+
+            position.initialize(position.owner, position.pool, -10, 10, 0)?;
+
+            assert_eq!(position.tokens_owed_0, 0);
+            assert_eq!(position.tokens_owed_1, 0);
+            position.ensure_closable()
+        }
+    }
+
+    /// Tests for fee accounting shared by fee collection and rebalancing
+    mod position_fee_tests {
+        use super::*;
+        use crate::constants::Q64;
+        use crate::math;
+        use crate::state::pool::{InitializePoolParams, Pool};
+        use crate::tick::TickData;
+
+        const LIQUIDITY: u128 = 1 << 40;
+        /// Fees earned by `LIQUIDITY` for each unit passed to `accrue`.
+        pub(super) const UNIT: u64 = 1 << 20;
+
+        pub(super) struct Setup {
+            pub(super) pool: Pool,
+            pub(super) position: PositionData,
+            pub(super) tick_lower: TickData,
+            pub(super) tick_upper: TickData,
+        }
+
+        /// Pool at tick 0 with a single position on [-120, 120].
+        pub(super) fn setup() -> Result<Setup> {
+            let mut pool = Pool::default();
+            pool.initialize(InitializePoolParams {
+                bump: 1,
+                factory: Pubkey::new_unique(),
+                authority: Pubkey::new_unique(),
+                token0_mint: Pubkey::new_unique(),
+                token1_mint: Pubkey::new_unique(),
+                token0_vault: Pubkey::new_unique(),
+                token1_vault: Pubkey::new_unique(),
+                token_pair: Pubkey::new_unique(),
+                initial_sqrt_price_q64: math::tick_to_sqrt_price_q64(0)?,
+                fee_rate: 30,
+                protocol_fee: 0,
+                immutable_parameters: false,
+                tick_spacing: 60,
+                timestamp: 0,
+            })?;
+            let mut tick_lower = TickData::default();
+            let mut tick_upper = TickData::default();
+            pool.modify_liquidity_for_test(
+                -120,
+                120,
+                LIQUIDITY as i128,
+                &mut tick_lower,
+                &mut tick_upper,
+            )?;
+
+            let mut position = PositionData::default();
+            position.initialize(
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                -120,
+                120,
+                LIQUIDITY,
+            )?;
+            position.snapshot_fee_growth_inside(&pool, &tick_lower, &tick_upper);
+
+            Ok(Setup {
+                pool,
+                position,
+                tick_lower,
+                tick_upper,
+            })
+        }
+
+        /// What collect_fees does to the position: credit fees, then pay out all owed tokens.
+        pub(super) fn collect(setup: &mut Setup) -> Result<(u64, u64)> {
+            setup
+                .position
+                .update_fees(&setup.pool, &setup.tick_lower, &setup.tick_upper)?;
+            let collected = (setup.position.tokens_owed_0, setup.position.tokens_owed_1);
+            setup.position.tokens_owed_0 = 0;
+            setup.position.tokens_owed_1 = 0;
+            Ok(collected)
+        }
+
+        /// What update_position does to the position: credit fees, then move the liquidity.
+        fn rebalance(setup: &mut Setup, new_tick_lower: i32, new_tick_upper: i32) -> Result<()> {
+            let liquidity = setup.position.liquidity as i128;
+            setup
+                .position
+                .update_fees(&setup.pool, &setup.tick_lower, &setup.tick_upper)?;
+            setup.pool.modify_liquidity_for_test(
+                setup.position.tick_lower_index,
+                setup.position.tick_upper_index,
+                -liquidity,
+                &mut setup.tick_lower,
+                &mut setup.tick_upper,
+            )?;
+
+            setup.position.tick_lower_index = new_tick_lower;
+            setup.position.tick_upper_index = new_tick_upper;
+            setup.tick_lower = TickData::default();
+            setup.tick_upper = TickData::default();
+            setup.pool.modify_liquidity_for_test(
+                new_tick_lower,
+                new_tick_upper,
+                liquidity,
+                &mut setup.tick_lower,
+                &mut setup.tick_upper,
+            )?;
+            setup.position.snapshot_fee_growth_inside(
+                &setup.pool,
+                &setup.tick_lower,
+                &setup.tick_upper,
+            );
+            Ok(())
+        }
+
+        /// Swap fees paid to the pool's active liquidity, in multiples of `UNIT` for `LIQUIDITY`.
+        pub(super) fn accrue(pool: &mut Pool, units_0: u128, units_1: u128) {
+            pool.fee_growth_global_0_q64 += units_0 * (Q64 >> 20);
+            pool.fee_growth_global_1_q64 += units_1 * (Q64 >> 20);
+        }
+
+        #[test]
+        fn test_collect_then_rebalance_accounts_every_fee_once() -> Result<()> {
+            let mut setup = setup()?;
+
+            // Fees while in range are collected
+            accrue(&mut setup.pool, 3, 5);
+            let first = collect(&mut setup)?;
+            assert_eq!(first, (3 * UNIT, 5 * UNIT));
+
+            // Collecting again right away pays nothing more
+            assert_eq!(collect(&mut setup)?, (0, 0));
+
+            // Fees earned before a rebalance are kept as owed tokens
+            accrue(&mut setup.pool, 2, 0);
+            rebalance(&mut setup, 60, 180)?;
+            assert_eq!(
+                (setup.position.tokens_owed_0, setup.position.tokens_owed_1),
+                (2 * UNIT, 0)
+            );
+
+            // The new range is above the price, so later fees are not earned by the position
+            accrue(&mut setup.pool, 7, 7);
+            let second = collect(&mut setup)?;
+            assert_eq!(second, (2 * UNIT, 0));
+            assert_eq!(collect(&mut setup)?, (0, 0));
+            Ok(())
+        }
+
+        #[test]
+        fn test_rebalance_then_collect_matches_collect_then_rebalance() -> Result<()> {
+            let mut collect_first = setup()?;
+            accrue(&mut collect_first.pool, 4, 1);
+            let mut total = collect(&mut collect_first)?;
+            rebalance(&mut collect_first, -60, 60)?;
+            accrue(&mut collect_first.pool, 2, 3);
+            let (later_0, later_1) = collect(&mut collect_first)?;
+            total = (total.0 + later_0, total.1 + later_1);
+
+            let mut rebalance_first = setup()?;
+            accrue(&mut rebalance_first.pool, 4, 1);
+            rebalance(&mut rebalance_first, -60, 60)?;
+            accrue(&mut rebalance_first.pool, 2, 3);
+            let total_rebalance_first = collect(&mut rebalance_first)?;
+
+            assert_eq!(total, total_rebalance_first);
+            assert_eq!(total, (6 * UNIT, 4 * UNIT));
+            Ok(())
+        }
+
+        #[test]
+        fn test_update_fees_without_growth_credits_nothing() -> Result<()> {
+            let mut setup = setup()?;
+            setup
+                .position
+                .update_fees(&setup.pool, &setup.tick_lower, &setup.tick_upper)?;
+            assert_eq!(setup.position.tokens_owed_0, 0);
+            assert_eq!(setup.position.tokens_owed_1, 0);
+            Ok(())
+        }
+    }
+
+    /// Tests for reading positions stored under earlier layouts and migrating them
+    mod position_migration_tests {
+        use super::position_fee_tests::{accrue, collect, setup, UNIT};
+        use super::*;
+
+        /// Size of the first position layout: the owner, pool, range and liquidity.
+        const FIRST_LAYOUT_LEN: usize = 8 + 32 + 32 + 4 + 4 + 16;
+
+        /// The account data of `position` stored under a layout of `len` bytes.
+        fn stored(position: &PositionData, len: usize) -> Vec<u8> {
+            let mut data = Vec::new();
+            position.try_serialize(&mut data).unwrap();
+            assert_eq!(data.len(), PositionData::LEN);
+            data.truncate(len);
+            data
+        }
+
+        #[test]
+        fn test_current_layout_reads_back_unchanged() -> Result<()> {
+            let mut position = setup()?.position;
+            position.tokens_owed_0 = 5;
+            position.position_mint = Pubkey::new_unique();
+            position.in_owner_index = true;
+            position.entry_sqrt_price_q64 = 1u128 << 64;
+
+            let read =
+                PositionData::try_deserialize_any_layout(&stored(&position, PositionData::LEN))?;
+            assert_eq!(format!("{read:?}"), format!("{position:?}"));
+            Ok(())
+        }
+
+        #[test]
+        fn test_first_layout_reads_with_empty_new_fields() -> Result<()> {
+            let mut position = setup()?.position;
+            position.tokens_owed_1 = 5;
+            position.position_index = 3;
+            position.next_in_owner_index = Pubkey::new_unique();
+
+            let read =
+                PositionData::try_deserialize_any_layout(&stored(&position, FIRST_LAYOUT_LEN))?;
+            assert_eq!(
+                (read.owner, read.pool, read.liquidity),
+                (position.owner, position.pool, position.liquidity)
+            );
+            assert_eq!(
+                (read.tick_lower_index, read.tick_upper_index),
+                (position.tick_lower_index, position.tick_upper_index)
+            );
+            assert_eq!((read.tokens_owed_0, read.tokens_owed_1), (0, 0));
+            assert_eq!(read.fee_growth_inside_0_last_q64, 0);
+            assert_eq!(read.position_mint, Pubkey::default());
+            assert_eq!(read.position_index, 0);
+            assert!(!read.in_owner_index);
+            assert_eq!(read.next_in_owner_index, Pubkey::default());
+            assert_eq!(read.entry_sqrt_price_q64, 0);
+            Ok(())
+        }
+
+        #[test]
+        fn test_account_of_another_type_is_rejected() {
+            let mut data = stored(&PositionData::default(), FIRST_LAYOUT_LEN);
+            data[..8].copy_from_slice(PositionCounter::DISCRIMINATOR);
+            assert_eq!(
+                PositionData::try_deserialize_any_layout(&data).unwrap_err(),
+                anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch.into()
+            );
+        }
+
+        #[test]
+        fn test_first_layout_position_earns_fees_from_migration_on() -> Result<()> {
+            let mut setup = setup()?;
+            // Fees earned before the migration were never tracked for the position
+            accrue(&mut setup.pool, 3, 5);
+            let data = stored(&setup.position, FIRST_LAYOUT_LEN);
+
+            setup.position = PositionData::try_deserialize_any_layout(&data)?;
+            setup.position.migrate_layout(
+                data.len(),
+                &setup.pool,
+                &setup.tick_lower,
+                &setup.tick_upper,
+            );
+            assert_eq!(collect(&mut setup)?, (0, 0));
+
+            accrue(&mut setup.pool, 2, 1);
+            assert_eq!(collect(&mut setup)?, (2 * UNIT, UNIT));
+            Ok(())
+        }
+
+        #[test]
+        fn test_migration_keeps_stored_fee_snapshots() -> Result<()> {
+            let mut setup = setup()?;
+            accrue(&mut setup.pool, 3, 5);
+            let data = stored(&setup.position, PositionData::FEE_GROWTH_SNAPSHOTS_END);
+
+            setup.position = PositionData::try_deserialize_any_layout(&data)?;
+            setup.position.migrate_layout(
+                data.len(),
+                &setup.pool,
+                &setup.tick_lower,
+                &setup.tick_upper,
+            );
+            // Fees earned before the migration are still owed
+            assert_eq!(collect(&mut setup)?, (3 * UNIT, 5 * UNIT));
+            Ok(())
+        }
+
+        #[test]
+        fn test_position_without_entry_price_is_given_pool_price() -> Result<()> {
+            let mut setup = setup()?;
+            setup.position.entry_sqrt_price_q64 = 1u128 << 64;
+            setup.pool.sqrt_price_q64 = crate::math::tick_to_sqrt_price_q64(60)?;
+            let data = stored(&setup.position, PositionData::ENTRY_SQRT_PRICE_END - 16);
+
+            let mut position = PositionData::try_deserialize_any_layout(&data)?;
+            assert_eq!(position.entry_sqrt_price_q64, 0);
+            position.migrate_layout(
+                data.len(),
+                &setup.pool,
+                &setup.tick_lower,
+                &setup.tick_upper,
+            );
+            assert_eq!(position.entry_sqrt_price_q64, setup.pool.sqrt_price_q64);
+            assert_eq!(
+                position.fee_growth_inside_0_last_q64,
+                setup.position.fee_growth_inside_0_last_q64
+            );
+            Ok(())
+        }
+    }
+
+    /// Tests for who may act on a position, with and without a position NFT
+    mod position_holder_tests {
+        use super::*;
+        use anchor_lang::solana_program::program_option::COption;
+        use anchor_lang::solana_program::program_pack::Pack;
+        use anchor_spl::token::{spl_token, TokenAccount};
+
+        fn position(owner: Pubkey, position_mint: Pubkey) -> PositionData {
+            let mut position = PositionData::default();
+            position
+                .initialize(owner, Pubkey::new_unique(), -60, 60, 1_000)
+                .unwrap();
+            position.position_mint = position_mint;
+            position
+        }
+
+        fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> TokenAccount {
+            let token_account = spl_token::state::Account {
+                mint,
+                owner,
+                amount,
+                delegate: COption::None,
+                state: spl_token::state::AccountState::Initialized,
+                is_native: COption::None,
+                delegated_amount: 0,
+                close_authority: COption::None,
+            };
+            let mut data = vec![0; spl_token::state::Account::LEN];
+            token_account.pack_into_slice(&mut data);
+            TokenAccount::try_deserialize(&mut &data[..]).unwrap()
+        }
+
+        #[test]
+        fn test_initialize_leaves_position_without_nft() -> Result<()> {
+            let mut position = PositionData {
+                position_mint: Pubkey::new_unique(),
+                ..Default::default()
+            };
+            position.initialize(Pubkey::new_unique(), Pubkey::new_unique(), -60, 60, 1_000)?;
+            assert_eq!(position.position_mint, Pubkey::default());
+            Ok(())
+        }
+
+        #[test]
+        fn test_position_without_nft_is_held_by_owner() {
+            let owner = Pubkey::new_unique();
+            let position = position(owner, Pubkey::default());
+            assert!(position.is_held_by(&owner, None));
+            assert!(!position.is_held_by(&Pubkey::new_unique(), None));
+
+            // A token account does not let anyone else in
+            let other = Pubkey::new_unique();
+            let token_account = token_account(Pubkey::new_unique(), other, 1);
+            assert!(!position.is_held_by(&other, Some(&token_account)));
+        }
+
+        #[test]
+        fn test_position_with_nft_is_held_by_nft_holder() {
+            let minter = Pubkey::new_unique();
+            let holder = Pubkey::new_unique();
+            let position_mint = Pubkey::new_unique();
+            let position = position(minter, position_mint);
+
+            let holder_account = token_account(position_mint, holder, 1);
+            assert!(position.is_held_by(&holder, Some(&holder_account)));
+            // Someone else passing the holder's token account
+            assert!(!position.is_held_by(&minter, Some(&holder_account)));
+            // The minter no longer holds the position once the NFT is transferred
+            assert!(!position.is_held_by(&minter, None));
+            let emptied_account = token_account(position_mint, minter, 0);
+            assert!(!position.is_held_by(&minter, Some(&emptied_account)));
+        }
+
+        #[test]
+        fn test_nft_holder_needs_the_position_mint() {
+            let holder = Pubkey::new_unique();
+            let position = position(holder, Pubkey::new_unique());
+            let other_nft = token_account(Pubkey::new_unique(), holder, 1);
+            assert!(!position.is_held_by(&holder, Some(&other_nft)));
+            assert!(!position.is_held_by(&holder, None));
+        }
+    }
+
+    /// Tests for the per-owner position counter that indexes positions on the same range
+    mod position_counter_tests {
+        use super::*;
+
+        #[test]
+        fn test_take_index_counts_up_from_zero() -> Result<()> {
+            let mut counter = PositionCounter::default();
+            assert_eq!(counter.take_index()?, 0);
+            assert_eq!(counter.take_index()?, 1);
+            assert_eq!(counter.take_index()?, 2);
+            assert_eq!(counter.next_index, 3);
+            Ok(())
+        }
+
+        #[test]
+        fn test_take_index_overflow() {
+            let mut counter = PositionCounter {
+                next_index: u64::MAX,
+            };
+            assert_eq!(
+                counter.take_index().unwrap_err(),
+                ErrorCode::MathOverflow.into()
+            );
+            assert_eq!(counter.next_index, u64::MAX);
+        }
+
+        #[test]
+        fn test_initialize_resets_position_index() -> Result<()> {
+            let mut position = PositionData {
+                position_index: 7,
+                ..Default::default()
+            };
+            position.initialize(Pubkey::new_unique(), Pubkey::new_unique(), -60, 60, 1_000)?;
+            assert_eq!(position.position_index, 0);
+            Ok(())
+        }
+    }
+
+    /// Tests for the per-owner linked list of positions
+    mod owner_position_index_tests {
+        use super::*;
+
+        /// Lists three positions of one owner, returning the index and the positions in
+        /// listing order.
+        fn index_with_three_positions() -> (OwnerPositionIndex, Vec<(Pubkey, PositionData)>) {
+            let owner = Pubkey::new_unique();
+            let mut index = OwnerPositionIndex {
+                owner,
+                ..Default::default()
+            };
+            let mut positions = Vec::new();
+            for _ in 0..3 {
+                let mut position = PositionData {
+                    owner,
+                    ..Default::default()
+                };
+                let key = Pubkey::new_unique();
+                index.push(key, &mut position).unwrap();
+                positions.push((key, position));
+            }
+            (index, positions)
+        }
+
+        /// Follows the links from the head of the index.
+        fn walk(index: &OwnerPositionIndex, positions: &[(Pubkey, PositionData)]) -> Vec<Pubkey> {
+            let mut listed = Vec::new();
+            let mut next = index.head;
+            while next != Pubkey::default() {
+                listed.push(next);
+                let (_, position) = positions.iter().find(|(key, _)| *key == next).unwrap();
+                next = position.next_in_owner_index;
+            }
+            listed
+        }
+
+        #[test]
+        fn test_push_lists_newest_first() {
+            let (index, positions) = index_with_three_positions();
+            assert_eq!(index.count, 3);
+            assert_eq!(
+                walk(&index, &positions),
+                vec![positions[2].0, positions[1].0, positions[0].0]
+            );
+            assert!(positions
+                .iter()
+                .all(|(_, position)| position.in_owner_index));
+        }
+
+        #[test]
+        fn test_push_twice_fails() {
+            let (mut index, mut positions) = index_with_three_positions();
+            let (key, position) = &mut positions[0];
+            assert_eq!(
+                index.push(*key, position).unwrap_err(),
+                ErrorCode::InvalidOwnerIndexLink.into()
+            );
+            assert_eq!(index.count, 3);
+        }
+
+        #[test]
+        fn test_remove_head() -> Result<()> {
+            let (mut index, mut positions) = index_with_three_positions();
+            let (key, mut position) = positions.remove(2);
+            index.remove(key, &mut position, None)?;
+
+            assert_eq!(index.count, 2);
+            assert_eq!(
+                walk(&index, &positions),
+                vec![positions[1].0, positions[0].0]
+            );
+            assert!(!position.in_owner_index);
+            assert_eq!(position.next_in_owner_index, Pubkey::default());
+            Ok(())
+        }
+
+        #[test]
+        fn test_remove_middle_relinks_previous() -> Result<()> {
+            let (mut index, mut positions) = index_with_three_positions();
+            let (key, mut position) = positions.remove(1);
+            index.remove(key, &mut position, Some(&mut positions[1].1))?;
+
+            assert_eq!(index.count, 2);
+            assert_eq!(
+                walk(&index, &positions),
+                vec![positions[1].0, positions[0].0]
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn test_remove_tail_relinks_previous() -> Result<()> {
+            let (mut index, mut positions) = index_with_three_positions();
+            let (key, mut position) = positions.remove(0);
+            index.remove(key, &mut position, Some(&mut positions[0].1))?;
+
+            assert_eq!(index.count, 2);
+            assert_eq!(
+                walk(&index, &positions),
+                vec![positions[1].0, positions[0].0]
+            );
+            assert_eq!(positions[0].1.next_in_owner_index, Pubkey::default());
+            Ok(())
+        }
+
+        #[test]
+        fn test_remove_requires_previous_linking_to_position() {
+            let (mut index, mut positions) = index_with_three_positions();
+            let (key, mut position) = positions.remove(0);
+
+            // No previous position for a position below the head
+            assert_eq!(
+                index.remove(key, &mut position, None).unwrap_err(),
+                ErrorCode::InvalidOwnerIndexLink.into()
+            );
+            // The head links to the middle position, not the tail
+            assert_eq!(
+                index
+                    .remove(key, &mut position, Some(&mut positions[1].1))
+                    .unwrap_err(),
+                ErrorCode::InvalidOwnerIndexLink.into()
+            );
+            // A previous position of another owner
+            positions[0].1.owner = Pubkey::new_unique();
+            assert_eq!(
+                index
+                    .remove(key, &mut position, Some(&mut positions[0].1))
+                    .unwrap_err(),
+                ErrorCode::InvalidOwnerIndexLink.into()
+            );
+            assert_eq!(index.count, 3);
+            assert!(position.in_owner_index);
+        }
+
+        #[test]
+        fn test_remove_unlisted_position_fails() {
+            let (mut index, _) = index_with_three_positions();
+            let mut position = PositionData::default();
+            assert_eq!(
+                index
+                    .remove(Pubkey::new_unique(), &mut position, None)
+                    .unwrap_err(),
+                ErrorCode::InvalidOwnerIndexLink.into()
+            );
+        }
+
+        #[test]
+        fn test_initialize_clears_owner_index_link() -> Result<()> {
+            let mut position = PositionData {
+                in_owner_index: true,
+                next_in_owner_index: Pubkey::new_unique(),
+                ..Default::default()
+            };
+            position.initialize(Pubkey::new_unique(), Pubkey::new_unique(), -60, 60, 1_000)?;
+            assert!(!position.in_owner_index);
+            assert_eq!(position.next_in_owner_index, Pubkey::default());
+            Ok(())
+        }
+    }
 }
@@ -0,0 +1,134 @@
+use super::tick_array_test::tick_loader;
+use crate::constants::MIN_SQRT_PRICE;
+use crate::errors::ErrorCode;
+use crate::instructions::resize_pool_account::validate_resize;
+use crate::state::pool::{InitializePoolParams, Pool};
+use crate::tick_array::TickArray;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use anchor_lang::solana_program::system_instruction::MAX_PERMITTED_DATA_LENGTH;
+
+/// Pool at tick 0 with liquidity on [-120, 120), stored in an account of `Pool::LEN`
+/// bytes.
+fn pool_account_data() -> Vec<u8> {
+    let mut pool = Pool::default();
+    pool.initialize(InitializePoolParams {
+        bump: 1,
+        factory: Pubkey::new_unique(),
+        authority: Pubkey::new_unique(),
+        token0_mint: Pubkey::new_unique(),
+        token1_mint: Pubkey::new_unique(),
+        token0_vault: Pubkey::new_unique(),
+        token1_vault: Pubkey::new_unique(),
+        token_pair: Pubkey::new_unique(),
+        initial_sqrt_price_q64: 1u128 << 64,
+        fee_rate: 30,
+        protocol_fee: 0,
+        immutable_parameters: false,
+        tick_spacing: 60,
+        timestamp: 0,
+    })
+    .unwrap();
+    let pool_key = Pubkey::new_unique();
+    pool.modify_liquidity(
+        -120,
+        120,
+        1 << 80,
+        &tick_loader(&pool_key, -120),
+        &tick_loader(&pool_key, 120),
+    )
+    .unwrap();
+
+    let mut data = vec![0u8; Pool::LEN];
+    pool.try_serialize(&mut data.as_mut_slice()).unwrap();
+    data
+}
+
+mod validate_resize_tests {
+    use super::*;
+
+    #[test]
+    fn test_shrinking_below_serialized_length_is_rejected() {
+        let current_len = Pool::LEN + 1_000;
+        assert_eq!(
+            validate_resize(current_len, Pool::LEN - 1, Pool::LEN).unwrap_err(),
+            ErrorCode::AccountShrinkTooSmall.into()
+        );
+        validate_resize(current_len, Pool::LEN, Pool::LEN).unwrap();
+
+        let tick_array_len = 8 + TickArray::LEN;
+        assert_eq!(
+            validate_resize(tick_array_len, tick_array_len - 8, tick_array_len).unwrap_err(),
+            ErrorCode::AccountShrinkTooSmall.into()
+        );
+    }
+
+    #[test]
+    fn test_growth_is_bounded_per_call() {
+        validate_resize(
+            Pool::LEN,
+            Pool::LEN + MAX_PERMITTED_DATA_INCREASE,
+            Pool::LEN,
+        )
+        .unwrap();
+        assert_eq!(
+            validate_resize(
+                Pool::LEN,
+                Pool::LEN + MAX_PERMITTED_DATA_INCREASE + 1,
+                Pool::LEN
+            )
+            .unwrap_err(),
+            ErrorCode::AccountGrowthTooLarge.into()
+        );
+        // Resizing to the current size is a no-op
+        validate_resize(Pool::LEN, Pool::LEN, Pool::LEN).unwrap();
+    }
+
+    #[test]
+    fn test_growth_is_bounded_by_largest_account() {
+        let max_len = MAX_PERMITTED_DATA_LENGTH as usize;
+        validate_resize(max_len - 1, max_len, Pool::LEN).unwrap();
+        assert_eq!(
+            validate_resize(max_len, max_len + 1, Pool::LEN).unwrap_err(),
+            ErrorCode::AccountGrowthTooLarge.into()
+        );
+    }
+}
+
+mod resized_pool_tests {
+    use super::*;
+
+    #[test]
+    fn test_grown_pool_deserializes_and_swaps() {
+        let data = pool_account_data();
+        let mut grown = data.clone();
+        // `realloc` zero-fills the new tail
+        grown.resize(Pool::LEN + MAX_PERMITTED_DATA_INCREASE, 0);
+
+        let mut pool = Pool::try_deserialize(&mut grown.as_slice()).unwrap();
+        let original = Pool::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(pool.try_to_vec().unwrap(), original.try_to_vec().unwrap());
+
+        // A swap within the range needs no tick accounts
+        let (amount_in, amount_out) = pool
+            .swap(
+                true,
+                1_000_000,
+                MIN_SQRT_PRICE,
+                &Pubkey::new_unique(),
+                &[],
+                0,
+            )
+            .unwrap();
+        assert_eq!(amount_in, 1_000_000);
+        assert!(amount_out > 0);
+        assert!(pool.sqrt_price_q64 < original.sqrt_price_q64);
+
+        // The swapped pool is written back without touching the tail
+        pool.try_serialize(&mut grown.as_mut_slice()).unwrap();
+        assert!(grown[Pool::LEN..].iter().all(|&byte| byte == 0));
+        let reread = Pool::try_deserialize(&mut grown.as_slice()).unwrap();
+        assert_eq!(reread.try_to_vec().unwrap(), pool.try_to_vec().unwrap());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::constants::MAX_SWAP_HOPS;
+use crate::errors::ErrorCode;
+use crate::instructions::swap_multi_hop::{hop_mints, swap_hop, validate_hops, HopParams};
+use crate::math;
+use crate::state::pool::{InitializePoolParams, Pool};
+use crate::tick_array::SwapTickAccounts;
+
+use anchor_lang::prelude::*;
+
+const LIQUIDITY: u128 = 1 << 64;
+
+/// Pool of `token0_mint` and `token1_mint` at tick 0, with `LIQUIDITY` active and no
+/// initialized ticks.
+fn create_pool(token0_mint: Pubkey, token1_mint: Pubkey) -> Pool {
+    let mut pool = Pool::default();
+    pool.initialize(InitializePoolParams {
+        bump: 1,
+        factory: Pubkey::new_unique(),
+        authority: Pubkey::new_unique(),
+        token0_mint,
+        token1_mint,
+        token0_vault: Pubkey::new_unique(),
+        token1_vault: Pubkey::new_unique(),
+        token_pair: Pubkey::new_unique(),
+        initial_sqrt_price_q64: 1u128 << 64,
+        fee_rate: 30,
+        protocol_fee: 0,
+        immutable_parameters: false,
+        tick_spacing: 60,
+        timestamp: 0,
+    })
+    .unwrap();
+    pool.liquidity = LIQUIDITY;
+    pool
+}
+
+fn hop(zero_for_one: bool, sqrt_price_limit_q64: u128) -> HopParams {
+    HopParams {
+        pool: Pubkey::new_unique(),
+        zero_for_one,
+        tick_accounts: 0,
+        sqrt_price_limit_q64,
+    }
+}
+
+fn no_tick_accounts() -> SwapTickAccounts<'static> {
+    SwapTickAccounts {
+        ticks: Vec::new(),
+        tick_arrays: Vec::new(),
+        tick_bitmaps: Vec::new(),
+    }
+}
+
+mod validate_hops_tests {
+    use super::*;
+
+    #[test]
+    fn test_route_of_one_to_max_hops_is_valid() {
+        for len in 1..=MAX_SWAP_HOPS {
+            let hops: Vec<HopParams> = (0..len).map(|_| hop(true, 0)).collect();
+            assert!(validate_hops(&hops).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_empty_route_is_rejected() {
+        assert_eq!(
+            validate_hops(&[]).unwrap_err(),
+            ErrorCode::InvalidHopCount.into()
+        );
+    }
+
+    #[test]
+    fn test_route_longer_than_max_hops_is_rejected() {
+        let hops: Vec<HopParams> = (0..=MAX_SWAP_HOPS).map(|_| hop(true, 0)).collect();
+        assert_eq!(
+            validate_hops(&hops).unwrap_err(),
+            ErrorCode::InvalidHopCount.into()
+        );
+    }
+
+    #[test]
+    fn test_route_through_same_pool_twice_is_rejected() {
+        let first = hop(true, 0);
+        let hops = vec![first.clone(), hop(false, 0), first];
+        assert_eq!(
+            validate_hops(&hops).unwrap_err(),
+            ErrorCode::InvalidHopPath.into()
+        );
+    }
+}
+
+mod swap_hop_tests {
+    use super::*;
+
+    #[test]
+    fn test_hop_mints_follow_direction() {
+        let (mint0, mint1) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let pool = create_pool(mint0, mint1);
+        assert_eq!(hop_mints(&pool, true), (mint0, mint1));
+        assert_eq!(hop_mints(&pool, false), (mint1, mint0));
+    }
+
+    #[test]
+    fn test_output_of_first_hop_feeds_second_hop() {
+        // Route A -> B -> C through pools (A, B) and (C, B): the second hop swaps token1
+        // for token0.
+        let (mint_a, mint_b, mint_c) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut first_pool = create_pool(mint_a, mint_b);
+        let mut second_pool = create_pool(mint_c, mint_b);
+        let first_hop = hop(true, math::tick_to_sqrt_price_q64(-600).unwrap());
+        let second_hop = hop(false, math::tick_to_sqrt_price_q64(600).unwrap());
+        assert_eq!(
+            hop_mints(&first_pool, first_hop.zero_for_one).1,
+            hop_mints(&second_pool, second_hop.zero_for_one).0
+        );
+
+        let amount_in = 1_000_000;
+        let middle_amount = swap_hop(
+            &mut first_pool,
+            &first_hop,
+            amount_in,
+            &no_tick_accounts(),
+            0,
+        )
+        .unwrap()
+        .amount_out;
+        let amount_out = swap_hop(
+            &mut second_pool,
+            &second_hop,
+            middle_amount,
+            &no_tick_accounts(),
+            0,
+        )
+        .unwrap()
+        .amount_out;
+
+        // Each hop pays the pool fee, so the output falls short of the input at par prices.
+        assert!(middle_amount < amount_in);
+        assert!(amount_out > 0 && amount_out < middle_amount);
+        assert!(first_pool.sqrt_price_q64 < 1u128 << 64);
+        assert!(second_pool.sqrt_price_q64 > 1u128 << 64);
+    }
+
+    #[test]
+    fn test_hop_stopped_by_price_limit_is_rejected() {
+        let mut pool = create_pool(Pubkey::new_unique(), Pubkey::new_unique());
+        // A limit one tick away cannot absorb the whole input.
+        let limited_hop = hop(true, math::tick_to_sqrt_price_q64(-1).unwrap());
+
+        let err = swap_hop(&mut pool, &limited_hop, 1 << 90, &no_tick_accounts(), 0).unwrap_err();
+        assert_eq!(err, ErrorCode::PriceLimitReached.into());
+    }
+
+    #[test]
+    fn test_hop_without_liquidity_is_rejected() {
+        let mut pool = create_pool(Pubkey::new_unique(), Pubkey::new_unique());
+        pool.liquidity = 0;
+        let zero_for_one_hop = hop(true, math::tick_to_sqrt_price_q64(-600).unwrap());
+
+        let err =
+            swap_hop(&mut pool, &zero_for_one_hop, 1_000, &no_tick_accounts(), 0).unwrap_err();
+        assert_eq!(err, ErrorCode::PriceLimitReached.into());
+    }
+
+    #[test]
+    fn test_hop_during_flash_loan_is_rejected() {
+        let mut pool = create_pool(Pubkey::new_unique(), Pubkey::new_unique());
+        pool.flash_loan_active = 1;
+        let zero_for_one_hop = hop(true, math::tick_to_sqrt_price_q64(-600).unwrap());
+
+        let err =
+            swap_hop(&mut pool, &zero_for_one_hop, 1_000, &no_tick_accounts(), 0).unwrap_err();
+        assert_eq!(err, ErrorCode::FlashLoanActive.into());
+    }
+
+    #[test]
+    fn test_pool_address_matches_pool_pda() {
+        let mut pool = create_pool(Pubkey::new_unique(), Pubkey::new_unique());
+        let (address, bump) = Pubkey::find_program_address(
+            &[
+                b"pool".as_ref(),
+                pool.token0_mint.as_ref(),
+                pool.token1_mint.as_ref(),
+                pool.fee_rate.to_le_bytes().as_ref(),
+            ],
+            &crate::ID,
+        );
+        pool.bump = bump;
+        assert_eq!(pool.address().unwrap(), address);
+    }
+}
@@ -0,0 +1,924 @@
+use crate::constants::{MAX_BATCH_ITERATIONS, MAX_SWAP_ITERATIONS, MAX_TICK, MIN_TICK};
+use crate::errors::ErrorCode;
+use crate::math;
+use crate::position::PositionData;
+use crate::state::pool::{InitializePoolParams, Pool, SwapResult};
+use crate::tick::TickData;
+use crate::tick_array::*;
+use crate::tick_bitmap::{self, TickBitmap};
+
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use std::collections::BTreeMap;
+
+const TICK_SPACING: u16 = 60;
+/// Number of ticks spanned by one tick array at `TICK_SPACING`.
+const ARRAY_SPAN: i32 = TICK_ARRAY_SIZE as i32 * TICK_SPACING as i32;
+const LIQUIDITY: u128 = 1 << 80;
+
+/// Pool at tick 0 with a tick spacing of `TICK_SPACING` and no liquidity.
+fn create_pool() -> Pool {
+    let mut pool = Pool::default();
+    pool.initialize(InitializePoolParams {
+        bump: 1,
+        factory: Pubkey::new_unique(),
+        authority: Pubkey::new_unique(),
+        token0_mint: Pubkey::new_unique(),
+        token1_mint: Pubkey::new_unique(),
+        token0_vault: Pubkey::new_unique(),
+        token1_vault: Pubkey::new_unique(),
+        token_pair: Pubkey::new_unique(),
+        initial_sqrt_price_q64: 1u128 << 64,
+        fee_rate: 30,
+        protocol_fee: 0,
+        immutable_parameters: false,
+        tick_spacing: TICK_SPACING,
+        timestamp: 0,
+    })
+    .unwrap();
+    pool
+}
+
+/// Builds a writable program-owned account holding `discriminator` followed by `bytes`.
+pub(super) fn program_account(
+    key: Pubkey,
+    discriminator: &[u8],
+    bytes: &[u8],
+) -> &'static AccountInfo<'static> {
+    // Zero-copy loads need the data after the 8-byte discriminator to be 16-byte aligned,
+    // so the buffer is carved out of u128 words starting 8 bytes in.
+    let words: &'static mut [u128] =
+        Box::leak(vec![0u128; 1 + (8 + bytes.len()) / 16].into_boxed_slice());
+    let data = &mut bytemuck::cast_slice_mut::<u128, u8>(words)[8..];
+    data[..8].copy_from_slice(discriminator);
+    data[8..].copy_from_slice(bytes);
+
+    let key = Box::leak(Box::new(key));
+    let lamports = Box::leak(Box::new(1_000_000_000u64));
+    Box::leak(Box::new(AccountInfo::new(
+        key,
+        false,
+        true,
+        lamports,
+        data,
+        &crate::ID,
+        false,
+        0,
+    )))
+}
+
+/// An empty tick array of `pool_key` starting at `start_tick_index`, at its PDA.
+pub(super) fn tick_array_loader(
+    pool_key: &Pubkey,
+    start_tick_index: i32,
+) -> AccountLoader<'static, TickArray> {
+    let mut tick_array: TickArray = bytemuck::Zeroable::zeroed();
+    tick_array
+        .initialize(*pool_key, start_tick_index, TICK_SPACING)
+        .unwrap();
+    AccountLoader::try_from(program_account(
+        TickArray::address(pool_key, start_tick_index),
+        TickArray::DISCRIMINATOR,
+        bytemuck::bytes_of(&tick_array),
+    ))
+    .unwrap()
+}
+
+/// An empty `TickData` account of `pool_key` for `tick_index`, at its PDA.
+pub(super) fn tick_loader(pool_key: &Pubkey, tick_index: i32) -> AccountLoader<'static, TickData> {
+    let mut tick = TickData::default();
+    tick.initialize(*pool_key, tick_index);
+    AccountLoader::try_from(program_account(
+        TickData::address(pool_key, tick_index),
+        TickData::DISCRIMINATOR,
+        bytemuck::bytes_of(&tick),
+    ))
+    .unwrap()
+}
+
+/// A `TickBitmap` account of `pool_key` for `word_index` holding `word`, at its PDA.
+pub(super) fn tick_bitmap_loader(
+    pool_key: &Pubkey,
+    word_index: i16,
+    word: u64,
+) -> AccountLoader<'static, TickBitmap> {
+    let tick_bitmap = TickBitmap {
+        word,
+        pool: *pool_key,
+        word_index,
+        _padding: [0; 6],
+    };
+    AccountLoader::try_from(program_account(
+        TickBitmap::address(pool_key, word_index),
+        TickBitmap::DISCRIMINATOR,
+        bytemuck::bytes_of(&tick_bitmap),
+    ))
+    .unwrap()
+}
+
+fn is_initialized_in_bitmap(pool: &Pool, tick_index: i32) -> bool {
+    let bitmap: BTreeMap<i16, u64> =
+        borsh::BorshDeserialize::try_from_slice(&pool.tick_bitmap_data).unwrap();
+    tick_bitmap::is_tick_initialized(&bitmap, tick_index, pool.tick_spacing).unwrap()
+}
+
+mod tick_array_tests {
+    use super::*;
+
+    fn empty_tick_array(start_tick_index: i32) -> TickArray {
+        let mut tick_array: TickArray = bytemuck::Zeroable::zeroed();
+        tick_array
+            .initialize(Pubkey::new_unique(), start_tick_index, TICK_SPACING)
+            .unwrap();
+        tick_array
+    }
+
+    #[test]
+    fn test_len_matches_struct_size() {
+        assert_eq!(TickArray::LEN, std::mem::size_of::<TickArray>());
+    }
+
+    #[test]
+    fn test_start_tick_index_rounds_down_to_array_span() {
+        assert_eq!(TickArray::start_tick_index(0, TICK_SPACING), 0);
+        assert_eq!(TickArray::start_tick_index(ARRAY_SPAN - 1, TICK_SPACING), 0);
+        assert_eq!(
+            TickArray::start_tick_index(ARRAY_SPAN, TICK_SPACING),
+            ARRAY_SPAN
+        );
+        assert_eq!(TickArray::start_tick_index(-1, TICK_SPACING), -ARRAY_SPAN);
+        assert_eq!(
+            TickArray::start_tick_index(-ARRAY_SPAN, TICK_SPACING),
+            -ARRAY_SPAN
+        );
+        assert_eq!(
+            TickArray::start_tick_index(-ARRAY_SPAN - 1, TICK_SPACING),
+            -2 * ARRAY_SPAN
+        );
+    }
+
+    #[test]
+    fn test_initialize_sets_every_entry() {
+        let pool = Pubkey::new_unique();
+        let mut tick_array: TickArray = bytemuck::Zeroable::zeroed();
+        tick_array
+            .initialize(pool, -ARRAY_SPAN, TICK_SPACING)
+            .unwrap();
+
+        assert_eq!(tick_array.pool, pool);
+        assert_eq!(tick_array.start_tick_index, -ARRAY_SPAN);
+        for (i, tick) in tick_array.ticks.iter().enumerate() {
+            assert_eq!(tick.pool, pool);
+            assert_eq!(tick.index, -ARRAY_SPAN + i as i32 * TICK_SPACING as i32);
+            assert_eq!(tick.liquidity_gross, 0);
+            assert_eq!(tick.initialized, 0);
+        }
+    }
+
+    #[test]
+    fn test_initialize_rejects_invalid_start_indices() {
+        let lowest_start = TickArray::start_tick_index(MIN_TICK, TICK_SPACING);
+        let highest_start = TickArray::start_tick_index(MAX_TICK, TICK_SPACING);
+        for start_tick_index in [
+            TICK_SPACING as i32,
+            ARRAY_SPAN / 2,
+            lowest_start - ARRAY_SPAN,
+            highest_start + ARRAY_SPAN,
+        ] {
+            let mut tick_array: TickArray = bytemuck::Zeroable::zeroed();
+            assert_eq!(
+                tick_array
+                    .initialize(Pubkey::new_unique(), start_tick_index, TICK_SPACING)
+                    .unwrap_err(),
+                ErrorCode::InvalidTickArrayStartIndex.into(),
+                "start tick index {start_tick_index}"
+            );
+        }
+
+        for start_tick_index in [lowest_start, highest_start] {
+            let mut tick_array: TickArray = bytemuck::Zeroable::zeroed();
+            assert!(tick_array
+                .initialize(Pubkey::new_unique(), start_tick_index, TICK_SPACING)
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn test_get_tick_mut_bounds() {
+        let mut tick_array = empty_tick_array(-ARRAY_SPAN);
+
+        let first = tick_array.get_tick_mut(-ARRAY_SPAN, TICK_SPACING).unwrap();
+        assert_eq!(first.index, -ARRAY_SPAN);
+        let last = tick_array
+            .get_tick_mut(-(TICK_SPACING as i32), TICK_SPACING)
+            .unwrap();
+        assert_eq!(last.index, -(TICK_SPACING as i32));
+
+        for tick_index in [0, -ARRAY_SPAN - TICK_SPACING as i32] {
+            assert_eq!(
+                tick_array
+                    .get_tick_mut(tick_index, TICK_SPACING)
+                    .unwrap_err(),
+                ErrorCode::TickNotInTickArray.into()
+            );
+        }
+        assert_eq!(
+            tick_array.get_tick(-30, TICK_SPACING).unwrap_err(),
+            ErrorCode::InvalidTickSpacing.into()
+        );
+    }
+
+    #[test]
+    fn test_get_tick_mut_writes_through() {
+        let mut tick_array = empty_tick_array(0);
+        tick_array
+            .get_tick_mut(600, TICK_SPACING)
+            .unwrap()
+            .liquidity_gross = 42;
+
+        assert_eq!(tick_array.ticks[10].liquidity_gross, 42);
+        assert_eq!(
+            tick_array
+                .get_tick(600, TICK_SPACING)
+                .unwrap()
+                .liquidity_gross,
+            42
+        );
+    }
+
+    #[test]
+    fn test_contains() {
+        let tick_array = empty_tick_array(0);
+        assert!(tick_array.contains(0, TICK_SPACING));
+        assert!(tick_array.contains(ARRAY_SPAN - 1, TICK_SPACING));
+        assert!(!tick_array.contains(ARRAY_SPAN, TICK_SPACING));
+        assert!(!tick_array.contains(-1, TICK_SPACING));
+    }
+}
+
+mod modify_liquidity_in_tick_arrays_tests {
+    use super::*;
+
+    #[test]
+    fn test_both_ticks_in_one_array() {
+        let mut pool = create_pool();
+        let pool_key = Pubkey::new_unique();
+        let tick_array = tick_array_loader(&pool_key, -ARRAY_SPAN);
+
+        pool.modify_liquidity_in_tick_arrays(
+            -600,
+            -120,
+            LIQUIDITY as i128,
+            &tick_array,
+            &tick_array,
+        )
+        .unwrap();
+
+        let loaded = tick_array.load().unwrap();
+        let lower = loaded.get_tick(-600, TICK_SPACING).unwrap();
+        let upper = loaded.get_tick(-120, TICK_SPACING).unwrap();
+        assert_eq!(lower.liquidity_gross, LIQUIDITY);
+        assert_eq!(lower.liquidity_net, LIQUIDITY as i128);
+        assert_eq!(upper.liquidity_gross, LIQUIDITY);
+        assert_eq!(upper.liquidity_net, -(LIQUIDITY as i128));
+        assert!(is_initialized_in_bitmap(&pool, -600));
+        assert!(is_initialized_in_bitmap(&pool, -120));
+        // The range is below the current tick, so the active liquidity is unchanged.
+        assert_eq!(pool.liquidity, 0);
+    }
+
+    #[test]
+    fn test_ticks_in_two_arrays_update_active_liquidity() {
+        let mut pool = create_pool();
+        let pool_key = Pubkey::new_unique();
+        let lower_array = tick_array_loader(&pool_key, -ARRAY_SPAN);
+        let upper_array = tick_array_loader(&pool_key, 0);
+
+        pool.modify_liquidity_in_tick_arrays(
+            -600,
+            600,
+            LIQUIDITY as i128,
+            &lower_array,
+            &upper_array,
+        )
+        .unwrap();
+        assert_eq!(pool.liquidity, LIQUIDITY);
+
+        pool.modify_liquidity_in_tick_arrays(
+            -600,
+            600,
+            -(LIQUIDITY as i128),
+            &lower_array,
+            &upper_array,
+        )
+        .unwrap();
+        assert_eq!(pool.liquidity, 0);
+        assert_eq!(
+            lower_array
+                .load()
+                .unwrap()
+                .get_tick(-600, TICK_SPACING)
+                .unwrap()
+                .initialized,
+            0
+        );
+        assert!(!is_initialized_in_bitmap(&pool, -600));
+        assert!(!is_initialized_in_bitmap(&pool, 600));
+    }
+
+    #[test]
+    fn test_tick_outside_supplied_array_is_rejected() {
+        let mut pool = create_pool();
+        let pool_key = Pubkey::new_unique();
+        let tick_array = tick_array_loader(&pool_key, -ARRAY_SPAN);
+
+        assert_eq!(
+            pool.modify_liquidity_in_tick_arrays(
+                -600,
+                600,
+                LIQUIDITY as i128,
+                &tick_array,
+                &tick_array
+            )
+            .unwrap_err(),
+            ErrorCode::TickNotInTickArray.into()
+        );
+    }
+
+    #[test]
+    fn test_tick_held_by_tick_account_cannot_move_to_array() {
+        let mut pool = create_pool();
+        let pool_key = Pubkey::new_unique();
+        let tick_lower = tick_loader(&pool_key, -600);
+        let tick_upper = tick_loader(&pool_key, 600);
+        pool.modify_liquidity(-600, 600, LIQUIDITY as i128, &tick_lower, &tick_upper)
+            .unwrap();
+
+        let lower_array = tick_array_loader(&pool_key, -ARRAY_SPAN);
+        let upper_array = tick_array_loader(&pool_key, 0);
+        assert_eq!(
+            pool.modify_liquidity_in_tick_arrays(
+                -600,
+                1200,
+                LIQUIDITY as i128,
+                &lower_array,
+                &upper_array
+            )
+            .unwrap_err(),
+            ErrorCode::TickStorageConflict.into()
+        );
+    }
+
+    #[test]
+    fn test_tick_held_by_array_cannot_move_to_tick_account() {
+        let mut pool = create_pool();
+        let pool_key = Pubkey::new_unique();
+        let lower_array = tick_array_loader(&pool_key, -ARRAY_SPAN);
+        let upper_array = tick_array_loader(&pool_key, 0);
+        pool.modify_liquidity_in_tick_arrays(
+            -600,
+            600,
+            LIQUIDITY as i128,
+            &lower_array,
+            &upper_array,
+        )
+        .unwrap();
+
+        let tick_lower = tick_loader(&pool_key, -1200);
+        let tick_upper = tick_loader(&pool_key, 600);
+        assert_eq!(
+            pool.modify_liquidity(-1200, 600, LIQUIDITY as i128, &tick_lower, &tick_upper)
+                .unwrap_err(),
+            ErrorCode::TickStorageConflict.into()
+        );
+    }
+
+    #[test]
+    fn test_position_ticks_load_rejects_empty_storage() {
+        let pool_key = Pubkey::new_unique();
+        let lower_array = tick_array_loader(&pool_key, -ARRAY_SPAN);
+        let upper_array = tick_array_loader(&pool_key, 0);
+        let position = PositionData {
+            pool: pool_key,
+            tick_lower_index: -600,
+            tick_upper_index: 600,
+            liquidity: LIQUIDITY,
+            ..Default::default()
+        };
+        let ticks = PositionTicks::Arrays {
+            lower: &lower_array,
+            upper: &upper_array,
+        };
+
+        assert_eq!(
+            ticks.load(&position, TICK_SPACING).unwrap_err(),
+            ErrorCode::TickStorageConflict.into()
+        );
+
+        let mut pool = create_pool();
+        ticks
+            .modify_liquidity(&mut pool, -600, 600, LIQUIDITY as i128)
+            .unwrap();
+        let (lower, upper) = ticks.load(&position, TICK_SPACING).unwrap();
+        assert_eq!(lower.index, -600);
+        assert_eq!(upper.index, 600);
+    }
+}
+
+mod swap_with_tick_arrays_tests {
+    use super::*;
+
+    /// Liquidity of each position whose lower tick the swaps cross.
+    const STEP_LIQUIDITY: u128 = LIQUIDITY / 8;
+
+    /// Pool with a wide position over `[-3000, 3000)` and one position over `[-60k, 3000)`
+    /// for each k in 1..=4, all stored in the arrays starting at `-ARRAY_SPAN` and 0.
+    fn setup_pool(
+        pool_key: &Pubkey,
+    ) -> (
+        Pool,
+        AccountLoader<'static, TickArray>,
+        AccountLoader<'static, TickArray>,
+    ) {
+        let mut pool = create_pool();
+        let lower_array = tick_array_loader(pool_key, -ARRAY_SPAN);
+        let upper_array = tick_array_loader(pool_key, 0);
+        pool.modify_liquidity_in_tick_arrays(
+            -3000,
+            3000,
+            (LIQUIDITY / 2) as i128,
+            &lower_array,
+            &upper_array,
+        )
+        .unwrap();
+        for k in 1..=4 {
+            pool.modify_liquidity_in_tick_arrays(
+                -60 * k,
+                3000,
+                STEP_LIQUIDITY as i128,
+                &lower_array,
+                &upper_array,
+            )
+            .unwrap();
+        }
+        (pool, lower_array, upper_array)
+    }
+
+    fn swap_down_to_tick(
+        pool: &mut Pool,
+        pool_key: &Pubkey,
+        tick: i32,
+        ticks: &[&AccountLoader<'static, TickData>],
+        tick_arrays: &[&AccountLoader<'static, TickArray>],
+    ) -> Result<SwapResult> {
+        let limit = math::tick_to_sqrt_price_q64(tick).unwrap();
+        pool.swap_with_tick_arrays(
+            true,
+            1i128 << 100,
+            limit,
+            pool_key,
+            ticks,
+            tick_arrays,
+            &[],
+            0,
+        )
+    }
+
+    #[test]
+    fn test_swap_crosses_ticks_stored_in_array() {
+        let pool_key = Pubkey::new_unique();
+        let (mut pool, lower_array, _) = setup_pool(&pool_key);
+        assert_eq!(pool.liquidity, LIQUIDITY / 2 + 4 * STEP_LIQUIDITY);
+
+        swap_down_to_tick(&mut pool, &pool_key, -270, &[], &[&lower_array]).unwrap();
+
+        assert!(pool.current_tick < -240);
+        assert_eq!(pool.liquidity, LIQUIDITY / 2);
+        let loaded = lower_array.load().unwrap();
+        let mut previous_outside = 0;
+        for k in 1..=4 {
+            let outside = loaded
+                .get_tick(-60 * k, TICK_SPACING)
+                .unwrap()
+                .fee_growth_outside_0_q64;
+            assert!(outside > previous_outside);
+            assert!(outside <= pool.fee_growth_global_0_q64);
+            previous_outside = outside;
+        }
+    }
+
+    #[test]
+    fn test_swap_without_covering_array_is_rejected() {
+        let pool_key = Pubkey::new_unique();
+        let (mut pool, _, upper_array) = setup_pool(&pool_key);
+
+        let err = swap_down_to_tick(&mut pool, &pool_key, -270, &[], &[&upper_array]).unwrap_err();
+        assert_eq!(err, ErrorCode::MissingTickAccount.into());
+        assert!(err.to_string().contains("(expected tick index -60)"));
+    }
+
+    #[test]
+    fn test_swap_rejects_array_of_another_pool() {
+        let pool_key = Pubkey::new_unique();
+        let (mut pool, _, _) = setup_pool(&pool_key);
+        let foreign_array = tick_array_loader(&Pubkey::new_unique(), -ARRAY_SPAN);
+
+        assert_eq!(
+            swap_down_to_tick(&mut pool, &pool_key, -270, &[], &[&foreign_array]).unwrap_err(),
+            ErrorCode::InvalidTickAccount.into()
+        );
+    }
+
+    #[test]
+    fn test_swap_mixes_tick_accounts_and_arrays() {
+        let pool_key = Pubkey::new_unique();
+        let (mut pool, lower_array, _) = setup_pool(&pool_key);
+        // One more position whose lower tick lives in its own `TickData` account.
+        let tick_lower = tick_loader(&pool_key, -300);
+        let tick_upper = tick_loader(&pool_key, 6000);
+        pool.modify_liquidity(-300, 6000, STEP_LIQUIDITY as i128, &tick_lower, &tick_upper)
+            .unwrap();
+
+        swap_down_to_tick(&mut pool, &pool_key, -330, &[&tick_lower], &[&lower_array]).unwrap();
+
+        assert!(pool.current_tick < -300);
+        assert_eq!(pool.liquidity, LIQUIDITY / 2);
+        assert!(tick_lower.load().unwrap().fee_growth_outside_0_q64 > 0);
+    }
+
+    #[test]
+    fn test_simulated_swap_matches_swap_without_writing_accounts() {
+        let pool_key = Pubkey::new_unique();
+        let (mut pool, lower_array, _) = setup_pool(&pool_key);
+        let tick_lower = tick_loader(&pool_key, -300);
+        let tick_upper = tick_loader(&pool_key, 6000);
+        pool.modify_liquidity(-300, 6000, STEP_LIQUIDITY as i128, &tick_lower, &tick_upper)
+            .unwrap();
+        let limit = math::tick_to_sqrt_price_q64(-330).unwrap();
+
+        let mut simulated_pool = pool.clone();
+        let simulated = simulated_pool
+            .simulate_swap_with_tick_arrays(
+                true,
+                1i128 << 100,
+                limit,
+                &pool_key,
+                &[&tick_lower],
+                &[&lower_array],
+                &[],
+                0,
+            )
+            .unwrap();
+        assert_eq!(simulated.ticks_crossed, 5);
+        assert_eq!(tick_lower.load().unwrap().fee_growth_outside_0_q64, 0);
+        assert_eq!(
+            lower_array
+                .load()
+                .unwrap()
+                .get_tick(-60, TICK_SPACING)
+                .unwrap()
+                .fee_growth_outside_0_q64,
+            0
+        );
+
+        let swapped =
+            swap_down_to_tick(&mut pool, &pool_key, -330, &[&tick_lower], &[&lower_array]).unwrap();
+        assert_eq!(simulated, swapped);
+        assert_eq!(simulated_pool.sqrt_price_q64, pool.sqrt_price_q64);
+        assert_eq!(simulated_pool.current_tick, pool.current_tick);
+        assert_eq!(simulated_pool.liquidity, pool.liquidity);
+        assert_eq!(
+            simulated_pool.fee_growth_global_0_q64,
+            pool.fee_growth_global_0_q64
+        );
+        assert!(tick_lower.load().unwrap().fee_growth_outside_0_q64 > 0);
+    }
+
+    #[test]
+    fn test_swap_rejects_empty_tick_account_for_array_tick() {
+        let pool_key = Pubkey::new_unique();
+        let (mut pool, _, _) = setup_pool(&pool_key);
+        // The tick's liquidity lives in the array, not in this empty tick account.
+        let stale_tick = tick_loader(&pool_key, -60);
+
+        assert_eq!(
+            swap_down_to_tick(&mut pool, &pool_key, -90, &[&stale_tick], &[]).unwrap_err(),
+            ErrorCode::MissingTickAccount.into()
+        );
+    }
+}
+
+mod tick_bitmap_account_tests {
+    use super::*;
+
+    /// Pool whose bitmap words -1 and 0 live in `TickBitmap` accounts, with a position over
+    /// `[-120, 120)` stored in the arrays starting at `-ARRAY_SPAN` and 0. At a spacing of
+    /// 60 a bitmap word and a tick array both span 3840 ticks, so tick 0 starts word 0.
+    fn setup_pool(
+        pool_key: &Pubkey,
+    ) -> (
+        Pool,
+        [AccountLoader<'static, TickArray>; 2],
+        [AccountLoader<'static, TickBitmap>; 2],
+    ) {
+        let mut pool = create_pool();
+        let words = [-1, 0].map(|word_index| {
+            let word = pool.externalize_bitmap_word(word_index).unwrap();
+            tick_bitmap_loader(pool_key, word_index, word)
+        });
+        let arrays = [
+            tick_array_loader(pool_key, -ARRAY_SPAN),
+            tick_array_loader(pool_key, 0),
+        ];
+        pool.load_bitmap_words(pool_key, &[&words[0], &words[1]])
+            .unwrap();
+        pool.modify_liquidity_in_tick_arrays(-120, 120, LIQUIDITY as i128, &arrays[0], &arrays[1])
+            .unwrap();
+        pool.store_bitmap_words(&[&words[0], &words[1]]).unwrap();
+        (pool, arrays, words)
+    }
+
+    #[test]
+    fn test_len_matches_struct_size() {
+        assert_eq!(TickBitmap::LEN, std::mem::size_of::<TickBitmap>());
+    }
+
+    #[test]
+    fn test_word_index_of_tick_rounds_down() {
+        assert_eq!(tick_bitmap::word_index_of_tick(0, TICK_SPACING), 0);
+        assert_eq!(
+            tick_bitmap::word_index_of_tick(ARRAY_SPAN - 1, TICK_SPACING),
+            0
+        );
+        assert_eq!(tick_bitmap::word_index_of_tick(-1, TICK_SPACING), -1);
+        assert_eq!(
+            tick_bitmap::word_index_of_tick(-ARRAY_SPAN, TICK_SPACING),
+            -1
+        );
+        assert_eq!(tick_bitmap::word_start_tick(-1, TICK_SPACING), -ARRAY_SPAN);
+    }
+
+    #[test]
+    fn test_flips_are_stored_in_word_accounts() {
+        let pool_key = Pubkey::new_unique();
+        let (pool, _, words) = setup_pool(&pool_key);
+
+        assert_eq!(pool.external_bitmap_words, vec![-1, 0]);
+        assert!(!is_initialized_in_bitmap(&pool, -120));
+        assert!(!is_initialized_in_bitmap(&pool, 120));
+        // Compressed tick -2 is bit 62 of word -1; compressed tick 2 is bit 2 of word 0.
+        assert_eq!(words[0].load().unwrap().word, 1 << 62);
+        assert_eq!(words[1].load().unwrap().word, 1 << 2);
+    }
+
+    #[test]
+    fn test_externalize_moves_inline_word() {
+        let pool_key = Pubkey::new_unique();
+        let mut pool = create_pool();
+        let lower_array = tick_array_loader(&pool_key, -ARRAY_SPAN);
+        let upper_array = tick_array_loader(&pool_key, 0);
+        pool.modify_liquidity_in_tick_arrays(
+            -120,
+            120,
+            LIQUIDITY as i128,
+            &lower_array,
+            &upper_array,
+        )
+        .unwrap();
+
+        assert_eq!(pool.externalize_bitmap_word(0).unwrap(), 1 << 2);
+        assert!(!is_initialized_in_bitmap(&pool, 120));
+        assert!(is_initialized_in_bitmap(&pool, -120));
+        assert_eq!(
+            pool.externalize_bitmap_word(0).unwrap_err(),
+            ErrorCode::TickBitmapWordExists.into()
+        );
+        assert_eq!(
+            pool.externalize_bitmap_word(i16::MAX).unwrap_err(),
+            ErrorCode::TickWordIndexOutOfBounds.into()
+        );
+    }
+
+    #[test]
+    fn test_flip_without_word_account_is_rejected() {
+        let pool_key = Pubkey::new_unique();
+        let (mut pool, arrays, words) = setup_pool(&pool_key);
+
+        pool.load_bitmap_words(&pool_key, &[&words[1]]).unwrap();
+        let err = pool
+            .modify_liquidity_in_tick_arrays(-180, 60, LIQUIDITY as i128, &arrays[0], &arrays[1])
+            .unwrap_err();
+        assert_eq!(err, ErrorCode::MissingTickBitmapWord.into());
+        assert!(err.to_string().contains("(expected word index -1)"));
+    }
+
+    #[test]
+    fn test_load_rejects_word_of_another_pool() {
+        let pool_key = Pubkey::new_unique();
+        let (mut pool, _, _) = setup_pool(&pool_key);
+        let foreign_word = tick_bitmap_loader(&Pubkey::new_unique(), 0, 0);
+
+        assert_eq!(
+            pool.load_bitmap_words(&pool_key, &[&foreign_word])
+                .unwrap_err(),
+            ErrorCode::InvalidTickAccount.into()
+        );
+    }
+
+    #[test]
+    fn test_swap_walks_bitmap_across_word_boundary() {
+        let pool_key = Pubkey::new_unique();
+        let (mut pool, arrays, words) = setup_pool(&pool_key);
+        // A wide position keeps liquidity active once the price leaves `[-120, 120)`.
+        pool.load_bitmap_words(&pool_key, &[&words[0], &words[1]])
+            .unwrap();
+        pool.modify_liquidity_in_tick_arrays(
+            -ARRAY_SPAN,
+            ARRAY_SPAN - 60,
+            LIQUIDITY as i128,
+            &arrays[0],
+            &arrays[1],
+        )
+        .unwrap();
+        pool.store_bitmap_words(&[&words[0], &words[1]]).unwrap();
+
+        // The swap starts in word 0 and finds tick -120 in word -1.
+        let limit = math::tick_to_sqrt_price_q64(-180).unwrap();
+        pool.swap_with_tick_arrays(
+            true,
+            1i128 << 100,
+            limit,
+            &pool_key,
+            &[],
+            &[&arrays[0], &arrays[1]],
+            &[&words[0], &words[1]],
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(pool.sqrt_price_q64, limit);
+        assert_eq!(pool.liquidity, LIQUIDITY);
+        let crossed = *arrays[0]
+            .load()
+            .unwrap()
+            .get_tick(-120, TICK_SPACING)
+            .unwrap();
+        assert!(crossed.fee_growth_outside_0_q64 > 0);
+        // Crossing leaves the bitmap untouched, and the words are back in their accounts.
+        assert_eq!(words[0].load().unwrap().word, 1 << 62 | 1);
+        assert_eq!(words[1].load().unwrap().word, 1 << 63 | 1 << 2);
+        assert_eq!(pool.external_bitmap_words, vec![-1, 0]);
+    }
+
+    #[test]
+    fn test_swap_into_unsupplied_word_is_rejected() {
+        let pool_key = Pubkey::new_unique();
+        let (mut pool, arrays, words) = setup_pool(&pool_key);
+        let limit = math::tick_to_sqrt_price_q64(-180).unwrap();
+
+        let err = pool
+            .swap_with_tick_arrays(
+                true,
+                1i128 << 100,
+                limit,
+                &pool_key,
+                &[],
+                &[&arrays[0], &arrays[1]],
+                &[&words[1]],
+                0,
+            )
+            .unwrap_err();
+        assert_eq!(err, ErrorCode::MissingTickBitmapWord.into());
+        assert!(err.to_string().contains("(expected word index -1)"));
+    }
+
+    #[test]
+    fn test_swap_stopping_before_unsupplied_word_succeeds() {
+        let pool_key = Pubkey::new_unique();
+        let (mut pool, arrays, words) = setup_pool(&pool_key);
+        // Moving up from tick 0 only reads word 0.
+        let limit = math::tick_to_sqrt_price_q64(60).unwrap();
+
+        pool.swap_with_tick_arrays(
+            false,
+            1i128 << 100,
+            limit,
+            &pool_key,
+            &[],
+            &[&arrays[1]],
+            &[&words[1]],
+            0,
+        )
+        .unwrap();
+        assert_eq!(pool.sqrt_price_q64, limit);
+        assert_eq!(pool.liquidity, LIQUIDITY);
+    }
+}
+
+mod remaining_account_limit_tests {
+    use super::*;
+
+    /// `count` `TickData` accounts of `pool_key`, for ticks 60, 120, ...
+    fn tick_accounts(pool_key: &Pubkey, count: usize) -> &'static [AccountInfo<'static>] {
+        let accounts: Vec<AccountInfo<'static>> = (1..=count as i32)
+            .map(|k| {
+                let mut tick = TickData::default();
+                tick.initialize(*pool_key, 60 * k);
+                program_account(
+                    TickData::address(pool_key, 60 * k),
+                    TickData::DISCRIMINATOR,
+                    bytemuck::bytes_of(&tick),
+                )
+                .clone()
+            })
+            .collect();
+        Box::leak(accounts.into_boxed_slice())
+    }
+
+    /// `count` `TickBitmap` accounts of `pool_key`, for words 0, 1, ...
+    fn tick_bitmap_accounts(pool_key: &Pubkey, count: usize) -> &'static [AccountInfo<'static>] {
+        let accounts: Vec<AccountInfo<'static>> = (0..count as i16)
+            .map(|word_index| {
+                tick_bitmap_loader(pool_key, word_index, 0)
+                    .to_account_info()
+                    .clone()
+            })
+            .collect();
+        Box::leak(accounts.into_boxed_slice())
+    }
+
+    #[test]
+    fn test_swap_tick_accounts_at_batch_limit_load() {
+        let pool_key = Pubkey::new_unique();
+        let accounts = tick_accounts(&pool_key, MAX_BATCH_ITERATIONS);
+
+        let loaded = load_swap_tick_accounts(accounts).unwrap();
+        assert_eq!(loaded.ticks.len(), MAX_BATCH_ITERATIONS);
+    }
+
+    #[test]
+    fn test_swap_tick_accounts_over_batch_limit_are_rejected() {
+        let pool_key = Pubkey::new_unique();
+        let accounts = tick_accounts(&pool_key, MAX_BATCH_ITERATIONS + 1);
+
+        assert_eq!(
+            load_swap_tick_accounts(accounts).err().unwrap(),
+            ErrorCode::MaxBatchSizeExceeded.into()
+        );
+    }
+
+    #[test]
+    fn test_tick_bitmap_accounts_at_batch_limit_load() {
+        let pool_key = Pubkey::new_unique();
+        let accounts = tick_bitmap_accounts(&pool_key, MAX_BATCH_ITERATIONS);
+
+        let loaded = tick_bitmap::load_tick_bitmap_accounts(accounts).unwrap();
+        assert_eq!(loaded.len(), MAX_BATCH_ITERATIONS);
+    }
+
+    #[test]
+    fn test_tick_bitmap_accounts_over_batch_limit_are_rejected() {
+        let pool_key = Pubkey::new_unique();
+        let accounts = tick_bitmap_accounts(&pool_key, MAX_BATCH_ITERATIONS + 1);
+
+        assert_eq!(
+            tick_bitmap::load_tick_bitmap_accounts(accounts)
+                .err()
+                .unwrap(),
+            ErrorCode::MaxBatchSizeExceeded.into()
+        );
+    }
+
+    #[test]
+    fn test_swap_with_tick_accounts_at_iteration_limit_runs() {
+        let pool_key = Pubkey::new_unique();
+        let mut pool = create_pool();
+        let loaded =
+            load_swap_tick_accounts(tick_accounts(&pool_key, MAX_SWAP_ITERATIONS)).unwrap();
+        let ticks: Vec<&AccountLoader<TickData>> = loaded.ticks.iter().collect();
+
+        // The pool has no liquidity, so the swap moves nothing, but it is accepted.
+        pool.swap_with_tick_arrays(false, 1_000, 1u128 << 65, &pool_key, &ticks, &[], &[], 100)
+            .unwrap();
+        assert_eq!(pool.last_observation_timestamp, 100);
+    }
+
+    #[test]
+    fn test_swap_with_tick_accounts_over_iteration_limit_is_rejected_before_writes() {
+        let pool_key = Pubkey::new_unique();
+        let mut pool = create_pool();
+        let loaders: Vec<AccountLoader<TickData>> =
+            tick_accounts(&pool_key, MAX_SWAP_ITERATIONS + 1)
+                .iter()
+                .map(|account| AccountLoader::try_from(account).unwrap())
+                .collect();
+        let ticks: Vec<&AccountLoader<TickData>> = loaders.iter().collect();
+
+        let err = pool
+            .swap_with_tick_arrays(false, 1_000, 1u128 << 65, &pool_key, &ticks, &[], &[], 100)
+            .unwrap_err();
+        assert_eq!(err, ErrorCode::MaxTickCrossingsExceeded.into());
+        // Not even the oracle observation was written.
+        assert_eq!(pool.last_observation_timestamp, 0);
+    }
+}
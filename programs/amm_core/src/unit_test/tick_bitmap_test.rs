@@ -1053,6 +1053,71 @@ mod next_initialized_tick_tests {
         }
     }
 
+    #[test]
+    fn test_next_initialized_tick_skips_empty_words() {
+        let tick_spacing = 1;
+        let word_size_in_ticks = 64;
+        let lower_tick = -5_000 * word_size_in_ticks;
+        let upper_tick = 5_000 * word_size_in_ticks;
+        let mut bitmap = BTreeMap::new();
+        flip_tick_initialized_status(&mut bitmap, lower_tick, tick_spacing, true).unwrap();
+        flip_tick_initialized_status(&mut bitmap, upper_tick, tick_spacing, true).unwrap();
+
+        // 10,000 empty words separate the two ticks; only the two populated words are read.
+        let mut word_lookups = 0;
+        assert_eq!(
+            next_initialized_tick_counting_lookups(
+                &bitmap,
+                lower_tick + 1,
+                tick_spacing,
+                false,
+                &mut word_lookups
+            )
+            .unwrap(),
+            Some(upper_tick)
+        );
+        assert_eq!(word_lookups, 2);
+
+        let mut word_lookups = 0;
+        assert_eq!(
+            next_initialized_tick_counting_lookups(
+                &bitmap,
+                upper_tick - 1,
+                tick_spacing,
+                true,
+                &mut word_lookups
+            )
+            .unwrap(),
+            Some(lower_tick)
+        );
+        assert_eq!(word_lookups, 1);
+
+        // Populated words in between are each read once at most.
+        let populated_between = 10;
+        for i in 1..=populated_between {
+            let tick = lower_tick + i * 900 * word_size_in_ticks + 7;
+            flip_tick_initialized_status(&mut bitmap, tick, tick_spacing, true).unwrap();
+        }
+        let mut position = lower_tick;
+        let mut word_lookups = 0;
+        let mut found = 0;
+        while let Some(tick) = next_initialized_tick_counting_lookups(
+            &bitmap,
+            position + 1,
+            tick_spacing,
+            false,
+            &mut word_lookups,
+        )
+        .unwrap()
+        {
+            found += 1;
+            position = tick;
+        }
+        assert_eq!(position, upper_tick);
+        assert_eq!(found, populated_between + 1);
+        assert!(word_lookups <= 2 * (populated_between as u32 + 2));
+    }
+
     // Property-based testing for next_initialized_tick
     proptest! {
         #[test]
@@ -1139,6 +1204,96 @@ mod next_initialized_tick_tests {
     }
 }
 
+// Tests for initialized_ticks_in_range
+mod initialized_ticks_in_range_tests {
+    use super::*;
+
+    /// Collects the initialized ticks in `[tick_lower, tick_upper]` one aligned tick at a time.
+    fn brute_force(
+        bitmap: &BTreeMap<i16, u64>,
+        tick_lower: i32,
+        tick_upper: i32,
+        tick_spacing: u16,
+    ) -> Vec<i32> {
+        (tick_lower..=tick_upper)
+            .filter(|tick| tick.rem_euclid(tick_spacing as i32) == 0)
+            .filter(|&tick| is_tick_initialized(bitmap, tick, tick_spacing).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_initialized_ticks_in_range_across_negative_words() {
+        let tick_spacing = 10;
+        let word_size_in_ticks = 64 * tick_spacing as i32;
+        let ticks = [
+            -3 * word_size_in_ticks - 10,
+            -word_size_in_ticks,
+            -10,
+            0,
+            630,
+            word_size_in_ticks,
+            5 * word_size_in_ticks + 20,
+        ];
+        let mut bitmap = BTreeMap::new();
+        for &tick in &ticks {
+            flip_tick_initialized_status(&mut bitmap, tick, tick_spacing, true).unwrap();
+        }
+
+        let all: Vec<i32> =
+            initialized_ticks_in_range(&bitmap, i32::MIN, i32::MAX, tick_spacing).collect();
+        assert_eq!(all, ticks.to_vec());
+
+        // Unaligned bounds include only the aligned ticks inside them.
+        let inner: Vec<i32> =
+            initialized_ticks_in_range(&bitmap, -word_size_in_ticks + 1, 635, tick_spacing)
+                .collect();
+        assert_eq!(inner, vec![-10, 0, 630]);
+
+        // Bounds on initialized ticks are inclusive.
+        let exact: Vec<i32> = initialized_ticks_in_range(&bitmap, -10, 0, tick_spacing).collect();
+        assert_eq!(exact, vec![-10, 0]);
+    }
+
+    #[test]
+    fn test_initialized_ticks_in_range_empty() {
+        let mut bitmap = BTreeMap::new();
+        assert_eq!(
+            initialized_ticks_in_range(&bitmap, -1000, 1000, 10).count(),
+            0
+        );
+
+        flip_tick_initialized_status(&mut bitmap, 100, 10, true).unwrap();
+        // Inverted range.
+        assert_eq!(initialized_ticks_in_range(&bitmap, 200, 0, 10).count(), 0);
+        // No aligned tick between the bounds.
+        assert_eq!(initialized_ticks_in_range(&bitmap, 101, 109, 10).count(), 0);
+        // Zero spacing.
+        assert_eq!(initialized_ticks_in_range(&bitmap, 0, 200, 0).count(), 0);
+    }
+
+    proptest! {
+        #[test]
+        fn test_initialized_ticks_in_range_matches_brute_force(
+            ticks in prop::collection::vec(-20000..20000i32, 0..30),
+            tick_lower in -25000..25000i32,
+            width in 0..20000i32,
+            tick_spacing in 1..100u16,
+        ) {
+            let mut bitmap = BTreeMap::new();
+            for tick in ticks {
+                let aligned = tick.div_euclid(tick_spacing as i32) * tick_spacing as i32;
+                flip_tick_initialized_status(&mut bitmap, aligned, tick_spacing, true).unwrap();
+            }
+            let tick_upper = tick_lower + width;
+
+            let found: Vec<i32> =
+                initialized_ticks_in_range(&bitmap, tick_lower, tick_upper, tick_spacing)
+                    .collect();
+            prop_assert_eq!(found, brute_force(&bitmap, tick_lower, tick_upper, tick_spacing));
+        }
+    }
+}
+
 /// Security tests focusing on edge cases and potential vulnerabilities in tick_bitmap functions
 mod security_tests {
     use super::*;
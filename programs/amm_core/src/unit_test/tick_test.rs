@@ -580,4 +580,37 @@ mod tick_tests {
             Ok(())
         }
     }
+
+    /// Tests for crossing a tick during a swap
+    mod tick_cross_tests {
+        use super::*;
+
+        #[test]
+        fn test_cross_flips_fee_growth_outside() {
+            let mut tick_data = TickData::default();
+            tick_data.initialize(Pubkey::new_unique(), 60);
+            tick_data.liquidity_net = -500;
+            tick_data.fee_growth_outside_0_q64 = 100;
+            tick_data.fee_growth_outside_1_q64 = 40;
+
+            let liquidity_net = tick_data.cross(250, 90);
+
+            assert_eq!(liquidity_net, -500);
+            assert_eq!(tick_data.fee_growth_outside_0_q64, 150);
+            assert_eq!(tick_data.fee_growth_outside_1_q64, 50);
+        }
+
+        #[test]
+        fn test_cross_twice_restores_fee_growth_outside() {
+            let mut tick_data = TickData::default();
+            tick_data.initialize(Pubkey::new_unique(), -60);
+            tick_data.fee_growth_outside_0_q64 = u128::MAX - 5; // wraps on the first cross
+
+            tick_data.cross(10, 0);
+            tick_data.cross(10, 0);
+
+            assert_eq!(tick_data.fee_growth_outside_0_q64, u128::MAX - 5);
+            assert_eq!(tick_data.fee_growth_outside_1_q64, 0);
+        }
+    }
 }
@@ -0,0 +1,83 @@
+use crate::errors::ErrorCode;
+use crate::state::token_pair::TokenPair;
+
+use anchor_lang::prelude::*;
+
+mod token_pair_tests {
+    use super::*;
+
+    /// Two distinct mints, in canonical order.
+    fn ordered_mints() -> (Pubkey, Pubkey) {
+        let (mint_a, mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        if mint_a < mint_b {
+            (mint_a, mint_b)
+        } else {
+            (mint_b, mint_a)
+        }
+    }
+
+    #[test]
+    fn test_initialize_records_pair() {
+        let mut pair = TokenPair::default();
+        let (mint_a, mint_b) = ordered_mints();
+        let oracle_feed = Pubkey::new_unique();
+
+        pair.initialize(253, mint_a, mint_b, oracle_feed).unwrap();
+
+        assert_eq!(pair.bump, 253);
+        assert_eq!(pair.mint_a, mint_a);
+        assert_eq!(pair.mint_b, mint_b);
+        assert_eq!(pair.oracle_feed, oracle_feed);
+        assert!(pair.is_canonical);
+        assert_eq!(pair.pool_count, 0);
+    }
+
+    #[test]
+    fn test_initialize_rejects_non_canonical_order() {
+        let (mint_a, mint_b) = ordered_mints();
+
+        for (first, second) in [(mint_b, mint_a), (mint_a, mint_a)] {
+            let mut pair = TokenPair::default();
+            assert_eq!(
+                pair.initialize(1, first, second, Pubkey::default())
+                    .unwrap_err(),
+                ErrorCode::MintsNotInCanonicalOrder.into()
+            );
+            assert!(!pair.is_canonical);
+        }
+    }
+
+    #[test]
+    fn test_record_pool_increments_count() {
+        let mut pair = TokenPair::default();
+        let (mint_a, mint_b) = ordered_mints();
+        pair.initialize(1, mint_a, mint_b, Pubkey::default())
+            .unwrap();
+
+        pair.record_pool().unwrap();
+        pair.record_pool().unwrap();
+
+        assert_eq!(pair.pool_count, 2);
+    }
+
+    #[test]
+    fn test_record_pool_overflow() {
+        let mut pair = TokenPair {
+            pool_count: u32::MAX,
+            ..TokenPair::default()
+        };
+
+        assert_eq!(
+            pair.record_pool().unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn test_len_fits_serialized_pair() {
+        let pair = TokenPair::default();
+        let serialized = pair.try_to_vec().unwrap();
+
+        assert_eq!(8 + serialized.len(), TokenPair::LEN);
+    }
+}
@@ -0,0 +1,301 @@
+use super::tick_array_test::{program_account, tick_loader};
+use crate::constants::{MAX_TICK, MIN_LIQUIDITY, MIN_TICK};
+use crate::errors::ErrorCode;
+use crate::instructions::mint_position::{validate_mint_params, validate_tick_range};
+use crate::instructions::update_position::{
+    check_tick_account_roles, move_liquidity, rebalance_amounts,
+};
+use crate::state::pool::{InitializePoolParams, Pool};
+use crate::tick::TickData;
+
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+const LIQUIDITY: u128 = 1 << 80;
+
+/// Pool at tick 0 with a tick spacing of 60, holding `LIQUIDITY` on [-120, 120) in the
+/// returned tick accounts.
+fn setup() -> (
+    Pool,
+    Pubkey,
+    AccountLoader<'static, TickData>,
+    AccountLoader<'static, TickData>,
+) {
+    let mut pool = Pool::default();
+    pool.initialize(InitializePoolParams {
+        bump: 1,
+        factory: Pubkey::new_unique(),
+        authority: Pubkey::new_unique(),
+        token0_mint: Pubkey::new_unique(),
+        token1_mint: Pubkey::new_unique(),
+        token0_vault: Pubkey::new_unique(),
+        token1_vault: Pubkey::new_unique(),
+        token_pair: Pubkey::new_unique(),
+        initial_sqrt_price_q64: 1u128 << 64,
+        fee_rate: 30,
+        protocol_fee: 0,
+        immutable_parameters: false,
+        tick_spacing: 60,
+        timestamp: 0,
+    })
+    .unwrap();
+    let pool_key = Pubkey::new_unique();
+    let tick_lower = tick_loader(&pool_key, -120);
+    let tick_upper = tick_loader(&pool_key, 120);
+    pool.modify_liquidity(-120, 120, LIQUIDITY as i128, &tick_lower, &tick_upper)
+        .unwrap();
+    (pool, pool_key, tick_lower, tick_upper)
+}
+
+/// A `TickData` account for `tick_index` as `init_if_needed` leaves it, not yet initialized.
+fn new_tick_loader(pool_key: &Pubkey, tick_index: i32) -> AccountLoader<'static, TickData> {
+    AccountLoader::try_from(program_account(
+        TickData::address(pool_key, tick_index),
+        TickData::DISCRIMINATOR,
+        bytemuck::bytes_of(&TickData::default()),
+    ))
+    .unwrap()
+}
+
+fn liquidity_of(tick_loader: &AccountLoader<TickData>) -> (u128, i128) {
+    let tick = tick_loader.load().unwrap();
+    (tick.liquidity_gross, tick.liquidity_net)
+}
+
+mod shared_tick_account_tests {
+    use super::*;
+
+    #[test]
+    fn test_upper_boundary_moved_reuses_lower_tick_account() {
+        let (mut pool, pool_key, tick_lower, old_tick_upper) = setup();
+        let new_tick_upper = new_tick_loader(&pool_key, 240);
+        check_tick_account_roles([
+            (tick_lower.key(), -120),
+            (old_tick_upper.key(), 120),
+            (tick_lower.key(), -120),
+            (new_tick_upper.key(), 240),
+        ])
+        .unwrap();
+
+        move_liquidity(
+            &mut pool,
+            pool_key,
+            LIQUIDITY,
+            LIQUIDITY,
+            [(-120, &tick_lower), (120, &old_tick_upper)],
+            [(-120, &tick_lower), (240, &new_tick_upper)],
+        )
+        .unwrap();
+
+        assert_eq!(liquidity_of(&tick_lower), (LIQUIDITY, LIQUIDITY as i128));
+        assert_eq!(liquidity_of(&old_tick_upper), (0, 0));
+        assert_eq!(
+            liquidity_of(&new_tick_upper),
+            (LIQUIDITY, -(LIQUIDITY as i128))
+        );
+        let new_tick_upper_data = new_tick_upper.load().unwrap();
+        assert_eq!(new_tick_upper_data.pool, pool_key);
+        assert_eq!(new_tick_upper_data.index, 240);
+        assert_eq!(pool.liquidity, LIQUIDITY);
+    }
+
+    #[test]
+    fn test_range_shifted_by_its_width_reuses_old_upper_as_new_lower() {
+        let (mut pool, pool_key, old_tick_lower, shared_tick) = setup();
+        let new_tick_upper = new_tick_loader(&pool_key, 360);
+        check_tick_account_roles([
+            (old_tick_lower.key(), -120),
+            (shared_tick.key(), 120),
+            (shared_tick.key(), 120),
+            (new_tick_upper.key(), 360),
+        ])
+        .unwrap();
+
+        move_liquidity(
+            &mut pool,
+            pool_key,
+            LIQUIDITY,
+            LIQUIDITY,
+            [(-120, &old_tick_lower), (120, &shared_tick)],
+            [(120, &shared_tick), (360, &new_tick_upper)],
+        )
+        .unwrap();
+
+        assert_eq!(liquidity_of(&old_tick_lower), (0, 0));
+        // The tick now opens the range instead of closing it
+        assert_eq!(liquidity_of(&shared_tick), (LIQUIDITY, LIQUIDITY as i128));
+        assert_eq!(
+            liquidity_of(&new_tick_upper),
+            (LIQUIDITY, -(LIQUIDITY as i128))
+        );
+        // The price is below the new range
+        assert_eq!(pool.liquidity, 0);
+    }
+
+    #[test]
+    fn test_unchanged_range_reuses_both_tick_accounts() {
+        let (mut pool, pool_key, tick_lower, tick_upper) = setup();
+
+        move_liquidity(
+            &mut pool,
+            pool_key,
+            LIQUIDITY,
+            LIQUIDITY,
+            [(-120, &tick_lower), (120, &tick_upper)],
+            [(-120, &tick_lower), (120, &tick_upper)],
+        )
+        .unwrap();
+
+        assert_eq!(liquidity_of(&tick_lower), (LIQUIDITY, LIQUIDITY as i128));
+        assert_eq!(liquidity_of(&tick_upper), (LIQUIDITY, -(LIQUIDITY as i128)));
+        assert_eq!(pool.liquidity, LIQUIDITY);
+    }
+
+    #[test]
+    fn test_account_passed_for_two_different_ticks_is_rejected() {
+        let pool_key = Pubkey::new_unique();
+        let old_tick_upper = TickData::address(&pool_key, 120);
+        // The old upper tick's account is passed again as the new upper tick 240
+        let result = check_tick_account_roles([
+            (TickData::address(&pool_key, -120), -120),
+            (old_tick_upper, 120),
+            (TickData::address(&pool_key, -120), -120),
+            (old_tick_upper, 240),
+        ]);
+        assert_eq!(result.unwrap_err(), ErrorCode::DuplicateTickAccount.into());
+
+        // Lower and upper roles of the same range can never share an account
+        let result = check_tick_account_roles([
+            (old_tick_upper, -120),
+            (old_tick_upper, 120),
+            (TickData::address(&pool_key, -60), -60),
+            (TickData::address(&pool_key, 60), 60),
+        ]);
+        assert_eq!(result.unwrap_err(), ErrorCode::DuplicateTickAccount.into());
+    }
+
+    #[test]
+    fn test_distinct_tick_accounts_are_accepted() {
+        let pool_key = Pubkey::new_unique();
+        let roles = [-120, 120, -60, 60]
+            .map(|tick_index| (TickData::address(&pool_key, tick_index), tick_index));
+        assert!(check_tick_account_roles(roles).is_ok());
+    }
+}
+
+/// Tests for the tick range checks shared by `mint_position` and `update_position`
+mod tick_range_validation_tests {
+    use super::*;
+
+    #[test]
+    fn test_reversed_and_empty_ranges_are_rejected() {
+        assert_eq!(
+            validate_tick_range(60, 120, -120).unwrap_err(),
+            ErrorCode::InvalidTickRange.into()
+        );
+        assert_eq!(
+            validate_tick_range(60, 120, 120).unwrap_err(),
+            ErrorCode::InvalidTickRange.into()
+        );
+        validate_tick_range(60, -120, 120).unwrap();
+    }
+
+    #[test]
+    fn test_unaligned_ticks_are_rejected() {
+        assert_eq!(
+            validate_tick_range(60, -90, 120).unwrap_err(),
+            ErrorCode::InvalidTickSpacing.into()
+        );
+        assert_eq!(
+            validate_tick_range(60, -120, 150).unwrap_err(),
+            ErrorCode::InvalidTickSpacing.into()
+        );
+    }
+
+    #[test]
+    fn test_boundary_ticks_are_accepted_and_beyond_them_rejected() {
+        // MAX_TICK is a multiple of 8, so the full range is aligned at that spacing
+        validate_tick_range(1, MIN_TICK, MAX_TICK).unwrap();
+        validate_tick_range(8, MIN_TICK, MAX_TICK).unwrap();
+
+        assert_eq!(
+            validate_tick_range(1, MIN_TICK - 1, 0).unwrap_err(),
+            ErrorCode::InvalidTickRange.into()
+        );
+        assert_eq!(
+            validate_tick_range(1, 0, MAX_TICK + 1).unwrap_err(),
+            ErrorCode::InvalidTickRange.into()
+        );
+        // In range, but not a multiple of 60
+        assert_eq!(
+            validate_tick_range(60, MIN_TICK, 0).unwrap_err(),
+            ErrorCode::InvalidTickSpacing.into()
+        );
+    }
+
+    #[test]
+    fn test_mint_rejects_zero_liquidity() {
+        assert_eq!(
+            validate_mint_params(60, -120, 120, 0).unwrap_err(),
+            ErrorCode::ZeroLiquidityDelta.into()
+        );
+        validate_mint_params(60, -120, 120, MIN_LIQUIDITY).unwrap();
+        // The range is checked before the liquidity
+        assert_eq!(
+            validate_mint_params(60, 120, -120, 0).unwrap_err(),
+            ErrorCode::InvalidTickRange.into()
+        );
+    }
+}
+
+mod rebalance_amount_tests {
+    use super::*;
+
+    const SQRT_PRICE_Q64: u128 = 1 << 64;
+    // Small enough for the position's tokens to fit in a `u64`
+    const POSITION_LIQUIDITY: u128 = 1 << 60;
+
+    #[test]
+    fn test_range_above_price_takes_only_token0() {
+        let amounts =
+            rebalance_amounts(SQRT_PRICE_Q64, (-120, 120), POSITION_LIQUIDITY, (120, 360)).unwrap();
+
+        // The price is below the new range, so all of the token1 comes back
+        assert!(amounts.liquidity > 0);
+        assert!(amounts.amount0_deposited > 0);
+        assert_eq!(amounts.amount1_deposited, 0);
+        let withdrawn =
+            rebalance_amounts(SQRT_PRICE_Q64, (-120, 120), POSITION_LIQUIDITY, (-120, 120))
+                .unwrap();
+        assert_eq!(
+            amounts.amount1_returned,
+            withdrawn.amount1_deposited + withdrawn.amount1_returned
+        );
+    }
+
+    #[test]
+    fn test_wider_range_keeps_the_limiting_token_and_returns_dust() {
+        let amounts =
+            rebalance_amounts(SQRT_PRICE_Q64, (-120, 120), POSITION_LIQUIDITY, (-600, 600))
+                .unwrap();
+
+        // The same tokens spread over a wider range make less liquidity
+        assert!(amounts.liquidity < POSITION_LIQUIDITY);
+        // At tick 0 the range is symmetric, so nearly everything is redeposited
+        assert!(amounts.amount0_returned <= 1);
+        assert!(amounts.amount1_returned <= 1);
+    }
+
+    #[test]
+    fn test_unchanged_range_keeps_liquidity() {
+        let amounts =
+            rebalance_amounts(SQRT_PRICE_Q64, (-120, 120), POSITION_LIQUIDITY, (-120, 120))
+                .unwrap();
+
+        // Withdrawing rounds down, so the redeposit can fall short by a unit of liquidity
+        assert!(amounts.liquidity <= POSITION_LIQUIDITY);
+        assert!(POSITION_LIQUIDITY - amounts.liquidity <= 1 << 20);
+        assert!(amounts.amount0_returned <= 1);
+        assert!(amounts.amount1_returned <= 1);
+    }
+}
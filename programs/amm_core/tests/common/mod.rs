@@ -0,0 +1,433 @@
+// /tests/common/mod.rs
+//
+// Fixtures shared by the integration tests: processing transactions, decoding events,
+// creating mints and token accounts, deriving PDAs, and creating the factory config and a
+// pool the way a deployment would.
+//
+// Each test binary compiles its own copy of this module and uses only part of it.
+#![allow(dead_code)]
+
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{program_pack::Pack, system_instruction},
+    AccountDeserialize, Event, InstructionData, ToAccountMetas,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    account::AccountSharedData,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    instruction::{Instruction, InstructionError},
+    rent::Rent,
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::{Transaction, TransactionError},
+};
+
+use amm_core::state::factory_config::{FactoryConfig, ProtocolFeeCurve};
+use amm_core::{errors::ErrorCode, ID as PROGRAM_ID};
+
+pub const FEE_RATE: u16 = 30;
+pub const TICK_SPACING: u16 = 60;
+/// What `setup_pool` funds each of the payer's token accounts with.
+pub const OWNER_FUNDING: u64 = 1_000_000_000_000;
+
+/// Processes a transaction and returns its log lines, or the error it failed with.
+pub async fn try_process(
+    context: &mut ProgramTestContext,
+    ixs: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<Vec<String>, TransactionError> {
+    let transaction = Transaction::new_signed_with_payer(
+        ixs,
+        Some(&context.payer.pubkey()),
+        signers,
+        context.last_blockhash,
+    );
+    let processed = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    processed.result?;
+    Ok(processed
+        .metadata
+        .expect("transaction metadata missing")
+        .log_messages)
+}
+
+/// Processes a transaction that must succeed and returns its log lines.
+pub async fn process(
+    context: &mut ProgramTestContext,
+    ixs: &[Instruction],
+    signers: &[&Keypair],
+) -> Vec<String> {
+    try_process(context, ixs, signers)
+        .await
+        .unwrap_or_else(|err| panic!("transaction failed: {err:?}"))
+}
+
+/// The error of a transaction whose first instruction failed with `error`.
+pub fn program_error(error: ErrorCode) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error as u32 + 6000))
+}
+
+/// Decodes every event of type `E` in `logs`.
+pub fn events<E: Event>(logs: &[String]) -> Vec<E> {
+    logs.iter()
+        .filter_map(|line| line.strip_prefix("Program data: "))
+        .map(|data| STANDARD.decode(data).unwrap())
+        .filter(|bytes| bytes.starts_with(E::DISCRIMINATOR))
+        .map(|bytes| E::try_from_slice(&bytes[E::DISCRIMINATOR.len()..]).unwrap())
+        .collect()
+}
+
+/// Creates a mint with no decimals, whose mint authority is the payer.
+pub async fn create_mint(context: &mut ProgramTestContext) -> Pubkey {
+    create_mint_with_decimals(context, 0).await
+}
+
+/// Creates a mint with `decimals`, whose mint authority is the payer.
+pub async fn create_mint_with_decimals(context: &mut ProgramTestContext, decimals: u8) -> Pubkey {
+    let payer = context.payer.insecure_clone();
+    let mint_keypair = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let ixs = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &mint_keypair.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &mint_keypair.pubkey(),
+            &payer.pubkey(),
+            None,
+            decimals,
+        )
+        .unwrap(),
+    ];
+    process(context, &ixs, &[&payer, &mint_keypair]).await;
+    mint_keypair.pubkey()
+}
+
+pub async fn create_token_account(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Pubkey {
+    let payer = context.payer.insecure_clone();
+    let account_keypair = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let ixs = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &account_keypair.pubkey(),
+            rent.minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account(
+            &spl_token::id(),
+            &account_keypair.pubkey(),
+            mint,
+            owner,
+        )
+        .unwrap(),
+    ];
+    process(context, &ixs, &[&payer, &account_keypair]).await;
+    account_keypair.pubkey()
+}
+
+pub async fn mint_to(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    account: &Pubkey,
+    amount: u64,
+) {
+    let payer = context.payer.insecure_clone();
+    let ix = spl_token::instruction::mint_to(
+        &spl_token::id(),
+        mint,
+        account,
+        &payer.pubkey(),
+        &[],
+        amount,
+    )
+    .unwrap();
+    process(context, &[ix], &[&payer]).await;
+}
+
+/// Creates a token account of `mint` owned by the payer and funds it with `amount`.
+pub async fn create_funded_account(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let owner = context.payer.pubkey();
+    let account = create_token_account(context, mint, &owner).await;
+    mint_to(context, mint, &account, amount).await;
+    account
+}
+
+/// Reads the balance of a token account.
+pub async fn token_balance(context: &mut ProgramTestContext, account: Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(account)
+        .await
+        .unwrap()
+        .expect("token account missing");
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .amount
+}
+
+pub fn tick_pda(pool: &Pubkey, index: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"tick".as_ref(),
+            pool.as_ref(),
+            index.to_le_bytes().as_ref(),
+        ],
+        &PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn position_pda(
+    pool: &Pubkey,
+    owner: &Pubkey,
+    range: (i32, i32),
+    position_index: u64,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"position".as_ref(),
+            pool.as_ref(),
+            owner.as_ref(),
+            range.0.to_le_bytes().as_ref(),
+            range.1.to_le_bytes().as_ref(),
+            position_index.to_le_bytes().as_ref(),
+        ],
+        &PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn position_counter_pda(pool: &Pubkey, owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"position_counter".as_ref(), pool.as_ref(), owner.as_ref()],
+        &PROGRAM_ID,
+    )
+    .0
+}
+
+/// Installs the program's ProgramData account with `authority` as its upgrade authority,
+/// which `initialize_factory_config` requires of its signer. The test loads the program
+/// without the upgradeable loader, so the account does not exist otherwise.
+pub fn set_upgrade_authority(context: &mut ProgramTestContext, authority: Pubkey) -> Pubkey {
+    let (program_data, _) =
+        Pubkey::find_program_address(&[PROGRAM_ID.as_ref()], &bpf_loader_upgradeable::ID);
+    let state = UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(authority),
+    };
+    let lamports =
+        Rent::default().minimum_balance(UpgradeableLoaderState::size_of_programdata_metadata());
+    let account =
+        AccountSharedData::new_data(lamports, &state, &bpf_loader_upgradeable::ID).unwrap();
+    context.set_account(&program_data, &account);
+    program_data
+}
+
+/// Instructions creating the factory config, with the payer as its authority, and enabling
+/// `fee_rate` with `tick_spacing` on it, skipping whichever an earlier pool of the test
+/// already did. Returns the factory config along with them.
+pub async fn fee_tier_ixs(
+    context: &mut ProgramTestContext,
+    fee_rate: u16,
+    tick_spacing: u16,
+) -> (Pubkey, Vec<Instruction>) {
+    let authority = context.payer.pubkey();
+    let (factory_config, _) =
+        Pubkey::find_program_address(&[b"factory_config".as_ref()], &PROGRAM_ID);
+    let config = context
+        .banks_client
+        .get_account(factory_config)
+        .await
+        .unwrap()
+        .map(|account| FactoryConfig::try_deserialize(&mut account.data.as_slice()).unwrap());
+
+    let mut ixs = Vec::new();
+    if config.is_none() {
+        let program_data = set_upgrade_authority(context, authority);
+        ixs.push(Instruction {
+            program_id: PROGRAM_ID,
+            accounts: amm_core::accounts::InitializeFactoryConfig {
+                factory_config,
+                authority,
+                program_data,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm_core::instruction::InitializeFactoryConfigHandler {
+                // No protocol share at any liquidity.
+                protocol_fee_curve: ProtocolFeeCurve {
+                    tvl_breakpoints: [1, 2, 3, 4],
+                    protocol_share_bps: [0; 5],
+                },
+            }
+            .data(),
+        });
+    }
+    if config.is_none_or(|config| config.tick_spacing_for(fee_rate).is_none()) {
+        ixs.push(Instruction {
+            program_id: PROGRAM_ID,
+            accounts: amm_core::accounts::EnableFeeTier {
+                factory_config,
+                authority,
+            }
+            .to_account_metas(None),
+            data: amm_core::instruction::EnableFeeTierHandler {
+                fee_rate,
+                tick_spacing,
+            }
+            .data(),
+        });
+    }
+    (factory_config, ixs)
+}
+
+/// A pool created by `initialize_pool`.
+pub struct PoolAccounts {
+    pub pool: Pubkey,
+    pub factory_config: Pubkey,
+    pub token_pair: Pubkey,
+    pub vault_a: Pubkey,
+    pub vault_b: Pubkey,
+}
+
+/// Creates the token pair of `mint_a` and `mint_b`, which must be in ascending order, and
+/// a pool of them at `FEE_RATE` and `TICK_SPACING` starting at `initial_sqrt_price_q64`.
+pub async fn initialize_pool(
+    context: &mut ProgramTestContext,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    initial_sqrt_price_q64: u128,
+) -> PoolAccounts {
+    let payer = context.payer.insecure_clone();
+    let (pool, _) = Pubkey::find_program_address(
+        &[
+            b"pool".as_ref(),
+            mint_a.as_ref(),
+            mint_b.as_ref(),
+            FEE_RATE.to_le_bytes().as_ref(),
+        ],
+        &PROGRAM_ID,
+    );
+    let (pool_registry, _) = Pubkey::find_program_address(
+        &[b"pool_registry".as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        &PROGRAM_ID,
+    );
+    let (token_pair, _) = Pubkey::find_program_address(
+        &[b"token_pair".as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        &PROGRAM_ID,
+    );
+    let create_pair_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::CreateTokenPair {
+            token_pair,
+            mint_a,
+            mint_b,
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::CreateTokenPairHandler {
+            oracle_feed: Pubkey::default(),
+        }
+        .data(),
+    };
+    let vault_a = Keypair::new();
+    let vault_b = Keypair::new();
+    let (factory_config, fee_tier_ixs) = fee_tier_ixs(context, FEE_RATE, TICK_SPACING).await;
+    let init_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::InitializePool {
+            pool,
+            mint_a,
+            mint_b,
+            factory: factory_config,
+            pool_vault_a: vault_a.pubkey(),
+            pool_vault_b: vault_b.pubkey(),
+            pool_registry,
+            token_pair,
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+            token_program: spl_token::ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::InitializePoolHandler {
+            initial_sqrt_price_q64,
+            fee_rate: FEE_RATE,
+            tick_spacing: TICK_SPACING,
+            immutable_parameters: false,
+        }
+        .data(),
+    };
+    process(
+        context,
+        &[fee_tier_ixs, vec![create_pair_ix, init_ix]].concat(),
+        &[&payer, &vault_a, &vault_b],
+    )
+    .await;
+
+    PoolAccounts {
+        pool,
+        factory_config,
+        token_pair,
+        vault_a: vault_a.pubkey(),
+        vault_b: vault_b.pubkey(),
+    }
+}
+
+/// Accounts shared by the instructions of a test.
+pub struct Setup {
+    pub pool: Pubkey,
+    pub factory_config: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub vault_a: Pubkey,
+    pub vault_b: Pubkey,
+    /// The payer's token0 account.
+    pub owner_a: Pubkey,
+    /// The payer's token1 account.
+    pub owner_b: Pubkey,
+}
+
+/// Creates two mints, their token pair, a pool of them at price 1.0 and token accounts for
+/// the payer funded with `OWNER_FUNDING` each.
+pub async fn setup_pool(context: &mut ProgramTestContext) -> Setup {
+    let mut mint_a = create_mint(context).await;
+    let mut mint_b = create_mint(context).await;
+    if mint_a > mint_b {
+        std::mem::swap(&mut mint_a, &mut mint_b);
+    }
+    let accounts = initialize_pool(context, mint_a, mint_b, 1u128 << 64).await;
+    let owner_a = create_funded_account(context, &mint_a, OWNER_FUNDING).await;
+    let owner_b = create_funded_account(context, &mint_b, OWNER_FUNDING).await;
+    Setup {
+        pool: accounts.pool,
+        factory_config: accounts.factory_config,
+        mint_a,
+        mint_b,
+        vault_a: accounts.vault_a,
+        vault_b: accounts.vault_b,
+        owner_a,
+        owner_b,
+    }
+}
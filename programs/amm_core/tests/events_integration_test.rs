@@ -0,0 +1,281 @@
+// /tests/events_integration_test.rs
+//
+// Checks that the position and swap instructions emit their Anchor events, by decoding the
+// `Program data:` lines of the transaction logs.
+//
+// The program is loaded as `amm_core.so`, like the other integration tests.
+
+mod common;
+
+use anchor_lang::{prelude::Pubkey, InstructionData, ToAccountMetas};
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    instruction::Instruction,
+    signature::{Keypair, Signer},
+    sysvar,
+};
+
+use amm_core::{
+    position::{CollectFeesEvent, DecreaseLiquidityEvent, MintPositionEvent},
+    state::pool::SwapEvent,
+    ID as PROGRAM_ID,
+};
+use common::*;
+
+const TICK_LOWER: i32 = -600;
+const TICK_UPPER: i32 = 600;
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000;
+const SWAP_AMOUNT_IN: u64 = 10_000;
+#[tokio::test]
+async fn test_position_and_swap_instructions_emit_events() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+
+    let mut mint_a = create_mint(&mut context).await;
+    let mut mint_b = create_mint(&mut context).await;
+    if mint_a > mint_b {
+        std::mem::swap(&mut mint_a, &mut mint_b);
+    }
+
+    // 1. Create the pair's record and initialize the pool at price 1.0
+    let (pool, _) = Pubkey::find_program_address(
+        &[
+            b"pool".as_ref(),
+            mint_a.as_ref(),
+            mint_b.as_ref(),
+            FEE_RATE.to_le_bytes().as_ref(),
+        ],
+        &PROGRAM_ID,
+    );
+    let (pool_registry, _) = Pubkey::find_program_address(
+        &[b"pool_registry".as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        &PROGRAM_ID,
+    );
+    let (token_pair, _) = Pubkey::find_program_address(
+        &[b"token_pair".as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        &PROGRAM_ID,
+    );
+    let create_pair_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::CreateTokenPair {
+            token_pair,
+            mint_a,
+            mint_b,
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::CreateTokenPairHandler {
+            oracle_feed: Pubkey::default(),
+        }
+        .data(),
+    };
+    let vault_a = Keypair::new();
+    let vault_b = Keypair::new();
+    let (factory_config, fee_tier_ixs) = fee_tier_ixs(&mut context, FEE_RATE, 60).await;
+    let init_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::InitializePool {
+            pool,
+            mint_a,
+            mint_b,
+            factory: factory_config,
+            pool_vault_a: vault_a.pubkey(),
+            pool_vault_b: vault_b.pubkey(),
+            pool_registry,
+            token_pair,
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+            token_program: spl_token::ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::InitializePoolHandler {
+            initial_sqrt_price_q64: 1u128 << 64,
+            fee_rate: FEE_RATE,
+            tick_spacing: 60,
+            immutable_parameters: false,
+        }
+        .data(),
+    };
+    process(
+        &mut context,
+        &[fee_tier_ixs, vec![create_pair_ix, init_ix]].concat(),
+        &[&payer, &vault_a, &vault_b],
+    )
+    .await;
+
+    // 2. Mint a position around the current price
+    let owner_a = create_token_account(&mut context, &mint_a, &payer.pubkey()).await;
+    let owner_b = create_token_account(&mut context, &mint_b, &payer.pubkey()).await;
+    mint_to(&mut context, &mint_a, &owner_a, OWNER_FUNDING).await;
+    mint_to(&mut context, &mint_b, &owner_b, OWNER_FUNDING).await;
+    let (position, _) = Pubkey::find_program_address(
+        &[
+            b"position".as_ref(),
+            pool.as_ref(),
+            payer.pubkey().as_ref(),
+            TICK_LOWER.to_le_bytes().as_ref(),
+            TICK_UPPER.to_le_bytes().as_ref(),
+            0u64.to_le_bytes().as_ref(),
+        ],
+        &PROGRAM_ID,
+    );
+    let (position_counter, _) = Pubkey::find_program_address(
+        &[
+            b"position_counter".as_ref(),
+            pool.as_ref(),
+            payer.pubkey().as_ref(),
+        ],
+        &PROGRAM_ID,
+    );
+    let mint_position_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::MintPosition {
+            pool,
+            position,
+            tick_lower: tick_pda(&pool, TICK_LOWER),
+            tick_upper: tick_pda(&pool, TICK_UPPER),
+            owner: payer.pubkey(),
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            position_counter,
+            token0_vault: vault_a.pubkey(),
+            token1_vault: vault_b.pubkey(),
+            owner_token0_account: owner_a,
+            owner_token1_account: owner_b,
+            token_program: spl_token::ID,
+            owner_index: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionHandler {
+            tick_lower_index: TICK_LOWER,
+            tick_upper_index: TICK_UPPER,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            position_index: 0,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    };
+    let logs = process(&mut context, &[mint_position_ix], &[&payer]).await;
+    let minted = events::<MintPositionEvent>(&logs);
+    assert_eq!(minted.len(), 1);
+    assert_eq!(
+        (minted[0].pool, minted[0].position, minted[0].owner),
+        (pool, position, payer.pubkey())
+    );
+    assert_eq!(
+        (minted[0].tick_lower, minted[0].tick_upper),
+        (TICK_LOWER, TICK_UPPER)
+    );
+    assert_eq!(minted[0].liquidity, POSITION_LIQUIDITY);
+    // The price is inside the range, so both tokens are deposited
+    assert!(minted[0].amount0 > 0 && minted[0].amount1 > 0);
+
+    // 3. Swap token0 for token1 inside the position's range
+    let swap_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::SwapExactInput {
+            pool,
+            token0_vault: vault_a.pubkey(),
+            token1_vault: vault_b.pubkey(),
+            user_token_in_account: owner_a,
+            user_token_out_account: owner_b,
+            user_authority: payer.pubkey(),
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::SwapExactInputHandler {
+            amount_in: SWAP_AMOUNT_IN,
+            amount_out_minimum: 1,
+            sqrt_price_limit_q64: 1,
+        }
+        .data(),
+    };
+    let logs = process(&mut context, &[swap_ix], &[&payer]).await;
+    let swaps = events::<SwapEvent>(&logs);
+    assert_eq!(swaps.len(), 1);
+    let swap = &swaps[0];
+    assert_eq!((swap.pool, swap.user), (pool, payer.pubkey()));
+    assert!(swap.zero_for_one);
+    assert_eq!(swap.amount_in, SWAP_AMOUNT_IN);
+    assert!(swap.amount_out > 0 && swap.amount_out < SWAP_AMOUNT_IN);
+    assert_eq!(swap.sqrt_price_before, 1u128 << 64);
+    assert!(swap.sqrt_price_after < swap.sqrt_price_before);
+    // 0.3% of the input, rounded up
+    assert_eq!(swap.fee_paid, 30);
+    assert!(swap.tick_after < 0);
+    assert_eq!(
+        swap.price_impact_bps,
+        amm_core::math::price_impact_bps(swap.sqrt_price_before, swap.sqrt_price_after).unwrap()
+    );
+
+    // 4. Collect the swap fees, then remove part of the liquidity
+    let collect_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::CollectFees {
+            pool,
+            position,
+            tick_lower: tick_pda(&pool, TICK_LOWER),
+            tick_upper: tick_pda(&pool, TICK_UPPER),
+            token0_vault: vault_a.pubkey(),
+            token1_vault: vault_b.pubkey(),
+            owner_token0_account: owner_a,
+            owner_token1_account: owner_b,
+            owner: payer.pubkey(),
+            token_program: spl_token::ID,
+            position_token_account: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::CollectFeesHandler {
+            amount_0_requested: u64::MAX,
+            amount_1_requested: u64::MAX,
+        }
+        .data(),
+    };
+    let logs = process(&mut context, &[collect_ix], &[&payer]).await;
+    let collected = events::<CollectFeesEvent>(&logs);
+    assert_eq!(collected.len(), 1);
+    assert_eq!(
+        (collected[0].pool, collected[0].position, collected[0].owner),
+        (pool, position, payer.pubkey())
+    );
+    // The only position earned the whole swap fee, less rounding
+    assert!(collected[0].amount0 > 0 && collected[0].amount0 <= swap.fee_paid);
+    assert_eq!(collected[0].amount1, 0);
+
+    let liquidity_delta = POSITION_LIQUIDITY / 4;
+    let decrease_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::DecreaseLiquidity {
+            pool,
+            position,
+            tick_lower: tick_pda(&pool, TICK_LOWER),
+            tick_upper: tick_pda(&pool, TICK_UPPER),
+            token0_vault: vault_a.pubkey(),
+            token1_vault: vault_b.pubkey(),
+            owner_token0_account: owner_a,
+            owner_token1_account: owner_b,
+            owner: payer.pubkey(),
+            token_program: spl_token::ID,
+            position_token_account: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::DecreaseLiquidityHandler { liquidity_delta }.data(),
+    };
+    let logs = process(&mut context, &[decrease_ix], &[&payer]).await;
+    let decreased = events::<DecreaseLiquidityEvent>(&logs);
+    assert_eq!(decreased.len(), 1);
+    assert_eq!(
+        (decreased[0].pool, decreased[0].position, decreased[0].owner),
+        (pool, position, payer.pubkey())
+    );
+    assert_eq!(decreased[0].liquidity_delta, liquidity_delta);
+    assert_eq!(decreased[0].liquidity, POSITION_LIQUIDITY - liquidity_delta);
+    // The price is inside the range, so both tokens are paid out
+    assert!(decreased[0].amount0 > 0 && decreased[0].amount1 > 0);
+}
@@ -8,8 +8,11 @@ use anchor_lang::{
 };
 use solana_program_test::{BanksClientError, ProgramTest, ProgramTestContext}; // Explicit imports, Added BanksClientError
 use solana_sdk::{
+    account::AccountSharedData,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
     instruction::AccountMeta, // Import AccountMeta directly
     instruction::Instruction,
+    rent::Rent,
     signature::{Keypair, Signer},
     sysvar, // For sysvar::rent::id()
     transaction::Transaction,
@@ -18,10 +21,17 @@ use solana_sdk::{
 
 // Assuming your crate is named amm_core
 use amm_core::{
-    self,                                                     // Import the crate itself
-    errors::ErrorCode,                                        // Import ErrorCode
+    self,              // Import the crate itself
+    errors::ErrorCode, // Import ErrorCode
+    instruction::CreateTokenPairHandler as CreateTokenPairData,
+    instruction::EnableFeeTierHandler as EnableFeeTierData,
+    instruction::InitializeFactoryConfigHandler as InitializeFactoryConfigData,
     instruction::InitializePoolHandler as InitializePoolData, // Correct instruction data struct
+    instruction::SetProtocolFeeHandler as SetProtocolFeeData,
+    state::factory_config::ProtocolFeeCurve,
     state::pool::Pool,
+    state::pool_registry::PoolRegistry,
+    state::token_pair::TokenPair,
     ID as PROGRAM_ID, // Use the declared program ID
 };
 
@@ -103,6 +113,150 @@ async fn create_token_account(
     Ok(token_account_keypair.pubkey())
 }
 
+// Helper to derive the pool PDA for a (pair, fee tier)
+fn find_pool_pda(mint_a: &Pubkey, mint_b: &Pubkey, fee_rate: u16) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"pool".as_ref(),
+            mint_a.as_ref(),
+            mint_b.as_ref(),
+            fee_rate.to_le_bytes().as_ref(),
+        ],
+        &PROGRAM_ID,
+    )
+}
+
+// Helper to derive the registry PDA listing all fee tiers of a pair
+fn find_pool_registry_pda(mint_a: &Pubkey, mint_b: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"pool_registry".as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        &PROGRAM_ID,
+    )
+    .0
+}
+
+// Helper to derive the record of a pair
+fn find_token_pair_pda(mint_a: &Pubkey, mint_b: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"token_pair".as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        &PROGRAM_ID,
+    )
+    .0
+}
+
+// Helper to build the create_token_pair instruction that must precede a pair's first pool.
+fn build_create_token_pair_ix(
+    payer: &Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    oracle_feed: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(find_token_pair_pda(&mint_a, &mint_b), false), // token_pair
+            AccountMeta::new_readonly(mint_a, false),
+            AccountMeta::new_readonly(mint_b, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+        ],
+        data: CreateTokenPairData { oracle_feed }.data(),
+    }
+}
+
+// Helper to derive the factory config, which every pool names as its factory
+fn find_factory_config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"factory_config".as_ref()], &PROGRAM_ID).0
+}
+
+// Helper to build the enable_fee_tier instruction, signed by the factory config authority.
+fn build_enable_fee_tier_ix(authority: &Pubkey, fee_rate: u16, tick_spacing: u16) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(find_factory_config_pda(), false), // factory_config
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data: EnableFeeTierData {
+            fee_rate,
+            tick_spacing,
+        }
+        .data(),
+    }
+}
+
+// Helper to build the set_protocol_fee instruction, signed by `authority`.
+fn build_set_protocol_fee_ix(pool: Pubkey, authority: &Pubkey, protocol_fee: u16) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(pool, false),
+            AccountMeta::new_readonly(find_factory_config_pda(), false), // factory_config
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data: SetProtocolFeeData {
+            protocol_fee: Some(protocol_fee),
+        }
+        .data(),
+    }
+}
+
+// Helper to install the program's ProgramData account with `authority` as its upgrade
+// authority, which initialize_factory_config requires of its signer. The test loads the
+// program without the upgradeable loader, so the account does not exist otherwise.
+fn set_upgrade_authority(context: &mut ProgramTestContext, authority: &Pubkey) -> Pubkey {
+    let (program_data, _) =
+        Pubkey::find_program_address(&[PROGRAM_ID.as_ref()], &bpf_loader_upgradeable::ID);
+    let state = UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(*authority),
+    };
+    let lamports =
+        Rent::default().minimum_balance(UpgradeableLoaderState::size_of_programdata_metadata());
+    let account =
+        AccountSharedData::new_data(lamports, &state, &bpf_loader_upgradeable::ID).unwrap();
+    context.set_account(&program_data, &account);
+    program_data
+}
+
+// Helper to build the initialize_factory_config instruction, signed by `authority`.
+fn build_initialize_factory_config_ix(authority: &Pubkey, program_data: Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(find_factory_config_pda(), false), // factory_config
+            AccountMeta::new(*authority, true),                 // authority
+            AccountMeta::new_readonly(program_data, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+        ],
+        data: InitializeFactoryConfigData {
+            // No protocol share at any liquidity.
+            protocol_fee_curve: ProtocolFeeCurve {
+                tvl_breakpoints: [1, 2, 3, 4],
+                protocol_share_bps: [0; 5],
+            },
+        }
+        .data(),
+    }
+}
+
+// Helper to build the instructions creating the factory config, with `payer` as its
+// authority and the program's upgrade authority, and enabling each
+// `(fee_rate, tick_spacing)` of `fee_tiers` on it.
+fn build_factory_config_ixs(
+    context: &mut ProgramTestContext,
+    payer: &Pubkey,
+    fee_tiers: &[(u16, u16)],
+) -> Vec<Instruction> {
+    let program_data = set_upgrade_authority(context, payer);
+    let initialize_instruction = build_initialize_factory_config_ix(payer, program_data);
+    std::iter::once(initialize_instruction)
+        .chain(fee_tiers.iter().map(|&(fee_rate, tick_spacing)| {
+            build_enable_fee_tier_ix(payer, fee_rate, tick_spacing)
+        }))
+        .collect()
+}
+
 #[tokio::test]
 async fn test_initialize_pool_success() {
     let program_test = ProgramTest::new(
@@ -120,7 +274,6 @@ async fn test_initialize_pool_success() {
 
     let mut context = program_test.start_with_context().await;
     let payer = context.payer.insecure_clone(); // Payer for transactions
-    let factory_keypair = Keypair::new(); // For the factory account
 
     // 1. Create Mints (ensure canonical order for PDA derivation)
     // We'll create two mints and then sort them by pubkey to ensure canonical order.
@@ -139,14 +292,11 @@ async fn test_initialize_pool_success() {
     println!("Mint B: {mint_b_pubkey}");
 
     // 2. Define PDAs for Pool and Vaults
-    let (pool_pda, pool_bump) = Pubkey::find_program_address(
-        &[
-            b"pool".as_ref(),
-            mint_a_pubkey.as_ref(),
-            mint_b_pubkey.as_ref(),
-        ],
-        &PROGRAM_ID,
-    );
+    let fee_rate: u16 = 30; // 0.3%
+    let (pool_pda, pool_bump) = find_pool_pda(&mint_a_pubkey, &mint_b_pubkey, fee_rate);
+    let pool_registry_pda = find_pool_registry_pda(&mint_a_pubkey, &mint_b_pubkey);
+    let token_pair_pda = find_token_pair_pda(&mint_a_pubkey, &mint_b_pubkey);
+    let oracle_feed = Pubkey::new_unique();
 
     // Vault PDAs are derived with the pool PDA as authority (as per constraints)
     // but for init, the authority is the pool PDA itself.
@@ -158,7 +308,6 @@ async fn test_initialize_pool_success() {
 
     // 3. Define Instruction Parameters
     let initial_sqrt_price_q64: u128 = 79228162514264337593543950336; // Example: 1 * 2^64 (for price 1)
-    let fee_rate: u16 = 30; // 0.3%
     let tick_spacing: u16 = 60; // Example tick spacing
 
     // 4. Construct the instruction
@@ -167,9 +316,11 @@ async fn test_initialize_pool_success() {
         AccountMeta::new(pool_pda, false), // pool (writable, not signer by instruction itself for init)
         AccountMeta::new_readonly(mint_a_pubkey, false), // mint_a
         AccountMeta::new_readonly(mint_b_pubkey, false), // mint_b
-        AccountMeta::new_readonly(factory_keypair.pubkey(), false), // factory
+        AccountMeta::new_readonly(find_factory_config_pda(), false), // factory
         AccountMeta::new(pool_vault_a_keypair.pubkey(), true), // pool_vault_a (writable, signer)
         AccountMeta::new(pool_vault_b_keypair.pubkey(), true), // pool_vault_b (writable, signer)
+        AccountMeta::new(pool_registry_pda, false), // pool_registry (writable, init_if_needed)
+        AccountMeta::new(token_pair_pda, false), // token_pair (writable, must exist)
         AccountMeta::new(payer.pubkey(), true), // payer (writable, signer)
         AccountMeta::new_readonly(anchor_lang::system_program::ID, false), // system_program
         AccountMeta::new_readonly(spl_token::ID, false), // token_program
@@ -180,6 +331,7 @@ async fn test_initialize_pool_success() {
         initial_sqrt_price_q64,
         fee_rate,
         tick_spacing,
+        immutable_parameters: false,
     };
 
     let instruction = Instruction {
@@ -191,8 +343,14 @@ async fn test_initialize_pool_success() {
     // Signers: payer, and also vault keypairs because they are being initialized.
     // The `InitializePool` struct in `lib.rs` uses `init` for `pool_vault_a` and `pool_vault_b`,
     // meaning these accounts must be signers if they are new.
+    // The pair's record must exist before its first pool.
+    let create_pair_instruction =
+        build_create_token_pair_ix(&payer.pubkey(), mint_a_pubkey, mint_b_pubkey, oracle_feed);
+    let mut instructions =
+        build_factory_config_ixs(&mut context, &payer.pubkey(), &[(fee_rate, tick_spacing)]);
+    instructions.extend([create_pair_instruction, instruction]);
     let transaction = Transaction::new_signed_with_payer(
-        &[instruction],
+        &instructions,
         Some(&payer.pubkey()),
         &[&payer, &pool_vault_a_keypair, &pool_vault_b_keypair], // Vaults must sign as they are initialized.
         context.last_blockhash,
@@ -216,7 +374,7 @@ async fn test_initialize_pool_success() {
     let pool_state = Pool::try_deserialize(&mut pool_account_data.data.as_slice()).unwrap();
 
     assert_eq!(pool_state.bump, pool_bump);
-    assert_eq!(pool_state.factory, factory_keypair.pubkey());
+    assert_eq!(pool_state.factory, find_factory_config_pda());
     assert_eq!(pool_state.token0_mint, mint_a_pubkey); // mint_a is token0 due to canonical order
     assert_eq!(pool_state.token1_mint, mint_b_pubkey); // mint_b is token1
     assert_eq!(pool_state.token0_vault, pool_vault_a_keypair.pubkey());
@@ -224,6 +382,7 @@ async fn test_initialize_pool_success() {
     assert_eq!(pool_state.fee_rate, fee_rate);
     assert_eq!(pool_state.tick_spacing, tick_spacing);
     assert_eq!(pool_state.sqrt_price_q64, initial_sqrt_price_q64);
+    assert_eq!(pool_state.token_pair, token_pair_pda);
 
     // Calculate expected_current_tick (this requires access to math::sqrt_price_q64_to_tick)
     // For now, we'll assert it's not the default i32 (0), assuming successful calculation.
@@ -257,6 +416,35 @@ async fn test_initialize_pool_success() {
     assert_eq!(vault_b_state.mint, mint_b_pubkey);
     assert_eq!(vault_b_state.owner, pool_pda); // Authority should be the pool PDA
 
+    // Verify the pool was recorded in the pair's registry
+    let registry_account_data = context
+        .banks_client
+        .get_account(pool_registry_pda)
+        .await
+        .expect("Pool registry not found")
+        .expect("Pool registry is empty");
+    let registry_state =
+        PoolRegistry::try_deserialize(&mut registry_account_data.data.as_slice()).unwrap();
+    assert_eq!(registry_state.mint_a, mint_a_pubkey);
+    assert_eq!(registry_state.mint_b, mint_b_pubkey);
+    assert_eq!(registry_state.entries.len(), 1);
+    assert_eq!(registry_state.pool_for_fee_rate(fee_rate), Some(pool_pda));
+
+    // Verify the pair's record counts the pool
+    let token_pair_account_data = context
+        .banks_client
+        .get_account(token_pair_pda)
+        .await
+        .expect("Token pair not found")
+        .expect("Token pair is empty");
+    let token_pair_state =
+        TokenPair::try_deserialize(&mut token_pair_account_data.data.as_slice()).unwrap();
+    assert_eq!(token_pair_state.mint_a, mint_a_pubkey);
+    assert_eq!(token_pair_state.mint_b, mint_b_pubkey);
+    assert_eq!(token_pair_state.oracle_feed, oracle_feed);
+    assert!(token_pair_state.is_canonical);
+    assert_eq!(token_pair_state.pool_count, 1);
+
     println!("Successfully initialized pool and verified state!");
 }
 
@@ -265,7 +453,6 @@ async fn test_initialize_pool_mints_not_canonical() {
     let program_test = ProgramTest::new("amm_core", PROGRAM_ID, None);
     let mut context = program_test.start_with_context().await;
     let payer = context.payer.insecure_clone();
-    let factory_keypair = Keypair::new();
 
     let (mut mint_a_keypair, mut mint_a_pubkey) =
         create_mint(&mut context, &payer.pubkey()).await.unwrap();
@@ -280,13 +467,11 @@ async fn test_initialize_pool_mints_not_canonical() {
     println!("Mint A (non-canonical): {mint_a_pubkey}");
     println!("Mint B (non-canonical): {mint_b_pubkey}");
 
-    let (_pool_pda, _pool_bump) = Pubkey::find_program_address(
-        &[
-            b"pool".as_ref(),
-            mint_a_pubkey.as_ref(), // These will be in non-canonical order for PDA seed
-            mint_b_pubkey.as_ref(),
-        ],
-        &PROGRAM_ID,
+    let fee_rate: u16 = 30;
+    let (_pool_pda, _pool_bump) = find_pool_pda(
+        &mint_a_pubkey, // These will be in non-canonical order for PDA seed
+        &mint_b_pubkey,
+        fee_rate,
     );
     // The actual PDA derivation for the transaction will use these non-canonical mints.
     // However, the instruction handler has a specific check:
@@ -301,28 +486,27 @@ async fn test_initialize_pool_mints_not_canonical() {
     // So, the `pool_pda` here should be derived using the *passed* mint_a and mint_b for consistency,
     // even though the program expects them to be canonical for the *actual* stored PDA.
     // The critical part is that `mint_a_pubkey` passed to the instruction is greater than `mint_b_pubkey`.
-    let (pool_pda_attempt, _pool_bump_attempt) = Pubkey::find_program_address(
-        &[
-            b"pool".as_ref(),
-            mint_a_pubkey.as_ref(), // Intentionally using the larger key first for seeds to match instruction
-            mint_b_pubkey.as_ref(), // Intentionally using the smaller key second
-        ],
-        &PROGRAM_ID,
+    let (pool_pda_attempt, _pool_bump_attempt) = find_pool_pda(
+        &mint_a_pubkey, // Intentionally using the larger key first for seeds to match instruction
+        &mint_b_pubkey, // Intentionally using the smaller key second
+        fee_rate,
     );
+    let pool_registry_pda_attempt = find_pool_registry_pda(&mint_a_pubkey, &mint_b_pubkey);
 
     let pool_vault_a_keypair = Keypair::new();
     let pool_vault_b_keypair = Keypair::new();
     let initial_sqrt_price_q64: u128 = 79228162514264337593543950336;
-    let fee_rate: u16 = 30;
     let tick_spacing: u16 = 60;
 
     let account_metas = vec![
         AccountMeta::new(pool_pda_attempt, false),
         AccountMeta::new_readonly(mint_a_pubkey, false), // mint_a (non-canonical larger)
         AccountMeta::new_readonly(mint_b_pubkey, false), // mint_b (non-canonical smaller)
-        AccountMeta::new_readonly(factory_keypair.pubkey(), false),
+        AccountMeta::new_readonly(find_factory_config_pda(), false),
         AccountMeta::new(pool_vault_a_keypair.pubkey(), true),
         AccountMeta::new(pool_vault_b_keypair.pubkey(), true),
+        AccountMeta::new(pool_registry_pda_attempt, false),
+        AccountMeta::new(find_token_pair_pda(&mint_a_pubkey, &mint_b_pubkey), false),
         AccountMeta::new(payer.pubkey(), true),
         AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
         AccountMeta::new_readonly(spl_token::ID, false),
@@ -333,6 +517,7 @@ async fn test_initialize_pool_mints_not_canonical() {
         initial_sqrt_price_q64,
         fee_rate,
         tick_spacing,
+        immutable_parameters: false,
     };
     let instruction = Instruction {
         program_id: PROGRAM_ID,
@@ -340,8 +525,19 @@ async fn test_initialize_pool_mints_not_canonical() {
         data: instruction_data_struct.data(),
     };
 
+    // A pool needs its pair's record, and the record of a non-canonical pair cannot be
+    // created, so the order is rejected by create_token_pair before the pool is reached.
+    let create_pair_instruction = build_create_token_pair_ix(
+        &payer.pubkey(),
+        mint_a_pubkey,
+        mint_b_pubkey,
+        Pubkey::default(),
+    );
+    let mut instructions =
+        build_factory_config_ixs(&mut context, &payer.pubkey(), &[(fee_rate, 60)]);
+    instructions.extend([create_pair_instruction, instruction]);
     let transaction = Transaction::new_signed_with_payer(
-        &[instruction],
+        &instructions,
         Some(&payer.pubkey()),
         &[&payer, &pool_vault_a_keypair, &pool_vault_b_keypair],
         context.last_blockhash,
@@ -380,7 +576,6 @@ async fn test_initialize_pool_invalid_tick_spacing() {
     let program_test = ProgramTest::new("amm_core", PROGRAM_ID, None);
     let mut context = program_test.start_with_context().await;
     let payer = context.payer.insecure_clone();
-    let factory_keypair = Keypair::new();
 
     let (mut mint_a_keypair, mut mint_a_pubkey) =
         create_mint(&mut context, &payer.pubkey()).await.unwrap();
@@ -392,27 +587,23 @@ async fn test_initialize_pool_invalid_tick_spacing() {
         std::mem::swap(&mut mint_a_pubkey, &mut mint_b_pubkey);
     }
 
-    let (pool_pda, _pool_bump) = Pubkey::find_program_address(
-        &[
-            b"pool".as_ref(),
-            mint_a_pubkey.as_ref(),
-            mint_b_pubkey.as_ref(),
-        ],
-        &PROGRAM_ID,
-    );
+    let fee_rate: u16 = 30;
+    let (pool_pda, _pool_bump) = find_pool_pda(&mint_a_pubkey, &mint_b_pubkey, fee_rate);
+    let pool_registry_pda = find_pool_registry_pda(&mint_a_pubkey, &mint_b_pubkey);
     let pool_vault_a_keypair = Keypair::new();
     let pool_vault_b_keypair = Keypair::new();
     let initial_sqrt_price_q64: u128 = 79228162514264337593543950336;
-    let fee_rate: u16 = 30;
     let tick_spacing: u16 = 0; // Invalid tick spacing
 
     let account_metas = vec![
         AccountMeta::new(pool_pda, false),
         AccountMeta::new_readonly(mint_a_pubkey, false),
         AccountMeta::new_readonly(mint_b_pubkey, false),
-        AccountMeta::new_readonly(factory_keypair.pubkey(), false),
+        AccountMeta::new_readonly(find_factory_config_pda(), false),
         AccountMeta::new(pool_vault_a_keypair.pubkey(), true),
         AccountMeta::new(pool_vault_b_keypair.pubkey(), true),
+        AccountMeta::new(pool_registry_pda, false),
+        AccountMeta::new(find_token_pair_pda(&mint_a_pubkey, &mint_b_pubkey), false),
         AccountMeta::new(payer.pubkey(), true),
         AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
         AccountMeta::new_readonly(spl_token::ID, false),
@@ -423,6 +614,7 @@ async fn test_initialize_pool_invalid_tick_spacing() {
         initial_sqrt_price_q64,
         fee_rate,
         tick_spacing,
+        immutable_parameters: false,
     };
     let instruction = Instruction {
         program_id: PROGRAM_ID,
@@ -430,8 +622,17 @@ async fn test_initialize_pool_invalid_tick_spacing() {
         data: instruction_data_struct.data(),
     };
 
+    let create_pair_instruction = build_create_token_pair_ix(
+        &payer.pubkey(),
+        mint_a_pubkey,
+        mint_b_pubkey,
+        Pubkey::default(),
+    );
+    let mut instructions =
+        build_factory_config_ixs(&mut context, &payer.pubkey(), &[(fee_rate, 60)]);
+    instructions.extend([create_pair_instruction, instruction]);
     let transaction = Transaction::new_signed_with_payer(
-        &[instruction],
+        &instructions,
         Some(&payer.pubkey()),
         &[&payer, &pool_vault_a_keypair, &pool_vault_b_keypair],
         context.last_blockhash,
@@ -458,7 +659,6 @@ async fn test_initialize_pool_invalid_initial_price() {
     let program_test = ProgramTest::new("amm_core", PROGRAM_ID, None);
     let mut context = program_test.start_with_context().await;
     let payer = context.payer.insecure_clone();
-    let factory_keypair = Keypair::new();
 
     let (mut mint_a_keypair, mut mint_a_pubkey) =
         create_mint(&mut context, &payer.pubkey()).await.unwrap();
@@ -470,27 +670,23 @@ async fn test_initialize_pool_invalid_initial_price() {
         std::mem::swap(&mut mint_a_pubkey, &mut mint_b_pubkey);
     }
 
-    let (pool_pda, _pool_bump) = Pubkey::find_program_address(
-        &[
-            b"pool".as_ref(),
-            mint_a_pubkey.as_ref(),
-            mint_b_pubkey.as_ref(),
-        ],
-        &PROGRAM_ID,
-    );
+    let fee_rate: u16 = 30;
+    let (pool_pda, _pool_bump) = find_pool_pda(&mint_a_pubkey, &mint_b_pubkey, fee_rate);
+    let pool_registry_pda = find_pool_registry_pda(&mint_a_pubkey, &mint_b_pubkey);
     let pool_vault_a_keypair = Keypair::new();
     let pool_vault_b_keypair = Keypair::new();
     let initial_sqrt_price_q64: u128 = 0; // Invalid initial price
-    let fee_rate: u16 = 30;
     let tick_spacing: u16 = 60;
 
     let account_metas_zero_price = vec![
         AccountMeta::new(pool_pda, false),
         AccountMeta::new_readonly(mint_a_pubkey, false),
         AccountMeta::new_readonly(mint_b_pubkey, false),
-        AccountMeta::new_readonly(factory_keypair.pubkey(), false),
+        AccountMeta::new_readonly(find_factory_config_pda(), false),
         AccountMeta::new(pool_vault_a_keypair.pubkey(), true),
         AccountMeta::new(pool_vault_b_keypair.pubkey(), true),
+        AccountMeta::new(pool_registry_pda, false),
+        AccountMeta::new(find_token_pair_pda(&mint_a_pubkey, &mint_b_pubkey), false),
         AccountMeta::new(payer.pubkey(), true),
         AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
         AccountMeta::new_readonly(spl_token::ID, false),
@@ -501,6 +697,7 @@ async fn test_initialize_pool_invalid_initial_price() {
         initial_sqrt_price_q64,
         fee_rate,
         tick_spacing,
+        immutable_parameters: false,
     };
     let instruction = Instruction {
         program_id: PROGRAM_ID,
@@ -508,8 +705,18 @@ async fn test_initialize_pool_invalid_initial_price() {
         data: instruction_data_zero_price.data(),
     };
 
+    // Failed transactions are rolled back, so each attempt creates the factory config and
+    // the pair's record again.
+    let mut setup_instructions =
+        build_factory_config_ixs(&mut context, &payer.pubkey(), &[(fee_rate, tick_spacing)]);
+    setup_instructions.push(build_create_token_pair_ix(
+        &payer.pubkey(),
+        mint_a_pubkey,
+        mint_b_pubkey,
+        Pubkey::default(),
+    ));
     let transaction = Transaction::new_signed_with_payer(
-        &[instruction],
+        &[setup_instructions.clone(), vec![instruction]].concat(),
         Some(&payer.pubkey()),
         &[&payer, &pool_vault_a_keypair, &pool_vault_b_keypair], // Added missing comma here
         context.last_blockhash,
@@ -540,6 +747,7 @@ async fn test_initialize_pool_invalid_initial_price() {
         initial_sqrt_price_q64: too_large_sqrt_price_q64,
         fee_rate,
         tick_spacing,
+        immutable_parameters: false,
     };
     let instruction_large_price = Instruction {
         program_id: PROGRAM_ID,
@@ -547,7 +755,7 @@ async fn test_initialize_pool_invalid_initial_price() {
         data: instruction_data_large_price.data(),
     };
     let transaction_large_price = Transaction::new_signed_with_payer(
-        &[instruction_large_price],
+        &[setup_instructions, vec![instruction_large_price]].concat(),
         Some(&payer.pubkey()),
         &[&payer, &pool_vault_a_keypair, &pool_vault_b_keypair],
         context.last_blockhash,
@@ -570,3 +778,645 @@ async fn test_initialize_pool_invalid_initial_price() {
     }
     println!("Successfully tested invalid initial price failure (too large).");
 }
+
+// Helper to build an initialize_pool instruction for the given pool account and fee tier.
+// Returns the instruction and the vault keypairs that must sign it.
+fn build_initialize_pool_ix(
+    payer: &Pubkey,
+    pool: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    fee_rate: u16,
+    tick_spacing: u16,
+) -> (Instruction, Keypair, Keypair) {
+    let pool_vault_a_keypair = Keypair::new();
+    let pool_vault_b_keypair = Keypair::new();
+    let instruction = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(pool, false),
+            AccountMeta::new_readonly(mint_a, false),
+            AccountMeta::new_readonly(mint_b, false),
+            AccountMeta::new_readonly(find_factory_config_pda(), false),
+            AccountMeta::new(pool_vault_a_keypair.pubkey(), true),
+            AccountMeta::new(pool_vault_b_keypair.pubkey(), true),
+            AccountMeta::new(find_pool_registry_pda(&mint_a, &mint_b), false),
+            AccountMeta::new(find_token_pair_pda(&mint_a, &mint_b), false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+        ],
+        data: InitializePoolData {
+            initial_sqrt_price_q64: 1u128 << 64,
+            fee_rate,
+            tick_spacing,
+            immutable_parameters: false,
+        }
+        .data(),
+    };
+    (instruction, pool_vault_a_keypair, pool_vault_b_keypair)
+}
+
+#[tokio::test]
+async fn test_initialize_two_fee_tiers_for_same_pair() {
+    let program_test = ProgramTest::new("amm_core", PROGRAM_ID, None);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+
+    let (_, mut mint_a_pubkey) = create_mint(&mut context, &payer.pubkey()).await.unwrap();
+    let (_, mut mint_b_pubkey) = create_mint(&mut context, &payer.pubkey()).await.unwrap();
+    if mint_a_pubkey > mint_b_pubkey {
+        std::mem::swap(&mut mint_a_pubkey, &mut mint_b_pubkey);
+    }
+
+    let tiers: [(u16, u16); 2] = [(5, 10), (30, 60)];
+    let mut instructions = build_factory_config_ixs(&mut context, &payer.pubkey(), &tiers);
+    instructions.push(build_create_token_pair_ix(
+        &payer.pubkey(),
+        mint_a_pubkey,
+        mint_b_pubkey,
+        Pubkey::default(),
+    ));
+    let create_pair_transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[&payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(create_pair_transaction)
+        .await
+        .unwrap();
+
+    let mut pools = Vec::new();
+    for (fee_rate, tick_spacing) in tiers {
+        let (pool_pda, _) = find_pool_pda(&mint_a_pubkey, &mint_b_pubkey, fee_rate);
+        let (instruction, vault_a, vault_b) = build_initialize_pool_ix(
+            &payer.pubkey(),
+            pool_pda,
+            mint_a_pubkey,
+            mint_b_pubkey,
+            fee_rate,
+            tick_spacing,
+        );
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&payer.pubkey()),
+            &[&payer, &vault_a, &vault_b],
+            context.last_blockhash,
+        );
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+        pools.push(pool_pda);
+    }
+    assert_ne!(pools[0], pools[1]);
+
+    // Both pools exist with their own fee rate
+    for (pool_pda, (fee_rate, _)) in pools.iter().zip(tiers) {
+        let pool_account_data = context
+            .banks_client
+            .get_account(*pool_pda)
+            .await
+            .unwrap()
+            .expect("Pool account not found");
+        let pool_state = Pool::try_deserialize(&mut pool_account_data.data.as_slice()).unwrap();
+        assert_eq!(pool_state.fee_rate, fee_rate);
+        assert_eq!(pool_state.token0_mint, mint_a_pubkey);
+        assert_eq!(pool_state.token1_mint, mint_b_pubkey);
+    }
+
+    // A single registry fetch enumerates both tiers
+    let registry_account_data = context
+        .banks_client
+        .get_account(find_pool_registry_pda(&mint_a_pubkey, &mint_b_pubkey))
+        .await
+        .unwrap()
+        .expect("Pool registry not found");
+    let registry_state =
+        PoolRegistry::try_deserialize(&mut registry_account_data.data.as_slice()).unwrap();
+    assert_eq!(registry_state.entries.len(), 2);
+    assert_eq!(registry_state.pool_for_fee_rate(5), Some(pools[0]));
+    assert_eq!(registry_state.pool_for_fee_rate(30), Some(pools[1]));
+
+    // The pair's record counts both pools
+    let token_pair_account_data = context
+        .banks_client
+        .get_account(find_token_pair_pda(&mint_a_pubkey, &mint_b_pubkey))
+        .await
+        .unwrap()
+        .expect("Token pair not found");
+    let token_pair_state =
+        TokenPair::try_deserialize(&mut token_pair_account_data.data.as_slice()).unwrap();
+    assert_eq!(token_pair_state.pool_count, 2);
+}
+
+#[tokio::test]
+async fn test_initialize_pool_with_reversed_mints_fails() {
+    let program_test = ProgramTest::new("amm_core", PROGRAM_ID, None);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+
+    let (_, mut mint_a_pubkey) = create_mint(&mut context, &payer.pubkey()).await.unwrap();
+    let (_, mut mint_b_pubkey) = create_mint(&mut context, &payer.pubkey()).await.unwrap();
+    if mint_a_pubkey > mint_b_pubkey {
+        std::mem::swap(&mut mint_a_pubkey, &mut mint_b_pubkey);
+    }
+
+    // The pair and its 30 bps pool, in canonical order
+    let (pool_pda, _) = find_pool_pda(&mint_a_pubkey, &mint_b_pubkey, 30);
+    let (instruction, vault_a, vault_b) = build_initialize_pool_ix(
+        &payer.pubkey(),
+        pool_pda,
+        mint_a_pubkey,
+        mint_b_pubkey,
+        30,
+        60,
+    );
+    let mut instructions = build_factory_config_ixs(&mut context, &payer.pubkey(), &[(30, 60)]);
+    instructions.extend([
+        build_create_token_pair_ix(
+            &payer.pubkey(),
+            mint_a_pubkey,
+            mint_b_pubkey,
+            Pubkey::default(),
+        ),
+        instruction,
+    ]);
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[&payer, &vault_a, &vault_b],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // The same pair and fee rate with the mints swapped derives another pool PDA
+    let (reversed_pool_pda, _) = find_pool_pda(&mint_b_pubkey, &mint_a_pubkey, 30);
+    assert_ne!(reversed_pool_pda, pool_pda);
+    let (instruction, vault_a, vault_b) = build_initialize_pool_ix(
+        &payer.pubkey(),
+        reversed_pool_pda,
+        mint_b_pubkey,
+        mint_a_pubkey,
+        30,
+        60,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[&payer, &vault_a, &vault_b],
+        context.last_blockhash,
+    );
+    let result = context.banks_client.process_transaction(transaction).await;
+    match result.unwrap_err() {
+        BanksClientError::TransactionError(
+            solana_sdk::transaction::TransactionError::InstructionError(
+                _,
+                solana_sdk::instruction::InstructionError::Custom(code),
+            ),
+        ) => {
+            assert_eq!(code, ErrorCode::MintsNotInCanonicalOrder as u32);
+        }
+        err => panic!("Expected MintsNotInCanonicalOrder error for reversed mints, got {err:?}"),
+    }
+
+    // No second pool exists, and the pair still counts one
+    assert!(context
+        .banks_client
+        .get_account(reversed_pool_pda)
+        .await
+        .unwrap()
+        .is_none());
+    let token_pair_account_data = context
+        .banks_client
+        .get_account(find_token_pair_pda(&mint_a_pubkey, &mint_b_pubkey))
+        .await
+        .unwrap()
+        .expect("Token pair not found");
+    let token_pair_state =
+        TokenPair::try_deserialize(&mut token_pair_account_data.data.as_slice()).unwrap();
+    assert_eq!(token_pair_state.pool_count, 1);
+}
+
+#[tokio::test]
+async fn test_initialize_pool_with_legacy_seeds_fails() {
+    let program_test = ProgramTest::new("amm_core", PROGRAM_ID, None);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+
+    let (_, mut mint_a_pubkey) = create_mint(&mut context, &payer.pubkey()).await.unwrap();
+    let (_, mut mint_b_pubkey) = create_mint(&mut context, &payer.pubkey()).await.unwrap();
+    if mint_a_pubkey > mint_b_pubkey {
+        std::mem::swap(&mut mint_a_pubkey, &mut mint_b_pubkey);
+    }
+
+    // Pool PDA derived without the fee rate, as before fee tiers were part of the seeds
+    let (legacy_pool_pda, _) = Pubkey::find_program_address(
+        &[
+            b"pool".as_ref(),
+            mint_a_pubkey.as_ref(),
+            mint_b_pubkey.as_ref(),
+        ],
+        &PROGRAM_ID,
+    );
+    let (instruction, vault_a, vault_b) = build_initialize_pool_ix(
+        &payer.pubkey(),
+        legacy_pool_pda,
+        mint_a_pubkey,
+        mint_b_pubkey,
+        30,
+        60,
+    );
+    let create_pair_instruction = build_create_token_pair_ix(
+        &payer.pubkey(),
+        mint_a_pubkey,
+        mint_b_pubkey,
+        Pubkey::default(),
+    );
+    let mut instructions = build_factory_config_ixs(&mut context, &payer.pubkey(), &[(30, 60)]);
+    instructions.extend([create_pair_instruction, instruction]);
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[&payer, &vault_a, &vault_b],
+        context.last_blockhash,
+    );
+
+    let result = context.banks_client.process_transaction(transaction).await;
+    match result.unwrap_err() {
+        BanksClientError::TransactionError(
+            solana_sdk::transaction::TransactionError::InstructionError(
+                _,
+                solana_sdk::instruction::InstructionError::Custom(code),
+            ),
+        ) => {
+            assert_eq!(code, anchor_lang::error::ErrorCode::ConstraintSeeds as u32);
+        }
+        err => panic!("Expected ConstraintSeeds error for legacy pool PDA, got {err:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_create_token_pair_twice_fails() {
+    let program_test = ProgramTest::new("amm_core", PROGRAM_ID, None);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+
+    let (_, mut mint_a_pubkey) = create_mint(&mut context, &payer.pubkey()).await.unwrap();
+    let (_, mut mint_b_pubkey) = create_mint(&mut context, &payer.pubkey()).await.unwrap();
+    if mint_a_pubkey > mint_b_pubkey {
+        std::mem::swap(&mut mint_a_pubkey, &mut mint_b_pubkey);
+    }
+
+    let first_oracle_feed = Pubkey::new_unique();
+    for (attempt, oracle_feed) in [first_oracle_feed, Pubkey::new_unique()]
+        .into_iter()
+        .enumerate()
+    {
+        let transaction = Transaction::new_signed_with_payer(
+            &[build_create_token_pair_ix(
+                &payer.pubkey(),
+                mint_a_pubkey,
+                mint_b_pubkey,
+                oracle_feed,
+            )],
+            Some(&payer.pubkey()),
+            &[&payer],
+            context.last_blockhash,
+        );
+        let result = context.banks_client.process_transaction(transaction).await;
+        if attempt == 0 {
+            result.unwrap();
+        } else {
+            // The record already exists, so the system program refuses to create it again.
+            assert!(result.is_err(), "second creation of a pair must fail");
+        }
+    }
+
+    // The first record is left untouched
+    let token_pair_account_data = context
+        .banks_client
+        .get_account(find_token_pair_pda(&mint_a_pubkey, &mint_b_pubkey))
+        .await
+        .unwrap()
+        .expect("Token pair not found");
+    let token_pair_state =
+        TokenPair::try_deserialize(&mut token_pair_account_data.data.as_slice()).unwrap();
+    assert_eq!(token_pair_state.oracle_feed, first_oracle_feed);
+}
+
+#[tokio::test]
+async fn test_initialize_pool_without_token_pair_fails() {
+    let program_test = ProgramTest::new("amm_core", PROGRAM_ID, None);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+
+    let (_, mut mint_a_pubkey) = create_mint(&mut context, &payer.pubkey()).await.unwrap();
+    let (_, mut mint_b_pubkey) = create_mint(&mut context, &payer.pubkey()).await.unwrap();
+    if mint_a_pubkey > mint_b_pubkey {
+        std::mem::swap(&mut mint_a_pubkey, &mut mint_b_pubkey);
+    }
+
+    let (pool_pda, _) = find_pool_pda(&mint_a_pubkey, &mint_b_pubkey, 30);
+    let (instruction, vault_a, vault_b) = build_initialize_pool_ix(
+        &payer.pubkey(),
+        pool_pda,
+        mint_a_pubkey,
+        mint_b_pubkey,
+        30,
+        60,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            build_factory_config_ixs(&mut context, &payer.pubkey(), &[(30, 60)]),
+            vec![instruction],
+        ]
+        .concat(),
+        Some(&payer.pubkey()),
+        &[&payer, &vault_a, &vault_b],
+        context.last_blockhash,
+    );
+
+    let result = context.banks_client.process_transaction(transaction).await;
+    match result.unwrap_err() {
+        BanksClientError::TransactionError(
+            solana_sdk::transaction::TransactionError::InstructionError(
+                _,
+                solana_sdk::instruction::InstructionError::Custom(code),
+            ),
+        ) => {
+            assert_eq!(
+                code,
+                anchor_lang::error::ErrorCode::AccountNotInitialized as u32
+            );
+        }
+        err => panic!("Expected AccountNotInitialized error for missing token pair, got {err:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_initialize_pool_needs_enabled_fee_tier() {
+    let program_test = ProgramTest::new("amm_core", PROGRAM_ID, None);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+
+    let (_, mut mint_a_pubkey) = create_mint(&mut context, &payer.pubkey()).await.unwrap();
+    let (_, mut mint_b_pubkey) = create_mint(&mut context, &payer.pubkey()).await.unwrap();
+    if mint_a_pubkey > mint_b_pubkey {
+        std::mem::swap(&mut mint_a_pubkey, &mut mint_b_pubkey);
+    }
+
+    // The factory config enables 30 bps pools only
+    let mut instructions = build_factory_config_ixs(&mut context, &payer.pubkey(), &[(30, 60)]);
+    instructions.push(build_create_token_pair_ix(
+        &payer.pubkey(),
+        mint_a_pubkey,
+        mint_b_pubkey,
+        Pubkey::default(),
+    ));
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[&payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // A 5 bps pool is rejected until its tier is enabled
+    let (pool_pda, _) = find_pool_pda(&mint_a_pubkey, &mint_b_pubkey, 5);
+    let (instruction, vault_a, vault_b) = build_initialize_pool_ix(
+        &payer.pubkey(),
+        pool_pda,
+        mint_a_pubkey,
+        mint_b_pubkey,
+        5,
+        10,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        std::slice::from_ref(&instruction),
+        Some(&payer.pubkey()),
+        &[&payer, &vault_a, &vault_b],
+        context.last_blockhash,
+    );
+    let result = context.banks_client.process_transaction(transaction).await;
+    match result.unwrap_err() {
+        BanksClientError::TransactionError(
+            solana_sdk::transaction::TransactionError::InstructionError(
+                _,
+                solana_sdk::instruction::InstructionError::Custom(code),
+            ),
+        ) => {
+            assert_eq!(code, ErrorCode::InvalidFeeTier as u32);
+        }
+        err => panic!("Expected InvalidFeeTier error for a disabled fee tier, got {err:?}"),
+    }
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            build_enable_fee_tier_ix(&payer.pubkey(), 5, 10),
+            instruction,
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &vault_a, &vault_b],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let pool_account_data = context
+        .banks_client
+        .get_account(pool_pda)
+        .await
+        .unwrap()
+        .expect("Pool account not found");
+    let pool_state = Pool::try_deserialize(&mut pool_account_data.data.as_slice()).unwrap();
+    assert_eq!(pool_state.fee_rate, 5);
+    assert_eq!(pool_state.tick_spacing, 10);
+    assert_eq!(pool_state.factory, find_factory_config_pda());
+}
+
+#[tokio::test]
+async fn test_initialize_factory_config_needs_upgrade_authority() {
+    let program_test = ProgramTest::new("amm_core", PROGRAM_ID, None);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+
+    // Someone other than the upgrade authority cannot claim the singleton config
+    let program_data = set_upgrade_authority(&mut context, &payer.pubkey());
+    let intruder = Keypair::new();
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::transfer(&payer.pubkey(), &intruder.pubkey(), 1_000_000_000),
+            build_initialize_factory_config_ix(&intruder.pubkey(), program_data),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &intruder],
+        context.last_blockhash,
+    );
+    let result = context.banks_client.process_transaction(transaction).await;
+    match result.unwrap_err() {
+        BanksClientError::TransactionError(
+            solana_sdk::transaction::TransactionError::InstructionError(
+                _,
+                solana_sdk::instruction::InstructionError::Custom(code),
+            ),
+        ) => {
+            assert_eq!(code, ErrorCode::UnauthorizedAccess as u32);
+        }
+        err => panic!("Expected UnauthorizedAccess error for another signer, got {err:?}"),
+    }
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[build_initialize_factory_config_ix(
+            &payer.pubkey(),
+            program_data,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    assert!(context
+        .banks_client
+        .get_account(find_factory_config_pda())
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+async fn test_pool_creator_does_not_administer_pool() {
+    let program_test = ProgramTest::new("amm_core", PROGRAM_ID, None);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let creator = Keypair::new();
+
+    let (_, mut mint_a_pubkey) = create_mint(&mut context, &payer.pubkey()).await.unwrap();
+    let (_, mut mint_b_pubkey) = create_mint(&mut context, &payer.pubkey()).await.unwrap();
+    if mint_a_pubkey > mint_b_pubkey {
+        std::mem::swap(&mut mint_a_pubkey, &mut mint_b_pubkey);
+    }
+
+    // The payer governs the factory config; someone else creates the pool
+    let mut instructions = build_factory_config_ixs(&mut context, &payer.pubkey(), &[(30, 60)]);
+    instructions.push(system_instruction::transfer(
+        &payer.pubkey(),
+        &creator.pubkey(),
+        1_000_000_000,
+    ));
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[&payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let (pool_pda, _) = find_pool_pda(&mint_a_pubkey, &mint_b_pubkey, 30);
+    let (instruction, vault_a, vault_b) = build_initialize_pool_ix(
+        &creator.pubkey(),
+        pool_pda,
+        mint_a_pubkey,
+        mint_b_pubkey,
+        30,
+        60,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            build_create_token_pair_ix(
+                &creator.pubkey(),
+                mint_a_pubkey,
+                mint_b_pubkey,
+                Pubkey::default(),
+            ),
+            instruction,
+        ],
+        Some(&creator.pubkey()),
+        &[&creator, &vault_a, &vault_b],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let pool_account_data = context
+        .banks_client
+        .get_account(pool_pda)
+        .await
+        .unwrap()
+        .expect("Pool account not found");
+    let pool_state = Pool::try_deserialize(&mut pool_account_data.data.as_slice()).unwrap();
+    assert_eq!(pool_state.authority, payer.pubkey());
+    assert_eq!(pool_state.oracle_authority, payer.pubkey());
+    assert_eq!(pool_state.protocol_fee, 0);
+
+    // The creator cannot take the swap fees for the protocol
+    let transaction = Transaction::new_signed_with_payer(
+        &[build_set_protocol_fee_ix(
+            pool_pda,
+            &creator.pubkey(),
+            10_000,
+        )],
+        Some(&creator.pubkey()),
+        &[&creator],
+        context.last_blockhash,
+    );
+    let result = context.banks_client.process_transaction(transaction).await;
+    match result.unwrap_err() {
+        BanksClientError::TransactionError(
+            solana_sdk::transaction::TransactionError::InstructionError(
+                _,
+                solana_sdk::instruction::InstructionError::Custom(code),
+            ),
+        ) => {
+            assert_eq!(code, ErrorCode::UnauthorizedAccess as u32);
+        }
+        err => panic!("Expected UnauthorizedAccess error for the pool creator, got {err:?}"),
+    }
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[build_set_protocol_fee_ix(pool_pda, &payer.pubkey(), 500)],
+        Some(&payer.pubkey()),
+        &[&payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    let pool_account_data = context
+        .banks_client
+        .get_account(pool_pda)
+        .await
+        .unwrap()
+        .expect("Pool account not found");
+    let pool_state = Pool::try_deserialize(&mut pool_account_data.data.as_slice()).unwrap();
+    assert_eq!(pool_state.protocol_fee, 500);
+}
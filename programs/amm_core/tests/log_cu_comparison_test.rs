@@ -0,0 +1,280 @@
+// /tests/log_cu_comparison_test.rs
+//
+// Compares the compute units consumed by a swap between the default (mainnet) build of
+// amm_core and a build with the `log_debug` feature enabled.
+//
+// The default program is loaded as `amm_core.so`, like the other integration tests.
+// The debug build has to be produced separately and pointed to with `AMM_CORE_LOG_DEBUG_SO`:
+//
+//   cargo build-sbf --manifest-path programs/amm_core/Cargo.toml --features log_debug
+//   cp target/deploy/amm_core.so target/deploy/amm_core_log_debug.so
+//   cargo build-sbf --manifest-path programs/amm_core/Cargo.toml
+//   AMM_CORE_LOG_DEBUG_SO=target/deploy/amm_core_log_debug.so cargo test --test log_cu_comparison_test
+//
+// The test is skipped when the variable is not set.
+
+mod common;
+
+use anchor_lang::{prelude::Pubkey, InstructionData};
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    account::Account,
+    bpf_loader,
+    instruction::{AccountMeta, Instruction},
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::Transaction,
+};
+
+use amm_core::{
+    instruction::{
+        CreateTokenPairHandler as CreateTokenPairData, InitializePoolHandler as InitializePoolData,
+        MintPositionHandler as MintPositionData, SwapExactInputHandler as SwapExactInputData,
+    },
+    ID as PROGRAM_ID,
+};
+use common::*;
+
+const TICK_LOWER: i32 = -600;
+const TICK_UPPER: i32 = 600;
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000;
+const SWAP_AMOUNT_IN: u64 = 10_000;
+/// Result of simulating the swap instruction against one build of the program.
+struct SwapRun {
+    units_consumed: u64,
+    logs: Vec<String>,
+}
+
+/// Sets up a pool with one in-range position and simulates a token0 -> token1 swap.
+async fn simulate_swap(program_test: ProgramTest) -> SwapRun {
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+
+    let mut mint_a = create_mint(&mut context).await;
+    let mut mint_b = create_mint(&mut context).await;
+    if mint_a > mint_b {
+        std::mem::swap(&mut mint_a, &mut mint_b);
+    }
+
+    // 1. Create the pair's record and initialize the pool at price 1.0
+    let fee_rate: u16 = 30;
+    let (pool, _) = Pubkey::find_program_address(
+        &[
+            b"pool".as_ref(),
+            mint_a.as_ref(),
+            mint_b.as_ref(),
+            fee_rate.to_le_bytes().as_ref(),
+        ],
+        &PROGRAM_ID,
+    );
+    let (pool_registry, _) = Pubkey::find_program_address(
+        &[b"pool_registry".as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        &PROGRAM_ID,
+    );
+    let (token_pair, _) = Pubkey::find_program_address(
+        &[b"token_pair".as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        &PROGRAM_ID,
+    );
+    let create_pair_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(token_pair, false),
+            AccountMeta::new_readonly(mint_a, false),
+            AccountMeta::new_readonly(mint_b, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+        ],
+        data: CreateTokenPairData {
+            oracle_feed: Pubkey::default(),
+        }
+        .data(),
+    };
+    let vault_a = Keypair::new();
+    let vault_b = Keypair::new();
+    let init_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(pool, false),
+            AccountMeta::new_readonly(mint_a, false),
+            AccountMeta::new_readonly(mint_b, false),
+            AccountMeta::new_readonly(Keypair::new().pubkey(), false),
+            AccountMeta::new(vault_a.pubkey(), true),
+            AccountMeta::new(vault_b.pubkey(), true),
+            AccountMeta::new(pool_registry, false),
+            AccountMeta::new(token_pair, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+        ],
+        data: InitializePoolData {
+            initial_sqrt_price_q64: 1u128 << 64,
+            fee_rate,
+            tick_spacing: 60,
+            immutable_parameters: false,
+        }
+        .data(),
+    };
+    process(
+        &mut context,
+        &[create_pair_ix, init_ix],
+        &[&payer, &vault_a, &vault_b],
+    )
+    .await;
+
+    // 2. Mint a position around the current price
+    let (position, _) = Pubkey::find_program_address(
+        &[
+            b"position".as_ref(),
+            pool.as_ref(),
+            payer.pubkey().as_ref(),
+            TICK_LOWER.to_le_bytes().as_ref(),
+            TICK_UPPER.to_le_bytes().as_ref(),
+            0u64.to_le_bytes().as_ref(),
+        ],
+        &PROGRAM_ID,
+    );
+    let (position_counter, _) = Pubkey::find_program_address(
+        &[
+            b"position_counter".as_ref(),
+            pool.as_ref(),
+            payer.pubkey().as_ref(),
+        ],
+        &PROGRAM_ID,
+    );
+    let tick_pda = |index: i32| {
+        Pubkey::find_program_address(
+            &[
+                b"tick".as_ref(),
+                pool.as_ref(),
+                index.to_le_bytes().as_ref(),
+            ],
+            &PROGRAM_ID,
+        )
+        .0
+    };
+    // The owner deposits both tokens for the position, and swaps from the same accounts.
+    let user_in = create_token_account(&mut context, &mint_a, &payer.pubkey()).await;
+    let user_out = create_token_account(&mut context, &mint_b, &payer.pubkey()).await;
+    mint_to(&mut context, &mint_a, &user_in, OWNER_FUNDING).await;
+    mint_to(&mut context, &mint_b, &user_out, OWNER_FUNDING).await;
+    let mint_position_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(pool, false),
+            AccountMeta::new(position, false),
+            AccountMeta::new(tick_pda(TICK_LOWER), false),
+            AccountMeta::new(tick_pda(TICK_UPPER), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+            AccountMeta::new(position_counter, false),
+            AccountMeta::new(vault_a.pubkey(), false),
+            AccountMeta::new(vault_b.pubkey(), false),
+            AccountMeta::new(user_in, false),
+            AccountMeta::new(user_out, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+        ],
+        data: MintPositionData {
+            tick_lower_index: TICK_LOWER,
+            tick_upper_index: TICK_UPPER,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            position_index: 0,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    };
+    process(&mut context, &[mint_position_ix], &[&payer]).await;
+
+    // 3. Simulate the swap. It stays inside the position, so no tick accounts are needed.
+    let swap_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(pool, false),
+            AccountMeta::new(vault_a.pubkey(), false),
+            AccountMeta::new(vault_b.pubkey(), false),
+            AccountMeta::new(user_in, false),
+            AccountMeta::new(user_out, false),
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new_readonly(spl_token::ID, false),
+        ],
+        data: SwapExactInputData {
+            amount_in: SWAP_AMOUNT_IN,
+            amount_out_minimum: 1,
+            sqrt_price_limit_q64: 1,
+        }
+        .data(),
+    };
+    let transaction = Transaction::new_signed_with_payer(
+        &[swap_ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        context.last_blockhash,
+    );
+    let simulation = context
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    assert!(
+        matches!(simulation.result, Some(Ok(()))),
+        "swap simulation failed: {:?}",
+        simulation.result
+    );
+    let details = simulation
+        .simulation_details
+        .expect("simulation details missing");
+
+    SwapRun {
+        units_consumed: details.units_consumed,
+        logs: details.logs,
+    }
+}
+
+#[tokio::test]
+async fn test_swap_cu_mainnet_profile_below_log_debug_profile() {
+    let Ok(debug_so_path) = std::env::var("AMM_CORE_LOG_DEBUG_SO") else {
+        println!("AMM_CORE_LOG_DEBUG_SO not set; skipping CU comparison.");
+        return;
+    };
+    let debug_so = std::fs::read(&debug_so_path).expect("failed to read log_debug program");
+
+    let mainnet_run = simulate_swap(ProgramTest::new("amm_core", PROGRAM_ID, None)).await;
+
+    let mut debug_program_test = ProgramTest::default();
+    debug_program_test.add_account(
+        PROGRAM_ID,
+        Account {
+            lamports: 1_000_000_000,
+            data: debug_so,
+            owner: bpf_loader::id(),
+            executable: true,
+            rent_epoch: 0,
+        },
+    );
+    let debug_run = simulate_swap(debug_program_test).await;
+
+    println!(
+        "swap CU: mainnet profile = {}, log_debug profile = {}",
+        mainnet_run.units_consumed, debug_run.units_consumed
+    );
+
+    // The mainnet profile emits no flog! lines, the debug profile emits the swap event.
+    assert!(!mainnet_run
+        .logs
+        .iter()
+        .any(|line| line.contains("level=info") || line.contains("level=debug")));
+    assert!(debug_run
+        .logs
+        .iter()
+        .any(|line| line.contains("level=info event=swap ")));
+
+    assert!(
+        mainnet_run.units_consumed < debug_run.units_consumed,
+        "expected the mainnet profile to use fewer CU ({} >= {})",
+        mainnet_run.units_consumed,
+        debug_run.units_consumed
+    );
+}
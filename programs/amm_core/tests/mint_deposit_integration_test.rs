@@ -0,0 +1,459 @@
+// /tests/mint_deposit_integration_test.rs
+//
+// Checks that minting a position moves the tokens its liquidity is worth from the owner to
+// the pool vaults, that decreasing it moves them back, that the vaults always hold what was
+// deposited minus what was withdrawn, and that minting from token amounts never deposits
+// more than offered.
+//
+// The program is loaded as `amm_core.so`, like the other integration tests.
+
+mod common;
+
+use anchor_lang::{prelude::Pubkey, AccountDeserialize, InstructionData, ToAccountMetas};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    signature::Signer,
+    sysvar,
+    transaction::TransactionError,
+};
+
+use amm_core::state::pool::Pool;
+use amm_core::{
+    errors::ErrorCode,
+    math,
+    position::{DecreaseLiquidityEvent, MintPositionEvent, PositionData},
+    ID as PROGRAM_ID,
+};
+use common::*;
+
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000;
+/// A range above the starting price, one below it and one around it.
+const ABOVE: (i32, i32) = (60, 600);
+const BELOW: (i32, i32) = (-600, -60);
+const AROUND: (i32, i32) = (-600, 600);
+
+/// The balances of the two vaults and the payer's two token accounts.
+async fn balances(context: &mut ProgramTestContext, setup: &Setup) -> [u64; 4] {
+    [
+        token_balance(context, setup.vault_a).await,
+        token_balance(context, setup.vault_b).await,
+        token_balance(context, setup.owner_a).await,
+        token_balance(context, setup.owner_b).await,
+    ]
+}
+
+/// Reads the liquidity of the pool and of one of its positions.
+async fn liquidity(
+    context: &mut ProgramTestContext,
+    pool: Pubkey,
+    position: Pubkey,
+) -> (u128, u128) {
+    let pool = context
+        .banks_client
+        .get_account(pool)
+        .await
+        .unwrap()
+        .unwrap();
+    let position = context
+        .banks_client
+        .get_account(position)
+        .await
+        .unwrap()
+        .unwrap();
+    (
+        Pool::try_deserialize(&mut pool.data.as_slice())
+            .unwrap()
+            .liquidity,
+        PositionData::try_deserialize(&mut position.data.as_slice())
+            .unwrap()
+            .liquidity,
+    )
+}
+
+/// Mints `range` for `owner` as their position `position_index`, depositing at most the
+/// given amounts from the setup's token accounts.
+fn mint_position_ix(
+    setup: &Setup,
+    owner: &Pubkey,
+    range: (i32, i32),
+    position_index: u64,
+    amount_max: (u64, u64),
+) -> Instruction {
+    let pool = &setup.pool;
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::MintPosition {
+            pool: *pool,
+            position: position_pda(pool, owner, range, position_index),
+            tick_lower: tick_pda(pool, range.0),
+            tick_upper: tick_pda(pool, range.1),
+            owner: *owner,
+            payer: *owner,
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            position_counter: position_counter_pda(pool, owner),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: setup.owner_a,
+            owner_token1_account: setup.owner_b,
+            token_program: spl_token::ID,
+            owner_index: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionHandler {
+            tick_lower_index: range.0,
+            tick_upper_index: range.1,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            position_index,
+            amount0_max: amount_max.0,
+            amount1_max: amount_max.1,
+        }
+        .data(),
+    }
+}
+
+/// Mints `range` for `owner` as their position `position_index` from the desired token
+/// amounts, requiring at least the minimum amounts to be deposited.
+fn mint_position_with_amounts_ix(
+    setup: &Setup,
+    owner: &Pubkey,
+    range: (i32, i32),
+    position_index: u64,
+    amount_desired: (u64, u64),
+    amount_min: (u64, u64),
+) -> Instruction {
+    let pool = &setup.pool;
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::MintPositionWithAmounts {
+            pool: *pool,
+            position: position_pda(pool, owner, range, position_index),
+            tick_lower: tick_pda(pool, range.0),
+            tick_upper: tick_pda(pool, range.1),
+            owner: *owner,
+            payer: *owner,
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            position_counter: position_counter_pda(pool, owner),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: setup.owner_a,
+            owner_token1_account: setup.owner_b,
+            token_program: spl_token::ID,
+            owner_index: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionWithAmountsHandler {
+            tick_lower_index: range.0,
+            tick_upper_index: range.1,
+            amount0_desired: amount_desired.0,
+            amount1_desired: amount_desired.1,
+            amount0_min: amount_min.0,
+            amount1_min: amount_min.1,
+        }
+        .data(),
+    }
+}
+
+/// Removes `liquidity_delta` from the owner's position, paying out to the setup's token
+/// accounts.
+fn decrease_liquidity_ix(
+    setup: &Setup,
+    owner: &Pubkey,
+    range: (i32, i32),
+    position_index: u64,
+    liquidity_delta: u128,
+) -> Instruction {
+    let pool = &setup.pool;
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::DecreaseLiquidity {
+            pool: *pool,
+            position: position_pda(pool, owner, range, position_index),
+            tick_lower: tick_pda(pool, range.0),
+            tick_upper: tick_pda(pool, range.1),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: setup.owner_a,
+            owner_token1_account: setup.owner_b,
+            owner: *owner,
+            token_program: spl_token::ID,
+            position_token_account: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::DecreaseLiquidityHandler { liquidity_delta }.data(),
+    }
+}
+
+#[tokio::test]
+async fn test_mint_deposits_the_tokens_of_its_range() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+    let owner = payer.pubkey();
+
+    for (position_index, range) in [ABOVE, BELOW, AROUND].into_iter().enumerate() {
+        let before = balances(&mut context, &setup).await;
+        let ix = mint_position_ix(
+            &setup,
+            &owner,
+            range,
+            position_index as u64,
+            (u64::MAX, u64::MAX),
+        );
+        let logs = process(&mut context, &[ix], &[&payer]).await;
+        let minted = events::<MintPositionEvent>(&logs);
+        assert_eq!(minted.len(), 1);
+        let (amount0, amount1) = (minted[0].amount0, minted[0].amount1);
+
+        // The event's amounts moved from the owner to the vaults
+        let after = balances(&mut context, &setup).await;
+        assert_eq!(after[0], before[0] + amount0);
+        assert_eq!(after[1], before[1] + amount1);
+        assert_eq!(after[2], before[2] - amount0);
+        assert_eq!(after[3], before[3] - amount1);
+
+        match range {
+            ABOVE => assert!(amount0 > 0 && amount1 == 0),
+            BELOW => assert!(amount0 == 0 && amount1 > 0),
+            _ => assert!(amount0 > 0 && amount1 > 0),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_mint_rejects_deposits_above_the_maximums() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+    let owner = payer.pubkey();
+
+    let ix = mint_position_ix(&setup, &owner, AROUND, 0, (u64::MAX, u64::MAX));
+    let logs = process(&mut context, &[ix], &[&payer]).await;
+    let minted = &events::<MintPositionEvent>(&logs)[0];
+
+    // The same liquidity again needs the same amounts, so one unit less of either fails
+    for amount_max in [
+        (minted.amount0 - 1, minted.amount1),
+        (minted.amount0, minted.amount1 - 1),
+    ] {
+        let ix = mint_position_ix(&setup, &owner, AROUND, 1, amount_max);
+        assert_eq!(
+            try_process(&mut context, &[ix], &[&payer])
+                .await
+                .unwrap_err(),
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(ErrorCode::SlippageExceeded as u32 + 6000)
+            )
+        );
+    }
+    let ix = mint_position_ix(&setup, &owner, AROUND, 1, (minted.amount0, minted.amount1));
+    process(&mut context, &[ix], &[&payer]).await;
+}
+
+#[tokio::test]
+async fn test_decrease_liquidity_pays_the_owner_from_the_vaults() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+    let owner = payer.pubkey();
+    let position = position_pda(&setup.pool, &owner, AROUND, 0);
+
+    let ix = mint_position_ix(&setup, &owner, AROUND, 0, (u64::MAX, u64::MAX));
+    process(&mut context, &[ix], &[&payer]).await;
+
+    // A quarter of the liquidity is worth its share of both tokens at the starting price,
+    // rounded down
+    let liquidity_delta = POSITION_LIQUIDITY / 4;
+    let (amount0, amount1) = math::get_amounts_for_liquidity(
+        1u128 << 64,
+        math::tick_to_sqrt_price_q64(AROUND.0).unwrap(),
+        math::tick_to_sqrt_price_q64(AROUND.1).unwrap(),
+        liquidity_delta,
+        false,
+    )
+    .unwrap();
+    let (amount0, amount1) = (amount0 as u64, amount1 as u64);
+    assert!(amount0 > 0 && amount1 > 0);
+
+    let before = balances(&mut context, &setup).await;
+    let ix = decrease_liquidity_ix(&setup, &owner, AROUND, 0, liquidity_delta);
+    let logs = process(&mut context, &[ix], &[&payer]).await;
+    let decreased = &events::<DecreaseLiquidityEvent>(&logs)[0];
+    assert_eq!((decreased.amount0, decreased.amount1), (amount0, amount1));
+
+    // Exactly those amounts moved from the vaults to the owner
+    let after = balances(&mut context, &setup).await;
+    assert_eq!(after[0], before[0] - amount0);
+    assert_eq!(after[1], before[1] - amount1);
+    assert_eq!(after[2], before[2] + amount0);
+    assert_eq!(after[3], before[3] + amount1);
+    let remaining = POSITION_LIQUIDITY - liquidity_delta;
+    assert_eq!(
+        liquidity(&mut context, setup.pool, position).await,
+        (remaining, remaining)
+    );
+
+    // More than the position has left is rejected and moves nothing
+    let ix = decrease_liquidity_ix(&setup, &owner, AROUND, 0, remaining + 1);
+    assert_eq!(
+        try_process(&mut context, &[ix], &[&payer])
+            .await
+            .unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(ErrorCode::PositionLiquidityTooLow as u32 + 6000)
+        )
+    );
+    assert_eq!(balances(&mut context, &setup).await, after);
+
+    // The rest empties the position and the pool's active liquidity
+    let ix = decrease_liquidity_ix(&setup, &owner, AROUND, 0, remaining);
+    let logs = process(&mut context, &[ix], &[&payer]).await;
+    let decreased = &events::<DecreaseLiquidityEvent>(&logs)[0];
+    let last = balances(&mut context, &setup).await;
+    assert_eq!(last[0], after[0] - decreased.amount0);
+    assert_eq!(last[1], after[1] - decreased.amount1);
+    assert_eq!(last[2], after[2] + decreased.amount0);
+    assert_eq!(last[3], after[3] + decreased.amount1);
+    assert_eq!(liquidity(&mut context, setup.pool, position).await, (0, 0));
+}
+
+#[tokio::test]
+async fn test_vault_balances_equal_deposits_minus_withdrawals() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+    let owner = payer.pubkey();
+    let ranges = [ABOVE, BELOW, AROUND];
+
+    let (mut deposited0, mut deposited1) = (0u64, 0u64);
+    for (position_index, range) in ranges.into_iter().enumerate() {
+        let ix = mint_position_ix(
+            &setup,
+            &owner,
+            range,
+            position_index as u64,
+            (u64::MAX, u64::MAX),
+        );
+        let logs = process(&mut context, &[ix], &[&payer]).await;
+        let minted = &events::<MintPositionEvent>(&logs)[0];
+        deposited0 += minted.amount0;
+        deposited1 += minted.amount1;
+    }
+    let vaults = balances(&mut context, &setup).await;
+    assert_eq!((vaults[0], vaults[1]), (deposited0, deposited1));
+
+    // Withdraw every position in two halves, checking the vaults after each withdrawal
+    let (mut withdrawn0, mut withdrawn1) = (0u64, 0u64);
+    for (position_index, range) in ranges.into_iter().enumerate() {
+        for _ in 0..2 {
+            let ix = decrease_liquidity_ix(
+                &setup,
+                &owner,
+                range,
+                position_index as u64,
+                POSITION_LIQUIDITY / 2,
+            );
+            let logs = process(&mut context, &[ix], &[&payer]).await;
+            let decreased = &events::<DecreaseLiquidityEvent>(&logs)[0];
+            withdrawn0 += decreased.amount0;
+            withdrawn1 += decreased.amount1;
+
+            let vaults = balances(&mut context, &setup).await;
+            assert_eq!(vaults[0], deposited0 - withdrawn0);
+            assert_eq!(vaults[1], deposited1 - withdrawn1);
+        }
+    }
+
+    // Deposits round up and withdrawals round down, so only dust is left behind
+    let [vault_a, vault_b, owner_a, owner_b] = balances(&mut context, &setup).await;
+    assert!(vault_a <= 6 && vault_b <= 6);
+    assert_eq!(owner_a, OWNER_FUNDING - vault_a);
+    assert_eq!(owner_b, OWNER_FUNDING - vault_b);
+}
+
+#[tokio::test]
+async fn test_mint_with_amounts_deposits_at_most_the_desired_amounts() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+    let owner = payer.pubkey();
+    let desired = (1_000_000, 400_000);
+
+    for (position_index, range) in [ABOVE, BELOW, AROUND].into_iter().enumerate() {
+        let before = balances(&mut context, &setup).await;
+        let ix = mint_position_with_amounts_ix(
+            &setup,
+            &owner,
+            range,
+            position_index as u64,
+            desired,
+            (0, 0),
+        );
+        let logs = process(&mut context, &[ix], &[&payer]).await;
+        let minted = &events::<MintPositionEvent>(&logs)[0];
+        assert!(minted.liquidity > 0);
+        assert!(minted.amount0 <= desired.0 && minted.amount1 <= desired.1);
+
+        // The event reports what actually moved to the vaults
+        let after = balances(&mut context, &setup).await;
+        assert_eq!(after[0], before[0] + minted.amount0);
+        assert_eq!(after[1], before[1] + minted.amount1);
+
+        match range {
+            ABOVE => assert!(minted.amount0 > 0 && minted.amount1 == 0),
+            BELOW => assert!(minted.amount0 == 0 && minted.amount1 > 0),
+            // Around the price token1 is the scarcer one and is used up
+            _ => assert!(minted.amount0 < desired.0 && minted.amount1 + 1 >= desired.1),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_mint_with_amounts_rejects_deposits_below_the_minimums() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+    let owner = payer.pubkey();
+    let desired = (1_000_000, 400_000);
+
+    // Only part of the token0 is used around the price, so asking for all of it fails
+    let ix = mint_position_with_amounts_ix(&setup, &owner, AROUND, 0, desired, (desired.0, 0));
+    assert_eq!(
+        try_process(&mut context, &[ix], &[&payer])
+            .await
+            .unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(ErrorCode::SlippageExceeded as u32 + 6000)
+        )
+    );
+
+    let ix = mint_position_with_amounts_ix(&setup, &owner, AROUND, 0, desired, (0, 0));
+    let logs = process(&mut context, &[ix], &[&payer]).await;
+    let minted = &events::<MintPositionEvent>(&logs)[0];
+    let ix = mint_position_with_amounts_ix(
+        &setup,
+        &owner,
+        AROUND,
+        1,
+        desired,
+        (minted.amount0, minted.amount1),
+    );
+    process(&mut context, &[ix], &[&payer]).await;
+}
@@ -0,0 +1,271 @@
+// /tests/multi_hop_integration_test.rs
+//
+// Checks that `swap_multi_hop` routes A -> B -> C through two pools in one transaction when
+// the intermediate token has more decimals than the tokens at either end: the output of the
+// first hop is fed to the second in raw units, without any rescaling, and a route that
+// misses its minimum output is rolled back as a whole.
+//
+// The program is loaded as `amm_core.so`, like the other integration tests.
+
+mod common;
+
+use anchor_lang::{prelude::Pubkey, InstructionData, ToAccountMetas};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    signature::Signer,
+    sysvar,
+    transaction::TransactionError,
+};
+
+use amm_core::{
+    constants::{MAX_SQRT_PRICE, MIN_SQRT_PRICE},
+    errors::ErrorCode,
+    instructions::swap_multi_hop::HopParams,
+    math,
+    state::pool::SwapEvent,
+    ID as PROGRAM_ID,
+};
+use common::*;
+
+/// Decimals of the tokens at either end of the route, and of the token between them.
+const OUTER_DECIMALS: u8 = 6;
+const INTERMEDIATE_DECIMALS: u8 = 9;
+/// The usable tick closest to a raw price of 1000, where one whole 6-decimal token is worth
+/// one whole 9-decimal token.
+const DECIMALS_TICK: i32 = 69_060;
+/// Half the width of each pool's position, around the pool's price.
+const POSITION_HALF_WIDTH: i32 = 600;
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000_000;
+const OWNER_FUNDING: u64 = 1_000_000_000_000_000_000;
+/// One whole token A.
+const AMOUNT_IN: u64 = 1_000_000;
+
+/// A token the test holds: its mint, decimals and the payer's funded account.
+#[derive(Clone, Copy)]
+struct Token {
+    mint: Pubkey,
+    decimals: u8,
+    account: Pubkey,
+}
+
+async fn create_token(context: &mut ProgramTestContext, decimals: u8) -> Token {
+    let mint = create_mint_with_decimals(context, decimals).await;
+    let account = create_funded_account(context, &mint, OWNER_FUNDING).await;
+    Token {
+        mint,
+        decimals,
+        account,
+    }
+}
+
+/// A pool and its vaults.
+struct PoolSetup {
+    pool: Pubkey,
+    token0_mint: Pubkey,
+    vault0: Pubkey,
+    vault1: Pubkey,
+}
+
+impl PoolSetup {
+    /// The hop swapping `input` through this pool, with no price limit in its direction.
+    fn hop(&self, input: &Token) -> HopParams {
+        let zero_for_one = input.mint == self.token0_mint;
+        HopParams {
+            pool: self.pool,
+            zero_for_one,
+            tick_accounts: 0,
+            sqrt_price_limit_q64: if zero_for_one {
+                MIN_SQRT_PRICE
+            } else {
+                MAX_SQRT_PRICE
+            },
+        }
+    }
+
+    /// The accounts passed for this pool's hop: the pool and its vaults.
+    fn hop_accounts(&self) -> [AccountMeta; 3] {
+        [
+            AccountMeta::new(self.pool, false),
+            AccountMeta::new(self.vault0, false),
+            AccountMeta::new(self.vault1, false),
+        ]
+    }
+}
+
+/// Creates a pool of `x` and `y` where one whole token of either is worth one whole token of
+/// the other, and mints a position around that price from the payer's accounts.
+async fn setup_pool(context: &mut ProgramTestContext, x: Token, y: Token) -> PoolSetup {
+    let payer = context.payer.insecure_clone();
+    let (token0, token1) = if x.mint < y.mint { (x, y) } else { (y, x) };
+
+    // The raw price is in base units of token1 per base unit of token0, so the decimals
+    // place it above or below 1.0
+    let price_tick = match token1.decimals.cmp(&token0.decimals) {
+        std::cmp::Ordering::Greater => DECIMALS_TICK,
+        std::cmp::Ordering::Less => -DECIMALS_TICK,
+        std::cmp::Ordering::Equal => 0,
+    };
+    let (tick_lower, tick_upper) = (
+        price_tick - POSITION_HALF_WIDTH,
+        price_tick + POSITION_HALF_WIDTH,
+    );
+
+    // 1. Create the pair's record and initialize the pool at that price
+    let accounts = initialize_pool(
+        context,
+        token0.mint,
+        token1.mint,
+        math::tick_to_sqrt_price_q64(price_tick).unwrap(),
+    )
+    .await;
+    let (pool, vault0, vault1) = (accounts.pool, accounts.vault_a, accounts.vault_b);
+
+    // 2. Mint a position around the price, deep enough that the route barely moves it
+    let mint_position_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::MintPosition {
+            pool,
+            position: position_pda(&pool, &payer.pubkey(), (tick_lower, tick_upper), 0),
+            tick_lower: tick_pda(&pool, tick_lower),
+            tick_upper: tick_pda(&pool, tick_upper),
+            owner: payer.pubkey(),
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            position_counter: position_counter_pda(&pool, &payer.pubkey()),
+            token0_vault: vault0,
+            token1_vault: vault1,
+            owner_token0_account: token0.account,
+            owner_token1_account: token1.account,
+            token_program: spl_token::ID,
+            owner_index: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionHandler {
+            tick_lower_index: tick_lower,
+            tick_upper_index: tick_upper,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            position_index: 0,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    };
+    process(context, &[mint_position_ix], &[&payer]).await;
+
+    PoolSetup {
+        pool,
+        token0_mint: token0.mint,
+        vault0,
+        vault1,
+    }
+}
+
+fn multi_hop_ix(
+    payer: &Pubkey,
+    token_in: &Token,
+    token_out: &Token,
+    route: &[(&PoolSetup, &Token)],
+    amount_out_minimum: u64,
+) -> Instruction {
+    let mut accounts = amm_core::accounts::SwapMultiHop {
+        user_token_in_account: token_in.account,
+        user_token_out_account: token_out.account,
+        user_authority: *payer,
+        token_program: spl_token::ID,
+    }
+    .to_account_metas(None);
+    accounts.extend(route.iter().flat_map(|(pool, _)| pool.hop_accounts()));
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts,
+        data: amm_core::instruction::SwapMultiHopHandler {
+            amount_in: AMOUNT_IN,
+            amount_out_minimum,
+            hops: route
+                .iter()
+                .map(|(pool, hop_input)| pool.hop(hop_input))
+                .collect(),
+        }
+        .data(),
+    }
+}
+
+#[tokio::test]
+async fn test_route_through_token_with_more_decimals() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+
+    // 1. A and C have 6 decimals, and B between them has 9
+    let token_a = create_token(&mut context, OUTER_DECIMALS).await;
+    let token_b = create_token(&mut context, INTERMEDIATE_DECIMALS).await;
+    let token_c = create_token(&mut context, OUTER_DECIMALS).await;
+    let pool_ab = setup_pool(&mut context, token_a, token_b).await;
+    let pool_bc = setup_pool(&mut context, token_b, token_c).await;
+    let balances_before = [
+        token_balance(&mut context, token_a.account).await,
+        token_balance(&mut context, token_b.account).await,
+        token_balance(&mut context, token_c.account).await,
+    ];
+
+    // 2. Swap one whole A for C through both pools
+    let route = [(&pool_ab, &token_a), (&pool_bc, &token_b)];
+    let swap_ix = multi_hop_ix(&payer.pubkey(), &token_a, &token_c, &route, 1);
+    let logs = process(&mut context, &[swap_ix], &[&payer]).await;
+    let swaps = events::<SwapEvent>(&logs);
+    assert_eq!(swaps.len(), 2);
+    let (first_hop, second_hop) = (&swaps[0], &swaps[1]);
+    assert_eq!(
+        (first_hop.pool, second_hop.pool),
+        (pool_ab.pool, pool_bc.pool)
+    );
+    assert_eq!(first_hop.amount_in, AMOUNT_IN);
+
+    // One whole A buys about one whole B, a thousand times as many base units, less the fee
+    let intermediate_amount = first_hop.amount_out;
+    assert!(
+        intermediate_amount > AMOUNT_IN * 990 && intermediate_amount < AMOUNT_IN * 1_000,
+        "{intermediate_amount}"
+    );
+    // and all of it is swapped for C, which has the same decimals as A
+    assert_eq!(second_hop.amount_in, intermediate_amount);
+    let amount_out = second_hop.amount_out;
+    assert!(
+        amount_out > AMOUNT_IN * 99 / 100 && amount_out < AMOUNT_IN,
+        "{amount_out}"
+    );
+
+    // The user pays A and receives C, and B never leaves the pools
+    let balances_after = [
+        token_balance(&mut context, token_a.account).await,
+        token_balance(&mut context, token_b.account).await,
+        token_balance(&mut context, token_c.account).await,
+    ];
+    assert_eq!(
+        balances_after,
+        [
+            balances_before[0] - AMOUNT_IN,
+            balances_before[1],
+            balances_before[2] + amount_out,
+        ]
+    );
+
+    // 3. A route that cannot deliver its minimum fails as a whole
+    let swap_ix = multi_hop_ix(&payer.pubkey(), &token_a, &token_c, &route, amount_out * 2);
+    let err = try_process(&mut context, &[swap_ix], &[&payer])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(ErrorCode::SlippageExceeded as u32 + 6000),
+        )
+    );
+    for (account, balance) in [token_a, token_b, token_c].iter().zip(balances_after) {
+        assert_eq!(token_balance(&mut context, account.account).await, balance);
+    }
+}
@@ -0,0 +1,204 @@
+// /tests/multiple_positions_integration_test.rs
+//
+// Opens two positions for the same owner over the same range and checks that they are
+// separate accounts whose liquidity and fees are managed independently.
+//
+// The program is loaded as `amm_core.so`, like the other integration tests.
+
+mod common;
+
+use anchor_lang::{prelude::Pubkey, AccountDeserialize, InstructionData, ToAccountMetas};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    signature::Signer,
+    sysvar,
+    transaction::TransactionError,
+};
+
+use amm_core::{
+    errors::ErrorCode,
+    position::{CollectFeesEvent, PositionCounter, PositionData},
+    ID as PROGRAM_ID,
+};
+use common::*;
+
+const TICK_LOWER: i32 = -600;
+const TICK_UPPER: i32 = 600;
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000;
+const SWAP_AMOUNT_IN: u64 = 10_000;
+/// Mints `[TICK_LOWER, TICK_UPPER)` for `owner`, as their position `position_index`,
+/// depositing from the setup's token accounts.
+fn mint_position_ix(setup: &Setup, owner: &Pubkey, position_index: u64) -> Instruction {
+    let pool = &setup.pool;
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::MintPosition {
+            pool: *pool,
+            position: position_pda(pool, owner, (TICK_LOWER, TICK_UPPER), position_index),
+            tick_lower: tick_pda(pool, TICK_LOWER),
+            tick_upper: tick_pda(pool, TICK_UPPER),
+            owner: *owner,
+            payer: *owner,
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            position_counter: position_counter_pda(pool, owner),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: setup.owner_a,
+            owner_token1_account: setup.owner_b,
+            token_program: spl_token::ID,
+            owner_index: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionHandler {
+            tick_lower_index: TICK_LOWER,
+            tick_upper_index: TICK_UPPER,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            position_index,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    }
+}
+
+async fn position_data(context: &mut ProgramTestContext, position: Pubkey) -> PositionData {
+    let account = context
+        .banks_client
+        .get_account(position)
+        .await
+        .unwrap()
+        .unwrap();
+    PositionData::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+#[tokio::test]
+async fn test_owner_manages_two_positions_on_the_same_range() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+    let owner = payer.pubkey();
+
+    // 1. Open two positions over the same range
+    for position_index in [0, 1] {
+        let ix = mint_position_ix(&setup, &owner, position_index);
+        process(&mut context, &[ix], &[&payer]).await;
+    }
+    let first = position_pda(&setup.pool, &owner, (TICK_LOWER, TICK_UPPER), 0);
+    let second = position_pda(&setup.pool, &owner, (TICK_LOWER, TICK_UPPER), 1);
+    assert_ne!(first, second);
+    assert_eq!(position_data(&mut context, first).await.position_index, 0);
+    assert_eq!(position_data(&mut context, second).await.position_index, 1);
+
+    let account = context
+        .banks_client
+        .get_account(position_counter_pda(&setup.pool, &owner))
+        .await
+        .unwrap()
+        .unwrap();
+    let counter = PositionCounter::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(counter.next_index, 2);
+
+    // An index other than the counter's next one is rejected
+    let ix = mint_position_ix(&setup, &owner, 5);
+    assert_eq!(
+        try_process(&mut context, &[ix], &[&payer])
+            .await
+            .unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(ErrorCode::InvalidPositionIndex as u32 + 6000)
+        )
+    );
+
+    // 2. Swap inside the range so both positions earn fees
+    let (owner_a, owner_b) = (setup.owner_a, setup.owner_b);
+    let swap_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::SwapExactInput {
+            pool: setup.pool,
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            user_token_in_account: owner_a,
+            user_token_out_account: owner_b,
+            user_authority: owner,
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::SwapExactInputHandler {
+            amount_in: SWAP_AMOUNT_IN,
+            amount_out_minimum: 1,
+            sqrt_price_limit_q64: 1,
+        }
+        .data(),
+    };
+    process(&mut context, &[swap_ix], &[&payer]).await;
+
+    // 3. Withdraw half of the second position only
+    let liquidity_delta = POSITION_LIQUIDITY / 2;
+    let decrease_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::DecreaseLiquidity {
+            pool: setup.pool,
+            position: second,
+            tick_lower: tick_pda(&setup.pool, TICK_LOWER),
+            tick_upper: tick_pda(&setup.pool, TICK_UPPER),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: owner_a,
+            owner_token1_account: owner_b,
+            owner,
+            token_program: spl_token::ID,
+            position_token_account: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::DecreaseLiquidityHandler { liquidity_delta }.data(),
+    };
+    process(&mut context, &[decrease_ix], &[&payer]).await;
+    assert_eq!(
+        position_data(&mut context, first).await.liquidity,
+        POSITION_LIQUIDITY
+    );
+    assert_eq!(
+        position_data(&mut context, second).await.liquidity,
+        POSITION_LIQUIDITY - liquidity_delta
+    );
+
+    // 4. Each position collects its own half of the swap fee
+    let mut collected = Vec::new();
+    for position in [first, second] {
+        let collect_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: amm_core::accounts::CollectFees {
+                pool: setup.pool,
+                position,
+                tick_lower: tick_pda(&setup.pool, TICK_LOWER),
+                tick_upper: tick_pda(&setup.pool, TICK_UPPER),
+                token0_vault: setup.vault_a,
+                token1_vault: setup.vault_b,
+                owner_token0_account: owner_a,
+                owner_token1_account: owner_b,
+                owner,
+                token_program: spl_token::ID,
+                position_token_account: None,
+            }
+            .to_account_metas(None),
+            data: amm_core::instruction::CollectFeesHandler {
+                amount_0_requested: u64::MAX,
+                amount_1_requested: u64::MAX,
+            }
+            .data(),
+        };
+        let logs = process(&mut context, &[collect_ix], &[&payer]).await;
+        let events = events::<CollectFeesEvent>(&logs);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].position, position);
+        collected.push(events[0].amount0);
+    }
+    // Both had the same liquidity during the swap
+    assert!(collected[0] > 0);
+    assert_eq!(collected[0], collected[1]);
+}
@@ -0,0 +1,381 @@
+// /tests/owner_index_integration_test.rs
+//
+// Lists positions in an owner's position index as they are minted and checks that the
+// links stay consistent when a listed position's NFT changes hands and the new holder
+// closes it.
+//
+// The program is loaded as `amm_core.so`, like the other integration tests.
+
+mod common;
+
+use anchor_lang::{prelude::Pubkey, AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::TransactionError,
+};
+
+use amm_core::{
+    errors::ErrorCode,
+    position::{OwnerPositionIndex, PositionData},
+    ID as PROGRAM_ID,
+};
+use common::*;
+
+const TICK_LOWER: i32 = -600;
+const TICK_UPPER: i32 = 600;
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000;
+fn custom_error(code: ErrorCode) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(code as u32 + 6000))
+}
+
+async fn owner_index(context: &mut ProgramTestContext, owner: &Pubkey) -> OwnerPositionIndex {
+    let account = context
+        .banks_client
+        .get_account(OwnerPositionIndex::address(owner))
+        .await
+        .unwrap()
+        .expect("owner index not found");
+    OwnerPositionIndex::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+async fn position_data(context: &mut ProgramTestContext, position: Pubkey) -> PositionData {
+    let account = context
+        .banks_client
+        .get_account(position)
+        .await
+        .unwrap()
+        .expect("position not found");
+    PositionData::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+/// Follows the links of `owner`'s index from its head.
+async fn listed_positions(context: &mut ProgramTestContext, owner: &Pubkey) -> Vec<Pubkey> {
+    let mut listed = Vec::new();
+    let mut next = owner_index(context, owner).await.head;
+    while next != Pubkey::default() {
+        listed.push(next);
+        next = position_data(context, next).await.next_in_owner_index;
+    }
+    listed
+}
+
+/// Mints `[TICK_LOWER, TICK_UPPER)` for `owner` as their position `position_index`,
+/// listing it in their index.
+fn mint_position_ix(
+    setup: &Setup,
+    owner: &Pubkey,
+    position_index: u64,
+    token_accounts: (Pubkey, Pubkey),
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::MintPosition {
+            pool: setup.pool,
+            position: position_pda(&setup.pool, owner, (TICK_LOWER, TICK_UPPER), position_index),
+            tick_lower: tick_pda(&setup.pool, TICK_LOWER),
+            tick_upper: tick_pda(&setup.pool, TICK_UPPER),
+            owner: *owner,
+            payer: *owner,
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            position_counter: position_counter_pda(&setup.pool, owner),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: token_accounts.0,
+            owner_token1_account: token_accounts.1,
+            token_program: spl_token::ID,
+            owner_index: Some(OwnerPositionIndex::address(owner)),
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionHandler {
+            tick_lower_index: TICK_LOWER,
+            tick_upper_index: TICK_UPPER,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            position_index,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    }
+}
+
+/// Closes `position` for `holder`, passing `owner_index` and `previous` to unlist it.
+fn close_position_ix(
+    setup: &Setup,
+    position: Pubkey,
+    holder: &Pubkey,
+    position_token_account: Option<Pubkey>,
+    owner_index: Option<Pubkey>,
+    previous: Option<Pubkey>,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::ClosePosition {
+            pool: setup.pool,
+            position,
+            tick_lower: None,
+            tick_upper: None,
+            owner: *holder,
+            rent_receiver: *holder,
+            position_token_account,
+            owner_index,
+            previous_indexed_position: previous,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::ClosePositionHandler {}.data(),
+    }
+}
+
+#[tokio::test]
+async fn test_owner_index_follows_mint_transfer_and_close() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+    let payer_a = create_token_account(&mut context, &setup.mint_a, &payer.pubkey()).await;
+    let payer_b = create_token_account(&mut context, &setup.mint_b, &payer.pubkey()).await;
+    mint_to(&mut context, &setup.mint_a, &payer_a, OWNER_FUNDING).await;
+    mint_to(&mut context, &setup.mint_b, &payer_b, OWNER_FUNDING).await;
+
+    // 1. Register the payer's index
+    let owner_index_key = OwnerPositionIndex::address(&payer.pubkey());
+    let register_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::RegisterOwnerIndex {
+            owner_index: owner_index_key,
+            owner: payer.pubkey(),
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::RegisterOwnerIndexHandler {}.data(),
+    };
+    process(&mut context, &[register_ix], &[&payer]).await;
+    let index = owner_index(&mut context, &payer.pubkey()).await;
+    assert_eq!(index.owner, payer.pubkey());
+    assert_eq!(index.head, Pubkey::default());
+    assert_eq!(index.count, 0);
+
+    // 2. Mint a position, a position NFT and another position, all listed
+    let first = position_pda(&setup.pool, &payer.pubkey(), (TICK_LOWER, TICK_UPPER), 0);
+    let ix = mint_position_ix(&setup, &payer.pubkey(), 0, (payer_a, payer_b));
+    process(&mut context, &[ix], &[&payer]).await;
+
+    let position_mint = Keypair::new();
+    let (nft_position, _) = Pubkey::find_program_address(
+        &[b"position".as_ref(), position_mint.pubkey().as_ref()],
+        &PROGRAM_ID,
+    );
+    let minter_nft_account = get_associated_token_address(&payer.pubkey(), &position_mint.pubkey());
+    let mint_nft_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::MintPositionNft {
+            pool: setup.pool,
+            position: nft_position,
+            position_mint: position_mint.pubkey(),
+            position_token_account: minter_nft_account,
+            tick_lower: tick_pda(&setup.pool, TICK_LOWER),
+            tick_upper: tick_pda(&setup.pool, TICK_UPPER),
+            owner: payer.pubkey(),
+            payer: payer.pubkey(),
+            token_program: spl_token::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: payer_a,
+            owner_token1_account: payer_b,
+            owner_index: Some(owner_index_key),
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionNftHandler {
+            tick_lower_index: TICK_LOWER,
+            tick_upper_index: TICK_UPPER,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    };
+    process(&mut context, &[mint_nft_ix], &[&payer, &position_mint]).await;
+
+    let last = position_pda(&setup.pool, &payer.pubkey(), (TICK_LOWER, TICK_UPPER), 1);
+    let ix = mint_position_ix(&setup, &payer.pubkey(), 1, (payer_a, payer_b));
+    process(&mut context, &[ix], &[&payer]).await;
+
+    // 3. The index lists them newest first
+    assert_eq!(owner_index(&mut context, &payer.pubkey()).await.count, 3);
+    assert_eq!(
+        listed_positions(&mut context, &payer.pubkey()).await,
+        vec![last, nft_position, first]
+    );
+
+    // 4. Transfer the NFT, then the new holder withdraws everything
+    let holder = Keypair::new();
+    let holder_nft_account =
+        create_token_account(&mut context, &position_mint.pubkey(), &holder.pubkey()).await;
+    let holder_a = create_token_account(&mut context, &setup.mint_a, &holder.pubkey()).await;
+    let holder_b = create_token_account(&mut context, &setup.mint_b, &holder.pubkey()).await;
+    let transfer_ix = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &minter_nft_account,
+        &holder_nft_account,
+        &payer.pubkey(),
+        &[],
+        1,
+    )
+    .unwrap();
+    let decrease_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::DecreaseLiquidity {
+            pool: setup.pool,
+            position: nft_position,
+            tick_lower: tick_pda(&setup.pool, TICK_LOWER),
+            tick_upper: tick_pda(&setup.pool, TICK_UPPER),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: holder_a,
+            owner_token1_account: holder_b,
+            owner: holder.pubkey(),
+            token_program: spl_token::ID,
+            position_token_account: Some(holder_nft_account),
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::DecreaseLiquidityHandler {
+            liquidity_delta: POSITION_LIQUIDITY,
+        }
+        .data(),
+    };
+    process(&mut context, &[transfer_ix], &[&payer]).await;
+    process(&mut context, &[decrease_ix], &[&payer, &holder]).await;
+
+    // 5. Closing a listed position needs the index, and the position linking to it
+    let close = |owner_index, previous| {
+        close_position_ix(
+            &setup,
+            nft_position,
+            &holder.pubkey(),
+            Some(holder_nft_account),
+            owner_index,
+            previous,
+        )
+    };
+    assert_eq!(
+        try_process(&mut context, &[close(None, None)], &[&payer, &holder])
+            .await
+            .unwrap_err(),
+        custom_error(ErrorCode::OwnerIndexRequired)
+    );
+    for previous in [None, Some(first)] {
+        assert_eq!(
+            try_process(
+                &mut context,
+                &[close(Some(owner_index_key), previous)],
+                &[&payer, &holder]
+            )
+            .await
+            .unwrap_err(),
+            custom_error(ErrorCode::InvalidOwnerIndexLink)
+        );
+    }
+
+    // 6. The new holder closes it, unlinking it from the minter's index
+    process(
+        &mut context,
+        &[close(Some(owner_index_key), Some(last))],
+        &[&payer, &holder],
+    )
+    .await;
+    assert!(context
+        .banks_client
+        .get_account(nft_position)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(owner_index(&mut context, &payer.pubkey()).await.count, 2);
+    assert_eq!(
+        listed_positions(&mut context, &payer.pubkey()).await,
+        vec![last, first]
+    );
+}
+
+#[tokio::test]
+async fn test_closing_the_head_moves_the_head() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+    let payer_a = create_token_account(&mut context, &setup.mint_a, &payer.pubkey()).await;
+    let payer_b = create_token_account(&mut context, &setup.mint_b, &payer.pubkey()).await;
+    mint_to(&mut context, &setup.mint_a, &payer_a, OWNER_FUNDING).await;
+    mint_to(&mut context, &setup.mint_b, &payer_b, OWNER_FUNDING).await;
+
+    let owner_index_key = OwnerPositionIndex::address(&payer.pubkey());
+    let register_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::RegisterOwnerIndex {
+            owner_index: owner_index_key,
+            owner: payer.pubkey(),
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::RegisterOwnerIndexHandler {}.data(),
+    };
+    let mint_ixs = [
+        mint_position_ix(&setup, &payer.pubkey(), 0, (payer_a, payer_b)),
+        mint_position_ix(&setup, &payer.pubkey(), 1, (payer_a, payer_b)),
+    ];
+    process(&mut context, &[register_ix], &[&payer]).await;
+    process(&mut context, &mint_ixs, &[&payer]).await;
+
+    let (first, head) = (
+        position_pda(&setup.pool, &payer.pubkey(), (TICK_LOWER, TICK_UPPER), 0),
+        position_pda(&setup.pool, &payer.pubkey(), (TICK_LOWER, TICK_UPPER), 1),
+    );
+    let decrease_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::DecreaseLiquidity {
+            pool: setup.pool,
+            position: head,
+            tick_lower: tick_pda(&setup.pool, TICK_LOWER),
+            tick_upper: tick_pda(&setup.pool, TICK_UPPER),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: payer_a,
+            owner_token1_account: payer_b,
+            owner: payer.pubkey(),
+            token_program: spl_token::ID,
+            position_token_account: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::DecreaseLiquidityHandler {
+            liquidity_delta: POSITION_LIQUIDITY,
+        }
+        .data(),
+    };
+    let close_ix = close_position_ix(
+        &setup,
+        head,
+        &payer.pubkey(),
+        None,
+        Some(owner_index_key),
+        None,
+    );
+    process(&mut context, &[decrease_ix, close_ix], &[&payer]).await;
+
+    let index = owner_index(&mut context, &payer.pubkey()).await;
+    assert_eq!(index.count, 1);
+    assert_eq!(index.head, first);
+    assert_eq!(
+        listed_positions(&mut context, &payer.pubkey()).await,
+        vec![first]
+    );
+}
@@ -0,0 +1,221 @@
+// /tests/pool_resize_integration_test.rs
+//
+// Checks that `resize_pool_account` grows a pool account with a zero-filled tail and the
+// authority paying its rent, that it cannot shrink the pool below `Pool::LEN` or grow it
+// by more than the runtime allows in one call, and that the resized pool keeps serving
+// swaps.
+//
+// The program is loaded as `amm_core.so`, like the other integration tests.
+
+mod common;
+
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{entrypoint::MAX_PERMITTED_DATA_INCREASE, system_instruction},
+    AccountDeserialize, InstructionData, ToAccountMetas,
+};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::Instruction,
+    signature::{Keypair, Signer},
+    sysvar,
+};
+
+use amm_core::{errors::ErrorCode, state::pool::Pool, ID as PROGRAM_ID};
+use common::*;
+
+const TICK_LOWER: i32 = -600;
+const TICK_UPPER: i32 = 600;
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000;
+const SWAP_AMOUNT_IN: u64 = 10_000;
+/// A pool at price 1.0 holding one position of the payer's over
+/// `[TICK_LOWER, TICK_UPPER)`.
+async fn setup(context: &mut ProgramTestContext) -> Setup {
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(context).await;
+    let position = position_pda(&setup.pool, &payer.pubkey(), (TICK_LOWER, TICK_UPPER), 0);
+    let mint_position_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::MintPosition {
+            pool: setup.pool,
+            position,
+            tick_lower: tick_pda(&setup.pool, TICK_LOWER),
+            tick_upper: tick_pda(&setup.pool, TICK_UPPER),
+            owner: payer.pubkey(),
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            position_counter: position_counter_pda(&setup.pool, &payer.pubkey()),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: setup.owner_a,
+            owner_token1_account: setup.owner_b,
+            token_program: spl_token::ID,
+            owner_index: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionHandler {
+            tick_lower_index: TICK_LOWER,
+            tick_upper_index: TICK_UPPER,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            position_index: 0,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    };
+    process(context, &[mint_position_ix], &[&payer]).await;
+
+    setup
+}
+
+/// Swaps `SWAP_AMOUNT_IN` of token0 for token1 inside the position's range.
+fn swap_ix(setup: &Setup, user: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::SwapExactInput {
+            pool: setup.pool,
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            user_token_in_account: setup.owner_a,
+            user_token_out_account: setup.owner_b,
+            user_authority: *user,
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::SwapExactInputHandler {
+            amount_in: SWAP_AMOUNT_IN,
+            amount_out_minimum: 1,
+            sqrt_price_limit_q64: 1,
+        }
+        .data(),
+    }
+}
+
+fn resize_ix(setup: &Setup, authority: &Pubkey, new_len: usize) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::ResizePoolAccount {
+            pool: setup.pool,
+            factory_config: setup.factory_config,
+            authority: *authority,
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::ResizePoolAccountHandler {
+            new_len: new_len as u32,
+        }
+        .data(),
+    }
+}
+
+async fn pool_account(context: &mut ProgramTestContext, pool: Pubkey) -> (Vec<u8>, u64) {
+    let account = context
+        .banks_client
+        .get_account(pool)
+        .await
+        .unwrap()
+        .unwrap();
+    (account.data, account.lamports)
+}
+
+#[tokio::test]
+async fn test_resized_pool_keeps_serving_swaps() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup(&mut context).await;
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let (data, _) = pool_account(&mut context, setup.pool).await;
+    assert_eq!(data.len(), Pool::LEN);
+    let pool_before = Pool::try_deserialize(&mut data.as_slice()).unwrap();
+
+    // 1. Only the pool's authority can resize it
+    let stranger = Keypair::new();
+    let fund_ix = system_instruction::transfer(&payer.pubkey(), &stranger.pubkey(), 1_000_000_000);
+    process(&mut context, &[fund_ix], &[&payer]).await;
+    let err = try_process(
+        &mut context,
+        &[resize_ix(&setup, &stranger.pubkey(), Pool::LEN + 1_024)],
+        &[&payer, &stranger],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::UnauthorizedAccess));
+
+    // 2. Growth is bounded per call, and the pool cannot shrink below its layout
+    let err = try_process(
+        &mut context,
+        &[resize_ix(
+            &setup,
+            &payer.pubkey(),
+            Pool::LEN + MAX_PERMITTED_DATA_INCREASE + 1,
+        )],
+        &[&payer],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::AccountGrowthTooLarge));
+    let err = try_process(
+        &mut context,
+        &[resize_ix(&setup, &payer.pubkey(), Pool::LEN - 1)],
+        &[&payer],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::AccountShrinkTooSmall));
+
+    // 3. Grow the pool: the tail is zeroed, the rent topped up and the pool unchanged
+    let grown_len = Pool::LEN + MAX_PERMITTED_DATA_INCREASE;
+    process(
+        &mut context,
+        &[resize_ix(&setup, &payer.pubkey(), grown_len)],
+        &[&payer],
+    )
+    .await;
+    let (data, lamports) = pool_account(&mut context, setup.pool).await;
+    assert_eq!(data.len(), grown_len);
+    assert_eq!(lamports, rent.minimum_balance(grown_len));
+    assert!(data[Pool::LEN..].iter().all(|&byte| byte == 0));
+    let pool = Pool::try_deserialize(&mut data.as_slice()).unwrap();
+    assert_eq!(pool.sqrt_price_q64, pool_before.sqrt_price_q64);
+    assert_eq!(pool.liquidity, POSITION_LIQUIDITY);
+
+    // 4. The grown pool serves swaps
+    process(&mut context, &[swap_ix(&setup, &payer.pubkey())], &[&payer]).await;
+    let (data, _) = pool_account(&mut context, setup.pool).await;
+    let pool = Pool::try_deserialize(&mut data.as_slice()).unwrap();
+    assert!(pool.sqrt_price_q64 < pool_before.sqrt_price_q64);
+    assert!(pool.fee_growth_global_0_q64 > pool_before.fee_growth_global_0_q64);
+
+    // 5. Shrink it back: the authority gets the extra rent back and swaps keep working
+    let authority_balance = context
+        .banks_client
+        .get_balance(payer.pubkey())
+        .await
+        .unwrap();
+    process(
+        &mut context,
+        &[resize_ix(&setup, &payer.pubkey(), Pool::LEN)],
+        &[&payer],
+    )
+    .await;
+    let (data, lamports) = pool_account(&mut context, setup.pool).await;
+    assert_eq!(data.len(), Pool::LEN);
+    assert_eq!(lamports, rent.minimum_balance(Pool::LEN));
+    let refund = rent.minimum_balance(grown_len) - rent.minimum_balance(Pool::LEN);
+    let authority_balance_after = context
+        .banks_client
+        .get_balance(payer.pubkey())
+        .await
+        .unwrap();
+    // Less the transaction fee
+    assert!(authority_balance_after > authority_balance + refund - 10_000);
+
+    context.last_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    process(&mut context, &[swap_ix(&setup, &payer.pubkey())], &[&payer]).await;
+    let (data, _) = pool_account(&mut context, setup.pool).await;
+    let shrunk = Pool::try_deserialize(&mut data.as_slice()).unwrap();
+    assert!(shrunk.sqrt_price_q64 < pool.sqrt_price_q64);
+}
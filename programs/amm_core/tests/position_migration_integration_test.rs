@@ -0,0 +1,277 @@
+// /tests/position_migration_integration_test.rs
+//
+// Checks that `migrate_position` brings a position stored under the first `PositionData`
+// layout, which held only the owner, pool, range and liquidity, up to the current one, and
+// that the position can then be collected from and withdrawn from like any other.
+//
+// The program is loaded as `amm_core.so`, like the other integration tests.
+
+mod common;
+
+use anchor_lang::{
+    prelude::Pubkey, solana_program::system_instruction, AccountDeserialize, InstructionData,
+    ToAccountMetas,
+};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::AccountSharedData,
+    instruction::{Instruction, InstructionError},
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::TransactionError,
+};
+
+use amm_core::{
+    errors::ErrorCode,
+    position::{CollectFeesEvent, PositionData},
+    ID as PROGRAM_ID,
+};
+use common::*;
+
+const TICK_LOWER: i32 = -600;
+const TICK_UPPER: i32 = 600;
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000;
+const SWAP_AMOUNT_IN: u64 = 10_000;
+/// Size of the first position layout: the owner, pool, range and liquidity.
+const FIRST_LAYOUT_LEN: usize = 8 + 32 + 32 + 4 + 4 + 16;
+
+/// A pool at price 1.0 holding one position of the payer's over
+/// `[TICK_LOWER, TICK_UPPER)`, and that position.
+async fn setup(context: &mut ProgramTestContext) -> (Setup, Pubkey) {
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(context).await;
+    let position = position_pda(&setup.pool, &payer.pubkey(), (TICK_LOWER, TICK_UPPER), 0);
+    let mint_position_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::MintPosition {
+            pool: setup.pool,
+            position,
+            tick_lower: tick_pda(&setup.pool, TICK_LOWER),
+            tick_upper: tick_pda(&setup.pool, TICK_UPPER),
+            owner: payer.pubkey(),
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            position_counter: position_counter_pda(&setup.pool, &payer.pubkey()),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: setup.owner_a,
+            owner_token1_account: setup.owner_b,
+            token_program: spl_token::ID,
+            owner_index: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionHandler {
+            tick_lower_index: TICK_LOWER,
+            tick_upper_index: TICK_UPPER,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            position_index: 0,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    };
+    process(context, &[mint_position_ix], &[&payer]).await;
+
+    (setup, position)
+}
+
+/// Swaps `SWAP_AMOUNT_IN` of token0 for token1 inside the position's range.
+fn swap_ix(setup: &Setup, user: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::SwapExactInput {
+            pool: setup.pool,
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            user_token_in_account: setup.owner_a,
+            user_token_out_account: setup.owner_b,
+            user_authority: *user,
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::SwapExactInputHandler {
+            amount_in: SWAP_AMOUNT_IN,
+            amount_out_minimum: 1,
+            sqrt_price_limit_q64: 1,
+        }
+        .data(),
+    }
+}
+
+fn collect_ix(setup: &Setup, position: &Pubkey, owner: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::CollectFees {
+            pool: setup.pool,
+            position: *position,
+            tick_lower: tick_pda(&setup.pool, TICK_LOWER),
+            tick_upper: tick_pda(&setup.pool, TICK_UPPER),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: setup.owner_a,
+            owner_token1_account: setup.owner_b,
+            owner: *owner,
+            token_program: spl_token::ID,
+            position_token_account: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::CollectFeesHandler {
+            amount_0_requested: u64::MAX,
+            amount_1_requested: u64::MAX,
+        }
+        .data(),
+    }
+}
+
+fn migrate_ix(setup: &Setup, position: &Pubkey, authority: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::MigratePosition {
+            pool: setup.pool,
+            factory_config: setup.factory_config,
+            position: *position,
+            tick_lower: tick_pda(&setup.pool, TICK_LOWER),
+            tick_upper: tick_pda(&setup.pool, TICK_UPPER),
+            authority: *authority,
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MigratePositionHandler {}.data(),
+    }
+}
+
+#[tokio::test]
+async fn test_first_layout_position_migrates_and_stays_usable() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let (setup, position) = setup(&mut context).await;
+
+    // 1. Earn fees, then rewrite the position as the first layout stored it: the fee
+    // snapshots and every later field did not exist yet
+    process(&mut context, &[swap_ix(&setup, &payer.pubkey())], &[&payer]).await;
+    let mut account = context
+        .banks_client
+        .get_account(position)
+        .await
+        .unwrap()
+        .unwrap();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    account.data.truncate(FIRST_LAYOUT_LEN);
+    account.lamports = rent.minimum_balance(FIRST_LAYOUT_LEN);
+    context.set_account(&position, &AccountSharedData::from(account));
+
+    // The old layout cannot be read by the position instructions
+    let err = try_process(
+        &mut context,
+        &[collect_ix(&setup, &position, &payer.pubkey())],
+        &[&payer],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(
+                anchor_lang::error::ErrorCode::AccountDidNotDeserialize as u32
+            )
+        )
+    );
+
+    // 2. Only the pool's authority can migrate the position
+    let stranger = Keypair::new();
+    let fund_ix = system_instruction::transfer(&payer.pubkey(), &stranger.pubkey(), 1_000_000_000);
+    process(&mut context, &[fund_ix], &[&payer]).await;
+    let err = try_process(
+        &mut context,
+        &[migrate_ix(&setup, &position, &stranger.pubkey())],
+        &[&payer, &stranger],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::UnauthorizedAccess));
+
+    process(
+        &mut context,
+        &[migrate_ix(&setup, &position, &payer.pubkey())],
+        &[&payer],
+    )
+    .await;
+    let account = context
+        .banks_client
+        .get_account(position)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data.len(), PositionData::LEN);
+    assert!(rent.is_exempt(account.lamports, PositionData::LEN));
+    let position_data = PositionData::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(position_data.owner, payer.pubkey());
+    assert_eq!(position_data.pool, setup.pool);
+    assert_eq!(position_data.liquidity, POSITION_LIQUIDITY);
+    assert_eq!(
+        (position_data.tokens_owed_0, position_data.tokens_owed_1),
+        (0, 0)
+    );
+
+    // A position already on the current layout is not migrated again
+    context.last_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let err = try_process(
+        &mut context,
+        &[migrate_ix(&setup, &position, &payer.pubkey())],
+        &[&payer],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::PositionAlreadyMigrated));
+
+    // 3. The position earns the fees of later swaps and collects them
+    context.last_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    process(&mut context, &[swap_ix(&setup, &payer.pubkey())], &[&payer]).await;
+    let logs = process(
+        &mut context,
+        &[collect_ix(&setup, &position, &payer.pubkey())],
+        &[&payer],
+    )
+    .await;
+    let collected = events::<CollectFeesEvent>(&logs);
+    assert_eq!(collected.len(), 1);
+    // Only the second swap's fee, 0.3% of its input rounded up, less rounding
+    assert!(collected[0].amount0 > 0 && collected[0].amount0 <= 30);
+    assert_eq!(collected[0].amount1, 0);
+
+    // 4. All of its liquidity can be withdrawn
+    let decrease_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::DecreaseLiquidity {
+            pool: setup.pool,
+            position,
+            tick_lower: tick_pda(&setup.pool, TICK_LOWER),
+            tick_upper: tick_pda(&setup.pool, TICK_UPPER),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: setup.owner_a,
+            owner_token1_account: setup.owner_b,
+            owner: payer.pubkey(),
+            token_program: spl_token::ID,
+            position_token_account: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::DecreaseLiquidityHandler {
+            liquidity_delta: POSITION_LIQUIDITY,
+        }
+        .data(),
+    };
+    process(&mut context, &[decrease_ix], &[&payer]).await;
+    let account = context
+        .banks_client
+        .get_account(position)
+        .await
+        .unwrap()
+        .unwrap();
+    let position_data = PositionData::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(position_data.liquidity, 0);
+}
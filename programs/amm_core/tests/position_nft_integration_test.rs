@@ -0,0 +1,243 @@
+// /tests/position_nft_integration_test.rs
+//
+// Mints a position represented by an NFT, transfers the NFT and checks that the position's
+// fees and liquidity follow it: the new holder can collect and withdraw, the minter cannot.
+//
+// The program is loaded as `amm_core.so`, like the other integration tests.
+
+mod common;
+
+use anchor_lang::{
+    prelude::Pubkey, solana_program::program_pack::Pack, AccountDeserialize, InstructionData,
+    ToAccountMetas,
+};
+use anchor_spl::associated_token::get_associated_token_address;
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::TransactionError,
+};
+
+use amm_core::{
+    errors::ErrorCode,
+    position::{CollectFeesEvent, DecreaseLiquidityEvent, PositionData},
+    ID as PROGRAM_ID,
+};
+use common::*;
+
+const TICK_LOWER: i32 = -600;
+const TICK_UPPER: i32 = 600;
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000;
+const SWAP_AMOUNT_IN: u64 = 10_000;
+fn collect_fees_ix(
+    setup: &Setup,
+    position: Pubkey,
+    holder: &Pubkey,
+    destinations: (Pubkey, Pubkey),
+    position_token_account: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::CollectFees {
+            pool: setup.pool,
+            position,
+            tick_lower: tick_pda(&setup.pool, TICK_LOWER),
+            tick_upper: tick_pda(&setup.pool, TICK_UPPER),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: destinations.0,
+            owner_token1_account: destinations.1,
+            owner: *holder,
+            token_program: spl_token::ID,
+            position_token_account: Some(position_token_account),
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::CollectFeesHandler {
+            amount_0_requested: u64::MAX,
+            amount_1_requested: u64::MAX,
+        }
+        .data(),
+    }
+}
+
+#[tokio::test]
+async fn test_position_nft_transfer_moves_fees_and_liquidity() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+
+    // 1. Mint a position NFT to the payer, who deposits both tokens for it
+    let payer_a = create_token_account(&mut context, &setup.mint_a, &payer.pubkey()).await;
+    let payer_b = create_token_account(&mut context, &setup.mint_b, &payer.pubkey()).await;
+    mint_to(&mut context, &setup.mint_a, &payer_a, OWNER_FUNDING).await;
+    mint_to(&mut context, &setup.mint_b, &payer_b, OWNER_FUNDING).await;
+    let position_mint = Keypair::new();
+    let (position, _) = Pubkey::find_program_address(
+        &[b"position".as_ref(), position_mint.pubkey().as_ref()],
+        &PROGRAM_ID,
+    );
+    let minter_nft_account = get_associated_token_address(&payer.pubkey(), &position_mint.pubkey());
+    let mint_position_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::MintPositionNft {
+            pool: setup.pool,
+            position,
+            position_mint: position_mint.pubkey(),
+            position_token_account: minter_nft_account,
+            tick_lower: tick_pda(&setup.pool, TICK_LOWER),
+            tick_upper: tick_pda(&setup.pool, TICK_UPPER),
+            owner: payer.pubkey(),
+            payer: payer.pubkey(),
+            token_program: spl_token::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: payer_a,
+            owner_token1_account: payer_b,
+            owner_index: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionNftHandler {
+            tick_lower_index: TICK_LOWER,
+            tick_upper_index: TICK_UPPER,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    };
+    process(&mut context, &[mint_position_ix], &[&payer, &position_mint]).await;
+
+    let account = context
+        .banks_client
+        .get_account(position)
+        .await
+        .unwrap()
+        .unwrap();
+    let position_data = PositionData::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(position_data.position_mint, position_mint.pubkey());
+    assert_eq!(position_data.liquidity, POSITION_LIQUIDITY);
+
+    // The supply is one and can never grow
+    let account = context
+        .banks_client
+        .get_account(position_mint.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    let mint = spl_token::state::Mint::unpack(&account.data).unwrap();
+    assert_eq!(mint.supply, 1);
+    assert_eq!(mint.decimals, 0);
+    assert!(mint.mint_authority.is_none());
+
+    // 2. Swap inside the range so the position earns fees
+    let swap_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::SwapExactInput {
+            pool: setup.pool,
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            user_token_in_account: payer_a,
+            user_token_out_account: payer_b,
+            user_authority: payer.pubkey(),
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::SwapExactInputHandler {
+            amount_in: SWAP_AMOUNT_IN,
+            amount_out_minimum: 1,
+            sqrt_price_limit_q64: 1,
+        }
+        .data(),
+    };
+    process(&mut context, &[swap_ix], &[&payer]).await;
+
+    // 3. Transfer the NFT to a new holder
+    let holder = Keypair::new();
+    let holder_nft_account =
+        create_token_account(&mut context, &position_mint.pubkey(), &holder.pubkey()).await;
+    let transfer_ix = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &minter_nft_account,
+        &holder_nft_account,
+        &payer.pubkey(),
+        &[],
+        1,
+    )
+    .unwrap();
+    process(&mut context, &[transfer_ix], &[&payer]).await;
+
+    // 4. The minter can no longer collect, with or without the NFT account
+    for nft_account in [minter_nft_account, holder_nft_account] {
+        let ix = collect_fees_ix(
+            &setup,
+            position,
+            &payer.pubkey(),
+            (payer_a, payer_b),
+            nft_account,
+        );
+        assert_eq!(
+            try_process(&mut context, &[ix], &[&payer])
+                .await
+                .unwrap_err(),
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(ErrorCode::UnauthorizedAccess as u32 + 6000)
+            )
+        );
+    }
+
+    // 5. The new holder collects the fees earned before the transfer
+    let holder_a = create_token_account(&mut context, &setup.mint_a, &holder.pubkey()).await;
+    let holder_b = create_token_account(&mut context, &setup.mint_b, &holder.pubkey()).await;
+    let collect_ix = collect_fees_ix(
+        &setup,
+        position,
+        &holder.pubkey(),
+        (holder_a, holder_b),
+        holder_nft_account,
+    );
+    let logs = process(&mut context, &[collect_ix], &[&payer, &holder]).await;
+    let collected = events::<CollectFeesEvent>(&logs);
+    assert_eq!(collected.len(), 1);
+    assert!(collected[0].amount0 > 0);
+    let account = context
+        .banks_client
+        .get_account(holder_a)
+        .await
+        .unwrap()
+        .unwrap();
+    let holder_token0 = spl_token::state::Account::unpack(&account.data).unwrap();
+    assert_eq!(holder_token0.amount, collected[0].amount0);
+
+    // 6. And withdraws liquidity
+    let liquidity_delta = POSITION_LIQUIDITY / 2;
+    let decrease_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::DecreaseLiquidity {
+            pool: setup.pool,
+            position,
+            tick_lower: tick_pda(&setup.pool, TICK_LOWER),
+            tick_upper: tick_pda(&setup.pool, TICK_UPPER),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: holder_a,
+            owner_token1_account: holder_b,
+            owner: holder.pubkey(),
+            token_program: spl_token::ID,
+            position_token_account: Some(holder_nft_account),
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::DecreaseLiquidityHandler { liquidity_delta }.data(),
+    };
+    let logs = process(&mut context, &[decrease_ix], &[&payer, &holder]).await;
+    let decreased = events::<DecreaseLiquidityEvent>(&logs);
+    assert_eq!(decreased.len(), 1);
+    assert_eq!(decreased[0].liquidity, POSITION_LIQUIDITY - liquidity_delta);
+}
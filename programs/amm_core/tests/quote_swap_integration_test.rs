@@ -0,0 +1,345 @@
+// /tests/quote_swap_integration_test.rs
+//
+// Checks that `quote_swap_exact_input` returns exactly what `swap_exact_input` then does:
+// the quote is simulated against a pool whose swap crosses two ticks, with every account
+// passed read-only, and the swap sent right after it against the same state must report
+// the same output, fee, final price and input. It also checks that `assert_quote` placed
+// before that swap lets it through, and fails the bundle once an earlier swap has moved
+// the price.
+//
+// The program is loaded as `amm_core.so`, like the other integration tests.
+
+mod common;
+
+use anchor_lang::{prelude::Pubkey, AnchorDeserialize, InstructionData, ToAccountMetas};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    signature::Signer,
+    sysvar,
+    transaction::Transaction,
+};
+
+use amm_core::{
+    constants::MIN_SQRT_PRICE,
+    errors::ErrorCode,
+    math,
+    state::pool::{QuoteResult, SwapEvent},
+    ID as PROGRAM_ID,
+};
+use common::*;
+
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000_000;
+const OWNER_FUNDING: u64 = 1_000_000_000_000_000_000;
+/// Enough token0 to push the price from tick 0 past both -60 and -600, but not to -1200.
+const AMOUNT_IN: u64 = 60_000_000_000_000;
+/// The ticks the swap crosses, in crossing order.
+const CROSSED_TICKS: [i32; 2] = [-60, -600];
+
+/// A pool at tick 0, its vaults and the payer's funded token accounts.
+struct PoolSetup {
+    pool: Pubkey,
+    vault0: Pubkey,
+    vault1: Pubkey,
+    owner_token0: Pubkey,
+    owner_token1: Pubkey,
+}
+
+async fn setup_pool(context: &mut ProgramTestContext) -> PoolSetup {
+    let mint_a = create_mint_with_decimals(context, 6).await;
+    let mint_b = create_mint_with_decimals(context, 6).await;
+    let (mint0, mint1) = if mint_a < mint_b {
+        (mint_a, mint_b)
+    } else {
+        (mint_b, mint_a)
+    };
+    let owner_token0 = create_funded_account(context, &mint0, OWNER_FUNDING).await;
+    let owner_token1 = create_funded_account(context, &mint1, OWNER_FUNDING).await;
+    let accounts = initialize_pool(
+        context,
+        mint0,
+        mint1,
+        math::tick_to_sqrt_price_q64(0).unwrap(),
+    )
+    .await;
+
+    PoolSetup {
+        pool: accounts.pool,
+        vault0: accounts.vault_a,
+        vault1: accounts.vault_b,
+        owner_token0,
+        owner_token1,
+    }
+}
+
+/// Mints the payer's `position_index`-th position over `[tick_lower, tick_upper)`.
+async fn mint_position(
+    context: &mut ProgramTestContext,
+    setup: &PoolSetup,
+    position_index: u64,
+    tick_lower: i32,
+    tick_upper: i32,
+) {
+    let payer = context.payer.insecure_clone();
+    let pool = setup.pool;
+    let (position, _) = Pubkey::find_program_address(
+        &[
+            b"position".as_ref(),
+            pool.as_ref(),
+            payer.pubkey().as_ref(),
+            tick_lower.to_le_bytes().as_ref(),
+            tick_upper.to_le_bytes().as_ref(),
+            position_index.to_le_bytes().as_ref(),
+        ],
+        &PROGRAM_ID,
+    );
+    let (position_counter, _) = Pubkey::find_program_address(
+        &[
+            b"position_counter".as_ref(),
+            pool.as_ref(),
+            payer.pubkey().as_ref(),
+        ],
+        &PROGRAM_ID,
+    );
+    let mint_position_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::MintPosition {
+            pool,
+            position,
+            tick_lower: tick_pda(&pool, tick_lower),
+            tick_upper: tick_pda(&pool, tick_upper),
+            owner: payer.pubkey(),
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            position_counter,
+            token0_vault: setup.vault0,
+            token1_vault: setup.vault1,
+            owner_token0_account: setup.owner_token0,
+            owner_token1_account: setup.owner_token1,
+            token_program: spl_token::ID,
+            owner_index: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionHandler {
+            tick_lower_index: tick_lower,
+            tick_upper_index: tick_upper,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            position_index,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    };
+    process(context, &[mint_position_ix], &[&payer]).await;
+}
+
+/// The payer's `swap_exact_input` of `amount_in` token0 for token1, passing the crossed
+/// ticks.
+fn swap_ix(
+    setup: &PoolSetup,
+    payer: &Pubkey,
+    amount_in: u64,
+    amount_out_minimum: u64,
+) -> Instruction {
+    let mut accounts = amm_core::accounts::SwapExactInput {
+        pool: setup.pool,
+        token0_vault: setup.vault0,
+        token1_vault: setup.vault1,
+        user_token_in_account: setup.owner_token0,
+        user_token_out_account: setup.owner_token1,
+        user_authority: *payer,
+        token_program: spl_token::ID,
+    }
+    .to_account_metas(None);
+    accounts.extend(
+        CROSSED_TICKS
+            .iter()
+            .map(|&tick| AccountMeta::new(tick_pda(&setup.pool, tick), false)),
+    );
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts,
+        data: amm_core::instruction::SwapExactInputHandler {
+            amount_in,
+            amount_out_minimum,
+            sqrt_price_limit_q64: MIN_SQRT_PRICE,
+        }
+        .data(),
+    }
+}
+
+/// `assert_quote` for a token0 -> token1 swap of `AMOUNT_IN`, passing the crossed ticks
+/// read-only.
+fn assert_quote_ix(setup: &PoolSetup, expected_out: u64, tolerance_bps: u16) -> Instruction {
+    let mut accounts = amm_core::accounts::AssertQuote { pool: setup.pool }.to_account_metas(None);
+    accounts.extend(
+        CROSSED_TICKS
+            .iter()
+            .map(|&tick| AccountMeta::new_readonly(tick_pda(&setup.pool, tick), false)),
+    );
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts,
+        data: amm_core::instruction::AssertQuoteHandler {
+            amount_in: AMOUNT_IN,
+            zero_for_one: true,
+            expected_out,
+            tolerance_bps,
+        }
+        .data(),
+    }
+}
+
+/// Simulates `quote_swap_exact_input` for token0 -> token1 and decodes its return data.
+async fn quote(context: &mut ProgramTestContext, setup: &PoolSetup) -> QuoteResult {
+    let payer = context.payer.insecure_clone();
+    let mut accounts =
+        amm_core::accounts::QuoteSwapExactInput { pool: setup.pool }.to_account_metas(None);
+    accounts.extend(
+        CROSSED_TICKS
+            .iter()
+            .map(|&tick| AccountMeta::new_readonly(tick_pda(&setup.pool, tick), false)),
+    );
+    let ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts,
+        data: amm_core::instruction::QuoteSwapExactInputHandler {
+            zero_for_one: true,
+            amount_in: AMOUNT_IN,
+            sqrt_price_limit_q64: MIN_SQRT_PRICE,
+        }
+        .data(),
+    };
+    let transaction = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        context.last_blockhash,
+    );
+    let simulation = context
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    assert!(
+        matches!(simulation.result, Some(Ok(()))),
+        "quote simulation failed: {:?}",
+        simulation.result
+    );
+    let return_data = simulation
+        .simulation_details
+        .and_then(|details| details.return_data)
+        .expect("quote_swap_exact_input returned no data");
+    QuoteResult::try_from_slice(&return_data.data).unwrap()
+}
+
+#[tokio::test]
+async fn test_quote_matches_swap_across_tick_crossings() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+
+    // 1. Two overlapping positions, so a token0 -> token1 swap adds liquidity at -60 and
+    // removes some at -600
+    let setup = setup_pool(&mut context).await;
+    mint_position(&mut context, &setup, 0, -600, 600).await;
+    mint_position(&mut context, &setup, 1, -1200, -60).await;
+
+    // 2. Quote the swap, then send it against the same state
+    let quote = quote(&mut context, &setup).await;
+    assert_eq!(quote.ticks_crossed, CROSSED_TICKS.len() as u32);
+    assert_eq!(quote.amount_in_consumed, AMOUNT_IN);
+
+    let swap_ix = swap_ix(&setup, &payer.pubkey(), AMOUNT_IN, quote.amount_out);
+    let logs = process(&mut context, &[swap_ix], &[&payer]).await;
+    let swaps = events::<SwapEvent>(&logs);
+    assert_eq!(swaps.len(), 1);
+    let swap = &swaps[0];
+
+    // 3. The quote predicted the swap exactly
+    assert_eq!(quote.amount_out, swap.amount_out);
+    assert_eq!(quote.amount_in_consumed, swap.amount_in);
+    assert_eq!(quote.fee_paid, swap.fee_paid);
+    assert_eq!(quote.sqrt_price_after, swap.sqrt_price_after);
+    assert!(swap.tick_after < CROSSED_TICKS[1] && swap.tick_after > -1200);
+}
+
+#[tokio::test]
+async fn test_assert_quote_lets_bundled_swap_through() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+    mint_position(&mut context, &setup, 0, -600, 600).await;
+    mint_position(&mut context, &setup, 1, -1200, -60).await;
+
+    // The swap's own minimum is left at zero: the assertion is the only slippage bound
+    let quote = quote(&mut context, &setup).await;
+    let logs = process(
+        &mut context,
+        &[
+            assert_quote_ix(&setup, quote.amount_out, 10),
+            swap_ix(&setup, &payer.pubkey(), AMOUNT_IN, 0),
+        ],
+        &[&payer],
+    )
+    .await;
+    let swaps = events::<SwapEvent>(&logs);
+    assert_eq!(swaps.len(), 1);
+    assert_eq!(swaps[0].amount_out, quote.amount_out);
+}
+
+#[tokio::test]
+async fn test_assert_quote_fails_bundle_after_price_moved() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+    mint_position(&mut context, &setup, 0, -600, 600).await;
+    mint_position(&mut context, &setup, 1, -1200, -60).await;
+
+    // 1. The client quotes, then another swap in the same direction lands first
+    let quote = quote(&mut context, &setup).await;
+    process(
+        &mut context,
+        &[swap_ix(&setup, &payer.pubkey(), AMOUNT_IN / 2, 0)],
+        &[&payer],
+    )
+    .await;
+    let pool_before = context
+        .banks_client
+        .get_account(setup.pool)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // 2. The live quote now pays out more than 50 bps less than the stale one, so the whole
+    //    bundle fails and the pool is left as it was
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            assert_quote_ix(&setup, quote.amount_out, 50),
+            swap_ix(&setup, &payer.pubkey(), AMOUNT_IN, 0),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        context.last_blockhash,
+    );
+    let err = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, program_error(ErrorCode::QuoteDeviationExceeded));
+    let pool_after = context
+        .banks_client
+        .get_account(setup.pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(pool_after.data, pool_before.data);
+}
@@ -0,0 +1,351 @@
+// /tests/tick_array_cu_comparison_test.rs
+//
+// Compares the compute units and accounts needed by a swap crossing several initialized
+// ticks when the ticks are stored in per-tick `TickData` accounts and when they are stored
+// in a single `TickArray`.
+//
+// Like the other integration tests, the program is loaded as `amm_core.so`:
+//
+//   cargo build-sbf --manifest-path programs/amm_core/Cargo.toml
+//   cargo test --test tick_array_cu_comparison_test -- --nocapture
+
+mod common;
+
+use anchor_lang::{prelude::Pubkey, InstructionData};
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::Transaction,
+};
+
+use amm_core::{
+    instruction::{
+        CreateTokenPairHandler as CreateTokenPairData, InitializePoolHandler as InitializePoolData,
+        InitializeTickArrayHandler as InitializeTickArrayData,
+        MintPositionHandler as MintPositionData,
+        MintPositionWithTickArraysHandler as MintPositionWithTickArraysData,
+        SwapExactInputHandler as SwapExactInputData,
+    },
+    tick_array::TickArray,
+    ID as PROGRAM_ID,
+};
+use common::*;
+
+/// Upper tick shared by every position.
+const TICK_UPPER: i32 = 3000;
+/// Lower tick of the wide position that keeps the swap in range.
+const WIDE_TICK_LOWER: i32 = -3000;
+/// Lower ticks of the narrower positions, crossed in this order by the swap.
+const CROSSED_TICKS: [i32; 4] = [-60, -120, -180, -240];
+/// The swap stops here, after crossing all of `CROSSED_TICKS`.
+const SWAP_LIMIT_TICK: i32 = -270;
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000;
+const SWAP_AMOUNT_IN: u64 = 1_000_000_000_000;
+/// Enough of each token for the owner to deposit into every position.
+const OWNER_FUNDING: u64 = 1_000_000_000_000;
+
+/// How the pool's ticks are stored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TickStorage {
+    TickAccounts,
+    TickArrays,
+}
+
+/// Result of simulating the swap for one tick storage.
+struct SwapRun {
+    units_consumed: u64,
+    account_count: usize,
+}
+
+/// Builds the instruction minting `[tick_lower, TICK_UPPER)` as the owner's position
+/// `position_index`, with the given tick storage. The owner deposits from
+/// `owner_token_accounts` into `vaults`, both given as (token0, token1).
+fn mint_position_ix(
+    pool: &Pubkey,
+    owner: &Pubkey,
+    vaults: (Pubkey, Pubkey),
+    owner_token_accounts: (Pubkey, Pubkey),
+    tick_lower: i32,
+    position_index: u64,
+    storage: TickStorage,
+) -> Instruction {
+    let position = position_pda(pool, owner, (tick_lower, TICK_UPPER), position_index);
+    let deposit_accounts = [
+        AccountMeta::new(vaults.0, false),
+        AccountMeta::new(vaults.1, false),
+        AccountMeta::new(owner_token_accounts.0, false),
+        AccountMeta::new(owner_token_accounts.1, false),
+        AccountMeta::new_readonly(spl_token::ID, false),
+    ];
+    match storage {
+        TickStorage::TickAccounts => Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(*pool, false),
+                AccountMeta::new(position, false),
+                AccountMeta::new(tick_pda(pool, tick_lower), false),
+                AccountMeta::new(tick_pda(pool, TICK_UPPER), false),
+                AccountMeta::new(*owner, true),
+                AccountMeta::new(*owner, true),
+                AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+                AccountMeta::new_readonly(sysvar::rent::ID, false),
+                AccountMeta::new(position_counter_pda(pool, owner), false),
+            ]
+            .into_iter()
+            .chain(deposit_accounts)
+            .collect(),
+            data: MintPositionData {
+                tick_lower_index: tick_lower,
+                tick_upper_index: TICK_UPPER,
+                liquidity_amount_desired: POSITION_LIQUIDITY,
+                position_index,
+                amount0_max: u64::MAX,
+                amount1_max: u64::MAX,
+            }
+            .data(),
+        },
+        TickStorage::TickArrays => Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(*pool, false),
+                AccountMeta::new(position, false),
+                AccountMeta::new(tick_array_pda(pool, tick_lower), false),
+                AccountMeta::new(tick_array_pda(pool, TICK_UPPER), false),
+                AccountMeta::new(*owner, true),
+                AccountMeta::new(*owner, true),
+                AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+                AccountMeta::new_readonly(sysvar::rent::ID, false),
+                AccountMeta::new(position_counter_pda(pool, owner), false),
+            ]
+            .into_iter()
+            .chain(deposit_accounts)
+            .collect(),
+            data: MintPositionWithTickArraysData {
+                tick_lower_index: tick_lower,
+                tick_upper_index: TICK_UPPER,
+                liquidity_amount_desired: POSITION_LIQUIDITY,
+                position_index,
+                amount0_max: u64::MAX,
+                amount1_max: u64::MAX,
+            }
+            .data(),
+        },
+    }
+}
+
+/// The PDA of the tick array holding `tick_index`.
+fn tick_array_pda(pool: &Pubkey, tick_index: i32) -> Pubkey {
+    TickArray::address(pool, TickArray::start_tick_index(tick_index, TICK_SPACING))
+}
+
+/// Sets up a pool whose positions are stored in `storage` and simulates a token0 -> token1
+/// swap crossing every tick of `CROSSED_TICKS`.
+async fn simulate_swap(storage: TickStorage) -> SwapRun {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+
+    let mut mint_a = create_mint(&mut context).await;
+    let mut mint_b = create_mint(&mut context).await;
+    if mint_a > mint_b {
+        std::mem::swap(&mut mint_a, &mut mint_b);
+    }
+
+    // 1. Create the pair's record and initialize the pool at price 1.0
+    let fee_rate: u16 = 30;
+    let (pool, _) = Pubkey::find_program_address(
+        &[
+            b"pool".as_ref(),
+            mint_a.as_ref(),
+            mint_b.as_ref(),
+            fee_rate.to_le_bytes().as_ref(),
+        ],
+        &PROGRAM_ID,
+    );
+    let (pool_registry, _) = Pubkey::find_program_address(
+        &[b"pool_registry".as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        &PROGRAM_ID,
+    );
+    let (token_pair, _) = Pubkey::find_program_address(
+        &[b"token_pair".as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        &PROGRAM_ID,
+    );
+    let create_pair_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(token_pair, false),
+            AccountMeta::new_readonly(mint_a, false),
+            AccountMeta::new_readonly(mint_b, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+        ],
+        data: CreateTokenPairData {
+            oracle_feed: Pubkey::default(),
+        }
+        .data(),
+    };
+    let vault_a = Keypair::new();
+    let vault_b = Keypair::new();
+    let init_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(pool, false),
+            AccountMeta::new_readonly(mint_a, false),
+            AccountMeta::new_readonly(mint_b, false),
+            AccountMeta::new_readonly(Keypair::new().pubkey(), false),
+            AccountMeta::new(vault_a.pubkey(), true),
+            AccountMeta::new(vault_b.pubkey(), true),
+            AccountMeta::new(pool_registry, false),
+            AccountMeta::new(token_pair, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+        ],
+        data: InitializePoolData {
+            initial_sqrt_price_q64: 1u128 << 64,
+            fee_rate,
+            tick_spacing: TICK_SPACING,
+            immutable_parameters: false,
+        }
+        .data(),
+    };
+    process(
+        &mut context,
+        &[create_pair_ix, init_ix],
+        &[&payer, &vault_a, &vault_b],
+    )
+    .await;
+
+    // 2. Create the tick arrays covering the positions, if needed
+    if storage == TickStorage::TickArrays {
+        for tick_index in [WIDE_TICK_LOWER, TICK_UPPER] {
+            let start_tick_index = TickArray::start_tick_index(tick_index, TICK_SPACING);
+            let init_tick_array_ix = Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new_readonly(pool, false),
+                    AccountMeta::new(tick_array_pda(&pool, tick_index), false),
+                    AccountMeta::new(payer.pubkey(), true),
+                    AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+                ],
+                data: InitializeTickArrayData { start_tick_index }.data(),
+            };
+            process(&mut context, &[init_tick_array_ix], &[&payer]).await;
+        }
+    }
+
+    // 3. Mint a wide position and one position per crossed tick. The owner deposits from
+    // the accounts it swaps with.
+    let user_in = create_token_account(&mut context, &mint_a, &payer.pubkey()).await;
+    let user_out = create_token_account(&mut context, &mint_b, &payer.pubkey()).await;
+    mint_to(
+        &mut context,
+        &mint_a,
+        &user_in,
+        OWNER_FUNDING + SWAP_AMOUNT_IN,
+    )
+    .await;
+    mint_to(&mut context, &mint_b, &user_out, OWNER_FUNDING).await;
+    for (position_index, tick_lower) in std::iter::once(WIDE_TICK_LOWER)
+        .chain(CROSSED_TICKS)
+        .enumerate()
+    {
+        let ix = mint_position_ix(
+            &pool,
+            &payer.pubkey(),
+            (vault_a.pubkey(), vault_b.pubkey()),
+            (user_in, user_out),
+            tick_lower,
+            position_index as u64,
+            storage,
+        );
+        process(&mut context, &[ix], &[&payer]).await;
+    }
+
+    // 4. Simulate the swap, passing the crossed ticks' storage as remaining accounts
+    let mut accounts = vec![
+        AccountMeta::new(pool, false),
+        AccountMeta::new(vault_a.pubkey(), false),
+        AccountMeta::new(vault_b.pubkey(), false),
+        AccountMeta::new(user_in, false),
+        AccountMeta::new(user_out, false),
+        AccountMeta::new_readonly(payer.pubkey(), true),
+        AccountMeta::new_readonly(spl_token::ID, false),
+    ];
+    match storage {
+        TickStorage::TickAccounts => accounts.extend(
+            CROSSED_TICKS
+                .iter()
+                .map(|&index| AccountMeta::new(tick_pda(&pool, index), false)),
+        ),
+        TickStorage::TickArrays => accounts.push(AccountMeta::new(
+            tick_array_pda(&pool, CROSSED_TICKS[0]),
+            false,
+        )),
+    }
+    let account_count = accounts.len();
+    let swap_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts,
+        data: SwapExactInputData {
+            amount_in: SWAP_AMOUNT_IN,
+            amount_out_minimum: 1,
+            sqrt_price_limit_q64: amm_core::math::tick_to_sqrt_price_q64(SWAP_LIMIT_TICK).unwrap(),
+        }
+        .data(),
+    };
+    let transaction = Transaction::new_signed_with_payer(
+        &[swap_ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        context.last_blockhash,
+    );
+    let simulation = context
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    assert!(
+        matches!(simulation.result, Some(Ok(()))),
+        "{storage:?} swap simulation failed: {:?}",
+        simulation.result
+    );
+    let details = simulation
+        .simulation_details
+        .expect("simulation details missing");
+
+    SwapRun {
+        units_consumed: details.units_consumed,
+        account_count,
+    }
+}
+
+#[tokio::test]
+async fn test_swap_crossing_ticks_cheaper_with_tick_arrays() {
+    let tick_accounts_run = simulate_swap(TickStorage::TickAccounts).await;
+    let tick_arrays_run = simulate_swap(TickStorage::TickArrays).await;
+
+    println!(
+        "swap crossing {} ticks: TickData accounts = {} CU / {} accounts, tick array = {} CU / {} accounts",
+        CROSSED_TICKS.len(),
+        tick_accounts_run.units_consumed,
+        tick_accounts_run.account_count,
+        tick_arrays_run.units_consumed,
+        tick_arrays_run.account_count
+    );
+
+    assert_eq!(
+        tick_accounts_run.account_count - tick_arrays_run.account_count,
+        CROSSED_TICKS.len() - 1
+    );
+    assert!(
+        tick_arrays_run.units_consumed < tick_accounts_run.units_consumed,
+        "expected the tick array swap to use fewer CU ({} >= {})",
+        tick_arrays_run.units_consumed,
+        tick_accounts_run.units_consumed
+    );
+}
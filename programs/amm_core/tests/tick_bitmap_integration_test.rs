@@ -0,0 +1,309 @@
+// /tests/tick_bitmap_integration_test.rs
+//
+// Checks that a pool's tick bitmap words live on-chain in `TickBitmap` accounts once moved
+// out of the pool: minting and removing liquidity flip their bits, and a swap finds the
+// ticks it crosses through them.
+//
+// The program is loaded as `amm_core.so`, like the other integration tests.
+
+mod common;
+
+use anchor_lang::{prelude::Pubkey, AccountDeserialize, InstructionData, ToAccountMetas};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    signature::Signer,
+    sysvar,
+    transaction::TransactionError,
+};
+
+use amm_core::{
+    errors::ErrorCode, math, state::pool::Pool, tick_bitmap::TickBitmap, ID as PROGRAM_ID,
+};
+use common::*;
+
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000;
+/// A narrow range around the starting price, and a wide one containing it.
+const INNER: (i32, i32) = (-600, 600);
+const OUTER: (i32, i32) = (-6_000, 6_000);
+/// Bitmap words covering the ticks of the two ranges, with tick spacing 60.
+const WORDS: [i16; 4] = [-2, -1, 0, 1];
+
+/// Moves bitmap word `word_index` out of the pool into its `TickBitmap` account.
+fn initialize_bitmap_word_ix(setup: &Setup, payer: &Pubkey, word_index: i16) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::InitializeBitmapWord {
+            pool: setup.pool,
+            tick_bitmap: TickBitmap::address(&setup.pool, word_index),
+            payer: *payer,
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::InitializeBitmapWordHandler { word_index }.data(),
+    }
+}
+
+/// Writable metas of the `TickBitmap` accounts of `words`, for `remaining_accounts`.
+fn bitmap_word_metas(pool: &Pubkey, words: &[i16]) -> Vec<AccountMeta> {
+    words
+        .iter()
+        .map(|&word_index| AccountMeta::new(TickBitmap::address(pool, word_index), false))
+        .collect()
+}
+
+/// Mints `range` for `owner` as their position `position_index`, passing the bitmap word
+/// accounts of `words`.
+fn mint_position_ix(
+    setup: &Setup,
+    owner: &Pubkey,
+    range: (i32, i32),
+    position_index: u64,
+    words: &[i16],
+) -> Instruction {
+    let pool = &setup.pool;
+    let mut accounts = amm_core::accounts::MintPosition {
+        pool: *pool,
+        position: position_pda(pool, owner, range, position_index),
+        tick_lower: tick_pda(pool, range.0),
+        tick_upper: tick_pda(pool, range.1),
+        owner: *owner,
+        payer: *owner,
+        system_program: anchor_lang::system_program::ID,
+        rent: sysvar::rent::ID,
+        position_counter: position_counter_pda(pool, owner),
+        token0_vault: setup.vault_a,
+        token1_vault: setup.vault_b,
+        owner_token0_account: setup.owner_a,
+        owner_token1_account: setup.owner_b,
+        token_program: spl_token::ID,
+        owner_index: None,
+    }
+    .to_account_metas(None);
+    accounts.extend(bitmap_word_metas(pool, words));
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts,
+        data: amm_core::instruction::MintPositionHandler {
+            tick_lower_index: range.0,
+            tick_upper_index: range.1,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            position_index,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    }
+}
+
+/// Removes all the liquidity of the owner's position, passing the bitmap word accounts of
+/// `words`.
+fn decrease_liquidity_ix(
+    setup: &Setup,
+    owner: &Pubkey,
+    range: (i32, i32),
+    position_index: u64,
+    words: &[i16],
+) -> Instruction {
+    let pool = &setup.pool;
+    let mut accounts = amm_core::accounts::DecreaseLiquidity {
+        pool: *pool,
+        position: position_pda(pool, owner, range, position_index),
+        tick_lower: tick_pda(pool, range.0),
+        tick_upper: tick_pda(pool, range.1),
+        token0_vault: setup.vault_a,
+        token1_vault: setup.vault_b,
+        owner_token0_account: setup.owner_a,
+        owner_token1_account: setup.owner_b,
+        owner: *owner,
+        token_program: spl_token::ID,
+        position_token_account: None,
+    }
+    .to_account_metas(None);
+    accounts.extend(bitmap_word_metas(pool, words));
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts,
+        data: amm_core::instruction::DecreaseLiquidityHandler {
+            liquidity_delta: POSITION_LIQUIDITY,
+        }
+        .data(),
+    }
+}
+
+/// Swaps `amount_in` of token0 for token1, passing the `TickData` accounts of `ticks` and
+/// the bitmap word accounts of `words`.
+fn swap_ix(
+    setup: &Setup,
+    user: &Pubkey,
+    amount_in: u64,
+    sqrt_price_limit_q64: u128,
+    ticks: &[i32],
+    words: &[i16],
+) -> Instruction {
+    let pool = &setup.pool;
+    let mut accounts = amm_core::accounts::SwapExactInput {
+        pool: *pool,
+        token0_vault: setup.vault_a,
+        token1_vault: setup.vault_b,
+        user_token_in_account: setup.owner_a,
+        user_token_out_account: setup.owner_b,
+        user_authority: *user,
+        token_program: spl_token::ID,
+    }
+    .to_account_metas(None);
+    accounts.extend(
+        ticks
+            .iter()
+            .map(|&tick| AccountMeta::new(tick_pda(pool, tick), false)),
+    );
+    accounts.extend(bitmap_word_metas(pool, words));
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts,
+        data: amm_core::instruction::SwapExactInputHandler {
+            amount_in,
+            amount_out_minimum: 1,
+            sqrt_price_limit_q64,
+        }
+        .data(),
+    }
+}
+
+async fn load_pool(context: &mut ProgramTestContext, pool: Pubkey) -> Pool {
+    let account = context
+        .banks_client
+        .get_account(pool)
+        .await
+        .unwrap()
+        .unwrap();
+    Pool::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+/// The word stored in the `TickBitmap` account of `word_index`.
+async fn bitmap_word(context: &mut ProgramTestContext, pool: &Pubkey, word_index: i16) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(TickBitmap::address(pool, word_index))
+        .await
+        .unwrap()
+        .unwrap();
+    // The word is the first field after the discriminator
+    u64::from_le_bytes(account.data[8..16].try_into().unwrap())
+}
+
+/// The bit of `tick` in its bitmap word.
+fn tick_bit(tick: i32) -> u64 {
+    1 << (tick / TICK_SPACING as i32).rem_euclid(64)
+}
+
+fn missing_word_error() -> TransactionError {
+    TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(ErrorCode::MissingTickBitmapWord as u32 + 6000),
+    )
+}
+
+#[tokio::test]
+async fn test_liquidity_flips_bits_in_bitmap_accounts() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+    let owner = payer.pubkey();
+    let ixs: Vec<Instruction> = WORDS
+        .iter()
+        .map(|&word_index| initialize_bitmap_word_ix(&setup, &owner, word_index))
+        .collect();
+    process(&mut context, &ixs, &[&payer]).await;
+    assert_eq!(
+        load_pool(&mut context, setup.pool)
+            .await
+            .external_bitmap_words,
+        WORDS
+    );
+
+    // Each tick of the range lives in a moved-out word, which must be supplied
+    let ix = mint_position_ix(&setup, &owner, INNER, 0, &[0]);
+    assert_eq!(
+        try_process(&mut context, &[ix], &[&payer])
+            .await
+            .unwrap_err(),
+        missing_word_error()
+    );
+    let ix = mint_position_ix(&setup, &owner, INNER, 0, &[-1, 0]);
+    process(&mut context, &[ix], &[&payer]).await;
+    assert_eq!(
+        bitmap_word(&mut context, &setup.pool, -1).await,
+        tick_bit(INNER.0)
+    );
+    assert_eq!(
+        bitmap_word(&mut context, &setup.pool, 0).await,
+        tick_bit(INNER.1)
+    );
+
+    // Removing the liquidity uninitializes both ticks again
+    let ix = decrease_liquidity_ix(&setup, &owner, INNER, 0, &[-1, 0]);
+    process(&mut context, &[ix], &[&payer]).await;
+    assert_eq!(bitmap_word(&mut context, &setup.pool, -1).await, 0);
+    assert_eq!(bitmap_word(&mut context, &setup.pool, 0).await, 0);
+    // The words stay in their accounts
+    assert_eq!(
+        load_pool(&mut context, setup.pool)
+            .await
+            .external_bitmap_words,
+        WORDS
+    );
+}
+
+#[tokio::test]
+async fn test_swap_finds_crossed_ticks_in_bitmap_accounts() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+    let owner = payer.pubkey();
+    let ixs: Vec<Instruction> = WORDS
+        .iter()
+        .map(|&word_index| initialize_bitmap_word_ix(&setup, &owner, word_index))
+        .collect();
+    process(&mut context, &ixs, &[&payer]).await;
+    let ixs = [
+        mint_position_ix(&setup, &owner, INNER, 0, &[-1, 0]),
+        mint_position_ix(&setup, &owner, OUTER, 1, &[-2, 1]),
+    ];
+    process(&mut context, &ixs, &[&payer]).await;
+    assert_eq!(
+        load_pool(&mut context, setup.pool).await.liquidity,
+        2 * POSITION_LIQUIDITY
+    );
+
+    // Enough token0 to push the price past the inner range's lower tick, but not down to
+    // the limit
+    let amount_in = 80_000_000_000;
+    let limit = math::tick_to_sqrt_price_q64(-1_200).unwrap();
+
+    // The swap starts in word 0 and reaches word -1, so both must be supplied
+    let ix = swap_ix(&setup, &owner, amount_in, limit, &[INNER.0], &[0]);
+    assert_eq!(
+        try_process(&mut context, &[ix], &[&payer])
+            .await
+            .unwrap_err(),
+        missing_word_error()
+    );
+    let ix = swap_ix(&setup, &owner, amount_in, limit, &[INNER.0], &[0, -1]);
+    process(&mut context, &[ix], &[&payer]).await;
+
+    // Crossing the inner range's lower tick took its liquidity out of the pool
+    let pool = load_pool(&mut context, setup.pool).await;
+    assert!(pool.current_tick < INNER.0 && pool.current_tick > -1_200);
+    assert!(pool.sqrt_price_q64 > limit);
+    assert_eq!(pool.liquidity, POSITION_LIQUIDITY);
+    // Crossing a tick does not change which ticks are initialized
+    assert_eq!(
+        bitmap_word(&mut context, &setup.pool, -1).await,
+        tick_bit(INNER.0)
+    );
+}
@@ -0,0 +1,291 @@
+// /tests/tick_spacing_migration_integration_test.rs
+//
+// Checks that `migrate_tick_spacing` widens the spacing of a pool holding liquidity
+// without disturbing it: a position on ticks of the old spacing keeps its liquidity in
+// the pool, swaps still cross its ticks in both directions, and its owner can then move
+// it onto the new spacing with `update_position`, but not onto ticks it no longer allows.
+//
+// The program is loaded as `amm_core.so`, like the other integration tests.
+
+mod common;
+
+use anchor_lang::{prelude::Pubkey, AccountDeserialize, InstructionData, ToAccountMetas};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    signature::Signer,
+    sysvar,
+};
+
+use amm_core::{
+    errors::ErrorCode,
+    math,
+    position::PositionData,
+    state::pool::{Pool, SwapEvent},
+    ID as PROGRAM_ID,
+};
+use common::*;
+
+/// Ticks of the pool's original spacing that the new spacing no longer allows.
+const OLD_TICK_LOWER: i32 = -60;
+const OLD_TICK_UPPER: i32 = 60;
+const NEW_TICK_SPACING: u16 = 120;
+const NEW_TICK_LOWER: i32 = -120;
+const NEW_TICK_UPPER: i32 = 120;
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000;
+/// More than it takes to push the price across either end of the position.
+const SWAP_AMOUNT_IN: u64 = 10_000_000_000;
+
+/// Mints the payer's first position, over `[OLD_TICK_LOWER, OLD_TICK_UPPER)`.
+async fn mint_position(context: &mut ProgramTestContext, setup: &Setup) -> Pubkey {
+    let payer = context.payer.insecure_clone();
+    let range = (OLD_TICK_LOWER, OLD_TICK_UPPER);
+    let position = position_pda(&setup.pool, &payer.pubkey(), range, 0);
+    let mint_position_ix = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::MintPosition {
+            pool: setup.pool,
+            position,
+            tick_lower: tick_pda(&setup.pool, OLD_TICK_LOWER),
+            tick_upper: tick_pda(&setup.pool, OLD_TICK_UPPER),
+            owner: payer.pubkey(),
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            position_counter: position_counter_pda(&setup.pool, &payer.pubkey()),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: setup.owner_a,
+            owner_token1_account: setup.owner_b,
+            token_program: spl_token::ID,
+            owner_index: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionHandler {
+            tick_lower_index: OLD_TICK_LOWER,
+            tick_upper_index: OLD_TICK_UPPER,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            position_index: 0,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    };
+    process(context, &[mint_position_ix], &[&payer]).await;
+    position
+}
+
+fn migrate_ix(setup: &Setup, authority: &Pubkey, new_tick_spacing: u16) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::MigrateTickSpacing {
+            pool: setup.pool,
+            factory_config: setup.factory_config,
+            authority: *authority,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MigrateTickSpacingHandler { new_tick_spacing }.data(),
+    }
+}
+
+/// Swaps `SWAP_AMOUNT_IN` of token0 for token1 when `zero_for_one`, of token1 for token0
+/// otherwise, stopping at `limit_tick` and passing the `TickData` accounts of `ticks`.
+fn swap_ix(
+    setup: &Setup,
+    user: &Pubkey,
+    zero_for_one: bool,
+    limit_tick: i32,
+    ticks: &[i32],
+) -> Instruction {
+    let (user_token_in_account, user_token_out_account) = if zero_for_one {
+        (setup.owner_a, setup.owner_b)
+    } else {
+        (setup.owner_b, setup.owner_a)
+    };
+    let mut accounts = amm_core::accounts::SwapExactInput {
+        pool: setup.pool,
+        token0_vault: setup.vault_a,
+        token1_vault: setup.vault_b,
+        user_token_in_account,
+        user_token_out_account,
+        user_authority: *user,
+        token_program: spl_token::ID,
+    }
+    .to_account_metas(None);
+    accounts.extend(
+        ticks
+            .iter()
+            .map(|&tick| AccountMeta::new(tick_pda(&setup.pool, tick), false)),
+    );
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts,
+        data: amm_core::instruction::SwapExactInputHandler {
+            amount_in: SWAP_AMOUNT_IN,
+            amount_out_minimum: 1,
+            sqrt_price_limit_q64: math::tick_to_sqrt_price_q64(limit_tick).unwrap(),
+        }
+        .data(),
+    }
+}
+
+/// Moves the payer's position from `old_range` to `new_range`.
+fn update_position_ix(
+    setup: &Setup,
+    position: &Pubkey,
+    owner: &Pubkey,
+    old_range: (i32, i32),
+    new_range: (i32, i32),
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: amm_core::accounts::UpdatePosition {
+            pool: setup.pool,
+            position: *position,
+            old_tick_lower: tick_pda(&setup.pool, old_range.0),
+            old_tick_upper: tick_pda(&setup.pool, old_range.1),
+            new_tick_lower: tick_pda(&setup.pool, new_range.0),
+            new_tick_upper: tick_pda(&setup.pool, new_range.1),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: setup.owner_a,
+            owner_token1_account: setup.owner_b,
+            owner: *owner,
+            payer: *owner,
+            system_program: anchor_lang::system_program::ID,
+            token_program: spl_token::ID,
+            rent: sysvar::rent::ID,
+            position_token_account: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::UpdatePositionHandler {
+            new_tick_lower_index: new_range.0,
+            new_tick_upper_index: new_range.1,
+            amount0_min: 0,
+            amount1_min: 0,
+        }
+        .data(),
+    }
+}
+
+async fn load<T: AccountDeserialize>(context: &mut ProgramTestContext, address: Pubkey) -> T {
+    let account = context
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap();
+    T::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+#[tokio::test]
+async fn test_migrated_pool_keeps_and_realigns_live_positions() {
+    let mut context = ProgramTest::new("amm_core", PROGRAM_ID, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context).await;
+    let position = mint_position(&mut context, &setup).await;
+
+    // 1. Widen the spacing under the live position: its liquidity stays in the pool
+    process(
+        &mut context,
+        &[migrate_ix(&setup, &payer.pubkey(), NEW_TICK_SPACING)],
+        &[&payer],
+    )
+    .await;
+    let pool: Pool = load(&mut context, setup.pool).await;
+    assert_eq!(pool.tick_spacing, NEW_TICK_SPACING);
+    assert_eq!(pool.bitmap_tick_spacing, TICK_SPACING);
+    assert_eq!(pool.liquidity, POSITION_LIQUIDITY);
+
+    // 2. Swaps still cross the position's old-spacing ticks, out of its range and back
+    let logs = process(
+        &mut context,
+        &[swap_ix(
+            &setup,
+            &payer.pubkey(),
+            true,
+            NEW_TICK_LOWER,
+            &[OLD_TICK_LOWER],
+        )],
+        &[&payer],
+    )
+    .await;
+    let swaps = events::<SwapEvent>(&logs);
+    assert!(swaps[0].tick_after < OLD_TICK_LOWER);
+    let pool: Pool = load(&mut context, setup.pool).await;
+    assert_eq!(pool.liquidity, 0);
+
+    let logs = process(
+        &mut context,
+        &[swap_ix(
+            &setup,
+            &payer.pubkey(),
+            false,
+            0,
+            &[OLD_TICK_LOWER],
+        )],
+        &[&payer],
+    )
+    .await;
+    let swaps = events::<SwapEvent>(&logs);
+    assert_eq!(swaps[0].tick_after, 0);
+    let pool: Pool = load(&mut context, setup.pool).await;
+    assert_eq!(pool.liquidity, POSITION_LIQUIDITY);
+
+    // 3. A position cannot move onto ticks the new spacing does not allow
+    let err = try_process(
+        &mut context,
+        &[update_position_ix(
+            &setup,
+            &position,
+            &payer.pubkey(),
+            (OLD_TICK_LOWER, OLD_TICK_UPPER),
+            (3 * OLD_TICK_LOWER, NEW_TICK_UPPER),
+        )],
+        &[&payer],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidTickSpacing));
+
+    // 4. Its owner re-aligns it, and swaps cross the new ticks
+    process(
+        &mut context,
+        &[update_position_ix(
+            &setup,
+            &position,
+            &payer.pubkey(),
+            (OLD_TICK_LOWER, OLD_TICK_UPPER),
+            (NEW_TICK_LOWER, NEW_TICK_UPPER),
+        )],
+        &[&payer],
+    )
+    .await;
+    let moved: PositionData = load(&mut context, position).await;
+    assert_eq!(
+        (moved.tick_lower_index, moved.tick_upper_index),
+        (NEW_TICK_LOWER, NEW_TICK_UPPER)
+    );
+    assert!(moved.liquidity > 0);
+    let pool: Pool = load(&mut context, setup.pool).await;
+    assert_eq!(pool.liquidity, moved.liquidity);
+
+    let logs = process(
+        &mut context,
+        &[swap_ix(
+            &setup,
+            &payer.pubkey(),
+            true,
+            2 * NEW_TICK_LOWER,
+            &[NEW_TICK_LOWER],
+        )],
+        &[&payer],
+    )
+    .await;
+    let swaps = events::<SwapEvent>(&logs);
+    assert!(swaps[0].tick_after < NEW_TICK_LOWER);
+    let pool: Pool = load(&mut context, setup.pool).await;
+    assert_eq!(pool.liquidity, 0);
+}
@@ -0,0 +1,138 @@
+//! Circuit breaker that pauses auto-rebalancing during extreme price moves.
+//!
+//! A depeg or exploit in one of a pool's tokens makes the optimizer chase the price,
+//! crystallizing the maximum impermanent loss. The risk engine samples the pool price,
+//! keeps a time-weighted average (TWAP) of the samples, and halts rebalancing for a
+//! while when the TWAP moves more than a configured amount within a configured window.
+//!
+//! All prices are u128 values scaled by `PRICE_SCALE_FACTOR`.
+use crate::errors::RiskEngineError as ErrorCode;
+use anchor_lang::prelude::*;
+use primitive_types::U256;
+
+/// Denominator for thresholds expressed in basis points.
+pub const BPS_DENOMINATOR: u128 = 10_000;
+
+/// A price sample recorded by the risk engine.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct PriceObservation {
+    /// Unix timestamp of the sample.
+    pub timestamp: i64,
+    /// Spot price at `timestamp`.
+    pub price: u128,
+    /// TWAP over the configured interval ending at `timestamp`.
+    pub twap: u128,
+}
+
+/// Emitted when a TWAP move trips the circuit breaker and halts auto-rebalancing.
+#[event]
+pub struct CircuitBreakerTripped {
+    /// The amm_core pool whose price moved.
+    pub pool: Pubkey,
+    /// TWAP the move was measured from.
+    pub reference_twap: u128,
+    /// TWAP at the time of the trip.
+    pub current_twap: u128,
+    /// Size of the move, in basis points.
+    pub move_bps: u128,
+    /// Unix timestamp until which rebalancing is halted.
+    pub halted_until: i64,
+}
+
+/// Converts a Q64.64 sqrt price into a price scaled by `scale`.
+pub fn sqrt_price_q64_to_scaled_price(sqrt_price_q64: u128, scale: u128) -> Result<u128> {
+    let price =
+        (U256::from(sqrt_price_q64) * U256::from(sqrt_price_q64) * U256::from(scale)) >> 128;
+    if price > U256::from(u128::MAX) {
+        return err!(ErrorCode::Overflow);
+    }
+    Ok(price.as_u128())
+}
+
+/// Converts a price scaled by `scale` into a Q64.64 sqrt price, rounding down. The inverse
+/// of [`sqrt_price_q64_to_scaled_price`], up to rounding.
+pub fn scaled_price_to_sqrt_price_q64(price: u128, scale: u128) -> Result<u128> {
+    if scale == 0 {
+        return err!(ErrorCode::CalculationError);
+    }
+    let sqrt_price = ((U256::from(price) << 128) / U256::from(scale)).integer_sqrt();
+    if sqrt_price > U256::from(u128::MAX) {
+        return err!(ErrorCode::Overflow);
+    }
+    Ok(sqrt_price.as_u128())
+}
+
+/// Time-weighted average of the spot prices in `observations` over `[now - interval_secs, now]`.
+///
+/// `observations` must be in chronological order. Each price holds from its timestamp until
+/// the next observation (the last one until `now`), so the observation at or before the start
+/// of the interval still contributes. Returns the latest price if no time has elapsed inside
+/// the interval, or `None` if there are no observations.
+pub fn time_weighted_average_price(
+    observations: &[PriceObservation],
+    now: i64,
+    interval_secs: i64,
+) -> Result<Option<u128>> {
+    let Some(latest) = observations.last() else {
+        return Ok(None);
+    };
+    let interval_start = now.saturating_sub(interval_secs);
+
+    let mut weighted_sum: u128 = 0;
+    let mut total_secs: u128 = 0;
+    for (i, observation) in observations.iter().enumerate() {
+        let segment_end = observations
+            .get(i + 1)
+            .map_or(now, |next| next.timestamp)
+            .min(now);
+        let segment_start = observation.timestamp.max(interval_start);
+        if segment_end <= segment_start {
+            continue;
+        }
+        let secs = (segment_end - segment_start) as u128;
+        weighted_sum = observation
+            .price
+            .checked_mul(secs)
+            .and_then(|weighted| weighted_sum.checked_add(weighted))
+            .ok_or(ErrorCode::Overflow)?;
+        total_secs += secs;
+    }
+
+    if total_secs == 0 {
+        return Ok(Some(latest.price));
+    }
+    Ok(Some(weighted_sum / total_secs))
+}
+
+/// Relative move from `from` to `to`, in basis points of `from`.
+pub fn price_move_bps(from: u128, to: u128) -> Result<u128> {
+    if from == 0 {
+        return err!(ErrorCode::CalculationError);
+    }
+    let delta = U256::from(from.abs_diff(to)) * U256::from(BPS_DENOMINATOR) / U256::from(from);
+    Ok(delta.min(U256::from(u128::MAX)).as_u128())
+}
+
+/// Largest TWAP move between `twap` and any observation recorded within `window_secs` of `now`.
+///
+/// Returns the reference TWAP and the move in basis points, or `None` if no observation
+/// falls inside the window.
+pub fn max_twap_move_bps(
+    observations: &[PriceObservation],
+    twap: u128,
+    now: i64,
+    window_secs: i64,
+) -> Result<Option<(u128, u128)>> {
+    let window_start = now.saturating_sub(window_secs);
+    let mut max_move: Option<(u128, u128)> = None;
+    for observation in observations
+        .iter()
+        .filter(|observation| observation.timestamp >= window_start && observation.twap > 0)
+    {
+        let move_bps = price_move_bps(observation.twap, twap)?;
+        if max_move.is_none_or(|(_, current)| move_bps > current) {
+            max_move = Some((observation.twap, move_bps));
+        }
+    }
+    Ok(max_move)
+}
@@ -18,4 +18,54 @@ pub enum RiskEngineError {
     CalculationError,
     #[msg("Overflow in calculation")]
     Overflow,
+    #[msg("Proposed range does not contain the current price; the position would earn no fees.")]
+    ProposedRangeExcludesCurrentPrice,
+    #[msg("Auto-rebalancing is halted by the circuit breaker.")]
+    RebalancingHalted,
+    #[msg("Invalid risk configuration parameters.")]
+    InvalidRiskConfig,
+    #[msg("Price observation timestamp is older than the latest observation.")]
+    InvalidObservationTimestamp,
+    #[msg("Signer is not the risk engine governance authority.")]
+    Unauthorized,
+    #[msg("Too many accounts passed in the batch.")]
+    BatchTooLarge,
+    #[msg("Invalid liquidity split configuration.")]
+    InvalidLiquiditySplitConfig,
+    #[msg("GARCH parameters must satisfy alpha + beta < 1.")]
+    InvalidGarchParameters,
+    #[msg("EWMA decay factor must be below 1.")]
+    InvalidEwmaLambda,
+    #[msg("Entry index is past the end of the price history.")]
+    InvalidEntryIndex,
+    #[msg("Keeper bond is too small.")]
+    InsufficientKeeperBond,
+    #[msg("Keeper bond does not belong to the payer.")]
+    KeeperBondMismatch,
+    #[msg("Keeper bond is locked until its rebalances can no longer be disputed.")]
+    KeeperBondLocked,
+    #[msg("Keeper has too many rebalances open to disputes.")]
+    TooManyOpenSubmissions,
+    #[msg("No disputable keeper rebalance of this position.")]
+    SubmissionNotFound,
+    #[msg("The rebalance's dispute window has closed.")]
+    DisputeWindowClosed,
+    #[msg("The pool has no oracle price to measure the rebalance against.")]
+    CheckpointPriceUnavailable,
+    #[msg("The rebalanced range did not lose more than the kept range.")]
+    SlashNotJustified,
+    #[msg("A split-range strategy needs at least one range.")]
+    InvalidSplitRangeCount,
+    #[msg("The position was rebalanced as many times as its limit allows this epoch.")]
+    RebalanceLimitReached,
+    #[msg("The Pyth price update is for another feed than the pool's token pair.")]
+    PythFeedMismatch,
+    #[msg("The Pyth price update is not fully verified.")]
+    PythPriceUnverified,
+    #[msg("The Pyth price is older than the configured maximum staleness.")]
+    PythPriceStale,
+    #[msg("The Pyth price cannot be converted into a pool price.")]
+    InvalidPythPrice,
+    #[msg("The pool's token pair names a Pyth feed, but its price update or mints are missing.")]
+    MissingPythPriceFeed,
 }
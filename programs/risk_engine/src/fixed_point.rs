@@ -0,0 +1,135 @@
+//! Signed fixed-point numbers with a decimal scale.
+//!
+//! `FixedI128<SCALE>` stores `value * SCALE` in an `i128`. The scale is part of the type,
+//! so values of different scales cannot be added or compared by mistake:
+//!
+//! ```compile_fail
+//! use fluxa_risk_engine::fixed_point::FixedI128;
+//!
+//! let percent = FixedI128::<100>::from_raw(150);
+//! let per_mille = FixedI128::<1_000>::from_raw(1_500);
+//! assert!(percent == per_mille);
+//! ```
+//!
+//! Converting between scales goes through `rescale`. Every operation is checked and
+//! returns `None` on overflow; results are truncated toward zero.
+use amm_core::math::FixedQ64;
+use primitive_types::U256;
+use std::fmt;
+
+/// A signed fixed-point number stored as `value * SCALE`.
+///
+/// `SCALE` must be nonzero and at most `i128::MAX`; using a type with another scale fails
+/// to compile.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FixedI128<const SCALE: u128>(i128);
+
+impl<const SCALE: u128> FixedI128<SCALE> {
+    const VALID_SCALE: () = assert!(SCALE > 0 && SCALE <= i128::MAX as u128);
+
+    /// 0.0
+    pub const ZERO: Self = Self(0);
+    /// 1.0
+    pub const ONE: Self = Self::from_raw(SCALE as i128);
+
+    /// Creates a value from its raw representation, scaled by `SCALE`.
+    pub const fn from_raw(raw: i128) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID_SCALE;
+        Self(raw)
+    }
+
+    /// Returns the raw representation, scaled by `SCALE`.
+    pub const fn raw(self) -> i128 {
+        self.0
+    }
+
+    /// Converts an integer, returning `None` if it does not fit at this scale.
+    pub fn from_int(value: i64) -> Option<Self> {
+        (value as i128).checked_mul(Self::ONE.0).map(Self)
+    }
+
+    /// Converts a Q64.64 value, returning `None` if it does not fit at this scale.
+    pub fn from_fixed_q64(value: FixedQ64) -> Option<Self> {
+        let raw = value.raw();
+        Self::from_magnitude(
+            (U256::from(raw.unsigned_abs()) * U256::from(SCALE)) >> 64,
+            raw < 0,
+        )
+    }
+
+    /// Converts to another scale, returning `None` if the value does not fit in it.
+    pub fn rescale<const TO: u128>(self) -> Option<FixedI128<TO>> {
+        FixedI128::<TO>::from_magnitude(
+            U256::from(self.0.unsigned_abs()) * U256::from(TO) / U256::from(SCALE),
+            self.is_negative(),
+        )
+    }
+
+    /// True if the value is below zero.
+    pub const fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    /// Adds two values, returning `None` on overflow.
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    /// Subtracts `rhs`, returning `None` on overflow.
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self)
+    }
+
+    /// Multiplies two values, returning `None` on overflow.
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let magnitude = U256::from(self.0.unsigned_abs()) * U256::from(rhs.0.unsigned_abs())
+            / U256::from(SCALE);
+        Self::from_magnitude(magnitude, self.is_negative() != rhs.is_negative())
+    }
+
+    /// Divides by `rhs`, returning `None` if `rhs` is zero or the quotient overflows.
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        if rhs.0 == 0 {
+            return None;
+        }
+        let magnitude = U256::from(self.0.unsigned_abs()) * U256::from(SCALE)
+            / U256::from(rhs.0.unsigned_abs());
+        Self::from_magnitude(magnitude, self.is_negative() != rhs.is_negative())
+    }
+
+    /// Negates the value, returning `None` for `i128::MIN` raw.
+    pub fn checked_neg(self) -> Option<Self> {
+        self.0.checked_neg().map(Self)
+    }
+
+    /// Returns the absolute value, or `None` for `i128::MIN` raw.
+    pub fn checked_abs(self) -> Option<Self> {
+        self.0.checked_abs().map(Self)
+    }
+
+    /// Applies a sign to a magnitude, returning `None` if the result is out of range.
+    fn from_magnitude(magnitude: U256, negative: bool) -> Option<Self> {
+        let limit = if negative {
+            i128::MIN.unsigned_abs()
+        } else {
+            i128::MAX as u128
+        };
+        if magnitude > U256::from(limit) {
+            return None;
+        }
+        let magnitude = magnitude.as_u128() as i128;
+        Some(Self::from_raw(if negative {
+            magnitude.wrapping_neg()
+        } else {
+            magnitude
+        }))
+    }
+}
+
+/// Formats the raw, scaled value, as logs report it next to its scale.
+impl<const SCALE: u128> fmt::Display for FixedI128<SCALE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
@@ -1,91 +1,204 @@
 //! Calculates Impermanent Loss (IL) percentage for a liquidity position.
-//! This implementation uses fixed-point arithmetic with u128/i128 and U256 for on-chain compatibility.
 //!
-//! The calculation is based on the formula: IL = (2 * sqrt(k)) / (1 + k) - 1, where k = P_current / P_initial.
-//! This is equivalent to: IL = -(sqrt(k) - 1)^2 / (sqrt(k)^2 + 1).
-//! We use sqrt(k) = S_current / S_initial, where S is the square root price (sqrt_price_q64).
+//! A concentrated liquidity position on `[tick_lower, tick_upper)` holds
+//! `L * (1/S - 1/S_upper)` of token0 and `L * (S - S_lower)` of token1 at a sqrt price
+//! `S` clamped to the range, so below the range it is all token0 and above it all
+//! token1. Its IL is the value of the position at the current price against the value of
+//! holding the tokens it held at entry instead, both in token1:
+//! IL = V_position / V_hold - 1.
 //!
-//! The output is an i128 representing the IL percentage scaled by `IL_PERCENTAGE_SCALE`.
-//! E.g., a return value of -50_000_000 means -5% IL if `IL_PERCENTAGE_SCALE` is 10^9.
-use anchor_lang::prelude::*;
-// Assuming AmmPositionData is a simplified struct mirroring necessary fields
-// from amm_core::PositionData for IL calculation.
-// Or, you pass the amm_core::PositionData account directly.
+//! For a full range position this reduces to the classic IL = (2 * sqrt(k)) / (1 + k) - 1,
+//! where k = P_current / P_initial. Narrower ranges amplify it while the price stays in
+//! range, and once the price has left the range the position stops converting and the
+//! loss stops growing relative to holding.
+//!
+//! The output is an `IlPercentage`, the IL percentage scaled by `IL_PERCENTAGE_SCALE`.
+//! E.g., a raw value of -5_000_000_000 means -5% IL if `IL_PERCENTAGE_SCALE` is 10^9.
 use amm_core::math as amm_math;
-use primitive_types::U256; // For U256 operations
+use amm_core::position::PositionData as AmmPositionData;
+use anchor_lang::prelude::*;
+use primitive_types::U256;
+
+use crate::errors::RiskEngineError;
+use crate::fixed_point::FixedI128;
 /// Scaling factor for the final IL percentage result.
 /// A value of 10^9 means 9 decimal places of precision for the percentage.
 pub(crate) const IL_PERCENTAGE_SCALE: u128 = 1_000_000_000; // 10^9
 
-// Simplified IL calculation based on Section 3.1 & 3.2.1
-// This is a conceptual guide; actual implementation needs careful fixed-point math.
-// For MVP, we might focus on the percentage IL.
+/// An IL percentage, e.g. -5.0 for -5% IL.
+pub type IlPercentage = FixedI128<IL_PERCENTAGE_SCALE>;
+
+/// Liquidity of the position whose token amounts are valued. IL is a ratio of values, so
+/// it does not depend on the liquidity; 2^64 keeps the amounts precise at any price.
+const NOMINAL_LIQUIDITY: u128 = 1 << 64;
+
+/// IL percentage of a position on `[position_tick_lower, position_tick_upper)` entered at
+/// `position_entry_sqrt_price_q64`, at `current_sqrt_price_q64`.
+///
+/// The token amounts are those of the position at each sqrt price, per
+/// `amm_core::math::get_amount_0_delta` and `get_amount_1_delta`, so a position entered or
+/// valued outside its range is fully in one token. The result is zero or negative, up to
+/// rounding, and zero if either price is zero.
+///
+/// Fails with `CalculationError` if `position_tick_lower` is not below
+/// `position_tick_upper`.
 pub fn calculate_current_il_percentage(
     position_tick_lower: i32,
     position_tick_upper: i32,
     position_entry_sqrt_price_q64: u128, // Sqrt price when position was entered/last rebalanced
     current_sqrt_price_q64: u128,
-) -> Result<i128> {
-    // Return scaled 0 if initial price was zero or current price is zero.
-    // If initial price is zero, the ratio is undefined.
-    // If current price is zero, sqrt(k) is zero, IL is (0/1) - 1 = -1 (-100%).
-    // However, a zero price is often an invalid state for IL calculation context.
-    // Let's return 0 scaled for simplicity in these edge cases, matching the original f64 0.0.
-    if position_entry_sqrt_price_q64 == 0 {
-        return Ok(0);
+) -> Result<IlPercentage> {
+    // If either price is zero, the ratio is undefined.
+    if position_entry_sqrt_price_q64 == 0 || current_sqrt_price_q64 == 0 {
+        return Ok(IlPercentage::ZERO);
     }
+    if position_tick_lower >= position_tick_upper {
+        return err!(RiskEngineError::CalculationError);
+    }
+    let sqrt_price_lower_q64 = amm_math::tick_to_sqrt_price_q64(position_tick_lower)?;
+    let sqrt_price_upper_q64 = amm_math::tick_to_sqrt_price_q64(position_tick_upper)?;
 
-    // Check if current price tick is within the position range.
-    // If outside, IL calculation is different (comparing value of assets if held vs one-sided LP).
-    // For simplicity, we return 0 scaled if outside the range, matching the original f64 0.0 behavior.
-    // In a real system, you might calculate the actual out-of-range IL.
-    let p_current_tick = amm_math::sqrt_price_q64_to_tick(current_sqrt_price_q64)?;
-
-    if p_current_tick >= position_tick_lower && p_current_tick < position_tick_upper {
-        // Calculate IL using fixed-point arithmetic.
-        // Formula: IL = -(S_current - S_initial)^2 / (S_current^2 + S_initial^2)
-        // where S = sqrt_price_q64
-
-        let s_current_u256 = U256::from(current_sqrt_price_q64);
-        let s_initial_u256 = U256::from(position_entry_sqrt_price_q64);
+    let held_at_entry = position_amounts(
+        position_entry_sqrt_price_q64,
+        sqrt_price_lower_q64,
+        sqrt_price_upper_q64,
+    )?;
+    let held_now = position_amounts(
+        current_sqrt_price_q64,
+        sqrt_price_lower_q64,
+        sqrt_price_upper_q64,
+    )?;
+    let hold_value = value_over_sqrt_price(held_at_entry, current_sqrt_price_q64);
+    let position_value = value_over_sqrt_price(held_now, current_sqrt_price_q64);
+    if hold_value.is_zero() {
+        return Ok(IlPercentage::ZERO);
+    }
 
-        // Calculate (S_current - S_initial)^2
-        let diff_u256 = if s_current_u256 >= s_initial_u256 {
-            s_current_u256 - s_initial_u256
-        } else {
-            s_initial_u256 - s_current_u256
-        };
-        let diff_sq_u256 = diff_u256 * diff_u256;
+    // (V_position - V_hold) / V_hold * 100, scaled
+    let percent_scale = U256::from(100 * IL_PERCENTAGE_SCALE);
+    let il = if position_value >= hold_value {
+        i128::try_from((position_value - hold_value) * percent_scale / hold_value)
+    } else {
+        i128::try_from((hold_value - position_value) * percent_scale / hold_value).map(|il| -il)
+    }
+    .map_err(|_| error!(RiskEngineError::Overflow))?;
+    Ok(IlPercentage::from_raw(il))
+}
 
-        // Calculate S_current^2 + S_initial^2
-        let s_current_sq_u256 = s_current_u256 * s_current_u256;
-        let s_initial_sq_u256 = s_initial_u256 * s_initial_u256;
-        let denominator_u256 = s_current_sq_u256 + s_initial_sq_u256;
+/// IL percentage of `amm_position` at `current_sqrt_price_q64`, measured against the entry
+/// sqrt price recorded in the position account rather than one supplied by a caller.
+pub fn calculate_position_il_percentage(
+    amm_position: &AmmPositionData,
+    current_sqrt_price_q64: u128,
+) -> Result<IlPercentage> {
+    calculate_current_il_percentage(
+        amm_position.tick_lower_index,
+        amm_position.tick_upper_index,
+        amm_position.entry_sqrt_price_q64,
+        current_sqrt_price_q64,
+    )
+}
 
-        // Avoid division by zero. This should not happen if initial_sqrt_price is non-zero,
-        // as s_initial_sq_u256 will be non-zero.
-        if denominator_u256.is_zero() {
-            return Ok(0); // Should be caught by initial check, but defensive
-        }
+/// IL percentage of a position on `[tick_lower, tick_upper)` entered at
+/// `sqrt_price_history[entry_index]`, at each later price of the history.
+///
+/// `sqrt_price_history` holds Q64.64 sqrt prices, oldest first, such as the prices
+/// recorded in `PoolRiskState` converted with
+/// `circuit_breaker::scaled_price_to_sqrt_price_q64`. The result has one entry per price
+/// after `entry_index`. Fails with `InvalidEntryIndex` if `entry_index` is past the end of
+/// the history.
+pub fn calculate_historical_il(
+    sqrt_price_history: &[u128],
+    tick_lower: i32,
+    tick_upper: i32,
+    entry_index: usize,
+) -> Result<Vec<IlPercentage>> {
+    let Some(&entry_sqrt_price_q64) = sqrt_price_history.get(entry_index) else {
+        return err!(RiskEngineError::InvalidEntryIndex);
+    };
+    sqrt_price_history[entry_index + 1..]
+        .iter()
+        .map(|&sqrt_price_q64| {
+            calculate_current_il_percentage(
+                tick_lower,
+                tick_upper,
+                entry_sqrt_price_q64,
+                sqrt_price_q64,
+            )
+        })
+        .collect()
+}
 
-        // Calculate the ratio: diff_sq_u256 / denominator_u256
-        // Scale the numerator before division to maintain precision for the fractional part.
-        // We want the result scaled by 100 * IL_PERCENTAGE_SCALE for percentage.
-        let total_scale_u256 = U256::from(100) * U256::from(IL_PERCENTAGE_SCALE);
-        let numerator_scaled_u256 = diff_sq_u256 * total_scale_u256;
+/// Net P&L percentage of the same position as [`calculate_historical_il`], at each price
+/// after `entry_index`: its IL plus the fees it has earned since entry.
+///
+/// Fees are estimated per interval between consecutive prices. An interval that starts
+/// with the price in the range earns `fee_rate_bps` on a volume of
+/// `volume_to_liquidity_bps` basis points of the position's value; one that starts out of
+/// range earns nothing. Fails like [`calculate_historical_il`].
+pub fn calculate_cumulative_il(
+    sqrt_price_history: &[u128],
+    tick_lower: i32,
+    tick_upper: i32,
+    entry_index: usize,
+    fee_rate_bps: u16,
+    volume_to_liquidity_bps: u32,
+) -> Result<Vec<IlPercentage>> {
+    let historical_il =
+        calculate_historical_il(sqrt_price_history, tick_lower, tick_upper, entry_index)?;
+    // fee_rate_bps / 10^4 * volume_to_liquidity_bps / 10^4 * 100%, scaled
+    let fees_per_interval = IlPercentage::from_raw(
+        (fee_rate_bps as i128 * volume_to_liquidity_bps as i128 * IL_PERCENTAGE_SCALE as i128)
+            / 1_000_000,
+    );
 
-        let ratio_scaled_u256 = numerator_scaled_u256 / denominator_u256;
+    let mut fees_earned = IlPercentage::ZERO;
+    historical_il
+        .into_iter()
+        .zip(&sqrt_price_history[entry_index..])
+        .map(|(il, &interval_start_sqrt_price_q64)| {
+            let tick = amm_math::sqrt_price_q64_to_tick(interval_start_sqrt_price_q64)?;
+            if tick >= tick_lower && tick < tick_upper {
+                fees_earned = fees_earned
+                    .checked_add(fees_per_interval)
+                    .ok_or_else(|| error!(RiskEngineError::Overflow))?;
+            }
+            il.checked_add(fees_earned)
+                .ok_or_else(|| error!(RiskEngineError::Overflow))
+        })
+        .collect()
+}
 
-        // The formula result is -(ratio). Convert to i128 and negate.
-        // ratio_scaled_u256 is scaled by 100 * IL_PERCENTAGE_SCALE.
-        // The result fits in i128 because the maximum absolute value of IL is 1 (or 100%).
-        // So the maximum scaled value is 1 * 100 * IL_PERCENTAGE_SCALE, which fits in i128.
-        let il_percentage_scaled_abs_i128 = ratio_scaled_u256.as_u128() as i128;
+/// Token0 and token1 held by `NOMINAL_LIQUIDITY` on the sqrt price range
+/// `[sqrt_price_lower_q64, sqrt_price_upper_q64]` at `sqrt_price_q64`.
+fn position_amounts(
+    sqrt_price_q64: u128,
+    sqrt_price_lower_q64: u128,
+    sqrt_price_upper_q64: u128,
+) -> Result<(u128, u128)> {
+    let sqrt_price_q64 = sqrt_price_q64.clamp(sqrt_price_lower_q64, sqrt_price_upper_q64);
+    Ok((
+        amm_math::get_amount_0_delta(
+            sqrt_price_q64,
+            sqrt_price_upper_q64,
+            NOMINAL_LIQUIDITY,
+            false,
+        )?,
+        amm_math::get_amount_1_delta(
+            sqrt_price_lower_q64,
+            sqrt_price_q64,
+            NOMINAL_LIQUIDITY,
+            false,
+        )?,
+    ))
+}
 
-        Ok(-il_percentage_scaled_abs_i128)
-    } else {
-        // Position is out of range, IL calculation is different (value of assets if held vs one-sided LP)
-        // For MVP, can return 0 or a simplified out-of-range IL.
-        Ok(0) // Simplified for MVP
-    }
+/// Value of `(amount_0, amount_1)` in token1 at a non-zero `sqrt_price_q64`, divided by
+/// the sqrt price: `amount_0 * S + amount_1 / S`.
+///
+/// Dividing by `S` keeps the terms within a U256 across the whole sqrt price range, and
+/// does not change the ratio of two values at the same price.
+fn value_over_sqrt_price((amount_0, amount_1): (u128, u128), sqrt_price_q64: u128) -> U256 {
+    ((U256::from(amount_0) * U256::from(sqrt_price_q64)) >> 64)
+        + (U256::from(amount_1) << 64) / U256::from(sqrt_price_q64)
 }
@@ -0,0 +1,111 @@
+//! Bonds that keepers post to submit rebalances, and the check that slashes them.
+//!
+//! A keeper runs `trigger_rebalance_check` as its payer and passes its `KeeperBond`. Each
+//! rebalance it executes is recorded in the bond as a `KeeperSubmission`, along with the
+//! bond's size at the time. Until the submission's dispute window closes, the position's
+//! owner can compare the new range with the one it replaced at the pool's current oracle
+//! price, the counterfactual checkpoint. If the new range lost more to IL than the
+//! kept one would have, beyond `KEEPER_SLASH_TOLERANCE`, `slash_keeper` pays the owner a
+//! share of the bond. A bond cannot be withdrawn while any of its submissions can still be
+//! disputed.
+use crate::errors::RiskEngineError as ErrorCode;
+use crate::il_analyzer::{self, IlPercentage, IL_PERCENTAGE_SCALE};
+use anchor_lang::prelude::*;
+
+/// Bond, in lamports, a keeper must hold to have a rebalance recorded.
+pub const MIN_KEEPER_BOND_LAMPORTS: u64 = 100_000_000;
+
+/// How long, in seconds, the owner of a rebalanced position can dispute the rebalance.
+pub const KEEPER_DISPUTE_WINDOW_SECS: i64 = 86_400;
+
+/// Number of rebalances a bond can have within their dispute window at once.
+pub const MAX_OPEN_KEEPER_SUBMISSIONS: usize = 8;
+
+/// Share, in basis points, of the bond at submission time paid to the owner on a slash.
+pub const KEEPER_SLASH_BPS: u64 = 5_000;
+
+/// IL, as a percentage, by which the new range must trail the kept one for a slash:
+/// 0.5%.
+pub const KEEPER_SLASH_TOLERANCE: IlPercentage =
+    IlPercentage::from_raw(IL_PERCENTAGE_SCALE as i128 / 2);
+
+/// A rebalance executed by a keeper, kept in its `KeeperBond` for disputes.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct KeeperSubmission {
+    /// The amm_core position that was moved.
+    pub position: Pubkey,
+    pub old_tick_lower: i32,
+    pub old_tick_upper: i32,
+    pub new_tick_lower: i32,
+    pub new_tick_upper: i32,
+    /// The pool's reference sqrt price when the position was moved, in Q64.64.
+    pub entry_sqrt_price_q64: u128,
+    /// Unix timestamp of the rebalance.
+    pub submitted_at: i64,
+    /// Unix timestamp after which the rebalance can no longer be disputed. Zero for an
+    /// unused slot.
+    pub dispute_deadline: i64,
+    /// The keeper's bond, in lamports, when the rebalance was recorded.
+    pub bond_snapshot: u64,
+    /// Set once the keeper has been slashed for this rebalance.
+    pub slashed: bool,
+}
+
+impl KeeperSubmission {
+    /// Size of a serialized `KeeperSubmission`.
+    pub const LEN: usize = 32 // position
+        + 4 * 4 // ticks
+        + 16 // entry_sqrt_price_q64
+        + 8 // submitted_at
+        + 8 // dispute_deadline
+        + 8 // bond_snapshot
+        + 1; // slashed
+
+    /// True while the rebalance can be disputed at `now`.
+    pub fn is_open(&self, now: i64) -> bool {
+        now <= self.dispute_deadline
+    }
+
+    /// Lamports paid to the position owner if the keeper is slashed for this rebalance.
+    pub fn slash_amount(&self) -> u64 {
+        (self.bond_snapshot as u128 * KEEPER_SLASH_BPS as u128 / 10_000) as u64
+    }
+}
+
+/// How much more the new range lost to IL than the kept range would have, from the
+/// rebalance to `checkpoint_sqrt_price_q64`. Negative if the new range did better.
+pub fn rebalance_harm(
+    submission: &KeeperSubmission,
+    checkpoint_sqrt_price_q64: u128,
+) -> Result<IlPercentage> {
+    let new_il = il_analyzer::calculate_current_il_percentage(
+        submission.new_tick_lower,
+        submission.new_tick_upper,
+        submission.entry_sqrt_price_q64,
+        checkpoint_sqrt_price_q64,
+    )?;
+    let kept_il = il_analyzer::calculate_current_il_percentage(
+        submission.old_tick_lower,
+        submission.old_tick_upper,
+        submission.entry_sqrt_price_q64,
+        checkpoint_sqrt_price_q64,
+    )?;
+    kept_il
+        .checked_sub(new_il)
+        .ok_or_else(|| error!(ErrorCode::Overflow))
+}
+
+/// Emitted when `slash_keeper` pays a position owner out of a keeper's bond.
+#[event]
+pub struct KeeperSlashed {
+    /// The keeper whose bond was slashed.
+    pub keeper: Pubkey,
+    /// The position the disputed rebalance moved.
+    pub position: Pubkey,
+    /// The owner who disputed it and received `amount`.
+    pub owner: Pubkey,
+    /// Lamports moved from the bond to the owner.
+    pub amount: u64,
+    /// `rebalance_harm` at the checkpoint, scaled by `il_analyzer::IL_PERCENTAGE_SCALE`.
+    pub harm_scaled: i128,
+}
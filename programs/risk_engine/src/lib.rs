@@ -2,19 +2,39 @@
 use amm_core::position::PositionData as AmmPositionData;
 use amm_core::program::AmmCore; // To CPI to amm_core
 use amm_core::state::pool::Pool as AmmPool;
+use amm_core::state::token_pair::TokenPair;
 use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
 // use amm_core::tick::TickData as AmmTickData; // For CPI context if needed
 use amm_core::cpi;
-use amm_core::cpi::accounts::UpdatePosition as AmmUpdatePositionCtx; // For CPI // For cpi::update_position_handler
+use amm_core::cpi::accounts::UpdatePosition as AmmUpdatePositionCtx;
+use amm_core::flog; // For CPI // For cpi::update_position_handler
 
+pub mod circuit_breaker;
 pub mod errors;
+pub mod fixed_point;
 pub mod il_analyzer;
+pub mod keeper_bond;
+pub mod pool_characteristics;
 pub mod position_optimizer;
+pub mod pyth;
+#[cfg(feature = "simulation")]
+pub mod simulation;
+pub mod state;
 pub mod volatility_detector;
 
+#[cfg(test)]
+mod unit_test;
+
+use circuit_breaker::CircuitBreakerTripped;
 use errors::RiskEngineError;
+use keeper_bond::KeeperSlashed;
+use pyth::PriceUpdateV2;
+use state::{
+    KeeperBond, PoolCategory, PoolRiskState, PositionRiskState, RiskConfig, RiskConfigParams,
+};
 // Use the isqrt function from volatility_detector
-use volatility_detector::isqrt_u128;
+use volatility_detector::{isqrt_u128, TimeWeightedVolatility, VolatilityScore};
 
 /// Placeholder for price precision, e.g., 10^6 for 6 decimal places.
 const PRICE_SCALE_FACTOR: u128 = 1_000_000; // 6 decimal places
@@ -25,118 +45,128 @@ declare_id!("6wVb2AKyTcGE3x2xFjpPaDR1CE3q8LZZkHx3JvYrKNoa"); // Replace with you
 pub mod fluxa_risk_engine {
     use super::*;
 
+    /// Moves the position to the range proposed by the optimizer when the IL it saves is
+    /// worth the cost of the move, per `position_optimizer::should_rebalance`.
+    ///
+    /// The position's tokens are redeposited into the new range, and the part it cannot
+    /// use is returned to the owner's token accounts.
+    ///
+    /// The check first records a price sample for the pool: from the Pyth feed its
+    /// `TokenPair` names, if any, or else the pool's own price (see `sampled_sqrt_price_q64`).
+    ///
+    /// A keeper submitting the check as `payer` can pass its `KeeperBond`: an executed
+    /// rebalance is then recorded against the bond, and the position's owner can dispute
+    /// it with `slash_keeper` for `keeper_bond::KEEPER_DISPUTE_WINDOW_SECS`.
+    ///
+    /// Once the position's holder has initialized its `PositionRiskState`, every executed
+    /// rebalance counts against its per-epoch limit, and the check fails with
+    /// `RebalanceLimitReached` once the limit is used up.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing the accounts
+    /// * `allow_one_sided` - Accept proposed ranges that exclude the current price (e.g.
+    ///   range orders)
+    /// * `rebalance_cost_lamports` - Estimated cost of the rebalance: transaction fees and
+    ///   the cost of any swap needed to fund the new range
+    /// * `position_value_lamports` - Value of the position, in lamports
     pub fn trigger_rebalance_check(
         ctx: Context<TriggerRebalanceCheck>,
-        // We might need position_entry_sqrt_price if not stored in AmmPositionData
-        // For MVP, assume it's derivable or we use a fixed one for demo.
-        // For a real system, this would be tracked.
-        position_entry_sqrt_price_q64: u128,
+        allow_one_sided: bool,
+        rebalance_cost_lamports: u64,
+        position_value_lamports: u64,
     ) -> Result<()> {
-        let amm_position = &ctx.accounts.amm_position;
-        let amm_pool = &ctx.accounts.amm_pool;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let amm_pool_key = ctx.accounts.amm_pool.key();
 
-        // --- 1. Get Data ---
-        // For MVP, assume price history comes from oracle or is simulated for volatility.
-        // Let's use a placeholder for price history for the volatility calculation.
-        // Prices are scaled by PRICE_SCALE_FACTOR.
-        let placeholder_price_history: Vec<u128> = vec![
-            100 * PRICE_SCALE_FACTOR,
-            101 * PRICE_SCALE_FACTOR,
-            100 * PRICE_SCALE_FACTOR + 500_000, // 100.5
-            102 * PRICE_SCALE_FACTOR,
-            101 * PRICE_SCALE_FACTOR + 500_000, // 101.5
-            103 * PRICE_SCALE_FACTOR,
-            102 * PRICE_SCALE_FACTOR + 500_000, // 102.5
-            104 * PRICE_SCALE_FACTOR,
-            103 * PRICE_SCALE_FACTOR + 500_000, // 103.5
-            105 * PRICE_SCALE_FACTOR,
-            104 * PRICE_SCALE_FACTOR + 500_000, // 104.5
-            106 * PRICE_SCALE_FACTOR,
-            105 * PRICE_SCALE_FACTOR + 500_000, // 105.5
-            107 * PRICE_SCALE_FACTOR,
-            106 * PRICE_SCALE_FACTOR + 500_000, // 106.5
-            108 * PRICE_SCALE_FACTOR,
-            107 * PRICE_SCALE_FACTOR + 500_000, // 107.5
-            109 * PRICE_SCALE_FACTOR,
-            108 * PRICE_SCALE_FACTOR + 500_000, // 108.5
-            110 * PRICE_SCALE_FACTOR,
-        ]; // Needs at least `window_size` elements
-        let current_sqrt_price_q64 = amm_pool.sqrt_price_q64; // From the AMM pool state
-
-        // --- 2. Volatility Detection (Simplified) ---
-        let window_size = 10; // Example window size
-        let daily_volatility_scaled = volatility_detector::calculate_rolling_std_dev_volatility(
-            &placeholder_price_history, // Replace with actual price data source
-            window_size,
+        // --- 0. Circuit Breaker ---
+        // Refuse to chase the price while a previous extreme move keeps rebalancing halted.
+        ctx.accounts
+            .pool_risk_state
+            .ensure_rebalancing_allowed(now)?;
+        let decimals = ctx
+            .accounts
+            .token0_mint
+            .as_ref()
+            .zip(ctx.accounts.token1_mint.as_ref())
+            .map(|(token0_mint, token1_mint)| (token0_mint.decimals, token1_mint.decimals));
+        let sampled_sqrt_price_q64 = sampled_sqrt_price_q64(
+            &ctx.accounts.amm_pool,
+            &ctx.accounts.token_pair.oracle_feed,
+            ctx.accounts.pyth_price_feed.as_deref(),
+            decimals,
+            &clock,
+            ctx.accounts.risk_config.max_staleness_secs,
         )?;
-        // daily_volatility_scaled is scaled by volatility_detector::RETURN_SCALING_FACTOR
-
-        // Convert to annualized: annualized_vol = daily_vol * sqrt(365)
-        // All calculations in fixed point.
-        const DAYS_IN_YEAR_U128: u128 = 365;
-        // Using a precision scale for sqrt calculation intermediate step
-        const SQRT_PRECISION_SCALE: u128 = 1_000_000_000; // 10^9 for sqrt precision
-
-        let sqrt_365_scaled_for_calc =
-            isqrt_u128(DAYS_IN_YEAR_U128 * SQRT_PRECISION_SCALE * SQRT_PRECISION_SCALE);
-
-        // annualized_volatility_scaled will have the same scale as daily_volatility_scaled
-        // (i.e., volatility_detector::RETURN_SCALING_FACTOR)
-        let annualized_volatility_scaled =
-            (daily_volatility_scaled * sqrt_365_scaled_for_calc) / SQRT_PRECISION_SCALE;
-
-        msg!(
-            "Calculated Volatility (annualized, scaled by {}): {}",
-            volatility_detector::RETURN_SCALING_FACTOR,
-            annualized_volatility_scaled
-        );
-
-        // --- 3. IL Analysis (Basic) ---
-        let il_percentage = il_analyzer::calculate_current_il_percentage(
-            amm_position.tick_lower_index,
-            amm_position.tick_upper_index,
-            position_entry_sqrt_price_q64, // Sqrt price when position was opened
-            current_sqrt_price_q64,
-        )?;
-        // il_percentage is an i128 scaled by il_analyzer::IL_PERCENTAGE_SCALE
-        msg!(
-            "Current IL Percentage (scaled by {}): {}",
-            il_analyzer::IL_PERCENTAGE_SCALE,
-            il_percentage
-        );
+        if record_pool_price(
+            &mut ctx.accounts.pool_risk_state,
+            &ctx.accounts.risk_config,
+            amm_pool_key,
+            sampled_sqrt_price_q64,
+            now,
+        )? {
+            // Return Ok so the halt persists; the rebalance itself is skipped.
+            return Ok(());
+        }
 
-        // --- 4. Position Optimization (Simplified) ---
-        let (new_lower_tick, new_upper_tick) =
-            position_optimizer::calculate_optimal_boundaries_mvp(
-                current_sqrt_price_q64,
-                annualized_volatility_scaled, // Pass annualized volatility, scaled by VOLATILITY_INPUT_SCALE
-                amm_pool.tick_spacing,
-            )?;
-        msg!(
-            "Proposed new boundaries: Lower Tick {}, Upper Tick {}",
-            new_lower_tick,
-            new_upper_tick
-        );
+        let amm_position = &ctx.accounts.amm_position;
+        let amm_pool = &ctx.accounts.amm_pool;
+        let RebalanceProposal {
+            tick_lower: new_lower_tick,
+            tick_upper: new_upper_tick,
+            il_percentage,
+        } = propose_rebalance(amm_pool, amm_position, &ctx.accounts.pool_risk_state)?;
 
-        // --- 5. Rebalance Decision (MVP: Rebalance if different and IL is negative) ---
+        // --- 5. Rebalance Decision: only if the IL saved pays for the move ---
         let old_lower_tick = amm_position.tick_lower_index;
         let old_upper_tick = amm_position.tick_upper_index;
 
         if new_lower_tick != old_lower_tick || new_upper_tick != old_upper_tick {
-            // For MVP, let's add a simple condition, e.g. rebalance if IL is negative.
-            // A real system would have a much more sophisticated cost/benefit analysis.
-            // -0.01% IL threshold, scaled:
-            // -0.01 / 100 * IL_PERCENTAGE_SCALE = -(IL_PERCENTAGE_SCALE / 10_000)
-            let il_threshold_scaled: i128 = -((il_analyzer::IL_PERCENTAGE_SCALE as i128) / 10_000);
-
-            if il_percentage < il_threshold_scaled {
-                msg!(
-                    "Rebalancing conditions met. IL (scaled by {}): {}, New Ticks: [{}, {}]",
-                    il_analyzer::IL_PERCENTAGE_SCALE,
-                    il_percentage,
-                    new_lower_tick,
-                    new_upper_tick
+            // A range that excludes the current price leaves the position inactive.
+            position_optimizer::validate_proposed_range(
+                new_lower_tick,
+                new_upper_tick,
+                amm_pool.current_tick,
+                allow_one_sided,
+            )?;
+
+            // The position re-enters at the current price, so its IL starts over from zero.
+            let rebalance_worthwhile = position_optimizer::should_rebalance(
+                il_percentage,
+                il_analyzer::IlPercentage::ZERO,
+                rebalance_cost_lamports,
+                position_value_lamports,
+            );
+
+            if rebalance_worthwhile && ctx.accounts.risk_config.shadow_mode {
+                // Shadow mode: the price sample above is kept, but the position is left as is.
+                flog!(
+                    info,
+                    "shadow_rebalance",
+                    position = ctx.accounts.amm_position.key(),
+                    il_percentage = il_percentage,
+                    tick_lower = new_lower_tick,
+                    tick_upper = new_upper_tick
                 );
+                emit!(ShadowRebalance {
+                    position: ctx.accounts.amm_position.key(),
+                    old_lower: old_lower_tick,
+                    old_upper: old_upper_tick,
+                    new_lower: new_lower_tick,
+                    new_upper: new_upper_tick,
+                    il_scaled: il_percentage.raw(),
+                });
+            } else if rebalance_worthwhile {
+                flog!(
+                    info,
+                    "rebalance_triggered",
+                    position = ctx.accounts.amm_position.key(),
+                    il_percentage = il_percentage,
+                    tick_lower = new_lower_tick,
+                    tick_upper = new_upper_tick
+                );
+
+                record_position_rebalance(&ctx.accounts.position_risk_state, clock.epoch)?;
 
                 // --- 6. CPI to amm_core to update position ---
                 let cpi_program = ctx.accounts.amm_core_program.to_account_info();
@@ -147,33 +177,716 @@ pub mod fluxa_risk_engine {
                     old_tick_upper: ctx.accounts.amm_old_tick_upper.to_account_info(),
                     new_tick_lower: ctx.accounts.amm_new_tick_lower.to_account_info(),
                     new_tick_upper: ctx.accounts.amm_new_tick_upper.to_account_info(),
+                    token0_vault: ctx.accounts.amm_token0_vault.to_account_info(),
+                    token1_vault: ctx.accounts.amm_token1_vault.to_account_info(),
+                    owner_token0_account: ctx.accounts.owner_token0_account.to_account_info(),
+                    owner_token1_account: ctx.accounts.owner_token1_account.to_account_info(),
                     owner: ctx.accounts.owner.to_account_info(), // Risk engine is the authority
                     payer: ctx.accounts.payer.to_account_info(),
                     system_program: ctx.accounts.system_program.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
                     rent: ctx.accounts.rent.to_account_info(),
+                    position_token_account: ctx
+                        .accounts
+                        .amm_position_token_account
+                        .as_ref()
+                        .map(|account| account.to_account_info()),
                 };
 
                 // Derive PDA signer seeds if risk engine is the authority
                 // For MVP, owner is signer, so no PDA seeds needed here for CPI authority.
 
+                // No redeposit minimums: the new range is sized from the pool's price, and
+                // the circuit breaker above refuses to act on an extreme move of it.
                 cpi::update_position_handler(
                     CpiContext::new(cpi_program, cpi_accounts),
                     new_lower_tick,
                     new_upper_tick,
+                    0,
+                    0,
                 )?;
-                msg!("Position rebalanced in AMM Core.");
+                emit!(RebalanceEvent {
+                    position: ctx.accounts.amm_position.key(),
+                    old_lower: old_lower_tick,
+                    old_upper: old_upper_tick,
+                    new_lower: new_lower_tick,
+                    new_upper: new_upper_tick,
+                    il_scaled: il_percentage.raw(),
+                });
+                flog!(
+                    info,
+                    "position_rebalanced",
+                    position = ctx.accounts.amm_position.key()
+                );
+
+                // --- 7. Keeper accountability ---
+                let position = ctx.accounts.amm_position.key();
+                if let Some(keeper_bond) = ctx.accounts.keeper_bond.as_mut() {
+                    let dispute_deadline = keeper_bond.record_submission(
+                        position,
+                        (old_lower_tick, old_upper_tick),
+                        (new_lower_tick, new_upper_tick),
+                        sampled_sqrt_price_q64,
+                        now,
+                    )?;
+                    flog!(
+                        info,
+                        "keeper_rebalance_recorded",
+                        keeper = keeper_bond.keeper,
+                        position = position,
+                        dispute_deadline = dispute_deadline
+                    );
+                }
             } else {
-                msg!(
-                    "Rebalance not beneficial or IL not significant enough for MVP. IL (scaled by {}): {}",
-                    il_analyzer::IL_PERCENTAGE_SCALE, il_percentage
+                flog!(
+                    error,
+                    "rebalance_not_beneficial",
+                    position = ctx.accounts.amm_position.key(),
+                    il_percentage = il_percentage,
+                    rebalance_cost = rebalance_cost_lamports,
+                    position_value = position_value_lamports
                 );
                 return Err(RiskEngineError::RebalanceNotBeneficialMvp.into());
             }
         } else {
-            msg!("No change in optimal boundaries. No rebalance needed.");
+            flog!(
+                info,
+                "rebalance_not_needed",
+                position = ctx.accounts.amm_position.key()
+            );
+        }
+        Ok(())
+    }
+
+    /// Computes the range `trigger_rebalance_check` would move the position to, without
+    /// executing it. Works while the circuit breaker is tripped.
+    ///
+    /// Both measure volatility over the prices recorded for the pool with
+    /// `record_price_observation` or earlier checks. Until `VOLATILITY_WINDOW` prices have
+    /// been recorded, the measurement is blended with the pool's volatility prior.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing the accounts
+    /// * `allow_one_sided` - Accept proposed ranges that exclude the current price
+    pub fn preview_rebalance(
+        ctx: Context<PreviewRebalance>,
+        allow_one_sided: bool,
+    ) -> Result<RebalancePreview> {
+        let amm_position = &ctx.accounts.amm_position;
+        let amm_pool = &ctx.accounts.amm_pool;
+        let proposal = propose_rebalance(amm_pool, amm_position, &ctx.accounts.pool_risk_state)?;
+
+        let rebalance_needed = proposal.tick_lower != amm_position.tick_lower_index
+            || proposal.tick_upper != amm_position.tick_upper_index;
+        if rebalance_needed {
+            position_optimizer::validate_proposed_range(
+                proposal.tick_lower,
+                proposal.tick_upper,
+                amm_pool.current_tick,
+                allow_one_sided,
+            )?;
         }
+
+        let rebalancing_halted_until = ctx.accounts.pool_risk_state.rebalancing_halted_until;
+        flog!(
+            info,
+            "rebalance_previewed",
+            position = amm_position.key(),
+            tick_lower = proposal.tick_lower,
+            tick_upper = proposal.tick_upper,
+            halted_until = rebalancing_halted_until
+        );
+        Ok(RebalancePreview {
+            tick_lower: proposal.tick_lower,
+            tick_upper: proposal.tick_upper,
+            il_percentage: proposal.il_percentage.raw(),
+            rebalance_needed,
+            rebalancing_halted_until,
+        })
+    }
+
+    /// Creates the global risk configuration. The signer becomes the governance authority.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing the accounts
+    /// * `params` - Circuit breaker thresholds and windows
+    pub fn initialize_risk_config(
+        ctx: Context<InitializeRiskConfig>,
+        params: RiskConfigParams,
+    ) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        ctx.accounts
+            .risk_config
+            .initialize(ctx.bumps.risk_config, authority, params)
+    }
+
+    /// Creates the risk state tracking an amm_core pool.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing the accounts
+    /// * `category` - The kind of market the pool trades, which picks the volatility prior
+    ///   from `RiskConfig` that ranges are sized with until prices have been recorded
+    pub fn initialize_pool_risk_state(
+        ctx: Context<InitializePoolRiskState>,
+        category: PoolCategory,
+    ) -> Result<()> {
+        let pool = ctx.accounts.amm_pool.key();
+        let volatility_prior_bps = ctx.accounts.risk_config.volatility_prior_bps(category);
+        ctx.accounts.pool_risk_state.initialize(
+            ctx.bumps.pool_risk_state,
+            pool,
+            volatility_prior_bps,
+        );
+        flog!(
+            info,
+            "pool_risk_state_initialized",
+            pool = pool,
+            volatility_prior_bps = volatility_prior_bps
+        );
+        Ok(())
+    }
+
+    /// Creates the rebalance limits of a position, signed by its holder.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing the accounts
+    /// * `max_rebalances_per_epoch` - Most rebalances `trigger_rebalance_check` can execute
+    ///   on the position per epoch. Must be non-zero.
+    pub fn initialize_position_risk_state(
+        ctx: Context<InitializePositionRiskState>,
+        max_rebalances_per_epoch: u16,
+    ) -> Result<()> {
+        let position = ctx.accounts.amm_position.key();
+        ctx.accounts.position_risk_state.initialize(
+            ctx.bumps.position_risk_state,
+            position,
+            max_rebalances_per_epoch,
+        )?;
+        flog!(
+            info,
+            "position_risk_state_initialized",
+            position = position,
+            max_rebalances_per_epoch = max_rebalances_per_epoch
+        );
+        Ok(())
+    }
+
+    /// Changes how many rebalances a position allows per epoch, signed by its holder.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing the accounts
+    /// * `max_rebalances_per_epoch` - The new limit. Must be non-zero.
+    pub fn set_max_rebalances_per_epoch(
+        ctx: Context<SetMaxRebalancesPerEpoch>,
+        max_rebalances_per_epoch: u16,
+    ) -> Result<()> {
+        let position_risk_state = &mut ctx.accounts.position_risk_state;
+        position_risk_state.set_max_rebalances_per_epoch(max_rebalances_per_epoch)?;
+        flog!(
+            info,
+            "max_rebalances_per_epoch_set",
+            position = position_risk_state.position,
+            max_rebalances_per_epoch = max_rebalances_per_epoch
+        );
+        Ok(())
+    }
+
+    /// Records the pool's current price and evaluates the circuit breaker.
+    ///
+    /// The price is the pool's oracle price if it has one (see `reference_sqrt_price_q64`).
+    /// Permissionless, so keepers can keep the TWAP fresh between rebalance checks.
+    pub fn record_price_observation(ctx: Context<RecordPriceObservation>) -> Result<()> {
+        let clock = Clock::get()?;
+        record_pool_price(
+            &mut ctx.accounts.pool_risk_state,
+            &ctx.accounts.risk_config,
+            ctx.accounts.amm_pool.key(),
+            reference_sqrt_price_q64(&ctx.accounts.amm_pool, clock.slot)?,
+            clock.unix_timestamp,
+        )?;
+        Ok(())
+    }
+
+    /// Governance override that clears a tripped circuit breaker before it expires.
+    pub fn clear_circuit_breaker(ctx: Context<ClearCircuitBreaker>) -> Result<()> {
+        let pool_risk_state = &mut ctx.accounts.pool_risk_state;
+        flog!(
+            info,
+            "circuit_breaker_cleared",
+            pool = pool_risk_state.pool,
+            halted_until = pool_risk_state.rebalancing_halted_until
+        );
+        pool_risk_state.clear_halt();
+        Ok(())
+    }
+
+    /// Governance switch for shadow mode, in which `trigger_rebalance_check` runs every check
+    /// and records its price sample, but emits `ShadowRebalance` instead of moving the
+    /// position.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing the accounts
+    /// * `shadow_mode` - True to stop moving positions, false to resume
+    pub fn set_shadow_mode(ctx: Context<SetShadowMode>, shadow_mode: bool) -> Result<()> {
+        ctx.accounts.risk_config.shadow_mode = shadow_mode;
+        flog!(info, "shadow_mode_set", shadow_mode = shadow_mode);
+        Ok(())
+    }
+
+    /// Recomputes a pool's volatility from its recorded prices and returns it, scaled by
+    /// `volatility_detector::RETURN_SCALING_FACTOR`.
+    ///
+    /// Permissionless, like `record_price_observation`.
+    pub fn calculate_volatility(ctx: Context<CalculateVolatility>) -> Result<u128> {
+        let now = Clock::get()?.unix_timestamp;
+        let pool_risk_state = &mut ctx.accounts.pool_risk_state;
+        let volatility = pool_risk_state.update_volatility(now)?;
+        flog!(
+            debug,
+            "volatility_updated",
+            pool = pool_risk_state.pool,
+            volatility = volatility
+        );
+        Ok(volatility)
+    }
+
+    /// Recomputes the volatility of several pools at once and returns how many were updated.
+    ///
+    /// The `PoolRiskState` accounts are passed, writable, through `remaining_accounts`, at
+    /// most `volatility_detector::MAX_VOLATILITY_BATCH` of them. A pool that cannot be
+    /// updated, for instance one without enough recorded prices, is skipped and the others
+    /// are still updated, unless `require_all` is set, in which case the whole call fails.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context, with the pool risk states in `remaining_accounts`
+    /// * `require_all` - Fail the call if any pool cannot be updated
+    pub fn calculate_volatility_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CalculateVolatilityBatch<'info>>,
+        require_all: bool,
+    ) -> Result<u32> {
+        let now = Clock::get()?.unix_timestamp;
+        update_pool_volatilities(ctx.remaining_accounts, now, require_all)
+    }
+
+    /// Creates the signer's `KeeperBond`, empty, for `deposit_keeper_bond` to fund.
+    pub fn initialize_keeper_bond(ctx: Context<InitializeKeeperBond>) -> Result<()> {
+        let keeper = ctx.accounts.keeper.key();
+        ctx.accounts
+            .keeper_bond
+            .initialize(ctx.bumps.keeper_bond, keeper);
+        flog!(info, "keeper_bond_initialized", keeper = keeper);
+        Ok(())
+    }
+
+    /// Adds lamports from the keeper to its bond.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing the accounts
+    /// * `amount` - Lamports to deposit
+    pub fn deposit_keeper_bond(ctx: Context<DepositKeeperBond>, amount: u64) -> Result<()> {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.keeper.to_account_info(),
+                    to: ctx.accounts.keeper_bond.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        let keeper_bond = &mut ctx.accounts.keeper_bond;
+        keeper_bond.deposit(amount)?;
+        flog!(
+            info,
+            "keeper_bond_deposited",
+            keeper = keeper_bond.keeper,
+            amount = amount,
+            bond = keeper_bond.amount
+        );
+        Ok(())
+    }
+
+    /// Returns lamports from the bond to the keeper. Fails with `KeeperBondLocked` until
+    /// every rebalance recorded against the bond is past its dispute window.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing the accounts
+    /// * `amount` - Lamports to withdraw
+    pub fn withdraw_keeper_bond(ctx: Context<WithdrawKeeperBond>, amount: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.keeper_bond.withdraw(amount, now)?;
+        move_bonded_lamports(
+            &ctx.accounts.keeper_bond.to_account_info(),
+            &ctx.accounts.keeper.to_account_info(),
+            amount,
+        )?;
+        flog!(
+            info,
+            "keeper_bond_withdrawn",
+            keeper = ctx.accounts.keeper.key(),
+            amount = amount,
+            bond = ctx.accounts.keeper_bond.amount
+        );
         Ok(())
     }
+
+    /// Disputes the latest keeper rebalance of a position, paying its owner part of the
+    /// keeper's bond if the rebalance is proven harmful.
+    ///
+    /// The counterfactual checkpoint is the pool's oracle price at the dispute, which must
+    /// be fresh; a pool without one cannot be disputed. If, from the rebalance to that
+    /// price, the new range lost more to IL than the range it replaced would have, by more
+    /// than `keeper_bond::KEEPER_SLASH_TOLERANCE`,
+    /// `keeper_bond::KEEPER_SLASH_BPS` of the bond the keeper held at the rebalance moves
+    /// to the owner. Callable by the position's owner within the dispute window, once per
+    /// rebalance.
+    pub fn slash_keeper(ctx: Context<SlashKeeper>) -> Result<()> {
+        let clock = Clock::get()?;
+        let position = ctx.accounts.amm_position.key();
+        let checkpoint_sqrt_price_q64 =
+            checkpoint_sqrt_price_q64(&ctx.accounts.amm_pool, clock.slot)?;
+        let keeper_bond = &mut ctx.accounts.keeper_bond;
+        let index = keeper_bond.disputable_submission(&position, clock.unix_timestamp)?;
+        let (amount, harm) = keeper_bond.slash(index, checkpoint_sqrt_price_q64)?;
+        move_bonded_lamports(
+            &keeper_bond.to_account_info(),
+            &ctx.accounts.owner.to_account_info(),
+            amount,
+        )?;
+
+        flog!(
+            info,
+            "keeper_slashed",
+            keeper = keeper_bond.keeper,
+            position = position,
+            amount = amount,
+            harm = harm
+        );
+        emit!(KeeperSlashed {
+            keeper: keeper_bond.keeper,
+            position,
+            owner: ctx.accounts.owner.key(),
+            amount,
+            harm_scaled: harm.raw(),
+        });
+        Ok(())
+    }
+}
+
+/// Result of `preview_rebalance`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RebalancePreview {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    /// Scaled by `il_analyzer::IL_PERCENTAGE_SCALE`.
+    pub il_percentage: i128,
+    /// True if the proposed range differs from the position's current range.
+    pub rebalance_needed: bool,
+    /// Unix timestamp until which rebalancing is halted. Zero when not halted.
+    pub rebalancing_halted_until: i64,
+}
+
+/// Emitted when `trigger_rebalance_check` moves a position to a new range.
+#[event]
+pub struct RebalanceEvent {
+    /// The amm_core position that was moved.
+    pub position: Pubkey,
+    pub old_lower: i32,
+    pub old_upper: i32,
+    pub new_lower: i32,
+    pub new_upper: i32,
+    /// The position's IL percentage when it was moved, scaled by
+    /// `il_analyzer::IL_PERCENTAGE_SCALE`.
+    pub il_scaled: i128,
+}
+
+/// Emitted instead of `RebalanceEvent` when `trigger_rebalance_check` would have moved a
+/// position while `RiskConfig::shadow_mode` is set.
+#[event]
+pub struct ShadowRebalance {
+    /// The amm_core position that would have been moved.
+    pub position: Pubkey,
+    pub old_lower: i32,
+    pub old_upper: i32,
+    pub new_lower: i32,
+    pub new_upper: i32,
+    /// The position's IL percentage, scaled by `il_analyzer::IL_PERCENTAGE_SCALE`.
+    pub il_scaled: i128,
+}
+
+/// The sqrt price the circuit breaker samples for `amm_pool`.
+///
+/// This is the pool's oracle price, which cannot be moved by trading in the same
+/// transaction, falling back to the spot price for pools whose oracle authority never
+/// published one. A stale oracle price fails with `OraclePriceStale` rather than falling back.
+pub(crate) fn reference_sqrt_price_q64(amm_pool: &AmmPool, current_slot: u64) -> Result<u128> {
+    Ok(amm_pool
+        .oracle_sqrt_price(current_slot)?
+        .unwrap_or(amm_pool.sqrt_price_q64))
+}
+
+/// The sqrt price `slash_keeper` measures a disputed rebalance against.
+///
+/// Only the pool's oracle price is used: the spot price could be moved by trading in the
+/// dispute's own transaction. Fails with `CheckpointPriceUnavailable` if the pool has no
+/// oracle price and with `OraclePriceStale` if it is stale.
+pub(crate) fn checkpoint_sqrt_price_q64(amm_pool: &AmmPool, current_slot: u64) -> Result<u128> {
+    amm_pool
+        .oracle_sqrt_price(current_slot)?
+        .ok_or_else(|| RiskEngineError::CheckpointPriceUnavailable.into())
+}
+
+/// Counts an executed rebalance against `position_risk_state`, the position's
+/// `PositionRiskState` PDA.
+///
+/// The PDA holds no data until the position's holder initializes it, and until then the
+/// position has no limits. Once initialized, the account is owned by this program and its
+/// limit is enforced.
+pub(crate) fn record_position_rebalance(
+    position_risk_state: &AccountInfo,
+    epoch: u64,
+) -> Result<()> {
+    if position_risk_state.data_is_empty() {
+        return Ok(());
+    }
+    require_keys_eq!(
+        *position_risk_state.owner,
+        crate::ID,
+        anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram
+    );
+    let mut data = position_risk_state.try_borrow_mut_data()?;
+    let mut state = PositionRiskState::try_deserialize(&mut &data[..])?;
+    state.record_rebalance(epoch)?;
+    state.try_serialize(&mut &mut data[..])
+}
+
+/// The sqrt price `trigger_rebalance_check` samples for `amm_pool`.
+///
+/// A pool whose `TokenPair` names an `oracle_feed` is sampled from that Pyth feed: its
+/// price update and the decimals of the pool's mints must be passed, and the price must be
+/// no older than `max_staleness_secs` (see `pyth::PriceUpdateV2::price_no_older_than`).
+/// Other pools are sampled at `reference_sqrt_price_q64`.
+pub(crate) fn sampled_sqrt_price_q64(
+    amm_pool: &AmmPool,
+    oracle_feed: &Pubkey,
+    price_update: Option<&PriceUpdateV2>,
+    decimals: Option<(u8, u8)>,
+    clock: &Clock,
+    max_staleness_secs: i64,
+) -> Result<u128> {
+    if *oracle_feed == Pubkey::default() {
+        return reference_sqrt_price_q64(amm_pool, clock.slot);
+    }
+    let (Some(price_update), Some((token0_decimals, token1_decimals))) = (price_update, decimals)
+    else {
+        return err!(RiskEngineError::MissingPythPriceFeed);
+    };
+    price_update
+        .price_no_older_than(
+            clock.unix_timestamp,
+            max_staleness_secs,
+            &oracle_feed.to_bytes(),
+        )?
+        .sqrt_price_q64(token0_decimals, token1_decimals)
+}
+
+/// Records a price sample for `pool` and emits `CircuitBreakerTripped` if it trips the breaker.
+///
+/// Returns true if the breaker tripped.
+fn record_pool_price(
+    pool_risk_state: &mut PoolRiskState,
+    risk_config: &RiskConfig,
+    pool: Pubkey,
+    sqrt_price_q64: u128,
+    now: i64,
+) -> Result<bool> {
+    let price =
+        circuit_breaker::sqrt_price_q64_to_scaled_price(sqrt_price_q64, PRICE_SCALE_FACTOR)?;
+    let Some(trip) = pool_risk_state.record_price(now, price, risk_config)? else {
+        return Ok(false);
+    };
+
+    flog!(
+        error,
+        "circuit_breaker_tripped",
+        pool = pool,
+        move_bps = trip.move_bps,
+        halted_until = trip.halted_until
+    );
+    emit!(CircuitBreakerTripped {
+        pool,
+        reference_twap: trip.reference_twap,
+        current_twap: trip.current_twap,
+        move_bps: trip.move_bps,
+        halted_until: trip.halted_until,
+    });
+    Ok(true)
+}
+
+/// Moves `amount` bonded lamports out of a `KeeperBond` account, which the risk engine
+/// owns, to `recipient`.
+fn move_bonded_lamports(
+    keeper_bond: &AccountInfo,
+    recipient: &AccountInfo,
+    amount: u64,
+) -> Result<()> {
+    let mut bond_lamports = keeper_bond.try_borrow_mut_lamports()?;
+    **bond_lamports = bond_lamports
+        .checked_sub(amount)
+        .ok_or(RiskEngineError::InsufficientKeeperBond)?;
+    let mut recipient_lamports = recipient.try_borrow_mut_lamports()?;
+    **recipient_lamports = recipient_lamports
+        .checked_add(amount)
+        .ok_or(RiskEngineError::Overflow)?;
+    Ok(())
+}
+
+/// Updates the volatility of every `PoolRiskState` in `accounts` and returns how many were
+/// updated. Failures are logged and skipped unless `require_all` is set.
+pub(crate) fn update_pool_volatilities<'info>(
+    accounts: &'info [AccountInfo<'info>],
+    now: i64,
+    require_all: bool,
+) -> Result<u32> {
+    require!(
+        accounts.len() <= volatility_detector::MAX_VOLATILITY_BATCH,
+        RiskEngineError::BatchTooLarge
+    );
+    let mut updated = 0;
+    for account in accounts {
+        match update_pool_volatility(account, now) {
+            Ok(volatility) => {
+                updated += 1;
+                flog!(
+                    debug,
+                    "volatility_updated",
+                    pool_risk_state = account.key(),
+                    volatility = volatility
+                );
+            }
+            Err(error) if !require_all => {
+                flog!(
+                    error,
+                    "volatility_update_skipped",
+                    pool_risk_state = account.key(),
+                    code = u64::from(ProgramError::from(error))
+                );
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(updated)
+}
+
+/// Updates the volatility of the `PoolRiskState` in `account` and writes it back.
+fn update_pool_volatility<'info>(account: &'info AccountInfo<'info>, now: i64) -> Result<u128> {
+    if !account.is_writable {
+        return err!(anchor_lang::error::ErrorCode::ConstraintMut);
+    }
+    let mut pool_risk_state = Account::<'info, PoolRiskState>::try_from(account)?;
+    let volatility = pool_risk_state.update_volatility(now)?;
+    pool_risk_state.exit(&crate::ID)?;
+    Ok(volatility)
+}
+
+/// Range proposed by the optimizer for a position, along with its current IL.
+pub(crate) struct RebalanceProposal {
+    pub(crate) tick_lower: i32,
+    pub(crate) tick_upper: i32,
+    pub(crate) il_percentage: il_analyzer::IlPercentage,
+}
+
+/// Runs volatility detection, IL analysis and position optimization for `amm_position`.
+///
+/// Volatility is measured over the latest `VOLATILITY_WINDOW` prices recorded in
+/// `pool_risk_state`, each return weighted by the time between its prices. Until that many
+/// have been recorded, the measurement is blended with the pool's volatility prior,
+/// weighted by the number of returns it is based on, so a new pool's range narrows
+/// smoothly from the prior's to the measured one. IL is measured from the entry price
+/// recorded in the position.
+pub(crate) fn propose_rebalance(
+    amm_pool: &AmmPool,
+    amm_position: &AmmPositionData,
+    pool_risk_state: &PoolRiskState,
+) -> Result<RebalanceProposal> {
+    // --- 1. Get Data ---
+    // The prices recorded for the pool, the latest being the sample taken by this check.
+    // They are scaled by PRICE_SCALE_FACTOR.
+    let observations = pool_risk_state.observations_chronological();
+    let current_sqrt_price_q64 = amm_pool.sqrt_price_q64; // From the AMM pool state
+
+    // --- 2. Volatility Detection (Simplified) ---
+    // Returns between recorded prices are normalized to daily returns by their time delta.
+    let measured = volatility_detector::calculate_daily_volatility_score(&observations)?;
+    let daily_volatility_scaled = measured.volatility;
+    // daily_volatility_scaled is scaled by volatility_detector::RETURN_SCALING_FACTOR
+
+    // Convert to annualized: annualized_vol = daily_vol * sqrt(365)
+    // All calculations in fixed point.
+    const DAYS_IN_YEAR_U128: u128 = 365;
+    // Using a precision scale for sqrt calculation intermediate step
+    const SQRT_PRECISION_SCALE: u128 = 1_000_000_000; // 10^9 for sqrt precision
+
+    let sqrt_365_scaled_for_calc =
+        isqrt_u128(DAYS_IN_YEAR_U128 * SQRT_PRECISION_SCALE * SQRT_PRECISION_SCALE);
+
+    // annualized_volatility_scaled will have the same scale as daily_volatility_scaled
+    // (i.e., volatility_detector::RETURN_SCALING_FACTOR)
+    let measured_annualized_volatility_scaled =
+        (daily_volatility_scaled * sqrt_365_scaled_for_calc) / SQRT_PRECISION_SCALE;
+
+    // Lean on the prior for the part of the window not recorded yet.
+    let annualized_volatility_scaled = TimeWeightedVolatility::blend(
+        VolatilityScore {
+            volatility: measured_annualized_volatility_scaled,
+            confidence: measured.confidence,
+        },
+        VolatilityScore {
+            volatility: pool_risk_state.annualized_volatility_prior(),
+            confidence: 0,
+        },
+        measured.confidence as u64,
+    )
+    .volatility;
+
+    flog!(
+        debug,
+        "volatility_calculated",
+        annualized_volatility = annualized_volatility_scaled,
+        confidence = measured.confidence,
+        scale = volatility_detector::RETURN_SCALING_FACTOR
+    );
+
+    // --- 3. IL Analysis (Basic) ---
+    let il_percentage =
+        il_analyzer::calculate_position_il_percentage(amm_position, current_sqrt_price_q64)?;
+    flog!(
+        debug,
+        "il_calculated",
+        il_percentage = il_percentage,
+        scale = il_analyzer::IL_PERCENTAGE_SCALE
+    );
+
+    // --- 4. Position Optimization (Simplified) ---
+    let (tick_lower, tick_upper) = position_optimizer::calculate_optimal_boundaries_mvp(
+        current_sqrt_price_q64,
+        annualized_volatility_scaled, // Pass annualized volatility, scaled by VOLATILITY_INPUT_SCALE
+        position_optimizer::MVP_RANGE_Z_SCORE_BPS,
+        position_optimizer::MVP_RANGE_HORIZON_DAYS,
+        amm_pool.tick_spacing,
+    )?;
+    flog!(
+        debug,
+        "boundaries_proposed",
+        tick_lower = tick_lower,
+        tick_upper = tick_upper
+    );
+
+    Ok(RebalanceProposal {
+        tick_lower,
+        tick_upper,
+        il_percentage,
+    })
 }
 
 #[derive(Accounts)]
@@ -200,17 +913,54 @@ pub struct TriggerRebalanceCheck<'info> {
     /// CHECK: Account for new_tick_upper, validated by CPI to amm_core
     #[account(mut)]
     pub amm_new_tick_upper: UncheckedAccount<'info>,
+    /// CHECK: The pool's token0 vault, validated by CPI to amm_core
+    #[account(mut)]
+    pub amm_token0_vault: UncheckedAccount<'info>,
+    /// CHECK: The pool's token1 vault, validated by CPI to amm_core
+    #[account(mut)]
+    pub amm_token1_vault: UncheckedAccount<'info>,
+    /// CHECK: The owner's token0 account, receiving the token0 the new range cannot use.
+    /// Validated by CPI to amm_core
+    #[account(mut)]
+    pub owner_token0_account: UncheckedAccount<'info>,
+    /// CHECK: The owner's token1 account, receiving the token1 the new range cannot use.
+    /// Validated by CPI to amm_core
+    #[account(mut)]
+    pub owner_token1_account: UncheckedAccount<'info>,
+
+    // Oracle accounts
+    /// The pool's token pair, whose `oracle_feed`, if set, is the Pyth feed the check samples.
+    #[account(constraint = token_pair.key() == amm_pool.token_pair @ RiskEngineError::InvalidAmmCoreAccount)]
+    pub token_pair: Account<'info, TokenPair>,
+    /// Pyth price update of the pair's feed. Required when the pair names one.
+    pub pyth_price_feed: Option<Account<'info, PriceUpdateV2>>,
+    /// The pool's token0 mint, whose decimals scale the Pyth price. Required with
+    /// `pyth_price_feed`.
+    #[account(constraint = token0_mint.key() == amm_pool.token0_mint @ RiskEngineError::InvalidAmmCoreAccount)]
+    pub token0_mint: Option<Account<'info, Mint>>,
+    /// The pool's token1 mint. Required with `pyth_price_feed`.
+    #[account(constraint = token1_mint.key() == amm_pool.token1_mint @ RiskEngineError::InvalidAmmCoreAccount)]
+    pub token1_mint: Option<Account<'info, Mint>>,
 
-    // Oracle account (e.g., Pyth price feed)
-    // For MVP, this might be simplified or data passed directly.
-    // If used, ensure it's properly constrained (e.g., correct feed for the pool's tokens)
-    // pub pyth_price_feed: Account<'info, pyth_sdk_solana::Price>,
+    // Risk engine accounts
+    #[account(seeds = [b"risk_config"], bump = risk_config.bump)]
+    pub risk_config: Account<'info, RiskConfig>,
+    #[account(
+        mut,
+        seeds = [b"pool_risk_state", amm_pool.key().as_ref()],
+        bump = pool_risk_state.bump,
+    )]
+    pub pool_risk_state: Account<'info, PoolRiskState>,
 
     // Signer & Payer
     // For MVP, the position owner might be the one signing to trigger this.
     // In a more automated system, this could be a keeper bot or the risk engine's PDA.
-    #[account(mut, address = amm_position.owner @ RiskEngineError::PositionAccessDenied)]
-    // Ensure signer is the position owner
+    #[account(
+        mut,
+        constraint = amm_position.is_held_by(&owner.key(), amm_position_token_account.as_deref())
+            @ RiskEngineError::PositionAccessDenied
+    )]
+    // Ensure signer holds the position
     pub owner: Signer<'info>,
     #[account(mut)]
     pub payer: Signer<'info>, // To pay for CPI and potentially new tick accounts in AMM
@@ -218,5 +968,227 @@ pub struct TriggerRebalanceCheck<'info> {
     // Programs
     pub amm_core_program: Program<'info, AmmCore>, // CPI to amm_core
     pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
     pub rent: Sysvar<'info, Rent>,
+
+    /// The owner's token account holding the position NFT, passed on to amm_core. Only
+    /// needed for positions minted with `mint_position_nft`.
+    pub amm_position_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Bond of the keeper submitting the check as `payer`, which an executed rebalance
+    /// is recorded against.
+    #[account(
+        mut,
+        constraint = keeper_bond.keeper == payer.key() @ RiskEngineError::KeeperBondMismatch
+    )]
+    pub keeper_bond: Option<Account<'info, KeeperBond>>,
+
+    /// CHECK: The position's `PositionRiskState` PDA, which an executed rebalance counts
+    /// against. Required even before it is initialized, so that the limits cannot be
+    /// skipped by leaving it out; see `record_position_rebalance`.
+    #[account(
+        mut,
+        seeds = [b"position_risk_state", amm_position.key().as_ref()],
+        bump,
+    )]
+    pub position_risk_state: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PreviewRebalance<'info> {
+    #[account(constraint = amm_pool.key() == amm_position.pool @ RiskEngineError::InvalidAmmCoreAccount)]
+    pub amm_pool: Account<'info, AmmPool>,
+    pub amm_position: Account<'info, AmmPositionData>,
+    #[account(seeds = [b"pool_risk_state", amm_pool.key().as_ref()], bump = pool_risk_state.bump)]
+    pub pool_risk_state: Account<'info, PoolRiskState>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRiskConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = RiskConfig::LEN,
+        seeds = [b"risk_config"],
+        bump
+    )]
+    pub risk_config: Account<'info, RiskConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePoolRiskState<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = PoolRiskState::LEN,
+        seeds = [b"pool_risk_state", amm_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_risk_state: Account<'info, PoolRiskState>,
+    pub amm_pool: Account<'info, AmmPool>,
+    #[account(seeds = [b"risk_config"], bump = risk_config.bump)]
+    pub risk_config: Account<'info, RiskConfig>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePositionRiskState<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = PositionRiskState::LEN,
+        seeds = [b"position_risk_state", amm_position.key().as_ref()],
+        bump
+    )]
+    pub position_risk_state: Account<'info, PositionRiskState>,
+    pub amm_position: Account<'info, AmmPositionData>,
+    #[account(
+        mut,
+        constraint = amm_position.is_held_by(&owner.key(), amm_position_token_account.as_deref())
+            @ RiskEngineError::PositionAccessDenied
+    )]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// The owner's token account holding the position NFT. Only needed for positions
+    /// minted with `mint_position_nft`.
+    pub amm_position_token_account: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxRebalancesPerEpoch<'info> {
+    #[account(
+        mut,
+        seeds = [b"position_risk_state", amm_position.key().as_ref()],
+        bump = position_risk_state.bump,
+    )]
+    pub position_risk_state: Account<'info, PositionRiskState>,
+    pub amm_position: Account<'info, AmmPositionData>,
+    #[account(
+        constraint = amm_position.is_held_by(&owner.key(), amm_position_token_account.as_deref())
+            @ RiskEngineError::PositionAccessDenied
+    )]
+    pub owner: Signer<'info>,
+    /// The owner's token account holding the position NFT. Only needed for positions
+    /// minted with `mint_position_nft`.
+    pub amm_position_token_account: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct RecordPriceObservation<'info> {
+    #[account(seeds = [b"risk_config"], bump = risk_config.bump)]
+    pub risk_config: Account<'info, RiskConfig>,
+    #[account(
+        mut,
+        seeds = [b"pool_risk_state", amm_pool.key().as_ref()],
+        bump = pool_risk_state.bump,
+    )]
+    pub pool_risk_state: Account<'info, PoolRiskState>,
+    pub amm_pool: Account<'info, AmmPool>,
+}
+
+#[derive(Accounts)]
+pub struct ClearCircuitBreaker<'info> {
+    #[account(
+        seeds = [b"risk_config"],
+        bump = risk_config.bump,
+        has_one = authority @ RiskEngineError::Unauthorized
+    )]
+    pub risk_config: Account<'info, RiskConfig>,
+    #[account(mut)]
+    pub pool_risk_state: Account<'info, PoolRiskState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetShadowMode<'info> {
+    #[account(
+        mut,
+        seeds = [b"risk_config"],
+        bump = risk_config.bump,
+        has_one = authority @ RiskEngineError::Unauthorized
+    )]
+    pub risk_config: Account<'info, RiskConfig>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CalculateVolatility<'info> {
+    #[account(mut)]
+    pub pool_risk_state: Account<'info, PoolRiskState>,
+}
+
+#[derive(Accounts)]
+pub struct CalculateVolatilityBatch<'info> {
+    // The pool risk states are passed through `remaining_accounts`. The generated CPI
+    // client needs at least one account to carry the 'info lifetime.
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeKeeperBond<'info> {
+    #[account(
+        init,
+        payer = keeper,
+        space = KeeperBond::LEN,
+        seeds = [b"keeper_bond", keeper.key().as_ref()],
+        bump
+    )]
+    pub keeper_bond: Account<'info, KeeperBond>,
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositKeeperBond<'info> {
+    #[account(
+        mut,
+        seeds = [b"keeper_bond", keeper.key().as_ref()],
+        bump = keeper_bond.bump,
+    )]
+    pub keeper_bond: Account<'info, KeeperBond>,
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawKeeperBond<'info> {
+    #[account(
+        mut,
+        seeds = [b"keeper_bond", keeper.key().as_ref()],
+        bump = keeper_bond.bump,
+    )]
+    pub keeper_bond: Account<'info, KeeperBond>,
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SlashKeeper<'info> {
+    #[account(
+        mut,
+        seeds = [b"keeper_bond", keeper_bond.keeper.as_ref()],
+        bump = keeper_bond.bump,
+    )]
+    pub keeper_bond: Account<'info, KeeperBond>,
+    pub amm_position: Account<'info, AmmPositionData>,
+    /// The position's pool, whose oracle price is the counterfactual checkpoint.
+    #[account(constraint = amm_pool.key() == amm_position.pool @ RiskEngineError::InvalidAmmCoreAccount)]
+    pub amm_pool: Account<'info, AmmPool>,
+    /// The position's owner, who receives the slashed lamports.
+    #[account(
+        mut,
+        constraint = amm_position.is_held_by(&owner.key(), amm_position_token_account.as_deref())
+            @ RiskEngineError::PositionAccessDenied
+    )]
+    pub owner: Signer<'info>,
+    /// The owner's token account holding the position NFT. Only needed for positions
+    /// minted with `mint_position_nft`.
+    pub amm_position_token_account: Option<Account<'info, TokenAccount>>,
 }
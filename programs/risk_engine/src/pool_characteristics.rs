@@ -0,0 +1,33 @@
+//! Characteristics of an amm_core pool that risk thresholds depend on, read from the pool
+//! account itself.
+//!
+//! Taking them from the live account rather than from the caller means a keeper cannot
+//! size thresholds with stale or made-up figures: liquidity and fee tier are the pool's
+//! current ones, and the trading volume is the one its swaps have recorded for the day.
+use amm_core::state::pool::Pool as AmmPool;
+
+/// What the pool looks like at a given time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolCharacteristics {
+    /// Liquidity active at the pool's current price.
+    pub liquidity_depth: u128,
+    /// Fee rate the pool was created with, in basis points.
+    pub fee_tier: u16,
+    /// Token0 traded through the pool so far in the current daily window. See
+    /// `amm_core::constants::VOLUME_WINDOW_SECS`.
+    pub trading_volume_24h: u64,
+}
+
+impl PoolCharacteristics {
+    /// Reads the characteristics of `pool` at Unix timestamp `now`.
+    ///
+    /// The volume is zero if no swap has gone through the pool yet in the daily window
+    /// containing `now`, rather than the count of an earlier day still stored in it.
+    pub fn from_live_pool(pool: &AmmPool, now: i64) -> Self {
+        Self {
+            liquidity_depth: pool.liquidity,
+            fee_tier: pool.fee_tier,
+            trading_volume_24h: pool.volume_24h_at(now),
+        }
+    }
+}
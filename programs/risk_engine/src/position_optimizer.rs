@@ -1,7 +1,12 @@
 //! This module calculates optimal liquidity boundaries for a position.
 //! It uses fixed-point arithmetic throughout to avoid floating-point numbers.
+use crate::circuit_breaker::BPS_DENOMINATOR;
 use crate::errors::RiskEngineError as ErrorCode; // Assuming this is the correct path
-use amm_core::constants::{MAX_TICK, MIN_TICK}; // Assuming these are pub
+use crate::il_analyzer::{calculate_current_il_percentage, IlPercentage, IL_PERCENTAGE_SCALE};
+use amm_core::constants::{
+    FEE_TIER_HIGH, FEE_TIER_LOW, FEE_TIER_MEDIUM, MAX_SQRT_PRICE, MAX_TICK, MIN_TICK,
+}; // Assuming these are pub
+use amm_core::liquidity_histogram::LiquidityHistogram;
 use amm_core::math as amm_math;
 use anchor_lang::prelude::*; // For tick_to_sqrt_price_q64 and sqrt_price_q64_to_tick
 use primitive_types::U256;
@@ -14,16 +19,21 @@ const PRECISION_SCALE: u128 = 1_000_000_000_000;
 /// Assuming it's 10^9 as per volatility_detector.rs example.
 const VOLATILITY_INPUT_SCALE: u128 = 1_000_000_000;
 
-/// Alpha factor numerator for price range calculation (e.g., 1.5 = 3/2).
-const ALPHA_MVP_NUM: u128 = 3;
-/// Alpha factor denominator for price range calculation.
-const ALPHA_MVP_DEN: u128 = 2;
+/// Z-score of the range proposed for rebalances, in basis points (1.5).
+pub const MVP_RANGE_Z_SCORE_BPS: u32 = 15_000;
 
-/// Time horizon for range calculation, in days (numerator). E.g., 1 day.
-const TIME_HORIZON_DAYS_NUM: u128 = 1;
+/// Horizon of the range proposed for rebalances, in days.
+pub const MVP_RANGE_HORIZON_DAYS: u32 = 1;
 /// Time horizon for range calculation, days in a year (denominator). E.g., 365 days.
 const DAYS_IN_YEAR_DEN: u128 = 365;
 
+/// ln(1.0001), the log price step of one tick, scaled by `PRECISION_SCALE`.
+const LN_TICK_BASE_SCALED: u128 = 99_995_000;
+
+/// IL a rebalance must save on top of its cost, in basis points of the position's value,
+/// so that a position whose IL hovers around break-even is not moved back and forth.
+pub const REBALANCE_HYSTERESIS_BPS: u128 = 1;
+
 /// Calculates the integer square root of a u128 number using the Babylonian method.
 /// Returns floor(sqrt(n)).
 /// Note: In a larger project, this would ideally be in a shared math utility module.
@@ -46,35 +56,42 @@ fn isqrt_u128(n: u128) -> u128 {
     x
 }
 
-// Simplified version of Section 4.1.2 for MVP
-// Returns (new_lower_sqrt_price_q64, new_upper_sqrt_price_q64)
-pub fn calculate_optimal_boundaries_mvp(
-    current_sqrt_price_q64: u128,
-    volatility_annualized_scaled: u128, // e.g., 800_000_000 for 80% annualized vol if VOLATILITY_INPUT_SCALE is 10^9
-    pool_tick_spacing: u16,
-) -> Result<(i32, i32)> {
-    if current_sqrt_price_q64 == 0 {
-        return Ok((MIN_TICK, MAX_TICK)); // Default to full range or error
-    }
+/// Returns the relative price move expected over the optimizer's time horizon,
+/// alpha * sigma * sqrt(T) with alpha `MVP_RANGE_Z_SCORE_BPS` and T
+/// `MVP_RANGE_HORIZON_DAYS`, scaled by `PRECISION_SCALE`.
+fn expected_price_move_scaled(volatility_annualized_scaled: u128) -> Result<u128> {
+    let alpha_scaled = MVP_RANGE_Z_SCORE_BPS as u128 * PRECISION_SCALE / BPS_DENOMINATOR;
+    scaled_price_move(
+        alpha_scaled,
+        volatility_annualized_scaled,
+        MVP_RANGE_HORIZON_DAYS as u128,
+    )
+}
 
-    // Calculate price_range_factor = alpha * sigma * sqrt(T) using fixed-point arithmetic.
+/// Returns `k * sigma * sqrt(T)` scaled by `PRECISION_SCALE`, for a multiplier `k_scaled`
+/// scaled by `PRECISION_SCALE` and a horizon `T` of `horizon_days` days.
+fn scaled_price_move(
+    k_scaled: u128,
+    volatility_annualized_scaled: u128,
+    horizon_days: u128,
+) -> Result<u128> {
+    // Calculate price_range_factor = k * sigma * sqrt(T) using fixed-point arithmetic.
     // All components will be scaled by PRECISION_SCALE or VOLATILITY_INPUT_SCALE.
 
-    // alpha_scaled = (ALPHA_MVP_NUM / ALPHA_MVP_DEN) * PRECISION_SCALE
-    let alpha_scaled: u128 = (ALPHA_MVP_NUM * PRECISION_SCALE) / ALPHA_MVP_DEN;
-
     // sigma_scaled is volatility_annualized_scaled (input, scaled by VOLATILITY_INPUT_SCALE)
 
-    // sqrt_T_scaled = sqrt(TIME_HORIZON_DAYS_NUM / DAYS_IN_YEAR_DEN) * PRECISION_SCALE
+    // sqrt_T_scaled = sqrt(horizon_days / DAYS_IN_YEAR_DEN) * PRECISION_SCALE
     // sqrt(A/B) * S = sqrt(A*B*S*S) / B
-    let sqrt_t_numerator =
-        isqrt_u128(TIME_HORIZON_DAYS_NUM * DAYS_IN_YEAR_DEN * PRECISION_SCALE * PRECISION_SCALE);
+    let sqrt_t_numerator = isqrt_u128(
+        horizon_days
+            .checked_mul(DAYS_IN_YEAR_DEN * PRECISION_SCALE * PRECISION_SCALE)
+            .ok_or(ErrorCode::Overflow)?,
+    );
     let sqrt_t_scaled: u128 = sqrt_t_numerator / DAYS_IN_YEAR_DEN;
 
     // price_range_factor_numerator_u256 has scale: PRECISION_SCALE * VOLATILITY_INPUT_SCALE * PRECISION_SCALE
-    let price_range_factor_numerator_u256: U256 = U256::from(alpha_scaled)
-        * U256::from(volatility_annualized_scaled)
-        * U256::from(sqrt_t_scaled);
+    let price_range_factor_numerator_u256: U256 =
+        U256::from(k_scaled) * U256::from(volatility_annualized_scaled) * U256::from(sqrt_t_scaled);
 
     // We want price_range_factor_scaled to have scale: PRECISION_SCALE
     // So, divide by (VOLATILITY_INPUT_SCALE * PRECISION_SCALE)
@@ -86,70 +103,542 @@ pub fn calculate_optimal_boundaries_mvp(
         return Err(ErrorCode::CalculationError.into());
     }
 
-    let price_range_factor_scaled: u128 =
-        (price_range_factor_numerator_u256 / price_range_factor_denominator_u256).as_u128();
+    Ok((price_range_factor_numerator_u256 / price_range_factor_denominator_u256).as_u128())
+}
+
+/// Computes the range `[current_tick - h, current_tick + h]` the price is expected to stay
+/// in, with a half-width `h = k * sigma * sqrt(T)` in ticks: a z-score band of the log
+/// price, which moves by ln(1.0001) per tick.
+///
+/// `k_bps` is the z-score `k` in basis points, e.g. `MVP_RANGE_Z_SCORE_BPS` for 1.5, and
+/// `horizon_days` the horizon `T`. The half-width grows linearly with
+/// `volatility_annualized_scaled` (scaled by 10^9, e.g. 800_000_000 for 80%) and `k_bps`,
+/// and with the square root of `horizon_days`. The bounds are widened outward to multiples
+/// of `pool_tick_spacing` and clamped to the usable ticks within `[MIN_TICK, MAX_TICK]`,
+/// and the range is at least one tick spacing wide. A zero price gets the full range.
+///
+/// Fails with `CalculationError` if `pool_tick_spacing` is zero.
+pub fn calculate_optimal_boundaries_mvp(
+    current_sqrt_price_q64: u128,
+    volatility_annualized_scaled: u128,
+    k_bps: u32,
+    horizon_days: u32,
+    pool_tick_spacing: u16,
+) -> Result<(i32, i32)> {
+    if pool_tick_spacing == 0 {
+        return Err(ErrorCode::CalculationError.into());
+    }
+    let tick_range = MAX_TICK - MIN_TICK;
+    let boundary = if current_sqrt_price_q64 == 0 {
+        aligned_range(0, tick_range, pool_tick_spacing)?
+    } else {
+        let current_tick = amm_math::sqrt_price_q64_to_tick(current_sqrt_price_q64)?;
+        let k_scaled = k_bps as u128 * PRECISION_SCALE / BPS_DENOMINATOR;
+        let spread_ticks = spread_ticks(k_scaled, volatility_annualized_scaled, horizon_days)?;
+        aligned_range(current_tick, spread_ticks, pool_tick_spacing)?
+    };
+    Ok((boundary.tick_lower, boundary.tick_upper))
+}
+
+/// Range suggested by [`compute_optimal_range`] and [`compute_asymmetric_range`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceBoundary {
+    /// Lower tick of the range, a multiple of the tick spacing.
+    pub tick_lower: i32,
+    /// Upper tick of the range, a multiple of the tick spacing.
+    pub tick_upper: i32,
+    /// Sqrt price at `tick_lower`, in Q64.64.
+    pub sqrt_price_lower_q64: u128,
+    /// Sqrt price at `tick_upper`, in Q64.64.
+    pub sqrt_price_upper_q64: u128,
+}
+
+/// Computes a range centered on the current price that the price is expected to stay in
+/// over `time_horizon_days`.
+///
+/// Prices are modeled as log-normal, as in Black-Scholes, so the range spans `k * sigma *
+/// sqrt(T)` of log price on either side of the current price, which is the same number of
+/// ticks on either side. `k` grows with `fee_tier` (in hundredths of a basis point, like
+/// `amm_core`'s `FEE_TIER_*`): low-fee pools earn little per swap and need the liquidity
+/// concentrated, while high-fee pools can afford a wider range that stays in range longer.
+///
+/// The bounds are widened outward to multiples of `tick_spacing` and clamped to the usable
+/// ticks within `[MIN_TICK, MAX_TICK]`. The range is at least one tick spacing wide.
+pub fn compute_optimal_range(
+    current_sqrt_price_q64: u128,
+    volatility_annualized_scaled: u128,
+    fee_tier: u16,
+    time_horizon_days: u32,
+    tick_spacing: u16,
+) -> Result<PriceBoundary> {
+    compute_asymmetric_range(
+        current_sqrt_price_q64,
+        volatility_annualized_scaled,
+        0,
+        fee_tier,
+        time_horizon_days,
+        tick_spacing,
+    )
+}
+
+/// Same as [`compute_optimal_range`], but centers the range on the price expected after
+/// `time_horizon_days` of `drift_annualized_scaled`, the annualized drift of the log price
+/// scaled like the volatility. A positive drift shifts the range up and a negative one
+/// shifts it down. A drift larger than the volatility spread can move the range off the
+/// current price, which [`validate_proposed_range`] rejects for regular positions.
+pub fn compute_asymmetric_range(
+    current_sqrt_price_q64: u128,
+    volatility_annualized_scaled: u128,
+    drift_annualized_scaled: i128,
+    fee_tier: u16,
+    time_horizon_days: u32,
+    tick_spacing: u16,
+) -> Result<PriceBoundary> {
+    if tick_spacing == 0 {
+        return Err(ErrorCode::CalculationError.into());
+    }
+    let current_tick = amm_math::sqrt_price_q64_to_tick(current_sqrt_price_q64)?;
+    let tick_range = (MAX_TICK - MIN_TICK) as u128;
+    let spread_ticks = spread_ticks(
+        range_width_multiplier_scaled(fee_tier),
+        volatility_annualized_scaled,
+        time_horizon_days,
+    )?;
+
+    // Shift of the center, mu * T, in ticks
+    let drift_scaled = U256::from(drift_annualized_scaled.unsigned_abs())
+        * U256::from(time_horizon_days)
+        * U256::from(PRECISION_SCALE)
+        / U256::from(VOLATILITY_INPUT_SCALE * DAYS_IN_YEAR_DEN);
+    let drift_ticks = (drift_scaled / U256::from(LN_TICK_BASE_SCALED))
+        .min(U256::from(tick_range))
+        .as_u128() as i32;
+    let center_tick = if drift_annualized_scaled < 0 {
+        current_tick - drift_ticks
+    } else {
+        current_tick + drift_ticks
+    };
 
-    // Calculate multipliers: (1 +/- price_range_factor_scaled/PRECISION_SCALE)
-    // lower_multiplier_scaled = (1.0 - price_range_factor) * PRECISION_SCALE
-    // upper_multiplier_scaled = (1.0 + price_range_factor) * PRECISION_SCALE
-    let one_scaled = PRECISION_SCALE;
-    let min_lower_multiplier_scaled = PRECISION_SCALE / 100; // Corresponds to 0.01
+    aligned_range(center_tick, spread_ticks, tick_spacing)
+}
 
-    let mut lower_multiplier_scaled = one_scaled.saturating_sub(price_range_factor_scaled);
-    lower_multiplier_scaled = lower_multiplier_scaled.max(min_lower_multiplier_scaled);
+/// Half-width of the range, `k * sigma * sqrt(T)`, in ticks, for a multiplier `k_scaled`
+/// scaled by `PRECISION_SCALE`.
+fn spread_ticks(
+    k_scaled: u128,
+    volatility_annualized_scaled: u128,
+    time_horizon_days: u32,
+) -> Result<i32> {
+    let spread_scaled = scaled_price_move(
+        k_scaled,
+        volatility_annualized_scaled,
+        time_horizon_days as u128,
+    )?;
+    Ok((spread_scaled / LN_TICK_BASE_SCALED).min((MAX_TICK - MIN_TICK) as u128) as i32)
+}
 
-    let upper_multiplier_scaled = one_scaled + price_range_factor_scaled;
+/// The range `spread_ticks` either side of `center_tick`, widened outward to multiples of
+/// a non-zero `tick_spacing` within the usable ticks, and at least one spacing wide.
+fn aligned_range(center_tick: i32, spread_ticks: i32, tick_spacing: u16) -> Result<PriceBoundary> {
+    let spacing = tick_spacing as i32;
+    let min_usable_tick = -(-MIN_TICK / spacing) * spacing;
+    let max_usable_tick = (MAX_TICK / spacing) * spacing;
+    let mut tick_lower = ((center_tick - spread_ticks).div_euclid(spacing) * spacing)
+        .clamp(min_usable_tick, max_usable_tick - spacing);
+    let mut tick_upper = (-(-(center_tick + spread_ticks)).div_euclid(spacing) * spacing)
+        .clamp(min_usable_tick + spacing, max_usable_tick);
+    if tick_lower >= tick_upper {
+        if tick_upper + spacing <= max_usable_tick {
+            tick_upper = tick_lower + spacing;
+        } else {
+            tick_lower = tick_upper - spacing;
+        }
+    }
 
-    // new_sqrt_price = current_sqrt_price * sqrt(multiplier_actual)
-    // sqrt(multiplier_actual) = sqrt(multiplier_scaled / PRECISION_SCALE)
-    //                         = sqrt(multiplier_scaled * PRECISION_SCALE) / PRECISION_SCALE
-    // Let sqrt_multiplier_intermediate = isqrt(multiplier_scaled * PRECISION_SCALE)
-    // This intermediate is sqrt(multiplier_actual) * PRECISION_SCALE
+    Ok(PriceBoundary {
+        tick_lower,
+        tick_upper,
+        sqrt_price_lower_q64: amm_math::tick_to_sqrt_price_q64(tick_lower)?,
+        sqrt_price_upper_q64: amm_math::tick_to_sqrt_price_q64(tick_upper)?,
+    })
+}
 
-    let sqrt_lower_multiplier_intermediate = isqrt_u128(
-        lower_multiplier_scaled
-            .checked_mul(PRECISION_SCALE)
-            .ok_or(ErrorCode::Overflow)?,
+/// Multiplier `k` of the volatility spread for a fee tier, scaled by `PRECISION_SCALE`.
+fn range_width_multiplier_scaled(fee_tier: u16) -> u128 {
+    let k_bps: u128 = if fee_tier <= FEE_TIER_LOW {
+        10_000
+    } else if fee_tier <= FEE_TIER_MEDIUM {
+        15_000
+    } else if fee_tier <= FEE_TIER_HIGH {
+        20_000
+    } else {
+        25_000
+    };
+    k_bps * PRECISION_SCALE / BPS_DENOMINATOR
+}
+
+/// Checks that a proposed `[tick_lower, tick_upper)` range contains the current tick.
+///
+/// A range entirely above or below the current price leaves the position inactive, so it
+/// earns no fees until the price comes back. Such one-sided ranges are rejected unless
+/// `allow_one_sided` is set, e.g. for a deliberate range order.
+pub fn validate_proposed_range(
+    tick_lower: i32,
+    tick_upper: i32,
+    current_tick: i32,
+    allow_one_sided: bool,
+) -> Result<()> {
+    let contains_current_tick = tick_lower <= current_tick && current_tick < tick_upper;
+    if !contains_current_tick && !allow_one_sided {
+        return Err(ErrorCode::ProposedRangeExcludesCurrentPrice.into());
+    }
+    Ok(())
+}
+
+/// Weighs the IL a rebalance is expected to save against what it costs.
+///
+/// Moving the position from `current_il` to `projected_il_after` saves their difference,
+/// as a share of `position_value_lamports`. The rebalance is only worth it if that saving
+/// exceeds `rebalance_cost_lamports`, the fees and swap costs of moving the position, plus
+/// `REBALANCE_HYSTERESIS_BPS` of the position's value. Never recommends a rebalance that
+/// does not reduce IL.
+pub fn should_rebalance(
+    current_il: IlPercentage,
+    projected_il_after: IlPercentage,
+    rebalance_cost_lamports: u64,
+    position_value_lamports: u64,
+) -> bool {
+    let il_reduction = projected_il_after.raw().saturating_sub(current_il.raw());
+    if il_reduction <= 0 {
+        return false;
+    }
+    // IL percentages are scaled by IL_PERCENTAGE_SCALE and count 100 per unit
+    let expected_il_saved = U256::from(il_reduction.unsigned_abs())
+        * U256::from(position_value_lamports)
+        / U256::from(100 * IL_PERCENTAGE_SCALE);
+    let hysteresis = U256::from(position_value_lamports) * U256::from(REBALANCE_HYSTERESIS_BPS)
+        / U256::from(BPS_DENOMINATOR);
+    expected_il_saved > U256::from(rebalance_cost_lamports) + hysteresis
+}
+
+/// Tunes how [`recommend_liquidity_split`] weighs impermanent loss against fee capture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiquiditySplitConfig {
+    /// Weight of a range's expected IL against its expected fees, in basis points.
+    /// 10_000 counts them one for one; 0 ignores IL.
+    pub il_weight_bps: u16,
+    /// Smallest share of the budget either range receives, in basis points. At most 5_000.
+    pub min_share_bps: u16,
+}
+
+impl Default for LiquiditySplitConfig {
+    fn default() -> Self {
+        Self {
+            il_weight_bps: BPS_DENOMINATOR as u16,
+            min_share_bps: 0,
+        }
+    }
+}
+
+/// Liquidity split recommended by [`recommend_liquidity_split`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiquiditySplit {
+    /// Liquidity for the first range.
+    pub first_liquidity: u128,
+    /// Liquidity for the second range. The two always add up to the budget.
+    pub second_liquidity: u128,
+    /// Share of the budget given to the first range, in basis points.
+    pub first_share_bps: u16,
+}
+
+/// Recommends how to split a liquidity budget between two ranges that both contain the
+/// current price.
+///
+/// Each range is scored over the optimizer's time horizon, using the same expected price
+/// move `m` as [`calculate_optimal_boundaries_mvp`]. A range with relative half-width `r`
+/// (its upper over lower sqrt price, minus one) concentrates liquidity by about `1 / r`,
+/// and stays in range for about `min(1, r / m)` of the move, so it captures fees in
+/// proportion to `1 / max(r, m)`. Its expected IL is the full-range IL of a move of
+/// `min(r, m)`, amplified by the same `1 / r`, and counts against the fees with weight
+/// `config.il_weight_bps`. The budget is split in proportion to the scores, then clamped so
+/// that neither range gets less than `config.min_share_bps`.
+///
+/// Higher volatility widens the expected move, which erodes the fee advantage of the
+/// narrower range and shifts the split toward the wider one.
+///
+/// Fails with `ProposedRangeExcludesCurrentPrice` if a range does not contain the current
+/// price, `InvalidLiquiditySplitConfig` if `config.min_share_bps` exceeds 5_000, and
+/// `OptimizationFailed` if the expected IL outweighs the fees in both ranges.
+pub fn recommend_liquidity_split(
+    total_liquidity: u128,
+    first_range: (i32, i32),
+    second_range: (i32, i32),
+    current_sqrt_price_q64: u128,
+    volatility_annualized_scaled: u128,
+    config: LiquiditySplitConfig,
+) -> Result<LiquiditySplit> {
+    if config.min_share_bps as u128 > BPS_DENOMINATOR / 2 {
+        return Err(ErrorCode::InvalidLiquiditySplitConfig.into());
+    }
+    let current_tick = amm_math::sqrt_price_q64_to_tick(current_sqrt_price_q64)?;
+    for &(tick_lower, tick_upper) in &[first_range, second_range] {
+        validate_proposed_range(tick_lower, tick_upper, current_tick, false)?;
+    }
+
+    let expected_move = expected_price_move_scaled(volatility_annualized_scaled)?;
+    let first_score = range_score(
+        first_range,
+        expected_move,
+        current_sqrt_price_q64,
+        config.il_weight_bps,
+    )?;
+    let second_score = range_score(
+        second_range,
+        expected_move,
+        current_sqrt_price_q64,
+        config.il_weight_bps,
+    )?;
+    let total_score = first_score
+        .checked_add(second_score)
+        .ok_or(ErrorCode::Overflow)?;
+    if total_score == 0 {
+        return Err(ErrorCode::OptimizationFailed.into());
+    }
+
+    let first_share_bps = (U256::from(first_score) * U256::from(BPS_DENOMINATOR)
+        / U256::from(total_score))
+    .as_u128()
+    .clamp(
+        config.min_share_bps as u128,
+        BPS_DENOMINATOR - config.min_share_bps as u128,
     );
-    let sqrt_upper_multiplier_intermediate = isqrt_u128(
-        upper_multiplier_scaled
+    let first_liquidity = (U256::from(total_liquidity) * U256::from(first_share_bps)
+        / U256::from(BPS_DENOMINATOR))
+    .as_u128();
+
+    Ok(LiquiditySplit {
+        first_liquidity,
+        second_liquidity: total_liquidity - first_liquidity,
+        first_share_bps: first_share_bps as u16,
+    })
+}
+
+/// Expected fees minus weighted expected IL per unit of liquidity budget placed in
+/// `(tick_lower, tick_upper)`, scaled by `PRECISION_SCALE` and floored at zero.
+fn range_score(
+    (tick_lower, tick_upper): (i32, i32),
+    expected_move: u128,
+    current_sqrt_price_q64: u128,
+    il_weight_bps: u16,
+) -> Result<u128> {
+    let sqrt_lower = amm_math::tick_to_sqrt_price_q64(tick_lower)?;
+    let sqrt_upper = amm_math::tick_to_sqrt_price_q64(tick_upper)?;
+    let half_width = (U256::from(sqrt_upper) * U256::from(PRECISION_SCALE)
+        / U256::from(sqrt_lower))
+    .min(U256::from(u128::MAX))
+    .as_u128()
+    .saturating_sub(PRECISION_SCALE)
+    .max(1);
+
+    let fees = PRECISION_SCALE * PRECISION_SCALE / half_width.max(expected_move);
+
+    let il = full_range_il_scaled(current_sqrt_price_q64, half_width.min(expected_move))?;
+    let weighted_il = U256::from(il) * U256::from(PRECISION_SCALE) * U256::from(il_weight_bps)
+        / U256::from(half_width)
+        / U256::from(BPS_DENOMINATOR);
+
+    Ok(U256::from(fees).saturating_sub(weighted_il).as_u128())
+}
+
+/// Magnitude of the impermanent loss of a full-range position after the price rises by
+/// `price_move` (scaled by `PRECISION_SCALE`), as a fraction scaled by `PRECISION_SCALE`.
+fn full_range_il_scaled(current_sqrt_price_q64: u128, price_move: u128) -> Result<u128> {
+    let sqrt_multiplier = isqrt_u128(
+        (PRECISION_SCALE + price_move)
             .checked_mul(PRECISION_SCALE)
             .ok_or(ErrorCode::Overflow)?,
     );
-
-    // new_sqrt_price_q64 = (current_sqrt_price_q64 * sqrt_multiplier_intermediate) / PRECISION_SCALE
-    let new_lower_sqrt_price_q64 = (U256::from(current_sqrt_price_q64)
-        * U256::from(sqrt_lower_multiplier_intermediate)
-        / U256::from(PRECISION_SCALE))
-    .as_u128();
-    let new_upper_sqrt_price_q64 = (U256::from(current_sqrt_price_q64)
-        * U256::from(sqrt_upper_multiplier_intermediate)
+    let moved_sqrt_price_q64 = (U256::from(current_sqrt_price_q64) * U256::from(sqrt_multiplier)
         / U256::from(PRECISION_SCALE))
+    .min(U256::from(MAX_SQRT_PRICE))
     .as_u128();
 
-    let mut new_lower_tick = amm_math::sqrt_price_q64_to_tick(new_lower_sqrt_price_q64)?;
-    let mut new_upper_tick = amm_math::sqrt_price_q64_to_tick(new_upper_sqrt_price_q64)?;
+    let il_percentage = calculate_current_il_percentage(
+        MIN_TICK,
+        MAX_TICK,
+        current_sqrt_price_q64,
+        moved_sqrt_price_q64,
+    )?;
+    Ok(il_percentage.raw().unsigned_abs() * PRECISION_SCALE / (100 * IL_PERCENTAGE_SCALE))
+}
 
-    // Align to tick_spacing
-    let tick_spacing_i32 = pool_tick_spacing as i32;
-    new_lower_tick = (new_lower_tick / tick_spacing_i32) * tick_spacing_i32;
-    new_upper_tick =
-        ((new_upper_tick + tick_spacing_i32 - 1) / tick_spacing_i32) * tick_spacing_i32; // Ceiling division for upper
+/// Most bands [`compute_split_ranges`] splits a budget across.
+pub const MAX_SPLIT_RANGES: u8 = 5;
 
-    // Ensure lower < upper and within bounds
-    if new_lower_tick >= new_upper_tick {
-        // Fallback or error, e.g., make a minimum width range around current price
-        let current_tick = amm_math::sqrt_price_q64_to_tick(current_sqrt_price_q64)?;
-        new_lower_tick = ((current_tick - tick_spacing_i32) / tick_spacing_i32) * tick_spacing_i32;
-        new_upper_tick = ((current_tick + tick_spacing_i32) / tick_spacing_i32) * tick_spacing_i32;
-        if new_lower_tick >= new_upper_tick {
-            // if current_tick was 0 and spacing makes them overlap
-            new_upper_tick = new_lower_tick + tick_spacing_i32;
+/// One band of the split-range strategy suggested by [`compute_split_ranges`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SplitRange {
+    /// The band's range, centered on the current price.
+    pub boundary: PriceBoundary,
+    /// Liquidity placed in the band.
+    pub liquidity: u128,
+    /// Share of the budget given to the band, in basis points.
+    pub share_bps: u16,
+    /// Expected fees net of IL per unit of budget, on the same relative scale as the
+    /// scores [`recommend_liquidity_split`] splits by. Zero if IL outweighs the fees.
+    pub net_score: u128,
+}
+
+/// Splits a liquidity budget across nested bands centered on the current price, for
+/// LPs who ladder their liquidity instead of holding a single range.
+///
+/// The outermost band is the range [`compute_optimal_range`] suggests; band `i` of `n`
+/// spans `i / n` of its half-width on either side, widened outward to the tick spacing.
+/// Each band gets a share of `total_liquidity` inversely proportional to its width in
+/// ticks, so narrower bands get more, with the rounding left to the narrowest band.
+/// `num_ranges` is capped at `MAX_SPLIT_RANGES`; bands that snap to the same ticks are
+/// kept separate. The bands are returned best first by `net_score`, scored over
+/// `time_horizon_days` as in [`recommend_liquidity_split`] with IL counted one for one.
+///
+/// Fails with `InvalidSplitRangeCount` if `num_ranges` is zero and `CalculationError` if
+/// `tick_spacing` is zero.
+pub fn compute_split_ranges(
+    current_sqrt_price_q64: u128,
+    volatility_annualized_scaled: u128,
+    fee_tier: u16,
+    time_horizon_days: u32,
+    tick_spacing: u16,
+    num_ranges: u8,
+    total_liquidity: u128,
+) -> Result<Vec<SplitRange>> {
+    if num_ranges == 0 {
+        return Err(ErrorCode::InvalidSplitRangeCount.into());
+    }
+    if tick_spacing == 0 {
+        return Err(ErrorCode::CalculationError.into());
+    }
+    let num_ranges = num_ranges.min(MAX_SPLIT_RANGES) as i32;
+    let current_tick = amm_math::sqrt_price_q64_to_tick(current_sqrt_price_q64)?;
+    let outer_spread_ticks = spread_ticks(
+        range_width_multiplier_scaled(fee_tier),
+        volatility_annualized_scaled,
+        time_horizon_days,
+    )?;
+    let expected_move = scaled_price_move(
+        range_width_multiplier_scaled(fee_tier),
+        volatility_annualized_scaled,
+        time_horizon_days as u128,
+    )?;
+
+    // Narrowest band first, so the rounding below lands on it
+    let boundaries = (1..=num_ranges)
+        .map(|i| {
+            aligned_range(
+                current_tick,
+                outer_spread_ticks * i / num_ranges,
+                tick_spacing,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let weights: Vec<u128> = boundaries
+        .iter()
+        .map(|boundary| PRECISION_SCALE / (boundary.tick_upper - boundary.tick_lower) as u128)
+        .collect();
+    let total_weight: u128 = weights.iter().sum();
+
+    let mut ranges = Vec::with_capacity(boundaries.len());
+    for (boundary, weight) in boundaries.into_iter().zip(weights) {
+        ranges.push(SplitRange {
+            boundary,
+            liquidity: (U256::from(total_liquidity) * U256::from(weight)
+                / U256::from(total_weight))
+            .as_u128(),
+            share_bps: (weight * BPS_DENOMINATOR / total_weight) as u16,
+            net_score: range_score(
+                (boundary.tick_lower, boundary.tick_upper),
+                expected_move,
+                current_sqrt_price_q64,
+                BPS_DENOMINATOR as u16,
+            )?,
+        });
+    }
+    let placed: u128 = ranges.iter().map(|range| range.liquidity).sum();
+    ranges[0].liquidity += total_liquidity - placed;
+
+    ranges.sort_by_key(|range| std::cmp::Reverse(range.net_score));
+    Ok(ranges)
+}
+
+/// Shifts `range` toward the price levels where the pool holds the least liquidity,
+/// where a position earns a larger share of the fees.
+///
+/// Candidates are `range` moved by whole buckets of `histogram`, which keeps them aligned
+/// to the tick spacing, that still contain `current_tick` and lie inside both the
+/// histogram and `[MIN_TICK, MAX_TICK]`.
+/// The one with the lowest liquidity over its ticks, as averaged by the histogram, wins,
+/// the smallest shift breaking ties. `range` is returned unchanged if the histogram was
+/// refreshed more than `max_age_secs` before `now`, as its liquidity may have moved since,
+/// or if `range` does not lie inside the histogram.
+pub fn bias_range_toward_undersupplied(
+    range: PriceBoundary,
+    current_tick: i32,
+    histogram: &LiquidityHistogram,
+    now: i64,
+    max_age_secs: i64,
+) -> Result<PriceBoundary> {
+    if histogram.ensure_fresh(now, max_age_secs).is_err() {
+        return Ok(range);
+    }
+    let histogram_liquidity = |tick_lower: i32, tick_upper: i32| {
+        if tick_lower < histogram.lower_tick || tick_upper > histogram.upper_tick() {
+            return None;
+        }
+        let bucket_ticks = histogram.bucket_ticks as i32;
+        let mut total = U256::zero();
+        let mut tick = tick_lower;
+        while tick < tick_upper {
+            let bucket = histogram.bucket_of(tick)?;
+            let bucket_end = histogram.lower_tick + (bucket as i32 + 1) * bucket_ticks;
+            let covered_end = tick_upper.min(bucket_end);
+            total += U256::from(histogram.liquidity[bucket]) * U256::from(covered_end - tick);
+            tick = covered_end;
         }
+        Some(total)
+    };
+    let Some(mut best_liquidity) = histogram_liquidity(range.tick_lower, range.tick_upper) else {
+        return Ok(range);
+    };
+
+    let bucket_ticks = histogram.bucket_ticks as i32;
+    let max_shift = (range.tick_upper - range.tick_lower) / bucket_ticks + 1;
+    let mut best_shift = 0;
+    for distance in 1..=max_shift {
+        for shift in [-distance * bucket_ticks, distance * bucket_ticks] {
+            let (tick_lower, tick_upper) = (range.tick_lower + shift, range.tick_upper + shift);
+            if tick_lower < MIN_TICK
+                || tick_upper > MAX_TICK
+                || validate_proposed_range(tick_lower, tick_upper, current_tick, false).is_err()
+            {
+                continue;
+            }
+            if let Some(liquidity) = histogram_liquidity(tick_lower, tick_upper) {
+                if liquidity < best_liquidity {
+                    best_liquidity = liquidity;
+                    best_shift = shift;
+                }
+            }
+        }
+    }
+    if best_shift == 0 {
+        return Ok(range);
     }
 
-    Ok((
-        new_lower_tick.clamp(MIN_TICK, MAX_TICK - tick_spacing_i32),
-        new_upper_tick.clamp(MIN_TICK + tick_spacing_i32, MAX_TICK),
-    ))
+    let tick_lower = range.tick_lower + best_shift;
+    let tick_upper = range.tick_upper + best_shift;
+    Ok(PriceBoundary {
+        tick_lower,
+        tick_upper,
+        sqrt_price_lower_q64: amm_math::tick_to_sqrt_price_q64(tick_lower)?,
+        sqrt_price_upper_q64: amm_math::tick_to_sqrt_price_q64(tick_upper)?,
+    })
 }
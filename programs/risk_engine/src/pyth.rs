@@ -0,0 +1,160 @@
+//! Pyth price updates sampled by `trigger_rebalance_check`.
+//!
+//! `PriceUpdateV2` mirrors the account the Pyth Solana receiver program posts verified
+//! price updates to, as `pyth-solana-receiver-sdk` defines it. That crate is not a
+//! dependency of this workspace, so the layout is declared here: Anchor's 8-byte account
+//! discriminator followed by the Borsh-encoded fields below.
+//!
+//! A pool is sampled from Pyth when its amm_core `TokenPair` names a feed: the pair's
+//! `oracle_feed` holds the 32-byte Pyth feed id, quoting whole token0 in whole token1.
+use crate::errors::RiskEngineError as ErrorCode;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::pubkey;
+use anchor_lang::Discriminator;
+use primitive_types::U256;
+
+/// The Pyth Solana receiver program, which owns `PriceUpdateV2` accounts.
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// Largest power of ten a `U256` holds.
+const MAX_U256_EXP10: i32 = 77;
+
+/// How thoroughly the receiver verified a price update's Wormhole signatures.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationLevel {
+    /// Verified by `num_signatures` guardians, fewer than a quorum.
+    Partial { num_signatures: u8 },
+    /// Verified by a quorum of guardians.
+    Full,
+}
+
+/// A price published for one Pyth feed. The price is `price * 10^exponent`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    /// Unix timestamp at which the price was published.
+    pub publish_time: i64,
+    pub prev_publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+impl PriceFeedMessage {
+    /// The price as the Q64.64 sqrt price of a pool trading the feed's base as token0.
+    ///
+    /// The feed quotes whole tokens, while pool prices are in base units, so the price is
+    /// scaled by `10^(token1_decimals - token0_decimals)` first.
+    ///
+    /// # Errors
+    /// * `InvalidPythPrice` - The price is not positive, or too small to be a pool price.
+    /// * `Overflow` - The price is too large to be a pool price.
+    pub fn sqrt_price_q64(&self, token0_decimals: u8, token1_decimals: u8) -> Result<u128> {
+        require!(self.price > 0, ErrorCode::InvalidPythPrice);
+        let exponent = self.exponent + token1_decimals as i32 - token0_decimals as i32;
+        let price = U256::from(self.price as u64);
+
+        // The pool price in Q128.128, so its square root is in Q64.64.
+        let price_x128 = if exponent >= 0 {
+            require!(exponent <= MAX_U256_EXP10, ErrorCode::Overflow);
+            let price = price
+                .checked_mul(U256::exp10(exponent as usize))
+                .ok_or(ErrorCode::Overflow)?;
+            require!(price <= U256::from(u128::MAX), ErrorCode::Overflow);
+            price << 128
+        } else if -exponent <= MAX_U256_EXP10 {
+            (price << 128) / U256::exp10(-exponent as usize)
+        } else {
+            U256::zero()
+        };
+
+        let sqrt_price_q64 = price_x128.integer_sqrt();
+        require!(
+            sqrt_price_q64 > U256::zero() && sqrt_price_q64 <= U256::from(u128::MAX),
+            ErrorCode::InvalidPythPrice
+        );
+        Ok(sqrt_price_q64.as_u128())
+    }
+}
+
+/// A verified price update posted by the Pyth receiver program.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceUpdateV2 {
+    pub write_authority: Pubkey,
+    pub verification_level: VerificationLevel,
+    pub price_message: PriceFeedMessage,
+    /// Slot at which the update was posted.
+    pub posted_slot: u64,
+}
+
+impl PriceUpdateV2 {
+    /// The price of the update, once it is checked to be a fully verified price of
+    /// `feed_id` published no more than `max_staleness_secs` before `now`.
+    ///
+    /// # Errors
+    /// * `PythFeedMismatch` - The update is for another feed.
+    /// * `PythPriceUnverified` - The update was only partially verified.
+    /// * `PythPriceStale` - The price was published too long before `now`.
+    pub fn price_no_older_than(
+        &self,
+        now: i64,
+        max_staleness_secs: i64,
+        feed_id: &[u8; 32],
+    ) -> Result<PriceFeedMessage> {
+        require!(
+            self.price_message.feed_id == *feed_id,
+            ErrorCode::PythFeedMismatch
+        );
+        require!(
+            self.verification_level == VerificationLevel::Full,
+            ErrorCode::PythPriceUnverified
+        );
+        require!(
+            self.price_message
+                .publish_time
+                .saturating_add(max_staleness_secs)
+                >= now,
+            ErrorCode::PythPriceStale
+        );
+        Ok(self.price_message)
+    }
+}
+
+impl Discriminator for PriceUpdateV2 {
+    /// The first 8 bytes of `sha256("account:PriceUpdateV2")`.
+    const DISCRIMINATOR: &'static [u8] = &[34, 241, 35, 99, 157, 126, 244, 205];
+}
+
+impl Owner for PriceUpdateV2 {
+    fn owner() -> Pubkey {
+        PYTH_RECEIVER_PROGRAM_ID
+    }
+}
+
+impl AccountSerialize for PriceUpdateV2 {
+    fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
+        writer
+            .write_all(Self::DISCRIMINATOR)
+            .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotSerialize)?;
+        self.serialize(writer)
+            .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotSerialize)?;
+        Ok(())
+    }
+}
+
+impl AccountDeserialize for PriceUpdateV2 {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self> {
+        if !buf.starts_with(Self::DISCRIMINATOR) {
+            return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        Self::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+        let mut data = &buf[Self::DISCRIMINATOR.len().min(buf.len())..];
+        Self::deserialize(&mut data)
+            .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())
+    }
+}
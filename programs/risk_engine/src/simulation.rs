@@ -0,0 +1,183 @@
+//! Monte Carlo simulation of pool prices, for weighing ranges off-chain.
+//!
+//! `StochasticModel` simulates price paths under geometric Brownian motion, optionally
+//! pulled back towards the starting price. The estimators below then measure how often a
+//! range is left and what a position on it can expect to lose to IL, net of fees.
+//!
+//! Only built with the `simulation` feature. Floating point is fine here because nothing
+//! in this module runs on-chain.
+
+/// Days per year, to turn `SimulationParameters::time_horizon` into the unit of the
+/// annualized drift and volatility.
+const DAYS_IN_YEAR: f64 = 365.0;
+
+/// The price process simulated by [`StochasticModel::simulate`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SimulationParameters {
+    /// Annualized drift of the price, e.g. 0.1 for 10% a year.
+    pub drift: f64,
+    /// Annualized volatility of the log price, e.g. 0.8 for 80%.
+    pub volatility: f64,
+    /// Annualized rate at which the log price is pulled back towards the initial price's.
+    /// Zero for plain geometric Brownian motion.
+    pub mean_reversion_strength: f64,
+    /// Number of steps in each path.
+    pub time_steps: usize,
+    /// Length of each path, in days.
+    pub time_horizon: f64,
+    /// Number of paths to simulate.
+    pub num_paths: usize,
+}
+
+/// Simulates price paths from a seeded pseudo-random generator, so the same seed always
+/// gives the same paths.
+#[derive(Clone, Debug)]
+pub struct StochasticModel {
+    /// SplitMix64 state.
+    state: u64,
+    /// The second sample of the last Box-Muller pair, not yet handed out.
+    spare_gaussian: Option<f64>,
+}
+
+impl StochasticModel {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            spare_gaussian: None,
+        }
+    }
+
+    /// Simulates `params.num_paths` price paths starting at `initial_price`.
+    ///
+    /// Each path holds `params.time_steps + 1` prices, starting with `initial_price`, with
+    /// steps of `params.time_horizon / params.time_steps` days. The log price `x` moves by
+    /// `(μ - σ²/2 - κ·(x - x₀))·dt + σ·√dt·Z` each step, `Z` a standard normal sample, so
+    /// with `κ = mean_reversion_strength` of zero the price follows geometric Brownian
+    /// motion with drift `μ`.
+    pub fn simulate(&mut self, params: SimulationParameters, initial_price: f64) -> Vec<Vec<f64>> {
+        let dt = if params.time_steps == 0 {
+            0.0
+        } else {
+            params.time_horizon / DAYS_IN_YEAR / params.time_steps as f64
+        };
+        let drift_per_step = (params.drift - params.volatility.powi(2) / 2.0) * dt;
+        let diffusion_per_step = params.volatility * dt.sqrt();
+        let initial_log_price = initial_price.ln();
+
+        (0..params.num_paths)
+            .map(|_| {
+                let mut log_price = initial_log_price;
+                let mut path = Vec::with_capacity(params.time_steps + 1);
+                path.push(initial_price);
+                for _ in 0..params.time_steps {
+                    let reversion =
+                        params.mean_reversion_strength * (log_price - initial_log_price) * dt;
+                    log_price +=
+                        drift_per_step - reversion + diffusion_per_step * self.next_gaussian();
+                    path.push(log_price.exp());
+                }
+                path
+            })
+            .collect()
+    }
+
+    /// A standard normal sample, drawn in pairs with the Box-Muller transform.
+    fn next_gaussian(&mut self) -> f64 {
+        if let Some(sample) = self.spare_gaussian.take() {
+            return sample;
+        }
+        let radius = (-2.0 * self.next_uniform().ln()).sqrt();
+        let angle = 2.0 * std::f64::consts::PI * self.next_uniform();
+        self.spare_gaussian = Some(radius * angle.sin());
+        radius * angle.cos()
+    }
+
+    /// A uniform sample in (0, 1], never zero so its logarithm is finite.
+    fn next_uniform(&mut self) -> f64 {
+        // The top 53 bits fill an f64 mantissa exactly
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    /// SplitMix64.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// The share of `paths` that leave `[lower, upper)` at any step.
+///
+/// Like a tick range, the range includes `lower` but not `upper`. Zero if there are no
+/// paths.
+pub fn estimate_out_of_range_probability(paths: &[Vec<f64>], lower: f64, upper: f64) -> f64 {
+    if paths.is_empty() {
+        return 0.0;
+    }
+    let out_of_range = paths
+        .iter()
+        .filter(|path| path.iter().any(|&price| price < lower || price >= upper))
+        .count();
+    out_of_range as f64 / paths.len() as f64
+}
+
+/// The average IL, net of fees, of a position on `[lower, upper]` opened at the first
+/// price of each path and closed at its last, as a fraction of the value of holding the
+/// position's initial tokens instead. Negative for a loss.
+///
+/// Fees are those a pool charging `fee_tier`, a fraction such as 0.003 for 0.3%, earns
+/// on the swaps that move its price along the path while it is in the range. `lower`
+/// must be positive and below `upper`. Zero if there are no paths.
+pub fn estimate_expected_il(paths: &[Vec<f64>], lower: f64, upper: f64, fee_tier: f64) -> f64 {
+    let paths: Vec<&Vec<f64>> = paths.iter().filter(|path| !path.is_empty()).collect();
+    if paths.is_empty() {
+        return 0.0;
+    }
+    let range = (lower.sqrt(), upper.sqrt());
+    let total_il: f64 = paths
+        .iter()
+        .map(|path| path_il(path, range, fee_tier))
+        .sum();
+    total_il / paths.len() as f64
+}
+
+/// IL net of fees along one non-empty `path` for a position on the sqrt price range
+/// `range`, per [`estimate_expected_il`].
+fn path_il(path: &[f64], range: (f64, f64), fee_tier: f64) -> f64 {
+    // A unit of liquidity: amounts scale with it, so the ratios below do not depend on it
+    let initial_price = path[0];
+    let final_price = path[path.len() - 1];
+    let (initial_amount_0, initial_amount_1) = amounts_for_unit_liquidity(initial_price, range);
+    let hold_value = initial_amount_0 * final_price + initial_amount_1;
+    if hold_value == 0.0 {
+        return 0.0;
+    }
+    let (final_amount_0, final_amount_1) = amounts_for_unit_liquidity(final_price, range);
+    let position_value = final_amount_0 * final_price + final_amount_1;
+
+    // Moving the price from one sqrt price to another within the range swaps in
+    // `Δ√P` of token1 going up and `Δ(1/√P)` of token0 going down
+    let fees_value: f64 = path
+        .windows(2)
+        .map(|step| {
+            let from = step[0].sqrt().clamp(range.0, range.1);
+            let to = step[1].sqrt().clamp(range.0, range.1);
+            let value_in = if to >= from {
+                to - from
+            } else {
+                (1.0 / to - 1.0 / from) * step[1]
+            };
+            fee_tier * value_in
+        })
+        .sum();
+
+    (position_value + fees_value - hold_value) / hold_value
+}
+
+/// Token0 and token1 held by a unit of liquidity on the sqrt price `range` at `price`.
+fn amounts_for_unit_liquidity(price: f64, (sqrt_lower, sqrt_upper): (f64, f64)) -> (f64, f64) {
+    let sqrt_price = price.sqrt().clamp(sqrt_lower, sqrt_upper);
+    (1.0 / sqrt_price - 1.0 / sqrt_upper, sqrt_price - sqrt_lower)
+}
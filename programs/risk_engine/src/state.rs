@@ -0,0 +1,545 @@
+//! Accounts owned by the risk engine.
+use crate::circuit_breaker::{self, PriceObservation, BPS_DENOMINATOR};
+use crate::errors::RiskEngineError as ErrorCode;
+use crate::il_analyzer::IlPercentage;
+use crate::keeper_bond::{
+    self, KeeperSubmission, KEEPER_DISPUTE_WINDOW_SECS, KEEPER_SLASH_TOLERANCE,
+    MAX_OPEN_KEEPER_SUBMISSIONS, MIN_KEEPER_BOND_LAMPORTS,
+};
+use crate::volatility_detector::{self, RETURN_SCALING_FACTOR, VOLATILITY_WINDOW};
+use anchor_lang::prelude::*;
+
+/// Number of price observations kept per pool.
+pub const MAX_PRICE_OBSERVATIONS: usize = 32;
+
+/// Kind of market a pool trades, which sets the volatility assumed for it before enough
+/// prices have been recorded to measure one.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolCategory {
+    /// Pegged pairs, such as two stablecoins.
+    Stable,
+    /// Liquid majors, such as SOL against a stablecoin.
+    Major,
+    /// Thinly traded or newly launched tokens.
+    LongTail,
+}
+
+/// Global risk engine settings, a PDA of `[b"risk_config"]`.
+#[account]
+#[derive(Default, Debug)]
+pub struct RiskConfig {
+    /// Bump seed for PDA.
+    pub bump: u8,
+    /// Governance authority allowed to clear a tripped circuit breaker.
+    pub authority: Pubkey,
+    /// TWAP move, in basis points, that trips the circuit breaker.
+    pub circuit_breaker_threshold_bps: u16,
+    /// Window, in seconds, within which the TWAP move is measured.
+    pub circuit_breaker_window_secs: i64,
+    /// Interval, in seconds, the TWAP is averaged over.
+    pub twap_interval_secs: i64,
+    /// How long, in seconds, rebalancing stays halted once the breaker trips.
+    pub halt_duration_secs: i64,
+    /// Oldest, in seconds, a Pyth price `trigger_rebalance_check` samples may be.
+    pub max_staleness_secs: i64,
+    /// When set, `trigger_rebalance_check` evaluates and records rebalances but never moves
+    /// positions. Off when the config is created.
+    pub shadow_mode: bool,
+    /// Annualized volatility, in basis points, assumed for new `PoolCategory::Stable` pools.
+    pub stable_volatility_prior_bps: u16,
+    /// Annualized volatility, in basis points, assumed for new `PoolCategory::Major` pools.
+    pub major_volatility_prior_bps: u16,
+    /// Annualized volatility, in basis points, assumed for new `PoolCategory::LongTail`
+    /// pools.
+    pub long_tail_volatility_prior_bps: u16,
+}
+
+/// Parameters for [`RiskConfig::initialize`].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct RiskConfigParams {
+    pub circuit_breaker_threshold_bps: u16,
+    pub circuit_breaker_window_secs: i64,
+    pub twap_interval_secs: i64,
+    pub halt_duration_secs: i64,
+    pub max_staleness_secs: i64,
+    pub stable_volatility_prior_bps: u16,
+    pub major_volatility_prior_bps: u16,
+    pub long_tail_volatility_prior_bps: u16,
+}
+
+impl RiskConfig {
+    /// The size of the RiskConfig account in bytes.
+    pub const LEN: usize = 8 // discriminator
+        + 1 // bump
+        + 32 // authority
+        + 2 // circuit_breaker_threshold_bps
+        + 8 // circuit_breaker_window_secs
+        + 8 // twap_interval_secs
+        + 8 // halt_duration_secs
+        + 8 // max_staleness_secs
+        + 1 // shadow_mode
+        + 2 // stable_volatility_prior_bps
+        + 2 // major_volatility_prior_bps
+        + 2; // long_tail_volatility_prior_bps
+
+    /// Validates `params` and stores them along with the governance `authority`.
+    pub fn initialize(
+        &mut self,
+        bump: u8,
+        authority: Pubkey,
+        params: RiskConfigParams,
+    ) -> Result<()> {
+        if params.circuit_breaker_threshold_bps == 0
+            || params.circuit_breaker_threshold_bps as u128 > BPS_DENOMINATOR
+            || params.circuit_breaker_window_secs <= 0
+            || params.twap_interval_secs <= 0
+            || params.halt_duration_secs <= 0
+            || params.max_staleness_secs <= 0
+            || params.stable_volatility_prior_bps == 0
+            || params.major_volatility_prior_bps == 0
+            || params.long_tail_volatility_prior_bps == 0
+        {
+            return err!(ErrorCode::InvalidRiskConfig);
+        }
+
+        self.bump = bump;
+        self.authority = authority;
+        self.circuit_breaker_threshold_bps = params.circuit_breaker_threshold_bps;
+        self.circuit_breaker_window_secs = params.circuit_breaker_window_secs;
+        self.twap_interval_secs = params.twap_interval_secs;
+        self.halt_duration_secs = params.halt_duration_secs;
+        self.max_staleness_secs = params.max_staleness_secs;
+        self.shadow_mode = false;
+        self.stable_volatility_prior_bps = params.stable_volatility_prior_bps;
+        self.major_volatility_prior_bps = params.major_volatility_prior_bps;
+        self.long_tail_volatility_prior_bps = params.long_tail_volatility_prior_bps;
+        Ok(())
+    }
+
+    /// The annualized volatility, in basis points, assumed for new pools of `category`.
+    pub fn volatility_prior_bps(&self, category: PoolCategory) -> u16 {
+        match category {
+            PoolCategory::Stable => self.stable_volatility_prior_bps,
+            PoolCategory::Major => self.major_volatility_prior_bps,
+            PoolCategory::LongTail => self.long_tail_volatility_prior_bps,
+        }
+    }
+}
+
+/// Details of a circuit breaker trip, returned by [`PoolRiskState::record_price`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircuitBreakerTrip {
+    /// TWAP the move was measured from.
+    pub reference_twap: u128,
+    /// TWAP at the time of the trip.
+    pub current_twap: u128,
+    /// Size of the move, in basis points.
+    pub move_bps: u128,
+    /// Timestamp until which rebalancing is halted.
+    pub halted_until: i64,
+}
+
+/// Per-pool risk state, a PDA of `[b"pool_risk_state", pool]`.
+#[account]
+#[derive(Default, Debug)]
+pub struct PoolRiskState {
+    /// Bump seed for PDA.
+    pub bump: u8,
+    /// The amm_core pool this state tracks.
+    pub pool: Pubkey,
+    /// Unix timestamp until which auto-rebalancing is halted. Zero when not halted.
+    pub rebalancing_halted_until: i64,
+    /// Index of the most recent observation in `observations`.
+    pub observation_index: u8,
+    /// Number of populated entries in `observations`.
+    pub observation_count: u8,
+    /// Ring buffer of price observations.
+    pub observations: [PriceObservation; MAX_PRICE_OBSERVATIONS],
+    /// Rolling standard deviation of the returns between the latest `VOLATILITY_WINDOW`
+    /// observations, scaled by `volatility_detector::RETURN_SCALING_FACTOR`.
+    pub volatility: u128,
+    /// Unix timestamp at which `volatility` was last computed. Zero if it never was.
+    pub volatility_updated_at: i64,
+    /// Annualized volatility, in basis points, assumed for the pool until enough prices
+    /// have been recorded to measure it. Taken from `RiskConfig` for the pool's category.
+    pub volatility_prior_bps: u16,
+}
+
+impl PoolRiskState {
+    /// Size of a serialized `PriceObservation`: timestamp (8) + price (16) + twap (16).
+    const OBSERVATION_LEN: usize = 8 + 16 + 16;
+
+    /// The size of the PoolRiskState account in bytes.
+    pub const LEN: usize = 8 // discriminator
+        + 1 // bump
+        + 32 // pool
+        + 8 // rebalancing_halted_until
+        + 1 // observation_index
+        + 1 // observation_count
+        + MAX_PRICE_OBSERVATIONS * Self::OBSERVATION_LEN // observations
+        + 16 // volatility
+        + 8 // volatility_updated_at
+        + 2; // volatility_prior_bps
+
+    pub fn initialize(&mut self, bump: u8, pool: Pubkey, volatility_prior_bps: u16) {
+        self.bump = bump;
+        self.pool = pool;
+        self.rebalancing_halted_until = 0;
+        self.observation_index = 0;
+        self.observation_count = 0;
+        self.observations = [PriceObservation::default(); MAX_PRICE_OBSERVATIONS];
+        self.volatility = 0;
+        self.volatility_updated_at = 0;
+        self.volatility_prior_bps = volatility_prior_bps;
+    }
+
+    /// `volatility_prior_bps` scaled by `RETURN_SCALING_FACTOR`, like the annualized
+    /// volatility the position optimizer takes.
+    pub fn annualized_volatility_prior(&self) -> u128 {
+        self.volatility_prior_bps as u128 * RETURN_SCALING_FACTOR / BPS_DENOMINATOR
+    }
+
+    /// Returns the populated observations, oldest first.
+    pub fn observations_chronological(&self) -> Vec<PriceObservation> {
+        let count = self.observation_count as usize;
+        let start = (self.observation_index as usize + MAX_PRICE_OBSERVATIONS + 1 - count)
+            % MAX_PRICE_OBSERVATIONS;
+        (0..count)
+            .map(|i| self.observations[(start + i) % MAX_PRICE_OBSERVATIONS])
+            .collect()
+    }
+
+    /// Returns the recorded prices, oldest first.
+    pub fn recorded_prices(&self) -> Vec<u128> {
+        self.observations_chronological()
+            .iter()
+            .map(|observation| observation.price)
+            .collect()
+    }
+
+    /// True while auto-rebalancing is halted at `now`.
+    pub fn is_halted(&self, now: i64) -> bool {
+        now < self.rebalancing_halted_until
+    }
+
+    /// Fails with `RebalancingHalted` while the circuit breaker is tripped.
+    pub fn ensure_rebalancing_allowed(&self, now: i64) -> Result<()> {
+        if self.is_halted(now) {
+            return err!(ErrorCode::RebalancingHalted);
+        }
+        Ok(())
+    }
+
+    /// Records a spot price sample and trips the circuit breaker on an extreme TWAP move.
+    ///
+    /// A sample at the same timestamp as the previous one replaces it. While the breaker is
+    /// tripped, samples are still recorded but the breaker is not re-evaluated.
+    ///
+    /// Returns the trip details if this sample tripped the breaker.
+    pub fn record_price(
+        &mut self,
+        now: i64,
+        price: u128,
+        config: &RiskConfig,
+    ) -> Result<Option<CircuitBreakerTrip>> {
+        let mut history = self.observations_chronological();
+        if let Some(latest) = history.last() {
+            if now < latest.timestamp {
+                return err!(ErrorCode::InvalidObservationTimestamp);
+            }
+            if now == latest.timestamp {
+                history.pop();
+            }
+        }
+
+        history.push(PriceObservation {
+            timestamp: now,
+            price,
+            twap: 0,
+        });
+        let twap =
+            circuit_breaker::time_weighted_average_price(&history, now, config.twap_interval_secs)?
+                .unwrap_or(price);
+        history.pop();
+
+        let mut trip = None;
+        if !self.is_halted(now) {
+            if let Some((reference_twap, move_bps)) = circuit_breaker::max_twap_move_bps(
+                &history,
+                twap,
+                now,
+                config.circuit_breaker_window_secs,
+            )? {
+                if move_bps > config.circuit_breaker_threshold_bps as u128 {
+                    self.rebalancing_halted_until = now
+                        .checked_add(config.halt_duration_secs)
+                        .ok_or(ErrorCode::Overflow)?;
+                    trip = Some(CircuitBreakerTrip {
+                        reference_twap,
+                        current_twap: twap,
+                        move_bps,
+                        halted_until: self.rebalancing_halted_until,
+                    });
+                }
+            }
+        }
+
+        self.push_observation(
+            PriceObservation {
+                timestamp: now,
+                price,
+                twap,
+            },
+            history.len() < self.observation_count as usize,
+        );
+        Ok(trip)
+    }
+
+    /// Recomputes `volatility` from the latest `VOLATILITY_WINDOW` recorded prices and
+    /// returns it.
+    ///
+    /// Fails with `VolatilityDataError` until that many prices have been recorded.
+    pub fn update_volatility(&mut self, now: i64) -> Result<u128> {
+        if (self.observation_count as usize) < VOLATILITY_WINDOW {
+            return err!(ErrorCode::VolatilityDataError);
+        }
+        self.volatility = volatility_detector::calculate_rolling_std_dev_volatility(
+            &self.recorded_prices(),
+            VOLATILITY_WINDOW,
+        )?;
+        self.volatility_updated_at = now;
+        Ok(self.volatility)
+    }
+
+    /// Clears a tripped circuit breaker so rebalancing can resume immediately.
+    pub fn clear_halt(&mut self) {
+        self.rebalancing_halted_until = 0;
+    }
+
+    fn push_observation(&mut self, observation: PriceObservation, replace_latest: bool) {
+        if replace_latest {
+            self.observations[self.observation_index as usize] = observation;
+            return;
+        }
+        if self.observation_count > 0 {
+            self.observation_index =
+                ((self.observation_index as usize + 1) % MAX_PRICE_OBSERVATIONS) as u8;
+        }
+        self.observations[self.observation_index as usize] = observation;
+        if (self.observation_count as usize) < MAX_PRICE_OBSERVATIONS {
+            self.observation_count += 1;
+        }
+    }
+}
+
+/// Per-position rebalance limits, a PDA of `[b"position_risk_state", position]`.
+///
+/// Bounds how often keepers can move a position, so a misconfigured keeper cannot burn its
+/// fees rebalancing it over and over. Epochs are the cluster's.
+#[account]
+#[derive(Default, Debug)]
+pub struct PositionRiskState {
+    /// Bump seed for PDA.
+    pub bump: u8,
+    /// The amm_core position this state limits.
+    pub position: Pubkey,
+    /// Most rebalances executed in one epoch.
+    pub max_rebalances_per_epoch: u16,
+    /// Epoch `rebalances_in_epoch` counts for.
+    pub epoch: u64,
+    /// Rebalances executed so far in `epoch`.
+    pub rebalances_in_epoch: u16,
+}
+
+impl PositionRiskState {
+    /// The size of the PositionRiskState account in bytes.
+    pub const LEN: usize = 8 // discriminator
+        + 1 // bump
+        + 32 // position
+        + 2 // max_rebalances_per_epoch
+        + 8 // epoch
+        + 2; // rebalances_in_epoch
+
+    pub fn initialize(
+        &mut self,
+        bump: u8,
+        position: Pubkey,
+        max_rebalances_per_epoch: u16,
+    ) -> Result<()> {
+        self.bump = bump;
+        self.position = position;
+        self.epoch = 0;
+        self.rebalances_in_epoch = 0;
+        self.set_max_rebalances_per_epoch(max_rebalances_per_epoch)
+    }
+
+    /// Changes the limit. The rebalances already counted in the current epoch still count
+    /// against the new one. Fails with `InvalidRiskConfig` for a zero limit.
+    pub fn set_max_rebalances_per_epoch(&mut self, max_rebalances_per_epoch: u16) -> Result<()> {
+        if max_rebalances_per_epoch == 0 {
+            return err!(ErrorCode::InvalidRiskConfig);
+        }
+        self.max_rebalances_per_epoch = max_rebalances_per_epoch;
+        Ok(())
+    }
+
+    /// Counts a rebalance executed in `epoch`, starting the count over in a new epoch.
+    ///
+    /// Fails with `RebalanceLimitReached` once `max_rebalances_per_epoch` rebalances were
+    /// counted in `epoch`.
+    pub fn record_rebalance(&mut self, epoch: u64) -> Result<()> {
+        if epoch != self.epoch {
+            self.epoch = epoch;
+            self.rebalances_in_epoch = 0;
+        }
+        if self.rebalances_in_epoch >= self.max_rebalances_per_epoch {
+            return err!(ErrorCode::RebalanceLimitReached);
+        }
+        self.rebalances_in_epoch += 1;
+        Ok(())
+    }
+}
+
+/// Lamports a keeper has bonded to submit rebalances, a PDA of `[b"keeper_bond", keeper]`.
+///
+/// The account holds the bonded lamports on top of its rent-exempt balance.
+#[account]
+#[derive(Default, Debug)]
+pub struct KeeperBond {
+    /// Bump seed for PDA.
+    pub bump: u8,
+    /// The keeper that deposited the bond and can withdraw it.
+    pub keeper: Pubkey,
+    /// Bonded lamports, above the account's rent-exempt balance.
+    pub amount: u64,
+    /// Unix timestamp until which the bond cannot be withdrawn: the latest dispute
+    /// deadline of its submissions.
+    pub locked_until: i64,
+    /// The keeper's latest rebalances, in no particular order.
+    pub submissions: [KeeperSubmission; MAX_OPEN_KEEPER_SUBMISSIONS],
+}
+
+impl KeeperBond {
+    /// The size of the KeeperBond account in bytes.
+    pub const LEN: usize = 8 // discriminator
+        + 1 // bump
+        + 32 // keeper
+        + 8 // amount
+        + 8 // locked_until
+        + MAX_OPEN_KEEPER_SUBMISSIONS * KeeperSubmission::LEN; // submissions
+
+    pub fn initialize(&mut self, bump: u8, keeper: Pubkey) {
+        self.bump = bump;
+        self.keeper = keeper;
+        self.amount = 0;
+        self.locked_until = 0;
+        self.submissions = [KeeperSubmission::default(); MAX_OPEN_KEEPER_SUBMISSIONS];
+    }
+
+    /// Adds `amount` deposited lamports to the bond.
+    pub fn deposit(&mut self, amount: u64) -> Result<()> {
+        self.amount = self.amount.checked_add(amount).ok_or(ErrorCode::Overflow)?;
+        Ok(())
+    }
+
+    /// Removes `amount` lamports from the bond for the keeper to withdraw.
+    ///
+    /// Fails with `KeeperBondLocked` while a submission can still be disputed, and with
+    /// `InsufficientKeeperBond` if the bond holds less than `amount`.
+    pub fn withdraw(&mut self, amount: u64, now: i64) -> Result<()> {
+        if now <= self.locked_until {
+            return err!(ErrorCode::KeeperBondLocked);
+        }
+        self.amount = self
+            .amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientKeeperBond)?;
+        Ok(())
+    }
+
+    /// Records a rebalance of `position` from `old_range` to `new_range` at `now`, open to
+    /// disputes for `KEEPER_DISPUTE_WINDOW_SECS`, and returns its dispute deadline.
+    ///
+    /// Fails with `InsufficientKeeperBond` below `MIN_KEEPER_BOND_LAMPORTS`, and with
+    /// `TooManyOpenSubmissions` if all `MAX_OPEN_KEEPER_SUBMISSIONS` slots can still be
+    /// disputed.
+    pub fn record_submission(
+        &mut self,
+        position: Pubkey,
+        (old_tick_lower, old_tick_upper): (i32, i32),
+        (new_tick_lower, new_tick_upper): (i32, i32),
+        entry_sqrt_price_q64: u128,
+        now: i64,
+    ) -> Result<i64> {
+        if self.amount < MIN_KEEPER_BOND_LAMPORTS {
+            return err!(ErrorCode::InsufficientKeeperBond);
+        }
+        let slot = self
+            .submissions
+            .iter_mut()
+            .find(|submission| !submission.is_open(now))
+            .ok_or(ErrorCode::TooManyOpenSubmissions)?;
+        let dispute_deadline = now
+            .checked_add(KEEPER_DISPUTE_WINDOW_SECS)
+            .ok_or(ErrorCode::Overflow)?;
+        *slot = KeeperSubmission {
+            position,
+            old_tick_lower,
+            old_tick_upper,
+            new_tick_lower,
+            new_tick_upper,
+            entry_sqrt_price_q64,
+            submitted_at: now,
+            dispute_deadline,
+            bond_snapshot: self.amount,
+            slashed: false,
+        };
+        self.locked_until = self.locked_until.max(dispute_deadline);
+        Ok(dispute_deadline)
+    }
+
+    /// Index of the latest rebalance of `position` that can be disputed at `now`.
+    ///
+    /// Fails with `SubmissionNotFound` if no rebalance of the position is recorded or the
+    /// latest was already slashed, and with `DisputeWindowClosed` once its window closed.
+    pub fn disputable_submission(&self, position: &Pubkey, now: i64) -> Result<usize> {
+        let (index, submission) = self
+            .submissions
+            .iter()
+            .enumerate()
+            .filter(|(_, submission)| {
+                submission.dispute_deadline != 0 && submission.position == *position
+            })
+            .max_by_key(|(_, submission)| submission.submitted_at)
+            .ok_or(ErrorCode::SubmissionNotFound)?;
+        if submission.slashed {
+            return err!(ErrorCode::SubmissionNotFound);
+        }
+        if !submission.is_open(now) {
+            return err!(ErrorCode::DisputeWindowClosed);
+        }
+        Ok(index)
+    }
+
+    /// Slashes the keeper for the submission at `index` if its new range did worse than
+    /// the kept one by more than `KEEPER_SLASH_TOLERANCE` at `checkpoint_sqrt_price_q64`.
+    ///
+    /// Returns the lamports taken from the bond, `KEEPER_SLASH_BPS` of its size at
+    /// submission time or what is left of it, and the harm measured. Fails with
+    /// `SlashNotJustified` if the new range did not do worse than that.
+    pub fn slash(
+        &mut self,
+        index: usize,
+        checkpoint_sqrt_price_q64: u128,
+    ) -> Result<(u64, IlPercentage)> {
+        let submission = &mut self.submissions[index];
+        let harm = keeper_bond::rebalance_harm(submission, checkpoint_sqrt_price_q64)?;
+        if harm <= KEEPER_SLASH_TOLERANCE {
+            return err!(ErrorCode::SlashNotJustified);
+        }
+        submission.slashed = true;
+        let amount = submission.slash_amount().min(self.amount);
+        self.amount -= amount;
+        Ok((amount, harm))
+    }
+}
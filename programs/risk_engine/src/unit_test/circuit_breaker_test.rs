@@ -0,0 +1,417 @@
+use crate::circuit_breaker::{
+    max_twap_move_bps, price_move_bps, scaled_price_to_sqrt_price_q64,
+    sqrt_price_q64_to_scaled_price, time_weighted_average_price, PriceObservation,
+};
+use crate::errors::RiskEngineError;
+use crate::state::{
+    PoolCategory, PoolRiskState, RiskConfig, RiskConfigParams, MAX_PRICE_OBSERVATIONS,
+};
+use anchor_lang::prelude::*;
+
+const SCALE: u128 = 1_000_000;
+const HALT_DURATION_SECS: i64 = 3_600;
+
+fn observation(timestamp: i64, price: u128) -> PriceObservation {
+    PriceObservation {
+        timestamp,
+        price,
+        twap: price,
+    }
+}
+
+/// 5% TWAP move within 10 minutes halts rebalancing for an hour. TWAP over 5 minutes.
+fn config() -> RiskConfig {
+    let mut config = RiskConfig::default();
+    config
+        .initialize(
+            1,
+            Pubkey::new_unique(),
+            RiskConfigParams {
+                circuit_breaker_threshold_bps: 500,
+                circuit_breaker_window_secs: 600,
+                twap_interval_secs: 300,
+                halt_duration_secs: HALT_DURATION_SECS,
+                max_staleness_secs: 60,
+                stable_volatility_prior_bps: 500,
+                major_volatility_prior_bps: 8_000,
+                long_tail_volatility_prior_bps: 20_000,
+            },
+        )
+        .unwrap();
+    config
+}
+
+fn risk_state() -> PoolRiskState {
+    let mut state = PoolRiskState::default();
+    state.initialize(1, Pubkey::new_unique(), 8_000);
+    state
+}
+
+mod twap_tests {
+    use super::*;
+
+    #[test]
+    fn test_twap_without_observations_is_none() {
+        assert_eq!(time_weighted_average_price(&[], 100, 60).unwrap(), None);
+    }
+
+    #[test]
+    fn test_twap_weights_prices_by_duration() {
+        // 100 for 30s, then 200 for 10s.
+        let observations = [observation(0, 100), observation(30, 200)];
+        assert_eq!(
+            time_weighted_average_price(&observations, 40, 40).unwrap(),
+            Some(125)
+        );
+    }
+
+    #[test]
+    fn test_twap_ignores_time_before_interval() {
+        // Only the last 20s count: 100 for 10s, then 200 for 10s.
+        let observations = [observation(0, 100), observation(30, 200)];
+        assert_eq!(
+            time_weighted_average_price(&observations, 40, 20).unwrap(),
+            Some(150)
+        );
+    }
+
+    #[test]
+    fn test_twap_with_no_elapsed_time_is_latest_price() {
+        let observations = [observation(10, 100)];
+        assert_eq!(
+            time_weighted_average_price(&observations, 10, 60).unwrap(),
+            Some(100)
+        );
+    }
+
+    #[test]
+    fn test_price_move_bps() {
+        assert_eq!(price_move_bps(100 * SCALE, 95 * SCALE).unwrap(), 500);
+        assert_eq!(price_move_bps(100 * SCALE, 110 * SCALE).unwrap(), 1_000);
+        assert_eq!(
+            price_move_bps(0, SCALE).unwrap_err(),
+            RiskEngineError::CalculationError.into()
+        );
+    }
+
+    #[test]
+    fn test_max_twap_move_only_considers_window() {
+        let observations = [observation(0, 50 * SCALE), observation(500, 98 * SCALE)];
+        // The observation at t=0 is outside the 300s window.
+        assert_eq!(
+            max_twap_move_bps(&observations, 100 * SCALE, 600, 300).unwrap(),
+            Some((98 * SCALE, 204))
+        );
+        assert_eq!(
+            max_twap_move_bps(&observations, 100 * SCALE, 1_000, 300).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sqrt_price_to_scaled_price() {
+        assert_eq!(
+            sqrt_price_q64_to_scaled_price(1u128 << 64, SCALE).unwrap(),
+            SCALE
+        );
+        // sqrt(4) = 2
+        assert_eq!(
+            sqrt_price_q64_to_scaled_price(2u128 << 64, SCALE).unwrap(),
+            4 * SCALE
+        );
+    }
+
+    #[test]
+    fn test_scaled_price_to_sqrt_price() {
+        assert_eq!(
+            scaled_price_to_sqrt_price_q64(SCALE, SCALE).unwrap(),
+            1u128 << 64
+        );
+        assert_eq!(
+            scaled_price_to_sqrt_price_q64(4 * SCALE, SCALE).unwrap(),
+            2u128 << 64
+        );
+        // Round trips to within the rounding of the scaled price
+        let price = 1_234_567;
+        let sqrt_price_q64 = scaled_price_to_sqrt_price_q64(price, SCALE).unwrap();
+        assert!(price - sqrt_price_q64_to_scaled_price(sqrt_price_q64, SCALE).unwrap() <= 1);
+        assert_eq!(
+            scaled_price_to_sqrt_price_q64(price, 0).unwrap_err(),
+            RiskEngineError::CalculationError.into()
+        );
+    }
+}
+
+mod risk_config_tests {
+    use super::*;
+
+    fn params() -> RiskConfigParams {
+        RiskConfigParams {
+            circuit_breaker_threshold_bps: 500,
+            circuit_breaker_window_secs: 600,
+            twap_interval_secs: 300,
+            halt_duration_secs: 3_600,
+            max_staleness_secs: 60,
+            stable_volatility_prior_bps: 500,
+            major_volatility_prior_bps: 8_000,
+            long_tail_volatility_prior_bps: 20_000,
+        }
+    }
+
+    #[test]
+    fn test_invalid_params_are_rejected() {
+        let invalid = [
+            RiskConfigParams {
+                circuit_breaker_threshold_bps: 0,
+                ..params()
+            },
+            RiskConfigParams {
+                circuit_breaker_threshold_bps: 10_001,
+                ..params()
+            },
+            RiskConfigParams {
+                circuit_breaker_window_secs: 0,
+                ..params()
+            },
+            RiskConfigParams {
+                twap_interval_secs: -1,
+                ..params()
+            },
+            RiskConfigParams {
+                halt_duration_secs: 0,
+                ..params()
+            },
+            RiskConfigParams {
+                max_staleness_secs: 0,
+                ..params()
+            },
+            RiskConfigParams {
+                stable_volatility_prior_bps: 0,
+                ..params()
+            },
+            RiskConfigParams {
+                long_tail_volatility_prior_bps: 0,
+                ..params()
+            },
+        ];
+        for params in invalid {
+            let result = RiskConfig::default().initialize(1, Pubkey::new_unique(), params);
+            assert_eq!(
+                result.unwrap_err(),
+                RiskEngineError::InvalidRiskConfig.into()
+            );
+        }
+    }
+
+    #[test]
+    fn test_initialize_leaves_shadow_mode_off() {
+        let mut config = RiskConfig {
+            shadow_mode: true,
+            ..RiskConfig::default()
+        };
+        config
+            .initialize(1, Pubkey::new_unique(), params())
+            .unwrap();
+        assert!(!config.shadow_mode);
+    }
+
+    #[test]
+    fn test_volatility_prior_follows_pool_category() {
+        let mut config = RiskConfig::default();
+        config
+            .initialize(1, Pubkey::new_unique(), params())
+            .unwrap();
+        assert_eq!(config.volatility_prior_bps(PoolCategory::Stable), 500);
+        assert_eq!(config.volatility_prior_bps(PoolCategory::Major), 8_000);
+        assert_eq!(config.volatility_prior_bps(PoolCategory::LongTail), 20_000);
+    }
+}
+
+mod circuit_breaker_tests {
+    use super::*;
+
+    /// Feeds `prices` one minute apart starting at `start` and attempts a rebalance after
+    /// each sample. Returns the timestamps of the trips and of the rebalances that went through.
+    fn run_series(
+        state: &mut PoolRiskState,
+        config: &RiskConfig,
+        start: i64,
+        prices: &[u128],
+    ) -> (Vec<i64>, Vec<i64>) {
+        let mut trips = Vec::new();
+        let mut rebalances = Vec::new();
+        for (i, &price) in prices.iter().enumerate() {
+            let now = start + 60 * i as i64;
+            // Mirrors trigger_rebalance_check: refuse while halted, skip on the tripping sample.
+            if state.ensure_rebalancing_allowed(now).is_err() {
+                continue;
+            }
+            if state.record_price(now, price, config).unwrap().is_some() {
+                trips.push(now);
+                continue;
+            }
+            rebalances.push(now);
+        }
+        (trips, rebalances)
+    }
+
+    /// Stable at 1.00, then a depeg that bottoms out at 0.50 and stays there.
+    fn depeg_series() -> Vec<u128> {
+        let mut prices = vec![SCALE; 10];
+        prices.extend([980_000, 930_000, 850_000, 750_000, 600_000, 500_000]);
+        prices.extend(vec![500_000; 120]);
+        prices
+    }
+
+    #[test]
+    fn test_stable_prices_never_trip() {
+        let config = config();
+        let mut state = risk_state();
+        let prices: Vec<u128> = (0..60).map(|i| SCALE + (i % 3) * 5_000).collect();
+
+        let (trips, rebalances) = run_series(&mut state, &config, 1_000, &prices);
+
+        assert!(trips.is_empty());
+        assert_eq!(rebalances.len(), prices.len());
+        assert!(!state.is_halted(1_000 + 60 * prices.len() as i64));
+    }
+
+    #[test]
+    fn test_depeg_halts_rebalancing_for_halt_window() {
+        let config = config();
+        let mut state = risk_state();
+
+        let (trips, rebalances) = run_series(&mut state, &config, 1_000, &depeg_series());
+
+        let tripped_at = trips[0];
+        let halted_until = tripped_at + HALT_DURATION_SECS;
+        // The breaker trips while the price is falling, not after it has settled.
+        assert!(tripped_at <= 1_000 + 60 * 15);
+        assert!(rebalances
+            .iter()
+            .all(|&t| t < tripped_at || t >= halted_until));
+        // Rebalancing resumes once the halt expires and the price has settled.
+        assert!(rebalances.iter().any(|&t| t >= halted_until));
+    }
+
+    #[test]
+    fn test_record_price_refuses_rebalance_during_halt() {
+        let config = config();
+        let mut state = risk_state();
+        run_series(&mut state, &config, 1_000, &depeg_series()[..16]);
+
+        assert!(state.is_halted(1_000 + 60 * 16));
+        assert_eq!(
+            state
+                .ensure_rebalancing_allowed(1_000 + 60 * 16)
+                .unwrap_err(),
+            RiskEngineError::RebalancingHalted.into()
+        );
+    }
+
+    #[test]
+    fn test_governance_clear_resumes_rebalancing() {
+        let config = config();
+        let mut state = risk_state();
+        run_series(&mut state, &config, 1_000, &depeg_series()[..16]);
+        let now = 1_000 + 60 * 16;
+        assert!(state.is_halted(now));
+
+        state.clear_halt();
+
+        assert!(!state.is_halted(now));
+        assert!(state.ensure_rebalancing_allowed(now).is_ok());
+    }
+
+    #[test]
+    fn test_samples_during_halt_do_not_extend_it() {
+        let config = config();
+        let mut state = risk_state();
+        let (trips, _) = run_series(&mut state, &config, 1_000, &depeg_series()[..16]);
+        let halted_until = state.rebalancing_halted_until;
+
+        // A keeper keeps recording a falling price while halted.
+        let now = 1_000 + 60 * 16;
+        assert_eq!(state.record_price(now, 300_000, &config).unwrap(), None);
+
+        assert_eq!(trips.len(), 1);
+        assert_eq!(state.rebalancing_halted_until, halted_until);
+    }
+
+    #[test]
+    fn test_sample_at_same_timestamp_replaces_latest() {
+        let config = config();
+        let mut state = risk_state();
+        state.record_price(100, SCALE, &config).unwrap();
+        state.record_price(100, 2 * SCALE, &config).unwrap();
+
+        let observations = state.observations_chronological();
+        assert_eq!(observations.len(), 1);
+        assert_eq!(observations[0].price, 2 * SCALE);
+    }
+
+    #[test]
+    fn test_older_sample_is_rejected() {
+        let config = config();
+        let mut state = risk_state();
+        state.record_price(100, SCALE, &config).unwrap();
+
+        assert_eq!(
+            state.record_price(99, SCALE, &config).unwrap_err(),
+            RiskEngineError::InvalidObservationTimestamp.into()
+        );
+    }
+
+    #[test]
+    fn test_observation_ring_keeps_latest_samples() {
+        let config = config();
+        let mut state = risk_state();
+        let samples = MAX_PRICE_OBSERVATIONS as i64 + 5;
+        for i in 0..samples {
+            state.record_price(i * 60, SCALE, &config).unwrap();
+        }
+
+        let observations = state.observations_chronological();
+        assert_eq!(observations.len(), MAX_PRICE_OBSERVATIONS);
+        assert_eq!(observations[0].timestamp, 5 * 60);
+        assert_eq!(observations.last().unwrap().timestamp, (samples - 1) * 60);
+    }
+}
+
+mod reference_price_tests {
+    use crate::reference_sqrt_price_q64;
+    use amm_core::state::pool::Pool as AmmPool;
+
+    const SPOT: u128 = 1 << 64;
+    const ORACLE: u128 = 3 << 63;
+
+    fn amm_pool() -> AmmPool {
+        AmmPool {
+            sqrt_price_q64: SPOT,
+            max_oracle_age_slots: 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_spot_price_without_oracle() {
+        assert_eq!(reference_sqrt_price_q64(&amm_pool(), 100).unwrap(), SPOT);
+    }
+
+    #[test]
+    fn test_oracle_price_replaces_spot_price() {
+        let mut pool = amm_pool();
+        pool.set_oracle_price(ORACLE, 95, 100).unwrap();
+        assert_eq!(reference_sqrt_price_q64(&pool, 105).unwrap(), ORACLE);
+    }
+
+    #[test]
+    fn test_stale_oracle_price_is_rejected() {
+        let mut pool = amm_pool();
+        pool.set_oracle_price(ORACLE, 95, 100).unwrap();
+        assert_eq!(
+            reference_sqrt_price_q64(&pool, 106).unwrap_err(),
+            amm_core::errors::ErrorCode::OraclePriceStale.into()
+        );
+    }
+}
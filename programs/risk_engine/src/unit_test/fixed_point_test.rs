@@ -0,0 +1,93 @@
+use crate::fixed_point::FixedI128;
+use amm_core::math::FixedQ64;
+
+type Percent = FixedI128<100>;
+type Billionths = FixedI128<1_000_000_000>;
+
+mod arithmetic_tests {
+    use super::*;
+
+    #[test]
+    fn test_products_and_quotients_keep_the_scale() {
+        let a = Percent::from_raw(150); // 1.5
+        let b = Percent::from_raw(250); // 2.5
+        assert_eq!(a.checked_mul(b), Some(Percent::from_raw(375)));
+        assert_eq!(b.checked_div(a), Some(Percent::from_raw(166)));
+        assert_eq!(a.checked_add(b), Some(Percent::from_int(4).unwrap()));
+        assert_eq!(a.checked_sub(b), Some(Percent::from_raw(-100)));
+    }
+
+    #[test]
+    fn test_results_truncate_toward_zero() {
+        let third = Percent::ONE.checked_div(Percent::from_int(3).unwrap());
+        assert_eq!(third, Some(Percent::from_raw(33)));
+        let minus_third = Percent::ONE.checked_div(Percent::from_int(-3).unwrap());
+        assert_eq!(minus_third, Some(Percent::from_raw(-33)));
+        assert_eq!(
+            Percent::from_raw(-150).checked_mul(Percent::from_raw(-101)),
+            Some(Percent::from_raw(151))
+        );
+    }
+
+    #[test]
+    fn test_sign_handling() {
+        let value = Percent::from_raw(-150);
+        assert!(value.is_negative());
+        assert!(!Percent::ZERO.is_negative());
+        assert_eq!(value.checked_neg(), Some(Percent::from_raw(150)));
+        assert_eq!(value.checked_abs(), Some(Percent::from_raw(150)));
+    }
+
+    #[test]
+    fn test_overflow_is_rejected() {
+        let max = Percent::from_raw(i128::MAX);
+        let min = Percent::from_raw(i128::MIN);
+        assert_eq!(max.checked_add(Percent::ONE), None);
+        assert_eq!(min.checked_sub(Percent::ONE), None);
+        assert_eq!(max.checked_mul(Percent::from_int(2).unwrap()), None);
+        assert_eq!(max.checked_div(Percent::from_raw(50)), None);
+        assert_eq!(Percent::ONE.checked_div(Percent::ZERO), None);
+        assert_eq!(min.checked_neg(), None);
+        assert_eq!(min.checked_abs(), None);
+        assert_eq!(FixedI128::<{ 10u128.pow(30) }>::from_int(i64::MAX), None);
+        // The most negative value is reachable, the same magnitude positive is not
+        assert_eq!(min.checked_mul(Percent::ONE), Some(min));
+        assert_eq!(min.checked_mul(Percent::from_int(-1).unwrap()), None);
+    }
+}
+
+mod conversion_tests {
+    use super::*;
+
+    #[test]
+    fn test_rescale_compares_across_scales() {
+        let percent = Percent::from_raw(-150);
+        assert_eq!(
+            percent.rescale::<1_000_000_000>(),
+            Some(Billionths::from_raw(-1_500_000_000))
+        );
+        // Going down in scale truncates toward zero
+        assert_eq!(
+            Billionths::from_raw(-1_239_999_999).rescale::<100>(),
+            Some(Percent::from_raw(-123))
+        );
+        assert_eq!(Percent::from_raw(i128::MAX).rescale::<1_000>(), None);
+    }
+
+    #[test]
+    fn test_from_fixed_q64() {
+        let two_and_a_half = FixedQ64::from_i64(5) / FixedQ64::from_i64(2);
+        assert_eq!(
+            Percent::from_fixed_q64(two_and_a_half),
+            Some(Percent::from_raw(250))
+        );
+        assert_eq!(
+            Percent::from_fixed_q64(-two_and_a_half),
+            Some(Percent::from_raw(-250))
+        );
+        assert_eq!(
+            FixedI128::<{ 10u128.pow(30) }>::from_fixed_q64(FixedQ64::MAX),
+            None
+        );
+    }
+}
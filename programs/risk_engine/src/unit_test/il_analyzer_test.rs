@@ -0,0 +1,299 @@
+use crate::errors::RiskEngineError;
+use crate::il_analyzer::{
+    calculate_cumulative_il, calculate_current_il_percentage, calculate_historical_il,
+    calculate_position_il_percentage, IlPercentage, IL_PERCENTAGE_SCALE,
+};
+use amm_core::constants::{MAX_TICK, MIN_TICK};
+use amm_core::math::tick_to_sqrt_price_q64;
+use amm_core::position::PositionData as AmmPositionData;
+
+const Q64: u128 = 1 << 64;
+
+fn full_range_il(entry_sqrt_price_q64: u128, current_sqrt_price_q64: u128) -> i128 {
+    calculate_current_il_percentage(
+        MIN_TICK,
+        MAX_TICK,
+        entry_sqrt_price_q64,
+        current_sqrt_price_q64,
+    )
+    .unwrap()
+    .raw()
+}
+
+mod calculate_current_il_percentage_tests {
+    use super::*;
+
+    #[test]
+    fn test_unchanged_price_has_no_il() {
+        assert_eq!(full_range_il(Q64, Q64), 0);
+        assert_eq!(full_range_il(3 * Q64, 3 * Q64), 0);
+    }
+
+    #[test]
+    fn test_price_quadrupling_loses_twenty_percent() {
+        // sqrt(k) = 2: IL = -(2 - 1)^2 / (2^2 + 1) = -20%
+        let expected = -20 * IL_PERCENTAGE_SCALE as i128;
+        assert_eq!(full_range_il(Q64, 2 * Q64), expected);
+        // IL is the same whether the price rises or falls by the same factor.
+        assert_eq!(full_range_il(2 * Q64, Q64), expected);
+    }
+
+    #[test]
+    fn test_matches_exact_ratio() {
+        // IL = -(S_current - S_initial)^2 / (S_current^2 + S_initial^2), to within one unit
+        for (entry, current) in [
+            (Q64, 3 * Q64),
+            (Q64, Q64 + Q64 / 100),
+            (7 * Q64, 5 * Q64),
+            (Q64 / 1000, Q64),
+        ] {
+            let diff = entry.abs_diff(current) as f64;
+            let (entry_f, current_f) = (entry as f64, current as f64);
+            let expected = -(diff * diff) / (entry_f * entry_f + current_f * current_f)
+                * 100.0
+                * IL_PERCENTAGE_SCALE as f64;
+            let il = full_range_il(entry, current);
+            assert!(
+                (il as f64 - expected).abs() <= 1.0,
+                "IL for {entry} -> {current}: {il} vs {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_zero_entry_price_has_no_il() {
+        assert_eq!(full_range_il(0, Q64), 0);
+    }
+
+    #[test]
+    fn test_extreme_prices_are_valued_without_overflow() {
+        // At MIN_TICK the sqrt price is a single Q64.64 unit, whose inverse amm_core's
+        // token amounts cannot represent
+        let min_sqrt_price = tick_to_sqrt_price_q64(MIN_TICK + 100_000).unwrap();
+        let max_sqrt_price = tick_to_sqrt_price_q64(MAX_TICK).unwrap();
+        for (entry, current) in [
+            (Q64, max_sqrt_price),
+            (Q64, min_sqrt_price),
+            (max_sqrt_price, min_sqrt_price),
+        ] {
+            let il = full_range_il(entry, current);
+            assert!((-100 * IL_PERCENTAGE_SCALE as i128..=0).contains(&il));
+        }
+    }
+
+    #[test]
+    fn test_il_only_depends_on_price_ratios() {
+        // A position on 600 ticks entered at their middle loses ~0.7555% when the price
+        // reaches the upper tick, whatever the price level
+        for center in [0, 400_000, -400_000] {
+            let il = calculate_current_il_percentage(
+                center - 300,
+                center + 300,
+                tick_to_sqrt_price_q64(center).unwrap(),
+                tick_to_sqrt_price_q64(center + 300).unwrap(),
+            )
+            .unwrap()
+            .raw();
+            assert!(
+                (il + 755_530_176).abs() < 100_000,
+                "IL around {center}: {il}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty_range_is_rejected() {
+        assert_eq!(
+            calculate_current_il_percentage(10, 10, Q64, 2 * Q64).unwrap_err(),
+            RiskEngineError::CalculationError.into()
+        );
+    }
+}
+
+/// A position on [0.9, 1.1) entered at price 1, against IL computed with 50 digit decimals
+/// from the token amounts `L * (1/S - 1/S_upper)` and `L * (S - S_lower)`.
+mod concentrated_range_il_tests {
+    use super::*;
+
+    /// Ticks of prices 0.89997 and 1.09998.
+    const RANGE: (i32, i32) = (-1_054, 953);
+
+    fn il_at_tick(tick: i32) -> i128 {
+        calculate_current_il_percentage(
+            RANGE.0,
+            RANGE.1,
+            Q64,
+            tick_to_sqrt_price_q64(tick).unwrap(),
+        )
+        .unwrap()
+        .raw()
+    }
+
+    fn assert_il_at_tick(tick: i32, expected: i128) {
+        let il = il_at_tick(tick);
+        assert!(
+            (il - expected).abs() <= 1_000,
+            "IL at tick {tick}: {il} vs {expected}"
+        );
+    }
+
+    #[test]
+    fn test_il_in_range_is_amplified() {
+        // Price 1.0513: -0.639%, against -0.031% for a full range position
+        assert_il_at_tick(500, -639_200_406);
+        // Price 0.9512: -0.638%
+        assert_il_at_tick(-500, -637_634_292);
+        assert!(full_range_il(Q64, tick_to_sqrt_price_q64(500).unwrap()) > -40_000_000);
+    }
+
+    #[test]
+    fn test_il_at_range_bounds() {
+        // Price 1.1: the position has just turned fully into token1
+        assert_il_at_tick(RANGE.1, -2_323_101_516);
+        // Price 0.9: the position has just turned fully into token0
+        assert_il_at_tick(RANGE.0, -2_827_062_545);
+    }
+
+    #[test]
+    fn test_il_out_of_range_holds_one_token() {
+        // Price 1.2214: all token1, which no longer gains with the price
+        assert_il_at_tick(2_000, -7_424_463_894);
+        // Price 0.7788: all token0, which keeps losing with the price
+        assert_il_at_tick(-2_500, -10_495_501_690);
+    }
+
+    #[test]
+    fn test_no_il_while_range_stays_on_one_side() {
+        // Entered below the range and still below it: the position held token0 throughout
+        let il = calculate_current_il_percentage(
+            RANGE.0,
+            RANGE.1,
+            tick_to_sqrt_price_q64(-3_000).unwrap(),
+            tick_to_sqrt_price_q64(-2_000).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(il, IlPercentage::ZERO);
+    }
+}
+
+mod calculate_position_il_percentage_tests {
+    use super::*;
+
+    fn position(entry_sqrt_price_q64: u128) -> AmmPositionData {
+        AmmPositionData {
+            tick_lower_index: MIN_TICK,
+            tick_upper_index: MAX_TICK,
+            entry_sqrt_price_q64,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_il_is_measured_from_stored_entry_price() {
+        assert_eq!(
+            calculate_position_il_percentage(&position(Q64), 2 * Q64)
+                .unwrap()
+                .raw(),
+            full_range_il(Q64, 2 * Q64)
+        );
+        assert_eq!(
+            calculate_position_il_percentage(&position(2 * Q64), 2 * Q64).unwrap(),
+            IlPercentage::ZERO
+        );
+    }
+
+    #[test]
+    fn test_il_uses_stored_range() {
+        let mut position = position(Q64);
+        position.tick_lower_index = -10;
+        position.tick_upper_index = 10;
+        // Above the narrow range the position is fully in token1 and misses the gains
+        // of the token0 it held at entry, so it loses more than a full range position
+        let il = calculate_position_il_percentage(&position, 2 * Q64).unwrap();
+        assert_eq!(
+            il,
+            calculate_current_il_percentage(-10, 10, Q64, 2 * Q64).unwrap()
+        );
+        assert!(il.raw() < full_range_il(Q64, 2 * Q64));
+    }
+}
+
+mod historical_il_tests {
+    use super::*;
+
+    const HISTORY: [u128; 5] = [Q64, 2 * Q64, Q64, Q64 / 2, 3 * Q64];
+
+    fn raw(il: Vec<IlPercentage>) -> Vec<i128> {
+        il.into_iter().map(IlPercentage::raw).collect()
+    }
+
+    #[test]
+    fn test_il_at_each_price_after_entry() {
+        let il = calculate_historical_il(&HISTORY, MIN_TICK, MAX_TICK, 1).unwrap();
+        assert_eq!(
+            raw(il),
+            vec![
+                full_range_il(2 * Q64, Q64),
+                full_range_il(2 * Q64, Q64 / 2),
+                full_range_il(2 * Q64, 3 * Q64),
+            ]
+        );
+
+        let il = calculate_historical_il(&HISTORY, MIN_TICK, MAX_TICK, 0).unwrap();
+        assert_eq!(il.len(), HISTORY.len() - 1);
+        assert_eq!(il[1], IlPercentage::ZERO);
+    }
+
+    #[test]
+    fn test_entry_index_must_be_in_history() {
+        assert!(calculate_historical_il(&HISTORY, MIN_TICK, MAX_TICK, 4)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            calculate_historical_il(&HISTORY, MIN_TICK, MAX_TICK, 5).unwrap_err(),
+            RiskEngineError::InvalidEntryIndex.into()
+        );
+    }
+
+    #[test]
+    fn test_cumulative_il_without_fees_is_historical_il() {
+        assert_eq!(
+            calculate_cumulative_il(&HISTORY, MIN_TICK, MAX_TICK, 0, 30, 0).unwrap(),
+            calculate_historical_il(&HISTORY, MIN_TICK, MAX_TICK, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cumulative_il_adds_fees_earned_in_range() {
+        // 0.3% on a volume of the position's value: 0.3% per interval
+        let fees_per_interval = 3 * IL_PERCENTAGE_SCALE as i128 / 10;
+        let historical = raw(calculate_historical_il(&HISTORY, MIN_TICK, MAX_TICK, 0).unwrap());
+        let cumulative =
+            raw(calculate_cumulative_il(&HISTORY, MIN_TICK, MAX_TICK, 0, 30, 10_000).unwrap());
+        for (i, (net, il)) in cumulative.iter().zip(&historical).enumerate() {
+            assert_eq!(*net, il + (i as i128 + 1) * fees_per_interval);
+        }
+    }
+
+    #[test]
+    fn test_cumulative_il_earns_no_fees_out_of_range() {
+        // [0, 100) holds only the prices at Q64: intervals starting at 1 and 3 earn nothing
+        let fees_per_interval = 3 * IL_PERCENTAGE_SCALE as i128 / 10;
+        let historical = raw(calculate_historical_il(&HISTORY, 0, 100, 0).unwrap());
+        let cumulative = raw(calculate_cumulative_il(&HISTORY, 0, 100, 0, 30, 10_000).unwrap());
+        let fees: Vec<i128> = cumulative
+            .iter()
+            .zip(&historical)
+            .map(|(net, il)| net - il)
+            .collect();
+        assert_eq!(
+            fees,
+            vec![
+                fees_per_interval,
+                fees_per_interval,
+                2 * fees_per_interval,
+                2 * fees_per_interval,
+            ]
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use crate::checkpoint_sqrt_price_q64;
+use crate::errors::RiskEngineError;
+use crate::il_analyzer::IlPercentage;
+use crate::keeper_bond::{
+    rebalance_harm, KeeperSubmission, KEEPER_DISPUTE_WINDOW_SECS, MAX_OPEN_KEEPER_SUBMISSIONS,
+    MIN_KEEPER_BOND_LAMPORTS,
+};
+use crate::state::KeeperBond;
+use amm_core::math::tick_to_sqrt_price_q64;
+use amm_core::state::pool::Pool as AmmPool;
+use anchor_lang::prelude::*;
+
+const BOND: u64 = 1_000_000_000;
+const NOW: i64 = 1_000_000;
+/// The range the position is moved from.
+const KEPT_RANGE: (i32, i32) = (-600, 600);
+
+fn bond() -> KeeperBond {
+    let mut bond = KeeperBond::default();
+    bond.initialize(1, Pubkey::new_unique());
+    bond.deposit(BOND).unwrap();
+    bond
+}
+
+/// Records a rebalance of `position` at tick 0 from `KEPT_RANGE` to `new_range`.
+fn submit(bond: &mut KeeperBond, position: Pubkey, new_range: (i32, i32), now: i64) -> i64 {
+    bond.record_submission(
+        position,
+        KEPT_RANGE,
+        new_range,
+        tick_to_sqrt_price_q64(0).unwrap(),
+        now,
+    )
+    .unwrap()
+}
+
+mod submission_tests {
+    use super::*;
+
+    #[test]
+    fn test_submission_snapshots_bond_and_locks_it() {
+        let mut bond = bond();
+        let position = Pubkey::new_unique();
+        let deadline = submit(&mut bond, position, (-1_200, 1_200), NOW);
+
+        assert_eq!(deadline, NOW + KEEPER_DISPUTE_WINDOW_SECS);
+        assert_eq!(bond.locked_until, deadline);
+        let submission = bond.submissions[bond.disputable_submission(&position, NOW).unwrap()];
+        assert_eq!(submission.bond_snapshot, BOND);
+        assert_eq!(submission.submitted_at, NOW);
+        assert_eq!(
+            (submission.new_tick_lower, submission.new_tick_upper),
+            (-1_200, 1_200)
+        );
+        assert!(!submission.slashed);
+    }
+
+    #[test]
+    fn test_submission_requires_minimum_bond() {
+        let mut bond = KeeperBond::default();
+        bond.initialize(1, Pubkey::new_unique());
+        bond.deposit(MIN_KEEPER_BOND_LAMPORTS - 1).unwrap();
+        let result = bond.record_submission(
+            Pubkey::new_unique(),
+            KEPT_RANGE,
+            (-1_200, 1_200),
+            tick_to_sqrt_price_q64(0).unwrap(),
+            NOW,
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            RiskEngineError::InsufficientKeeperBond.into()
+        );
+    }
+
+    #[test]
+    fn test_slots_free_up_once_dispute_windows_close() {
+        let mut bond = bond();
+        for _ in 0..MAX_OPEN_KEEPER_SUBMISSIONS {
+            submit(&mut bond, Pubkey::new_unique(), (-1_200, 1_200), NOW);
+        }
+        let result = bond.record_submission(
+            Pubkey::new_unique(),
+            KEPT_RANGE,
+            (-1_200, 1_200),
+            tick_to_sqrt_price_q64(0).unwrap(),
+            NOW + 1,
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            RiskEngineError::TooManyOpenSubmissions.into()
+        );
+
+        let later = NOW + KEEPER_DISPUTE_WINDOW_SECS + 1;
+        let deadline = submit(&mut bond, Pubkey::new_unique(), (-1_200, 1_200), later);
+        assert_eq!(bond.locked_until, deadline);
+    }
+
+    #[test]
+    fn test_dispute_targets_latest_rebalance_of_position() {
+        let mut bond = bond();
+        let position = Pubkey::new_unique();
+        submit(&mut bond, position, (-1_200, 1_200), NOW);
+        submit(&mut bond, Pubkey::new_unique(), (-1_200, 1_200), NOW + 5);
+        submit(&mut bond, position, (-2_400, 2_400), NOW + 10);
+
+        let index = bond.disputable_submission(&position, NOW + 20).unwrap();
+        assert_eq!(bond.submissions[index].submitted_at, NOW + 10);
+        assert_eq!(
+            bond.disputable_submission(&Pubkey::new_unique(), NOW + 20)
+                .unwrap_err(),
+            RiskEngineError::SubmissionNotFound.into()
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_is_fresh_oracle_price() {
+        let mut pool = AmmPool {
+            sqrt_price_q64: 1 << 64,
+            max_oracle_age_slots: 10,
+            ..Default::default()
+        };
+        assert_eq!(
+            checkpoint_sqrt_price_q64(&pool, 100).unwrap_err(),
+            RiskEngineError::CheckpointPriceUnavailable.into()
+        );
+
+        pool.set_oracle_price(3 << 63, 95, 100).unwrap();
+        assert_eq!(checkpoint_sqrt_price_q64(&pool, 105).unwrap(), 3 << 63);
+        assert_eq!(
+            checkpoint_sqrt_price_q64(&pool, 106).unwrap_err(),
+            amm_core::errors::ErrorCode::OraclePriceStale.into()
+        );
+    }
+}
+
+mod slash_tests {
+    use super::*;
+
+    #[test]
+    fn test_rebalance_harm_compares_new_and_kept_ranges() {
+        let submission = KeeperSubmission {
+            old_tick_lower: KEPT_RANGE.0,
+            old_tick_upper: KEPT_RANGE.1,
+            new_tick_lower: -60,
+            new_tick_upper: 60,
+            entry_sqrt_price_q64: tick_to_sqrt_price_q64(0).unwrap(),
+            ..KeeperSubmission::default()
+        };
+        // At tick 600 the price is up ~6.2%: the kept range has lost ~1.52% and the
+        // narrower new range, concentrated on the price it has left, ~2.85%.
+        let harm = rebalance_harm(&submission, tick_to_sqrt_price_q64(600).unwrap()).unwrap();
+        assert!(harm > IlPercentage::from_int(1).unwrap());
+        assert!(harm < IlPercentage::from_int(2).unwrap());
+
+        // Moving to a wider range loses less.
+        let widened = KeeperSubmission {
+            new_tick_lower: -6_000,
+            new_tick_upper: 6_000,
+            ..submission
+        };
+        let harm = rebalance_harm(&widened, tick_to_sqrt_price_q64(600).unwrap()).unwrap();
+        assert!(harm < IlPercentage::ZERO);
+    }
+
+    #[test]
+    fn test_justified_slash_pays_share_of_bond_at_submission() {
+        let mut bond = bond();
+        let position = Pubkey::new_unique();
+        submit(&mut bond, position, (-60, 60), NOW);
+        // A later top-up is not at stake for the earlier rebalance.
+        bond.deposit(BOND).unwrap();
+
+        let index = bond.disputable_submission(&position, NOW + 60).unwrap();
+        let (amount, harm) = bond
+            .slash(index, tick_to_sqrt_price_q64(600).unwrap())
+            .unwrap();
+        assert_eq!(amount, BOND / 2);
+        assert!(harm > IlPercentage::ZERO);
+        assert_eq!(bond.amount, 2 * BOND - BOND / 2);
+        assert!(bond.submissions[index].slashed);
+
+        // A rebalance is only slashed once.
+        assert_eq!(
+            bond.disputable_submission(&position, NOW + 60).unwrap_err(),
+            RiskEngineError::SubmissionNotFound.into()
+        );
+    }
+
+    #[test]
+    fn test_unjustified_slash_is_rejected() {
+        let mut bond = bond();
+        let position = Pubkey::new_unique();
+        submit(&mut bond, position, (-6_000, 6_000), NOW);
+
+        let index = bond.disputable_submission(&position, NOW + 60).unwrap();
+        assert_eq!(
+            bond.slash(index, tick_to_sqrt_price_q64(300).unwrap())
+                .unwrap_err(),
+            RiskEngineError::SlashNotJustified.into()
+        );
+        assert_eq!(bond.amount, BOND);
+        assert!(!bond.submissions[index].slashed);
+    }
+
+    #[test]
+    fn test_harm_within_tolerance_is_not_slashed() {
+        let mut bond = bond();
+        let position = Pubkey::new_unique();
+        submit(&mut bond, position, (-480, 480), NOW);
+
+        // At tick 600 the slightly narrower new range has lost ~0.3% more than the kept one.
+        let index = bond.disputable_submission(&position, NOW + 60).unwrap();
+        let checkpoint = tick_to_sqrt_price_q64(600).unwrap();
+        assert!(rebalance_harm(&bond.submissions[index], checkpoint).unwrap() > IlPercentage::ZERO);
+        assert_eq!(
+            bond.slash(index, checkpoint).unwrap_err(),
+            RiskEngineError::SlashNotJustified.into()
+        );
+    }
+
+    #[test]
+    fn test_no_dispute_after_window() {
+        let mut bond = bond();
+        let position = Pubkey::new_unique();
+        let deadline = submit(&mut bond, position, (3_000, 6_000), NOW);
+
+        assert!(bond.disputable_submission(&position, deadline).is_ok());
+        assert_eq!(
+            bond.disputable_submission(&position, deadline + 1)
+                .unwrap_err(),
+            RiskEngineError::DisputeWindowClosed.into()
+        );
+    }
+}
+
+mod withdrawal_tests {
+    use super::*;
+
+    #[test]
+    fn test_withdrawal_after_dispute_window() {
+        let mut bond = bond();
+        let deadline = submit(&mut bond, Pubkey::new_unique(), (3_000, 6_000), NOW);
+
+        assert_eq!(
+            bond.withdraw(BOND, deadline).unwrap_err(),
+            RiskEngineError::KeeperBondLocked.into()
+        );
+        bond.withdraw(BOND, deadline + 1).unwrap();
+        assert_eq!(bond.amount, 0);
+    }
+
+    #[test]
+    fn test_withdrawal_limited_to_bond() {
+        let mut bond = bond();
+        assert_eq!(
+            bond.withdraw(BOND + 1, NOW).unwrap_err(),
+            RiskEngineError::InsufficientKeeperBond.into()
+        );
+        bond.withdraw(BOND / 4, NOW).unwrap();
+        assert_eq!(bond.amount, BOND - BOND / 4);
+    }
+}
@@ -1 +1,11 @@
+pub mod circuit_breaker_test;
+pub mod fixed_point_test;
+pub mod il_analyzer_test;
+pub mod keeper_bond_test;
+pub mod pool_characteristics_test;
+pub mod position_optimizer_test;
+pub mod position_risk_state_test;
+pub mod pyth_test;
+#[cfg(feature = "simulation")]
+pub mod simulation_test;
 pub mod volatility_detector_test;
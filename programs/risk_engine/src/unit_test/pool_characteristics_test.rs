@@ -0,0 +1,59 @@
+use crate::pool_characteristics::PoolCharacteristics;
+use amm_core::constants::VOLUME_WINDOW_SECS;
+use amm_core::state::pool::Pool as AmmPool;
+
+/// Noon on the second day.
+const NOW: i64 = VOLUME_WINDOW_SECS + VOLUME_WINDOW_SECS / 2;
+
+fn pool() -> AmmPool {
+    AmmPool {
+        liquidity: 1 << 70,
+        fee_rate: 100,
+        fee_tier: 30,
+        volume_24h: 25_000_000,
+        volume_window_start: VOLUME_WINDOW_SECS,
+        ..AmmPool::default()
+    }
+}
+
+mod from_live_pool_tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_liquidity_fee_tier_and_volume() {
+        assert_eq!(
+            PoolCharacteristics::from_live_pool(&pool(), NOW),
+            PoolCharacteristics {
+                liquidity_depth: 1 << 70,
+                // The tier the pool was created with, not its current fee rate
+                fee_tier: 30,
+                trading_volume_24h: 25_000_000,
+            }
+        );
+    }
+
+    #[test]
+    fn test_volume_of_an_earlier_day_is_not_reported() {
+        let next_day = NOW + VOLUME_WINDOW_SECS;
+        let characteristics = PoolCharacteristics::from_live_pool(&pool(), next_day);
+        assert_eq!(characteristics.trading_volume_24h, 0);
+        assert_eq!(characteristics.liquidity_depth, 1 << 70);
+    }
+
+    #[test]
+    fn test_follows_swaps_recorded_by_the_pool() {
+        let mut pool = pool();
+        pool.record_volume(5_000_000, NOW);
+        assert_eq!(
+            PoolCharacteristics::from_live_pool(&pool, NOW).trading_volume_24h,
+            30_000_000
+        );
+
+        let next_day = NOW + VOLUME_WINDOW_SECS;
+        pool.record_volume(1_000, next_day);
+        assert_eq!(
+            PoolCharacteristics::from_live_pool(&pool, next_day).trading_volume_24h,
+            1_000
+        );
+    }
+}
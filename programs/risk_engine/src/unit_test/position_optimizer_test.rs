@@ -0,0 +1,746 @@
+use crate::errors::RiskEngineError;
+use crate::il_analyzer::IlPercentage;
+use crate::position_optimizer::{
+    bias_range_toward_undersupplied, calculate_optimal_boundaries_mvp, compute_asymmetric_range,
+    compute_optimal_range, compute_split_ranges, recommend_liquidity_split, should_rebalance,
+    validate_proposed_range, LiquiditySplitConfig, PriceBoundary, SplitRange, MAX_SPLIT_RANGES,
+    MVP_RANGE_HORIZON_DAYS, MVP_RANGE_Z_SCORE_BPS,
+};
+use amm_core::constants::{FEE_TIER_HIGH, FEE_TIER_LOW, FEE_TIER_MEDIUM, MAX_TICK, MIN_TICK};
+use amm_core::liquidity_histogram::{LiquidityHistogram, HISTOGRAM_BUCKETS};
+use amm_core::math::tick_to_sqrt_price_q64;
+use anchor_lang::prelude::Pubkey;
+
+mod validate_proposed_range_tests {
+    use super::*;
+
+    #[test]
+    fn test_range_containing_current_tick_is_accepted() {
+        assert!(validate_proposed_range(-600, 600, 0, false).is_ok());
+        // The lower bound is inclusive.
+        assert!(validate_proposed_range(0, 600, 0, false).is_ok());
+    }
+
+    #[test]
+    fn test_range_above_current_price_is_rejected_by_default() {
+        let result = validate_proposed_range(60, 600, 0, false);
+        assert_eq!(
+            result.unwrap_err(),
+            RiskEngineError::ProposedRangeExcludesCurrentPrice.into()
+        );
+    }
+
+    #[test]
+    fn test_range_below_current_price_is_rejected_by_default() {
+        let result = validate_proposed_range(-600, -60, 0, false);
+        assert_eq!(
+            result.unwrap_err(),
+            RiskEngineError::ProposedRangeExcludesCurrentPrice.into()
+        );
+    }
+
+    #[test]
+    fn test_range_ending_at_current_tick_is_rejected_by_default() {
+        // The upper bound is exclusive: at tick 600 the position is already inactive.
+        let result = validate_proposed_range(0, 600, 600, false);
+        assert_eq!(
+            result.unwrap_err(),
+            RiskEngineError::ProposedRangeExcludesCurrentPrice.into()
+        );
+    }
+
+    #[test]
+    fn test_one_sided_range_is_accepted_when_allowed() {
+        assert!(validate_proposed_range(60, 600, 0, true).is_ok());
+        assert!(validate_proposed_range(-600, -60, 0, true).is_ok());
+    }
+}
+
+mod recommend_liquidity_split_tests {
+    use super::*;
+
+    const PRICE_ONE_Q64: u128 = 1u128 << 64;
+    const NARROW: (i32, i32) = (-100, 100);
+    const WIDE: (i32, i32) = (-2_000, 2_000);
+    const BUDGET: u128 = 1_000_000_000;
+
+    fn wide_share_bps(volatility_annualized_scaled: u128) -> u16 {
+        let split = recommend_liquidity_split(
+            BUDGET,
+            NARROW,
+            WIDE,
+            PRICE_ONE_Q64,
+            volatility_annualized_scaled,
+            LiquiditySplitConfig::default(),
+        )
+        .unwrap();
+        10_000 - split.first_share_bps
+    }
+
+    #[test]
+    fn test_higher_volatility_shifts_split_toward_wider_range() {
+        let calm = wide_share_bps(300_000_000);
+        let volatile = wide_share_bps(1_200_000_000);
+        assert!(calm < 5_000, "calm markets favour the narrow range: {calm}");
+        assert!(volatile > calm, "expected {volatile} > {calm}");
+
+        let mut previous = 0;
+        for volatility in [100_000_000, 300_000_000, 600_000_000, 1_200_000_000] {
+            let share = wide_share_bps(volatility);
+            assert!(share >= previous, "share fell to {share} at {volatility}");
+            previous = share;
+        }
+    }
+
+    #[test]
+    fn test_split_uses_the_whole_budget() {
+        let split = recommend_liquidity_split(
+            BUDGET + 7,
+            NARROW,
+            WIDE,
+            PRICE_ONE_Q64,
+            800_000_000,
+            LiquiditySplitConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(split.first_liquidity + split.second_liquidity, BUDGET + 7);
+        assert_eq!(
+            split.first_liquidity,
+            (BUDGET + 7) * split.first_share_bps as u128 / 10_000
+        );
+    }
+
+    #[test]
+    fn test_min_share_is_respected() {
+        let config = LiquiditySplitConfig {
+            min_share_bps: 4_000,
+            ..LiquiditySplitConfig::default()
+        };
+        let split =
+            recommend_liquidity_split(BUDGET, NARROW, WIDE, PRICE_ONE_Q64, 300_000_000, config)
+                .unwrap();
+        assert_eq!(split.first_share_bps, 6_000);
+    }
+
+    #[test]
+    fn test_invalid_min_share_is_rejected() {
+        let config = LiquiditySplitConfig {
+            min_share_bps: 5_001,
+            ..LiquiditySplitConfig::default()
+        };
+        let result =
+            recommend_liquidity_split(BUDGET, NARROW, WIDE, PRICE_ONE_Q64, 300_000_000, config);
+        assert_eq!(
+            result.unwrap_err(),
+            RiskEngineError::InvalidLiquiditySplitConfig.into()
+        );
+    }
+
+    #[test]
+    fn test_range_excluding_current_price_is_rejected() {
+        let result = recommend_liquidity_split(
+            BUDGET,
+            (100, 200),
+            WIDE,
+            PRICE_ONE_Q64,
+            300_000_000,
+            LiquiditySplitConfig::default(),
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            RiskEngineError::ProposedRangeExcludesCurrentPrice.into()
+        );
+    }
+}
+
+mod calculate_optimal_boundaries_mvp_tests {
+    use super::*;
+
+    const PRICE_ONE_Q64: u128 = 1u128 << 64;
+    const VOLATILITY_40_PERCENT: u128 = 400_000_000;
+    const VOLATILITY_80_PERCENT: u128 = 800_000_000;
+
+    fn boundaries(volatility: u128, horizon_days: u32, tick_spacing: u16) -> (i32, i32) {
+        calculate_optimal_boundaries_mvp(
+            PRICE_ONE_Q64,
+            volatility,
+            MVP_RANGE_Z_SCORE_BPS,
+            horizon_days,
+            tick_spacing,
+        )
+        .unwrap()
+    }
+
+    fn width((tick_lower, tick_upper): (i32, i32)) -> i32 {
+        tick_upper - tick_lower
+    }
+
+    #[test]
+    fn test_half_width_is_z_score_band_in_ticks() {
+        // 1.5 * 0.8 * sqrt(1 / 365) / ln(1.0001) = 628.1 ticks
+        assert_eq!(
+            boundaries(VOLATILITY_80_PERCENT, MVP_RANGE_HORIZON_DAYS, 1),
+            (-628, 628)
+        );
+        // Widened outward to the tick spacing
+        assert_eq!(
+            boundaries(VOLATILITY_80_PERCENT, MVP_RANGE_HORIZON_DAYS, 60),
+            (-660, 660)
+        );
+
+        let half_width_at = |k_bps| {
+            calculate_optimal_boundaries_mvp(PRICE_ONE_Q64, VOLATILITY_80_PERCENT, k_bps, 1, 1)
+                .unwrap()
+                .1
+        };
+        assert_eq!(half_width_at(30_000), 1_256);
+    }
+
+    #[test]
+    fn test_doubling_volatility_doubles_width() {
+        for tick_spacing in [1, 10, 60] {
+            for horizon_days in [1, 7, 30] {
+                let calm = width(boundaries(
+                    VOLATILITY_40_PERCENT,
+                    horizon_days,
+                    tick_spacing,
+                ));
+                let wild = width(boundaries(
+                    VOLATILITY_80_PERCENT,
+                    horizon_days,
+                    tick_spacing,
+                ));
+                // Each bound is off by less than a tick spacing from the exact band
+                assert!(
+                    (wild - 2 * calm).abs() <= 4 * tick_spacing as i32,
+                    "{calm} {wild}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_width_grows_with_square_root_of_horizon() {
+        let one_day = width(boundaries(VOLATILITY_80_PERCENT, 1, 1));
+        let four_days = width(boundaries(VOLATILITY_80_PERCENT, 4, 1));
+        assert!((four_days - 2 * one_day).abs() <= 4);
+    }
+
+    #[test]
+    fn test_band_is_centered_on_current_tick() {
+        for current_tick in [-50_000, -1_234, 0, 777, 123_456] {
+            let sqrt_price_q64 = tick_to_sqrt_price_q64(current_tick).unwrap();
+            for tick_spacing in [1, 60] {
+                let (tick_lower, tick_upper) = calculate_optimal_boundaries_mvp(
+                    sqrt_price_q64,
+                    VOLATILITY_80_PERCENT,
+                    MVP_RANGE_Z_SCORE_BPS,
+                    7,
+                    tick_spacing,
+                )
+                .unwrap();
+                let below = current_tick - tick_lower;
+                let above = tick_upper - current_tick;
+                assert!(below > 0 && above > 0);
+                assert!((below - above).abs() <= tick_spacing as i32);
+                assert_eq!(tick_lower % tick_spacing as i32, 0);
+                assert_eq!(tick_upper % tick_spacing as i32, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_is_clamped_and_never_empty() {
+        let (tick_lower, tick_upper) = boundaries(u64::MAX as u128, 365, 60);
+        assert_eq!((tick_lower, tick_upper), (-887_220, 887_220));
+        assert!(tick_lower >= MIN_TICK && tick_upper <= MAX_TICK);
+
+        // No volatility still gives one tick spacing around the current tick
+        assert_eq!(boundaries(0, 1, 60), (0, 60));
+
+        let (tick_lower, tick_upper) = calculate_optimal_boundaries_mvp(
+            0,
+            VOLATILITY_80_PERCENT,
+            MVP_RANGE_Z_SCORE_BPS,
+            1,
+            60,
+        )
+        .unwrap();
+        assert_eq!((tick_lower, tick_upper), (-887_220, 887_220));
+    }
+
+    #[test]
+    fn test_zero_tick_spacing_is_rejected() {
+        assert_eq!(
+            calculate_optimal_boundaries_mvp(
+                PRICE_ONE_Q64,
+                VOLATILITY_80_PERCENT,
+                MVP_RANGE_Z_SCORE_BPS,
+                1,
+                0
+            )
+            .unwrap_err(),
+            RiskEngineError::CalculationError.into()
+        );
+    }
+}
+
+mod compute_optimal_range_tests {
+    use super::*;
+
+    const PRICE_ONE_Q64: u128 = 1u128 << 64;
+    const VOLATILITY_80_PERCENT: u128 = 800_000_000;
+
+    #[test]
+    fn test_range_is_symmetric_in_ticks_around_current_price() {
+        // 1.5 * 0.8 * sqrt(1 / 365) of log price is about 628 ticks
+        let range =
+            compute_optimal_range(PRICE_ONE_Q64, VOLATILITY_80_PERCENT, FEE_TIER_MEDIUM, 1, 10)
+                .unwrap();
+        assert_eq!((range.tick_lower, range.tick_upper), (-630, 630));
+        assert_eq!(
+            range.sqrt_price_lower_q64,
+            tick_to_sqrt_price_q64(-630).unwrap()
+        );
+        assert_eq!(
+            range.sqrt_price_upper_q64,
+            tick_to_sqrt_price_q64(630).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_lower_fee_tier_gives_narrower_range() {
+        let width = |fee_tier| {
+            let range = compute_optimal_range(PRICE_ONE_Q64, VOLATILITY_80_PERCENT, fee_tier, 7, 1)
+                .unwrap();
+            range.tick_upper - range.tick_lower
+        };
+        assert!(width(FEE_TIER_LOW) < width(FEE_TIER_MEDIUM));
+        assert!(width(FEE_TIER_MEDIUM) < width(FEE_TIER_HIGH));
+        assert!(width(FEE_TIER_HIGH) < width(10_000));
+    }
+
+    #[test]
+    fn test_longer_horizon_and_higher_volatility_widen_range() {
+        let width = |volatility, days| {
+            let range =
+                compute_optimal_range(PRICE_ONE_Q64, volatility, FEE_TIER_HIGH, days, 60).unwrap();
+            range.tick_upper - range.tick_lower
+        };
+        assert!(width(VOLATILITY_80_PERCENT, 1) < width(VOLATILITY_80_PERCENT, 30));
+        assert!(width(200_000_000, 7) < width(VOLATILITY_80_PERCENT, 7));
+    }
+
+    #[test]
+    fn test_bounds_are_snapped_outward_to_tick_spacing() {
+        let current_sqrt_price = tick_to_sqrt_price_q64(1_234).unwrap();
+        for tick_spacing in [1u16, 10, 60, 200] {
+            let range = compute_optimal_range(
+                current_sqrt_price,
+                VOLATILITY_80_PERCENT,
+                FEE_TIER_MEDIUM,
+                1,
+                tick_spacing,
+            )
+            .unwrap();
+            let spacing = tick_spacing as i32;
+            assert_eq!(range.tick_lower % spacing, 0);
+            assert_eq!(range.tick_upper % spacing, 0);
+            assert!(range.tick_lower <= 1_234 - 628 && 1_234 + 628 <= range.tick_upper);
+        }
+    }
+
+    #[test]
+    fn test_zero_volatility_gives_one_spacing_around_current_tick() {
+        let range = compute_optimal_range(PRICE_ONE_Q64, 0, FEE_TIER_MEDIUM, 1, 10).unwrap();
+        assert_eq!((range.tick_lower, range.tick_upper), (0, 10));
+
+        let current_sqrt_price = tick_to_sqrt_price_q64(-15).unwrap();
+        let range = compute_optimal_range(current_sqrt_price, 0, FEE_TIER_MEDIUM, 1, 10).unwrap();
+        assert_eq!((range.tick_lower, range.tick_upper), (-20, -10));
+    }
+
+    #[test]
+    fn test_extreme_volatility_is_clamped_to_usable_ticks() {
+        let range = compute_optimal_range(PRICE_ONE_Q64, 1_000_000_000_000, FEE_TIER_HIGH, 365, 60)
+            .unwrap();
+        assert_eq!(range.tick_lower, -(-MIN_TICK / 60) * 60);
+        assert_eq!(range.tick_upper, (MAX_TICK / 60) * 60);
+    }
+
+    #[test]
+    fn test_zero_tick_spacing_is_rejected() {
+        let result =
+            compute_optimal_range(PRICE_ONE_Q64, VOLATILITY_80_PERCENT, FEE_TIER_LOW, 1, 0);
+        assert_eq!(
+            result.unwrap_err(),
+            RiskEngineError::CalculationError.into()
+        );
+    }
+
+    #[test]
+    fn test_drift_shifts_range_in_its_direction() {
+        let symmetric = compute_optimal_range(
+            PRICE_ONE_Q64,
+            VOLATILITY_80_PERCENT,
+            FEE_TIER_MEDIUM,
+            30,
+            10,
+        )
+        .unwrap();
+        let drifted = |drift| {
+            compute_asymmetric_range(
+                PRICE_ONE_Q64,
+                VOLATILITY_80_PERCENT,
+                drift,
+                FEE_TIER_MEDIUM,
+                30,
+                10,
+            )
+            .unwrap()
+        };
+        assert_eq!(drifted(0), symmetric);
+
+        // A 50% annual drift over 30 days moves the center by about 411 ticks
+        let up = drifted(500_000_000);
+        let down = drifted(-500_000_000);
+        assert!(up.tick_lower > symmetric.tick_lower && up.tick_upper > symmetric.tick_upper);
+        assert!(down.tick_lower < symmetric.tick_lower && down.tick_upper < symmetric.tick_upper);
+        assert_eq!(up.tick_lower, -down.tick_upper);
+        assert_eq!(up.tick_upper, -down.tick_lower);
+        assert!((up.tick_lower + up.tick_upper - 2 * 411).abs() <= 20);
+    }
+}
+
+mod should_rebalance_tests {
+    use super::*;
+
+    /// 100 SOL.
+    const POSITION_VALUE: u64 = 100_000_000_000;
+
+    fn il(percent_thousandths: i64) -> IlPercentage {
+        IlPercentage::from_int(percent_thousandths)
+            .unwrap()
+            .checked_div(IlPercentage::from_int(1_000).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rebalance_when_saving_exceeds_cost() {
+        // 2% of 100 SOL is 2 SOL saved for a 0.01 SOL move.
+        assert!(should_rebalance(
+            il(-2_000),
+            IlPercentage::ZERO,
+            10_000_000,
+            POSITION_VALUE
+        ));
+    }
+
+    #[test]
+    fn test_bad_il_is_not_worth_a_more_expensive_rebalance() {
+        // 5% IL on a 1 SOL position saves 0.05 SOL, less than a 0.1 SOL rebalance.
+        assert!(!should_rebalance(
+            il(-5_000),
+            IlPercentage::ZERO,
+            100_000_000,
+            1_000_000_000
+        ));
+        // The same rebalance pays off on a position a hundred times larger.
+        assert!(should_rebalance(
+            il(-5_000),
+            IlPercentage::ZERO,
+            100_000_000,
+            100 * 1_000_000_000
+        ));
+    }
+
+    #[test]
+    fn test_saving_must_clear_hysteresis_on_top_of_cost() {
+        // The hysteresis is 0.01% of the value, 0.01 SOL here.
+        assert!(!should_rebalance(
+            il(-10),
+            IlPercentage::ZERO,
+            0,
+            POSITION_VALUE
+        ));
+        assert!(should_rebalance(
+            il(-11),
+            IlPercentage::ZERO,
+            0,
+            POSITION_VALUE
+        ));
+        // 0.015% saves 0.015 SOL, short of a 0.01 SOL cost plus the hysteresis.
+        assert!(!should_rebalance(
+            il(-15),
+            IlPercentage::ZERO,
+            10_000_000,
+            POSITION_VALUE
+        ));
+    }
+
+    #[test]
+    fn test_no_rebalance_without_il_reduction() {
+        assert!(!should_rebalance(il(-1_000), il(-1_000), 0, POSITION_VALUE));
+        assert!(!should_rebalance(il(-1_000), il(-2_000), 0, POSITION_VALUE));
+        assert!(!should_rebalance(
+            IlPercentage::ZERO,
+            IlPercentage::ZERO,
+            0,
+            POSITION_VALUE
+        ));
+    }
+
+    #[test]
+    fn test_saving_is_measured_from_projected_il() {
+        // Moving from -3% to a projected -1% saves 2% of the value.
+        assert!(should_rebalance(
+            il(-3_000),
+            il(-1_000),
+            1_900_000_000,
+            POSITION_VALUE
+        ));
+        assert!(!should_rebalance(
+            il(-3_000),
+            il(-1_000),
+            2_000_000_000,
+            POSITION_VALUE
+        ));
+    }
+
+    #[test]
+    fn test_empty_position_is_never_rebalanced() {
+        assert!(!should_rebalance(il(-50_000), IlPercentage::ZERO, 0, 0));
+    }
+}
+
+mod compute_split_ranges_tests {
+    use super::*;
+
+    const PRICE_ONE_Q64: u128 = 1u128 << 64;
+    const VOLATILITY_80_PERCENT: u128 = 800_000_000;
+    const BUDGET: u128 = 1_000_000_000_000;
+
+    fn split(num_ranges: u8) -> Vec<SplitRange> {
+        compute_split_ranges(
+            PRICE_ONE_Q64,
+            VOLATILITY_80_PERCENT,
+            FEE_TIER_MEDIUM,
+            1,
+            10,
+            num_ranges,
+            BUDGET,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_bands_are_nested_around_current_price() {
+        let mut ranges: Vec<(i32, i32)> = split(4)
+            .iter()
+            .map(|range| (range.boundary.tick_lower, range.boundary.tick_upper))
+            .collect();
+        ranges.sort_by_key(|&(tick_lower, tick_upper)| tick_upper - tick_lower);
+        // Quarters of the ~628 tick half-width of the optimal range, snapped outward
+        assert_eq!(
+            ranges,
+            vec![(-160, 160), (-320, 320), (-480, 480), (-630, 630)]
+        );
+    }
+
+    #[test]
+    fn test_single_band_is_the_optimal_range_with_the_whole_budget() {
+        let ranges = split(1);
+        let optimal =
+            compute_optimal_range(PRICE_ONE_Q64, VOLATILITY_80_PERCENT, FEE_TIER_MEDIUM, 1, 10)
+                .unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].boundary, optimal);
+        assert_eq!(ranges[0].liquidity, BUDGET);
+        assert_eq!(ranges[0].share_bps, 10_000);
+    }
+
+    #[test]
+    fn test_narrower_bands_get_more_of_the_budget() {
+        let mut ranges = split(3);
+        assert_eq!(
+            ranges.iter().map(|range| range.liquidity).sum::<u128>(),
+            BUDGET
+        );
+
+        ranges.sort_by_key(|range| range.boundary.tick_upper - range.boundary.tick_lower);
+        for pair in ranges.windows(2) {
+            assert!(pair[0].liquidity > pair[1].liquidity);
+            assert!(pair[0].share_bps > pair[1].share_bps);
+        }
+        // Widths of 420, 840 and 1260 ticks share the budget 6:3:2
+        assert_eq!(
+            ranges
+                .iter()
+                .map(|range| range.share_bps)
+                .collect::<Vec<_>>(),
+            vec![5_454, 2_727, 1_818]
+        );
+    }
+
+    #[test]
+    fn test_bands_are_sorted_by_net_score() {
+        let ranges = split(5);
+        assert!(ranges
+            .windows(2)
+            .all(|pair| pair[0].net_score >= pair[1].net_score));
+        assert!(ranges[0].net_score > 0);
+    }
+
+    #[test]
+    fn test_number_of_bands_is_capped() {
+        assert_eq!(split(MAX_SPLIT_RANGES + 3).len(), MAX_SPLIT_RANGES as usize);
+    }
+
+    #[test]
+    fn test_zero_bands_or_tick_spacing_is_rejected() {
+        let result = compute_split_ranges(
+            PRICE_ONE_Q64,
+            VOLATILITY_80_PERCENT,
+            FEE_TIER_MEDIUM,
+            1,
+            10,
+            0,
+            BUDGET,
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            RiskEngineError::InvalidSplitRangeCount.into()
+        );
+
+        let result = compute_split_ranges(
+            PRICE_ONE_Q64,
+            VOLATILITY_80_PERCENT,
+            FEE_TIER_MEDIUM,
+            1,
+            0,
+            2,
+            BUDGET,
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            RiskEngineError::CalculationError.into()
+        );
+    }
+
+    #[test]
+    fn test_extreme_volatility_keeps_bands_within_usable_ticks() {
+        let ranges = compute_split_ranges(
+            PRICE_ONE_Q64,
+            1_000_000_000_000,
+            FEE_TIER_HIGH,
+            365,
+            60,
+            MAX_SPLIT_RANGES,
+            BUDGET,
+        )
+        .unwrap();
+        for range in &ranges {
+            assert!(range.boundary.tick_lower >= MIN_TICK);
+            assert!(range.boundary.tick_upper <= MAX_TICK);
+        }
+    }
+}
+
+mod bias_range_toward_undersupplied_tests {
+    use super::*;
+
+    const NOW: i64 = 1_000_000;
+    const MAX_AGE: i64 = 60;
+
+    /// Histogram over [-640, 640) in buckets of 20 ticks, refreshed at `NOW`, holding
+    /// `below` up to tick 20 and `above` from there.
+    fn histogram(below: u128, above: u128) -> LiquidityHistogram {
+        let mut liquidity = [below; HISTOGRAM_BUCKETS];
+        for bucket in liquidity.iter_mut().skip(33) {
+            *bucket = above;
+        }
+        LiquidityHistogram {
+            liquidity,
+            pool: Pubkey::new_unique(),
+            updated_at: NOW,
+            lower_tick: -640,
+            bucket_ticks: 20,
+        }
+    }
+
+    fn range(tick_lower: i32, tick_upper: i32) -> PriceBoundary {
+        PriceBoundary {
+            tick_lower,
+            tick_upper,
+            sqrt_price_lower_q64: tick_to_sqrt_price_q64(tick_lower).unwrap(),
+            sqrt_price_upper_q64: tick_to_sqrt_price_q64(tick_upper).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_range_moves_toward_thinner_liquidity_and_keeps_the_price() {
+        let biased = bias_range_toward_undersupplied(
+            range(-40, 60),
+            0,
+            &histogram(1_000, 5_000),
+            NOW,
+            MAX_AGE,
+        )
+        .unwrap();
+        assert_eq!(biased, range(-80, 20));
+
+        let biased = bias_range_toward_undersupplied(
+            range(-40, 60),
+            0,
+            &histogram(5_000, 1_000),
+            NOW,
+            MAX_AGE,
+        )
+        .unwrap();
+        // Moving up further would leave tick 0 out of the range
+        assert_eq!(biased, range(0, 100));
+        validate_proposed_range(biased.tick_lower, biased.tick_upper, 0, false).unwrap();
+    }
+
+    #[test]
+    fn test_flat_liquidity_keeps_the_range() {
+        let flat = histogram(1_000, 1_000);
+        assert_eq!(
+            bias_range_toward_undersupplied(range(-40, 60), 0, &flat, NOW, MAX_AGE).unwrap(),
+            range(-40, 60)
+        );
+    }
+
+    #[test]
+    fn test_stale_histogram_keeps_the_range() {
+        let skewed = histogram(1_000, 5_000);
+        assert_eq!(
+            bias_range_toward_undersupplied(range(-40, 60), 0, &skewed, NOW + MAX_AGE + 1, MAX_AGE)
+                .unwrap(),
+            range(-40, 60)
+        );
+        let never_refreshed = LiquidityHistogram {
+            updated_at: i64::MIN,
+            ..skewed
+        };
+        assert_eq!(
+            bias_range_toward_undersupplied(range(-40, 60), 0, &never_refreshed, NOW, MAX_AGE)
+                .unwrap(),
+            range(-40, 60)
+        );
+    }
+
+    #[test]
+    fn test_range_outside_histogram_is_kept() {
+        let skewed = histogram(1_000, 5_000);
+        assert_eq!(
+            bias_range_toward_undersupplied(range(-1_000, 60), 0, &skewed, NOW, MAX_AGE).unwrap(),
+            range(-1_000, 60)
+        );
+        // Shifts are limited to the histogram
+        assert_eq!(
+            bias_range_toward_undersupplied(range(-620, 620), 0, &skewed, NOW, MAX_AGE).unwrap(),
+            range(-640, 600)
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use crate::errors::RiskEngineError;
+use crate::state::PositionRiskState;
+use anchor_lang::prelude::*;
+
+const MAX_REBALANCES: u16 = 3;
+const EPOCH: u64 = 500;
+
+fn position_risk_state() -> PositionRiskState {
+    let mut state = PositionRiskState::default();
+    state
+        .initialize(1, Pubkey::new_unique(), MAX_REBALANCES)
+        .unwrap();
+    state
+}
+
+mod rebalance_limit_tests {
+    use super::*;
+
+    #[test]
+    fn test_rebalance_past_the_limit_is_rejected() {
+        let mut state = position_risk_state();
+        for _ in 0..MAX_REBALANCES {
+            state.record_rebalance(EPOCH).unwrap();
+        }
+
+        assert_eq!(
+            state.record_rebalance(EPOCH).unwrap_err(),
+            RiskEngineError::RebalanceLimitReached.into()
+        );
+        assert_eq!(state.rebalances_in_epoch, MAX_REBALANCES);
+    }
+
+    #[test]
+    fn test_count_resets_next_epoch() {
+        let mut state = position_risk_state();
+        for _ in 0..MAX_REBALANCES {
+            state.record_rebalance(EPOCH).unwrap();
+        }
+
+        state.record_rebalance(EPOCH + 1).unwrap();
+        assert_eq!(state.epoch, EPOCH + 1);
+        assert_eq!(state.rebalances_in_epoch, 1);
+    }
+
+    #[test]
+    fn test_lowered_limit_counts_rebalances_already_made() {
+        let mut state = position_risk_state();
+        state.record_rebalance(EPOCH).unwrap();
+        state.record_rebalance(EPOCH).unwrap();
+
+        state.set_max_rebalances_per_epoch(2).unwrap();
+        assert_eq!(
+            state.record_rebalance(EPOCH).unwrap_err(),
+            RiskEngineError::RebalanceLimitReached.into()
+        );
+    }
+
+    #[test]
+    fn test_zero_limit_is_rejected() {
+        let mut state = PositionRiskState::default();
+        assert_eq!(
+            state.initialize(1, Pubkey::new_unique(), 0).unwrap_err(),
+            RiskEngineError::InvalidRiskConfig.into()
+        );
+
+        let mut state = position_risk_state();
+        assert_eq!(
+            state.set_max_rebalances_per_epoch(0).unwrap_err(),
+            RiskEngineError::InvalidRiskConfig.into()
+        );
+        assert_eq!(state.max_rebalances_per_epoch, MAX_REBALANCES);
+    }
+}
+
+mod record_position_rebalance_tests {
+    use super::*;
+    use crate::record_position_rebalance;
+
+    fn account_info(data: Vec<u8>, owner: Pubkey) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(Pubkey::new_unique())),
+            false,
+            true,
+            Box::leak(Box::new(1_000_000_000u64)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(owner)),
+            false,
+            0,
+        )
+    }
+
+    fn initialized_account() -> AccountInfo<'static> {
+        let mut data = Vec::new();
+        position_risk_state().try_serialize(&mut data).unwrap();
+        account_info(data, crate::ID)
+    }
+
+    fn read_state(account: &AccountInfo) -> PositionRiskState {
+        PositionRiskState::try_deserialize(&mut &account.data.borrow()[..]).unwrap()
+    }
+
+    #[test]
+    fn test_uninitialized_account_has_no_limit() {
+        let account = account_info(Vec::new(), anchor_lang::system_program::ID);
+        for _ in 0..=MAX_REBALANCES {
+            record_position_rebalance(&account, EPOCH).unwrap();
+        }
+        assert!(account.data_is_empty());
+    }
+
+    #[test]
+    fn test_initialized_account_enforces_limit_per_epoch() {
+        let account = initialized_account();
+        for _ in 0..MAX_REBALANCES {
+            record_position_rebalance(&account, EPOCH).unwrap();
+        }
+        assert_eq!(
+            record_position_rebalance(&account, EPOCH).unwrap_err(),
+            RiskEngineError::RebalanceLimitReached.into()
+        );
+        assert_eq!(read_state(&account).rebalances_in_epoch, MAX_REBALANCES);
+
+        record_position_rebalance(&account, EPOCH + 1).unwrap();
+        let state = read_state(&account);
+        assert_eq!(state.epoch, EPOCH + 1);
+        assert_eq!(state.rebalances_in_epoch, 1);
+    }
+
+    #[test]
+    fn test_account_of_other_program_is_rejected() {
+        let mut data = Vec::new();
+        position_risk_state().try_serialize(&mut data).unwrap();
+        let account = account_info(data, Pubkey::new_unique());
+        assert_eq!(
+            record_position_rebalance(&account, EPOCH).unwrap_err(),
+            anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram.into()
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use crate::circuit_breaker::{scaled_price_to_sqrt_price_q64, sqrt_price_q64_to_scaled_price};
+use crate::errors::RiskEngineError;
+use crate::pyth::{PriceFeedMessage, PriceUpdateV2, VerificationLevel, PYTH_RECEIVER_PROGRAM_ID};
+use anchor_lang::prelude::*;
+
+const FEED_ID: [u8; 32] = [7; 32];
+const PUBLISH_TIME: i64 = 1_000_000;
+const MAX_STALENESS_SECS: i64 = 60;
+/// 150 quoted with Pyth's usual exponent.
+const PRICE: i64 = 15_000_000_000;
+const EXPONENT: i32 = -8;
+
+/// A fully verified update of `FEED_ID` at `price * 10^EXPONENT`.
+fn price_update(price: i64) -> PriceUpdateV2 {
+    PriceUpdateV2 {
+        write_authority: Pubkey::new_unique(),
+        verification_level: VerificationLevel::Full,
+        price_message: PriceFeedMessage {
+            feed_id: FEED_ID,
+            price,
+            conf: 1_000_000,
+            exponent: EXPONENT,
+            publish_time: PUBLISH_TIME,
+            prev_publish_time: PUBLISH_TIME - 1,
+            ema_price: price,
+            ema_conf: 1_000_000,
+        },
+        posted_slot: 100,
+    }
+}
+
+/// A mocked account holding `data`, owned by `owner`.
+fn mock_account(data: Vec<u8>, owner: Pubkey) -> &'static AccountInfo<'static> {
+    Box::leak(Box::new(AccountInfo::new(
+        Box::leak(Box::new(Pubkey::new_unique())),
+        false,
+        false,
+        Box::leak(Box::new(1_000_000_000u64)),
+        Box::leak(data.into_boxed_slice()),
+        Box::leak(Box::new(owner)),
+        false,
+        0,
+    )))
+}
+
+fn serialized(update: &PriceUpdateV2) -> Vec<u8> {
+    let mut data = Vec::new();
+    update.try_serialize(&mut data).unwrap();
+    data
+}
+
+mod account_tests {
+    use super::*;
+
+    #[test]
+    fn test_mocked_receiver_account_loads() {
+        let update = price_update(PRICE);
+        let account = mock_account(serialized(&update), PYTH_RECEIVER_PROGRAM_ID);
+        let loaded = Account::<PriceUpdateV2>::try_from(account).unwrap();
+        assert_eq!(*loaded, update);
+    }
+
+    #[test]
+    fn test_account_of_other_program_is_rejected() {
+        let account = mock_account(serialized(&price_update(PRICE)), crate::ID);
+        assert_eq!(
+            Account::<PriceUpdateV2>::try_from(account).unwrap_err(),
+            anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram.into()
+        );
+    }
+
+    #[test]
+    fn test_account_of_other_type_is_rejected() {
+        let mut data = serialized(&price_update(PRICE));
+        data[0] ^= 1;
+        let account = mock_account(data, PYTH_RECEIVER_PROGRAM_ID);
+        assert_eq!(
+            Account::<PriceUpdateV2>::try_from(account).unwrap_err(),
+            anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch.into()
+        );
+    }
+}
+
+mod validation_tests {
+    use super::*;
+
+    #[test]
+    fn test_fresh_price_of_feed_is_accepted() {
+        let update = price_update(PRICE);
+        for now in [PUBLISH_TIME, PUBLISH_TIME + MAX_STALENESS_SECS] {
+            assert_eq!(
+                update
+                    .price_no_older_than(now, MAX_STALENESS_SECS, &FEED_ID)
+                    .unwrap(),
+                update.price_message
+            );
+        }
+    }
+
+    #[test]
+    fn test_price_of_other_feed_is_rejected() {
+        assert_eq!(
+            price_update(PRICE)
+                .price_no_older_than(PUBLISH_TIME, MAX_STALENESS_SECS, &[8; 32])
+                .unwrap_err(),
+            RiskEngineError::PythFeedMismatch.into()
+        );
+    }
+
+    #[test]
+    fn test_partially_verified_price_is_rejected() {
+        let update = PriceUpdateV2 {
+            verification_level: VerificationLevel::Partial { num_signatures: 5 },
+            ..price_update(PRICE)
+        };
+        assert_eq!(
+            update
+                .price_no_older_than(PUBLISH_TIME, MAX_STALENESS_SECS, &FEED_ID)
+                .unwrap_err(),
+            RiskEngineError::PythPriceUnverified.into()
+        );
+    }
+
+    #[test]
+    fn test_stale_price_is_rejected() {
+        assert_eq!(
+            price_update(PRICE)
+                .price_no_older_than(
+                    PUBLISH_TIME + MAX_STALENESS_SECS + 1,
+                    MAX_STALENESS_SECS,
+                    &FEED_ID
+                )
+                .unwrap_err(),
+            RiskEngineError::PythPriceStale.into()
+        );
+    }
+}
+
+mod conversion_tests {
+    use super::*;
+
+    #[test]
+    fn test_price_of_equal_decimals_tokens() {
+        // 4.00 per token, so a sqrt price of exactly 2.
+        let message = PriceFeedMessage {
+            price: 400,
+            exponent: -2,
+            ..PriceFeedMessage::default()
+        };
+        assert_eq!(message.sqrt_price_q64(6, 6).unwrap(), 2 << 64);
+    }
+
+    #[test]
+    fn test_price_is_scaled_to_base_units() {
+        // 150 USDC (6 decimals) per SOL (9 decimals) is 0.15 base units per base unit.
+        let sqrt_price_q64 = price_update(PRICE)
+            .price_message
+            .sqrt_price_q64(9, 6)
+            .unwrap();
+        let expected = scaled_price_to_sqrt_price_q64(150_000, 1_000_000).unwrap();
+        assert!(sqrt_price_q64.abs_diff(expected) <= 1);
+
+        // And the other way around, 150_000 base units per base unit.
+        let sqrt_price_q64 = price_update(PRICE)
+            .price_message
+            .sqrt_price_q64(6, 9)
+            .unwrap();
+        let expected = scaled_price_to_sqrt_price_q64(150_000, 1).unwrap();
+        assert!(sqrt_price_q64.abs_diff(expected) <= 1);
+    }
+
+    #[test]
+    fn test_unusable_prices_are_rejected() {
+        for price in [0, -PRICE] {
+            assert_eq!(
+                price_update(price)
+                    .price_message
+                    .sqrt_price_q64(9, 6)
+                    .unwrap_err(),
+                RiskEngineError::InvalidPythPrice.into()
+            );
+        }
+        let message = PriceFeedMessage {
+            price: 1,
+            exponent: -80,
+            ..PriceFeedMessage::default()
+        };
+        assert_eq!(
+            message.sqrt_price_q64(6, 6).unwrap_err(),
+            RiskEngineError::InvalidPythPrice.into()
+        );
+        let message = PriceFeedMessage {
+            price: i64::MAX,
+            exponent: 30,
+            ..PriceFeedMessage::default()
+        };
+        assert_eq!(
+            message.sqrt_price_q64(6, 6).unwrap_err(),
+            RiskEngineError::Overflow.into()
+        );
+    }
+}
+
+mod sampled_price_tests {
+    use super::*;
+    use crate::state::{PoolRiskState, RiskConfig, RiskConfigParams};
+    use crate::volatility_detector::{
+        calculate_daily_volatility_score, SECONDS_PER_DAY, VOLATILITY_WINDOW,
+    };
+    use crate::{record_pool_price, sampled_sqrt_price_q64};
+    use amm_core::state::pool::Pool as AmmPool;
+
+    const SPOT: u128 = 1 << 64;
+    const DECIMALS: Option<(u8, u8)> = Some((9, 6));
+
+    fn amm_pool() -> AmmPool {
+        AmmPool {
+            sqrt_price_q64: SPOT,
+            max_oracle_age_slots: 10,
+            ..Default::default()
+        }
+    }
+
+    fn feed() -> Pubkey {
+        Pubkey::new_from_array(FEED_ID)
+    }
+
+    fn clock(unix_timestamp: i64) -> Clock {
+        Clock {
+            slot: 100,
+            unix_timestamp,
+            ..Clock::default()
+        }
+    }
+
+    fn sample(price_update: Option<&PriceUpdateV2>, decimals: Option<(u8, u8)>) -> Result<u128> {
+        sampled_sqrt_price_q64(
+            &amm_pool(),
+            &feed(),
+            price_update,
+            decimals,
+            &clock(PUBLISH_TIME),
+            MAX_STALENESS_SECS,
+        )
+    }
+
+    #[test]
+    fn test_pool_without_feed_samples_its_own_price() {
+        let update = price_update(PRICE);
+        for price_update in [None, Some(&update)] {
+            assert_eq!(
+                sampled_sqrt_price_q64(
+                    &amm_pool(),
+                    &Pubkey::default(),
+                    price_update,
+                    DECIMALS,
+                    &clock(PUBLISH_TIME),
+                    MAX_STALENESS_SECS,
+                )
+                .unwrap(),
+                SPOT
+            );
+        }
+    }
+
+    #[test]
+    fn test_pool_with_feed_samples_pyth_price() {
+        let update = price_update(PRICE);
+        let sampled = sample(Some(&update), DECIMALS).unwrap();
+        assert_eq!(sampled, update.price_message.sqrt_price_q64(9, 6).unwrap());
+        assert_ne!(sampled, SPOT);
+    }
+
+    #[test]
+    fn test_pool_with_feed_needs_price_update_and_mints() {
+        let update = price_update(PRICE);
+        for (price_update, decimals) in [(None, DECIMALS), (Some(&update), None)] {
+            assert_eq!(
+                sample(price_update, decimals).unwrap_err(),
+                RiskEngineError::MissingPythPriceFeed.into()
+            );
+        }
+    }
+
+    #[test]
+    fn test_stale_pyth_price_is_not_sampled() {
+        let update = price_update(PRICE);
+        assert_eq!(
+            sampled_sqrt_price_q64(
+                &amm_pool(),
+                &feed(),
+                Some(&update),
+                DECIMALS,
+                &clock(PUBLISH_TIME + MAX_STALENESS_SECS + 1),
+                MAX_STALENESS_SECS,
+            )
+            .unwrap_err(),
+            RiskEngineError::PythPriceStale.into()
+        );
+    }
+
+    #[test]
+    fn test_volatility_is_measured_from_pyth_prices() {
+        let mut config = RiskConfig::default();
+        config
+            .initialize(
+                1,
+                Pubkey::new_unique(),
+                RiskConfigParams {
+                    circuit_breaker_threshold_bps: 10_000,
+                    circuit_breaker_window_secs: 600,
+                    twap_interval_secs: 300,
+                    halt_duration_secs: 3_600,
+                    max_staleness_secs: MAX_STALENESS_SECS,
+                    stable_volatility_prior_bps: 500,
+                    major_volatility_prior_bps: 8_000,
+                    long_tail_volatility_prior_bps: 20_000,
+                },
+            )
+            .unwrap();
+        let mut state = PoolRiskState::default();
+        state.initialize(1, Pubkey::new_unique(), 8_000);
+
+        // The pool's own price never moves, while Pyth's swings by 1% a day.
+        let mut expected_prices = Vec::new();
+        for i in 0..VOLATILITY_WINDOW as i64 {
+            let now = PUBLISH_TIME + i * SECONDS_PER_DAY as i64;
+            let mut update = price_update(if i % 2 == 0 { PRICE } else { PRICE / 100 * 101 });
+            update.price_message.publish_time = now;
+            let sampled = sampled_sqrt_price_q64(
+                &amm_pool(),
+                &feed(),
+                Some(&update),
+                DECIMALS,
+                &clock(now),
+                config.max_staleness_secs,
+            )
+            .unwrap();
+            record_pool_price(&mut state, &config, Pubkey::new_unique(), sampled, now).unwrap();
+            expected_prices.push(sqrt_price_q64_to_scaled_price(sampled, 1_000_000).unwrap());
+        }
+
+        assert_eq!(state.recorded_prices(), expected_prices);
+        let measured =
+            calculate_daily_volatility_score(&state.observations_chronological()).unwrap();
+        assert!(measured.volatility > 0);
+    }
+}
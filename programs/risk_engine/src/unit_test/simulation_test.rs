@@ -0,0 +1,157 @@
+use crate::simulation::{
+    estimate_expected_il, estimate_out_of_range_probability, SimulationParameters, StochasticModel,
+};
+
+/// A year of daily steps at 80% volatility without drift.
+fn params() -> SimulationParameters {
+    SimulationParameters {
+        drift: 0.0,
+        volatility: 0.8,
+        mean_reversion_strength: 0.0,
+        time_steps: 365,
+        time_horizon: 365.0,
+        num_paths: 2_000,
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    let mean = mean(values);
+    let variance =
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    variance.sqrt()
+}
+
+/// Log returns from the first to the last price of each path.
+fn final_log_returns(paths: &[Vec<f64>]) -> Vec<f64> {
+    paths
+        .iter()
+        .map(|path| (path[path.len() - 1] / path[0]).ln())
+        .collect()
+}
+
+mod simulate_tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_start_at_initial_price() {
+        let paths = StochasticModel::new(7).simulate(params(), 150.0);
+        assert_eq!(paths.len(), 2_000);
+        for path in &paths {
+            assert_eq!(path.len(), 366);
+            assert_eq!(path[0], 150.0);
+            assert!(path.iter().all(|&price| price > 0.0));
+        }
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_paths() {
+        let first = StochasticModel::new(42).simulate(params(), 1.0);
+        assert_eq!(first, StochasticModel::new(42).simulate(params(), 1.0));
+        assert_ne!(first, StochasticModel::new(43).simulate(params(), 1.0));
+    }
+
+    #[test]
+    fn test_log_returns_match_geometric_brownian_motion() {
+        let params = SimulationParameters {
+            drift: 0.2,
+            ..params()
+        };
+        let returns = final_log_returns(&StochasticModel::new(1).simulate(params, 1.0));
+
+        // ln(S_T / S_0) ~ N((μ - σ²/2)·T, σ²·T) with T one year
+        let expected_mean = 0.2 - 0.8f64.powi(2) / 2.0;
+        let standard_error = 0.8 / (returns.len() as f64).sqrt();
+        assert!((mean(&returns) - expected_mean).abs() < 4.0 * standard_error);
+        assert!((std_dev(&returns) - 0.8).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_time_horizon_scales_dispersion() {
+        let params = SimulationParameters {
+            time_horizon: 365.0 / 4.0,
+            ..params()
+        };
+        let returns = final_log_returns(&StochasticModel::new(2).simulate(params, 1.0));
+        // σ·√(1/4)
+        assert!((std_dev(&returns) - 0.4).abs() < 0.03);
+    }
+
+    #[test]
+    fn test_mean_reversion_narrows_dispersion() {
+        let reverting = SimulationParameters {
+            mean_reversion_strength: 10.0,
+            ..params()
+        };
+        let free = final_log_returns(&StochasticModel::new(3).simulate(params(), 1.0));
+        let pulled_back = final_log_returns(&StochasticModel::new(3).simulate(reverting, 1.0));
+        // The stationary deviation of an Ornstein-Uhlenbeck process, σ / √(2κ)
+        assert!((std_dev(&pulled_back) - 0.8 / 20f64.sqrt()).abs() < 0.02);
+        assert!(std_dev(&pulled_back) < std_dev(&free) / 3.0);
+    }
+
+    #[test]
+    fn test_zero_volatility_keeps_the_price() {
+        let params = SimulationParameters {
+            volatility: 0.0,
+            num_paths: 3,
+            ..params()
+        };
+        let paths = StochasticModel::new(4).simulate(params, 2.5);
+        assert!(paths.iter().flatten().all(|&price| price == 2.5));
+    }
+}
+
+mod estimate_tests {
+    use super::*;
+
+    #[test]
+    fn test_out_of_range_probability_grows_as_range_narrows() {
+        let paths = StochasticModel::new(5).simulate(params(), 1.0);
+        let probability = |half_width: f64| {
+            estimate_out_of_range_probability(&paths, 1.0 - half_width, 1.0 + half_width)
+        };
+
+        assert_eq!(probability(1e6), 0.0);
+        assert!(probability(0.9) < probability(0.5));
+        assert!(probability(0.5) < probability(0.1));
+        assert_eq!(probability(1e-9), 1.0);
+    }
+
+    #[test]
+    fn test_out_of_range_probability_excludes_upper_bound() {
+        let paths = vec![vec![1.0, 2.0], vec![1.0, 1.5]];
+        assert_eq!(estimate_out_of_range_probability(&paths, 1.0, 2.0), 0.5);
+        assert_eq!(estimate_out_of_range_probability(&[], 1.0, 2.0), 0.0);
+    }
+
+    #[test]
+    fn test_unmoved_price_has_no_il() {
+        let paths = vec![vec![1.0; 10]; 4];
+        assert_eq!(estimate_expected_il(&paths, 0.5, 2.0, 0.003), 0.0);
+        assert_eq!(estimate_expected_il(&[], 0.5, 2.0, 0.003), 0.0);
+    }
+
+    #[test]
+    fn test_il_matches_closed_form_for_one_move() {
+        // Full range position, price doubling: 2·√2 / (1 + 2) - 1
+        let paths = vec![vec![1.0, 2.0]];
+        let il = estimate_expected_il(&paths, 1e-12, 1e12, 0.0);
+        assert!((il - (2.0 * 2f64.sqrt() / 3.0 - 1.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_narrower_range_loses_more_and_fees_offset_it() {
+        let paths = StochasticModel::new(6).simulate(params(), 1.0);
+        let wide = estimate_expected_il(&paths, 0.1, 10.0, 0.0);
+        let narrow = estimate_expected_il(&paths, 0.5, 2.0, 0.0);
+        assert!(wide < 0.0);
+        assert!(narrow < wide);
+
+        let with_fees = estimate_expected_il(&paths, 0.5, 2.0, 0.003);
+        assert!(with_fees > narrow);
+    }
+}
@@ -0,0 +1,766 @@
+use crate::errors::RiskEngineError;
+use crate::state::{PoolCategory, PoolRiskState, RiskConfig, RiskConfigParams};
+use crate::update_pool_volatilities;
+use crate::volatility_detector::{
+    calculate_ewma_volatility, calculate_rolling_std_dev_volatility,
+    calculate_rolling_volatility_score, GarchModel, TimeWeightedVolatility, VolatilityScore,
+    DEFAULT_EWMA_LAMBDA, GARCH_MIN_PRICES, MAX_VOLATILITY_BATCH, RETURN_SCALING_FACTOR,
+    VOLATILITY_WINDOW,
+};
+use amm_core::math::exp_fixed;
+use anchor_lang::prelude::*;
+
+const STABLE_VOLATILITY_PRIOR_BPS: u16 = 500;
+const LONG_TAIL_VOLATILITY_PRIOR_BPS: u16 = 20_000;
+
+/// A config whose circuit breaker never trips, so any price series can be recorded.
+fn config() -> RiskConfig {
+    let mut config = RiskConfig::default();
+    config
+        .initialize(
+            1,
+            Pubkey::new_unique(),
+            RiskConfigParams {
+                circuit_breaker_threshold_bps: 10_000,
+                circuit_breaker_window_secs: 600,
+                twap_interval_secs: 300,
+                halt_duration_secs: 3_600,
+                max_staleness_secs: 60,
+                stable_volatility_prior_bps: STABLE_VOLATILITY_PRIOR_BPS,
+                major_volatility_prior_bps: 8_000,
+                long_tail_volatility_prior_bps: LONG_TAIL_VOLATILITY_PRIOR_BPS,
+            },
+        )
+        .unwrap();
+    config
+}
+
+/// A major pool's risk state with `prices` recorded one minute apart.
+fn risk_state_with_prices(prices: &[u128]) -> PoolRiskState {
+    risk_state_of_category(PoolCategory::Major, prices)
+}
+
+/// A risk state for a pool of `category` with `prices` recorded one minute apart.
+fn risk_state_of_category(category: PoolCategory, prices: &[u128]) -> PoolRiskState {
+    risk_state_sampled_every(category, prices, 60)
+}
+
+/// A risk state for a pool of `category` with `prices` recorded `interval` seconds apart.
+fn risk_state_sampled_every(
+    category: PoolCategory,
+    prices: &[u128],
+    interval: i64,
+) -> PoolRiskState {
+    let config = config();
+    let mut state = PoolRiskState::default();
+    state.initialize(
+        1,
+        Pubkey::new_unique(),
+        config.volatility_prior_bps(category),
+    );
+    for (i, price) in prices.iter().enumerate() {
+        state
+            .record_price(interval * i as i64, *price, &config)
+            .unwrap();
+    }
+    state
+}
+
+fn account_info(state: &PoolRiskState, is_writable: bool) -> AccountInfo<'static> {
+    let mut data = Vec::new();
+    state.try_serialize(&mut data).unwrap();
+    AccountInfo::new(
+        Box::leak(Box::new(Pubkey::new_unique())),
+        false,
+        is_writable,
+        Box::leak(Box::new(1_000_000_000u64)),
+        Box::leak(data.into_boxed_slice()),
+        Box::leak(Box::new(crate::ID)),
+        false,
+        0,
+    )
+}
+
+fn read_state(account: &AccountInfo) -> PoolRiskState {
+    PoolRiskState::try_deserialize(&mut &account.data.borrow()[..]).unwrap()
+}
+
+/// Prices whose log returns are `steps` multiples of `log_step`, a Q64.64 number.
+fn prices_from_steps(steps: &[i64], log_step: i128) -> Vec<u128> {
+    let mut log_price = 0i128;
+    let mut prices = vec![exp_fixed(0).unwrap()];
+    for &step in steps {
+        log_price += step as i128 * log_step;
+        prices.push(exp_fixed(log_price).unwrap());
+    }
+    prices
+}
+
+/// A series of `VOLATILITY_WINDOW` prices alternating around 1_000_000 by `swing`.
+fn swinging_prices(swing: u128) -> Vec<u128> {
+    (0..VOLATILITY_WINDOW)
+        .map(|i| {
+            if i % 2 == 0 {
+                1_000_000 - swing
+            } else {
+                1_000_000 + swing
+            }
+        })
+        .collect()
+}
+
+mod update_volatility_tests {
+    use super::*;
+
+    #[test]
+    fn test_update_volatility_uses_latest_window() {
+        let mut prices = vec![1_000_000; 5];
+        prices.extend(swinging_prices(10_000));
+        let mut state = risk_state_with_prices(&prices);
+
+        let volatility = state.update_volatility(1_000).unwrap();
+        assert_eq!(
+            volatility,
+            calculate_rolling_std_dev_volatility(&prices, VOLATILITY_WINDOW).unwrap()
+        );
+        assert!(volatility > 0);
+        assert_eq!(state.volatility, volatility);
+        assert_eq!(state.volatility_updated_at, 1_000);
+    }
+
+    #[test]
+    fn test_rolling_score_confidence_grows_until_window_is_full() {
+        let prices = swinging_prices(10_000);
+        let confidence = |count: usize| {
+            calculate_rolling_volatility_score(&prices[..count])
+                .unwrap()
+                .confidence
+        };
+
+        assert_eq!(confidence(2), 0);
+        assert_eq!(confidence(3), 2_222);
+        assert_eq!(confidence(VOLATILITY_WINDOW), 10_000);
+        assert_eq!(
+            calculate_rolling_volatility_score(&prices)
+                .unwrap()
+                .volatility,
+            calculate_rolling_std_dev_volatility(&prices, VOLATILITY_WINDOW).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_update_volatility_needs_full_window() {
+        let mut state = risk_state_with_prices(&[1_000_000; VOLATILITY_WINDOW - 1]);
+        assert_eq!(
+            state.update_volatility(1_000).unwrap_err(),
+            RiskEngineError::VolatilityDataError.into()
+        );
+        assert_eq!(state.volatility_updated_at, 0);
+    }
+}
+
+mod daily_volatility_tests {
+    use super::*;
+    use crate::volatility_detector::{calculate_daily_volatility_score, SECONDS_PER_DAY};
+
+    fn daily_volatility(prices: &[u128], interval: i64) -> u128 {
+        let state = risk_state_sampled_every(PoolCategory::Major, prices, interval);
+        calculate_daily_volatility_score(&state.observations_chronological())
+            .unwrap()
+            .volatility
+    }
+
+    #[test]
+    fn test_daily_returns_are_unscaled() {
+        let prices = swinging_prices(10_000);
+        assert_eq!(
+            daily_volatility(&prices, SECONDS_PER_DAY as i64),
+            calculate_rolling_std_dev_volatility(&prices, VOLATILITY_WINDOW).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_returns_are_normalized_by_time_between_prices() {
+        let prices = swinging_prices(10_000);
+        let daily = daily_volatility(&prices, SECONDS_PER_DAY as i64);
+
+        // The same moves a minute apart are sqrt(1440) times as volatile per day.
+        let per_minute = daily_volatility(&prices, 60);
+        let expected = daily * 37_947 / 1_000;
+        assert!(per_minute.abs_diff(expected) <= expected / 1_000);
+        // And four days apart, half as volatile.
+        let every_four_days = daily_volatility(&prices, 4 * SECONDS_PER_DAY as i64);
+        assert!(every_four_days.abs_diff(daily / 2) <= daily / 1_000);
+    }
+
+    #[test]
+    fn test_confidence_matches_rolling_score() {
+        let prices = swinging_prices(10_000);
+        for count in [2, 3, VOLATILITY_WINDOW] {
+            let state = risk_state_with_prices(&prices[..count]);
+            assert_eq!(
+                calculate_daily_volatility_score(&state.observations_chronological())
+                    .unwrap()
+                    .confidence,
+                calculate_rolling_volatility_score(&prices[..count])
+                    .unwrap()
+                    .confidence
+            );
+        }
+    }
+}
+
+mod volatility_batch_tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_updates_each_pool_from_its_own_history() {
+        let histories = [
+            vec![1_000_000; VOLATILITY_WINDOW],
+            swinging_prices(1_000),
+            swinging_prices(50_000),
+        ];
+        let accounts: &'static [AccountInfo<'static>] = Box::leak(
+            histories
+                .iter()
+                .map(|prices| account_info(&risk_state_with_prices(prices), true))
+                .collect::<Vec<_>>()
+                .into_boxed_slice(),
+        );
+
+        assert_eq!(update_pool_volatilities(accounts, 1_000, true).unwrap(), 3);
+
+        let volatilities: Vec<u128> = accounts
+            .iter()
+            .map(|account| read_state(account).volatility)
+            .collect();
+        for (prices, (account, volatility)) in
+            histories.iter().zip(accounts.iter().zip(&volatilities))
+        {
+            assert_eq!(
+                *volatility,
+                calculate_rolling_std_dev_volatility(prices, VOLATILITY_WINDOW).unwrap()
+            );
+            assert_eq!(read_state(account).volatility_updated_at, 1_000);
+        }
+        // Flat prices have no volatility, and wider swings have more
+        assert_eq!(volatilities[0], 0);
+        assert!(volatilities[1] > 0);
+        assert!(volatilities[2] > volatilities[1]);
+    }
+
+    #[test]
+    fn test_batch_skips_failing_pools() {
+        let accounts: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+            account_info(&risk_state_with_prices(&swinging_prices(1_000)), true),
+            // Not enough recorded prices
+            account_info(&risk_state_with_prices(&[1_000_000; 3]), true),
+            // Not writable
+            account_info(&risk_state_with_prices(&swinging_prices(2_000)), false),
+            account_info(&risk_state_with_prices(&swinging_prices(3_000)), true),
+        ]));
+
+        assert_eq!(update_pool_volatilities(accounts, 1_000, false).unwrap(), 2);
+        let updated: Vec<bool> = accounts
+            .iter()
+            .map(|account| read_state(account).volatility_updated_at == 1_000)
+            .collect();
+        assert_eq!(updated, [true, false, false, true]);
+    }
+
+    #[test]
+    fn test_batch_requiring_all_fails_on_first_failure() {
+        let accounts: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+            account_info(&risk_state_with_prices(&swinging_prices(1_000)), true),
+            account_info(&risk_state_with_prices(&[1_000_000; 3]), true),
+        ]));
+
+        assert_eq!(
+            update_pool_volatilities(accounts, 1_000, true).unwrap_err(),
+            RiskEngineError::VolatilityDataError.into()
+        );
+    }
+
+    #[test]
+    fn test_batch_size_is_bounded() {
+        let state = risk_state_with_prices(&swinging_prices(1_000));
+        let accounts: &'static [AccountInfo<'static>] = Box::leak(
+            (0..=MAX_VOLATILITY_BATCH)
+                .map(|_| account_info(&state, true))
+                .collect::<Vec<_>>()
+                .into_boxed_slice(),
+        );
+
+        assert_eq!(
+            update_pool_volatilities(accounts, 1_000, false).unwrap_err(),
+            RiskEngineError::BatchTooLarge.into()
+        );
+        assert_eq!(
+            update_pool_volatilities(&accounts[1..], 1_000, false).unwrap(),
+            MAX_VOLATILITY_BATCH as u32
+        );
+    }
+}
+
+mod garch_tests {
+    use super::*;
+    use crate::state::MAX_PRICE_OBSERVATIONS;
+    use proptest::prelude::*;
+
+    /// A variance of 0.0004 (a standard deviation of 2%), scaled by RETURN_SCALING_FACTOR².
+    const OMEGA: u64 = 400_000_000_000_000;
+    /// 0.1 and 0.85, scaled by RETURN_SCALING_FACTOR.
+    const ALPHA: u64 = 100_000_000;
+    const BETA: u64 = 850_000_000;
+
+    #[test]
+    fn test_without_shock_weights_volatility_is_omega() {
+        // With alpha and beta at zero the variance is omega whatever the prices
+        let model = GarchModel::new(OMEGA, 0, 0);
+        let score = model.estimate_volatility(&swinging_prices(10_000)).unwrap();
+        assert_eq!(score.volatility, 20_000_000);
+    }
+
+    #[test]
+    fn test_flat_prices_build_up_omega_only() {
+        let model = GarchModel::new(0, ALPHA, BETA);
+        let score = model.estimate_volatility(&[1_000_000; 12]).unwrap();
+        assert_eq!(score.volatility, 0);
+
+        // Without shocks the variance climbs from zero towards omega / (1 - beta)
+        let model = GarchModel::new(OMEGA, ALPHA, BETA);
+        let score = model.estimate_volatility(&[1_000_000; 12]).unwrap();
+        let long_run_variance =
+            OMEGA as u128 * RETURN_SCALING_FACTOR / (RETURN_SCALING_FACTOR - BETA as u128);
+        assert!(score.volatility >= 20_000_000);
+        assert!(score.volatility * score.volatility < long_run_variance);
+    }
+
+    #[test]
+    fn test_recent_shocks_weigh_more() {
+        // The same returns in a different order: a shock at the end of the history
+        // raises the forecast more than the same shock at its start
+        let mut calm_then_shock = vec![1_000_000u128; GARCH_MIN_PRICES];
+        *calm_then_shock.last_mut().unwrap() = 1_100_000;
+        let mut shock_then_calm = vec![1_100_000u128; GARCH_MIN_PRICES];
+        shock_then_calm[0] = 1_000_000;
+
+        let model = GarchModel::new(OMEGA, ALPHA, BETA);
+        let late = model.estimate_volatility(&calm_then_shock).unwrap();
+        let early = model.estimate_volatility(&shock_then_calm).unwrap();
+        assert!(late.volatility > early.volatility);
+    }
+
+    #[test]
+    fn test_confidence_grows_with_history() {
+        let model = GarchModel::new(OMEGA, ALPHA, BETA);
+        let confidence = |len: usize| {
+            let prices: Vec<u128> = (0..len)
+                .map(|i| 1_000_000 + (i as u128 % 3) * 1_000)
+                .collect();
+            model.estimate_volatility(&prices).unwrap().confidence
+        };
+        let midway = (GARCH_MIN_PRICES + MAX_PRICE_OBSERVATIONS) / 2;
+
+        assert_eq!(confidence(GARCH_MIN_PRICES), 0);
+        assert_eq!(confidence(midway), 5_000);
+        assert_eq!(confidence(MAX_PRICE_OBSERVATIONS), 10_000);
+        assert_eq!(confidence(MAX_PRICE_OBSERVATIONS * 2), 10_000);
+    }
+
+    #[test]
+    fn test_rejects_unbounded_parameters() {
+        let scale = RETURN_SCALING_FACTOR as u64;
+        for (alpha, beta) in [(scale, 0), (0, scale), (scale / 2, scale / 2)] {
+            assert_eq!(
+                GarchModel::new(OMEGA, alpha, beta)
+                    .estimate_volatility(&swinging_prices(10_000))
+                    .unwrap_err(),
+                RiskEngineError::InvalidGarchParameters.into()
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_short_or_zero_price_history() {
+        let model = GarchModel::new(OMEGA, ALPHA, BETA);
+        assert_eq!(
+            model
+                .estimate_volatility(&[1_000_000; GARCH_MIN_PRICES - 1])
+                .unwrap_err(),
+            RiskEngineError::VolatilityDataError.into()
+        );
+
+        let mut prices = swinging_prices(10_000);
+        prices[3] = 0;
+        assert_eq!(
+            model.estimate_volatility(&prices).unwrap_err(),
+            RiskEngineError::VolatilityDataError.into()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_higher_return_variance_raises_volatility(
+            steps in prop::collection::vec(-5i64..=5, GARCH_MIN_PRICES..MAX_PRICE_OBSERVATIONS),
+            scale in 2i128..5,
+            omega in 0u64..1_000_000_000_000_000,
+            alpha in 10_000_000u64..500_000_000,
+            beta in 0u64..490_000_000,
+        ) {
+            // Returns that all match have no shocks to scale up
+            prop_assume!(steps.iter().any(|&step| step != steps[0]));
+            // Log returns in multiples of 0.001
+            let log_step = (1i128 << 64) / 1_000;
+            let model = GarchModel::new(omega, alpha, beta);
+
+            let calm: VolatilityScore =
+                model.estimate_volatility(&prices_from_steps(&steps, log_step)).unwrap();
+            let wild =
+                model.estimate_volatility(&prices_from_steps(&steps, log_step * scale)).unwrap();
+            prop_assert!(wild.volatility > calm.volatility);
+            prop_assert_eq!(wild.confidence, calm.confidence);
+        }
+    }
+}
+
+mod ema_tests {
+    use super::*;
+
+    const SPAN: u64 = 20;
+    /// A log return of 0.01, in Q64.64.
+    const LOG_STEP: i128 = (1i128 << 64) / 100;
+
+    #[test]
+    fn test_constant_returns_give_their_size() {
+        // Every squared return is 0.0001, so their average is too, up or down
+        for step in [1, -1] {
+            let prices = prices_from_steps(&[step; SPAN as usize], LOG_STEP);
+            let volatility = TimeWeightedVolatility::compute_ewma_std_dev(&prices, SPAN).unwrap();
+            assert!(volatility.abs_diff(10_000_000) <= 2, "{volatility}");
+        }
+    }
+
+    #[test]
+    fn test_flat_prices_have_no_volatility() {
+        let score = TimeWeightedVolatility::compute_ema_volatility(&[1_000_000; 12], SPAN).unwrap();
+        assert_eq!(score.volatility, 0);
+    }
+
+    #[test]
+    fn test_span_of_one_keeps_only_the_latest_return() {
+        let prices = prices_from_steps(&[5, -3, 1, 2], LOG_STEP);
+        let volatility = TimeWeightedVolatility::compute_ewma_std_dev(&prices, 1).unwrap();
+        assert!(volatility.abs_diff(20_000_000) <= 2, "{volatility}");
+    }
+
+    #[test]
+    fn test_recent_returns_weigh_more() {
+        // The first return seeds the average, so the early shock is the second one
+        let mut steps = vec![0; SPAN as usize];
+        steps[1] = 1;
+        let early = prices_from_steps(&steps, LOG_STEP);
+        steps.swap(1, SPAN as usize - 1);
+        let late = prices_from_steps(&steps, LOG_STEP);
+
+        let late_volatility = TimeWeightedVolatility::compute_ewma_std_dev(&late, SPAN).unwrap();
+        let early_volatility = TimeWeightedVolatility::compute_ewma_std_dev(&early, SPAN).unwrap();
+        assert!(late_volatility > early_volatility);
+
+        // A shorter span reacts more to the latest return
+        let quick = TimeWeightedVolatility::compute_ewma_std_dev(&late, SPAN / 4).unwrap();
+        assert!(quick > late_volatility);
+    }
+
+    #[test]
+    fn test_confidence_grows_until_the_span_is_covered() {
+        let confidence = |returns: usize| {
+            let prices = prices_from_steps(&vec![1; returns], LOG_STEP);
+            TimeWeightedVolatility::compute_ema_volatility(&prices, SPAN)
+                .unwrap()
+                .confidence
+        };
+        assert_eq!(confidence(SPAN as usize / 2), 5_000);
+        assert_eq!(confidence(SPAN as usize), 10_000);
+        assert_eq!(confidence(SPAN as usize * 2), 10_000);
+    }
+
+    #[test]
+    fn test_rejects_short_or_invalid_history() {
+        let prices = prices_from_steps(&[1; SPAN as usize], LOG_STEP);
+        let short = &prices[..SPAN as usize / 2 - 1];
+        let mut with_zero = prices.clone();
+        with_zero[3] = 0;
+
+        for (history, span) in [
+            (&prices[..], 0),
+            (short, SPAN),
+            (&prices[..1], 1),
+            (&with_zero[..], SPAN),
+        ] {
+            assert_eq!(
+                TimeWeightedVolatility::compute_ema_volatility(history, span).unwrap_err(),
+                RiskEngineError::VolatilityDataError.into()
+            );
+        }
+        // Half the span is enough
+        assert!(
+            TimeWeightedVolatility::compute_ema_volatility(&prices[..SPAN as usize / 2], SPAN)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_blend_interpolates_between_spans() {
+        let short = VolatilityScore {
+            volatility: 40_000_000,
+            confidence: 2_000,
+        };
+        let long = VolatilityScore {
+            volatility: 20_000_000,
+            confidence: 10_000,
+        };
+
+        assert_eq!(TimeWeightedVolatility::blend(short, long, 0), long);
+        assert_eq!(TimeWeightedVolatility::blend(short, long, 10_000), short);
+        assert_eq!(TimeWeightedVolatility::blend(short, long, 20_000), short);
+        assert_eq!(
+            TimeWeightedVolatility::blend(short, long, 2_500),
+            VolatilityScore {
+                volatility: 25_000_000,
+                confidence: 8_000,
+            }
+        );
+    }
+
+    #[test]
+    fn test_blend_does_not_overflow() {
+        let high = VolatilityScore {
+            volatility: u128::MAX,
+            confidence: 10_000,
+        };
+        let blended = TimeWeightedVolatility::blend(high, high, 3_333);
+        assert_eq!(blended, high);
+    }
+}
+
+mod ewma_volatility_tests {
+    use super::*;
+
+    /// Prices starting at 10^9 whose simple returns alternate between `+move_bps` and
+    /// `-move_bps`, one per entry of `moves_bps`.
+    fn prices_from_moves(moves_bps: &[i128]) -> Vec<u128> {
+        let mut prices = vec![1_000_000_000u128];
+        for (i, move_bps) in moves_bps.iter().enumerate() {
+            let sign = if i % 2 == 0 { 1 } else { -1 };
+            let previous = *prices.last().unwrap() as i128;
+            prices.push((previous + previous * sign * move_bps / 10_000) as u128);
+        }
+        prices
+    }
+
+    #[test]
+    fn test_constant_size_returns_give_their_size() {
+        // Every squared return is 0.0001, whatever its weight
+        let prices = prices_from_moves(&[100; 20]);
+        for lambda in [0, DEFAULT_EWMA_LAMBDA, RETURN_SCALING_FACTOR - 1] {
+            let volatility = calculate_ewma_volatility(&prices, lambda).unwrap();
+            assert!(volatility.abs_diff(10_000_000) <= 2, "{volatility}");
+        }
+    }
+
+    #[test]
+    fn test_flat_or_short_history_has_no_volatility() {
+        assert_eq!(
+            calculate_ewma_volatility(&[1_000_000; 12], DEFAULT_EWMA_LAMBDA).unwrap(),
+            0
+        );
+        let prices = prices_from_moves(&[100]);
+        assert_eq!(
+            calculate_ewma_volatility(&prices, DEFAULT_EWMA_LAMBDA).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_lambda_of_zero_keeps_only_the_latest_return() {
+        let prices = prices_from_moves(&[500, 300, 100, 200]);
+        let volatility = calculate_ewma_volatility(&prices, 0).unwrap();
+        assert!(volatility.abs_diff(20_000_000) <= 2, "{volatility}");
+    }
+
+    #[test]
+    fn test_reacts_faster_than_rolling_std_dev_to_a_shock() {
+        // 40 calm returns of 0.2%, then 5 returns of 10%
+        let mut moves_bps = vec![20; 40];
+        let calm = prices_from_moves(&moves_bps);
+        moves_bps.extend([1_000; 5]);
+        let shocked = prices_from_moves(&moves_bps);
+
+        let rolling =
+            |prices: &[u128]| calculate_rolling_std_dev_volatility(prices, prices.len()).unwrap();
+        let ewma =
+            |prices: &[u128]| calculate_ewma_volatility(prices, DEFAULT_EWMA_LAMBDA).unwrap();
+
+        // Before the shock both see the calm regime
+        assert!(
+            rolling(&calm).abs_diff(2_000_000) < 100_000,
+            "{}",
+            rolling(&calm)
+        );
+        assert!(ewma(&calm).abs_diff(2_000_000) < 100_000, "{}", ewma(&calm));
+        // After it the EWMA is already near the new regime's 10%, the rolling estimate
+        // still dragged down by the calm returns
+        assert!(ewma(&shocked) > 50_000_000, "{}", ewma(&shocked));
+        assert!(rolling(&shocked) < 40_000_000, "{}", rolling(&shocked));
+    }
+
+    #[test]
+    fn test_rejects_lambda_of_one_or_more() {
+        let prices = prices_from_moves(&[100; 5]);
+        for lambda in [RETURN_SCALING_FACTOR, u128::MAX] {
+            assert_eq!(
+                calculate_ewma_volatility(&prices, lambda).unwrap_err(),
+                RiskEngineError::InvalidEwmaLambda.into()
+            );
+        }
+    }
+}
+
+mod rebalance_proposal_tests {
+    use super::*;
+    use crate::circuit_breaker::BPS_DENOMINATOR;
+    use crate::il_analyzer::{calculate_current_il_percentage, IlPercentage};
+    use crate::position_optimizer::{
+        calculate_optimal_boundaries_mvp, MVP_RANGE_HORIZON_DAYS, MVP_RANGE_Z_SCORE_BPS,
+    };
+    use crate::propose_rebalance;
+    use crate::volatility_detector::SECONDS_PER_DAY;
+    use amm_core::position::PositionData as AmmPositionData;
+    use amm_core::state::pool::Pool as AmmPool;
+
+    fn amm_pool() -> AmmPool {
+        AmmPool {
+            sqrt_price_q64: 1 << 64,
+            tick_spacing: 60,
+            ..Default::default()
+        }
+    }
+
+    fn amm_position(entry_sqrt_price_q64: u128) -> AmmPositionData {
+        AmmPositionData {
+            tick_lower_index: -6_000,
+            tick_upper_index: 6_000,
+            entry_sqrt_price_q64,
+            ..Default::default()
+        }
+    }
+
+    fn proposed_range(prices: &[u128]) -> Result<(i32, i32)> {
+        proposed_range_of_category(PoolCategory::Major, prices)
+    }
+
+    /// The range proposed once `prices` were recorded, a day apart, for a pool of `category`.
+    fn proposed_range_of_category(category: PoolCategory, prices: &[u128]) -> Result<(i32, i32)> {
+        let state = risk_state_sampled_every(category, prices, SECONDS_PER_DAY as i64);
+        let proposal = propose_rebalance(&amm_pool(), &amm_position(1 << 64), &state)?;
+        Ok((proposal.tick_lower, proposal.tick_upper))
+    }
+
+    fn width((tick_lower, tick_upper): (i32, i32)) -> i32 {
+        tick_upper - tick_lower
+    }
+
+    #[test]
+    fn test_proposal_widens_with_recorded_volatility() {
+        let (calm_lower, calm_upper) = proposed_range(&[1_000_000; VOLATILITY_WINDOW]).unwrap();
+        let (lower, upper) = proposed_range(&swinging_prices(10_000)).unwrap();
+        let (wild_lower, wild_upper) = proposed_range(&swinging_prices(50_000)).unwrap();
+
+        assert!(upper - lower > calm_upper - calm_lower);
+        assert!(wild_upper - wild_lower > upper - lower);
+    }
+
+    #[test]
+    fn test_faster_sampling_does_not_narrow_proposal() {
+        let prices = swinging_prices(10_000);
+        let daily = proposed_range(&prices).unwrap();
+        let state = risk_state_sampled_every(PoolCategory::Major, &prices, 60);
+        let proposal = propose_rebalance(&amm_pool(), &amm_position(1 << 64), &state).unwrap();
+
+        assert!(width((proposal.tick_lower, proposal.tick_upper)) > width(daily));
+    }
+
+    #[test]
+    fn test_proposal_uses_latest_recorded_window() {
+        let mut prices = swinging_prices(50_000);
+        prices.extend([1_000_000; VOLATILITY_WINDOW]);
+        assert_eq!(
+            proposed_range(&prices).unwrap(),
+            proposed_range(&[1_000_000; VOLATILITY_WINDOW]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_proposal_measures_il_from_stored_entry_price() {
+        let state = risk_state_with_prices(&[1_000_000; VOLATILITY_WINDOW]);
+        let il = |entry_sqrt_price_q64| {
+            propose_rebalance(&amm_pool(), &amm_position(entry_sqrt_price_q64), &state)
+                .unwrap()
+                .il_percentage
+        };
+
+        assert_eq!(il(1 << 64), IlPercentage::ZERO);
+        let entry_sqrt_price_q64 = amm_core::math::tick_to_sqrt_price_q64(2_000).unwrap();
+        assert_eq!(
+            il(entry_sqrt_price_q64),
+            calculate_current_il_percentage(-6_000, 6_000, entry_sqrt_price_q64, 1 << 64).unwrap()
+        );
+        assert!(il(entry_sqrt_price_q64) < IlPercentage::ZERO);
+    }
+
+    #[test]
+    fn test_new_pool_range_is_sized_from_category_prior() {
+        let long_tail_prior =
+            LONG_TAIL_VOLATILITY_PRIOR_BPS as u128 * RETURN_SCALING_FACTOR / BPS_DENOMINATOR;
+        let prior_range = calculate_optimal_boundaries_mvp(
+            1 << 64,
+            long_tail_prior,
+            MVP_RANGE_Z_SCORE_BPS,
+            MVP_RANGE_HORIZON_DAYS,
+            amm_pool().tick_spacing,
+        )
+        .unwrap();
+        for prices in [&[][..], &[1_000_000], &[1_000_000, 1_010_000]] {
+            assert_eq!(
+                proposed_range_of_category(PoolCategory::LongTail, prices).unwrap(),
+                prior_range
+            );
+        }
+
+        let stable_range = proposed_range_of_category(PoolCategory::Stable, &[]).unwrap();
+        assert!(width(prior_range) > width(stable_range));
+    }
+
+    #[test]
+    fn test_range_narrows_smoothly_from_prior_to_measured() {
+        let prices = swinging_prices(10_000);
+        let widths: Vec<i32> = (1..=VOLATILITY_WINDOW)
+            .map(|count| {
+                width(proposed_range_of_category(PoolCategory::LongTail, &prices[..count]).unwrap())
+            })
+            .collect();
+
+        // Prior alone until two returns are measured, then narrower with every price
+        assert_eq!(widths[0], widths[1]);
+        assert!(widths[1..].windows(2).all(|pair| pair[1] < pair[0]));
+
+        // A full window is sized from the measurement alone, whatever the category
+        let measured_range = proposed_range_of_category(PoolCategory::Stable, &prices).unwrap();
+        assert_eq!(widths[VOLATILITY_WINDOW - 1], width(measured_range));
+        assert_eq!(
+            proposed_range_of_category(PoolCategory::LongTail, &prices).unwrap(),
+            measured_range
+        );
+    }
+}
@@ -1,4 +1,6 @@
-//! Calculates volatility using a rolling window standard deviation.
+//! Calculates volatility using a rolling window standard deviation, a RiskMetrics EWMA with
+//! `calculate_ewma_volatility`, as a GARCH(1,1) forecast with `GarchModel`, or as an
+//! exponential moving average of squared returns with `TimeWeightedVolatility`.
 //! This implementation uses fixed-point arithmetic with u128/i128 for on-chain compatibility.
 //!
 //! Assumptions:
@@ -10,12 +12,33 @@
 //! 3. The output standard deviation is also a scaled integer. Using `RETURN_SCALING_FACTOR`,
 //!    a returned value of `X` represents an actual standard deviation of `X / RETURN_SCALING_FACTOR`.
 //!    For example, if `RETURN_SCALING_FACTOR` is 10^9, a result of 50,000,000 means 0.05 or 5%.
+use crate::circuit_breaker::{PriceObservation, BPS_DENOMINATOR};
+use crate::errors::RiskEngineError as ErrorCode;
+use crate::state::MAX_PRICE_OBSERVATIONS;
+use amm_core::math as amm_math;
 use anchor_lang::prelude::*;
+use primitive_types::U256;
 /// Scaling factor for representing returns and standard deviation.
 /// For example, 10^9 means 9 decimal places of precision for the percentage return.
 pub(crate) const RETURN_SCALING_FACTOR: u128 = 1_000_000_000; // 10^9
 const RETURN_SCALING_FACTOR_I128: i128 = 1_000_000_000; // 10^9 as i128
 
+/// Seconds in the day `calculate_daily_volatility_score` normalizes returns to.
+pub const SECONDS_PER_DAY: u128 = 86_400;
+
+/// Number of most recent recorded prices `PoolRiskState::update_volatility` uses.
+pub const VOLATILITY_WINDOW: usize = 10;
+
+/// Maximum number of `PoolRiskState` accounts `calculate_volatility_batch` processes.
+pub const MAX_VOLATILITY_BATCH: usize = 16;
+
+/// Fewest prices `GarchModel::estimate_volatility` accepts.
+pub const GARCH_MIN_PRICES: usize = VOLATILITY_WINDOW;
+
+/// RiskMetrics' decay factor for `calculate_ewma_volatility`, 0.94, scaled by
+/// `RETURN_SCALING_FACTOR`.
+pub const DEFAULT_EWMA_LAMBDA: u128 = 940_000_000;
+
 /// Calculates the integer square root of a u128 number using the Babylonian method.
 /// Returns floor(sqrt(n)).
 pub(crate) fn isqrt_u128(n: u128) -> u128 {
@@ -60,31 +83,16 @@ pub fn calculate_rolling_std_dev_volatility(
         return Ok(0);
     }
 
-    let mut returns_scaled: Vec<i128> = Vec::new();
-    for i in 1..relevant_prices.len() {
-        let p1 = relevant_prices[i - 1];
-        let p2 = relevant_prices[i];
-
-        if p1 == 0 {
-            // Cannot calculate return if previous price is zero. Skip this data point.
-            // Depending on requirements, could also return an error or a specific value.
-            continue;
-        }
-
-        // Calculate simple percentage return: (p2 - p1) / p1
-        // All prices are u128, diff can be negative.
-        let diff: i128 = (p2 as i128) - (p1 as i128);
-
-        // Scale the return: (diff * SCALING_FACTOR) / p1
-        // (diff / p1) is the unscaled return. Multiplying by SCALING_FACTOR gives scaled return.
-        // Order of operations: multiply first to maintain precision before division.
-        let return_scaled: i128 = (diff * RETURN_SCALING_FACTOR_I128) / (p1 as i128);
-        returns_scaled.push(return_scaled);
-    }
+    let returns_scaled = simple_returns_scaled(relevant_prices);
+    Ok(sample_std_dev_scaled(&returns_scaled))
+}
 
+/// Sample standard deviation of `returns_scaled`, on their scale. Zero for fewer than two
+/// returns.
+fn sample_std_dev_scaled(returns_scaled: &[i128]) -> u128 {
     // Sample standard deviation requires at least 2 returns.
     if returns_scaled.len() < 2 {
-        return Ok(0);
+        return 0;
     }
 
     let num_returns = returns_scaled.len() as i128;
@@ -109,7 +117,327 @@ pub fn calculate_rolling_std_dev_volatility(
     // Standard deviation is sqrt(variance).
     // isqrt_u128(value_scaled_by_S^2) returns sqrt(value) * S.
     // So, the result std_dev_scaled has a scale of RETURN_SCALING_FACTOR.
-    let std_dev_scaled = isqrt_u128(variance_scaled_twice);
+    isqrt_u128(variance_scaled_twice)
+}
+
+/// The rolling volatility of the latest prices of `price_history`, up to `VOLATILITY_WINDOW`
+/// of them, for histories that may not fill the window yet.
+///
+/// The confidence grows linearly with the number of returns measured: zero below the two
+/// a sample deviation needs, 10_000 once the history fills the window.
+pub fn calculate_rolling_volatility_score(price_history: &[u128]) -> Result<VolatilityScore> {
+    let window_size = price_history.len().min(VOLATILITY_WINDOW);
+    Ok(VolatilityScore {
+        volatility: calculate_rolling_std_dev_volatility(price_history, window_size)?,
+        confidence: window_confidence(window_size),
+    })
+}
+
+/// The daily volatility of the latest observations of `observations`, up to
+/// `VOLATILITY_WINDOW` of them, with the confidence of `calculate_rolling_volatility_score`.
+///
+/// Prices are recorded whenever the pool is sampled, so the returns between them span
+/// uneven times. Each return is scaled by `sqrt(SECONDS_PER_DAY / Δt)`, `Δt` being the
+/// seconds between its two observations, before the sample deviation is taken. A burst of
+/// closely spaced samples then does not read as a calm day, and the result is a daily
+/// volatility whatever the sampling rate. Returns from a zero price are skipped.
+///
+/// # Arguments
+/// * `observations` - Recorded observations, oldest first, at strictly increasing
+///   timestamps.
+pub fn calculate_daily_volatility_score(
+    observations: &[PriceObservation],
+) -> Result<VolatilityScore> {
+    let window_size = observations.len().min(VOLATILITY_WINDOW);
+    let window = &observations[observations.len() - window_size..];
+
+    let mut returns_scaled = Vec::with_capacity(window_size.saturating_sub(1));
+    for pair in window.windows(2) {
+        let (previous, current) = (&pair[0], &pair[1]);
+        if previous.price == 0 {
+            continue;
+        }
+        let elapsed = current.timestamp - previous.timestamp;
+        require!(elapsed > 0, ErrorCode::InvalidObservationTimestamp);
+
+        let return_scaled = (current.price as i128 - previous.price as i128)
+            * RETURN_SCALING_FACTOR_I128
+            / previous.price as i128;
+        // sqrt(SECONDS_PER_DAY / Δt), scaled by RETURN_SCALING_FACTOR.
+        let day_factor_scaled = isqrt_u128(
+            SECONDS_PER_DAY * RETURN_SCALING_FACTOR * RETURN_SCALING_FACTOR / elapsed as u128,
+        ) as i128;
+        returns_scaled.push(
+            return_scaled
+                .checked_mul(day_factor_scaled)
+                .ok_or(ErrorCode::Overflow)?
+                / RETURN_SCALING_FACTOR_I128,
+        );
+    }
+
+    Ok(VolatilityScore {
+        volatility: sample_std_dev_scaled(&returns_scaled),
+        confidence: window_confidence(window_size),
+    })
+}
+
+/// Confidence of a volatility measured over the latest `window_size` prices: zero below the
+/// two returns a sample deviation needs, 10_000 once they fill `VOLATILITY_WINDOW`.
+fn window_confidence(window_size: usize) -> u16 {
+    let num_returns = window_size.saturating_sub(1) as u128;
+    if num_returns < 2 {
+        return 0;
+    }
+    (num_returns * BPS_DENOMINATOR / (VOLATILITY_WINDOW as u128 - 1)) as u16
+}
+
+/// Simple percentage returns between consecutive prices, scaled by `RETURN_SCALING_FACTOR`.
+fn simple_returns_scaled(prices: &[u128]) -> Vec<i128> {
+    let mut returns_scaled: Vec<i128> = Vec::new();
+    for i in 1..prices.len() {
+        let p1 = prices[i - 1];
+        let p2 = prices[i];
 
-    Ok(std_dev_scaled)
+        if p1 == 0 {
+            // Cannot calculate return if previous price is zero. Skip this data point.
+            // Depending on requirements, could also return an error or a specific value.
+            continue;
+        }
+
+        // Calculate simple percentage return: (p2 - p1) / p1
+        // All prices are u128, diff can be negative.
+        let diff: i128 = (p2 as i128) - (p1 as i128);
+
+        // Scale the return: (diff * SCALING_FACTOR) / p1
+        // (diff / p1) is the unscaled return. Multiplying by SCALING_FACTOR gives scaled return.
+        // Order of operations: multiply first to maintain precision before division.
+        let return_scaled: i128 = (diff * RETURN_SCALING_FACTOR_I128) / (p1 as i128);
+        returns_scaled.push(return_scaled);
+    }
+    returns_scaled
+}
+
+/// Calculates the exponentially weighted volatility of `price_history`, oldest price first,
+/// on the same returns and scale as `calculate_rolling_std_dev_volatility`.
+///
+/// The variance starts from the first squared return, and each later return `r` moves it
+/// to `λ·σ² + (1 - λ)·r²`, the RiskMetrics recursion. A shock's weight decays by `λ` per
+/// return instead of staying constant until it leaves a window, so the estimate follows a
+/// change of regime sooner. Returns are not demeaned. As for the rolling estimate, fewer
+/// than two returns give zero.
+///
+/// # Arguments
+/// * `price_history` - Recorded prices, oldest first.
+/// * `lambda_scaled` - The decay factor `λ`, scaled by `RETURN_SCALING_FACTOR`; see
+///   `DEFAULT_EWMA_LAMBDA`.
+///
+/// # Errors
+/// * `InvalidEwmaLambda` - `lambda_scaled` is not below `RETURN_SCALING_FACTOR`.
+pub fn calculate_ewma_volatility(price_history: &[u128], lambda_scaled: u128) -> Result<u128> {
+    if lambda_scaled >= RETURN_SCALING_FACTOR {
+        return err!(ErrorCode::InvalidEwmaLambda);
+    }
+    let returns_scaled = simple_returns_scaled(price_history);
+    if returns_scaled.len() < 2 {
+        return Ok(0);
+    }
+
+    // Squared returns, scaled by RETURN_SCALING_FACTOR^2.
+    let squared_returns = returns_scaled
+        .iter()
+        .map(|r_scaled| r_scaled.unsigned_abs().checked_pow(2))
+        .collect::<Option<Vec<u128>>>()
+        .ok_or(ErrorCode::Overflow)?;
+    let mut variance_scaled_twice = squared_returns[0];
+    for &squared_return in &squared_returns[1..] {
+        let weighted = lambda_scaled
+            .checked_mul(variance_scaled_twice)
+            .zip((RETURN_SCALING_FACTOR - lambda_scaled).checked_mul(squared_return))
+            .and_then(|(carried, latest)| carried.checked_add(latest))
+            .ok_or(ErrorCode::Overflow)?;
+        variance_scaled_twice = weighted / RETURN_SCALING_FACTOR;
+    }
+
+    Ok(isqrt_u128(variance_scaled_twice))
+}
+
+/// Log returns between consecutive prices, scaled by `RETURN_SCALING_FACTOR`. Prices must
+/// be non-zero.
+fn log_returns_scaled(price_history: &[u128]) -> Result<Vec<i128>> {
+    // ln(P₂/P₁) as the difference of the logarithms, reading each price as a Q64.64
+    // number; the offset that introduces cancels out.
+    let mut log_prices = Vec::with_capacity(price_history.len());
+    for &price in price_history {
+        log_prices.push(amm_math::ln_fixed(price)?);
+    }
+    Ok(log_prices
+        .windows(2)
+        .map(|pair| ((pair[1] - pair[0]) * RETURN_SCALING_FACTOR_I128) >> 64)
+        .collect())
+}
+
+/// A volatility estimate together with how much data backs it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VolatilityScore {
+    /// Estimated standard deviation of the next return, scaled by `RETURN_SCALING_FACTOR`.
+    pub volatility: u128,
+    /// How much data backs the estimate, in basis points. Each estimator documents its
+    /// own rule.
+    pub confidence: u16,
+}
+
+/// A GARCH(1,1) volatility model, `σ²_t = ω + α·ε²_{t-1} + β·σ²_{t-1}`.
+///
+/// `omega` is a variance of returns scaled by `RETURN_SCALING_FACTOR²`; `alpha` and `beta`
+/// are weights scaled by `RETURN_SCALING_FACTOR`, and must add up to less than one for
+/// the variance to stay bounded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GarchModel {
+    pub omega: u64,
+    pub alpha: u64,
+    pub beta: u64,
+}
+
+impl GarchModel {
+    pub const fn new(omega: u64, alpha: u64, beta: u64) -> Self {
+        Self { omega, alpha, beta }
+    }
+
+    /// Estimates the volatility of the return following `price_history`, oldest price
+    /// first.
+    ///
+    /// The shocks `ε_t` are the log returns between consecutive prices minus their mean.
+    /// The recursion starts from their sample variance and runs over every shock, so the
+    /// final variance is the forecast for the next return.
+    ///
+    /// The confidence grows linearly with the number of prices beyond `GARCH_MIN_PRICES`,
+    /// reaching 10_000 at `MAX_PRICE_OBSERVATIONS`.
+    ///
+    /// Fails with `InvalidGarchParameters` if `alpha + beta` is not below one, and with
+    /// `VolatilityDataError` if the history has fewer than `GARCH_MIN_PRICES` prices or a
+    /// zero price.
+    pub fn estimate_volatility(&self, price_history: &[u128]) -> Result<VolatilityScore> {
+        let (omega, alpha, beta) = (self.omega as u128, self.alpha as u128, self.beta as u128);
+        if alpha + beta >= RETURN_SCALING_FACTOR {
+            return err!(ErrorCode::InvalidGarchParameters);
+        }
+        if price_history.len() < GARCH_MIN_PRICES || price_history.contains(&0) {
+            return err!(ErrorCode::VolatilityDataError);
+        }
+
+        let returns_scaled = log_returns_scaled(price_history)?;
+        let num_returns = returns_scaled.len() as i128;
+        let mean_return_scaled = returns_scaled.iter().sum::<i128>() / num_returns;
+        // Squared shocks, scaled by RETURN_SCALING_FACTOR^2.
+        let shocks_squared: Vec<u128> = returns_scaled
+            .iter()
+            .map(|r_scaled| (r_scaled - mean_return_scaled).unsigned_abs().pow(2))
+            .collect();
+
+        let mut variance_scaled_twice =
+            shocks_squared.iter().sum::<u128>() / (num_returns as u128 - 1);
+        for shock_squared in shocks_squared {
+            let weighted = alpha
+                .checked_mul(shock_squared)
+                .zip(beta.checked_mul(variance_scaled_twice))
+                .and_then(|(shock, carried)| shock.checked_add(carried))
+                .ok_or(ErrorCode::Overflow)?;
+            variance_scaled_twice = omega + weighted / RETURN_SCALING_FACTOR;
+        }
+
+        let extra_prices =
+            (price_history.len() - GARCH_MIN_PRICES).min(MAX_PRICE_OBSERVATIONS - GARCH_MIN_PRICES);
+        let confidence = extra_prices as u128 * BPS_DENOMINATOR
+            / (MAX_PRICE_OBSERVATIONS - GARCH_MIN_PRICES) as u128;
+
+        Ok(VolatilityScore {
+            volatility: isqrt_u128(variance_scaled_twice),
+            confidence: confidence as u16,
+        })
+    }
+}
+
+/// Volatility weighted towards recent returns: the square root of an exponential moving
+/// average of squared log returns.
+///
+/// The average over a span of `n` returns uses the decay factor `2 / (n + 1)`, as for the
+/// usual EMA of prices, so the latest return weighs most and older ones fade
+/// geometrically. Returns are not demeaned: over short spans the drift is negligible next
+/// to the noise.
+pub struct TimeWeightedVolatility;
+
+impl TimeWeightedVolatility {
+    /// Estimates the volatility of the return following `price_history`, oldest price
+    /// first, averaging squared returns over `span` returns.
+    ///
+    /// The confidence grows linearly with the number of returns, reaching 10_000 once the
+    /// history covers the whole span.
+    ///
+    /// Fails like [`TimeWeightedVolatility::compute_ewma_std_dev`].
+    pub fn compute_ema_volatility(price_history: &[u128], span: u64) -> Result<VolatilityScore> {
+        let volatility = Self::compute_ewma_std_dev(price_history, span)?;
+        let num_returns = (price_history.len() - 1) as u128;
+        let confidence = num_returns.min(span as u128) * BPS_DENOMINATOR / span as u128;
+        Ok(VolatilityScore {
+            volatility,
+            confidence: confidence as u16,
+        })
+    }
+
+    /// The exponentially weighted standard deviation of the log returns of
+    /// `price_history`, scaled by `RETURN_SCALING_FACTOR`.
+    ///
+    /// The average starts from the first squared return, then each later return `r` moves
+    /// it to `α·r² + (1 - α)·average` with `α = 2 / (span + 1)`.
+    ///
+    /// Fails with `VolatilityDataError` if `span` is zero, if the history has fewer than
+    /// two prices or fewer than half of `span`, or if it contains a zero price.
+    pub fn compute_ewma_std_dev(price_history: &[u128], span: u64) -> Result<u128> {
+        if span == 0
+            || price_history.len() < 2
+            || price_history.len() < (span / 2) as usize
+            || price_history.contains(&0)
+        {
+            return err!(ErrorCode::VolatilityDataError);
+        }
+        // α scaled by RETURN_SCALING_FACTOR
+        let alpha = 2 * RETURN_SCALING_FACTOR / (span as u128 + 1);
+
+        let returns_scaled = log_returns_scaled(price_history)?;
+        // Squared returns, scaled by RETURN_SCALING_FACTOR^2.
+        let mut squared_returns = returns_scaled
+            .iter()
+            .map(|r_scaled| r_scaled.unsigned_abs().pow(2));
+        let mut variance_scaled_twice = squared_returns.next().unwrap_or_default();
+        for squared_return in squared_returns {
+            let weighted = alpha
+                .checked_mul(squared_return)
+                .zip((RETURN_SCALING_FACTOR - alpha).checked_mul(variance_scaled_twice))
+                .and_then(|(latest, carried)| latest.checked_add(carried))
+                .ok_or(ErrorCode::Overflow)?;
+            variance_scaled_twice = weighted / RETURN_SCALING_FACTOR;
+        }
+
+        Ok(isqrt_u128(variance_scaled_twice))
+    }
+
+    /// Interpolates linearly between a short-span and a long-span estimate, giving
+    /// `short` a weight of `weight` basis points. Weights above 10_000 count as 10_000.
+    ///
+    /// Blending a quick estimate with a slow one keeps a single outlying return from
+    /// swinging the result as far as it swings the short-span estimate alone.
+    pub fn blend(short: VolatilityScore, long: VolatilityScore, weight: u64) -> VolatilityScore {
+        let weight = (weight as u128).min(BPS_DENOMINATOR);
+        let interpolate = |short: u128, long: u128| {
+            let blended = (U256::from(short) * U256::from(weight)
+                + U256::from(long) * U256::from(BPS_DENOMINATOR - weight))
+                / U256::from(BPS_DENOMINATOR);
+            // Never above the larger input, so it fits
+            blended.as_u128()
+        };
+        VolatilityScore {
+            volatility: interpolate(short.volatility, long.volatility),
+            confidence: interpolate(short.confidence as u128, long.confidence as u128) as u16,
+        }
+    }
 }
@@ -0,0 +1,230 @@
+//! Compatibility checks for the cross-program invocations made inside the workspace.
+//!
+//! Callers build their CPIs with the callee's generated `cpi::accounts` structs, so adding
+//! or removing an account in the callee already fails to compile. Reordering accounts or
+//! changing their signer and writable flags does not: the caller keeps building and only
+//! fails at runtime, e.g. with a privilege escalation error when an account becomes
+//! writable in the callee but stays read-only in the caller's own accounts struct.
+//!
+//! This crate records the account metas layout of every instruction invoked through a CPI
+//! and checks it three ways:
+//!
+//! * `tests/layout_lock_test.rs` compares a hash of each layout with `cpi_layouts.lock`, so
+//!   a layout change fails CI until the lock is regenerated alongside the caller update.
+//! * `tests/layout_lock_test.rs` also checks that each caller passes every account with at
+//!   least the privileges the callee requires.
+//! * `tests/cpi_runtime_test.rs` executes every CPI against the built programs.
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+/// An account of an instruction, at the position the instruction expects it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountLayout {
+    /// Field name in the instruction's accounts struct.
+    pub name: &'static str,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// The accounts an instruction expects, in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstructionLayout {
+    /// `<program>::<instruction>`.
+    pub name: &'static str,
+    pub accounts: Vec<AccountLayout>,
+}
+
+/// Deterministic key standing in for the account named `name` when reading a layout.
+pub fn placeholder_key(name: &str) -> Pubkey {
+    Pubkey::new_from_array(hashv(&[b"cpi_compat", name.as_bytes()]).to_bytes())
+}
+
+impl InstructionLayout {
+    /// Reads the layout from the account metas of a client accounts struct whose fields,
+    /// named `field_names`, are each set to their `placeholder_key`.
+    ///
+    /// Prefer the `instruction_layout!` macro, which builds the struct.
+    pub fn from_accounts(
+        name: &'static str,
+        accounts: &impl ToAccountMetas,
+        field_names: &[&'static str],
+    ) -> Self {
+        let accounts = accounts
+            .to_account_metas(None)
+            .iter()
+            .map(|meta| {
+                let field_name = field_names
+                    .iter()
+                    .copied()
+                    .find(|field_name| placeholder_key(field_name) == meta.pubkey)
+                    .unwrap_or_else(|| {
+                        panic!("{name}: account meta {} matches no field", meta.pubkey)
+                    });
+                AccountLayout {
+                    name: field_name,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                }
+            })
+            .collect();
+        InstructionLayout { name, accounts }
+    }
+
+    /// Returns the account named `name`.
+    pub fn account(&self, name: &str) -> Option<&AccountLayout> {
+        self.accounts.iter().find(|account| account.name == name)
+    }
+
+    /// Hash of the ordered account names and their signer and writable flags.
+    pub fn hash(&self) -> String {
+        let entries: Vec<String> = self
+            .accounts
+            .iter()
+            .map(|account| {
+                format!(
+                    "{}:{}:{}",
+                    account.name, account.is_signer as u8, account.is_writable as u8
+                )
+            })
+            .collect();
+        hashv(&[entries.join(",").as_bytes()]).to_string()
+    }
+}
+
+/// Builds the `InstructionLayout` of a client accounts struct.
+///
+/// Every field of the struct must be listed, so adding or removing an account in the
+/// instruction is a compile error here as well as in its callers. Optional accounts are
+/// read as present.
+#[macro_export]
+macro_rules! instruction_layout {
+    ($name:expr, $($accounts:ident)::+ { $($field:ident),* $(,)? }) => {
+        $crate::InstructionLayout::from_accounts(
+            $name,
+            &$($accounts)::+ {
+                $($field: $crate::placeholder_key(stringify!($field)).into(),)*
+            },
+            &[$(stringify!($field)),*],
+        )
+    };
+}
+
+/// A CPI made by one workspace program into another.
+#[derive(Clone, Debug)]
+pub struct CpiCall {
+    /// The instruction making the CPI.
+    pub caller: InstructionLayout,
+    /// The instruction invoked.
+    pub callee: InstructionLayout,
+    /// Caller account passed for each callee account, as `(caller field, callee field)`.
+    pub account_map: Vec<(&'static str, &'static str)>,
+}
+
+impl CpiCall {
+    /// Returns the callee accounts the caller passes with fewer privileges than the callee
+    /// requires, or that the caller does not pass at all.
+    pub fn missing_privileges(&self) -> Vec<&'static str> {
+        self.callee
+            .accounts
+            .iter()
+            .filter(|callee_account| {
+                let caller_account = self
+                    .account_map
+                    .iter()
+                    .find(|(_, callee_field)| *callee_field == callee_account.name)
+                    .and_then(|(caller_field, _)| self.caller.account(caller_field));
+                caller_account.is_none_or(|caller_account| {
+                    (callee_account.is_signer && !caller_account.is_signer)
+                        || (callee_account.is_writable && !caller_account.is_writable)
+                })
+            })
+            .map(|callee_account| callee_account.name)
+            .collect()
+    }
+}
+
+/// `amm_core::update_position_handler`, invoked by the risk engine to rebalance a position.
+pub fn amm_core_update_position() -> InstructionLayout {
+    instruction_layout!(
+        "amm_core::update_position_handler",
+        amm_core::accounts::UpdatePosition {
+            pool,
+            position,
+            old_tick_lower,
+            old_tick_upper,
+            new_tick_lower,
+            new_tick_upper,
+            token0_vault,
+            token1_vault,
+            owner_token0_account,
+            owner_token1_account,
+            owner,
+            payer,
+            system_program,
+            token_program,
+            rent,
+            position_token_account,
+        }
+    )
+}
+
+/// `fluxa_risk_engine::trigger_rebalance_check`, which invokes `update_position_handler`.
+pub fn risk_engine_trigger_rebalance_check() -> InstructionLayout {
+    instruction_layout!(
+        "fluxa_risk_engine::trigger_rebalance_check",
+        fluxa_risk_engine::accounts::TriggerRebalanceCheck {
+            amm_pool,
+            amm_position,
+            amm_old_tick_lower,
+            amm_old_tick_upper,
+            amm_new_tick_lower,
+            amm_new_tick_upper,
+            amm_token0_vault,
+            amm_token1_vault,
+            owner_token0_account,
+            owner_token1_account,
+            token_pair,
+            pyth_price_feed,
+            token0_mint,
+            token1_mint,
+            risk_config,
+            pool_risk_state,
+            owner,
+            payer,
+            amm_core_program,
+            system_program,
+            token_program,
+            rent,
+            amm_position_token_account,
+            keeper_bond,
+            position_risk_state,
+        }
+    )
+}
+
+/// Every CPI made between workspace programs.
+pub fn workspace_cpis() -> Vec<CpiCall> {
+    vec![CpiCall {
+        caller: risk_engine_trigger_rebalance_check(),
+        callee: amm_core_update_position(),
+        // Mirrors the `AmmUpdatePositionCtx` built in `trigger_rebalance_check`.
+        account_map: vec![
+            ("amm_pool", "pool"),
+            ("amm_position", "position"),
+            ("amm_old_tick_lower", "old_tick_lower"),
+            ("amm_old_tick_upper", "old_tick_upper"),
+            ("amm_new_tick_lower", "new_tick_lower"),
+            ("amm_new_tick_upper", "new_tick_upper"),
+            ("amm_token0_vault", "token0_vault"),
+            ("amm_token1_vault", "token1_vault"),
+            ("owner_token0_account", "owner_token0_account"),
+            ("owner_token1_account", "owner_token1_account"),
+            ("owner", "owner"),
+            ("payer", "payer"),
+            ("system_program", "system_program"),
+            ("token_program", "token_program"),
+            ("rent", "rent"),
+            ("amm_position_token_account", "position_token_account"),
+        ],
+    }]
+}
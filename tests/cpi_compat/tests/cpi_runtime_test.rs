@@ -0,0 +1,917 @@
+// /tests/cpi_runtime_test.rs
+//
+// Executes every CPI made between workspace programs against the built programs, once with
+// the minimum and once with the maximum set of accounts the caller accepts.
+//
+// Both programs are loaded from the shared objects in `SBF_OUT_DIR`, so build them first:
+//
+//   anchor build
+//   SBF_OUT_DIR=$PWD/target/deploy cargo test -p cpi_compat --test cpi_runtime_test
+
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{program_pack::Pack, system_instruction},
+    AccountDeserialize, AccountSerialize, AnchorDeserialize, Event, InstructionData,
+    ToAccountMetas,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::AccountSharedData,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    clock::Clock,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    rent::Rent,
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::{Transaction, TransactionError},
+};
+
+use amm_core::state::factory_config::{FactoryConfig, ProtocolFeeCurve};
+use amm_core::{math, position::PositionData};
+use fluxa_risk_engine::{
+    errors::RiskEngineError,
+    state::{PoolCategory, PoolRiskState, PositionRiskState, RiskConfigParams},
+    volatility_detector::VOLATILITY_WINDOW,
+    RebalanceEvent, RebalancePreview, ShadowRebalance,
+};
+
+const TICK_SPACING: u16 = 60;
+const TICK_LOWER: i32 = -6000;
+const TICK_UPPER: i32 = 6000;
+/// The position is opened at tick 3000 and the pool is then swapped down to tick 0, so it
+/// carries IL.
+const ENTRY_TICK: i32 = 3000;
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000;
+/// Enough of each token for the payer to deposit into the position.
+const OWNER_FUNDING: u64 = 1_000_000_000_000;
+/// More token0 than the swap from `ENTRY_TICK` down to tick 0 takes. The swap stops at its
+/// price limit.
+const SWAP_AMOUNT_IN: u64 = 250_000_000_000;
+/// Cost estimate and position value passed to `trigger_rebalance_check`. The IL from
+/// `ENTRY_TICK` saves far more than the cost.
+const REBALANCE_COST_LAMPORTS: u64 = 5_000;
+const POSITION_VALUE_LAMPORTS: u64 = 10_000_000_000;
+
+/// Which of the accounts a caller instruction accepts are passed.
+#[derive(Clone, Copy, Debug)]
+enum AccountSet {
+    /// Only the accounts the instruction requires.
+    Minimum,
+    /// Every account the instruction accepts. `trigger_rebalance_check` is only given the
+    /// optional accounts of pools sampled from Pyth and of NFT-held positions, so this
+    /// appends trailing accounts that both programs must ignore.
+    Maximum,
+}
+
+/// Processes a transaction and returns its log lines, or the error it failed with.
+async fn try_process(
+    context: &mut ProgramTestContext,
+    ixs: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<Vec<String>, TransactionError> {
+    let transaction = Transaction::new_signed_with_payer(
+        ixs,
+        Some(&context.payer.pubkey()),
+        signers,
+        context.last_blockhash,
+    );
+    let processed = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    processed.result?;
+    Ok(processed
+        .metadata
+        .expect("transaction metadata missing")
+        .log_messages)
+}
+
+/// Processes a transaction that must succeed and returns its log lines.
+async fn process(
+    context: &mut ProgramTestContext,
+    ixs: &[Instruction],
+    signers: &[&Keypair],
+) -> Vec<String> {
+    try_process(context, ixs, signers)
+        .await
+        .unwrap_or_else(|err| panic!("transaction failed: {err:?}"))
+}
+
+/// Decodes every event of type `E` in `logs`.
+fn events<E: Event>(logs: &[String]) -> Vec<E> {
+    logs.iter()
+        .filter_map(|line| line.strip_prefix("Program data: "))
+        .map(|data| STANDARD.decode(data).unwrap())
+        .filter(|bytes| bytes.starts_with(E::DISCRIMINATOR))
+        .map(|bytes| E::try_from_slice(&bytes[E::DISCRIMINATOR.len()..]).unwrap())
+        .collect()
+}
+
+async fn create_mint(context: &mut ProgramTestContext) -> Pubkey {
+    let payer = context.payer.insecure_clone();
+    let mint_keypair = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let ixs = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &mint_keypair.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &mint_keypair.pubkey(),
+            &payer.pubkey(),
+            None,
+            0,
+        )
+        .unwrap(),
+    ];
+    process(context, &ixs, &[&payer, &mint_keypair]).await;
+    mint_keypair.pubkey()
+}
+
+async fn create_token_account(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Pubkey {
+    let payer = context.payer.insecure_clone();
+    let account_keypair = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let ixs = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &account_keypair.pubkey(),
+            rent.minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account(
+            &spl_token::id(),
+            &account_keypair.pubkey(),
+            mint,
+            owner,
+        )
+        .unwrap(),
+    ];
+    process(context, &ixs, &[&payer, &account_keypair]).await;
+    account_keypair.pubkey()
+}
+
+async fn mint_to(context: &mut ProgramTestContext, mint: &Pubkey, account: &Pubkey, amount: u64) {
+    let payer = context.payer.insecure_clone();
+    let ix = spl_token::instruction::mint_to(
+        &spl_token::id(),
+        mint,
+        account,
+        &payer.pubkey(),
+        &[],
+        amount,
+    )
+    .unwrap();
+    process(context, &[ix], &[&payer]).await;
+}
+
+fn tick_pda(pool: &Pubkey, index: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"tick".as_ref(),
+            pool.as_ref(),
+            index.to_le_bytes().as_ref(),
+        ],
+        &amm_core::ID,
+    )
+    .0
+}
+
+/// Installs the program's ProgramData account with `authority` as its upgrade authority,
+/// which `initialize_factory_config` requires of its signer. The test loads the program
+/// without the upgradeable loader, so the account does not exist otherwise.
+fn set_upgrade_authority(context: &mut ProgramTestContext, authority: Pubkey) -> Pubkey {
+    let (program_data, _) =
+        Pubkey::find_program_address(&[amm_core::ID.as_ref()], &bpf_loader_upgradeable::ID);
+    let state = UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(authority),
+    };
+    let lamports =
+        Rent::default().minimum_balance(UpgradeableLoaderState::size_of_programdata_metadata());
+    let account =
+        AccountSharedData::new_data(lamports, &state, &bpf_loader_upgradeable::ID).unwrap();
+    context.set_account(&program_data, &account);
+    program_data
+}
+
+/// Instructions creating the factory config, with the payer as its authority, and enabling
+/// `fee_rate` with `tick_spacing` on it, skipping whichever an earlier pool of the test
+/// already did. Returns the factory config along with them.
+async fn fee_tier_ixs(
+    context: &mut ProgramTestContext,
+    fee_rate: u16,
+    tick_spacing: u16,
+) -> (Pubkey, Vec<Instruction>) {
+    let authority = context.payer.pubkey();
+    let (factory_config, _) =
+        Pubkey::find_program_address(&[b"factory_config".as_ref()], &amm_core::ID);
+    let config = context
+        .banks_client
+        .get_account(factory_config)
+        .await
+        .unwrap()
+        .map(|account| FactoryConfig::try_deserialize(&mut account.data.as_slice()).unwrap());
+
+    let mut ixs = Vec::new();
+    if config.is_none() {
+        let program_data = set_upgrade_authority(context, authority);
+        ixs.push(Instruction {
+            program_id: amm_core::ID,
+            accounts: amm_core::accounts::InitializeFactoryConfig {
+                factory_config,
+                authority,
+                program_data,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm_core::instruction::InitializeFactoryConfigHandler {
+                // No protocol share at any liquidity.
+                protocol_fee_curve: ProtocolFeeCurve {
+                    tvl_breakpoints: [1, 2, 3, 4],
+                    protocol_share_bps: [0; 5],
+                },
+            }
+            .data(),
+        });
+    }
+    if config.is_none_or(|config| config.tick_spacing_for(fee_rate).is_none()) {
+        ixs.push(Instruction {
+            program_id: amm_core::ID,
+            accounts: amm_core::accounts::EnableFeeTier {
+                factory_config,
+                authority,
+            }
+            .to_account_metas(None),
+            data: amm_core::instruction::EnableFeeTierHandler {
+                fee_rate,
+                tick_spacing,
+            }
+            .data(),
+        });
+    }
+    (factory_config, ixs)
+}
+
+/// The accounts created by `setup_pool_with_position`.
+struct PoolWithPosition {
+    pool: Pubkey,
+    token_pair: Pubkey,
+    position: Pubkey,
+    vault_a: Pubkey,
+    vault_b: Pubkey,
+    /// The payer's token accounts, which funded the position.
+    owner_a: Pubkey,
+    owner_b: Pubkey,
+}
+
+/// Creates a pool holding one position over `[TICK_LOWER, TICK_UPPER)` owned by the payer,
+/// opened at `ENTRY_TICK` while the pool now trades at tick 0, and the risk engine accounts
+/// tracking it with a window of recorded prices.
+async fn setup_pool_with_position(context: &mut ProgramTestContext) -> PoolWithPosition {
+    let payer = context.payer.insecure_clone();
+
+    let mut mint_a = create_mint(context).await;
+    let mut mint_b = create_mint(context).await;
+    if mint_a > mint_b {
+        std::mem::swap(&mut mint_a, &mut mint_b);
+    }
+
+    // 1. Create the pair's record and initialize the pool at the entry tick
+    let fee_rate: u16 = 30;
+    let (pool, _) = Pubkey::find_program_address(
+        &[
+            b"pool".as_ref(),
+            mint_a.as_ref(),
+            mint_b.as_ref(),
+            fee_rate.to_le_bytes().as_ref(),
+        ],
+        &amm_core::ID,
+    );
+    let (pool_registry, _) = Pubkey::find_program_address(
+        &[b"pool_registry".as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        &amm_core::ID,
+    );
+    let (token_pair, _) = Pubkey::find_program_address(
+        &[b"token_pair".as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        &amm_core::ID,
+    );
+    let create_pair_ix = Instruction {
+        program_id: amm_core::ID,
+        accounts: amm_core::accounts::CreateTokenPair {
+            token_pair,
+            mint_a,
+            mint_b,
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::CreateTokenPairHandler {
+            oracle_feed: Pubkey::default(),
+        }
+        .data(),
+    };
+    let vault_a = Keypair::new();
+    let vault_b = Keypair::new();
+    let (factory_config, fee_tier_ixs) = fee_tier_ixs(context, fee_rate, TICK_SPACING).await;
+    let init_pool_ix = Instruction {
+        program_id: amm_core::ID,
+        accounts: amm_core::accounts::InitializePool {
+            pool,
+            mint_a,
+            mint_b,
+            factory: factory_config,
+            pool_vault_a: vault_a.pubkey(),
+            pool_vault_b: vault_b.pubkey(),
+            pool_registry,
+            token_pair,
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+            token_program: spl_token::ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::InitializePoolHandler {
+            initial_sqrt_price_q64: amm_core::math::tick_to_sqrt_price_q64(ENTRY_TICK).unwrap(),
+            fee_rate,
+            tick_spacing: TICK_SPACING,
+            immutable_parameters: false,
+        }
+        .data(),
+    };
+    process(
+        context,
+        &[fee_tier_ixs, vec![create_pair_ix, init_pool_ix]].concat(),
+        &[&payer, &vault_a, &vault_b],
+    )
+    .await;
+
+    // 2. Mint the position, depositing from the payer's token accounts
+    let owner_a = create_token_account(context, &mint_a, &payer.pubkey()).await;
+    let owner_b = create_token_account(context, &mint_b, &payer.pubkey()).await;
+    mint_to(context, &mint_a, &owner_a, OWNER_FUNDING).await;
+    mint_to(context, &mint_b, &owner_b, OWNER_FUNDING).await;
+    let (position, _) = Pubkey::find_program_address(
+        &[
+            b"position".as_ref(),
+            pool.as_ref(),
+            payer.pubkey().as_ref(),
+            TICK_LOWER.to_le_bytes().as_ref(),
+            TICK_UPPER.to_le_bytes().as_ref(),
+            0u64.to_le_bytes().as_ref(),
+        ],
+        &amm_core::ID,
+    );
+    let (position_counter, _) = Pubkey::find_program_address(
+        &[
+            b"position_counter".as_ref(),
+            pool.as_ref(),
+            payer.pubkey().as_ref(),
+        ],
+        &amm_core::ID,
+    );
+    let mint_position_ix = Instruction {
+        program_id: amm_core::ID,
+        accounts: amm_core::accounts::MintPosition {
+            pool,
+            position,
+            tick_lower: tick_pda(&pool, TICK_LOWER),
+            tick_upper: tick_pda(&pool, TICK_UPPER),
+            owner: payer.pubkey(),
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            position_counter,
+            token0_vault: vault_a.pubkey(),
+            token1_vault: vault_b.pubkey(),
+            owner_token0_account: owner_a,
+            owner_token1_account: owner_b,
+            token_program: spl_token::ID,
+            owner_index: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionHandler {
+            tick_lower_index: TICK_LOWER,
+            tick_upper_index: TICK_UPPER,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            position_index: 0,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    };
+    process(context, &[mint_position_ix], &[&payer]).await;
+
+    // 3. Swap the price down to tick 0, away from the position's entry price
+    let swap_ix = Instruction {
+        program_id: amm_core::ID,
+        accounts: amm_core::accounts::SwapExactInput {
+            pool,
+            token0_vault: vault_a.pubkey(),
+            token1_vault: vault_b.pubkey(),
+            user_token_in_account: owner_a,
+            user_token_out_account: owner_b,
+            user_authority: payer.pubkey(),
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::SwapExactInputHandler {
+            amount_in: SWAP_AMOUNT_IN,
+            amount_out_minimum: 1,
+            sqrt_price_limit_q64: 1u128 << 64,
+        }
+        .data(),
+    };
+    process(context, &[swap_ix], &[&payer]).await;
+
+    // 4. Create the risk engine accounts
+    let init_risk_config_ix = Instruction {
+        program_id: fluxa_risk_engine::ID,
+        accounts: fluxa_risk_engine::accounts::InitializeRiskConfig {
+            risk_config: risk_config_pda(),
+            authority: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: fluxa_risk_engine::instruction::InitializeRiskConfig {
+            params: RiskConfigParams {
+                circuit_breaker_threshold_bps: 5_000,
+                circuit_breaker_window_secs: 3_600,
+                twap_interval_secs: 600,
+                halt_duration_secs: 3_600,
+                max_staleness_secs: 60,
+                stable_volatility_prior_bps: 500,
+                major_volatility_prior_bps: 8_000,
+                long_tail_volatility_prior_bps: 20_000,
+            },
+        }
+        .data(),
+    };
+    let init_pool_risk_state_ix = Instruction {
+        program_id: fluxa_risk_engine::ID,
+        accounts: fluxa_risk_engine::accounts::InitializePoolRiskState {
+            pool_risk_state: pool_risk_state_pda(&pool),
+            amm_pool: pool,
+            risk_config: risk_config_pda(),
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: fluxa_risk_engine::instruction::InitializePoolRiskState {
+            category: PoolCategory::Major,
+        }
+        .data(),
+    };
+    process(
+        context,
+        &[init_risk_config_ix, init_pool_risk_state_ix],
+        &[&payer],
+    )
+    .await;
+
+    record_price_window(context, &pool).await;
+
+    PoolWithPosition {
+        pool,
+        token_pair,
+        position,
+        vault_a: vault_a.pubkey(),
+        vault_b: vault_b.pubkey(),
+        owner_a,
+        owner_b,
+    }
+}
+
+/// Reads the balance of a token account.
+async fn token_balance(context: &mut ProgramTestContext, account: &Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(*account)
+        .await
+        .unwrap()
+        .expect("token account missing");
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .amount
+}
+
+/// Records `VOLATILITY_WINDOW` observations of the pool's price a minute apart, the history
+/// the risk engine measures volatility over before proposing a range.
+async fn record_price_window(context: &mut ProgramTestContext, pool: &Pubkey) {
+    let payer = context.payer.insecure_clone();
+    let mut timestamp = 0;
+    for _ in 0..VOLATILITY_WINDOW {
+        // Each observation is the same transaction, so it needs a fresh blockhash. The
+        // clock is moved afterwards so that the new slot does not overwrite it.
+        context.get_new_latest_blockhash().await.unwrap();
+        let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+        timestamp = clock.unix_timestamp.max(timestamp) + 60;
+        clock.unix_timestamp = timestamp;
+        context.set_sysvar(&clock);
+
+        let ix = Instruction {
+            program_id: fluxa_risk_engine::ID,
+            accounts: fluxa_risk_engine::accounts::RecordPriceObservation {
+                risk_config: risk_config_pda(),
+                pool_risk_state: pool_risk_state_pda(pool),
+                amm_pool: *pool,
+            }
+            .to_account_metas(None),
+            data: fluxa_risk_engine::instruction::RecordPriceObservation {}.data(),
+        };
+        process(context, &[ix], &[&payer]).await;
+    }
+}
+
+fn risk_config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"risk_config".as_ref()], &fluxa_risk_engine::ID).0
+}
+
+fn pool_risk_state_pda(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"pool_risk_state".as_ref(), pool.as_ref()],
+        &fluxa_risk_engine::ID,
+    )
+    .0
+}
+
+fn position_risk_state_pda(position: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"position_risk_state".as_ref(), position.as_ref()],
+        &fluxa_risk_engine::ID,
+    )
+    .0
+}
+
+/// Simulates `preview_rebalance` and returns the range the rebalance will move to.
+async fn preview_rebalance(
+    context: &mut ProgramTestContext,
+    pool: &Pubkey,
+    position: &Pubkey,
+) -> RebalancePreview {
+    let payer = context.payer.insecure_clone();
+    let ix = Instruction {
+        program_id: fluxa_risk_engine::ID,
+        accounts: fluxa_risk_engine::accounts::PreviewRebalance {
+            amm_pool: *pool,
+            amm_position: *position,
+            pool_risk_state: pool_risk_state_pda(pool),
+        }
+        .to_account_metas(None),
+        data: fluxa_risk_engine::instruction::PreviewRebalance {
+            allow_one_sided: false,
+        }
+        .data(),
+    };
+    let transaction = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        context.last_blockhash,
+    );
+    let simulation = context
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    assert!(
+        matches!(simulation.result, Some(Ok(()))),
+        "preview simulation failed: {:?}",
+        simulation.result
+    );
+    let return_data = simulation
+        .simulation_details
+        .and_then(|details| details.return_data)
+        .expect("preview_rebalance returned no data");
+    RebalancePreview::try_from_slice(&return_data.data).unwrap()
+}
+
+/// Builds a `trigger_rebalance_check` moving `position` to the previewed range, with the
+/// minimum set of accounts.
+fn trigger_rebalance_check_ix(
+    owner: &Pubkey,
+    setup: &PoolWithPosition,
+    preview: &RebalancePreview,
+) -> Instruction {
+    let pool = &setup.pool;
+    Instruction {
+        program_id: fluxa_risk_engine::ID,
+        accounts: fluxa_risk_engine::accounts::TriggerRebalanceCheck {
+            amm_pool: *pool,
+            amm_position: setup.position,
+            amm_old_tick_lower: tick_pda(pool, TICK_LOWER),
+            amm_old_tick_upper: tick_pda(pool, TICK_UPPER),
+            amm_new_tick_lower: tick_pda(pool, preview.tick_lower),
+            amm_new_tick_upper: tick_pda(pool, preview.tick_upper),
+            amm_token0_vault: setup.vault_a,
+            amm_token1_vault: setup.vault_b,
+            owner_token0_account: setup.owner_a,
+            owner_token1_account: setup.owner_b,
+            token_pair: setup.token_pair,
+            pyth_price_feed: None,
+            token0_mint: None,
+            token1_mint: None,
+            risk_config: risk_config_pda(),
+            pool_risk_state: pool_risk_state_pda(pool),
+            owner: *owner,
+            payer: *owner,
+            amm_core_program: amm_core::ID,
+            system_program: anchor_lang::system_program::ID,
+            token_program: spl_token::ID,
+            rent: sysvar::rent::ID,
+            amm_position_token_account: None,
+            keeper_bond: None,
+            position_risk_state: position_risk_state_pda(&setup.position),
+        }
+        .to_account_metas(None),
+        data: fluxa_risk_engine::instruction::TriggerRebalanceCheck {
+            allow_one_sided: false,
+            rebalance_cost_lamports: REBALANCE_COST_LAMPORTS,
+            position_value_lamports: POSITION_VALUE_LAMPORTS,
+        }
+        .data(),
+    }
+}
+
+/// Runs `trigger_rebalance_check`, which moves the position through a CPI into
+/// `amm_core::update_position_handler`, and checks the position was moved.
+async fn run_rebalance_cpi(account_set: AccountSet) {
+    let mut program_test = ProgramTest::new("amm_core", amm_core::ID, None);
+    program_test.add_program("fluxa_risk_engine", fluxa_risk_engine::ID, None);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+
+    let setup = setup_pool_with_position(&mut context).await;
+    let (pool, position) = (setup.pool, setup.position);
+    let preview = preview_rebalance(&mut context, &pool, &position).await;
+    assert!(preview.rebalance_needed, "{preview:?}");
+    let owner_a_before = token_balance(&mut context, &setup.owner_a).await;
+    let owner_b_before = token_balance(&mut context, &setup.owner_b).await;
+
+    let mut trigger_ix = trigger_rebalance_check_ix(&payer.pubkey(), &setup, &preview);
+    if let AccountSet::Maximum = account_set {
+        trigger_ix
+            .accounts
+            .push(AccountMeta::new_readonly(amm_core::ID, false));
+        trigger_ix
+            .accounts
+            .push(AccountMeta::new(Keypair::new().pubkey(), false));
+    }
+    let logs = process(&mut context, &[trigger_ix], &[&payer]).await;
+
+    let rebalances = events::<RebalanceEvent>(&logs);
+    assert_eq!(rebalances.len(), 1, "{account_set:?} account set");
+    let rebalance = &rebalances[0];
+    assert_eq!(rebalance.position, position);
+    assert_eq!(
+        (rebalance.old_lower, rebalance.old_upper),
+        (TICK_LOWER, TICK_UPPER)
+    );
+    assert_eq!(
+        (rebalance.new_lower, rebalance.new_upper),
+        (preview.tick_lower, preview.tick_upper)
+    );
+    assert_eq!(rebalance.il_scaled, preview.il_percentage);
+
+    let position_account = context
+        .banks_client
+        .get_account(position)
+        .await
+        .unwrap()
+        .expect("position account missing");
+    let position_data =
+        PositionData::try_deserialize(&mut position_account.data.as_slice()).unwrap();
+    assert_eq!(
+        (
+            position_data.tick_lower_index,
+            position_data.tick_upper_index
+        ),
+        (preview.tick_lower, preview.tick_upper),
+        "{account_set:?} account set"
+    );
+    // The moved position is re-entered at the current price
+    assert_eq!(position_data.entry_sqrt_price_q64, 1u128 << 64);
+
+    // The tokens withdrawn at tick 0 are redeposited into the new range, and what it
+    // cannot use is returned to the owner
+    let sqrt_price_q64 = 1u128 << 64;
+    let (withdrawn_a, withdrawn_b) = math::get_amounts_for_liquidity(
+        sqrt_price_q64,
+        math::tick_to_sqrt_price_q64(TICK_LOWER).unwrap(),
+        math::tick_to_sqrt_price_q64(TICK_UPPER).unwrap(),
+        POSITION_LIQUIDITY,
+        false,
+    )
+    .unwrap();
+    let new_sqrt_price_lower_q64 = math::tick_to_sqrt_price_q64(preview.tick_lower).unwrap();
+    let new_sqrt_price_upper_q64 = math::tick_to_sqrt_price_q64(preview.tick_upper).unwrap();
+    let new_liquidity = math::get_liquidity_for_amounts(
+        sqrt_price_q64,
+        new_sqrt_price_lower_q64,
+        new_sqrt_price_upper_q64,
+        withdrawn_a,
+        withdrawn_b,
+    )
+    .unwrap();
+    assert_eq!(position_data.liquidity, new_liquidity);
+    let (deposited_a, deposited_b) = math::get_amounts_for_liquidity(
+        sqrt_price_q64,
+        new_sqrt_price_lower_q64,
+        new_sqrt_price_upper_q64,
+        new_liquidity,
+        true,
+    )
+    .unwrap();
+    assert_eq!(
+        token_balance(&mut context, &setup.owner_a).await - owner_a_before,
+        (withdrawn_a - deposited_a) as u64
+    );
+    assert_eq!(
+        token_balance(&mut context, &setup.owner_b).await - owner_b_before,
+        (withdrawn_b - deposited_b) as u64
+    );
+}
+
+#[tokio::test]
+async fn test_rebalance_cpi_with_minimum_accounts() {
+    run_rebalance_cpi(AccountSet::Minimum).await;
+}
+
+#[tokio::test]
+async fn test_rebalance_cpi_with_maximum_accounts() {
+    run_rebalance_cpi(AccountSet::Maximum).await;
+}
+
+/// In shadow mode `trigger_rebalance_check` records its price sample and reports the range
+/// it would have set, but makes no CPI.
+#[tokio::test]
+async fn test_shadow_mode_records_rebalance_without_moving_position() {
+    let mut program_test = ProgramTest::new("amm_core", amm_core::ID, None);
+    program_test.add_program("fluxa_risk_engine", fluxa_risk_engine::ID, None);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+
+    let setup = setup_pool_with_position(&mut context).await;
+    let (pool, position) = (setup.pool, setup.position);
+    let set_shadow_mode_ix = Instruction {
+        program_id: fluxa_risk_engine::ID,
+        accounts: fluxa_risk_engine::accounts::SetShadowMode {
+            risk_config: risk_config_pda(),
+            authority: payer.pubkey(),
+        }
+        .to_account_metas(None),
+        data: fluxa_risk_engine::instruction::SetShadowMode { shadow_mode: true }.data(),
+    };
+    process(&mut context, &[set_shadow_mode_ix], &[&payer]).await;
+
+    let preview = preview_rebalance(&mut context, &pool, &position).await;
+    assert!(preview.rebalance_needed, "{preview:?}");
+    let position_before = context
+        .banks_client
+        .get_account(position)
+        .await
+        .unwrap()
+        .expect("position account missing");
+
+    let trigger_ix = trigger_rebalance_check_ix(&payer.pubkey(), &setup, &preview);
+    let logs = process(&mut context, &[trigger_ix], &[&payer]).await;
+
+    assert!(events::<RebalanceEvent>(&logs).is_empty());
+    let shadow_rebalances = events::<ShadowRebalance>(&logs);
+    assert_eq!(shadow_rebalances.len(), 1);
+    let shadow_rebalance = &shadow_rebalances[0];
+    assert_eq!(shadow_rebalance.position, position);
+    assert_eq!(
+        (shadow_rebalance.old_lower, shadow_rebalance.old_upper),
+        (TICK_LOWER, TICK_UPPER)
+    );
+    assert_eq!(
+        (shadow_rebalance.new_lower, shadow_rebalance.new_upper),
+        (preview.tick_lower, preview.tick_upper)
+    );
+    assert_eq!(shadow_rebalance.il_scaled, preview.il_percentage);
+
+    // The position is untouched and the new tick accounts were never created
+    let position_after = context
+        .banks_client
+        .get_account(position)
+        .await
+        .unwrap()
+        .expect("position account missing");
+    assert_eq!(position_after.data, position_before.data);
+    for tick in [preview.tick_lower, preview.tick_upper]
+        .into_iter()
+        .filter(|tick| ![TICK_LOWER, TICK_UPPER].contains(tick))
+    {
+        let tick_account = context
+            .banks_client
+            .get_account(tick_pda(&pool, tick))
+            .await
+            .unwrap();
+        assert!(tick_account.is_none(), "tick {tick} was created");
+    }
+
+    // The price sample was still recorded
+    let pool_risk_state_account = context
+        .banks_client
+        .get_account(pool_risk_state_pda(&pool))
+        .await
+        .unwrap()
+        .expect("pool risk state missing");
+    let pool_risk_state =
+        PoolRiskState::try_deserialize(&mut pool_risk_state_account.data.as_slice()).unwrap();
+    assert_eq!(pool_risk_state.observation_count, 1);
+}
+
+/// Once the position's `PositionRiskState` exists, `trigger_rebalance_check` rejects the
+/// rebalance over its per-epoch limit, and the count starts over in the next epoch.
+#[tokio::test]
+async fn test_rebalance_limit_is_enforced_per_epoch() {
+    let mut program_test = ProgramTest::new("amm_core", amm_core::ID, None);
+    program_test.add_program("fluxa_risk_engine", fluxa_risk_engine::ID, None);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+
+    let setup = setup_pool_with_position(&mut context).await;
+    let (pool, position) = (setup.pool, setup.position);
+    let position_risk_state = position_risk_state_pda(&position);
+    let initialize_ix = Instruction {
+        program_id: fluxa_risk_engine::ID,
+        accounts: fluxa_risk_engine::accounts::InitializePositionRiskState {
+            position_risk_state,
+            amm_position: position,
+            owner: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+            amm_position_token_account: None,
+        }
+        .to_account_metas(None),
+        data: fluxa_risk_engine::instruction::InitializePositionRiskState {
+            max_rebalances_per_epoch: 1,
+        }
+        .data(),
+    };
+    process(&mut context, &[initialize_ix], &[&payer]).await;
+
+    // Use up this epoch's only rebalance
+    let epoch = context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .epoch;
+    let mut account = context
+        .banks_client
+        .get_account(position_risk_state)
+        .await
+        .unwrap()
+        .expect("position risk state missing");
+    let mut state = PositionRiskState::try_deserialize(&mut account.data.as_slice()).unwrap();
+    state.record_rebalance(epoch).unwrap();
+    state
+        .try_serialize(&mut account.data.as_mut_slice())
+        .unwrap();
+    context.set_account(&position_risk_state, &AccountSharedData::from(account));
+
+    let preview = preview_rebalance(&mut context, &pool, &position).await;
+    assert!(preview.rebalance_needed, "{preview:?}");
+    let trigger_ix = trigger_rebalance_check_ix(&payer.pubkey(), &setup, &preview);
+    let err = try_process(&mut context, std::slice::from_ref(&trigger_ix), &[&payer])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(RiskEngineError::RebalanceLimitReached as u32 + 6000)
+        )
+    );
+
+    // The next epoch allows a rebalance again
+    context.last_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.epoch = epoch + 1;
+    clock.unix_timestamp += 60;
+    context.set_sysvar(&clock);
+    let logs = process(&mut context, &[trigger_ix], &[&payer]).await;
+    assert_eq!(events::<RebalanceEvent>(&logs).len(), 1);
+
+    let account = context
+        .banks_client
+        .get_account(position_risk_state)
+        .await
+        .unwrap()
+        .expect("position risk state missing");
+    let state = PositionRiskState::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!((state.epoch, state.rebalances_in_epoch), (epoch + 1, 1));
+}
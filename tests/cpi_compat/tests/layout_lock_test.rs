@@ -0,0 +1,113 @@
+// /tests/layout_lock_test.rs
+//
+// Fails when the account layout of an instruction invoked through a CPI changes without
+// `cpi_layouts.lock` being updated. After updating the callers of a changed instruction,
+// regenerate the lock with:
+//
+//   UPDATE_CPI_LAYOUTS=1 cargo test -p cpi_compat --test layout_lock_test
+
+use std::collections::BTreeMap;
+
+use cpi_compat::{workspace_cpis, InstructionLayout};
+
+const LOCK_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/cpi_layouts.lock");
+const LOCK_HEADER: &str = "\
+# Account metas layout hashes of every instruction invoked through a CPI in the workspace.
+# Regenerate with: UPDATE_CPI_LAYOUTS=1 cargo test -p cpi_compat --test layout_lock_test
+";
+
+/// Layout hash of every CPI callee, keyed by instruction name.
+fn callee_hashes() -> BTreeMap<&'static str, String> {
+    workspace_cpis()
+        .into_iter()
+        .map(|cpi| (cpi.callee.name, cpi.callee.hash()))
+        .collect()
+}
+
+fn read_lock() -> BTreeMap<String, String> {
+    let lock = std::fs::read_to_string(LOCK_PATH).expect("failed to read cpi_layouts.lock");
+    lock.lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (name, hash) = line
+                .split_once(' ')
+                .unwrap_or_else(|| panic!("malformed lock line: {line}"));
+            (name.to_string(), hash.to_string())
+        })
+        .collect()
+}
+
+fn describe(layout: &InstructionLayout) -> String {
+    layout
+        .accounts
+        .iter()
+        .map(|account| {
+            format!(
+                "  {} (signer: {}, writable: {})",
+                account.name, account.is_signer, account.is_writable
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn test_cpi_layouts_match_lock() {
+    let hashes = callee_hashes();
+
+    if std::env::var_os("UPDATE_CPI_LAYOUTS").is_some() {
+        let mut lock = LOCK_HEADER.to_string();
+        for (name, hash) in &hashes {
+            lock.push_str(&format!("{name} {hash}\n"));
+        }
+        std::fs::write(LOCK_PATH, lock).expect("failed to write cpi_layouts.lock");
+        return;
+    }
+
+    let locked = read_lock();
+    for cpi in workspace_cpis() {
+        let callee = &cpi.callee;
+        assert_eq!(
+            locked.get(callee.name),
+            Some(&callee.hash()),
+            "the account layout of {} changed. Update {} and its other callers, then \
+             regenerate cpi_layouts.lock. Current layout:\n{}",
+            callee.name,
+            cpi.caller.name,
+            describe(callee)
+        );
+    }
+    let stale: Vec<&String> = locked
+        .keys()
+        .filter(|name| !hashes.contains_key(name.as_str()))
+        .collect();
+    assert!(
+        stale.is_empty(),
+        "cpi_layouts.lock lists instructions no longer invoked through a CPI: {stale:?}"
+    );
+}
+
+#[test]
+fn test_callers_pass_required_privileges() {
+    for cpi in workspace_cpis() {
+        assert_eq!(
+            cpi.missing_privileges(),
+            Vec::<&str>::new(),
+            "{} does not pass these accounts to {} with the privileges it requires",
+            cpi.caller.name,
+            cpi.callee.name
+        );
+    }
+}
+
+#[test]
+fn test_layouts_name_every_account_once() {
+    for cpi in workspace_cpis() {
+        for layout in [&cpi.caller, &cpi.callee] {
+            let mut names: Vec<&str> = layout.accounts.iter().map(|account| account.name).collect();
+            names.sort_unstable();
+            names.dedup();
+            assert_eq!(names.len(), layout.accounts.len(), "{}", layout.name);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Compute unit budgets for the hot instructions of the workspace programs.
+//!
+//! CU regressions otherwise only show up once users hit the per-transaction limit. Each
+//! benchmark in `tests/cu_budget_test.rs` measures one instruction against the built
+//! programs, and `cu_budget.toml` records the compute units it is expected to consume. A
+//! benchmark exceeding its budget by more than the file's tolerance fails the test.
+//!
+//! After an intentional change to a hot path, re-record the budgets with:
+//!
+//!   anchor build
+//!   UPDATE_CU_BUDGETS=1 SBF_OUT_DIR=$PWD/target/deploy cargo test -p cu_budget --test cu_budget_test
+//!
+//! and commit the updated `cu_budget.toml` alongside the change.
+use std::collections::BTreeMap;
+
+/// Path of the budget file.
+pub const BUDGET_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/cu_budget.toml");
+
+/// Tolerance used when the budget file does not set `tolerance_bps`.
+pub const DEFAULT_TOLERANCE_BPS: u64 = 500;
+
+const BPS_DENOMINATOR: u64 = 10_000;
+
+const HEADER: &str = "\
+# Expected compute units of each benchmark in tests/cu_budget_test.rs.
+# A benchmark fails when it exceeds its budget by more than tolerance_bps.
+# Re-record with: UPDATE_CU_BUDGETS=1 SBF_OUT_DIR=$PWD/target/deploy cargo test -p cu_budget --test cu_budget_test
+";
+
+/// The contents of `cu_budget.toml`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CuBudgets {
+    /// How far, in basis points of its budget, a benchmark may exceed the budget.
+    pub tolerance_bps: u64,
+    /// Expected compute units, keyed by benchmark name.
+    pub budgets: BTreeMap<String, u64>,
+}
+
+impl Default for CuBudgets {
+    fn default() -> Self {
+        Self {
+            tolerance_bps: DEFAULT_TOLERANCE_BPS,
+            budgets: BTreeMap::new(),
+        }
+    }
+}
+
+impl CuBudgets {
+    /// Parses a budget file: an optional top-level `tolerance_bps = <u64>`, then a
+    /// `[budgets]` table of `<benchmark> = <compute units>` entries. Blank lines and `#`
+    /// comments are ignored.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut in_budgets = false;
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                if line != "[budgets]" {
+                    return Err(format!("line {}: unknown table {line}", number + 1));
+                }
+                in_budgets = true;
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected `key = value`", number + 1))?;
+            let (key, value) = (key.trim(), value.trim());
+            let value: u64 = value
+                .replace('_', "")
+                .parse()
+                .map_err(|_| format!("line {}: {value} is not a whole number", number + 1))?;
+            if in_budgets {
+                parsed.budgets.insert(key.to_string(), value);
+            } else if key == "tolerance_bps" {
+                parsed.tolerance_bps = value;
+            } else {
+                return Err(format!("line {}: unknown key {key}", number + 1));
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Reads and parses the budget file at [`BUDGET_PATH`].
+    pub fn load() -> Result<Self, String> {
+        let contents = std::fs::read_to_string(BUDGET_PATH)
+            .map_err(|error| format!("failed to read {BUDGET_PATH}: {error}"))?;
+        Self::parse(&contents)
+    }
+
+    /// Renders the budgets in the format read by [`CuBudgets::parse`].
+    pub fn render(&self) -> String {
+        let mut contents = format!(
+            "{HEADER}tolerance_bps = {}\n\n[budgets]\n",
+            self.tolerance_bps
+        );
+        for (name, units) in &self.budgets {
+            contents.push_str(&format!("{name} = {units}\n"));
+        }
+        contents
+    }
+
+    /// The most compute units `name` may consume, or `None` if it has no budget.
+    pub fn limit(&self, name: &str) -> Option<u64> {
+        self.budgets.get(name).map(|&budget| {
+            let allowance =
+                (budget as u128 * self.tolerance_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+            budget.saturating_add(allowance)
+        })
+    }
+
+    /// Checks a measurement of `name` against its budget, describing the failure if it has
+    /// no budget or exceeds it.
+    pub fn check(&self, name: &str, units_consumed: u64) -> Result<(), String> {
+        let limit = self
+            .limit(name)
+            .ok_or_else(|| format!("{name}: no budget recorded ({units_consumed} CU measured)"))?;
+        if units_consumed > limit {
+            return Err(format!(
+                "{name}: {units_consumed} CU exceeds the budget of {} CU by more than {} bps \
+                 (limit {limit} CU)",
+                self.budgets[name], self.tolerance_bps
+            ));
+        }
+        Ok(())
+    }
+}
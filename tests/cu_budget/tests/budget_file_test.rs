@@ -0,0 +1,61 @@
+// /tests/budget_file_test.rs
+//
+// Checks the budget file format and the tolerance applied to each budget.
+
+use cu_budget::{CuBudgets, DEFAULT_TOLERANCE_BPS};
+
+#[test]
+fn test_recorded_budget_file_parses() {
+    CuBudgets::load().unwrap();
+}
+
+#[test]
+fn test_render_round_trips() {
+    let mut budgets = CuBudgets {
+        tolerance_bps: 250,
+        ..CuBudgets::default()
+    };
+    budgets
+        .budgets
+        .insert("swap_1_crossing".to_string(), 41_234);
+    budgets.budgets.insert("mint_position".to_string(), 18_000);
+
+    assert_eq!(CuBudgets::parse(&budgets.render()).unwrap(), budgets);
+}
+
+#[test]
+fn test_parse_accepts_comments_and_separators() {
+    let budgets = CuBudgets::parse(
+        "# header\n\
+         \n\
+         [budgets]\n\
+         swap_5_crossings = 120_000 # five ticks\n",
+    )
+    .unwrap();
+    assert_eq!(budgets.tolerance_bps, DEFAULT_TOLERANCE_BPS);
+    assert_eq!(budgets.budgets["swap_5_crossings"], 120_000);
+}
+
+#[test]
+fn test_parse_rejects_malformed_lines() {
+    assert!(CuBudgets::parse("tolerance = 5").is_err());
+    assert!(CuBudgets::parse("[limits]\nswap = 1").is_err());
+    assert!(CuBudgets::parse("[budgets]\nswap = lots").is_err());
+    assert!(CuBudgets::parse("[budgets]\nswap").is_err());
+}
+
+#[test]
+fn test_check_applies_tolerance() {
+    let mut budgets = CuBudgets {
+        tolerance_bps: 500,
+        ..CuBudgets::default()
+    };
+    budgets.budgets.insert("mint_position".to_string(), 20_000);
+
+    assert_eq!(budgets.limit("mint_position"), Some(21_000));
+    assert!(budgets.check("mint_position", 19_000).is_ok());
+    assert!(budgets.check("mint_position", 21_000).is_ok());
+    assert!(budgets.check("mint_position", 21_001).is_err());
+    // A benchmark without a recorded budget fails rather than passing silently.
+    assert!(budgets.check("rebalance_check", 1).is_err());
+}
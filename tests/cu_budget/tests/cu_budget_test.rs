@@ -0,0 +1,822 @@
+// /tests/cu_budget_test.rs
+//
+// Measures the compute units of the workspace's hot instructions and fails when any of
+// them exceeds its budget in `cu_budget.toml` by more than the file's tolerance. Set
+// `CU_BUDGET_TOLERANCE_BPS` to override the tolerance for a run.
+//
+// Both programs are loaded from the shared objects in `SBF_OUT_DIR`, so build them first:
+//
+//   anchor build
+//   SBF_OUT_DIR=$PWD/target/deploy cargo test -p cu_budget --test cu_budget_test
+//
+// After an intentional change, re-record every budget by adding `UPDATE_CU_BUDGETS=1`.
+
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{program_pack::Pack, system_instruction},
+    AccountDeserialize, AnchorDeserialize, InstructionData, ToAccountMetas,
+};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::AccountSharedData,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    rent::Rent,
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::Transaction,
+};
+
+use amm_core::state::factory_config::{FactoryConfig, ProtocolFeeCurve};
+use amm_core::tick_array::TickArray;
+use cu_budget::CuBudgets;
+use fluxa_risk_engine::{
+    state::{PoolCategory, RiskConfigParams},
+    volatility_detector::VOLATILITY_WINDOW,
+    RebalancePreview,
+};
+
+const TICK_SPACING: u16 = 60;
+const FEE_RATE: u16 = 30;
+/// Upper tick shared by every position.
+const TICK_UPPER: i32 = 3000;
+/// Lower tick of the wide position that keeps swaps in range.
+const WIDE_TICK_LOWER: i32 = -3000;
+/// Range of the rebalanced position.
+const REBALANCE_TICK_LOWER: i32 = -6000;
+const REBALANCE_TICK_UPPER: i32 = 6000;
+/// The rebalanced position is opened at this tick and the pool is then swapped down to tick
+/// 0, so it carries IL.
+const ENTRY_TICK: i32 = 3000;
+const POSITION_LIQUIDITY: u128 = 1_000_000_000_000;
+const SWAP_AMOUNT_IN: u64 = 100_000_000_000_000;
+/// Cost estimate and position value passed to `trigger_rebalance_check`. The IL from
+/// `ENTRY_TICK` saves far more than the cost.
+const REBALANCE_COST_LAMPORTS: u64 = 5_000;
+const POSITION_VALUE_LAMPORTS: u64 = 10_000_000_000;
+/// Enough of each token for the payer to deposit into every position and swap.
+const OWNER_FUNDING: u64 = 1_000_000_000_000_000;
+
+/// Tick crossings measured by the swap benchmarks. All crossed ticks lie in the tick array
+/// below the starting price.
+const SWAP_CROSSINGS: [usize; 3] = [1, 5, 20];
+
+async fn process(context: &mut ProgramTestContext, ixs: &[Instruction], signers: &[&Keypair]) {
+    let transaction = Transaction::new_signed_with_payer(
+        ixs,
+        Some(&context.payer.pubkey()),
+        signers,
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+/// Simulates `ix`, signed by the payer, and returns the compute units it consumed along
+/// with any return data.
+async fn measure(
+    context: &mut ProgramTestContext,
+    name: &str,
+    ix: Instruction,
+) -> (u64, Option<Vec<u8>>) {
+    let payer = context.payer.insecure_clone();
+    let transaction = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        context.last_blockhash,
+    );
+    let simulation = context
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    assert!(
+        matches!(simulation.result, Some(Ok(()))),
+        "{name} simulation failed: {:?}",
+        simulation.result
+    );
+    let details = simulation
+        .simulation_details
+        .expect("simulation details missing");
+    (
+        details.units_consumed,
+        details.return_data.map(|return_data| return_data.data),
+    )
+}
+
+async fn create_mint(context: &mut ProgramTestContext) -> Pubkey {
+    let payer = context.payer.insecure_clone();
+    let mint_keypair = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let ixs = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &mint_keypair.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &mint_keypair.pubkey(),
+            &payer.pubkey(),
+            None,
+            0,
+        )
+        .unwrap(),
+    ];
+    process(context, &ixs, &[&payer, &mint_keypair]).await;
+    mint_keypair.pubkey()
+}
+
+async fn create_token_account(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Pubkey {
+    let payer = context.payer.insecure_clone();
+    let account_keypair = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let ixs = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &account_keypair.pubkey(),
+            rent.minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account(
+            &spl_token::id(),
+            &account_keypair.pubkey(),
+            mint,
+            owner,
+        )
+        .unwrap(),
+    ];
+    process(context, &ixs, &[&payer, &account_keypair]).await;
+    account_keypair.pubkey()
+}
+
+async fn mint_to(context: &mut ProgramTestContext, mint: &Pubkey, account: &Pubkey, amount: u64) {
+    let payer = context.payer.insecure_clone();
+    let ix = spl_token::instruction::mint_to(
+        &spl_token::id(),
+        mint,
+        account,
+        &payer.pubkey(),
+        &[],
+        amount,
+    )
+    .unwrap();
+    process(context, &[ix], &[&payer]).await;
+}
+
+fn tick_pda(pool: &Pubkey, index: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"tick".as_ref(),
+            pool.as_ref(),
+            index.to_le_bytes().as_ref(),
+        ],
+        &amm_core::ID,
+    )
+    .0
+}
+
+/// The PDA of the tick array holding `tick_index`.
+fn tick_array_pda(pool: &Pubkey, tick_index: i32) -> Pubkey {
+    TickArray::address(pool, TickArray::start_tick_index(tick_index, TICK_SPACING))
+}
+
+fn position_pda(
+    pool: &Pubkey,
+    owner: &Pubkey,
+    tick_lower: i32,
+    tick_upper: i32,
+    position_index: u64,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"position".as_ref(),
+            pool.as_ref(),
+            owner.as_ref(),
+            tick_lower.to_le_bytes().as_ref(),
+            tick_upper.to_le_bytes().as_ref(),
+            position_index.to_le_bytes().as_ref(),
+        ],
+        &amm_core::ID,
+    )
+    .0
+}
+
+fn position_counter_pda(pool: &Pubkey, owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"position_counter".as_ref(), pool.as_ref(), owner.as_ref()],
+        &amm_core::ID,
+    )
+    .0
+}
+
+fn risk_config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"risk_config".as_ref()], &fluxa_risk_engine::ID).0
+}
+
+fn pool_risk_state_pda(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"pool_risk_state".as_ref(), pool.as_ref()],
+        &fluxa_risk_engine::ID,
+    )
+    .0
+}
+
+fn position_risk_state_pda(position: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"position_risk_state".as_ref(), position.as_ref()],
+        &fluxa_risk_engine::ID,
+    )
+    .0
+}
+
+/// A pool and its accounts.
+struct PoolSetup {
+    pool: Pubkey,
+    token_pair: Pubkey,
+    vault_a: Pubkey,
+    vault_b: Pubkey,
+    /// The payer's token0 account, funded with `OWNER_FUNDING`.
+    owner_a: Pubkey,
+    /// The payer's token1 account, funded with `OWNER_FUNDING`.
+    owner_b: Pubkey,
+}
+
+/// Records `VOLATILITY_WINDOW` observations of the pool's price a minute apart, the history
+/// the risk engine measures volatility over before proposing a range.
+async fn record_price_window(context: &mut ProgramTestContext, pool: &Pubkey) {
+    let payer = context.payer.insecure_clone();
+    let mut timestamp = 0;
+    for _ in 0..VOLATILITY_WINDOW {
+        // Each observation is the same transaction, so it needs a fresh blockhash. The
+        // clock is moved afterwards so that the new slot does not overwrite it.
+        context.get_new_latest_blockhash().await.unwrap();
+        let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+        timestamp = clock.unix_timestamp.max(timestamp) + 60;
+        clock.unix_timestamp = timestamp;
+        context.set_sysvar(&clock);
+
+        let ix = Instruction {
+            program_id: fluxa_risk_engine::ID,
+            accounts: fluxa_risk_engine::accounts::RecordPriceObservation {
+                risk_config: risk_config_pda(),
+                pool_risk_state: pool_risk_state_pda(pool),
+                amm_pool: *pool,
+            }
+            .to_account_metas(None),
+            data: fluxa_risk_engine::instruction::RecordPriceObservation {}.data(),
+        };
+        process(context, &[ix], &[&payer]).await;
+    }
+}
+
+async fn start() -> ProgramTestContext {
+    let mut program_test = ProgramTest::new("amm_core", amm_core::ID, None);
+    program_test.add_program("fluxa_risk_engine", fluxa_risk_engine::ID, None);
+    program_test.start_with_context().await
+}
+
+/// Installs the program's ProgramData account with `authority` as its upgrade authority,
+/// which `initialize_factory_config` requires of its signer. The test loads the program
+/// without the upgradeable loader, so the account does not exist otherwise.
+fn set_upgrade_authority(context: &mut ProgramTestContext, authority: Pubkey) -> Pubkey {
+    let (program_data, _) =
+        Pubkey::find_program_address(&[amm_core::ID.as_ref()], &bpf_loader_upgradeable::ID);
+    let state = UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(authority),
+    };
+    let lamports =
+        Rent::default().minimum_balance(UpgradeableLoaderState::size_of_programdata_metadata());
+    let account =
+        AccountSharedData::new_data(lamports, &state, &bpf_loader_upgradeable::ID).unwrap();
+    context.set_account(&program_data, &account);
+    program_data
+}
+
+/// Instructions creating the factory config, with the payer as its authority, and enabling
+/// `fee_rate` with `tick_spacing` on it, skipping whichever an earlier pool of the test
+/// already did. Returns the factory config along with them.
+async fn fee_tier_ixs(
+    context: &mut ProgramTestContext,
+    fee_rate: u16,
+    tick_spacing: u16,
+) -> (Pubkey, Vec<Instruction>) {
+    let authority = context.payer.pubkey();
+    let (factory_config, _) =
+        Pubkey::find_program_address(&[b"factory_config".as_ref()], &amm_core::ID);
+    let config = context
+        .banks_client
+        .get_account(factory_config)
+        .await
+        .unwrap()
+        .map(|account| FactoryConfig::try_deserialize(&mut account.data.as_slice()).unwrap());
+
+    let mut ixs = Vec::new();
+    if config.is_none() {
+        let program_data = set_upgrade_authority(context, authority);
+        ixs.push(Instruction {
+            program_id: amm_core::ID,
+            accounts: amm_core::accounts::InitializeFactoryConfig {
+                factory_config,
+                authority,
+                program_data,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm_core::instruction::InitializeFactoryConfigHandler {
+                // No protocol share at any liquidity.
+                protocol_fee_curve: ProtocolFeeCurve {
+                    tvl_breakpoints: [1, 2, 3, 4],
+                    protocol_share_bps: [0; 5],
+                },
+            }
+            .data(),
+        });
+    }
+    if config.is_none_or(|config| config.tick_spacing_for(fee_rate).is_none()) {
+        ixs.push(Instruction {
+            program_id: amm_core::ID,
+            accounts: amm_core::accounts::EnableFeeTier {
+                factory_config,
+                authority,
+            }
+            .to_account_metas(None),
+            data: amm_core::instruction::EnableFeeTierHandler {
+                fee_rate,
+                tick_spacing,
+            }
+            .data(),
+        });
+    }
+    (factory_config, ixs)
+}
+
+/// Creates a pool trading at `initial_tick` and funds the payer's token accounts.
+async fn setup_pool(context: &mut ProgramTestContext, initial_tick: i32) -> PoolSetup {
+    let payer = context.payer.insecure_clone();
+
+    let mut mint_a = create_mint(context).await;
+    let mut mint_b = create_mint(context).await;
+    if mint_a > mint_b {
+        std::mem::swap(&mut mint_a, &mut mint_b);
+    }
+
+    let (pool, _) = Pubkey::find_program_address(
+        &[
+            b"pool".as_ref(),
+            mint_a.as_ref(),
+            mint_b.as_ref(),
+            FEE_RATE.to_le_bytes().as_ref(),
+        ],
+        &amm_core::ID,
+    );
+    let (pool_registry, _) = Pubkey::find_program_address(
+        &[b"pool_registry".as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        &amm_core::ID,
+    );
+    let (token_pair, _) = Pubkey::find_program_address(
+        &[b"token_pair".as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        &amm_core::ID,
+    );
+    let create_pair_ix = Instruction {
+        program_id: amm_core::ID,
+        accounts: amm_core::accounts::CreateTokenPair {
+            token_pair,
+            mint_a,
+            mint_b,
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::CreateTokenPairHandler {
+            oracle_feed: Pubkey::default(),
+        }
+        .data(),
+    };
+    let vault_a = Keypair::new();
+    let vault_b = Keypair::new();
+    let (factory_config, fee_tier_ixs) = fee_tier_ixs(context, FEE_RATE, TICK_SPACING).await;
+    let init_pool_ix = Instruction {
+        program_id: amm_core::ID,
+        accounts: amm_core::accounts::InitializePool {
+            pool,
+            mint_a,
+            mint_b,
+            factory: factory_config,
+            pool_vault_a: vault_a.pubkey(),
+            pool_vault_b: vault_b.pubkey(),
+            pool_registry,
+            token_pair,
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+            token_program: spl_token::ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::InitializePoolHandler {
+            initial_sqrt_price_q64: amm_core::math::tick_to_sqrt_price_q64(initial_tick).unwrap(),
+            fee_rate: FEE_RATE,
+            tick_spacing: TICK_SPACING,
+            immutable_parameters: false,
+        }
+        .data(),
+    };
+    process(
+        context,
+        &[fee_tier_ixs, vec![create_pair_ix, init_pool_ix]].concat(),
+        &[&payer, &vault_a, &vault_b],
+    )
+    .await;
+
+    let owner_a = create_token_account(context, &mint_a, &payer.pubkey()).await;
+    let owner_b = create_token_account(context, &mint_b, &payer.pubkey()).await;
+    mint_to(context, &mint_a, &owner_a, OWNER_FUNDING).await;
+    mint_to(context, &mint_b, &owner_b, OWNER_FUNDING).await;
+    PoolSetup {
+        pool,
+        token_pair,
+        vault_a: vault_a.pubkey(),
+        vault_b: vault_b.pubkey(),
+        owner_a,
+        owner_b,
+    }
+}
+
+/// Builds the instruction minting `[tick_lower, tick_upper)` as the owner's position
+/// `position_index`, with per-tick `TickData` accounts. The payer deposits from the
+/// setup's token accounts.
+fn mint_position_ix(
+    setup: &PoolSetup,
+    owner: &Pubkey,
+    tick_lower: i32,
+    tick_upper: i32,
+    position_index: u64,
+) -> Instruction {
+    let pool = &setup.pool;
+    Instruction {
+        program_id: amm_core::ID,
+        accounts: amm_core::accounts::MintPosition {
+            pool: *pool,
+            position: position_pda(pool, owner, tick_lower, tick_upper, position_index),
+            tick_lower: tick_pda(pool, tick_lower),
+            tick_upper: tick_pda(pool, tick_upper),
+            owner: *owner,
+            payer: *owner,
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            position_counter: position_counter_pda(pool, owner),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: setup.owner_a,
+            owner_token1_account: setup.owner_b,
+            token_program: spl_token::ID,
+            owner_index: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionHandler {
+            tick_lower_index: tick_lower,
+            tick_upper_index: tick_upper,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            position_index,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    }
+}
+
+/// Builds the instruction minting `[tick_lower, TICK_UPPER)` as the owner's position
+/// `position_index`, with its ticks stored in tick arrays. The payer deposits from the
+/// setup's token accounts.
+fn mint_position_with_tick_arrays_ix(
+    setup: &PoolSetup,
+    owner: &Pubkey,
+    tick_lower: i32,
+    position_index: u64,
+) -> Instruction {
+    let pool = &setup.pool;
+    Instruction {
+        program_id: amm_core::ID,
+        accounts: amm_core::accounts::MintPositionWithTickArrays {
+            pool: *pool,
+            position: position_pda(pool, owner, tick_lower, TICK_UPPER, position_index),
+            tick_array_lower: tick_array_pda(pool, tick_lower),
+            tick_array_upper: tick_array_pda(pool, TICK_UPPER),
+            owner: *owner,
+            payer: *owner,
+            system_program: anchor_lang::system_program::ID,
+            rent: sysvar::rent::ID,
+            position_counter: position_counter_pda(pool, owner),
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            owner_token0_account: setup.owner_a,
+            owner_token1_account: setup.owner_b,
+            token_program: spl_token::ID,
+            owner_index: None,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::MintPositionWithTickArraysHandler {
+            tick_lower_index: tick_lower,
+            tick_upper_index: TICK_UPPER,
+            liquidity_amount_desired: POSITION_LIQUIDITY,
+            position_index,
+            amount0_max: u64::MAX,
+            amount1_max: u64::MAX,
+        }
+        .data(),
+    }
+}
+
+/// Measures minting a first position into a fresh pool.
+async fn bench_mint_position() -> u64 {
+    let mut context = start().await;
+    let owner = context.payer.pubkey();
+    let setup = setup_pool(&mut context, 0).await;
+
+    let ix = mint_position_ix(&setup, &owner, WIDE_TICK_LOWER, TICK_UPPER, 0);
+    measure(&mut context, "mint_position", ix).await.0
+}
+
+/// Measures a token0 -> token1 swap crossing `crossings` initialized ticks, stored in a
+/// tick array.
+async fn bench_swap(crossings: usize) -> u64 {
+    let mut context = start().await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context, 0).await;
+    let pool = setup.pool;
+
+    // 1. Create the tick arrays covering the positions
+    for tick_index in [WIDE_TICK_LOWER, TICK_UPPER] {
+        let ix = Instruction {
+            program_id: amm_core::ID,
+            accounts: amm_core::accounts::InitializeTickArray {
+                pool,
+                tick_array: tick_array_pda(&pool, tick_index),
+                payer: payer.pubkey(),
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm_core::instruction::InitializeTickArrayHandler {
+                start_tick_index: TickArray::start_tick_index(tick_index, TICK_SPACING),
+            }
+            .data(),
+        };
+        process(&mut context, &[ix], &[&payer]).await;
+    }
+
+    // 2. Mint a wide position and one position per crossed tick
+    let crossed_ticks = (1..=crossings as i32).map(|step| -step * TICK_SPACING as i32);
+    for (position_index, tick_lower) in std::iter::once(WIDE_TICK_LOWER)
+        .chain(crossed_ticks)
+        .enumerate()
+    {
+        let ix = mint_position_with_tick_arrays_ix(
+            &setup,
+            &payer.pubkey(),
+            tick_lower,
+            position_index as u64,
+        );
+        process(&mut context, &[ix], &[&payer]).await;
+    }
+
+    // 3. Measure a swap stopping halfway past the last crossed tick
+    let limit_tick = -(crossings as i32 * TICK_SPACING as i32) - TICK_SPACING as i32 / 2;
+    let mut accounts = amm_core::accounts::SwapExactInput {
+        pool,
+        token0_vault: setup.vault_a,
+        token1_vault: setup.vault_b,
+        user_token_in_account: setup.owner_a,
+        user_token_out_account: setup.owner_b,
+        user_authority: payer.pubkey(),
+        token_program: spl_token::ID,
+    }
+    .to_account_metas(None);
+    accounts.push(AccountMeta::new(
+        tick_array_pda(&pool, -(TICK_SPACING as i32)),
+        false,
+    ));
+    let swap_ix = Instruction {
+        program_id: amm_core::ID,
+        accounts,
+        data: amm_core::instruction::SwapExactInputHandler {
+            amount_in: SWAP_AMOUNT_IN,
+            amount_out_minimum: 1,
+            sqrt_price_limit_q64: amm_core::math::tick_to_sqrt_price_q64(limit_tick).unwrap(),
+        }
+        .data(),
+    };
+    measure(&mut context, &swap_name(crossings), swap_ix)
+        .await
+        .0
+}
+
+fn swap_name(crossings: usize) -> String {
+    if crossings == 1 {
+        "swap_1_crossing".to_string()
+    } else {
+        format!("swap_{crossings}_crossings")
+    }
+}
+
+/// Measures `trigger_rebalance_check` moving a position that carries IL to the previewed
+/// range, including its CPI into `amm_core::update_position_handler`.
+async fn bench_rebalance_check() -> u64 {
+    let mut context = start().await;
+    let payer = context.payer.insecure_clone();
+    let setup = setup_pool(&mut context, ENTRY_TICK).await;
+    let pool = setup.pool;
+
+    // 1. Mint the position at the entry tick, swap the price down to tick 0, create the risk
+    // engine accounts and record a price window
+    let mint_ix = mint_position_ix(
+        &setup,
+        &payer.pubkey(),
+        REBALANCE_TICK_LOWER,
+        REBALANCE_TICK_UPPER,
+        0,
+    );
+    let swap_ix = Instruction {
+        program_id: amm_core::ID,
+        accounts: amm_core::accounts::SwapExactInput {
+            pool,
+            token0_vault: setup.vault_a,
+            token1_vault: setup.vault_b,
+            user_token_in_account: setup.owner_a,
+            user_token_out_account: setup.owner_b,
+            user_authority: payer.pubkey(),
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: amm_core::instruction::SwapExactInputHandler {
+            amount_in: SWAP_AMOUNT_IN,
+            amount_out_minimum: 1,
+            sqrt_price_limit_q64: 1u128 << 64,
+        }
+        .data(),
+    };
+    process(&mut context, &[mint_ix, swap_ix], &[&payer]).await;
+    let position = position_pda(
+        &pool,
+        &payer.pubkey(),
+        REBALANCE_TICK_LOWER,
+        REBALANCE_TICK_UPPER,
+        0,
+    );
+
+    let init_risk_config_ix = Instruction {
+        program_id: fluxa_risk_engine::ID,
+        accounts: fluxa_risk_engine::accounts::InitializeRiskConfig {
+            risk_config: risk_config_pda(),
+            authority: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: fluxa_risk_engine::instruction::InitializeRiskConfig {
+            params: RiskConfigParams {
+                circuit_breaker_threshold_bps: 5_000,
+                circuit_breaker_window_secs: 3_600,
+                twap_interval_secs: 600,
+                halt_duration_secs: 3_600,
+                max_staleness_secs: 60,
+                stable_volatility_prior_bps: 500,
+                major_volatility_prior_bps: 8_000,
+                long_tail_volatility_prior_bps: 20_000,
+            },
+        }
+        .data(),
+    };
+    let init_pool_risk_state_ix = Instruction {
+        program_id: fluxa_risk_engine::ID,
+        accounts: fluxa_risk_engine::accounts::InitializePoolRiskState {
+            pool_risk_state: pool_risk_state_pda(&pool),
+            amm_pool: pool,
+            risk_config: risk_config_pda(),
+            payer: payer.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: fluxa_risk_engine::instruction::InitializePoolRiskState {
+            category: PoolCategory::Major,
+        }
+        .data(),
+    };
+    process(
+        &mut context,
+        &[init_risk_config_ix, init_pool_risk_state_ix],
+        &[&payer],
+    )
+    .await;
+    record_price_window(&mut context, &pool).await;
+
+    // 2. Preview the rebalance to learn the new range
+    let preview_ix = Instruction {
+        program_id: fluxa_risk_engine::ID,
+        accounts: fluxa_risk_engine::accounts::PreviewRebalance {
+            amm_pool: pool,
+            amm_position: position,
+            pool_risk_state: pool_risk_state_pda(&pool),
+        }
+        .to_account_metas(None),
+        data: fluxa_risk_engine::instruction::PreviewRebalance {
+            allow_one_sided: false,
+        }
+        .data(),
+    };
+    let return_data = measure(&mut context, "preview_rebalance", preview_ix)
+        .await
+        .1
+        .expect("preview_rebalance returned no data");
+    let preview = RebalancePreview::try_from_slice(&return_data).unwrap();
+    assert!(preview.rebalance_needed, "{preview:?}");
+
+    // 3. Measure the rebalance itself
+    let trigger_ix = Instruction {
+        program_id: fluxa_risk_engine::ID,
+        accounts: fluxa_risk_engine::accounts::TriggerRebalanceCheck {
+            amm_pool: pool,
+            amm_position: position,
+            amm_old_tick_lower: tick_pda(&pool, REBALANCE_TICK_LOWER),
+            amm_old_tick_upper: tick_pda(&pool, REBALANCE_TICK_UPPER),
+            amm_new_tick_lower: tick_pda(&pool, preview.tick_lower),
+            amm_new_tick_upper: tick_pda(&pool, preview.tick_upper),
+            amm_token0_vault: setup.vault_a,
+            amm_token1_vault: setup.vault_b,
+            owner_token0_account: setup.owner_a,
+            owner_token1_account: setup.owner_b,
+            token_pair: setup.token_pair,
+            pyth_price_feed: None,
+            token0_mint: None,
+            token1_mint: None,
+            risk_config: risk_config_pda(),
+            pool_risk_state: pool_risk_state_pda(&pool),
+            owner: payer.pubkey(),
+            payer: payer.pubkey(),
+            amm_core_program: amm_core::ID,
+            system_program: anchor_lang::system_program::ID,
+            token_program: spl_token::ID,
+            rent: sysvar::rent::ID,
+            amm_position_token_account: None,
+            keeper_bond: None,
+            position_risk_state: position_risk_state_pda(&position),
+        }
+        .to_account_metas(None),
+        data: fluxa_risk_engine::instruction::TriggerRebalanceCheck {
+            allow_one_sided: false,
+            rebalance_cost_lamports: REBALANCE_COST_LAMPORTS,
+            position_value_lamports: POSITION_VALUE_LAMPORTS,
+        }
+        .data(),
+    };
+    measure(&mut context, "rebalance_check", trigger_ix).await.0
+}
+
+#[tokio::test]
+async fn test_hot_instructions_stay_within_cu_budget() {
+    let mut measurements = vec![("mint_position".to_string(), bench_mint_position().await)];
+    for crossings in SWAP_CROSSINGS {
+        measurements.push((swap_name(crossings), bench_swap(crossings).await));
+    }
+    measurements.push(("rebalance_check".to_string(), bench_rebalance_check().await));
+
+    for (name, units_consumed) in &measurements {
+        println!("{name}: {units_consumed} CU");
+    }
+
+    let mut budgets = CuBudgets::load().unwrap();
+    if std::env::var_os("UPDATE_CU_BUDGETS").is_some() {
+        budgets.budgets = measurements.into_iter().collect();
+        std::fs::write(cu_budget::BUDGET_PATH, budgets.render())
+            .expect("failed to write cu_budget.toml");
+        return;
+    }
+    if let Some(tolerance_bps) = std::env::var("CU_BUDGET_TOLERANCE_BPS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+    {
+        budgets.tolerance_bps = tolerance_bps;
+    }
+
+    let failures: Vec<String> = measurements
+        .iter()
+        .filter_map(|(name, units_consumed)| budgets.check(name, *units_consumed).err())
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "compute unit budgets exceeded. If the increase is intended, re-record \
+         cu_budget.toml with UPDATE_CU_BUDGETS=1:\n{}",
+        failures.join("\n")
+    );
+}
@@ -46,7 +46,7 @@ describe("AMM Core - Initialize Pool (TypeScript)", () => {
   let mintAPublicKey: PublicKey;
   let mintBPublicKey: PublicKey;
 
-  const factoryKeypair = Keypair.generate();
+  let factoryConfigPda: PublicKey;
 
   // Helper function to create a new mint
   async function createTestMint(
@@ -65,6 +65,79 @@ describe("AMM Core - Initialize Pool (TypeScript)", () => {
     return mintKeypair;
   }
 
+  // Builds the create_token_pair instruction that must precede a pair's first pool,
+  // and returns it with the pair's PDA.
+  async function createTokenPairIx(
+    mintA: PublicKey,
+    mintB: PublicKey
+  ): Promise<[PublicKey, web3.TransactionInstruction]> {
+    const [tokenPairPda] = await PublicKey.findProgramAddress(
+      [Buffer.from("token_pair"), mintA.toBuffer(), mintB.toBuffer()],
+      program.programId
+    );
+    const ix = await program.methods
+      .createTokenPairHandler(PublicKey.default)
+      .accountsStrict({
+        tokenPair: tokenPairPda,
+        mintA,
+        mintB,
+        payer: walletSigner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+    return [tokenPairPda, ix];
+  }
+
+  // Creates the factory config with the wallet as its authority unless another test did,
+  // enables the (feeRate, tickSpacing) fee tier on it unless already enabled, and returns it.
+  async function ensureFeeTier(
+    feeRate: number,
+    tickSpacing: number
+  ): Promise<PublicKey> {
+    const [configPda] = await PublicKey.findProgramAddress(
+      [Buffer.from("factory_config")],
+      program.programId
+    );
+    let factoryConfig = await program.account.factoryConfig.fetchNullable(
+      configPda
+    );
+    if (factoryConfig === null) {
+      // Only the program's upgrade authority, the wallet under `anchor test`, may create it.
+      const [programData] = await PublicKey.findProgramAddress(
+        [program.programId.toBuffer()],
+        new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+      );
+      await program.methods
+        .initializeFactoryConfigHandler({
+          tvlBreakpoints: [new BN(1), new BN(2), new BN(3), new BN(4)],
+          protocolShareBps: [0, 0, 0, 0, 0],
+        })
+        .accountsStrict({
+          factoryConfig: configPda,
+          authority: walletSigner.publicKey,
+          programData,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      factoryConfig = await program.account.factoryConfig.fetch(configPda);
+    }
+    const enabled = factoryConfig.feeTiers
+      .slice(0, factoryConfig.feeTierCount)
+      .some(
+        (tier) => tier.feeRate === feeRate && tier.tickSpacing === tickSpacing
+      );
+    if (!enabled) {
+      await program.methods
+        .enableFeeTierHandler(feeRate, tickSpacing)
+        .accountsStrict({
+          factoryConfig: configPda,
+          authority: walletSigner.publicKey,
+        })
+        .rpc();
+    }
+    return configPda;
+  }
+
   before(async () => {
     // Create two mints and ensure canonical order
     let tempMint1 = await createTestMint(
@@ -90,7 +163,9 @@ describe("AMM Core - Initialize Pool (TypeScript)", () => {
 
     console.log("Mint A (Canonical):", mintAPublicKey.toBase58());
     console.log("Mint B (Canonical):", mintBPublicKey.toBase58());
-    console.log("Factory:", factoryKeypair.publicKey.toBase58());
+    // Every pool below charges 0.3% with a tick spacing of 60.
+    factoryConfigPda = await ensureFeeTier(30, 60);
+    console.log("Factory config:", factoryConfigPda.toBase58());
     console.log("Payer:", walletSigner.publicKey.toBase58());
   });
 
@@ -104,9 +179,20 @@ describe("AMM Core - Initialize Pool (TypeScript)", () => {
         Buffer.from("pool"),
         mintAPublicKey.toBuffer(),
         mintBPublicKey.toBuffer(),
+        new BN(feeRate).toArrayLike(Buffer, "le", 2),
+      ],
+      program.programId
+    );
+    const [poolRegistryPda] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from("pool_registry"),
+        mintAPublicKey.toBuffer(),
+        mintBPublicKey.toBuffer(),
       ],
       program.programId
     );
+    const [tokenPairPda, createTokenPairInstruction] =
+      await createTokenPairIx(mintAPublicKey, mintBPublicKey);
 
     const poolVaultAKeypair = Keypair.generate();
     const poolVaultBKeypair = Keypair.generate();
@@ -116,19 +202,22 @@ describe("AMM Core - Initialize Pool (TypeScript)", () => {
     console.log("Pool Vault B:", poolVaultBKeypair.publicKey.toBase58());
 
     const txSignature = await program.methods
-      .initializePoolHandler(initialSqrtPriceQ64, feeRate, tickSpacing)
+      .initializePoolHandler(initialSqrtPriceQ64, feeRate, tickSpacing, false)
       .accountsStrict({
         pool: poolPda,
         mintA: mintAPublicKey,
         mintB: mintBPublicKey,
-        factory: factoryKeypair.publicKey,
+        factory: factoryConfigPda,
         poolVaultA: poolVaultAKeypair.publicKey,
         poolVaultB: poolVaultBKeypair.publicKey,
+        poolRegistry: poolRegistryPda,
+        tokenPair: tokenPairPda,
         payer: walletSigner.publicKey, // The publicKey of the wallet paying fees
         systemProgram: SystemProgram.programId,
         tokenProgram: TOKEN_PROGRAM_ID,
         rent: web3.SYSVAR_RENT_PUBKEY,
       })
+      .preInstructions([createTokenPairInstruction])
       .signers([poolVaultAKeypair, poolVaultBKeypair]) // Vaults are new accounts being initialized
       .rpc();
 
@@ -139,7 +228,7 @@ describe("AMM Core - Initialize Pool (TypeScript)", () => {
     const poolAccount = await program.account.pool.fetch(poolPda);
     expect(poolAccount.bump).to.equal(poolBump);
     expect(poolAccount.factory.toBase58()).to.equal(
-      factoryKeypair.publicKey.toBase58()
+      factoryConfigPda.toBase58()
     );
     expect(poolAccount.token0Mint.toBase58()).to.equal(
       mintAPublicKey.toBase58()
@@ -154,6 +243,14 @@ describe("AMM Core - Initialize Pool (TypeScript)", () => {
       poolVaultBKeypair.publicKey.toBase58()
     );
     expect(poolAccount.feeRate).to.equal(feeRate);
+    expect(poolAccount.tokenPair.toBase58()).to.equal(tokenPairPda.toBase58());
+
+    // The pair's record counts the pool
+    const tokenPairAccount = await program.account.tokenPair.fetch(
+      tokenPairPda
+    );
+    expect(tokenPairAccount.isCanonical).to.equal(true);
+    expect(tokenPairAccount.poolCount).to.equal(1);
     expect(poolAccount.tickSpacing).to.equal(tickSpacing);
     expect(poolAccount.sqrtPriceQ64.toString()).to.equal(
       initialSqrtPriceQ64.toString()
@@ -194,6 +291,8 @@ describe("AMM Core - Initialize Pool (TypeScript)", () => {
     const nonCanonicalMintA = mintBKeyPair.publicKey; // mintB is "larger"
     const nonCanonicalMintB = mintAKeyPair.publicKey; // mintA is "smaller"
 
+    // The pair's record cannot be created in this order, so create_token_pair rejects it
+    // before initialize_pool runs.
     // PDA derivation for the instruction must use the keys as they will be passed.
     // The program itself will derive the PDA based on canonical order if it were to succeed past the check.
     // However, the initial check `ctx.accounts.mint_a.key() >= ctx.accounts.mint_b.key()`
@@ -203,28 +302,42 @@ describe("AMM Core - Initialize Pool (TypeScript)", () => {
         Buffer.from("pool"),
         nonCanonicalMintA.toBuffer(), // Larger key first
         nonCanonicalMintB.toBuffer(), // Smaller key second
+        new BN(feeRate).toArrayLike(Buffer, "le", 2),
+      ],
+      program.programId
+    );
+    const [poolRegistryPda] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from("pool_registry"),
+        nonCanonicalMintA.toBuffer(),
+        nonCanonicalMintB.toBuffer(),
       ],
       program.programId
     );
+    const [tokenPairPda, createTokenPairInstruction] =
+      await createTokenPairIx(nonCanonicalMintA, nonCanonicalMintB);
 
     const poolVaultAKeypair = Keypair.generate();
     const poolVaultBKeypair = Keypair.generate();
 
     try {
       await program.methods
-        .initializePoolHandler(initialSqrtPriceQ64, feeRate, tickSpacing)
+        .initializePoolHandler(initialSqrtPriceQ64, feeRate, tickSpacing, false)
         .accountsStrict({
           pool: poolPdaAttempt,
           mintA: nonCanonicalMintA, // Larger key
           mintB: nonCanonicalMintB, // Smaller key
-          factory: factoryKeypair.publicKey,
+          factory: factoryConfigPda,
           poolVaultA: poolVaultAKeypair.publicKey,
           poolVaultB: poolVaultBKeypair.publicKey,
+          poolRegistry: poolRegistryPda,
+          tokenPair: tokenPairPda,
           payer: walletSigner.publicKey,
           systemProgram: SystemProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
           rent: web3.SYSVAR_RENT_PUBKEY,
         })
+        .preInstructions([createTokenPairInstruction])
         .signers([poolVaultAKeypair, poolVaultBKeypair])
         .rpc();
       expect.fail(
@@ -281,9 +394,20 @@ describe("AMM Core - Initialize Pool (TypeScript)", () => {
         Buffer.from("pool"),
         localMintAPublicKey.toBuffer(),
         localMintBPublicKey.toBuffer(),
+        new BN(feeRate).toArrayLike(Buffer, "le", 2),
       ],
       program.programId
     );
+    const [poolRegistryPda] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from("pool_registry"),
+        localMintAPublicKey.toBuffer(),
+        localMintBPublicKey.toBuffer(),
+      ],
+      program.programId
+    );
+    const [tokenPairPda, createTokenPairInstruction] =
+      await createTokenPairIx(localMintAPublicKey, localMintBPublicKey);
 
     // Vaults will be initialized by the program, so just need their keypairs for signing
     const poolVaultAKeypair = Keypair.generate();
@@ -291,19 +415,22 @@ describe("AMM Core - Initialize Pool (TypeScript)", () => {
 
     try {
       await program.methods
-        .initializePoolHandler(initialSqrtPriceQ64, feeRate, tickSpacing)
+        .initializePoolHandler(initialSqrtPriceQ64, feeRate, tickSpacing, false)
         .accountsStrict({
           pool: poolPda,
           mintA: localMintAPublicKey, // Use the local mint A for this test
           mintB: localMintBPublicKey,
-          factory: factoryKeypair.publicKey, // Can reuse factory
+          factory: factoryConfigPda,
           poolVaultA: poolVaultAKeypair.publicKey,
           poolVaultB: poolVaultBKeypair.publicKey,
+          poolRegistry: poolRegistryPda,
+          tokenPair: tokenPairPda,
           payer: walletSigner.publicKey,
           systemProgram: SystemProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
           rent: web3.SYSVAR_RENT_PUBKEY,
         })
+        .preInstructions([createTokenPairInstruction])
         .signers([poolVaultAKeypair, poolVaultBKeypair])
         .rpc();
       expect.fail(
@@ -358,9 +485,20 @@ describe("AMM Core - Initialize Pool (TypeScript)", () => {
         Buffer.from("pool"),
         localMintAPublicKey.toBuffer(),
         localMintBPublicKey.toBuffer(),
+        new BN(feeRate).toArrayLike(Buffer, "le", 2),
+      ],
+      program.programId
+    );
+    const [poolRegistryPda] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from("pool_registry"),
+        localMintAPublicKey.toBuffer(),
+        localMintBPublicKey.toBuffer(),
       ],
       program.programId
     );
+    const [tokenPairPda, createTokenPairInstruction] =
+      await createTokenPairIx(localMintAPublicKey, localMintBPublicKey);
 
     // Vaults will be initialized by the program, so just need their keypairs for signing
     const poolVaultAKeypair = Keypair.generate();
@@ -368,19 +506,22 @@ describe("AMM Core - Initialize Pool (TypeScript)", () => {
 
     try {
       await program.methods
-        .initializePoolHandler(initialSqrtPriceQ64, feeRate, tickSpacing)
+        .initializePoolHandler(initialSqrtPriceQ64, feeRate, tickSpacing, false)
         .accountsStrict({
           pool: poolPda,
           mintA: localMintAPublicKey, // Use the local mint A for this test
           mintB: localMintBPublicKey,
-          factory: factoryKeypair.publicKey, // Can reuse factory
+          factory: factoryConfigPda,
           poolVaultA: poolVaultAKeypair.publicKey,
           poolVaultB: poolVaultBKeypair.publicKey,
+          poolRegistry: poolRegistryPda,
+          tokenPair: tokenPairPda,
           payer: walletSigner.publicKey,
           systemProgram: SystemProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
           rent: web3.SYSVAR_RENT_PUBKEY,
         })
+        .preInstructions([createTokenPairInstruction])
         .signers([poolVaultAKeypair, poolVaultBKeypair])
         .rpc();
       expect.fail(
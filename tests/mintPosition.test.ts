@@ -42,7 +42,6 @@ describe("AMM Core - Mint Position (TypeScript)", () => {
   let mintAPublicKey: PublicKey;
   let mintBPublicKey: PublicKey;
 
-  const factoryKeypair = Keypair.generate();
   let poolPda: PublicKey;
   let poolBump: number;
   let poolVaultAKeypair: Keypair;
@@ -79,6 +78,56 @@ describe("AMM Core - Mint Position (TypeScript)", () => {
     return mintKeypair;
   }
 
+  // Creates the factory config with the wallet as its authority unless another test did,
+  // enables the (feeRate, tickSpacing) fee tier on it unless already enabled, and returns it.
+  async function ensureFeeTier(
+    feeRate: number,
+    tickSpacing: number
+  ): Promise<PublicKey> {
+    const [configPda] = await PublicKey.findProgramAddress(
+      [Buffer.from("factory_config")],
+      program.programId
+    );
+    let factoryConfig = await program.account.factoryConfig.fetchNullable(
+      configPda
+    );
+    if (factoryConfig === null) {
+      // Only the program's upgrade authority, the wallet under `anchor test`, may create it.
+      const [programData] = await PublicKey.findProgramAddress(
+        [program.programId.toBuffer()],
+        new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+      );
+      await program.methods
+        .initializeFactoryConfigHandler({
+          tvlBreakpoints: [new BN(1), new BN(2), new BN(3), new BN(4)],
+          protocolShareBps: [0, 0, 0, 0, 0],
+        })
+        .accountsStrict({
+          factoryConfig: configPda,
+          authority: walletSigner.publicKey,
+          programData,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      factoryConfig = await program.account.factoryConfig.fetch(configPda);
+    }
+    const enabled = factoryConfig.feeTiers
+      .slice(0, factoryConfig.feeTierCount)
+      .some(
+        (tier) => tier.feeRate === feeRate && tier.tickSpacing === tickSpacing
+      );
+    if (!enabled) {
+      await program.methods
+        .enableFeeTierHandler(feeRate, tickSpacing)
+        .accountsStrict({
+          factoryConfig: configPda,
+          authority: walletSigner.publicKey,
+        })
+        .rpc();
+    }
+    return configPda;
+  }
+
   before(async () => {
     // 1. Create Mints
     let tempMint1 = await createTestMint(
@@ -113,6 +162,24 @@ describe("AMM Core - Mint Position (TypeScript)", () => {
         Buffer.from("pool"),
         mintAPublicKey.toBuffer(),
         mintBPublicKey.toBuffer(),
+        new BN(feeRate).toArrayLike(Buffer, "le", 2),
+      ],
+      program.programId
+    );
+    const [poolRegistryPda] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from("pool_registry"),
+        mintAPublicKey.toBuffer(),
+        mintBPublicKey.toBuffer(),
+      ],
+      program.programId
+    );
+
+    const [tokenPairPda] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from("token_pair"),
+        mintAPublicKey.toBuffer(),
+        mintBPublicKey.toBuffer(),
       ],
       program.programId
     );
@@ -122,15 +189,31 @@ describe("AMM Core - Mint Position (TypeScript)", () => {
 
     console.log("Pool PDA for mint position tests:", poolPda.toBase58());
 
+    const factoryConfigPda = await ensureFeeTier(feeRate, tickSpacing);
+
+    // The pair's record must exist before its first pool.
+    await program.methods
+      .createTokenPairHandler(PublicKey.default)
+      .accountsStrict({
+        tokenPair: tokenPairPda,
+        mintA: mintAPublicKey,
+        mintB: mintBPublicKey,
+        payer: walletSigner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
     await program.methods
-      .initializePoolHandler(initialSqrtPriceQ64, feeRate, tickSpacing)
+      .initializePoolHandler(initialSqrtPriceQ64, feeRate, tickSpacing, false)
       .accountsStrict({
         pool: poolPda,
         mintA: mintAPublicKey,
         mintB: mintBPublicKey,
-        factory: factoryKeypair.publicKey,
+        factory: factoryConfigPda,
         poolVaultA: poolVaultAKeypair.publicKey,
         poolVaultB: poolVaultBKeypair.publicKey,
+        poolRegistry: poolRegistryPda,
+        tokenPair: tokenPairPda,
         payer: walletSigner.publicKey,
         systemProgram: SystemProgram.programId,
         tokenProgram: TOKEN_PROGRAM_ID,